@@ -0,0 +1,160 @@
+use crate::codegen::ir_generator::IRGenerator;
+use crate::codegen::jit::JITExecutor;
+use crate::parse_handler::ParseHandler;
+use std::time::{Duration, Instant};
+
+/// A benchmark program and the function to run after compiling it
+pub struct Workload {
+    pub name: &'static str,
+    pub source: String,
+    /// `None` for compile-only workloads
+    pub entry: Option<&'static str>,
+}
+
+/// Average time spent in each pipeline stage
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Timings {
+    pub parse: Duration,
+    pub ir_gen: Duration,
+    pub jit_startup: Duration,
+    pub execution: Option<Duration>,
+}
+
+/// The representative workloads measured by `col --bench`
+pub fn workloads() -> Vec<Workload> {
+    let mut many_functions = String::new();
+    for i in 0..100 {
+        many_functions.push_str(&format!(
+            "function f{i}(a, b) {{\n    var t = a * {i} + b;\n    if (t > 10) {{ return t - b; }}\n    return t;\n}}\n"
+        ));
+    }
+
+    vec![
+        Workload {
+            name: "fibonacci(25)",
+            source: r#"
+                function fib(n) {
+                    if (n < 2) { return n; }
+                    return fib(n - 1) + fib(n - 2);
+                }
+                function bench() {
+                    return fib(25);
+                }
+            "#
+            .to_string(),
+            entry: Some("bench"),
+        },
+        Workload {
+            name: "arithmetic loop",
+            source: r#"
+                function bench() {
+                    var i = 0;
+                    var sum = 0;
+                    while (i < 1000000) {
+                        sum = sum + i % 7 * 2 - 1;
+                        i++;
+                    }
+                    return sum;
+                }
+            "#
+            .to_string(),
+            entry: Some("bench"),
+        },
+        Workload {
+            name: "strings",
+            source: r#"
+                function bench() {
+                    var i = 0;
+                    var total = 0;
+                    while (i < 100000) {
+                        var label = "item " + string(i % 100) + ", " + string(i % 7);
+                        total = total + real(string(i % 10)) + string_length(label);
+                        i++;
+                    }
+                    return total;
+                }
+            "#
+            .to_string(),
+            entry: Some("bench"),
+        },
+        Workload {
+            name: "100 functions (compile only)",
+            source: many_functions,
+            entry: None,
+        },
+    ]
+}
+
+/// Run a workload `iterations` times and return the average stage timings
+pub fn run_workload(workload: &Workload, iterations: u32) -> Result<Timings, String> {
+    let iterations = iterations.max(1);
+    let mut total = Timings::default();
+
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let program = ParseHandler::parse_program(&workload.source)
+            .map_err(|errs| format!("{}: parse failed: {:?}", workload.name, errs))?;
+        total.parse += start.elapsed();
+
+        let context = inkwell::context::Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "bench_module");
+        let start = Instant::now();
+        program
+            .accept(&mut ir_generator)
+            .map_err(|e| format!("{}: IR generation failed: {:?}", workload.name, e))?;
+        total.ir_gen += start.elapsed();
+
+        ir_generator
+            .get_module()
+            .verify()
+            .map_err(|e| format!("{}: module verification failed: {}", workload.name, e))?;
+
+        let start = Instant::now();
+        let executor = JITExecutor::new(ir_generator.get_module())?;
+        total.jit_startup += start.elapsed();
+
+        if let Some(entry) = workload.entry {
+            let start = Instant::now();
+            executor.execute_function(entry, &[])?;
+            *total.execution.get_or_insert_default() += start.elapsed();
+        }
+    }
+
+    Ok(Timings {
+        parse: total.parse / iterations,
+        ir_gen: total.ir_gen / iterations,
+        jit_startup: total.jit_startup / iterations,
+        execution: total.execution.map(|d| d / iterations),
+    })
+}
+
+/// Run every workload and print a comparison table.
+/// There is no interpreter yet, so only the JIT pipeline is measured.
+#[cfg(feature = "bench")]
+pub fn run_all(iterations: u32) -> Result<(), String> {
+    use owo_colors::OwoColorize;
+
+    println!(
+        "{}",
+        format!("Benchmarking {} iteration(s) per workload...", iterations).green()
+    );
+    println!(
+        "{:<30} {:>12} {:>12} {:>12} {:>12}",
+        "workload", "parse", "ir gen", "jit startup", "execution"
+    );
+    for workload in workloads() {
+        let timings = run_workload(&workload, iterations)?;
+        let execution = timings
+            .execution
+            .map_or_else(|| "-".to_string(), |d| format!("{:.2?}", d));
+        println!(
+            "{:<30} {:>12} {:>12} {:>12} {:>12}",
+            workload.name,
+            format!("{:.2?}", timings.parse),
+            format!("{:.2?}", timings.ir_gen),
+            format!("{:.2?}", timings.jit_startup),
+            execution
+        );
+    }
+    Ok(())
+}
@@ -4,6 +4,7 @@ use std::collections::HashMap;
 
 pub mod ir_generator;
 pub mod jit;
+pub mod runtime;
 
 /// Type mapping table for converting language types to LLVM types
 pub struct TypeMapping<'ctx> {
@@ -57,6 +58,12 @@ impl<'ctx> TypeMapping<'ctx> {
         self.context.i32_type()
     }
 
+    /// Get the LLVM type for a value produced by `int64()`, which stays an integer
+    /// through arithmetic and bitwise operators
+    pub fn get_int64_type(&self) -> IntType<'ctx> {
+        self.context.i64_type()
+    }
+
     /// Get the LLVM type for a boolean value
     pub fn get_bool_type(&self) -> IntType<'ctx> {
         self.context.bool_type()
@@ -1,19 +1,68 @@
 use crate::codegen::TypeMapping;
+use crate::parser::language_options::DivByZeroPolicy;
 use crate::parser::visitor::Visitor;
 use crate::parser::{
-    expr::Expr, func::Func, func_def::FuncDef, program::Program, stmt::Stmt, top_level::TopLevel,
+    Span, expr::Expr, func::Func, func_def::FuncDef, program::Program, stmt::Stmt,
+    top_level::TopLevel,
 };
+use crate::utils::diagnostic::{Diagnostic, Severity};
+use annotations::IrSnippet;
+use compile_stats::{CompileStats, FunctionStats};
+use debug_info::DebugInfo;
+use function_table::FunctionTable;
+use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::module::Module;
 use inkwell::types::*;
 use inkwell::values::*;
-use std::collections::HashMap;
-
+use std::collections::{HashMap, HashSet};
+
+pub mod accessors;
+pub mod annotations;
+pub mod builtins;
+pub mod call_depth;
+pub mod compile_stats;
+pub mod debug_info;
+pub mod division;
+pub mod enums;
+pub mod function_refs;
+pub mod function_table;
+pub mod instances;
+pub mod integers;
 pub mod ir_helpers;
+pub mod math_epsilon;
+pub mod nan;
+pub mod null;
+pub mod profiling;
+pub mod return_paths;
+pub mod switch;
+pub mod symbols;
+pub mod tracing;
+pub mod unroll;
 pub mod visit_expr;
 pub mod visit_stmt;
 
+/// The function holding the top-level statements. `main` only calls it and returns
+/// its result, so an executor can run the statements once before calling any other
+/// function, see [`JITExecutor::ensure_initialized`](crate::codegen::jit::JITExecutor::ensure_initialized).
+pub const INIT_FUNCTION: &str = "__col_init";
+
+/// The function [`IRGenerator::gen_string_result`] generates
+pub const STRING_RESULT_FUNCTION: &str = "__col_string_result";
+
+/// The name `function` goes by in GML and in what is reported about it, whatever
+/// its symbol: the top-level code is `main`, though [`INIT_FUNCTION`] holds it
+pub(crate) fn gml_name(function: FunctionValue<'_>) -> String {
+    let name = symbols::recorded_gml_name(function)
+        .unwrap_or_else(|| function.get_name().to_string_lossy().into_owned());
+    if name == INIT_FUNCTION {
+        "main".to_string()
+    } else {
+        name
+    }
+}
+
 /// Error types for IR generation
 #[derive(Debug)]
 pub enum IRGenError {
@@ -21,12 +70,147 @@ pub enum IRGenError {
     UndefinedFunction(String),
     TypeMismatch(String),
     InvalidOperation(String),
+    ArgumentCountMismatch(String),
+    /// The program still contains error nodes from parser recovery, at these spans
+    SyntaxErrors(Vec<Span>),
+    /// Syntax the parser accepts but code generation does not support yet, and
+    /// where it is
+    Unsupported {
+        message: String,
+        span: Span,
+    },
+    /// LLVM rejected the code generated for a function; this is a compiler bug.
+    /// Carries the GML function's name, its IR cut to a few lines, and where it is
+    /// defined (`None` for the top-level code in `main`).
+    InvalidFunction {
+        function: String,
+        ir: String,
+        span: Option<Span>,
+    },
+    /// A function returns a value on some paths but can reach its end on others,
+    /// with strict returns on. Carries where control reaches the end.
+    MissingReturn {
+        function: String,
+        span: Span,
+    },
+    /// A `case` label equal to an earlier one in the same switch, as written, where
+    /// it is and where the earlier one is
+    DuplicateCase {
+        label: String,
+        span: Span,
+        first: Span,
+    },
+}
+
+impl IRGenError {
+    /// Stable diagnostic code for this kind of error
+    pub fn code(&self) -> u32 {
+        match self {
+            IRGenError::UndefinedVariable(_) => 301,
+            IRGenError::UndefinedFunction(_) => 302,
+            IRGenError::TypeMismatch(_) => 303,
+            IRGenError::InvalidOperation(_) => 304,
+            IRGenError::ArgumentCountMismatch(_) => 305,
+            IRGenError::SyntaxErrors(_) => 306,
+            IRGenError::InvalidFunction { .. } => 307,
+            IRGenError::Unsupported { .. } => 309,
+            IRGenError::MissingReturn { .. } => MISSING_RETURN,
+            IRGenError::DuplicateCase { .. } => DUPLICATE_CASE,
+        }
+    }
+}
+
+impl From<&IRGenError> for Diagnostic {
+    fn from(error: &IRGenError) -> Self {
+        let message = match error {
+            IRGenError::UndefinedVariable(name) => format!("Undefined variable '{}'", name),
+            IRGenError::UndefinedFunction(name) => format!("Undefined function '{}'", name),
+            IRGenError::TypeMismatch(message)
+            | IRGenError::InvalidOperation(message)
+            | IRGenError::ArgumentCountMismatch(message)
+            | IRGenError::Unsupported { message, .. } => message.clone(),
+            IRGenError::SyntaxErrors(spans) => {
+                let spans: Vec<String> = spans
+                    .iter()
+                    .map(|span| format!("{}..{}", span.start, span.end))
+                    .collect();
+                format!(
+                    "Cannot generate code with {} syntax error(s) left, at {}",
+                    spans.len(),
+                    spans.join(", ")
+                )
+            }
+            IRGenError::InvalidFunction { function, ir, .. } => format!(
+                "Generated code for function '{}' failed verification:\n{}",
+                function, ir
+            ),
+            IRGenError::MissingReturn { function, .. } => {
+                format!("Not every path through '{}' returns a value", function)
+            }
+            IRGenError::DuplicateCase { label, first, .. } => format!(
+                "Duplicate case label {}, already matched at {}..{}",
+                label, first.start, first.end
+            ),
+        };
+        let span = match error {
+            IRGenError::SyntaxErrors(spans) => spans.first().cloned(),
+            IRGenError::InvalidFunction { span, .. } => span.clone(),
+            IRGenError::Unsupported { span, .. }
+            | IRGenError::MissingReturn { span, .. }
+            | IRGenError::DuplicateCase { span, .. } => Some(span.clone()),
+            _ => None,
+        };
+        Diagnostic::new(error.code(), Severity::Error, message, span)
+    }
 }
 
 pub type IRGenResult<T> = Result<T, IRGenError>;
 
-/// IR Generator that implements the Visitor pattern to generate LLVM IR
+/// Default limit on how deeply expressions may nest during code generation
+pub const DEFAULT_MAX_EXPRESSION_DEPTH: usize = 512;
+
+/// Default number of IR lines a verification error shows of the rejected function
+pub const DEFAULT_VERIFY_EXCERPT_LINES: usize = 40;
+
+/// Diagnostic code for statements skipped because the one before never completes
+pub const UNREACHABLE_CODE: u32 = 308;
+
+/// Diagnostic code for functions that return a value on some paths only
+pub const MISSING_RETURN: u32 = 310;
+
+/// Diagnostic code for a `case` label that repeats an earlier one
+pub const DUPLICATE_CASE: u32 = 311;
+
+/// Run `f`, first moving to a fresh stack segment if little stack is left.
+/// Code generation recurses once per AST level with large frames, so even nesting
+/// within the depth limits can exhaust a small thread stack.
+pub(crate) fn with_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(64 * 1024, 1024 * 1024, f)
+}
+
+/// The first `max_lines` lines of `ir`, noting how many more there were
+fn ir_excerpt(ir: &str, max_lines: usize) -> String {
+    let lines: Vec<&str> = ir.lines().collect();
+    if lines.len() <= max_lines {
+        return lines.join("\n");
+    }
+    format!(
+        "{}\n... ({} more lines)",
+        lines[..max_lines].join("\n"),
+        lines.len() - max_lines
+    )
+}
+
+/// IR Generator that implements the Visitor pattern to generate LLVM IR.
+///
+/// Functions are added to the module in the order they are generated: `main` and
+/// [`INIT_FUNCTION`] first, then every function in source order, each nested function right after the
+/// function enclosing it. The same program therefore always prints the same IR.
 pub struct IRGenerator<'ctx> {
+    // Debug information, only emitted when enabled. Declared first so that it
+    // is dropped, and finalized, while the module is still alive
+    debug_info: Option<DebugInfo<'ctx>>,
+
     pub context: &'ctx Context,
     pub module: Module<'ctx>,
     pub builder: Builder<'ctx>,
@@ -35,10 +219,86 @@ pub struct IRGenerator<'ctx> {
     // Symbol tables
     pub(crate) variables: HashMap<String, PointerValue<'ctx>>,
     pub(crate) variable_types: HashMap<String, BasicTypeEnum<'ctx>>,
-    pub(crate) functions: HashMap<String, FunctionValue<'ctx>>,
+    pub(crate) functions: FunctionTable<'ctx>,
+    // Enum member values, keyed as `Enum.Member`
+    pub(crate) enum_members: HashMap<String, f64>,
+    // Anonymous functions generated so far, each referred to by its position plus
+    // one; see the function_refs module
+    anonymous_functions: Vec<FunctionValue<'ctx>>,
+    // Locals of the current function known to hold an anonymous function, and the
+    // variables it may store something else in later
+    callables: HashMap<String, FunctionValue<'ctx>>,
+    reassigned_variables: HashSet<String>,
 
     // Current function context
     pub(crate) current_function: Option<FunctionValue<'ctx>>,
+    // Loops generated so far in the current function, numbering their blocks and counters
+    loop_count: usize,
+    // Where `break` and `continue` jump to, for each loop or switch being generated,
+    // innermost last. A switch only takes `break`; `continue` goes to the loop around it
+    loop_targets: Vec<(BasicBlock<'ctx>, Option<BasicBlock<'ctx>>)>,
+    // Loops open around the code being generated in the current function, and the
+    // most ever open at once in any function
+    loop_depth: usize,
+    max_loop_depth: usize,
+
+    // Allow calls with more arguments than the callee declares
+    pub(crate) permissive_arity: bool,
+
+    // Make top-level `var`s external module globals that functions can see
+    pub(crate) persistent_globals: bool,
+
+    // Expression nesting limit and the current depth
+    pub(crate) max_expression_depth: usize,
+    expression_depth: usize,
+
+    // How many lines of a function's IR to show when it fails verification
+    pub(crate) verify_excerpt_lines: usize,
+
+    // Compare floats for (in)equality within the executor's epsilon instead of exactly
+    pub(crate) epsilon_comparisons: bool,
+
+    // Count calls and time per function through the runtime's profile
+    pub(crate) profiling: bool,
+
+    // Record each statement as it starts in the runtime's trace
+    pub(crate) tracing: bool,
+
+    // What `/`, `%` and `div` do with a zero divisor
+    pub(crate) div_by_zero: DivByZeroPolicy,
+
+    // Raise an error when a comparison has a NaN operand instead of giving false
+    pub(crate) strict_math: bool,
+
+    // Make a function that returns a value on some paths only an error rather
+    // than a warning
+    pub(crate) strict_returns: bool,
+
+    // Generate integer literals as exact int64 values; see the integers module
+    pub(crate) exact_integers: bool,
+    // Local variables of the current function given fractions somewhere, which
+    // are numbers even when declared with an integer
+    fractional_variables: HashSet<String>,
+
+    // Most calls of script functions that may be open at once, if limited
+    pub(crate) max_call_depth: Option<usize>,
+
+    // Name functions `{prefix}{module}_{name}` rather than by their GML names, see
+    // the symbols module
+    pub(crate) symbol_prefix: Option<String>,
+
+    // Largest constant `repeat` count generated as copies of the body instead of a
+    // loop, 0 to never unroll; see the unroll module
+    pub(crate) max_unrolled_repeat: usize,
+
+    // Per-function statistics, only collected when enabled
+    stats: Option<CompileStats>,
+
+    // IR per top-level statement and function, only recorded when enabled
+    annotations: Option<Vec<IrSnippet>>,
+
+    // Warnings found while generating, which unlike errors do not stop it
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'ctx> IRGenerator<'ctx> {
@@ -54,11 +314,104 @@ impl<'ctx> IRGenerator<'ctx> {
             type_mapping,
             variables: HashMap::new(),
             variable_types: HashMap::new(),
-            functions: HashMap::new(),
+            functions: FunctionTable::new(),
+            enum_members: HashMap::new(),
+            anonymous_functions: Vec::new(),
+            callables: HashMap::new(),
+            reassigned_variables: HashSet::new(),
             current_function: None,
+            loop_count: 0,
+            loop_targets: Vec::new(),
+            loop_depth: 0,
+            max_loop_depth: 0,
+            permissive_arity: false,
+            persistent_globals: false,
+            max_expression_depth: DEFAULT_MAX_EXPRESSION_DEPTH,
+            expression_depth: 0,
+            verify_excerpt_lines: DEFAULT_VERIFY_EXCERPT_LINES,
+            epsilon_comparisons: false,
+            profiling: false,
+            tracing: false,
+            div_by_zero: DivByZeroPolicy::default(),
+            strict_math: false,
+            strict_returns: false,
+            exact_integers: false,
+            fractional_variables: HashSet::new(),
+            max_call_depth: None,
+            symbol_prefix: None,
+            max_unrolled_repeat: 0,
+            stats: None,
+            annotations: None,
+            diagnostics: Vec::new(),
+            debug_info: None,
         }
     }
 
+    /// Warnings found so far, in the order they were found
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Take the warnings found so far
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// The most loops nested inside each other in any function generated so far.
+    /// A function defined inside a loop starts counting from 0 again.
+    pub fn max_loop_depth(&self) -> usize {
+        self.max_loop_depth
+    }
+
+    /// Collect [`FunctionStats`] for every function generated from now on.
+    /// Without this no timing or counting is done.
+    pub fn collect_stats(&mut self) {
+        self.stats = Some(CompileStats::default());
+    }
+
+    /// Take the statistics collected so far, in module order (`main` first).
+    /// Collection stays enabled if it was.
+    pub fn take_stats(&mut self) -> Vec<FunctionStats> {
+        let mut functions = self
+            .stats
+            .as_mut()
+            .map(|stats| std::mem::take(stats).into_functions())
+            .unwrap_or_default();
+        let order: Vec<String> = self.module.get_functions().map(gml_name).collect();
+        functions.sort_by_key(|stats| order.iter().position(|name| *name == stats.name));
+        functions
+    }
+
+    fn begin_stats(&mut self) -> Option<std::time::Instant> {
+        self.stats.as_mut().map(CompileStats::begin)
+    }
+
+    fn finish_stats(&mut self, function: FunctionValue<'ctx>, start: Option<std::time::Instant>) {
+        if let (Some(stats), Some(start)) = (self.stats.as_mut(), start) {
+            stats.finish(function, start);
+        }
+    }
+
+    /// Run LLVM's verifier on `function`, generated for the GML function `name`
+    /// defined at `span`. Each function is checked as soon as it is complete so a
+    /// failure can name it; the module is still verified as a whole afterwards.
+    pub(crate) fn verify_function(
+        &self,
+        function: FunctionValue<'ctx>,
+        name: &str,
+        span: Option<&Span>,
+    ) -> IRGenResult<()> {
+        if function.verify(false) {
+            return Ok(());
+        }
+        let ir = function.print_to_string().to_string();
+        Err(IRGenError::InvalidFunction {
+            function: name.to_string(),
+            ir: ir_excerpt(&ir, self.verify_excerpt_lines),
+            span: span.cloned(),
+        })
+    }
+
     /// Enter a function context
     pub fn enter_function(&mut self, function: FunctionValue<'ctx>) {
         self.current_function = Some(function);
@@ -69,6 +422,16 @@ impl<'ctx> IRGenerator<'ctx> {
         // Clear local variables when entering new function
         self.variables.clear();
         self.variable_types.clear();
+        self.callables.clear();
+        self.loop_count = 0;
+    }
+
+    /// Number the next loop or switch in the current function, in source order.
+    /// Outer ones are numbered before those nested in them.
+    pub fn next_loop_id(&mut self) -> usize {
+        let id = self.loop_count;
+        self.loop_count += 1;
+        id
     }
 
     /// Exit function context
@@ -78,25 +441,247 @@ impl<'ctx> IRGenerator<'ctx> {
         self.variables.clear();
         self.variable_types.clear();
     }
+
+    /// Generate a function under `llvm_name`, or the symbol made of it if symbols
+    /// are prefixed, callable from GML by its own name
+    pub fn gen_function(
+        &mut self,
+        func_def: &FuncDef,
+        llvm_name: &str,
+    ) -> IRGenResult<FunctionValue<'ctx>> {
+        let stats_start = self.begin_stats();
+        let (name, func) = (&func_def.name, &func_def.func);
+
+        // Create function signature with parameters
+        let param_types: Vec<inkwell::types::BasicMetadataTypeEnum<'ctx>> = func
+            .args
+            .iter()
+            .map(|_| self.type_mapping.get_number_type().into())
+            .collect();
+
+        let return_type = self.type_mapping.get_number_type();
+        let fn_type = return_type.fn_type(&param_types, false);
+
+        // Create function
+        let function = self.add_script_function(llvm_name, fn_type);
+        self.functions.insert(name.to_string(), function);
+
+        // Save current state. The enclosing function's locals are moved out rather
+        // than copied, as the new function must not see them anyway
+        let saved_variables = std::mem::take(&mut self.variables);
+        let saved_variable_types = std::mem::take(&mut self.variable_types);
+        let saved_functions = self.functions.clone();
+        let saved_function = self.current_function;
+        let saved_loop_count = self.loop_count;
+        let saved_loop_targets = std::mem::take(&mut self.loop_targets);
+        let saved_loop_depth = std::mem::take(&mut self.loop_depth);
+        let saved_fractional_variables = std::mem::take(&mut self.fractional_variables);
+        let saved_callables = std::mem::take(&mut self.callables);
+        let saved_reassigned_variables = std::mem::take(&mut self.reassigned_variables);
+
+        // Enter function context
+        self.begin_debug_function(function, Some(&func_def.span));
+        self.enter_function(function);
+        if self.exact_integers {
+            self.fractional_variables = integers::fractional_variables(&func.body);
+        }
+        self.reassigned_variables = function_refs::reassigned_variables(&func.body);
+        self.gen_profile_enter(function)?;
+        self.gen_call_depth_enter(function, name, &func_def.span)?;
+
+        // Declare parameters as local variables
+        for (i, param_name) in func.args.iter().enumerate() {
+            let param_value = function.get_nth_param(i as u32).unwrap();
+            let alloca =
+                self.declare_variable(param_name, self.type_mapping.get_number_type().into())?;
+            self.builder.build_store(alloca, param_value).map_err(|e| {
+                IRGenError::InvalidOperation(format!(
+                    "Failed to store parameter '{}': {}",
+                    param_name, e
+                ))
+            })?;
+        }
+
+        self.declare_argument_count(func.args.len())?;
+
+        // Generate function body
+        let mut previous = None;
+        for stmt in &func.body {
+            // Statements after one that never completes are skipped
+            if let (true, Some(previous)) = (self.is_terminated(), previous) {
+                self.warn_unreachable(previous, stmt);
+                break;
+            }
+            self.visit_stmt(stmt)?;
+            previous = Some(stmt);
+        }
+        self.check_return_paths(func_def)?;
+
+        // Add return if the body falls through. That is decided by the block it
+        // ends in, which need not be the last: the blocks of an `if` in a loop
+        // body come after the loop's exit block. Like the top-level code, a
+        // function that falls through returns 0.0 regardless of the last expression
+        if self
+            .builder
+            .get_insert_block()
+            .map_or(true, |bb| bb.get_terminator().is_none())
+        {
+            let ret_val = self.gen_number_const(0.0);
+            self.builder.build_return(Some(&ret_val)).map_err(|e| {
+                IRGenError::InvalidOperation(format!("Failed to build return: {}", e))
+            })?;
+        }
+        self.gen_profile_exits(function)?;
+        self.gen_call_depth_exits(function)?;
+        self.seal_unreachable_blocks(function)?;
+        self.verify_function(function, name, Some(&func_def.span))?;
+        self.annotate_function(func_def, function);
+
+        // Restore state
+        self.variables = saved_variables;
+        self.variable_types = saved_variable_types;
+        self.functions = saved_functions;
+        self.current_function = saved_function;
+        self.loop_count = saved_loop_count;
+        self.loop_targets = saved_loop_targets;
+        self.loop_depth = saved_loop_depth;
+        self.fractional_variables = saved_fractional_variables;
+        self.callables = saved_callables;
+        self.reassigned_variables = saved_reassigned_variables;
+        self.end_debug_function();
+
+        self.finish_stats(function, stats_start);
+        Ok(function)
+    }
+
+    /// Generate a function defined inside another function or block.
+    /// It is emitted at module level under a unique mangled name and its original
+    /// name is only visible in the enclosing block.
+    pub fn gen_nested_function(&mut self, func_def: &FuncDef) -> IRGenResult<()> {
+        let prefix = self
+            .current_function
+            .map(gml_name)
+            .unwrap_or_else(|| "main".to_string());
+        let mut llvm_name = format!("{}.{}", prefix, func_def.name);
+        let mut suffix = 1;
+        while self
+            .module
+            .get_function(&self.symbol_name(&llvm_name))
+            .is_some()
+        {
+            llvm_name = format!("{}.{}.{}", prefix, func_def.name, suffix);
+            suffix += 1;
+        }
+
+        let saved_block = self.builder.get_insert_block();
+        let function = self.gen_function(func_def, &llvm_name)?;
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+
+        self.functions.insert(func_def.name.clone(), function);
+        Ok(())
+    }
+
+    /// Generate [`STRING_RESULT_FUNCTION`], which takes nothing and returns `expr`
+    /// converted as by `string()`, for a host that wants a string rather than the
+    /// number `main` returns. It is generated after the program and reads the
+    /// program's variables as globals, so those must be persistent.
+    pub(crate) fn gen_string_result(&mut self, expr: &Expr) -> IRGenResult<()> {
+        let fn_type = self.type_mapping.get_string_type().fn_type(&[], false);
+        let function = self.add_script_function(STRING_RESULT_FUNCTION, fn_type);
+        self.enter_function(function);
+        let value = self.visit_expr_impl(expr)?;
+        let text = self.gen_to_string(value)?;
+        self.builder
+            .build_return(Some(&text))
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build return: {}", e)))?;
+        self.seal_unreachable_blocks(function)?;
+        self.verify_function(function, STRING_RESULT_FUNCTION, None)?;
+        self.exit_function();
+        Ok(())
+    }
+
+    /// Generate `main` as a call of `init_function` returning its result
+    fn gen_main(
+        &mut self,
+        main_function: FunctionValue<'ctx>,
+        init_function: FunctionValue<'ctx>,
+    ) -> IRGenResult<()> {
+        let entry = self.context.append_basic_block(main_function, "entry");
+        self.builder.position_at_end(entry);
+        let result = self
+            .builder
+            .build_call(init_function, &[], "init")
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build call: {}", e)))?
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(|| {
+                IRGenError::InvalidOperation("Top-level code returned no value".to_string())
+            })?;
+        self.builder
+            .build_return(Some(&result))
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build return: {}", e)))?;
+        Ok(())
+    }
 }
 
 impl<'ctx> Visitor<IRGenResult<BasicValueEnum<'ctx>>> for IRGenerator<'ctx> {
     fn visit_program(&mut self, program: &Program) -> IRGenResult<BasicValueEnum<'ctx>> {
-        // Create a main function to hold global statements
+        // Code skipped by parser recovery cannot be compiled
+        let error_spans = program.error_spans();
+        if !error_spans.is_empty() {
+            return Err(IRGenError::SyntaxErrors(error_spans));
+        }
+
+        let stats_start = self.begin_stats();
+
+        // Enums are global and can be used anywhere, including before their declaration
+        for top_level in &program.body {
+            if let TopLevel::Enum(enum_def) = top_level {
+                self.declare_enum(enum_def)?;
+            }
+        }
+
+        // `main` only calls the function holding the top-level statements; it is
+        // added first so it leads the module, and generated last
         let return_type = self.type_mapping.get_number_type();
         let fn_type = return_type.fn_type(&[], false);
-        let main_function = self.module.add_function("main", fn_type, None);
-        self.enter_function(main_function);
+        let main_function = self.add_script_function("main", fn_type);
+        let init_function = self.add_script_function(INIT_FUNCTION, fn_type);
+        self.begin_debug_function(init_function, None);
+        self.enter_function(init_function);
+        if self.exact_integers {
+            self.fractional_variables = integers::fractional_variables(program.statements());
+        }
+        self.reassigned_variables = function_refs::reassigned_variables(program.statements());
+        self.gen_profile_enter(init_function)?;
 
-        let mut _last_value = self.gen_number_const(0.0).into();
+        // Items are generated strictly in source order; see the ordering note on IRGenerator
+        // The statement generated last, until the first unreachable one is reported
+        let mut previous = None;
         for top_level in &program.body {
-            _last_value = self.visit_toplevel(top_level)?;
+            // Statements after a top-level return are unreachable, but functions
+            // defined after it still need to be generated
+            if let (true, TopLevel::Statement(stmt)) = (self.is_terminated(), top_level) {
+                if let Some(previous) = previous.take() {
+                    self.warn_unreachable(previous, stmt);
+                }
+                continue;
+            }
+            let mark = self.ir_mark();
+            self.visit_toplevel(top_level)?;
+            if let TopLevel::Statement(stmt) = top_level {
+                self.annotate_statement(stmt, mark);
+                previous = Some(stmt);
+            }
         }
 
         // Only add return if the block doesn't have a terminator
         if let Some(current_block) = self.builder.get_insert_block() {
             if current_block.get_terminator().is_none() {
-                // Always return a double 0.0 from main function, regardless of last expression type
+                // Without a top-level return, the top-level code returns 0.0
+                // regardless of the last expression
                 let return_value = self.gen_number_const(0.0);
                 self.builder
                     .build_return(Some(&return_value))
@@ -106,7 +691,15 @@ impl<'ctx> Visitor<IRGenResult<BasicValueEnum<'ctx>>> for IRGenerator<'ctx> {
             }
         }
 
+        self.gen_profile_exits(init_function)?;
+        self.seal_unreachable_blocks(init_function)?;
+        self.verify_function(init_function, "main", None)?;
         self.exit_function();
+        self.end_debug_function();
+        self.gen_main(main_function, init_function)?;
+        self.finish_dispatchers()?;
+        self.finalize_debug_info();
+        self.finish_stats(init_function, stats_start);
 
         // Return a dummy value
         Ok(self.gen_number_const(0.0).into())
@@ -132,77 +725,18 @@ impl<'ctx> Visitor<IRGenResult<BasicValueEnum<'ctx>>> for IRGenerator<'ctx> {
                 Ok(self.gen_number_const(0.0).into())
             }
             TopLevel::Statement(stmt) => self.visit_stmt(stmt),
+            // Members were resolved before generating anything
+            TopLevel::Enum(_) => Ok(self.gen_number_const(0.0).into()),
+            TopLevel::Include(path, _) => Err(IRGenError::InvalidOperation(format!(
+                "Unresolved include '{}'",
+                path
+            ))),
+            TopLevel::Error(span) => Err(IRGenError::SyntaxErrors(vec![span.clone()])),
         }
     }
 
     fn visit_func_def(&mut self, func_def: &FuncDef) -> IRGenResult<BasicValueEnum<'ctx>> {
-        let func_name = &func_def.name;
-
-        // Create function signature with parameters
-        let param_types: Vec<inkwell::types::BasicMetadataTypeEnum<'ctx>> = func_def
-            .func
-            .args
-            .iter()
-            .map(|_| self.type_mapping.get_number_type().into())
-            .collect();
-
-        let return_type = self.type_mapping.get_number_type();
-        let fn_type = return_type.fn_type(&param_types, false);
-
-        // Create function
-        let function = self.module.add_function(func_name, fn_type, None);
-        self.functions.insert(func_name.clone(), function);
-
-        // Save current state
-        let saved_variables = self.variables.clone();
-        let saved_variable_types = self.variable_types.clone();
-        let saved_function = self.current_function;
-
-        // Enter function context
-        self.enter_function(function);
-
-        // Declare parameters as local variables
-        for (i, param_name) in func_def.func.args.iter().enumerate() {
-            let param_value = function.get_nth_param(i as u32).unwrap();
-            let alloca =
-                self.declare_variable(param_name, self.type_mapping.get_number_type().into())?;
-            self.builder.build_store(alloca, param_value).map_err(|e| {
-                IRGenError::InvalidOperation(format!(
-                    "Failed to store parameter '{}': {}",
-                    param_name, e
-                ))
-            })?;
-        }
-
-        // Generate function body
-        let mut last_value = self.gen_number_const(0.0).into();
-        for stmt in &func_def.func.body {
-            // Check if current block already has a terminator
-            if let Some(current_block) = self.builder.get_insert_block() {
-                if current_block.get_terminator().is_some() {
-                    // Current block is terminated, skip remaining statements
-                    break;
-                }
-            }
-            last_value = self.visit_stmt(stmt)?;
-        }
-
-        // Add return if not present
-        if function
-            .get_last_basic_block()
-            .map_or(true, |bb| bb.get_terminator().is_none())
-        {
-            let ret_val = self.convert_to_return_type(last_value)?;
-            self.builder.build_return(Some(&ret_val)).map_err(|e| {
-                IRGenError::InvalidOperation(format!("Failed to build return: {}", e))
-            })?;
-        }
-
-        // Restore state
-        self.variables = saved_variables;
-        self.variable_types = saved_variable_types;
-        self.current_function = saved_function;
-
+        self.gen_function(func_def, &func_def.name)?;
         Ok(self.gen_number_const(0.0).into())
     }
 
@@ -0,0 +1,72 @@
+//! Accessors, `list[| i]`, `map[? key]`, `grid[# x, y]` and `array[@ i]`.
+//!
+//! Those with a ds_* builtin are lowered to a call to it: `list[| i]` reads like
+//! `ds_list_find_value(list, i)`, `map[? key]` like `ds_map_find_value(map, key)`
+//! and `map[? key] = value` like `ds_map_set(map, key, value)`. Grids and arrays
+//! do not exist yet, nor does writing to a list, so the other forms are reported
+//! at the accessor as not supported.
+
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::parser::Span;
+use crate::parser::expr::{AccessorKind, Expr};
+use inkwell::values::BasicValueEnum;
+
+fn unsupported(kind: AccessorKind, action: &str, span: &Span) -> IRGenError {
+    IRGenError::Unsupported {
+        message: format!(
+            "{} through the '{}' accessor is not supported yet",
+            action,
+            kind.opening()
+        ),
+        span: span.clone(),
+    }
+}
+
+/// The arguments of the builtin an accessor lowers to: the collection, the
+/// indices, then the value written, if any
+fn builtin_args(target: &Expr, indices: &[Expr], value: Option<&Expr>) -> Vec<Expr> {
+    std::iter::once(target)
+        .chain(indices)
+        .chain(value)
+        .cloned()
+        .collect()
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Read the element of `target` the accessor at `span` names
+    pub(crate) fn gen_accessor_get(
+        &mut self,
+        kind: AccessorKind,
+        target: &Expr,
+        indices: &[Expr],
+        span: &Span,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let builtin = match kind {
+            AccessorKind::List => "ds_list_find_value",
+            AccessorKind::Map => "ds_map_find_value",
+            AccessorKind::Grid | AccessorKind::ArrayRef => {
+                return Err(unsupported(kind, "Reading", span));
+            }
+        };
+        self.gen_builtin_call(builtin, &builtin_args(target, indices, None))
+    }
+
+    /// Assign `value` to the element of `target` the accessor at `span` names
+    pub(crate) fn gen_accessor_set(
+        &mut self,
+        kind: AccessorKind,
+        target: &Expr,
+        indices: &[Expr],
+        value: &Expr,
+        span: &Span,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        match kind {
+            AccessorKind::Map => {
+                self.gen_builtin_call("ds_map_set", &builtin_args(target, indices, Some(value)))
+            }
+            AccessorKind::List | AccessorKind::Grid | AccessorKind::ArrayRef => {
+                Err(unsupported(kind, "Assigning", span))
+            }
+        }
+    }
+}
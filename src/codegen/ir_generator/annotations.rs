@@ -0,0 +1,134 @@
+use crate::codegen::ir_generator::IRGenerator;
+use crate::codegen::ir_generator::debug_info::stmt_start;
+use crate::parser::{func_def::FuncDef, stmt::Stmt};
+use inkwell::basic_block::BasicBlock;
+use inkwell::values::{AnyValue, FunctionValue, InstructionValue};
+
+/// The IR generated for one top-level statement or one function, for listings
+/// that interleave source and IR
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrSnippet {
+    /// LLVM name of the function the IR is part of: `main` for top-level statements
+    pub function: String,
+    /// Byte offset in the source where the statement or function starts
+    pub start: usize,
+    /// Byte offset where it ends, for functions and compound statements
+    pub end: Option<usize>,
+    /// The IR, one line per entry: the instructions of a statement with the labels
+    /// of the blocks it started, or the whole definition of a function
+    pub ir: Vec<String>,
+}
+
+/// Where the builder was before a statement was generated
+pub(crate) struct IrMark<'ctx> {
+    block: BasicBlock<'ctx>,
+    last: Option<InstructionValue<'ctx>>,
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Record an [`IrSnippet`] for every top-level statement and function generated
+    /// from now on
+    pub fn record_annotations(&mut self) {
+        self.annotations = Some(Vec::new());
+    }
+
+    /// Take the snippets recorded so far, in source order. Recording stays enabled
+    /// if it was.
+    pub fn take_annotations(&mut self) -> Vec<IrSnippet> {
+        let mut snippets = self
+            .annotations
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default();
+        snippets.sort_by_key(|snippet| snippet.start);
+        snippets
+    }
+
+    /// Mark the current insert position, if snippets are being recorded
+    pub(crate) fn ir_mark(&self) -> Option<IrMark<'ctx>> {
+        self.annotations.as_ref()?;
+        let block = self.builder.get_insert_block()?;
+        Some(IrMark {
+            block,
+            last: block.get_last_instruction(),
+        })
+    }
+
+    /// Record the instructions emitted since `mark` for the top-level `stmt`.
+    /// Statements with no position of their own, like `return;`, join the one before.
+    pub(crate) fn annotate_statement(&mut self, stmt: &Stmt, mark: Option<IrMark<'ctx>>) {
+        let (Some(mark), Some(snippets)) = (mark, self.annotations.as_mut()) else {
+            return;
+        };
+        let ir = instructions_since(&mark);
+        let (start, end) = match stmt {
+            Stmt::If(.., span)
+            | Stmt::Block(_, span)
+            | Stmt::Repeat(.., span)
+            | Stmt::While(.., span)
+            | Stmt::DoUntil(.., span)
+            | Stmt::For(.., span)
+            | Stmt::Switch(.., span) => (span.start, Some(span.end)),
+            _ => match stmt_start(stmt) {
+                Some(span) => (span.start, None),
+                None => {
+                    if let Some(previous) = snippets.iter_mut().rev().find(|s| s.function == "main")
+                    {
+                        previous.ir.extend(ir);
+                        return;
+                    }
+                    (0, None)
+                }
+            },
+        };
+        snippets.push(IrSnippet {
+            function: "main".to_string(),
+            start,
+            end,
+            ir,
+        });
+    }
+
+    /// Record the whole of `function`, generated for `func_def`
+    pub(crate) fn annotate_function(&mut self, func_def: &FuncDef, function: FunctionValue<'ctx>) {
+        let Some(snippets) = self.annotations.as_mut() else {
+            return;
+        };
+        snippets.push(IrSnippet {
+            function: function.get_name().to_string_lossy().into_owned(),
+            start: func_def.extent.start,
+            end: Some(func_def.extent.end),
+            ir: function
+                .print_to_string()
+                .to_string()
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_string)
+                .collect(),
+        });
+    }
+}
+
+/// The instructions after `mark` in its function, with a label line for each block
+/// that follows. Blocks are appended as they are created, so these are exactly the
+/// ones generated since, except for allocas, which go to the entry block.
+fn instructions_since(mark: &IrMark<'_>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut block = mark.block;
+    let mut instruction = match mark.last {
+        Some(last) => last.get_next_instruction(),
+        None => block.get_first_instruction(),
+    };
+    loop {
+        while let Some(inst) = instruction {
+            lines.push(format!("  {}", inst.print_to_string().to_string().trim()));
+            instruction = inst.get_next_instruction();
+        }
+        let Some(next) = block.get_next_basic_block() else {
+            return lines;
+        };
+        lines.push(format!("{}:", next.get_name().to_string_lossy()));
+        block = next;
+        instruction = block.get_first_instruction();
+    }
+}
@@ -0,0 +1,495 @@
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::codegen::runtime;
+use crate::codegen::runtime::collections;
+use crate::codegen::runtime::instances;
+use crate::codegen::runtime::output;
+use crate::codegen::runtime::profile;
+use crate::codegen::runtime::trace;
+use crate::codegen::runtime::trap;
+use crate::parser::expr::Expr;
+use inkwell::module::Linkage;
+use inkwell::types::BasicMetadataTypeEnum;
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValueEnum, FloatValue, FunctionValue, IntValue, PointerValue,
+};
+
+/// Built-in functions with the least and most arguments each takes
+const BUILTINS: &[(&str, usize, usize)] = &[
+    ("string", 1, 1),
+    ("real", 1, 1),
+    ("int64", 1, 1),
+    ("bool", 1, 1),
+    ("string_format", 3, 3),
+    ("string_length", 1, 1),
+    ("string_char_at", 2, 2),
+    ("string_copy", 3, 3),
+    ("string_pos", 2, 2),
+    ("ds_list_create", 0, 0),
+    ("ds_list_destroy", 1, 1),
+    // Any number of values can be added at once
+    ("ds_list_add", 2, usize::MAX),
+    ("ds_list_size", 1, 1),
+    ("ds_list_find_value", 2, 2),
+    ("ds_list_copy", 2, 2),
+    ("ds_map_create", 0, 0),
+    ("ds_map_destroy", 1, 1),
+    ("ds_map_set", 3, 3),
+    ("ds_map_find_value", 2, 2),
+    ("ds_map_exists", 2, 2),
+    ("ds_map_copy", 2, 2),
+    ("math_set_epsilon", 1, 1),
+    ("math_get_epsilon", 0, 0),
+    ("is_nan", 1, 1),
+    ("is_infinity", 1, 1),
+    ("println", 1, usize::MAX),
+];
+
+/// Why `count` arguments are wrong for the built-in `name`, or `None` if they are
+/// right or there is no such built-in
+pub(crate) fn builtin_argument_error(name: &str, count: usize) -> Option<String> {
+    let &(_, min, max) = BUILTINS.iter().find(|(builtin, ..)| *builtin == name)?;
+    if (min..=max).contains(&count) {
+        return None;
+    }
+    let expected = if max == usize::MAX {
+        format!("at least {}", min)
+    } else {
+        min.to_string()
+    };
+    Some(format!(
+        "Function '{}' takes {} argument(s) but {} were given",
+        name, expected, count
+    ))
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Whether `name` is a built-in function a call can resolve to.
+    /// Functions defined by the script take precedence over built-ins.
+    pub fn is_builtin(&self, name: &str) -> bool {
+        self.functions.get(name).is_none() && BUILTINS.iter().any(|(builtin, ..)| *builtin == name)
+    }
+
+    /// Generate a call to the built-in function `name`
+    pub fn gen_builtin_call(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        if !BUILTINS.iter().any(|(builtin, ..)| *builtin == name) {
+            return Err(IRGenError::UndefinedFunction(name.to_string()));
+        }
+        if let Some(message) = builtin_argument_error(name, args.len()) {
+            return Err(IRGenError::ArgumentCountMismatch(message));
+        }
+
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.visit_expr_impl(arg)?);
+        }
+
+        match name {
+            "string" => self.gen_to_string(values[0]),
+            "real" => self.gen_to_number(values[0]),
+            "int64" => Ok(self.gen_to_int64(values[0])?.into()),
+            "bool" => Ok(self.convert_to_bool(values[0])?.into()),
+            "string_format" => {
+                let mut numbers = Vec::with_capacity(values.len());
+                for value in values {
+                    numbers.push(self.gen_to_number(value)?.into());
+                }
+                self.call_runtime(runtime::STRING_FORMAT, &numbers)
+            }
+            "string_length" | "string_char_at" | "string_copy" | "string_pos" => {
+                self.gen_string_function(name, &values)
+            }
+            "math_set_epsilon" => self.gen_math_set_epsilon(values[0]),
+            "math_get_epsilon" => self.gen_math_get_epsilon(),
+            "is_nan" | "is_infinity" => self.gen_float_class(name, values[0]),
+            "println" => self.gen_println(&values),
+            _ => self.gen_collection_call(name, &values),
+        }
+    }
+
+    /// Generate a call to one of the string inspection functions. Their strings are
+    /// converted as by `string()` and their positions and counts as by `real()`.
+    fn gen_string_function(
+        &self,
+        name: &str,
+        values: &[BasicValueEnum<'ctx>],
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        // Each takes its strings first
+        let (function, strings) = match name {
+            "string_length" => (runtime::STRING_LENGTH, 1),
+            "string_char_at" => (runtime::STRING_CHAR_AT, 1),
+            "string_copy" => (runtime::STRING_COPY, 1),
+            _ => (runtime::STRING_POS, 2),
+        };
+        let mut args: Vec<BasicMetadataValueEnum<'ctx>> = Vec::with_capacity(values.len());
+        for (i, &value) in values.iter().enumerate() {
+            let arg = if i < strings {
+                self.gen_to_string(value)?
+            } else {
+                self.gen_to_number(value)?
+            };
+            args.push(arg.into());
+        }
+        self.call_runtime(function, &args)
+    }
+
+    /// `is_nan(v)` or `is_infinity(v)`. A value that is not a number is converted as by
+    /// `real()` first, so `is_nan("abc")` is false.
+    fn gen_float_class(
+        &self,
+        name: &str,
+        value: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let number = self.gen_to_number(value)?.into_float_value();
+        let result = if name == "is_nan" {
+            self.gen_is_nan(number)?
+        } else {
+            self.gen_is_infinity(number)?
+        };
+        Ok(result.into())
+    }
+
+    /// `println(...)`: every argument converted as by `string()`, joined without
+    /// separators and printed as one line. Returns undefined (0).
+    fn gen_println(&self, values: &[BasicValueEnum<'ctx>]) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let mut text = self.gen_to_string(values[0])?;
+        for &value in &values[1..] {
+            let next = self.gen_to_string(value)?;
+            text = self.gen_string_concat(text.into_pointer_value(), next.into_pointer_value())?;
+        }
+        let function = self.runtime_function(output::PRINTLN);
+        self.builder
+            .build_call(function, &[self.output_ptr().into(), text.into()], "")
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build call: {}", e)))?;
+        Ok(self.gen_number_const(0.0).into())
+    }
+
+    /// The executor's output, declared in the module on first use
+    fn output_ptr(&self) -> PointerValue<'ctx> {
+        let global = self
+            .module
+            .get_global(output::OUTPUT_GLOBAL)
+            .unwrap_or_else(|| {
+                let global =
+                    self.module
+                        .add_global(self.context.i8_type(), None, output::OUTPUT_GLOBAL);
+                global.set_linkage(Linkage::External);
+                global
+            });
+        global.as_pointer_value()
+    }
+
+    /// Generate a call to one of the ds_list or ds_map functions. Handles, positions
+    /// and stored values are numbers; map keys may also be strings.
+    fn gen_collection_call(
+        &self,
+        name: &str,
+        values: &[BasicValueEnum<'ctx>],
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let mut args: Vec<BasicMetadataValueEnum<'ctx>> = vec![self.collections_ptr().into()];
+        match name {
+            "ds_list_create" => self.call_runtime(collections::LIST_CREATE, &args),
+            "ds_map_create" => self.call_runtime(collections::MAP_CREATE, &args),
+            "ds_list_add" => {
+                let list = self.collection_number(name, values[0])?;
+                for &value in &values[1..] {
+                    let value = self.collection_number(name, value)?;
+                    self.call_runtime(
+                        collections::LIST_ADD,
+                        &[args[0], list.into(), value.into()],
+                    )?;
+                }
+                Ok(self.gen_undefined_const().into())
+            }
+            "ds_map_set" | "ds_map_find_value" | "ds_map_exists" => {
+                args.push(self.collection_number(name, values[0])?.into());
+                let (key_string, key_number) = match values[1] {
+                    BasicValueEnum::PointerValue(key) => (key, self.gen_number_const(0.0)),
+                    key => (self.gen_null_const(), self.collection_number(name, key)?),
+                };
+                args.push(key_string.into());
+                args.push(key_number.into());
+                if let Some(&value) = values.get(2) {
+                    args.push(self.collection_number(name, value)?.into());
+                }
+                let function = match name {
+                    "ds_map_set" => collections::MAP_SET,
+                    "ds_map_find_value" => collections::MAP_FIND_VALUE,
+                    _ => collections::MAP_EXISTS,
+                };
+                let result = self.call_runtime(function, &args)?;
+                if name != "ds_map_exists" {
+                    return Ok(result);
+                }
+                self.builder
+                    .build_float_compare(
+                        inkwell::FloatPredicate::ONE,
+                        result.into_float_value(),
+                        self.gen_number_const(0.0),
+                        "exists",
+                    )
+                    .map(|v| v.into())
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("Float comparison failed: {}", e))
+                    })
+            }
+            _ => {
+                for &value in values {
+                    args.push(self.collection_number(name, value)?.into());
+                }
+                let function = match name {
+                    "ds_list_destroy" => collections::LIST_DESTROY,
+                    "ds_list_size" => collections::LIST_SIZE,
+                    "ds_list_find_value" => collections::LIST_FIND_VALUE,
+                    "ds_map_destroy" => collections::MAP_DESTROY,
+                    "ds_list_copy" => collections::LIST_COPY,
+                    "ds_map_copy" => collections::MAP_COPY,
+                    _ => unreachable!("unknown collection function '{}'", name),
+                };
+                self.call_runtime(function, &args)
+            }
+        }
+    }
+
+    /// A handle, position or value passed to a collection function, as a number
+    fn collection_number(
+        &self,
+        function: &str,
+        value: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<FloatValue<'ctx>> {
+        match self.convert_to_return_type(value)? {
+            BasicValueEnum::FloatValue(number) => Ok(number),
+            _ => Err(IRGenError::TypeMismatch(format!(
+                "'{}' only takes numbers here",
+                function
+            ))),
+        }
+    }
+
+    /// The executor's collections, declared in the module on first use
+    fn collections_ptr(&self) -> PointerValue<'ctx> {
+        let global = self
+            .module
+            .get_global(collections::COLLECTIONS_GLOBAL)
+            .unwrap_or_else(|| {
+                let global = self.module.add_global(
+                    self.context.i8_type(),
+                    None,
+                    collections::COLLECTIONS_GLOBAL,
+                );
+                global.set_linkage(Linkage::External);
+                global
+            });
+        global.as_pointer_value()
+    }
+
+    /// Convert a value to a string the way `string()` does
+    pub fn gen_to_string(&self, value: BasicValueEnum<'ctx>) -> IRGenResult<BasicValueEnum<'ctx>> {
+        match value {
+            BasicValueEnum::PointerValue(ptr) => {
+                // Null prints as "undefined", any other string is already one
+                let undefined = self.gen_string_const(runtime::UNDEFINED_TEXT);
+                let is_null = self.builder.build_is_null(ptr, "is_null").map_err(|e| {
+                    IRGenError::InvalidOperation(format!("Null check failed: {}", e))
+                })?;
+                self.builder
+                    .build_select(is_null, undefined, ptr, "to_string")
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("String conversion failed: {}", e))
+                    })
+            }
+            BasicValueEnum::IntValue(v) if v.get_type() == self.type_mapping.get_bool_type() => {
+                let true_str = self.gen_string_const(runtime::bool_text(true));
+                let false_str = self.gen_string_const(runtime::bool_text(false));
+                self.builder
+                    .build_select(v, true_str, false_str, "bool_to_string")
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("String conversion failed: {}", e))
+                    })
+            }
+            // All the digits of an int64, which a number may not hold
+            BasicValueEnum::IntValue(v) if v.get_type() == self.type_mapping.get_int64_type() => {
+                self.call_runtime(runtime::STRING_FROM_INT64, &[v.into()])
+            }
+            _ => {
+                let number = self.gen_to_number(value)?;
+                self.call_runtime(runtime::STRING_FROM_NUMBER, &[number.into()])
+            }
+        }
+    }
+
+    /// Convert a value to a number the way `real()` does
+    pub fn gen_to_number(&self, value: BasicValueEnum<'ctx>) -> IRGenResult<BasicValueEnum<'ctx>> {
+        match value {
+            BasicValueEnum::PointerValue(ptr) => {
+                self.call_runtime(runtime::STRING_TO_NUMBER, &[ptr.into()])
+            }
+            _ => self.convert_to_return_type(value),
+        }
+    }
+
+    /// Convert a value to a 64-bit integer the way `int64()` does, truncating
+    /// toward zero
+    pub fn gen_to_int64(&self, value: BasicValueEnum<'ctx>) -> IRGenResult<IntValue<'ctx>> {
+        let int64_type = self.type_mapping.get_int64_type();
+        match value {
+            BasicValueEnum::IntValue(v) if v.get_type() == int64_type => Ok(v),
+            _ => {
+                let number = self.gen_to_number(value)?.into_float_value();
+                self.builder
+                    .build_float_to_signed_int(number, int64_type, "to_int64")
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("Int64 conversion failed: {}", e))
+                    })
+            }
+        }
+    }
+
+    /// Concatenate two strings
+    pub fn gen_string_concat(
+        &self,
+        lhs: PointerValue<'ctx>,
+        rhs: PointerValue<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        self.call_runtime(runtime::STRING_CONCAT, &[lhs.into(), rhs.into()])
+    }
+
+    /// Compare two strings bytewise, giving a negative, zero or positive i32
+    pub fn gen_string_compare(
+        &self,
+        lhs: PointerValue<'ctx>,
+        rhs: PointerValue<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        self.call_runtime(runtime::STRING_COMPARE, &[lhs.into(), rhs.into()])
+    }
+
+    /// Call a runtime function, declaring it in the module on first use
+    pub(crate) fn call_runtime(
+        &self,
+        name: &str,
+        args: &[BasicMetadataValueEnum<'ctx>],
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let function = self.runtime_function(name);
+        self.builder
+            .build_call(function, args, "runtime_call")
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build call: {}", e)))?
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(|| {
+                IRGenError::InvalidOperation(format!("Runtime function '{}' returned void", name))
+            })
+    }
+
+    pub(crate) fn runtime_function(&self, name: &str) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function(name) {
+            return function;
+        }
+
+        let number: BasicMetadataTypeEnum = self.type_mapping.get_number_type().into();
+        let string: BasicMetadataTypeEnum = self.type_mapping.get_string_type().into();
+        let fn_type = match name {
+            runtime::STRING_CONCAT => self
+                .type_mapping
+                .get_string_type()
+                .fn_type(&[string, string], false),
+            runtime::STRING_COMPARE => self
+                .type_mapping
+                .get_int_type()
+                .fn_type(&[string, string], false),
+            runtime::STRING_FROM_NUMBER => self
+                .type_mapping
+                .get_string_type()
+                .fn_type(&[number], false),
+            runtime::STRING_FROM_INT64 => self
+                .type_mapping
+                .get_string_type()
+                .fn_type(&[self.type_mapping.get_int64_type().into()], false),
+            runtime::STRING_TO_NUMBER | runtime::STRING_LENGTH => self
+                .type_mapping
+                .get_number_type()
+                .fn_type(&[string], false),
+            runtime::STRING_FORMAT => self
+                .type_mapping
+                .get_string_type()
+                .fn_type(&[number, number, number], false),
+            runtime::STRING_CHAR_AT => self
+                .type_mapping
+                .get_string_type()
+                .fn_type(&[string, number], false),
+            runtime::STRING_COPY => self
+                .type_mapping
+                .get_string_type()
+                .fn_type(&[string, number, number], false),
+            runtime::STRING_POS => self
+                .type_mapping
+                .get_number_type()
+                .fn_type(&[string, string], false),
+            // The collection functions all take the collections first and return a number
+            collections::LIST_CREATE | collections::MAP_CREATE => self
+                .type_mapping
+                .get_number_type()
+                .fn_type(&[string], false),
+            collections::LIST_DESTROY | collections::LIST_SIZE | collections::MAP_DESTROY => self
+                .type_mapping
+                .get_number_type()
+                .fn_type(&[string, number], false),
+            collections::LIST_ADD
+            | collections::LIST_FIND_VALUE
+            | collections::LIST_COPY
+            | collections::MAP_COPY => self
+                .type_mapping
+                .get_number_type()
+                .fn_type(&[string, number, number], false),
+            collections::MAP_FIND_VALUE | collections::MAP_EXISTS => self
+                .type_mapping
+                .get_number_type()
+                .fn_type(&[string, number, string, number], false),
+            collections::MAP_SET => self
+                .type_mapping
+                .get_number_type()
+                .fn_type(&[string, number, string, number, number], false),
+            // The field functions take the instances and the field name
+            instances::GET_SELF_FIELD | instances::GET_OTHER_FIELD => self
+                .type_mapping
+                .get_number_type()
+                .fn_type(&[string, string], false),
+            instances::SET_SELF_FIELD | instances::SET_OTHER_FIELD => self
+                .type_mapping
+                .get_number_type()
+                .fn_type(&[string, string, number], false),
+            // The profiling hooks take the profile and the function's name
+            profile::PROFILE_ENTER | profile::PROFILE_EXIT => {
+                self.context.void_type().fn_type(&[string, string], false)
+            }
+            // Tracing takes the trace and the statement's offset
+            trace::TRACE_STATEMENT => {
+                let offset: BasicMetadataTypeEnum = self.type_mapping.get_int64_type().into();
+                self.context.void_type().fn_type(&[string, offset], false)
+            }
+            // Printing takes the output and the line
+            output::PRINTLN => self.context.void_type().fn_type(&[string, string], false),
+            // Raising takes the trap, the kind, the message, the function and the
+            // span's offsets; unwinding the trap, the caller and the call's offsets
+            trap::RAISE => {
+                let kind: BasicMetadataTypeEnum = self.type_mapping.get_int_type().into();
+                let offset: BasicMetadataTypeEnum = self.type_mapping.get_int64_type().into();
+                self.context
+                    .void_type()
+                    .fn_type(&[string, kind, string, string, offset, offset], false)
+            }
+            trap::UNWIND => {
+                let offset: BasicMetadataTypeEnum = self.type_mapping.get_int64_type().into();
+                self.context
+                    .void_type()
+                    .fn_type(&[string, string, offset, offset], false)
+            }
+            // Checking the stack takes nothing and answers 1 or 0
+            trap::STACK_LOW => self.type_mapping.get_int_type().fn_type(&[], false),
+            _ => unreachable!("unknown runtime function '{}'", name),
+        };
+        self.module.add_function(name, fn_type, None)
+    }
+}
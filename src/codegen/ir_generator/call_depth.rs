@@ -0,0 +1,141 @@
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::codegen::runtime::trap::{self, ErrorKind};
+use crate::parser::Span;
+use inkwell::IntPredicate;
+use inkwell::builder::BuilderError;
+use inkwell::module::Linkage;
+use inkwell::values::{FunctionValue, InstructionOpcode, IntValue, PointerValue};
+
+/// Name of the module global counting the calls of script functions that have not
+/// returned yet
+const CALL_DEPTH_GLOBAL: &str = "__col_call_depth";
+
+fn call_depth_error(e: BuilderError) -> IRGenError {
+    IRGenError::InvalidOperation(format!("Call depth check failed: {}", e))
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Count a call of `function`, at the insert position in its entry block, and
+    /// raise an error naming `name`, defined at `span`, if more calls than the limit
+    /// are open, or if the thread's stack is too low for another call, which a
+    /// thread with a small stack reaches before the limit. Nothing is generated
+    /// without a limit.
+    pub(crate) fn gen_call_depth_enter(
+        &self,
+        function: FunctionValue<'ctx>,
+        name: &str,
+        span: &Span,
+    ) -> IRGenResult<()> {
+        let Some(limit) = self.max_call_depth else {
+            return Ok(());
+        };
+        let depth_type = self.context.i64_type();
+        let depth = self.gen_call_depth_add(1)?;
+        let too_deep = self
+            .builder
+            .build_int_compare(
+                IntPredicate::UGT,
+                depth,
+                depth_type.const_int(limit as u64, false),
+                "call_too_many",
+            )
+            .map_err(call_depth_error)?;
+        let stack_low = self
+            .builder
+            .build_call(self.runtime_function(trap::STACK_LOW), &[], "stack_low")
+            .map_err(call_depth_error)?
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(|| {
+                IRGenError::InvalidOperation(format!("{} returned void", trap::STACK_LOW))
+            })?
+            .into_int_value();
+        let stack_low = self
+            .builder
+            .build_int_compare(
+                IntPredicate::NE,
+                stack_low,
+                self.type_mapping.get_int_type().const_zero(),
+                "stack_low_flag",
+            )
+            .map_err(call_depth_error)?;
+        let too_deep = self
+            .builder
+            .build_or(too_deep, stack_low, "call_too_deep")
+            .map_err(call_depth_error)?;
+
+        let exceeded_block = self
+            .context
+            .append_basic_block(function, "call_depth_exceeded");
+        let ok_block = self.context.append_basic_block(function, "call_depth_ok");
+        self.builder
+            .build_conditional_branch(too_deep, exceeded_block, ok_block)
+            .map_err(call_depth_error)?;
+        self.builder.position_at_end(exceeded_block);
+        self.gen_raise(
+            ErrorKind::CallDepthExceeded,
+            &format!("maximum call depth exceeded in function {}", name),
+            Some(span),
+        )?;
+        self.builder.position_at_end(ok_block);
+        Ok(())
+    }
+
+    /// Count the end of the call before every return in `function`, once its body
+    /// is complete, including the returns of a raised error. Nothing is generated
+    /// without a limit.
+    pub(crate) fn gen_call_depth_exits(&self, function: FunctionValue<'ctx>) -> IRGenResult<()> {
+        if self.max_call_depth.is_none() {
+            return Ok(());
+        }
+        for block in function.get_basic_blocks() {
+            let Some(terminator) = block.get_terminator() else {
+                continue;
+            };
+            if terminator.get_opcode() == InstructionOpcode::Return {
+                self.builder.position_before(&terminator);
+                self.gen_call_depth_add(-1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add `delta` to the call depth and return the new depth
+    fn gen_call_depth_add(&self, delta: i64) -> IRGenResult<IntValue<'ctx>> {
+        let depth_type = self.context.i64_type();
+        let depth_ptr = self.call_depth_ptr();
+        let depth = self
+            .builder
+            .build_load(depth_type, depth_ptr, "call_depth")
+            .map_err(call_depth_error)?
+            .into_int_value();
+        let depth = self
+            .builder
+            .build_int_add(
+                depth,
+                depth_type.const_int(delta as u64, true),
+                "call_depth_next",
+            )
+            .map_err(call_depth_error)?;
+        self.builder
+            .build_store(depth_ptr, depth)
+            .map_err(call_depth_error)?;
+        Ok(depth)
+    }
+
+    /// The module's call depth, declared on first use. Every call into the module
+    /// returns through the decrements, so it is back at 0 between calls.
+    fn call_depth_ptr(&self) -> PointerValue<'ctx> {
+        let depth_type = self.context.i64_type();
+        let global = self
+            .module
+            .get_global(CALL_DEPTH_GLOBAL)
+            .unwrap_or_else(|| {
+                let global = self.module.add_global(depth_type, None, CALL_DEPTH_GLOBAL);
+                global.set_linkage(Linkage::Internal);
+                global.set_initializer(&depth_type.const_zero());
+                global
+            });
+        global.as_pointer_value()
+    }
+}
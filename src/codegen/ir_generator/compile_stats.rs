@@ -0,0 +1,71 @@
+use crate::codegen::ir_generator::gml_name;
+use inkwell::values::{FunctionValue, InstructionOpcode};
+use std::time::{Duration, Instant};
+
+/// Size and generation time of one compiled function
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionStats {
+    /// LLVM name of the function, e.g. `outer.inner` for a nested function, or
+    /// `main` for the top-level code
+    pub name: String,
+    pub blocks: usize,
+    pub instructions: usize,
+    pub allocas: usize,
+    /// Time spent generating this function's IR, excluding nested functions
+    pub micros: u128,
+}
+
+/// Statistics collected while generating a module.
+///
+/// Functions are generated recursively (a nested function is generated while its
+/// enclosing function is still open), so time spent in a nested function is
+/// subtracted from the function enclosing it.
+#[derive(Debug, Default)]
+pub struct CompileStats {
+    functions: Vec<FunctionStats>,
+    // Time spent in already finished nested functions, one entry per open function
+    nested_time: Vec<Duration>,
+}
+
+impl CompileStats {
+    /// Start timing a function
+    pub fn begin(&mut self) -> Instant {
+        self.nested_time.push(Duration::ZERO);
+        Instant::now()
+    }
+
+    /// Record `function`, started at `start`, once its body has been generated
+    pub fn finish(&mut self, function: FunctionValue<'_>, start: Instant) {
+        let elapsed = start.elapsed();
+        let nested = self.nested_time.pop().unwrap_or_default();
+        if let Some(parent) = self.nested_time.last_mut() {
+            *parent += elapsed;
+        }
+
+        let mut instructions = 0;
+        let mut allocas = 0;
+        for block in function.get_basic_block_iter() {
+            let mut instruction = block.get_first_instruction();
+            while let Some(inst) = instruction {
+                instructions += 1;
+                if inst.get_opcode() == InstructionOpcode::Alloca {
+                    allocas += 1;
+                }
+                instruction = inst.get_next_instruction();
+            }
+        }
+
+        self.functions.push(FunctionStats {
+            name: gml_name(function),
+            blocks: function.count_basic_blocks() as usize,
+            instructions,
+            allocas,
+            micros: elapsed.saturating_sub(nested).as_micros(),
+        });
+    }
+
+    /// The recorded functions, in the order they finished generating
+    pub fn into_functions(self) -> Vec<FunctionStats> {
+        self.functions
+    }
+}
@@ -0,0 +1,293 @@
+use crate::codegen::ir_generator::{IRGenerator, gml_name};
+use crate::parser::Span;
+use crate::parser::expr::Expr;
+use crate::parser::program::IncludedSource;
+use crate::parser::stmt::Stmt;
+use crate::utils::line_index::LineIndex;
+use inkwell::debug_info::{
+    AsDIScope, DIFile, DIFlags, DIFlagsConstants, DILocation, DIScope, DIType, DWARFEmissionKind,
+    DWARFSourceLanguage, DebugInfoBuilder, debug_metadata_version,
+};
+use inkwell::module::FlagBehavior;
+use inkwell::values::FunctionValue;
+use std::path::Path;
+use std::sync::Arc;
+
+/// DWARF encoding of floating point base types
+const DW_ATE_FLOAT: u32 = 0x04;
+
+/// DWARF debug information emitted alongside the code, see [`IRGenerator::enable_debug_info`]
+pub struct DebugInfo<'ctx> {
+    builder: DebugInfoBuilder<'ctx>,
+    file: DIFile<'ctx>,
+    /// GML numbers, the type of every parameter and return value
+    number: DIType<'ctx>,
+    lines: LineIndex<'ctx>,
+    /// The files `#include` spliced code from, each with where its spans start
+    included: Vec<(usize, DIFile<'ctx>, LineIndex<'ctx>)>,
+    /// Subprograms of the functions being generated, innermost last, each with
+    /// its file and the location that was current before it was entered
+    scopes: Vec<(DIScope<'ctx>, DIFile<'ctx>, Option<DILocation<'ctx>>)>,
+}
+
+impl<'ctx> DebugInfo<'ctx> {
+    /// The file a source offset is in, with the line and column there
+    fn position(&self, offset: usize) -> (DIFile<'ctx>, u32, u32) {
+        let (file, lines, offset) = match self
+            .included
+            .iter()
+            .rev()
+            .find(|(start, ..)| *start <= offset)
+        {
+            Some((start, file, lines)) => (*file, lines, offset - start),
+            None => (self.file, &self.lines, offset),
+        };
+        let (line, column) = lines.line_col(offset);
+        (file, line as u32, column as u32)
+    }
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Emit debug information for the program generated from now on, mapping
+    /// every instruction back to the line and column of `source` it came from.
+    /// `path` names the source file in the compile unit.
+    pub fn enable_debug_info(&mut self, path: &Path, source: &'ctx str) {
+        let (file_name, directory) = file_and_directory(path);
+
+        let version = self
+            .context
+            .i32_type()
+            .const_int(u64::from(debug_metadata_version()), false);
+        self.module
+            .add_basic_value_flag("Debug Info Version", FlagBehavior::Warning, version);
+
+        let (builder, compile_unit) = self.module.create_debug_info_builder(
+            true,
+            DWARFSourceLanguage::C,
+            &file_name,
+            &directory,
+            "col",
+            false,
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+        let number = builder
+            .create_basic_type("number", 64, DW_ATE_FLOAT, DIFlags::ZERO)
+            .expect("the type has a name")
+            .as_type();
+        self.debug_info = Some(DebugInfo {
+            builder,
+            file: compile_unit.get_file(),
+            number,
+            lines: LineIndex::new(source),
+            included: Vec::new(),
+            scopes: Vec::new(),
+        });
+    }
+
+    /// Map the code spliced in from `included`, the files the program's `#include`s
+    /// named, back to those files. Does nothing unless debug information is enabled.
+    pub fn enable_included_debug_info(&mut self, included: &'ctx [Arc<IncludedSource>]) {
+        let Some(debug) = self.debug_info.as_mut() else {
+            return;
+        };
+        let files: Vec<_> = included
+            .iter()
+            .map(|source| {
+                let (file_name, directory) = file_and_directory(&source.path);
+                let file = debug.builder.create_file(&file_name, &directory);
+                (source.start, file, LineIndex::new(&source.content))
+            })
+            .collect();
+        debug.included = files;
+    }
+
+    /// Give `function` a subprogram starting at `span` (the top of the file if
+    /// `None`) and attach the code generated from now on to it
+    pub(crate) fn begin_debug_function(
+        &mut self,
+        function: FunctionValue<'ctx>,
+        span: Option<&Span>,
+    ) {
+        let Some(debug) = self.debug_info.as_mut() else {
+            return;
+        };
+        let (file, line, column) =
+            span.map_or((debug.file, 1, 1), |span| debug.position(span.start));
+        let name = gml_name(function);
+
+        let params = vec![debug.number; function.count_params() as usize];
+        let subroutine_type = debug.builder.create_subroutine_type(
+            file,
+            Some(debug.number),
+            &params,
+            DIFlags::PUBLIC,
+        );
+        let subprogram = debug.builder.create_function(
+            file.as_debug_info_scope(),
+            &name,
+            None,
+            file,
+            line,
+            subroutine_type,
+            false,
+            true,
+            line,
+            DIFlags::PUBLIC,
+            false,
+        );
+        function.set_subprogram(subprogram);
+
+        let scope = subprogram.as_debug_info_scope();
+        debug
+            .scopes
+            .push((scope, file, self.builder.get_current_debug_location()));
+        let location = debug
+            .builder
+            .create_debug_location(self.context, line, column, scope, None);
+        self.builder.set_current_debug_location(location);
+    }
+
+    /// Leave the function entered last, going back to the location of the
+    /// function enclosing it
+    pub(crate) fn end_debug_function(&mut self) {
+        let Some(debug) = self.debug_info.as_mut() else {
+            return;
+        };
+        match debug.scopes.pop() {
+            Some((_, _, Some(location))) => self.builder.set_current_debug_location(location),
+            _ => self.builder.unset_current_debug_location(),
+        }
+    }
+
+    /// Attach the code generated for `stmt` to the line it starts on.
+    /// Statements without a position of their own keep the current location.
+    pub(crate) fn set_debug_location(&mut self, stmt: &Stmt) {
+        let Some(debug) = self.debug_info.as_ref() else {
+            return;
+        };
+        let (Some(&(scope, scope_file, _)), Some(span)) = (debug.scopes.last(), stmt_start(stmt))
+        else {
+            return;
+        };
+        let (file, line, column) = debug.position(span.start);
+        // The top-level statements of an included file run in the including file's
+        // `main`, so they get a block in their own file
+        let scope = if file == scope_file {
+            scope
+        } else {
+            debug
+                .builder
+                .create_lexical_block(scope, file, line, column)
+                .as_debug_info_scope()
+        };
+        let location = debug
+            .builder
+            .create_debug_location(self.context, line, column, scope, None);
+        self.builder.set_current_debug_location(location);
+    }
+
+    /// Resolve the debug information; must be done before the module is verified
+    pub(crate) fn finalize_debug_info(&self) {
+        if let Some(debug) = &self.debug_info {
+            debug.builder.finalize();
+        }
+    }
+}
+
+/// The name of the file at `path` and the directory it is in
+fn file_and_directory(path: &Path) -> (String, String) {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let directory = path
+        .parent()
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    (file_name, directory)
+}
+
+/// The leftmost position in a statement's own code, not counting nested blocks
+pub(crate) fn stmt_start(stmt: &Stmt) -> Option<Span> {
+    match stmt {
+        Stmt::Expr(expr) | Stmt::Return(Some(expr)) => expr_start(expr),
+        Stmt::Var(vars) => vars.first().map(|(_, _, span)| span.clone()),
+        Stmt::If(cond, _, _, _)
+        | Stmt::Repeat(cond, _, _)
+        | Stmt::While(cond, _, _)
+        | Stmt::Switch(cond, _, _) => expr_start(cond),
+        Stmt::DoUntil(body, cond, _) => stmt_start(body).or_else(|| expr_start(cond)),
+        Stmt::For(init, cond, _, _, _) => init
+            .as_deref()
+            .and_then(stmt_start)
+            .or_else(|| cond.as_deref().and_then(expr_start)),
+        Stmt::Function(func_def) => Some(func_def.span.clone()),
+        Stmt::Error(span) => Some(span.clone()),
+        Stmt::Block(_, _) | Stmt::Return(None) | Stmt::Break | Stmt::Continue => None,
+    }
+}
+
+/// The leftmost position in an expression that records one
+pub(crate) fn expr_start(expr: &Expr) -> Option<Span> {
+    match expr {
+        Expr::Number(_, span)
+        | Expr::Integer(_, span)
+        | Expr::String(_, span)
+        | Expr::True(_, span)
+        | Expr::False(_, span)
+        | Expr::Null(span)
+        | Expr::Identifier(_, span)
+        | Expr::Call(_, _, span)
+        | Expr::Member(_, _, span)
+        | Expr::SelfRef(span)
+        | Expr::OtherRef(span)
+        | Expr::Function(_, span)
+        | Expr::Field(_, _, span)
+        | Expr::Accessor(_, _, _, span) => Some(span.clone()),
+        Expr::Undefined => None,
+        Expr::Not(operand)
+        | Expr::BitNot(operand)
+        | Expr::Positive(operand)
+        | Expr::Negative(operand)
+        | Expr::Paren(operand)
+        | Expr::PreIncrement(operand)
+        | Expr::PostIncrement(operand)
+        | Expr::PreDecrement(operand)
+        | Expr::PostDecrement(operand) => expr_start(operand),
+        Expr::Addition(lhs, rhs)
+        | Expr::Subtraction(lhs, rhs)
+        | Expr::Multiplication(lhs, rhs)
+        | Expr::Division(lhs, rhs)
+        | Expr::IntDivision(lhs, rhs)
+        | Expr::Percent(lhs, rhs)
+        | Expr::Greater(lhs, rhs)
+        | Expr::GreaterEqual(lhs, rhs)
+        | Expr::Less(lhs, rhs)
+        | Expr::LessEqual(lhs, rhs)
+        | Expr::EqualEqual(lhs, rhs)
+        | Expr::NotEqual(lhs, rhs)
+        | Expr::BitAnd(lhs, rhs)
+        | Expr::BitXor(lhs, rhs)
+        | Expr::BitOr(lhs, rhs)
+        | Expr::ShiftLeft(lhs, rhs)
+        | Expr::ShiftRight(lhs, rhs)
+        | Expr::And(lhs, rhs)
+        | Expr::Xor(lhs, rhs)
+        | Expr::Or(lhs, rhs)
+        | Expr::Ternary(lhs, rhs, _)
+        | Expr::Equal(lhs, rhs)
+        | Expr::PlusEqual(lhs, rhs)
+        | Expr::MinusEqual(lhs, rhs)
+        | Expr::StarEqual(lhs, rhs)
+        | Expr::SlashEqual(lhs, rhs)
+        | Expr::PercentEqual(lhs, rhs) => expr_start(lhs).or_else(|| expr_start(rhs)),
+    }
+}
@@ -0,0 +1,290 @@
+use crate::codegen::ir_generator::debug_info::expr_start;
+use crate::codegen::ir_generator::visit_expr::BinaryOp;
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator, gml_name};
+use crate::codegen::runtime::trap::{self, ErrorKind};
+use crate::parser::Span;
+use crate::parser::expr::Expr;
+use crate::parser::language_options::DivByZeroPolicy;
+use inkwell::FloatPredicate;
+use inkwell::builder::BuilderError;
+use inkwell::intrinsics::Intrinsic;
+use inkwell::module::Linkage;
+use inkwell::values::{BasicValueEnum, FloatValue, FunctionValue, IntValue, PointerValue};
+
+fn division_error(e: BuilderError) -> IRGenError {
+    IRGenError::InvalidOperation(format!("Division failed: {}", e))
+}
+
+/// The operators that divide
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Division {
+    /// `/`
+    Quotient,
+    /// `%` and `mod`
+    Remainder,
+    /// `div`
+    Truncated,
+}
+
+impl Division {
+    fn name(self) -> &'static str {
+        match self {
+            Division::Quotient => "division",
+            Division::Remainder => "modulo",
+            Division::Truncated => "integer division",
+        }
+    }
+
+    /// `lhs` divided by `rhs` as generated code computes it under `policy`, or
+    /// `None` if it raises an error for a zero divisor
+    pub(crate) fn evaluate(self, lhs: f64, rhs: f64, policy: DivByZeroPolicy) -> Option<f64> {
+        let result = match self {
+            Division::Quotient => lhs / rhs,
+            Division::Remainder => lhs % rhs,
+            Division::Truncated => (lhs / rhs).trunc(),
+        };
+        if rhs != 0.0 {
+            return Some(result);
+        }
+        match (policy, self) {
+            (DivByZeroPolicy::Zero, _) => Some(0.0),
+            (DivByZeroPolicy::Error, _) | (DivByZeroPolicy::Infinity, Division::Truncated) => None,
+            (DivByZeroPolicy::Infinity, _) => Some(result),
+        }
+    }
+
+    /// The message of the error a zero divisor raises at `span`
+    pub(crate) fn by_zero_message(self, span: Option<&Span>) -> String {
+        match span {
+            Some(span) => format!("{} by zero at {}..{}", self.name(), span.start, span.end),
+            None => format!("{} by zero", self.name()),
+        }
+    }
+}
+
+/// Where `lhs op rhs` is, as far as its operands record positions: from the start of
+/// `lhs` to the end of the first position in `rhs`
+pub(crate) fn operation_span(lhs: &Expr, rhs: &Expr) -> Option<Span> {
+    match (expr_start(lhs), expr_start(rhs)) {
+        (Some(lhs), Some(rhs)) => Some(lhs.start..rhs.end),
+        (lhs, rhs) => lhs.or(rhs),
+    }
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Divide `lhs` by `rhs` as `division` does, handling a zero divisor as the
+    /// [`DivByZeroPolicy`] says. `span` is where the operation is, for the error.
+    /// Nothing is checked when the divisor is a nonzero constant.
+    pub(crate) fn gen_division(
+        &self,
+        division: Division,
+        lhs: BasicValueEnum<'ctx>,
+        rhs: BasicValueEnum<'ctx>,
+        span: Option<Span>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let op = match division {
+            Division::Remainder => BinaryOp::Mod,
+            Division::Quotient | Division::Truncated => BinaryOp::Div,
+        };
+        let result = self.gen_binary_op(op, lhs, rhs)?;
+        let BasicValueEnum::FloatValue(mut result) = result else {
+            return Ok(result);
+        };
+        if let Division::Truncated = division {
+            result = self.gen_trunc(result)?;
+        }
+
+        let raises = match (self.div_by_zero, division) {
+            (DivByZeroPolicy::Zero, _) => false,
+            (DivByZeroPolicy::Error, _) | (DivByZeroPolicy::Infinity, Division::Truncated) => true,
+            (DivByZeroPolicy::Infinity, _) => return Ok(result.into()),
+        };
+        let divisor = self.gen_to_number(rhs)?.into_float_value();
+        if divisor
+            .get_constant()
+            .is_some_and(|(value, _)| value != 0.0)
+        {
+            return Ok(result.into());
+        }
+
+        let number_type = self.type_mapping.get_number_type();
+        let is_zero = self
+            .builder
+            .build_float_compare(
+                FloatPredicate::OEQ,
+                divisor,
+                number_type.const_zero(),
+                "div_by_zero",
+            )
+            .map_err(division_error)?;
+        if !raises {
+            return self
+                .builder
+                .build_select(is_zero, number_type.const_zero(), result, "div_or_zero")
+                .map_err(division_error);
+        }
+
+        let function = self.current_function.ok_or_else(|| {
+            IRGenError::InvalidOperation("Division outside of a function".to_string())
+        })?;
+        let zero_block = self.context.append_basic_block(function, "div_zero");
+        let ok_block = self.context.append_basic_block(function, "div_ok");
+        self.builder
+            .build_conditional_branch(is_zero, zero_block, ok_block)
+            .map_err(division_error)?;
+        self.builder.position_at_end(zero_block);
+        let message = division.by_zero_message(span.as_ref());
+        self.gen_raise(ErrorKind::DivisionByZero, &message, span.as_ref())?;
+        self.builder.position_at_end(ok_block);
+        Ok(result.into())
+    }
+
+    /// `value` truncated toward zero
+    fn gen_trunc(&self, value: FloatValue<'ctx>) -> IRGenResult<FloatValue<'ctx>> {
+        let number_type = self.type_mapping.get_number_type();
+        let trunc = Intrinsic::find("llvm.trunc")
+            .and_then(|intrinsic| intrinsic.get_declaration(&self.module, &[number_type.into()]))
+            .ok_or_else(|| {
+                IRGenError::InvalidOperation("llvm.trunc is not available".to_string())
+            })?;
+        self.builder
+            .build_call(trunc, &[value.into()], "div_trunc")
+            .map_err(division_error)?
+            .try_as_basic_value()
+            .left()
+            .map(|v| v.into_float_value())
+            .ok_or_else(|| IRGenError::InvalidOperation("llvm.trunc returned void".to_string()))
+    }
+
+    /// Raise an error of `kind` with `message`, at `span` in the current function,
+    /// through the executor's trap and return from the function, leaving the builder
+    /// in a terminated block
+    pub(crate) fn gen_raise(
+        &self,
+        kind: ErrorKind,
+        message: &str,
+        span: Option<&Span>,
+    ) -> IRGenResult<()> {
+        let function = self.current_function.ok_or_else(|| {
+            IRGenError::InvalidOperation("Raise outside of a function".to_string())
+        })?;
+        let message = self
+            .builder
+            .build_global_string_ptr(message, "trap_message")
+            .map_err(division_error)?;
+        let kind = self
+            .type_mapping
+            .get_int_type()
+            .const_int(kind as u64, false);
+        let [start, end] = self.span_offsets(span);
+        self.builder
+            .build_call(
+                self.runtime_function(trap::RAISE),
+                &[
+                    self.trap_ptr().into(),
+                    kind.into(),
+                    message.as_pointer_value().into(),
+                    self.frame_name(function).into(),
+                    start.into(),
+                    end.into(),
+                ],
+                "",
+            )
+            .map_err(division_error)?;
+        self.builder
+            .build_return(Some(&self.gen_number_const(0.0)))
+            .map_err(division_error)?;
+        Ok(())
+    }
+
+    /// After the call at `span` into a script function, return at once if the call
+    /// raised an error, adding the current function to the error's call stack, so the
+    /// error stops the whole call into the script. Only generated under
+    /// [`DivByZeroPolicy::Error`], under strict math or with a call depth limit;
+    /// otherwise a raising `div` only ends its own function, and its callers carry on
+    /// with 0.
+    pub(crate) fn gen_trap_check(&self, span: Option<&Span>) -> IRGenResult<()> {
+        if self.div_by_zero != DivByZeroPolicy::Error
+            && !self.strict_math
+            && self.max_call_depth.is_none()
+        {
+            return Ok(());
+        }
+        let function = self.current_function.ok_or_else(|| {
+            IRGenError::InvalidOperation("Call outside of a function".to_string())
+        })?;
+        let flag_type = self.context.i8_type();
+        let raised = self
+            .builder
+            .build_load(flag_type, self.trap_ptr(), "trap_raised")
+            .map_err(division_error)?
+            .into_int_value();
+        let raised = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::NE,
+                raised,
+                flag_type.const_zero(),
+                "trap_is_raised",
+            )
+            .map_err(division_error)?;
+        let raised_block = self.context.append_basic_block(function, "trap_return");
+        let continue_block = self.context.append_basic_block(function, "trap_continue");
+        self.builder
+            .build_conditional_branch(raised, raised_block, continue_block)
+            .map_err(division_error)?;
+        self.builder.position_at_end(raised_block);
+        let [start, end] = self.span_offsets(span);
+        self.builder
+            .build_call(
+                self.runtime_function(trap::UNWIND),
+                &[
+                    self.trap_ptr().into(),
+                    self.frame_name(function).into(),
+                    start.into(),
+                    end.into(),
+                ],
+                "",
+            )
+            .map_err(division_error)?;
+        self.builder
+            .build_return(Some(&self.gen_number_const(0.0)))
+            .map_err(division_error)?;
+        self.builder.position_at_end(continue_block);
+        Ok(())
+    }
+
+    /// `span` as the two offsets the trap takes, both -1 for none
+    fn span_offsets(&self, span: Option<&Span>) -> [IntValue<'ctx>; 2] {
+        let offset_type = self.type_mapping.get_int64_type();
+        let (start, end) = span.map_or((-1, -1), |span| (span.start as i64, span.end as i64));
+        [start, end].map(|offset| offset_type.const_int(offset as u64, true))
+    }
+
+    /// The name errors report `function` by, one constant per function
+    fn frame_name(&self, function: FunctionValue<'ctx>) -> PointerValue<'ctx> {
+        let name = gml_name(function);
+        let global_name = format!("__col_frame_name.{}", name);
+        let global = self.module.get_global(&global_name).unwrap_or_else(|| {
+            self.builder
+                .build_global_string_ptr(&name, &global_name)
+                .expect("Failed to build string constant")
+        });
+        global.as_pointer_value()
+    }
+
+    /// The executor's trap, declared in the module on first use
+    fn trap_ptr(&self) -> PointerValue<'ctx> {
+        let global = self
+            .module
+            .get_global(trap::TRAP_GLOBAL)
+            .unwrap_or_else(|| {
+                let global =
+                    self.module
+                        .add_global(self.context.i8_type(), None, trap::TRAP_GLOBAL);
+                global.set_linkage(Linkage::External);
+                global
+            });
+        global.as_pointer_value()
+    }
+}
@@ -0,0 +1,75 @@
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::parser::enum_def::EnumDef;
+use crate::parser::expr::Expr;
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Compute the values of an enum's members and register them as constants
+    pub fn declare_enum(&mut self, enum_def: &EnumDef) -> IRGenResult<()> {
+        let mut next = 0.0;
+        for member in &enum_def.members {
+            let key = format!("{}.{}", enum_def.name, member.name);
+            if self.enum_members.contains_key(&key) {
+                return Err(IRGenError::InvalidOperation(format!(
+                    "Enum member '{}' is declared twice",
+                    key
+                )));
+            }
+            let value = match &member.value {
+                Some(expr) => eval_constant(expr, &|object, member| {
+                    self.enum_member(object, member).ok()
+                })
+                .ok_or_else(|| {
+                    IRGenError::InvalidOperation(format!(
+                        "Value of enum member '{}' is not a constant",
+                        key
+                    ))
+                })?,
+                None => next,
+            };
+            self.enum_members.insert(key, value);
+            next = value + 1.0;
+        }
+        Ok(())
+    }
+
+    /// Look up the value of the enum member `object.member`
+    pub fn enum_member(&self, object: &str, member: &str) -> IRGenResult<f64> {
+        let key = format!("{}.{}", object, member);
+        self.enum_members
+            .get(&key)
+            .copied()
+            .ok_or(IRGenError::UndefinedVariable(key))
+    }
+}
+
+/// Evaluate an expression built only from numbers, arithmetic and bitwise
+/// operators, and enum members `member` finds the value of
+pub(crate) fn eval_constant(
+    expr: &Expr,
+    member: &dyn Fn(&str, &str) -> Option<f64>,
+) -> Option<f64> {
+    let eval = |e: &Expr| eval_constant(e, member);
+    // Bitwise operators work on 32-bit integers, as in generated code
+    let int = |e: &Expr| eval(e).map(|v| v as i32);
+    let value = match expr {
+        Expr::Number(n, _) => *n,
+        Expr::Integer(n, _) => *n as f64,
+        Expr::Member(object, name, _) => member(object, name)?,
+        Expr::Paren(e) | Expr::Positive(e) => eval(e)?,
+        Expr::Negative(e) => -eval(e)?,
+        Expr::Addition(l, r) => eval(l)? + eval(r)?,
+        Expr::Subtraction(l, r) => eval(l)? - eval(r)?,
+        Expr::Multiplication(l, r) => eval(l)? * eval(r)?,
+        Expr::Division(l, r) => eval(l)? / eval(r)?,
+        Expr::IntDivision(l, r) => (eval(l)? / eval(r)?).trunc(),
+        Expr::Percent(l, r) => eval(l)? % eval(r)?,
+        Expr::BitNot(e) => !int(e)? as f64,
+        Expr::BitAnd(l, r) => (int(l)? & int(r)?) as f64,
+        Expr::BitOr(l, r) => (int(l)? | int(r)?) as f64,
+        Expr::BitXor(l, r) => (int(l)? ^ int(r)?) as f64,
+        Expr::ShiftLeft(l, r) => int(l)?.wrapping_shl(int(r)? as u32) as f64,
+        Expr::ShiftRight(l, r) => int(l)?.wrapping_shr(int(r)? as u32) as f64,
+        _ => return None,
+    };
+    Some(value)
+}
@@ -0,0 +1,438 @@
+//! Anonymous functions and calls through variables.
+//!
+//! `function(a) { return a * 2; }` is lifted to a module function of its own,
+//! named `{enclosing}.function.{n}` like a nested function, and its value is a
+//! reference to it: a number, 1 for the first anonymous function generated, 2 for
+//! the next and so on. A function's locals are not visible in another function, so
+//! a body using a local of the function around it is rejected; it has to be passed
+//! in as an argument.
+//!
+//! Calling a variable, as in `cb(5)`, calls the function it refers to. When the
+//! variable is a local declared with an anonymous function and never assigned or
+//! declared again, the call is made directly. Otherwise, e.g. for a parameter, the
+//! reference is checked when the call runs, raising [`ErrorKind::NotCallable`] if it
+//! is no function, and the call goes through a dispatcher per argument count,
+//! generated once the whole script is, which passes missing arguments as undefined
+//! and drops extra ones.
+
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator, gml_name};
+use crate::codegen::runtime::trap::ErrorKind;
+use crate::parser::Span;
+use crate::parser::expr::Expr;
+use crate::parser::func::Func;
+use crate::parser::func_def::FuncDef;
+use crate::parser::stmt::Stmt;
+use crate::parser::visitor::{Pass, Visitor};
+use inkwell::FloatPredicate;
+use inkwell::builder::BuilderError;
+use inkwell::module::Linkage;
+use inkwell::types::BasicMetadataTypeEnum;
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValueEnum, FloatValue, FunctionValue, GlobalValue, IntValue,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Name of the module global holding how many anonymous functions there are
+const FUNCTION_COUNT_GLOBAL: &str = "__col_function_count";
+
+/// Prefix of the dispatchers calling a function reference, followed by how many
+/// arguments they pass
+const DISPATCHER_PREFIX: &str = "__col_call_reference.";
+
+fn reference_error(e: BuilderError) -> IRGenError {
+    IRGenError::InvalidOperation(format!("Call through a variable failed: {}", e))
+}
+
+/// How often each variable is declared, and those assigned to other than by their
+/// declaration
+#[derive(Default)]
+struct Assignments {
+    declared: HashMap<String, usize>,
+    assigned: HashSet<String>,
+}
+
+impl Pass for Assignments {
+    // A nested function, named or not, has variables of its own
+    fn visit_func(&mut self, _func: &Func) {}
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::Var(vars) = stmt {
+            for (name, _, _) in vars {
+                *self.declared.entry(name.clone()).or_default() += 1;
+            }
+        }
+        self.walk_stmt(stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        let target = match expr {
+            Expr::Equal(target, _)
+            | Expr::PlusEqual(target, _)
+            | Expr::MinusEqual(target, _)
+            | Expr::StarEqual(target, _)
+            | Expr::SlashEqual(target, _)
+            | Expr::PercentEqual(target, _)
+            | Expr::PreIncrement(target)
+            | Expr::PostIncrement(target)
+            | Expr::PreDecrement(target)
+            | Expr::PostDecrement(target) => Some(target),
+            _ => None,
+        };
+        if let Some(Expr::Identifier(name, _)) = target.map(|target| &**target) {
+            self.assigned.insert(name.clone());
+        }
+        self.walk_expr(expr);
+    }
+}
+
+/// The variables declared in `body` more than once or assigned to after their
+/// declaration, which may hold something else than what they were declared with,
+/// leaving nested functions aside
+pub(crate) fn reassigned_variables<'a>(
+    body: impl IntoIterator<Item = &'a Stmt>,
+) -> HashSet<String> {
+    let mut assignments = Assignments::default();
+    for stmt in body {
+        stmt.accept(&mut assignments);
+    }
+    let Assignments {
+        declared,
+        mut assigned,
+    } = assignments;
+    assigned.extend(
+        declared
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(name, _)| name),
+    );
+    assigned
+}
+
+/// The names an anonymous function's body uses, each with where and whether it is
+/// called, and those it declares itself, leaving functions nested in it aside
+#[derive(Default)]
+struct BodyNames {
+    used: Vec<(String, Span, bool)>,
+    declared: HashSet<String>,
+}
+
+impl Pass for BodyNames {
+    fn visit_func(&mut self, _func: &Func) {}
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::Var(vars) = stmt {
+            self.declared
+                .extend(vars.iter().map(|(name, _, _)| name.clone()));
+        }
+        self.walk_stmt(stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Identifier(name, span) => self.used.push((name.clone(), span.clone(), false)),
+            Expr::Call(name, _, span) => self.used.push((name.clone(), span.clone(), true)),
+            _ => {}
+        }
+        self.walk_expr(expr);
+    }
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Lift the anonymous function `func` at `span` to a module function and give a
+    /// reference to it
+    pub(crate) fn gen_function_expr(
+        &mut self,
+        func: &Func,
+        span: &Span,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let prefix = self
+            .current_function
+            .map(gml_name)
+            .unwrap_or_else(|| "main".to_string());
+        self.check_captures(func, &prefix)?;
+
+        let mut suffix = 1;
+        let mut llvm_name = format!("{}.function.{}", prefix, suffix);
+        while self
+            .module
+            .get_function(&self.symbol_name(&llvm_name))
+            .is_some()
+        {
+            suffix += 1;
+            llvm_name = format!("{}.function.{}", prefix, suffix);
+        }
+        let func_def = FuncDef {
+            name: llvm_name.clone(),
+            func: func.clone(),
+            span: span.clone(),
+            extent: span.clone(),
+        };
+
+        let saved_block = self.builder.get_insert_block();
+        let function = self.gen_function(&func_def, &llvm_name)?;
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+
+        self.anonymous_functions.push(function);
+        let reference = self.anonymous_functions.len() as f64;
+        Ok(self.gen_number_const(reference).into())
+    }
+
+    /// Reject an anonymous function in `enclosing` whose body uses one of the
+    /// locals of `enclosing`
+    fn check_captures(&self, func: &Func, enclosing: &str) -> IRGenResult<()> {
+        let mut names = BodyNames::default();
+        for stmt in &func.body {
+            stmt.accept(&mut names);
+        }
+        // A call names a function before it names a variable
+        let captured = names.used.into_iter().find(|(name, _, called)| {
+            name != "argument_count"
+                && !func.args.contains(name)
+                && !names.declared.contains(name)
+                && self.variables.contains_key(name)
+                && !(*called && (self.is_builtin(name) || self.functions.get(name).is_some()))
+        });
+        match captured {
+            Some((name, span, _)) => Err(IRGenError::Unsupported {
+                message: format!(
+                    "Anonymous function uses '{}', a local variable of '{}'; functions \
+                     cannot capture locals, so pass it in as an argument",
+                    name, enclosing
+                ),
+                span,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Remember that the local `name` holds the anonymous function it was just
+    /// declared with, if `init` is one and nothing else is ever stored in `name`
+    pub(crate) fn note_callable(&mut self, name: &str, init: Option<&Expr>) {
+        if self.reassigned_variables.contains(name) {
+            return;
+        }
+        if let (Some(Expr::Function(..)), Some(&function)) = (init, self.anonymous_functions.last())
+        {
+            self.callables.insert(name.to_string(), function);
+        }
+    }
+
+    /// Call the function the variable `name` refers to with `args`, at `span`,
+    /// raising an error if it refers to none
+    pub(crate) fn gen_reference_call(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+        span: &Span,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        if self.get_variable(name).is_err() {
+            return Err(IRGenError::UndefinedFunction(name.to_string()));
+        }
+        let BasicValueEnum::FloatValue(reference) = self.load_variable(name)? else {
+            return Err(IRGenError::TypeMismatch(format!(
+                "'{}' is called, but cannot hold a function",
+                name
+            )));
+        };
+
+        let mut arg_values: Vec<BasicMetadataValueEnum<'ctx>> = vec![];
+        for arg in args {
+            let value = self.visit_expr_impl(arg)?;
+            arg_values.push(self.convert_to_return_type(value)?.into());
+        }
+        let id = self.gen_reference_id(name, reference, span)?;
+        arg_values.insert(0, id.into());
+
+        let count_global = self.argument_count_global().as_pointer_value();
+        self.builder
+            .build_store(count_global, self.gen_number_const(args.len() as f64))
+            .map_err(reference_error)?;
+        let call_value = self
+            .builder
+            .build_call(self.reference_dispatcher(args.len()), &arg_values, "call")
+            .map_err(reference_error)?;
+        self.gen_trap_check(Some(span))?;
+
+        call_value
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(|| IRGenError::InvalidOperation("Function call returned void".to_string()))
+    }
+
+    /// The index of the anonymous function `reference`, read from the variable
+    /// `name`, refers to, raising an error at `span` unless it is one
+    fn gen_reference_id(
+        &self,
+        name: &str,
+        reference: FloatValue<'ctx>,
+        span: &Span,
+    ) -> IRGenResult<IntValue<'ctx>> {
+        let function = self.current_function.ok_or_else(|| {
+            IRGenError::InvalidOperation("Call outside of a function".to_string())
+        })?;
+        let number_type = self.type_mapping.get_number_type();
+        let id_type = self.type_mapping.get_int64_type();
+        let count = self
+            .builder
+            .build_load(
+                number_type,
+                self.function_count_global().as_pointer_value(),
+                "function_count",
+            )
+            .map_err(reference_error)?
+            .into_float_value();
+
+        // NaN and references out of range fail both comparisons. Those are replaced
+        // before converting, which would give nothing defined for them
+        let at_least_one = self
+            .builder
+            .build_float_compare(
+                FloatPredicate::OGE,
+                reference,
+                self.gen_number_const(1.0),
+                "ref_at_least_one",
+            )
+            .map_err(reference_error)?;
+        let at_most_count = self
+            .builder
+            .build_float_compare(FloatPredicate::OLE, reference, count, "ref_at_most_count")
+            .map_err(reference_error)?;
+        let in_range = self
+            .builder
+            .build_and(at_least_one, at_most_count, "ref_in_range")
+            .map_err(reference_error)?;
+        let checked = self
+            .builder
+            .build_select(in_range, reference, number_type.const_zero(), "ref_checked")
+            .map_err(reference_error)?
+            .into_float_value();
+        let id = self
+            .builder
+            .build_float_to_signed_int(checked, id_type, "ref_id")
+            .map_err(reference_error)?;
+        let back = self
+            .builder
+            .build_signed_int_to_float(id, number_type, "ref_id_number")
+            .map_err(reference_error)?;
+        let whole = self
+            .builder
+            .build_float_compare(FloatPredicate::OEQ, back, checked, "ref_is_whole")
+            .map_err(reference_error)?;
+        let valid = self
+            .builder
+            .build_and(in_range, whole, "ref_is_function")
+            .map_err(reference_error)?;
+
+        let invalid_block = self.context.append_basic_block(function, "not_callable");
+        let call_block = self.context.append_basic_block(function, "call_reference");
+        self.builder
+            .build_conditional_branch(valid, call_block, invalid_block)
+            .map_err(reference_error)?;
+        self.builder.position_at_end(invalid_block);
+        self.gen_raise(
+            ErrorKind::NotCallable,
+            &format!("'{}' does not hold a function", name),
+            Some(span),
+        )?;
+        self.builder.position_at_end(call_block);
+        Ok(id)
+    }
+
+    /// The global counting the anonymous functions, declared in the module on first
+    /// use and set once they are all generated
+    fn function_count_global(&self) -> GlobalValue<'ctx> {
+        self.module
+            .get_global(FUNCTION_COUNT_GLOBAL)
+            .unwrap_or_else(|| {
+                let global = self.module.add_global(
+                    self.type_mapping.get_number_type(),
+                    None,
+                    FUNCTION_COUNT_GLOBAL,
+                );
+                global.set_linkage(Linkage::Private);
+                global.set_constant(true);
+                global.set_initializer(&self.gen_number_const(0.0));
+                global
+            })
+    }
+
+    /// The dispatcher calling a reference with `arity` arguments, declared in the
+    /// module on first use; its body is generated by [`Self::finish_dispatchers`]
+    fn reference_dispatcher(&self, arity: usize) -> FunctionValue<'ctx> {
+        let name = format!("{}{}", DISPATCHER_PREFIX, arity);
+        self.module.get_function(&name).unwrap_or_else(|| {
+            let number_type = self.type_mapping.get_number_type();
+            let mut param_types: Vec<BasicMetadataTypeEnum<'ctx>> =
+                vec![self.type_mapping.get_int64_type().into()];
+            param_types.extend((0..arity).map(|_| number_type.into()));
+            let fn_type = number_type.fn_type(&param_types, false);
+            self.module
+                .add_function(&name, fn_type, Some(Linkage::Private))
+        })
+    }
+
+    /// Count the anonymous functions and generate the dispatchers calls through
+    /// variables use, switching over every anonymous function by its index. Done
+    /// once the whole script is generated, as a reference may be called before the
+    /// function it refers to is defined.
+    pub(crate) fn finish_dispatchers(&self) -> IRGenResult<()> {
+        if let Some(global) = self.module.get_global(FUNCTION_COUNT_GLOBAL) {
+            global.set_initializer(&self.gen_number_const(self.anonymous_functions.len() as f64));
+        }
+        let dispatchers = self.module.get_functions().filter(|function| {
+            function.count_basic_blocks() == 0
+                && function
+                    .get_name()
+                    .to_bytes()
+                    .starts_with(DISPATCHER_PREFIX.as_bytes())
+        });
+        // A fresh builder has no debug location left over from a script function
+        let builder = self.context.create_builder();
+        let id_type = self.type_mapping.get_int64_type();
+        for dispatcher in dispatchers {
+            let entry = self.context.append_basic_block(dispatcher, "entry");
+            // References are checked before they are called
+            let missing = self.context.append_basic_block(dispatcher, "no_function");
+            builder.position_at_end(missing);
+            builder.build_unreachable().map_err(reference_error)?;
+
+            let mut cases = Vec::with_capacity(self.anonymous_functions.len());
+            for (index, &function) in self.anonymous_functions.iter().enumerate() {
+                let block = self.context.append_basic_block(dispatcher, "call");
+                builder.position_at_end(block);
+                let args: Vec<BasicMetadataValueEnum<'ctx>> = (0..function.count_params())
+                    .map(|n| match dispatcher.get_nth_param(n + 1) {
+                        Some(arg) => arg.into(),
+                        None => self.gen_undefined_const().into(),
+                    })
+                    .collect();
+                let result = builder
+                    .build_call(function, &args, "call")
+                    .map_err(reference_error)?
+                    .try_as_basic_value()
+                    .left()
+                    .ok_or_else(|| {
+                        IRGenError::InvalidOperation("Function call returned void".to_string())
+                    })?;
+                builder
+                    .build_return(Some(&result))
+                    .map_err(reference_error)?;
+                cases.push((id_type.const_int(index as u64 + 1, false), block));
+            }
+
+            builder.position_at_end(entry);
+            let id = dispatcher
+                .get_first_param()
+                .ok_or_else(|| {
+                    IRGenError::InvalidOperation("Dispatcher without parameters".to_string())
+                })?
+                .into_int_value();
+            builder
+                .build_switch(id, missing, &cases)
+                .map_err(reference_error)?;
+        }
+        Ok(())
+    }
+}
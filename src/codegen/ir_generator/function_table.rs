@@ -0,0 +1,33 @@
+use inkwell::values::FunctionValue;
+use std::collections::HashMap;
+
+/// Functions visible to GML code, keyed by their GML name.
+///
+/// Entries are kept in insertion order, which is source order, so nothing derived
+/// from the table depends on hash order. Re-inserting a name (a nested function
+/// shadowing an outer one) replaces the entry in place.
+#[derive(Debug, Clone, Default)]
+pub struct FunctionTable<'ctx> {
+    entries: Vec<(String, FunctionValue<'ctx>)>,
+    index: HashMap<String, usize>,
+}
+
+impl<'ctx> FunctionTable<'ctx> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: String, function: FunctionValue<'ctx>) {
+        match self.index.get(&name) {
+            Some(&i) => self.entries[i].1 = function,
+            None => {
+                self.index.insert(name.clone(), self.entries.len());
+                self.entries.push((name, function));
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<FunctionValue<'ctx>> {
+        self.index.get(name).map(|&i| self.entries[i].1)
+    }
+}
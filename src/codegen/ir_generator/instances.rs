@@ -0,0 +1,75 @@
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::codegen::runtime::instances::{self, Instance};
+use crate::parser::expr::Expr;
+use inkwell::module::Linkage;
+use inkwell::values::{BasicValueEnum, PointerValue};
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// The instance a field is accessed on, which must be `self` or `other`
+    pub(crate) fn field_instance(object: &Expr, field: &str) -> IRGenResult<Instance> {
+        match object {
+            Expr::SelfRef(_) => Ok(Instance::Self_),
+            Expr::OtherRef(_) => Ok(Instance::Other),
+            _ => Err(IRGenError::InvalidOperation(format!(
+                "Field '{}' can only be accessed on self or other",
+                field
+            ))),
+        }
+    }
+
+    /// Read a field of the instance the host bound to `self` or `other`
+    pub(crate) fn gen_field_get(
+        &self,
+        instance: Instance,
+        field: &str,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let function = match instance {
+            Instance::Self_ => instances::GET_SELF_FIELD,
+            Instance::Other => instances::GET_OTHER_FIELD,
+        };
+        let name = self.gen_string_const(field);
+        self.call_runtime(function, &[self.instances_ptr().into(), name.into()])
+    }
+
+    /// Assign a field of the instance the host bound to `self` or `other`.
+    /// The result is the value as stored, a number.
+    pub(crate) fn gen_field_set(
+        &self,
+        instance: Instance,
+        field: &str,
+        value: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let BasicValueEnum::FloatValue(number) = self.convert_to_return_type(value)? else {
+            return Err(IRGenError::TypeMismatch(format!(
+                "Cannot assign a string to {}.{}; instance fields only hold numbers",
+                instance, field
+            )));
+        };
+        let function = match instance {
+            Instance::Self_ => instances::SET_SELF_FIELD,
+            Instance::Other => instances::SET_OTHER_FIELD,
+        };
+        let name = self.gen_string_const(field);
+        self.call_runtime(
+            function,
+            &[self.instances_ptr().into(), name.into(), number.into()],
+        )
+    }
+
+    /// The executor's instances, declared in the module on first use
+    fn instances_ptr(&self) -> PointerValue<'ctx> {
+        let global = self
+            .module
+            .get_global(instances::INSTANCES_GLOBAL)
+            .unwrap_or_else(|| {
+                let global = self.module.add_global(
+                    self.context.i8_type(),
+                    None,
+                    instances::INSTANCES_GLOBAL,
+                );
+                global.set_linkage(Linkage::External);
+                global
+            });
+        global.as_pointer_value()
+    }
+}
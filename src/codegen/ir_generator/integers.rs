@@ -0,0 +1,157 @@
+//! Exact integers, under
+//! [`LanguageOptions::exact_integers`](crate::parser::language_options::LanguageOptions::exact_integers).
+//!
+//! An integer literal that fits an `i64` is generated as an int64 value rather
+//! than a double, so `9007199254740993` keeps its last digit. Integers stay
+//! integers through `+`, `-`, `*`, negation and the bitwise and shift operators,
+//! which work on all 64 bits, so `(1 << 62) | 3` is exact. `/` and `%` always give
+//! numbers, so `7 / 2` is 3.5, and mixing an integer with a number gives a number.
+//!
+//! A local variable declared with an integer holds integers, unless it is ever given
+//! something that may not be one, like `x /= 2` or `x = y * 0.5`: it is then a number
+//! from its declaration on, so no fraction is cut off by storing it. Parameters,
+//! return values, script globals and instance fields are numbers as always, and
+//! `string()` writes integers out in full.
+
+use crate::codegen::ir_generator::{IRGenResult, IRGenerator};
+use crate::parser::expr::Expr;
+use crate::parser::func::Func;
+use crate::parser::stmt::Stmt;
+use crate::parser::visitor::{Pass, Visitor};
+use inkwell::values::BasicValueEnum;
+use std::collections::HashSet;
+
+/// The variables a function declares, and those of them given fractions
+#[derive(Default)]
+struct Fractions {
+    declared: HashSet<String>,
+    fractional: HashSet<String>,
+}
+
+impl Fractions {
+    /// Whether `expr` certainly gives an integer, as far as is known so far
+    fn integral(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Integer(..) => true,
+            Expr::Identifier(name, _) => {
+                self.declared.contains(name) && !self.fractional.contains(name)
+            }
+            Expr::Call(name, _, _) => name == "int64",
+            Expr::Paren(operand)
+            | Expr::Positive(operand)
+            | Expr::Negative(operand)
+            | Expr::BitNot(operand)
+            | Expr::PreIncrement(operand)
+            | Expr::PostIncrement(operand)
+            | Expr::PreDecrement(operand)
+            | Expr::PostDecrement(operand) => self.integral(operand),
+            Expr::Addition(lhs, rhs)
+            | Expr::Subtraction(lhs, rhs)
+            | Expr::Multiplication(lhs, rhs) => self.integral(lhs) && self.integral(rhs),
+            // Bitwise operators keep an integer operand's width
+            Expr::BitAnd(lhs, rhs)
+            | Expr::BitOr(lhs, rhs)
+            | Expr::BitXor(lhs, rhs)
+            | Expr::ShiftLeft(lhs, rhs)
+            | Expr::ShiftRight(lhs, rhs) => self.integral(lhs) || self.integral(rhs),
+            Expr::Ternary(_, then_expr, else_expr) => {
+                self.integral(then_expr) && self.integral(else_expr)
+            }
+            _ => false,
+        }
+    }
+
+    fn walk<'a>(&mut self, body: impl IntoIterator<Item = &'a Stmt>) {
+        for stmt in body {
+            stmt.accept(self);
+        }
+    }
+}
+
+impl Pass for Fractions {
+    // A nested function, named or not, has variables of its own
+    fn visit_func(&mut self, _func: &Func) {}
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::Var(vars) = stmt {
+            for (name, init, _) in vars {
+                self.declared.insert(name.clone());
+                if !init.as_ref().is_some_and(|init| self.integral(init)) {
+                    self.fractional.insert(name.clone());
+                }
+            }
+        }
+        self.walk_stmt(stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        let target = match expr {
+            Expr::Equal(target, value)
+            | Expr::PlusEqual(target, value)
+            | Expr::MinusEqual(target, value)
+            | Expr::StarEqual(target, value)
+                if !self.integral(value) =>
+            {
+                Some(target)
+            }
+            Expr::SlashEqual(target, _) | Expr::PercentEqual(target, _) => Some(target),
+            _ => None,
+        };
+        if let Some(Expr::Identifier(name, _)) = target.map(|target| &**target) {
+            self.fractional.insert(name.clone());
+        }
+        self.walk_expr(expr);
+    }
+}
+
+/// The variables declared in `body` that are ever given something other than an
+/// integer, leaving nested functions aside
+pub(crate) fn fractional_variables<'a, I>(body: I) -> HashSet<String>
+where
+    I: IntoIterator<Item = &'a Stmt> + Clone,
+{
+    let mut fractions = Fractions::default();
+    // A variable may be used before its declaration, so all of them are found
+    // before any is judged
+    fractions.walk(body.clone());
+    fractions.fractional.clear();
+    // Each variable found fractional may make others so
+    loop {
+        let known = fractions.fractional.len();
+        fractions.walk(body.clone());
+        if fractions.fractional.len() == known {
+            return fractions.fractional;
+        }
+    }
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// The value a `var` declaration gives `name`: an integer is made a number if
+    /// the variable is ever given a fraction, unless it is explicitly an `int64()`.
+    /// Variables are only ever found fractional under `exact_integers`.
+    pub(crate) fn declared_value(
+        &self,
+        name: &str,
+        init: Option<&Expr>,
+        value: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let int64_type = self.type_mapping.get_int64_type();
+        let integer = matches!(value, BasicValueEnum::IntValue(v) if v.get_type() == int64_type);
+        let explicit = matches!(init, Some(Expr::Call(function, _, _)) if function == "int64");
+        if !integer || explicit || !self.fractional_variables.contains(name) {
+            return Ok(value);
+        }
+        self.convert_to_return_type(value)
+    }
+
+    /// 1 of the same kind as `value`, for incrementing and decrementing it
+    pub(crate) fn gen_one_like(&self, value: BasicValueEnum<'ctx>) -> BasicValueEnum<'ctx> {
+        let int64_type = self.type_mapping.get_int64_type();
+        match value {
+            BasicValueEnum::IntValue(v) if v.get_type() == int64_type => {
+                int64_type.const_int(1, true).into()
+            }
+            _ => self.gen_number_const(1.0).into(),
+        }
+    }
+}
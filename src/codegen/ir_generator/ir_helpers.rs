@@ -1,7 +1,14 @@
-use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::codegen::ir_generator::{INIT_FUNCTION, IRGenError, IRGenResult, IRGenerator};
+use crate::codegen::runtime;
 use inkwell::types::BasicTypeEnum;
 use inkwell::values::*;
 
+const ARGUMENT_COUNT_GLOBAL: &str = "__argument_count";
+/// Prefix keeping script globals apart from functions in the module's symbol namespace
+pub const SCRIPT_GLOBAL_PREFIX: &str = "global.";
+/// Why a string is not a condition
+pub(crate) const STRING_CONDITION: &str = "A string cannot be used as a condition";
+
 impl<'ctx> IRGenerator<'ctx> {
     /// Generate IR for a constant number value
     pub fn gen_number_const(&self, value: f64) -> FloatValue<'ctx> {
@@ -28,66 +35,229 @@ impl<'ctx> IRGenerator<'ctx> {
         self.type_mapping.get_string_type().const_null()
     }
 
+    /// Generate IR for the undefined value passed for missing or elided arguments
+    pub fn gen_undefined_const(&self) -> FloatValue<'ctx> {
+        self.gen_number_const(0.0)
+    }
+
     /// Declare a variable in the current scope
     pub fn declare_variable(
         &mut self,
         name: &str,
         value_type: BasicTypeEnum<'ctx>,
     ) -> IRGenResult<PointerValue<'ctx>> {
-        let alloca = self.builder.build_alloca(value_type, name).map_err(|e| {
-            IRGenError::InvalidOperation(format!("Failed to allocate variable '{}': {}", name, e))
-        })?;
+        let alloca = self
+            .entry_block_builder()
+            .build_alloca(value_type, name)
+            .map_err(|e| {
+                IRGenError::InvalidOperation(format!(
+                    "Failed to allocate variable '{}': {}",
+                    name, e
+                ))
+            })?;
 
         self.variables.insert(name.to_string(), alloca);
         self.variable_types.insert(name.to_string(), value_type);
         Ok(alloca)
     }
 
-    /// Get a variable from the current scope
+    /// A builder placed after the allocas at the start of the current function.
+    /// Variables live there so their slots dominate every use, wherever in the
+    /// function they are declared.
+    pub(crate) fn entry_block_builder(&self) -> inkwell::builder::Builder<'ctx> {
+        let builder = self.context.create_builder();
+        match self
+            .current_function
+            .and_then(|f| f.get_first_basic_block())
+        {
+            Some(entry) => {
+                let mut instruction = entry.get_first_instruction();
+                while let Some(inst) = instruction {
+                    if inst.get_opcode() != inkwell::values::InstructionOpcode::Alloca {
+                        break;
+                    }
+                    instruction = inst.get_next_instruction();
+                }
+                match instruction {
+                    Some(inst) => builder.position_before(&inst),
+                    None => builder.position_at_end(entry),
+                }
+            }
+            None => {
+                if let Some(block) = self.builder.get_insert_block() {
+                    builder.position_at_end(block);
+                }
+            }
+        }
+        builder
+    }
+
+    /// End every block that nothing branches to and that has no terminator with
+    /// `unreachable`: the exit of a loop only left by `return`, or blocks started
+    /// after every path through an `if` or loop body already left it. Reachable
+    /// blocks without a terminator are left for verification to report.
+    pub(crate) fn seal_unreachable_blocks(&self, function: FunctionValue<'ctx>) -> IRGenResult<()> {
+        let builder = self.context.create_builder();
+        for block in function.get_basic_blocks().into_iter().skip(1) {
+            if block.get_terminator().is_none() && block.get_first_use().is_none() {
+                builder.position_at_end(block);
+                builder.build_unreachable().map_err(|e| {
+                    IRGenError::InvalidOperation(format!("Failed to build unreachable: {}", e))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get a variable from the current scope, falling back to script globals
     pub fn get_variable(&self, name: &str) -> IRGenResult<PointerValue<'ctx>> {
         self.variables
             .get(name)
             .copied()
+            .or_else(|| self.script_global(name).map(|g| g.as_pointer_value()))
             .ok_or_else(|| IRGenError::UndefinedVariable(name.to_string()))
     }
 
+    /// Whether code is currently generated for the script's top level
+    pub fn in_top_level(&self) -> bool {
+        let init = self.symbol_name(INIT_FUNCTION);
+        self.current_function
+            .is_some_and(|f| f.get_name().to_bytes() == init.as_bytes())
+    }
+
+    /// Declare a top-level variable as a module global.
+    /// The global is external: its storage belongs to the host, which maps it into the JIT.
+    pub fn declare_script_global(&mut self, name: &str) -> GlobalValue<'ctx> {
+        if let Some(global) = self.script_global(name) {
+            return global;
+        }
+        let global = self.module.add_global(
+            self.type_mapping.get_number_type(),
+            None,
+            &format!("{}{}", SCRIPT_GLOBAL_PREFIX, name),
+        );
+        global.set_linkage(inkwell::module::Linkage::External);
+        global
+    }
+
+    /// Look up a script global, if globals are enabled and `name` is one
+    pub fn script_global(&self, name: &str) -> Option<GlobalValue<'ctx>> {
+        if !self.persistent_globals {
+            return None;
+        }
+        self.module
+            .get_global(&format!("{}{}", SCRIPT_GLOBAL_PREFIX, name))
+    }
+
     /// Load a variable's value
     pub fn load_variable(&self, name: &str) -> IRGenResult<BasicValueEnum<'ctx>> {
         let var_ptr = self.get_variable(name)?;
-        // Get the type from our type tracking table
-        let var_type = self.variable_types.get(name).ok_or_else(|| {
-            IRGenError::InvalidOperation(format!(
-                "Type information missing for variable '{}'",
-                name
-            ))
-        })?;
+        // Get the type from our type tracking table; script globals are always numbers
+        let var_type = match self.variable_types.get(name) {
+            Some(var_type) => *var_type,
+            None if self.script_global(name).is_some() => {
+                self.type_mapping.get_number_type().into()
+            }
+            None => {
+                return Err(IRGenError::InvalidOperation(format!(
+                    "Type information missing for variable '{}'",
+                    name
+                )));
+            }
+        };
 
         self.builder
-            .build_load(*var_type, var_ptr, name)
+            .build_load(var_type, var_ptr, name)
             .map_err(|e| {
                 IRGenError::InvalidOperation(format!("Failed to load variable '{}': {}", name, e))
             })
     }
 
-    /// Store a value to a variable
-    pub fn store_variable(&mut self, name: &str, value: BasicValueEnum<'ctx>) -> IRGenResult<()> {
+    /// Store a value to a variable, converted to the variable's type.
+    /// Returns the value actually stored.
+    pub fn store_variable(
+        &mut self,
+        name: &str,
+        value: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
         let var_ptr = self.get_variable(name)?;
+        let value = match self.variable_types.get(name) {
+            Some(&var_type) if self.variables.contains_key(name) => {
+                self.convert_to_variable_type(name, var_type, value)?
+            }
+            _ => self.convert_to_global_value(name, value)?,
+        };
         self.builder.build_store(var_ptr, value).map_err(|e| {
             IRGenError::InvalidOperation(format!("Failed to store to variable '{}': {}", name, e))
         })?;
-        Ok(())
+        Ok(value)
     }
 
-    /// Convert a value to boolean for conditional operations
+    /// Convert a value to the type `name` was declared with. Bools, numbers and
+    /// int64 values convert into each other; any other mismatch is an error.
+    pub fn convert_to_variable_type(
+        &self,
+        name: &str,
+        var_type: BasicTypeEnum<'ctx>,
+        value: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        if self.get_value_type(value) == var_type {
+            return Ok(value);
+        }
+        if var_type == self.type_mapping.get_number_type().into() {
+            let value = self.convert_to_return_type(value)?;
+            if value.is_float_value() {
+                return Ok(value);
+            }
+        } else if value.is_pointer_value() {
+            // Strings never convert implicitly
+        } else if var_type == self.type_mapping.get_bool_type().into() {
+            return Ok(self.convert_to_bool(value)?.into());
+        } else if var_type == self.type_mapping.get_int64_type().into() {
+            return Ok(self.gen_to_int64(value)?.into());
+        }
+        Err(IRGenError::TypeMismatch(format!(
+            "Cannot assign a value of type {} to variable '{}' of type {}",
+            self.get_value_type(value),
+            name,
+            var_type
+        )))
+    }
+
+    /// Convert a value for storing in a script global, which only holds numbers
+    pub fn convert_to_global_value(
+        &self,
+        name: &str,
+        value: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let value = self.convert_to_return_type(value)?;
+        if value.is_float_value() {
+            Ok(value)
+        } else {
+            Err(IRGenError::TypeMismatch(format!(
+                "Script global '{}' can only hold numbers",
+                name
+            )))
+        }
+    }
+
+    /// Convert a value to boolean the way GML decides truth. Every condition (if,
+    /// while, do-until, for, ternary) and logical operator goes through here.
+    ///
+    /// Bools are used as they are and numbers are true when greater than 0.5, so
+    /// 0.4 and NaN are false. The `null` literal is false. Strings are not valid
+    /// conditions, and neither are string variables, even ones holding null; they
+    /// are statically typed, so this is reported at compile time.
     pub fn convert_to_bool(&self, value: BasicValueEnum<'ctx>) -> IRGenResult<IntValue<'ctx>> {
         match value {
             BasicValueEnum::IntValue(int_val) => {
                 if int_val.get_type() == self.type_mapping.get_bool_type() {
                     Ok(int_val)
                 } else {
+                    // For an integer, greater than 0.5 means at least 1
                     self.builder
                         .build_int_compare(
-                            inkwell::IntPredicate::NE,
+                            inkwell::IntPredicate::SGT,
                             int_val,
                             int_val.get_type().const_zero(),
                             "tobool",
@@ -103,24 +273,19 @@ impl<'ctx> IRGenerator<'ctx> {
             BasicValueEnum::FloatValue(float_val) => self
                 .builder
                 .build_float_compare(
-                    inkwell::FloatPredicate::ONE,
+                    inkwell::FloatPredicate::OGT,
                     float_val,
-                    float_val.get_type().const_zero(),
+                    float_val.get_type().const_float(runtime::TRUE_ABOVE),
                     "tobool",
                 )
                 .map_err(|e| {
                     IRGenError::InvalidOperation(format!("Failed to convert float to bool: {}", e))
                 }),
-            BasicValueEnum::PointerValue(ptr_val) => {
-                let null_ptr = ptr_val.get_type().const_null();
-                self.builder
-                    .build_int_compare(inkwell::IntPredicate::NE, ptr_val, null_ptr, "tobool")
-                    .map_err(|e| {
-                        IRGenError::InvalidOperation(format!(
-                            "Failed to convert pointer to bool: {}",
-                            e
-                        ))
-                    })
+            BasicValueEnum::PointerValue(ptr_val) if ptr_val.is_null() => {
+                Ok(self.gen_bool_const(false))
+            }
+            BasicValueEnum::PointerValue(_) => {
+                Err(IRGenError::TypeMismatch(STRING_CONDITION.to_string()))
             }
             _ => Err(IRGenError::TypeMismatch(
                 "Cannot convert value to boolean".to_string(),
@@ -151,7 +316,7 @@ impl<'ctx> IRGenerator<'ctx> {
         &self,
         value: BasicValueEnum<'ctx>,
     ) -> IRGenResult<BasicValueEnum<'ctx>> {
-        // For now, all functions return double, so convert booleans to double
+        // For now, all functions return double, so convert booleans and int64 values to double
         match value {
             BasicValueEnum::IntValue(int_val)
                 if int_val.get_type() == self.type_mapping.get_bool_type() =>
@@ -171,7 +336,88 @@ impl<'ctx> IRGenerator<'ctx> {
                     })?;
                 Ok(double_val.into())
             }
+            BasicValueEnum::IntValue(int_val)
+                if int_val.get_type() == self.type_mapping.get_int64_type() =>
+            {
+                let double_val = self
+                    .builder
+                    .build_signed_int_to_float(
+                        int_val,
+                        self.type_mapping.get_number_type(),
+                        "int64_to_double",
+                    )
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!(
+                            "Int64 to double conversion failed: {}",
+                            e
+                        ))
+                    })?;
+                Ok(double_val.into())
+            }
             _ => Ok(value), // Other types remain unchanged
         }
     }
+
+    /// Get the global the caller uses to pass its argument count to the callee
+    pub fn argument_count_global(&self) -> GlobalValue<'ctx> {
+        if let Some(global) = self.module.get_global(ARGUMENT_COUNT_GLOBAL) {
+            return global;
+        }
+        let global = self.module.add_global(
+            self.type_mapping.get_number_type(),
+            None,
+            ARGUMENT_COUNT_GLOBAL,
+        );
+        global.set_initializer(&self.gen_number_const(-1.0));
+        global
+    }
+
+    /// Bind `argument_count` in the current function to the call-site argument count.
+    /// Calls that did not come from GML (e.g. from the JIT host) see the declared arity.
+    pub fn declare_argument_count(&mut self, arity: usize) -> IRGenResult<()> {
+        let number_type = self.type_mapping.get_number_type();
+        let global = self.argument_count_global().as_pointer_value();
+        let passed = self
+            .builder
+            .build_load(number_type, global, "passed_count")
+            .map_err(|e| {
+                IRGenError::InvalidOperation(format!("Failed to load argument count: {}", e))
+            })?
+            .into_float_value();
+        let was_set = self
+            .builder
+            .build_float_compare(
+                inkwell::FloatPredicate::OGE,
+                passed,
+                number_type.const_zero(),
+                "count_set",
+            )
+            .map_err(|e| {
+                IRGenError::InvalidOperation(format!("Failed to compare argument count: {}", e))
+            })?;
+        let count = self
+            .builder
+            .build_select(
+                was_set,
+                passed,
+                self.gen_number_const(arity as f64),
+                "argument_count",
+            )
+            .map_err(|e| {
+                IRGenError::InvalidOperation(format!("Failed to select argument count: {}", e))
+            })?;
+
+        // Reset so a later host call does not see a stale count
+        self.builder
+            .build_store(global, self.gen_number_const(-1.0))
+            .map_err(|e| {
+                IRGenError::InvalidOperation(format!("Failed to reset argument count: {}", e))
+            })?;
+
+        let alloca = self.declare_variable("argument_count", number_type.into())?;
+        self.builder.build_store(alloca, count).map_err(|e| {
+            IRGenError::InvalidOperation(format!("Failed to store argument count: {}", e))
+        })?;
+        Ok(())
+    }
 }
@@ -0,0 +1,169 @@
+use crate::codegen::ir_generator::visit_expr::BinaryOp;
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::codegen::runtime::MATH_EPSILON_GLOBAL;
+use inkwell::FloatPredicate;
+use inkwell::builder::BuilderError;
+use inkwell::module::Linkage;
+use inkwell::values::{BasicValueEnum, FloatValue, PointerValue};
+
+fn float_error(e: BuilderError) -> IRGenError {
+    IRGenError::InvalidOperation(format!("Float operation failed: {}", e))
+}
+
+/// `lhs op rhs` for `==`, `!=`, `<=` or `>=` within `epsilon`, as
+/// [`IRGenerator::gen_epsilon_compare`] generates it
+pub(crate) fn compare_within(op: BinaryOp, lhs: f64, rhs: f64, epsilon: f64) -> bool {
+    let difference = lhs - rhs;
+    let distance = if difference < 0.0 {
+        -difference
+    } else {
+        difference
+    };
+    if let BinaryOp::Ne = op {
+        return distance > epsilon;
+    }
+    let equal = lhs == rhs || distance <= epsilon;
+    match op {
+        BinaryOp::Le => lhs < rhs || equal,
+        BinaryOp::Ge => lhs > rhs || equal,
+        _ => equal,
+    }
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Compare two numbers with `==`, `!=`, `<=` or `>=` the way GML does: equal when
+    /// they are at most the executor's epsilon apart. An epsilon of 0 gives the same
+    /// results as the exact comparisons, NaN included.
+    pub(crate) fn gen_epsilon_compare(
+        &self,
+        op: BinaryOp,
+        lhs: FloatValue<'ctx>,
+        rhs: FloatValue<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let number_type = self.type_mapping.get_number_type();
+        let epsilon = self
+            .builder
+            .build_load(number_type, self.math_epsilon_ptr(), "epsilon")
+            .map_err(float_error)?
+            .into_float_value();
+
+        let difference = self
+            .builder
+            .build_float_sub(lhs, rhs, "fdiff")
+            .map_err(float_error)?;
+        let negated = self
+            .builder
+            .build_float_neg(difference, "fdiff_neg")
+            .map_err(float_error)?;
+        let is_negative = self
+            .builder
+            .build_float_compare(
+                FloatPredicate::OLT,
+                difference,
+                number_type.const_zero(),
+                "fdiff_is_neg",
+            )
+            .map_err(float_error)?;
+        let distance = self
+            .builder
+            .build_select(is_negative, negated, difference, "fdist")
+            .map_err(float_error)?
+            .into_float_value();
+
+        // Unordered distances (NaN, or infinities of the same sign) are never
+        // greater, so this is as false as ONE is for them
+        if let BinaryOp::Ne = op {
+            return self
+                .builder
+                .build_float_compare(FloatPredicate::OGT, distance, epsilon, "fne")
+                .map(|v| v.into())
+                .map_err(float_error);
+        }
+
+        // Equal infinities are `==` even though their distance is NaN
+        let exact = self
+            .builder
+            .build_float_compare(FloatPredicate::OEQ, lhs, rhs, "feq_exact")
+            .map_err(float_error)?;
+        let close = self
+            .builder
+            .build_float_compare(FloatPredicate::OLE, distance, epsilon, "fclose")
+            .map_err(float_error)?;
+        let equal = self
+            .builder
+            .build_or(exact, close, "feq")
+            .map_err(float_error)?;
+        let ordered = match op {
+            BinaryOp::Le => FloatPredicate::OLT,
+            BinaryOp::Ge => FloatPredicate::OGT,
+            _ => return Ok(equal.into()),
+        };
+        let strict = self
+            .builder
+            .build_float_compare(ordered, lhs, rhs, "fstrict")
+            .map_err(float_error)?;
+        self.builder
+            .build_or(strict, equal, "fcmp_eps")
+            .map(|v| v.into())
+            .map_err(float_error)
+    }
+
+    /// `math_get_epsilon()`: the epsilon comparisons currently allow
+    pub(crate) fn gen_math_get_epsilon(&self) -> IRGenResult<BasicValueEnum<'ctx>> {
+        self.builder
+            .build_load(
+                self.type_mapping.get_number_type(),
+                self.math_epsilon_ptr(),
+                "epsilon",
+            )
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to load epsilon: {}", e)))
+    }
+
+    /// `math_set_epsilon(epsilon)`: set the epsilon for the comparisons that follow.
+    /// Negative values are treated as 0, as in GML.
+    pub(crate) fn gen_math_set_epsilon(
+        &self,
+        value: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let BasicValueEnum::FloatValue(epsilon) = self.gen_to_number(value)? else {
+            return Err(IRGenError::TypeMismatch(
+                "math_set_epsilon expects a number".to_string(),
+            ));
+        };
+        let number_type = self.type_mapping.get_number_type();
+        let is_negative = self
+            .builder
+            .build_float_compare(
+                FloatPredicate::OLT,
+                epsilon,
+                number_type.const_zero(),
+                "epsilon_is_neg",
+            )
+            .map_err(float_error)?;
+        let epsilon = self
+            .builder
+            .build_select(is_negative, number_type.const_zero(), epsilon, "epsilon")
+            .map_err(float_error)?;
+        self.builder
+            .build_store(self.math_epsilon_ptr(), epsilon)
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to store epsilon: {}", e)))?;
+        Ok(self.gen_undefined_const().into())
+    }
+
+    /// The executor's epsilon, declared in the module on first use
+    fn math_epsilon_ptr(&self) -> PointerValue<'ctx> {
+        let global = self
+            .module
+            .get_global(MATH_EPSILON_GLOBAL)
+            .unwrap_or_else(|| {
+                let global = self.module.add_global(
+                    self.type_mapping.get_number_type(),
+                    None,
+                    MATH_EPSILON_GLOBAL,
+                );
+                global.set_linkage(Linkage::External);
+                global
+            });
+        global.as_pointer_value()
+    }
+}
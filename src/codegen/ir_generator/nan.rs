@@ -0,0 +1,122 @@
+//! NaN in comparisons.
+//!
+//! Numbers compare with the ordered predicates, so every comparison with a NaN
+//! operand is false, `!=` included: for NaN `x`, `if (x >= 0)` takes its else branch
+//! and so does `if (x < 0)`. Negated comparisons are unordered instead, so `!(x < 0)`
+//! is true. Scripts test for NaN with `is_nan`, and under
+//! [`LanguageOptions::strict_math`](crate::parser::language_options::LanguageOptions::strict_math)
+//! comparing NaN stops the script with an error naming the function and where the
+//! comparison is. Arithmetic is never checked, so `0 / 0` still gives NaN.
+
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator, gml_name};
+use crate::codegen::runtime::trap::ErrorKind;
+use crate::parser::Span;
+use inkwell::FloatPredicate;
+use inkwell::builder::BuilderError;
+use inkwell::intrinsics::Intrinsic;
+use inkwell::values::{BasicValueEnum, FloatValue, IntValue};
+
+fn nan_error(e: BuilderError) -> IRGenError {
+    IRGenError::InvalidOperation(format!("NaN check failed: {}", e))
+}
+
+/// The message of the error comparing NaN at `span` in `function` raises under
+/// strict math
+pub(crate) fn nan_comparison_message(function: &str, span: Option<&Span>) -> String {
+    match span {
+        Some(span) => format!(
+            "comparison with NaN in '{}' at {}..{}",
+            function, span.start, span.end
+        ),
+        None => format!("comparison with NaN in '{}'", function),
+    }
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Whether `value` is NaN
+    pub(crate) fn gen_is_nan(&self, value: FloatValue<'ctx>) -> IRGenResult<IntValue<'ctx>> {
+        self.builder
+            .build_float_compare(FloatPredicate::UNO, value, value, "is_nan")
+            .map_err(nan_error)
+    }
+
+    /// Whether `value` is positive or negative infinity
+    pub(crate) fn gen_is_infinity(&self, value: FloatValue<'ctx>) -> IRGenResult<IntValue<'ctx>> {
+        let number_type = self.type_mapping.get_number_type();
+        let fabs = Intrinsic::find("llvm.fabs")
+            .and_then(|intrinsic| intrinsic.get_declaration(&self.module, &[number_type.into()]))
+            .ok_or_else(|| {
+                IRGenError::InvalidOperation("llvm.fabs is not available".to_string())
+            })?;
+        let magnitude = self
+            .builder
+            .build_call(fabs, &[value.into()], "fabs")
+            .map_err(nan_error)?
+            .try_as_basic_value()
+            .left()
+            .map(|v| v.into_float_value())
+            .ok_or_else(|| IRGenError::InvalidOperation("llvm.fabs returned void".to_string()))?;
+        self.builder
+            .build_float_compare(
+                FloatPredicate::OEQ,
+                magnitude,
+                number_type.const_float(f64::INFINITY),
+                "is_infinity",
+            )
+            .map_err(nan_error)
+    }
+
+    /// Under strict math, raise an error if a number operand of a comparison is NaN.
+    /// `span` is where the comparison is, for the error. Operands that are not
+    /// numbers, and constants that are not NaN, are not checked.
+    pub(crate) fn gen_nan_check(
+        &self,
+        lhs: BasicValueEnum<'ctx>,
+        rhs: BasicValueEnum<'ctx>,
+        span: Option<Span>,
+    ) -> IRGenResult<()> {
+        if !self.strict_math {
+            return Ok(());
+        }
+        let mut either_nan: Option<IntValue<'ctx>> = None;
+        for value in [lhs, rhs] {
+            let BasicValueEnum::FloatValue(value) = value else {
+                continue;
+            };
+            if value
+                .get_constant()
+                .is_some_and(|(constant, _)| !constant.is_nan())
+            {
+                continue;
+            }
+            let is_nan = self.gen_is_nan(value)?;
+            either_nan = Some(match either_nan {
+                Some(nan) => self
+                    .builder
+                    .build_or(nan, is_nan, "either_nan")
+                    .map_err(nan_error)?,
+                None => is_nan,
+            });
+        }
+        let Some(either_nan) = either_nan else {
+            return Ok(());
+        };
+
+        let function = self.current_function.ok_or_else(|| {
+            IRGenError::InvalidOperation("Comparison outside of a function".to_string())
+        })?;
+        let nan_block = self.context.append_basic_block(function, "nan_operand");
+        let ok_block = self.context.append_basic_block(function, "compare_ok");
+        self.builder
+            .build_conditional_branch(either_nan, nan_block, ok_block)
+            .map_err(nan_error)?;
+        self.builder.position_at_end(nan_block);
+        self.gen_raise(
+            ErrorKind::NanComparison,
+            &nan_comparison_message(&gml_name(function), span.as_ref()),
+            span.as_ref(),
+        )?;
+        self.builder.position_at_end(ok_block);
+        Ok(())
+    }
+}
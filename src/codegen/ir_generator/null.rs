@@ -0,0 +1,102 @@
+use crate::codegen::ir_generator::visit_expr::BinaryOp;
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::codegen::runtime::trap::ErrorKind;
+use inkwell::builder::BuilderError;
+use inkwell::values::{BasicValueEnum, IntValue, PointerValue};
+
+fn null_error(e: BuilderError) -> IRGenError {
+    IRGenError::InvalidOperation(format!("Null check failed: {}", e))
+}
+
+/// Whether `value` is the `null` literal, rather than a string that may hold null
+fn is_null_literal(value: BasicValueEnum<'_>) -> bool {
+    matches!(value, BasicValueEnum::PointerValue(ptr) if ptr.is_null())
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Apply `op` to operands one of which is the `null` literal, or give `None` if
+    /// neither is. Null is equal to null only, so `null == 0` and `null == ""` are
+    /// false; any other operator with a null operand is an error.
+    pub(crate) fn gen_null_operation(
+        &self,
+        op: BinaryOp,
+        lhs: BasicValueEnum<'ctx>,
+        rhs: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<Option<BasicValueEnum<'ctx>>> {
+        let other = match (is_null_literal(lhs), is_null_literal(rhs)) {
+            (false, false) => return Ok(None),
+            (true, _) => rhs,
+            (false, true) => lhs,
+        };
+        if !matches!(op, BinaryOp::Eq | BinaryOp::Ne) {
+            return Err(IRGenError::TypeMismatch(format!(
+                "null cannot be an operand of {:?}",
+                op
+            )));
+        }
+        // Only a string may hold null; numbers and bools never equal it
+        let equal = match other {
+            BasicValueEnum::PointerValue(other) => self
+                .builder
+                .build_is_null(other, "is_null")
+                .map_err(null_error)?,
+            _ => self.gen_bool_const(false),
+        };
+        if let BinaryOp::Ne = op {
+            return Ok(Some(
+                self.builder
+                    .build_not(equal, "not_null")
+                    .map_err(null_error)?
+                    .into(),
+            ));
+        }
+        Ok(Some(equal.into()))
+    }
+
+    /// Check string operands for null at run time, giving whether either of them is
+    /// null and whether both are. If one is and `op` is anything but `==` or `!=`,
+    /// an error is raised instead.
+    pub(crate) fn gen_string_null_check(
+        &self,
+        op: BinaryOp,
+        lhs: PointerValue<'ctx>,
+        rhs: PointerValue<'ctx>,
+    ) -> IRGenResult<(IntValue<'ctx>, IntValue<'ctx>)> {
+        let lhs_null = self
+            .builder
+            .build_is_null(lhs, "lhs_is_null")
+            .map_err(null_error)?;
+        let rhs_null = self
+            .builder
+            .build_is_null(rhs, "rhs_is_null")
+            .map_err(null_error)?;
+        let either_null = self
+            .builder
+            .build_or(lhs_null, rhs_null, "either_null")
+            .map_err(null_error)?;
+        let both_null = self
+            .builder
+            .build_and(lhs_null, rhs_null, "both_null")
+            .map_err(null_error)?;
+        if matches!(op, BinaryOp::Eq | BinaryOp::Ne) {
+            return Ok((either_null, both_null));
+        }
+
+        let function = self.current_function.ok_or_else(|| {
+            IRGenError::InvalidOperation("String operation outside of a function".to_string())
+        })?;
+        let null_block = self.context.append_basic_block(function, "null_operand");
+        let ok_block = self.context.append_basic_block(function, "strings_ok");
+        self.builder
+            .build_conditional_branch(either_null, null_block, ok_block)
+            .map_err(null_error)?;
+        self.builder.position_at_end(null_block);
+        self.gen_raise(
+            ErrorKind::NullOperand,
+            &format!("null cannot be an operand of {:?}", op),
+            None,
+        )?;
+        self.builder.position_at_end(ok_block);
+        Ok((either_null, both_null))
+    }
+}
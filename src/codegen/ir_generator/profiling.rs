@@ -0,0 +1,74 @@
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator, gml_name};
+use crate::codegen::runtime::profile;
+use inkwell::module::Linkage;
+use inkwell::values::{FunctionValue, InstructionOpcode, PointerValue};
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Record a call of `function`, at the insert position in its entry block.
+    /// Nothing is generated unless profiling is on.
+    pub(crate) fn gen_profile_enter(&self, function: FunctionValue<'ctx>) -> IRGenResult<()> {
+        if !self.profiling {
+            return Ok(());
+        }
+        self.call_profile_hook(profile::PROFILE_ENTER, function)
+    }
+
+    /// Record the end of the call before every return in `function`, once its body
+    /// is complete. Nothing is generated unless profiling is on.
+    pub(crate) fn gen_profile_exits(&self, function: FunctionValue<'ctx>) -> IRGenResult<()> {
+        if !self.profiling {
+            return Ok(());
+        }
+        for block in function.get_basic_blocks() {
+            let Some(terminator) = block.get_terminator() else {
+                continue;
+            };
+            if terminator.get_opcode() == InstructionOpcode::Return {
+                self.builder.position_before(&terminator);
+                self.call_profile_hook(profile::PROFILE_EXIT, function)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn call_profile_hook(&self, hook: &str, function: FunctionValue<'ctx>) -> IRGenResult<()> {
+        let args = [
+            self.profile_ptr().into(),
+            self.profile_name(function).into(),
+        ];
+        self.builder
+            .build_call(self.runtime_function(hook), &args, "")
+            .map_err(|e| {
+                IRGenError::InvalidOperation(format!("Failed to build profiling call: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// The name `function` is profiled under, one constant per function: the
+    /// runtime tells functions apart by its address
+    fn profile_name(&self, function: FunctionValue<'ctx>) -> PointerValue<'ctx> {
+        let name = gml_name(function);
+        let global_name = format!("__col_profile_name.{}", name);
+        let global = self.module.get_global(&global_name).unwrap_or_else(|| {
+            self.builder
+                .build_global_string_ptr(&name, &global_name)
+                .expect("Failed to build string constant")
+        });
+        global.as_pointer_value()
+    }
+
+    /// The executor's profile, declared in the module on first use
+    fn profile_ptr(&self) -> PointerValue<'ctx> {
+        let global = self
+            .module
+            .get_global(profile::PROFILE_GLOBAL)
+            .unwrap_or_else(|| {
+                let global =
+                    self.module
+                        .add_global(self.context.i8_type(), None, profile::PROFILE_GLOBAL);
+                global.set_linkage(Linkage::External);
+                global
+            });
+        global.as_pointer_value()
+    }
+}
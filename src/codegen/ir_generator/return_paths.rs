@@ -0,0 +1,129 @@
+//! Functions whose returns do not cover every path.
+//!
+//! A function that returns a value somewhere but can also reach the end of its
+//! body, like `function f(x) { if (x > 0) return 1; }`, gets a warning pointing
+//! at the statement control leaves through, or an error under
+//! [`LanguageOptions::strict_returns`](crate::parser::language_options::LanguageOptions::strict_returns).
+//! Functions without a `return` of a value are procedures and may end anywhere.
+//! Reaching the end of a function returns 0, whatever its last statement
+//! evaluated to.
+//!
+//! Only the AST is looked at. Loops are never taken to return, as their body may
+//! not run, so `while (true) { return 1; }` ending a function still warns; a
+//! `switch` returns when it has a `default`, nothing in it leaves it and its last
+//! label's statements return.
+
+use crate::codegen::ir_generator::visit_stmt::stmt_span;
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator, MISSING_RETURN};
+use crate::parser::Span;
+use crate::parser::func_def::FuncDef;
+use crate::parser::stmt::Stmt;
+use crate::utils::diagnostic::{Diagnostic, Severity};
+
+/// Whether `stmt` returns a value anywhere, leaving nested functions aside
+fn returns_value(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return(value) => value.is_some(),
+        Stmt::If(_, then_stmt, else_stmt, _) => {
+            returns_value(then_stmt) || else_stmt.as_deref().is_some_and(returns_value)
+        }
+        Stmt::Block(stmts, _) => stmts.iter().any(returns_value),
+        Stmt::Repeat(_, body, _) | Stmt::While(_, body, _) | Stmt::DoUntil(body, _, _) => {
+            returns_value(body)
+        }
+        Stmt::For(init, _, update, body, _) => {
+            init.as_deref().is_some_and(returns_value)
+                || update.as_deref().is_some_and(returns_value)
+                || returns_value(body)
+        }
+        Stmt::Switch(_, cases, _) => cases.iter().any(|case| case.body.iter().any(returns_value)),
+        Stmt::Expr(_)
+        | Stmt::Var(_)
+        | Stmt::Break
+        | Stmt::Continue
+        | Stmt::Function(_)
+        | Stmt::Error(_) => false,
+    }
+}
+
+/// Whether `stmt` has a `break` or `continue` that may leave the `switch` around
+/// it. Those of a nested `switch` count too, as its `continue`s do leave.
+fn leaves_switch(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Break | Stmt::Continue => true,
+        Stmt::If(_, then_stmt, else_stmt, _) => {
+            leaves_switch(then_stmt) || else_stmt.as_deref().is_some_and(leaves_switch)
+        }
+        Stmt::Block(stmts, _) => stmts.iter().any(leaves_switch),
+        Stmt::Switch(_, cases, _) => cases.iter().any(|case| case.body.iter().any(leaves_switch)),
+        _ => false,
+    }
+}
+
+/// Whether every path through `stmt` ends in a `return`
+fn always_returns(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return(_) => true,
+        Stmt::If(_, then_stmt, Some(else_stmt), _) => {
+            always_returns(then_stmt) && always_returns(else_stmt)
+        }
+        Stmt::Block(stmts, _) => stmts.iter().any(always_returns),
+        Stmt::Switch(_, cases, _) => {
+            cases.iter().any(|case| case.default)
+                && !cases.iter().any(|case| case.body.iter().any(leaves_switch))
+                && cases
+                    .last()
+                    .is_some_and(|case| case.body.iter().any(always_returns))
+        }
+        _ => false,
+    }
+}
+
+/// Where control leaves `stmt`, which does not always return, without returning
+fn fall_through(stmt: &Stmt) -> Option<Span> {
+    match stmt {
+        Stmt::Block(stmts, span) => match stmts.last() {
+            Some(last) => fall_through(last),
+            None => Some(span.clone()),
+        },
+        Stmt::If(_, then_stmt, Some(else_stmt), _) => {
+            if always_returns(then_stmt) {
+                fall_through(else_stmt)
+            } else {
+                fall_through(then_stmt)
+            }
+        }
+        _ => stmt_span(stmt),
+    }
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Report `func_def` if it returns a value on some paths and reaches its end
+    /// on others
+    pub(crate) fn check_return_paths(&mut self, func_def: &FuncDef) -> IRGenResult<()> {
+        let body = &func_def.func.body;
+        if !body.iter().any(returns_value) || body.iter().any(always_returns) {
+            return Ok(());
+        }
+        let span = body
+            .last()
+            .and_then(fall_through)
+            .unwrap_or_else(|| func_def.span.clone());
+        if self.strict_returns {
+            return Err(IRGenError::MissingReturn {
+                function: func_def.name.clone(),
+                span,
+            });
+        }
+        self.diagnostics.push(Diagnostic::new(
+            MISSING_RETURN,
+            Severity::Warning,
+            format!(
+                "not every path through '{}' returns a value; reaching the end returns 0",
+                func_def.name
+            ),
+            Some(span),
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,314 @@
+use crate::codegen::ir_generator::visit_expr::BinaryOp;
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::parser::Span;
+use crate::parser::expr::Expr;
+use crate::parser::stmt::SwitchCase;
+use crate::utils::number_format::format_number;
+use inkwell::FloatPredicate;
+use inkwell::basic_block::BasicBlock;
+use inkwell::builder::BuilderError;
+use inkwell::intrinsics::Intrinsic;
+use inkwell::values::{BasicValueEnum, FloatValue, FunctionValue, IntValue};
+use std::fmt;
+
+fn switch_error(e: BuilderError) -> IRGenError {
+    IRGenError::InvalidOperation(format!("Failed to build switch: {}", e))
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Jump to the statements of the arm with a label equal to the value, or of the
+    /// `default` arm if none is. Statements run on into the next arm's until a
+    /// `break`, which leaves the switch. When every label is an integer literal, the
+    /// value a number and comparisons exact, one LLVM `switch` picks the arm;
+    /// otherwise the labels are compared with the value in source order, so with
+    /// epsilon comparisons a value within epsilon of a label matches it.
+    pub(crate) fn generate_switch(
+        &mut self,
+        value: &Expr,
+        cases: &[SwitchCase],
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let current_fn = self.current_function.ok_or_else(|| {
+            IRGenError::InvalidOperation("Switch statement outside function".to_string())
+        })?;
+        check_duplicate_labels(cases)?;
+        let id = self.next_loop_id();
+
+        // Evaluated once, whichever label matches
+        let value = self.visit_expr_impl(value)?;
+
+        let case_blocks: Vec<_> = (0..cases.len())
+            .map(|i| {
+                self.context
+                    .append_basic_block(current_fn, &format!("switch_case.{}.{}", id, i))
+            })
+            .collect();
+        let exit_block = self
+            .context
+            .append_basic_block(current_fn, &format!("switch_exit.{}", id));
+        let no_match = cases
+            .iter()
+            .position(|case| case.default)
+            .map_or(exit_block, |i| case_blocks[i]);
+
+        let int64_type = self.type_mapping.get_int64_type();
+        let labels = integer_labels(cases, &case_blocks).filter(|_| !self.epsilon_comparisons);
+        match (labels, value) {
+            (Some(labels), BasicValueEnum::FloatValue(number)) => {
+                let integer =
+                    self.gen_switch_integer(number, current_fn, no_match, exit_block, id)?;
+                self.gen_switch_jump(integer, no_match, &labels)?;
+            }
+            (Some(labels), BasicValueEnum::IntValue(integer))
+                if integer.get_type() == int64_type =>
+            {
+                self.gen_switch_jump(integer, no_match, &labels)?;
+            }
+            _ => self.gen_switch_tests(
+                value,
+                cases,
+                &case_blocks,
+                current_fn,
+                no_match,
+                exit_block,
+                id,
+            )?,
+        }
+
+        let next_block = self
+            .loop_targets
+            .last()
+            .and_then(|&(_, next_block)| next_block);
+        self.loop_targets.push((exit_block, next_block));
+        let result = self.gen_switch_cases(cases, &case_blocks, exit_block);
+        self.loop_targets.pop();
+        result?;
+
+        self.builder.position_at_end(exit_block);
+        Ok(self.gen_number_const(0.0).into())
+    }
+
+    /// `number` as an integer, continuing in a new block if it is one and jumping
+    /// to `no_match` if it is not, as no integer label can equal it then
+    fn gen_switch_integer(
+        &self,
+        number: FloatValue<'ctx>,
+        current_fn: FunctionValue<'ctx>,
+        no_match: BasicBlock<'ctx>,
+        exit_block: BasicBlock<'ctx>,
+        id: usize,
+    ) -> IRGenResult<IntValue<'ctx>> {
+        let int64_type = self.type_mapping.get_int64_type();
+        // Saturating, so NaN and numbers out of range convert without poison, and
+        // fail the round trip below
+        let to_integer = Intrinsic::find("llvm.fptosi.sat")
+            .and_then(|intrinsic| {
+                intrinsic
+                    .get_declaration(&self.module, &[int64_type.into(), number.get_type().into()])
+            })
+            .ok_or_else(|| {
+                IRGenError::InvalidOperation("llvm.fptosi.sat is not available".to_string())
+            })?;
+        let integer = self
+            .builder
+            .build_call(to_integer, &[number.into()], "switch_int")
+            .map_err(switch_error)?
+            .try_as_basic_value()
+            .left()
+            .map(|v| v.into_int_value())
+            .ok_or_else(|| {
+                IRGenError::InvalidOperation("llvm.fptosi.sat returned void".to_string())
+            })?;
+        let back = self
+            .builder
+            .build_signed_int_to_float(integer, number.get_type(), "switch_back")
+            .map_err(switch_error)?;
+        let integral = self
+            .builder
+            .build_float_compare(FloatPredicate::OEQ, back, number, "switch_integral")
+            .map_err(switch_error)?;
+
+        let jump_block = self
+            .context
+            .append_basic_block(current_fn, &format!("switch_jump.{}", id));
+        jump_block
+            .move_before(exit_block)
+            .map_err(|_| IRGenError::InvalidOperation("Failed to order blocks".to_string()))?;
+        self.builder
+            .build_conditional_branch(integral, jump_block, no_match)
+            .map_err(switch_error)?;
+        self.builder.position_at_end(jump_block);
+        Ok(integer)
+    }
+
+    /// Jump to the block of the label equal to `integer`, or to `no_match`
+    fn gen_switch_jump(
+        &self,
+        integer: IntValue<'ctx>,
+        no_match: BasicBlock<'ctx>,
+        labels: &[(i64, BasicBlock<'ctx>)],
+    ) -> IRGenResult<()> {
+        let int64_type = self.type_mapping.get_int64_type();
+        let cases: Vec<_> = labels
+            .iter()
+            .map(|&(label, block)| (int64_type.const_int(label as u64, true), block))
+            .collect();
+        self.builder
+            .build_switch(integer, no_match, &cases)
+            .map_err(switch_error)?;
+        Ok(())
+    }
+
+    /// Compare `value` with each label in source order and jump to the block of the
+    /// first that is equal, or to `no_match`
+    #[allow(clippy::too_many_arguments)] // the blocks the tests jump between
+    fn gen_switch_tests(
+        &mut self,
+        value: BasicValueEnum<'ctx>,
+        cases: &[SwitchCase],
+        case_blocks: &[BasicBlock<'ctx>],
+        current_fn: FunctionValue<'ctx>,
+        no_match: BasicBlock<'ctx>,
+        exit_block: BasicBlock<'ctx>,
+        id: usize,
+    ) -> IRGenResult<()> {
+        for (case, &case_block) in cases.iter().zip(case_blocks) {
+            for label in &case.labels {
+                let label = self.visit_expr_impl(label)?;
+                let equal = self.gen_binary_op(BinaryOp::Eq, value, label)?;
+                let equal = self.convert_to_bool(equal)?;
+                let next_test = self
+                    .context
+                    .append_basic_block(current_fn, &format!("switch_test.{}", id));
+                next_test.move_before(exit_block).map_err(|_| {
+                    IRGenError::InvalidOperation("Failed to order blocks".to_string())
+                })?;
+                self.builder
+                    .build_conditional_branch(equal, case_block, next_test)
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!(
+                            "Failed to build conditional branch: {}",
+                            e
+                        ))
+                    })?;
+                self.builder.position_at_end(next_test);
+            }
+        }
+        self.builder
+            .build_unconditional_branch(no_match)
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build branch: {}", e)))?;
+        Ok(())
+    }
+
+    /// The statements of each arm of a switch, in `case_blocks`. Each runs on into
+    /// the next arm's, and the last into `exit_block`.
+    fn gen_switch_cases(
+        &mut self,
+        cases: &[SwitchCase],
+        case_blocks: &[BasicBlock<'ctx>],
+        exit_block: BasicBlock<'ctx>,
+    ) -> IRGenResult<()> {
+        for (i, (case, &case_block)) in cases.iter().zip(case_blocks).enumerate() {
+            self.builder.position_at_end(case_block);
+            let mut previous = None;
+            for stmt in &case.body {
+                if let (true, Some(previous)) = (self.is_terminated(), previous) {
+                    self.warn_unreachable(previous, stmt);
+                    break;
+                }
+                self.visit_stmt_impl(stmt)?;
+                previous = Some(stmt);
+            }
+            if !self.is_terminated() {
+                let next = case_blocks.get(i + 1).copied().unwrap_or(exit_block);
+                self.builder.build_unconditional_branch(next).map_err(|e| {
+                    IRGenError::InvalidOperation(format!("Failed to build branch: {}", e))
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every label of `cases` with the block of its arm, if all are integer literals
+fn integer_labels<'ctx>(
+    cases: &[SwitchCase],
+    case_blocks: &[BasicBlock<'ctx>],
+) -> Option<Vec<(i64, BasicBlock<'ctx>)>> {
+    cases
+        .iter()
+        .zip(case_blocks)
+        .flat_map(|(case, &block)| {
+            case.labels
+                .iter()
+                .map(move |label| Some((integer_label(label)?, block)))
+        })
+        .collect()
+}
+
+/// The value of a label that is an integer literal, possibly negated
+fn integer_label(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Integer(n, _) => Some(*n),
+        // 2^63 itself is out of range
+        Expr::Number(n, _)
+            if n.fract() == 0.0 && (i64::MIN as f64..-(i64::MIN as f64)).contains(n) =>
+        {
+            Some(*n as i64)
+        }
+        Expr::Paren(inner) => integer_label(inner),
+        Expr::Negative(inner) => integer_label(inner)?.checked_neg(),
+        _ => None,
+    }
+}
+
+/// The value of a literal label. Numbers compare as floats, so `1` and `1.0` are
+/// the same label.
+#[derive(Debug, PartialEq)]
+enum Constant {
+    Number(f64),
+    String(String),
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Number(n) => write!(f, "{}", format_number(*n)),
+            Constant::String(s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
+/// The value of a label that is a literal, possibly negated, and where the literal is
+fn constant_label(expr: &Expr) -> Option<(Constant, Span)> {
+    match expr {
+        Expr::Number(n, span) => Some((Constant::Number(*n), span.clone())),
+        Expr::Integer(n, span) => Some((Constant::Number(*n as f64), span.clone())),
+        Expr::String(s, span) => Some((Constant::String(s.clone()), span.clone())),
+        Expr::Paren(inner) => constant_label(inner),
+        Expr::Negative(inner) => match constant_label(inner)? {
+            (Constant::Number(n), span) => Some((Constant::Number(-n), span)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Fail on the first literal label equal to an earlier one of the same switch,
+/// which could never match
+fn check_duplicate_labels(cases: &[SwitchCase]) -> IRGenResult<()> {
+    let mut seen: Vec<(Constant, Span)> = vec![];
+    for label in cases.iter().flat_map(|case| &case.labels) {
+        let Some((constant, span)) = constant_label(label) else {
+            continue;
+        };
+        if let Some((_, first)) = seen.iter().find(|(other, _)| *other == constant) {
+            return Err(IRGenError::DuplicateCase {
+                label: constant.to_string(),
+                span,
+                first: first.clone(),
+            });
+        }
+        seen.push((constant, span));
+    }
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Symbol names of the functions generated for a script.
+//!
+//! By default functions are added to the module under their GML names, so the IR
+//! reads like the source. With a [`IRGenerator::symbol_prefix`] they are named
+//! `{prefix}{module}_{name}` instead, e.g. `col_script_update`, `col_script_main`
+//! for `main` and `col_script___col_init` for the top-level code, so they cannot
+//! clash with the symbols of the host process or of another script's module. Each
+//! renamed function records its GML name in a string attribute, which survives
+//! bitcode, and the executor finds functions by GML name through
+//! [`symbol_names`].
+
+use crate::codegen::ir_generator::IRGenerator;
+use inkwell::attributes::AttributeLoc;
+use inkwell::module::Module;
+use inkwell::types::FunctionType;
+use inkwell::values::FunctionValue;
+use std::collections::HashMap;
+
+/// The prefix scripts compiled through [`Script`](crate::script::Script) name their
+/// functions with
+pub const DEFAULT_SYMBOL_PREFIX: &str = "col_";
+
+/// Function attribute holding the GML name of a function with a prefixed symbol
+pub(crate) const GML_NAME_ATTRIBUTE: &str = "col-gml-name";
+
+/// The GML name `function` was generated for, if its symbol is prefixed
+pub(crate) fn recorded_gml_name(function: FunctionValue<'_>) -> Option<String> {
+    function
+        .get_string_attribute(AttributeLoc::Function, GML_NAME_ATTRIBUTE)
+        .map(|attribute| attribute.get_string_value().to_string_lossy().into_owned())
+}
+
+/// The symbol of every function in `module` generated under a prefixed name, by
+/// the GML name it goes by; the top-level code is under
+/// [`INIT_FUNCTION`](crate::codegen::ir_generator::INIT_FUNCTION). Empty for a
+/// module generated without a prefix.
+pub fn symbol_names(module: &Module<'_>) -> HashMap<String, String> {
+    module
+        .get_functions()
+        .filter_map(|function| {
+            let symbol = function.get_name().to_string_lossy().into_owned();
+            recorded_gml_name(function).map(|name| (name, symbol))
+        })
+        .collect()
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// The symbol the function `name` is generated under
+    pub(crate) fn symbol_name(&self, name: &str) -> String {
+        let Some(prefix) = &self.symbol_prefix else {
+            return name.to_string();
+        };
+        let module: String = self
+            .module
+            .get_name()
+            .to_string_lossy()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("{}{}_{}", prefix, module, name)
+    }
+
+    /// Add the function `name` to the module under its symbol, recording `name` if
+    /// the two differ
+    pub(crate) fn add_script_function(
+        &self,
+        name: &str,
+        fn_type: FunctionType<'ctx>,
+    ) -> FunctionValue<'ctx> {
+        let symbol = self.symbol_name(name);
+        let function = self.module.add_function(&symbol, fn_type, None);
+        if symbol != name {
+            let attribute = self
+                .context
+                .create_string_attribute(GML_NAME_ATTRIBUTE, name);
+            function.add_attribute(AttributeLoc::Function, attribute);
+        }
+        function
+    }
+}
@@ -0,0 +1,49 @@
+use crate::codegen::ir_generator::visit_stmt::stmt_span;
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::codegen::runtime::trace;
+use crate::parser::stmt::Stmt;
+use inkwell::module::Linkage;
+use inkwell::values::PointerValue;
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// Record that `stmt` starts, by where it is in the source. Blocks and function
+    /// definitions run nothing of their own and are not recorded, nor are statements
+    /// without a span, like `break`. Nothing is generated unless tracing is on.
+    pub(crate) fn gen_trace_statement(&self, stmt: &Stmt) -> IRGenResult<()> {
+        if !self.tracing || matches!(stmt, Stmt::Block(..) | Stmt::Function(_)) {
+            return Ok(());
+        }
+        let Some(span) = stmt_span(stmt) else {
+            return Ok(());
+        };
+        let offset = self
+            .type_mapping
+            .get_int64_type()
+            .const_int(span.start as u64, false);
+        self.builder
+            .build_call(
+                self.runtime_function(trace::TRACE_STATEMENT),
+                &[self.trace_ptr().into(), offset.into()],
+                "",
+            )
+            .map_err(|e| {
+                IRGenError::InvalidOperation(format!("Failed to build trace call: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// The executor's trace, declared in the module on first use
+    fn trace_ptr(&self) -> PointerValue<'ctx> {
+        let global = self
+            .module
+            .get_global(trace::TRACE_GLOBAL)
+            .unwrap_or_else(|| {
+                let global =
+                    self.module
+                        .add_global(self.context.i8_type(), None, trace::TRACE_GLOBAL);
+                global.set_linkage(Linkage::External);
+                global
+            });
+        global.as_pointer_value()
+    }
+}
@@ -0,0 +1,59 @@
+//! Unrolling `repeat` loops with a small constant count.
+//!
+//! `repeat (4) { ... }` generates its body four times in a row instead of a
+//! counter and the `repeat_cond`/`repeat_body`/`repeat_exit` blocks, for counts of
+//! 1 up to [`IRGenerator::max_unrolled_repeat`]. Only a literal whole number counts
+//! as constant. A body with a `break`, `continue` or `return` anywhere in it keeps
+//! the loop, as does one defining a function, which could not be defined again.
+//!
+//! A bare generator keeps every loop; the CLI and [`Script`](crate::script::Script)
+//! unroll up to [`DEFAULT_MAX_UNROLLED_REPEAT`].
+
+use crate::codegen::ir_generator::IRGenerator;
+use crate::parser::expr::Expr;
+use crate::parser::stmt::Stmt;
+
+/// Default largest `repeat` count unrolled
+pub const DEFAULT_MAX_UNROLLED_REPEAT: usize = 8;
+
+/// Whether `stmt` could leave the loop around it early or defines a function
+fn blocks_unrolling(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Break | Stmt::Continue | Stmt::Return(_) | Stmt::Function(_) => true,
+        Stmt::Expr(_) | Stmt::Var(_) | Stmt::Error(_) => false,
+        Stmt::If(_, then_stmt, else_stmt, _) => {
+            blocks_unrolling(then_stmt) || else_stmt.as_deref().is_some_and(blocks_unrolling)
+        }
+        Stmt::Block(stmts, _) => stmts.iter().any(blocks_unrolling),
+        Stmt::Repeat(_, body, _) | Stmt::While(_, body, _) | Stmt::DoUntil(body, _, _) => {
+            blocks_unrolling(body)
+        }
+        Stmt::For(init, _, update, body, _) => {
+            init.as_deref().is_some_and(blocks_unrolling)
+                || update.as_deref().is_some_and(blocks_unrolling)
+                || blocks_unrolling(body)
+        }
+        Stmt::Switch(_, cases, _) => cases
+            .iter()
+            .any(|case| case.body.iter().any(blocks_unrolling)),
+    }
+}
+
+impl<'ctx> IRGenerator<'ctx> {
+    /// How many times to generate `body` in place of the loop `repeat (count) body`,
+    /// or `None` to generate the loop
+    pub(crate) fn unrolled_repeat_count(&self, count: &Expr, body: &Stmt) -> Option<usize> {
+        let count = match count {
+            Expr::Number(count, _) => *count,
+            Expr::Integer(count, _) => *count as f64,
+            _ => return None,
+        };
+        if count.fract() != 0.0 || count < 1.0 || count > self.max_unrolled_repeat as f64 {
+            return None;
+        }
+        if blocks_unrolling(body) {
+            return None;
+        }
+        Some(count as usize)
+    }
+}
@@ -1,4 +1,6 @@
-use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::codegen::ir_generator::division::{Division, operation_span};
+use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator, with_stack};
+use crate::codegen::runtime::instances::Instance;
 use crate::parser::expr::Expr;
 use inkwell::values::*;
 
@@ -26,24 +28,131 @@ pub enum BinaryOp {
     BitAnd,
     BitOr,
     BitXor,
+    Shl,
+    Shr,
+}
+
+impl BinaryOp {
+    /// Bitwise and shift operators, which work on integers
+    fn is_bitwise(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor | BinaryOp::Shl | BinaryOp::Shr
+        )
+    }
+}
+
+/// What an assignment, compound assignment or increment writes to
+enum Target<'a> {
+    Variable(&'a str),
+    Field(Instance, &'a str),
+}
+
+impl<'a> Target<'a> {
+    /// The target `expr` names, if it can be assigned
+    fn of(expr: &'a Expr) -> Option<Self> {
+        match expr {
+            Expr::Identifier(name, _) => Some(Target::Variable(name)),
+            Expr::Field(object, field, _) => IRGenerator::field_instance(object, field)
+                .ok()
+                .map(|instance| Target::Field(instance, field)),
+            _ => None,
+        }
+    }
 }
 
 impl<'ctx> IRGenerator<'ctx> {
+    fn load_target(&self, target: &Target) -> IRGenResult<BasicValueEnum<'ctx>> {
+        match *target {
+            Target::Variable(name) => self.load_variable(name),
+            Target::Field(instance, field) => self.gen_field_get(instance, field),
+        }
+    }
+
+    fn store_target(
+        &mut self,
+        target: &Target,
+        value: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        match *target {
+            Target::Variable(name) => self.store_variable(name, value),
+            Target::Field(instance, field) => self.gen_field_set(instance, field, value),
+        }
+    }
+
     pub fn visit_expr_impl(&mut self, expr: &Expr) -> IRGenResult<BasicValueEnum<'ctx>> {
+        if self.expression_depth >= self.max_expression_depth {
+            return Err(IRGenError::InvalidOperation(
+                "expression too deeply nested".to_string(),
+            ));
+        }
+        self.expression_depth += 1;
+        let result = with_stack(|| self.gen_expr(expr));
+        self.expression_depth -= 1;
+        result
+    }
+
+    fn gen_expr(&mut self, expr: &Expr) -> IRGenResult<BasicValueEnum<'ctx>> {
         match expr {
-            Expr::Number(n) => Ok(self.gen_number_const(*n).into()),
-            Expr::String(s) => Ok(self.gen_string_const(s).into()),
-            Expr::True(_) => Ok(self.gen_bool_const(true).into()),
-            Expr::False(_) => Ok(self.gen_bool_const(false).into()),
-            Expr::Null => Ok(self.gen_null_const().into()),
+            Expr::Number(n, _) => Ok(self.gen_number_const(*n).into()),
+            Expr::Integer(n, _) => Ok(self
+                .type_mapping
+                .get_int64_type()
+                .const_int(*n as u64, true)
+                .into()),
+            Expr::String(s, _) => Ok(self.gen_string_const(s).into()),
+            Expr::True(..) => Ok(self.gen_bool_const(true).into()),
+            Expr::False(..) => Ok(self.gen_bool_const(false).into()),
+            Expr::Null(_) => Ok(self.gen_null_const().into()),
+            Expr::Undefined => Ok(self.gen_undefined_const().into()),
+
+            Expr::Identifier(name, _) => self.load_variable(name),
+            Expr::Member(object, member, _) => Ok(self
+                .gen_number_const(self.enum_member(object, member)?)
+                .into()),
+            Expr::Field(object, field, _) => {
+                let instance = Self::field_instance(object, field)?;
+                self.gen_field_get(instance, field)
+            }
+            Expr::Accessor(kind, target, indices, span) => {
+                self.gen_accessor_get(*kind, target, indices, span)
+            }
+            Expr::SelfRef(_) | Expr::OtherRef(_) => Err(IRGenError::InvalidOperation(
+                "self and other can only be used to access fields, as in self.x".to_string(),
+            )),
+            Expr::Function(func, span) => self.gen_function_expr(func, span),
 
-            Expr::Identifier(name) => self.load_variable(name),
+            Expr::Call(name, args, span) => {
+                if self.is_builtin(name) {
+                    return self.gen_builtin_call(name, args);
+                }
+                // A variable is only called if no function has its name
+                let known = self.functions.get(name);
+                let Some(function) = known.or_else(|| self.callables.get(name).copied()) else {
+                    return self.gen_reference_call(name, args, span);
+                };
+                let arity = function.count_params() as usize;
+                if args.len() > arity && !self.permissive_arity {
+                    return Err(IRGenError::ArgumentCountMismatch(format!(
+                        "Function '{}' takes {} argument(s) but {} were given",
+                        name,
+                        arity,
+                        args.len()
+                    )));
+                }
 
-            Expr::Call(name, args) => {
-                let function = self.get_function(name)?;
-                let arg_values: Result<Vec<_>, _> =
-                    args.iter().map(|arg| self.visit_expr_impl(arg)).collect();
-                let arg_values = arg_values?;
+                // Extra arguments are still evaluated for their side effects
+                let mut arg_values = Vec::with_capacity(args.len());
+                for arg in args {
+                    let value = self.visit_expr_impl(arg)?;
+                    arg_values.push(self.convert_to_return_type(value)?);
+                }
+                arg_values.truncate(arity);
+
+                // Missing arguments are undefined
+                while arg_values.len() < arity {
+                    arg_values.push(self.gen_undefined_const().into());
+                }
 
                 // Convert BasicValueEnum to BasicMetadataValueEnum
                 let metadata_args: Vec<BasicMetadataValueEnum> = arg_values
@@ -59,12 +168,23 @@ impl<'ctx> IRGenerator<'ctx> {
                     })
                     .collect();
 
+                let count_global = self.argument_count_global().as_pointer_value();
+                self.builder
+                    .build_store(count_global, self.gen_number_const(args.len() as f64))
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!(
+                            "Failed to store argument count: {}",
+                            e
+                        ))
+                    })?;
+
                 let call_value = self
                     .builder
                     .build_call(function, &metadata_args, "call")
                     .map_err(|e| {
                         IRGenError::InvalidOperation(format!("Failed to build call: {}", e))
                     })?;
+                self.gen_trap_check(Some(span))?;
 
                 call_value.try_as_basic_value().left().ok_or_else(|| {
                     IRGenError::InvalidOperation("Function call returned void".to_string())
@@ -90,118 +210,114 @@ impl<'ctx> IRGenerator<'ctx> {
             Expr::Division(lhs, rhs) => {
                 let l = self.visit_expr_impl(lhs)?;
                 let r = self.visit_expr_impl(rhs)?;
-                self.gen_binary_op(BinaryOp::Div, l, r)
+                self.gen_division(Division::Quotient, l, r, operation_span(lhs, rhs))
             }
-            Expr::Percent(lhs, rhs) => {
+            Expr::IntDivision(lhs, rhs) => {
                 let l = self.visit_expr_impl(lhs)?;
                 let r = self.visit_expr_impl(rhs)?;
-                self.gen_binary_op(BinaryOp::Mod, l, r)
+                self.gen_division(Division::Truncated, l, r, operation_span(lhs, rhs))
             }
-
-            // Comparison operations
-            Expr::EqualEqual(lhs, rhs) => {
-                let l = self.visit_expr_impl(lhs)?;
-                let r = self.visit_expr_impl(rhs)?;
-                self.gen_binary_op(BinaryOp::Eq, l, r)
-            }
-            Expr::NotEqual(lhs, rhs) => {
-                let l = self.visit_expr_impl(lhs)?;
-                let r = self.visit_expr_impl(rhs)?;
-                self.gen_binary_op(BinaryOp::Ne, l, r)
-            }
-            Expr::Less(lhs, rhs) => {
-                let l = self.visit_expr_impl(lhs)?;
-                let r = self.visit_expr_impl(rhs)?;
-                self.gen_binary_op(BinaryOp::Lt, l, r)
-            }
-            Expr::LessEqual(lhs, rhs) => {
-                let l = self.visit_expr_impl(lhs)?;
-                let r = self.visit_expr_impl(rhs)?;
-                self.gen_binary_op(BinaryOp::Le, l, r)
-            }
-            Expr::Greater(lhs, rhs) => {
-                let l = self.visit_expr_impl(lhs)?;
-                let r = self.visit_expr_impl(rhs)?;
-                self.gen_binary_op(BinaryOp::Gt, l, r)
-            }
-            Expr::GreaterEqual(lhs, rhs) => {
+            Expr::Percent(lhs, rhs) => {
                 let l = self.visit_expr_impl(lhs)?;
                 let r = self.visit_expr_impl(rhs)?;
-                self.gen_binary_op(BinaryOp::Ge, l, r)
+                self.gen_division(Division::Remainder, l, r, operation_span(lhs, rhs))
             }
 
+            // Comparison operations
+            Expr::EqualEqual(lhs, rhs) => self.gen_comparison(BinaryOp::Eq, lhs, rhs, false),
+            Expr::NotEqual(lhs, rhs) => self.gen_comparison(BinaryOp::Ne, lhs, rhs, false),
+            Expr::Less(lhs, rhs) => self.gen_comparison(BinaryOp::Lt, lhs, rhs, false),
+            Expr::LessEqual(lhs, rhs) => self.gen_comparison(BinaryOp::Le, lhs, rhs, false),
+            Expr::Greater(lhs, rhs) => self.gen_comparison(BinaryOp::Gt, lhs, rhs, false),
+            Expr::GreaterEqual(lhs, rhs) => self.gen_comparison(BinaryOp::Ge, lhs, rhs, false),
+
             // Logical operations (short-circuit evaluation)
             Expr::And(lhs, rhs) => self.generate_logical_and(lhs, rhs),
             Expr::Or(lhs, rhs) => self.generate_logical_or(lhs, rhs),
 
             // Assignment operations
             Expr::Equal(lhs, rhs) => {
-                if let Expr::Identifier(name) = lhs.as_ref() {
+                if let Expr::Accessor(kind, target, indices, span) = &**lhs {
+                    return self.gen_accessor_set(*kind, target, indices, rhs, span);
+                }
+                if let Some(target) = Target::of(lhs) {
+                    // The result is the value as stored, so `a = b = x > 0` gives `a`
+                    // the same value `b` holds whatever `b`'s type
                     let value = self.visit_expr_impl(rhs)?;
-                    self.store_variable(name, value)?;
-                    Ok(value)
+                    self.store_target(&target, value)
                 } else {
                     Err(IRGenError::InvalidOperation(
-                        "Assignment target must be a variable".to_string(),
+                        "Assignment target must be a variable or an instance field".to_string(),
                     ))
                 }
             }
             Expr::PlusEqual(lhs, rhs) => {
-                if let Expr::Identifier(name) = lhs.as_ref() {
-                    let current_value = self.load_variable(name)?;
+                if let Some(target) = Target::of(lhs) {
+                    let current_value = self.load_target(&target)?;
                     let rhs_value = self.visit_expr_impl(rhs)?;
                     let new_value = self.gen_binary_op(BinaryOp::Add, current_value, rhs_value)?;
-                    self.store_variable(name, new_value)?;
-                    Ok(new_value)
+                    self.store_target(&target, new_value)
                 } else {
                     Err(IRGenError::InvalidOperation(
-                        "Assignment target must be a variable".to_string(),
+                        "Assignment target must be a variable or an instance field".to_string(),
                     ))
                 }
             }
             Expr::MinusEqual(lhs, rhs) => {
-                if let Expr::Identifier(name) = lhs.as_ref() {
-                    let current_value = self.load_variable(name)?;
+                if let Some(target) = Target::of(lhs) {
+                    let current_value = self.load_target(&target)?;
                     let rhs_value = self.visit_expr_impl(rhs)?;
                     let new_value = self.gen_binary_op(BinaryOp::Sub, current_value, rhs_value)?;
-                    self.store_variable(name, new_value)?;
-                    Ok(new_value)
+                    self.store_target(&target, new_value)
                 } else {
                     Err(IRGenError::InvalidOperation(
-                        "Assignment target must be a variable".to_string(),
+                        "Assignment target must be a variable or an instance field".to_string(),
                     ))
                 }
             }
             Expr::StarEqual(lhs, rhs) => {
-                if let Expr::Identifier(name) = lhs.as_ref() {
-                    let current_value = self.load_variable(name)?;
+                if let Some(target) = Target::of(lhs) {
+                    let current_value = self.load_target(&target)?;
                     let rhs_value = self.visit_expr_impl(rhs)?;
                     let new_value = self.gen_binary_op(BinaryOp::Mul, current_value, rhs_value)?;
-                    self.store_variable(name, new_value)?;
-                    Ok(new_value)
+                    self.store_target(&target, new_value)
                 } else {
                     Err(IRGenError::InvalidOperation(
-                        "Assignment target must be a variable".to_string(),
+                        "Assignment target must be a variable or an instance field".to_string(),
                     ))
                 }
             }
             Expr::SlashEqual(lhs, rhs) => {
-                if let Expr::Identifier(name) = lhs.as_ref() {
-                    let current_value = self.load_variable(name)?;
+                if let Some(target) = Target::of(lhs) {
+                    let current_value = self.load_target(&target)?;
                     let rhs_value = self.visit_expr_impl(rhs)?;
-                    let new_value = self.gen_binary_op(BinaryOp::Div, current_value, rhs_value)?;
-                    self.store_variable(name, new_value)?;
-                    Ok(new_value)
+                    let new_value = self.gen_division(
+                        Division::Quotient,
+                        current_value,
+                        rhs_value,
+                        operation_span(lhs, rhs),
+                    )?;
+                    self.store_target(&target, new_value)
                 } else {
                     Err(IRGenError::InvalidOperation(
-                        "Assignment target must be a variable".to_string(),
+                        "Assignment target must be a variable or an instance field".to_string(),
                     ))
                 }
             }
 
             // Unary operations
             Expr::Not(expr) => {
-                let value = self.visit_expr_impl(expr)?;
+                let value = match without_parens(expr) {
+                    // `!!x` is the truthiness of `x`, with no need to negate it twice
+                    Expr::Not(inner) => {
+                        let value = self.visit_expr_impl(inner)?;
+                        return Ok(self.convert_to_bool(value)?.into());
+                    }
+                    operand => match comparison(operand) {
+                        Some((op, lhs, rhs)) => return self.gen_comparison(op, lhs, rhs, true),
+                        None => self.visit_expr_impl(expr)?,
+                    },
+                };
                 let bool_value = self.convert_to_bool(value)?;
                 let result = self.builder.build_not(bool_value, "not").map_err(|e| {
                     IRGenError::InvalidOperation(format!("Failed to build not: {}", e))
@@ -209,6 +325,10 @@ impl<'ctx> IRGenerator<'ctx> {
                 Ok(result.into())
             }
             Expr::Negative(expr) => {
+                // Signed literals such as `-5` and `- -5` are folded to a constant
+                if let Some(value) = signed_literal(expr) {
+                    return Ok(self.gen_number_const(-value).into());
+                }
                 let value = self.visit_expr_impl(expr)?;
                 match value {
                     BasicValueEnum::FloatValue(float_val) => {
@@ -241,54 +361,52 @@ impl<'ctx> IRGenerator<'ctx> {
 
             // Increment/Decrement operations
             Expr::PreIncrement(expr) => {
-                if let Expr::Identifier(name) = expr.as_ref() {
-                    let current_value = self.load_variable(name)?;
-                    let one = self.gen_number_const(1.0).into();
+                if let Some(target) = Target::of(expr) {
+                    let current_value = self.load_target(&target)?;
+                    let one = self.gen_one_like(current_value);
                     let new_value = self.gen_binary_op(BinaryOp::Add, current_value, one)?;
-                    self.store_variable(name, new_value)?;
-                    Ok(new_value) // Return new value for pre-increment
+                    self.store_target(&target, new_value)
                 } else {
                     Err(IRGenError::InvalidOperation(
-                        "Pre-increment only works on variables".to_string(),
+                        "Pre-increment only works on variables and instance fields".to_string(),
                     ))
                 }
             }
             Expr::PostIncrement(expr) => {
-                if let Expr::Identifier(name) = expr.as_ref() {
-                    let current_value = self.load_variable(name)?;
-                    let one = self.gen_number_const(1.0).into();
+                if let Some(target) = Target::of(expr) {
+                    let current_value = self.load_target(&target)?;
+                    let one = self.gen_one_like(current_value);
                     let new_value = self.gen_binary_op(BinaryOp::Add, current_value, one)?;
-                    self.store_variable(name, new_value)?;
+                    self.store_target(&target, new_value)?;
                     Ok(current_value) // Return old value for post-increment
                 } else {
                     Err(IRGenError::InvalidOperation(
-                        "Post-increment only works on variables".to_string(),
+                        "Post-increment only works on variables and instance fields".to_string(),
                     ))
                 }
             }
             Expr::PreDecrement(expr) => {
-                if let Expr::Identifier(name) = expr.as_ref() {
-                    let current_value = self.load_variable(name)?;
-                    let one = self.gen_number_const(1.0).into();
+                if let Some(target) = Target::of(expr) {
+                    let current_value = self.load_target(&target)?;
+                    let one = self.gen_one_like(current_value);
                     let new_value = self.gen_binary_op(BinaryOp::Sub, current_value, one)?;
-                    self.store_variable(name, new_value)?;
-                    Ok(new_value) // Return new value for pre-decrement
+                    self.store_target(&target, new_value)
                 } else {
                     Err(IRGenError::InvalidOperation(
-                        "Pre-decrement only works on variables".to_string(),
+                        "Pre-decrement only works on variables and instance fields".to_string(),
                     ))
                 }
             }
             Expr::PostDecrement(expr) => {
-                if let Expr::Identifier(name) = expr.as_ref() {
-                    let current_value = self.load_variable(name)?;
-                    let one = self.gen_number_const(1.0).into();
+                if let Some(target) = Target::of(expr) {
+                    let current_value = self.load_target(&target)?;
+                    let one = self.gen_one_like(current_value);
                     let new_value = self.gen_binary_op(BinaryOp::Sub, current_value, one)?;
-                    self.store_variable(name, new_value)?;
+                    self.store_target(&target, new_value)?;
                     Ok(current_value) // Return old value for post-decrement
                 } else {
                     Err(IRGenError::InvalidOperation(
-                        "Post-decrement only works on variables".to_string(),
+                        "Post-decrement only works on variables and instance fields".to_string(),
                     ))
                 }
             }
@@ -372,42 +490,128 @@ impl<'ctx> IRGenerator<'ctx> {
                 let r = self.visit_expr_impl(rhs)?;
                 self.gen_binary_op(BinaryOp::BitXor, l, r)
             }
+            Expr::ShiftLeft(lhs, rhs) => {
+                let l = self.visit_expr_impl(lhs)?;
+                let r = self.visit_expr_impl(rhs)?;
+                self.gen_binary_op(BinaryOp::Shl, l, r)
+            }
+            Expr::ShiftRight(lhs, rhs) => {
+                let l = self.visit_expr_impl(lhs)?;
+                let r = self.visit_expr_impl(rhs)?;
+                self.gen_binary_op(BinaryOp::Shr, l, r)
+            }
             Expr::Xor(lhs, rhs) => {
+                // Both sides are always evaluated, there is nothing to short-circuit
                 let l = self.visit_expr_impl(lhs)?;
+                let l = self.convert_to_bool(l)?;
                 let r = self.visit_expr_impl(rhs)?;
-                self.gen_binary_op(BinaryOp::Xor, l, r)
+                let r = self.convert_to_bool(r)?;
+                self.gen_binary_op(BinaryOp::Xor, l.into(), r.into())
             }
 
             Expr::PercentEqual(lhs, rhs) => {
-                if let Expr::Identifier(name) = lhs.as_ref() {
-                    let current_value = self.load_variable(name)?;
+                if let Some(target) = Target::of(lhs) {
+                    let current_value = self.load_target(&target)?;
                     let rhs_value = self.visit_expr_impl(rhs)?;
-                    let new_value = self.gen_binary_op(BinaryOp::Mod, current_value, rhs_value)?;
-                    self.store_variable(name, new_value)?;
-                    Ok(new_value)
+                    let new_value = self.gen_division(
+                        Division::Remainder,
+                        current_value,
+                        rhs_value,
+                        operation_span(lhs, rhs),
+                    )?;
+                    self.store_target(&target, new_value)
                 } else {
                     Err(IRGenError::InvalidOperation(
-                        "Assignment target must be a variable".to_string(),
+                        "Assignment target must be a variable or an instance field".to_string(),
                     ))
                 }
             }
         }
     }
 
-    /// Generate IR for binary operations
+    /// Generate `lhs op rhs` for a comparison operator, or `!(lhs op rhs)` if
+    /// `negated`, checking its operands for NaN under strict math
+    fn gen_comparison(
+        &mut self,
+        op: BinaryOp,
+        lhs: &Expr,
+        rhs: &Expr,
+        negated: bool,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        let l = self.visit_expr_impl(lhs)?;
+        let r = self.visit_expr_impl(rhs)?;
+        self.gen_nan_check(l, r, operation_span(lhs, rhs))?;
+        if !negated {
+            return self.gen_binary_op(op, l, r);
+        }
+        if let Some(inverted) = self.gen_inverted_compare(op, l, r)? {
+            return Ok(inverted);
+        }
+        let value = self.gen_binary_op(op, l, r)?;
+        let bool_value = self.convert_to_bool(value)?;
+        self.builder
+            .build_not(bool_value, "not")
+            .map(|v| v.into())
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build not: {}", e)))
+    }
+
+    /// Generate `!(lhs op rhs)` for a comparison of two numbers as one comparison with
+    /// the opposite predicate. The unordered predicates keep NaN operands giving
+    /// true, as negating the ordered comparison does. Returns `None` for other
+    /// operands, and under epsilon comparisons for the operators they change.
+    fn gen_inverted_compare(
+        &self,
+        op: BinaryOp,
+        lhs: BasicValueEnum<'ctx>,
+        rhs: BasicValueEnum<'ctx>,
+    ) -> IRGenResult<Option<BasicValueEnum<'ctx>>> {
+        let (BasicValueEnum::FloatValue(l), BasicValueEnum::FloatValue(r)) = (lhs, rhs) else {
+            return Ok(None);
+        };
+        let predicate = match op {
+            BinaryOp::Lt => inkwell::FloatPredicate::UGE,
+            BinaryOp::Gt => inkwell::FloatPredicate::ULE,
+            _ if self.epsilon_comparisons => return Ok(None),
+            BinaryOp::Le => inkwell::FloatPredicate::UGT,
+            BinaryOp::Ge => inkwell::FloatPredicate::ULT,
+            BinaryOp::Eq => inkwell::FloatPredicate::UNE,
+            BinaryOp::Ne => inkwell::FloatPredicate::UEQ,
+            _ => return Ok(None),
+        };
+        self.builder
+            .build_float_compare(predicate, l, r, "fcmp_not")
+            .map(|v| Some(v.into()))
+            .map_err(|e| IRGenError::InvalidOperation(format!("Float operation failed: {}", e)))
+    }
+
+    /// Generate IR for binary operations. Numbers compare with the ordered
+    /// predicates, so a comparison with NaN is false; see [`nan`](super::nan).
     pub fn gen_binary_op(
         &self,
         op: BinaryOp,
         lhs: BasicValueEnum<'ctx>,
         rhs: BasicValueEnum<'ctx>,
     ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        if let Some(result) = self.gen_null_operation(op, lhs, rhs)? {
+            return Ok(result);
+        }
         match (lhs, rhs) {
             (BasicValueEnum::FloatValue(l), BasicValueEnum::FloatValue(r)) => {
+                if self.epsilon_comparisons
+                    && matches!(
+                        op,
+                        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Le | BinaryOp::Ge
+                    )
+                {
+                    return self.gen_epsilon_compare(op, l, r);
+                }
                 let result = match op {
                     BinaryOp::Add => self.builder.build_float_add(l, r, "fadd").map(|v| v.into()),
                     BinaryOp::Sub => self.builder.build_float_sub(l, r, "fsub").map(|v| v.into()),
                     BinaryOp::Mul => self.builder.build_float_mul(l, r, "fmul").map(|v| v.into()),
                     BinaryOp::Div => self.builder.build_float_div(l, r, "fdiv").map(|v| v.into()),
+                    // Truncating, with the sign of `l` as in GameMaker and as enum
+                    // values fold with Rust's `%`; see `factor` in the grammar
                     BinaryOp::Mod => self.builder.build_float_rem(l, r, "frem").map(|v| v.into()),
                     BinaryOp::Eq => self
                         .builder
@@ -434,7 +638,11 @@ impl<'ctx> IRGenerator<'ctx> {
                         .build_float_compare(inkwell::FloatPredicate::OGE, l, r, "fge")
                         .map(|v| v.into()),
                     // For bitwise operations on floats, convert to int, operate, then convert back
-                    BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor => {
+                    BinaryOp::BitAnd
+                    | BinaryOp::BitOr
+                    | BinaryOp::BitXor
+                    | BinaryOp::Shl
+                    | BinaryOp::Shr => {
                         let l_int = self
                             .builder
                             .build_float_to_signed_int(l, self.type_mapping.get_int_type(), "f2i_l")
@@ -454,15 +662,7 @@ impl<'ctx> IRGenerator<'ctx> {
                                 ))
                             })?;
 
-                        let int_result = match op {
-                            BinaryOp::BitAnd => self.builder.build_and(l_int, r_int, "ibitand"),
-                            BinaryOp::BitOr => self.builder.build_or(l_int, r_int, "ibitor"),
-                            BinaryOp::BitXor => self.builder.build_xor(l_int, r_int, "ibitxor"),
-                            _ => unreachable!(),
-                        }
-                        .map_err(|e| {
-                            IRGenError::InvalidOperation(format!("Bitwise operation failed: {}", e))
-                        })?;
+                        let int_result = self.gen_int_bitwise(op, l_int, r_int)?;
 
                         let float_result = self
                             .builder
@@ -496,17 +696,22 @@ impl<'ctx> IRGenerator<'ctx> {
                 let l_is_bool = l.get_type() == self.type_mapping.get_bool_type();
                 let r_is_bool = r.get_type() == self.type_mapping.get_bool_type();
 
-                if (l_is_bool || r_is_bool)
-                    && matches!(
-                        op,
-                        BinaryOp::Add
-                            | BinaryOp::Sub
-                            | BinaryOp::Mul
-                            | BinaryOp::Div
-                            | BinaryOp::Mod
-                    )
-                {
-                    // Convert booleans to floats for arithmetic operations
+                // Two booleans can be compared for (in)equality and combined bitwise as i1,
+                // but ordering an i1 is signed (true is -1), so everything else goes through
+                // the number representation where true is 1 and false is 0
+                let bool_only_op = matches!(
+                    op,
+                    BinaryOp::Eq
+                        | BinaryOp::Ne
+                        | BinaryOp::And
+                        | BinaryOp::Or
+                        | BinaryOp::Xor
+                        | BinaryOp::BitAnd
+                        | BinaryOp::BitOr
+                        | BinaryOp::BitXor
+                );
+                if (l_is_bool || r_is_bool) && !(l_is_bool && r_is_bool && bool_only_op) {
+                    // Convert booleans to floats
                     let l_float = if l_is_bool {
                         let true_val = self.type_mapping.get_number_type().const_float(1.0);
                         let false_val = self.type_mapping.get_number_type().const_float(0.0);
@@ -564,18 +769,38 @@ impl<'ctx> IRGenerator<'ctx> {
                     return self.gen_binary_op(op, l_float, r_float);
                 }
 
+                // `/` and `%` are real operations even on int64 values, which also keeps
+                // a zero divisor from trapping
+                if matches!(op, BinaryOp::Div | BinaryOp::Mod) {
+                    let number_type = self.type_mapping.get_number_type();
+                    let l_float = self
+                        .builder
+                        .build_signed_int_to_float(l, number_type, "int_to_float")
+                        .map_err(|e| {
+                            IRGenError::InvalidOperation(format!(
+                                "Int to float conversion failed: {}",
+                                e
+                            ))
+                        })?;
+                    let r_float = self
+                        .builder
+                        .build_signed_int_to_float(r, number_type, "int_to_float")
+                        .map_err(|e| {
+                            IRGenError::InvalidOperation(format!(
+                                "Int to float conversion failed: {}",
+                                e
+                            ))
+                        })?;
+                    return self.gen_binary_op(op, l_float.into(), r_float.into());
+                }
+                if op.is_bitwise() {
+                    return Ok(self.gen_int_bitwise(op, l, r)?.into());
+                }
+
                 let result = match op {
                     BinaryOp::Add => self.builder.build_int_add(l, r, "iadd").map(|v| v.into()),
                     BinaryOp::Sub => self.builder.build_int_sub(l, r, "isub").map(|v| v.into()),
                     BinaryOp::Mul => self.builder.build_int_mul(l, r, "imul").map(|v| v.into()),
-                    BinaryOp::Div => self
-                        .builder
-                        .build_int_signed_div(l, r, "idiv")
-                        .map(|v| v.into()),
-                    BinaryOp::Mod => self
-                        .builder
-                        .build_int_signed_rem(l, r, "irem")
-                        .map(|v| v.into()),
                     BinaryOp::Eq => self
                         .builder
                         .build_int_compare(inkwell::IntPredicate::EQ, l, r, "ieq")
@@ -603,14 +828,31 @@ impl<'ctx> IRGenerator<'ctx> {
                     BinaryOp::And => self.builder.build_and(l, r, "iand").map(|v| v.into()),
                     BinaryOp::Or => self.builder.build_or(l, r, "ior").map(|v| v.into()),
                     BinaryOp::Xor => self.builder.build_xor(l, r, "ixor").map(|v| v.into()),
-                    BinaryOp::BitAnd => self.builder.build_and(l, r, "ibitand").map(|v| v.into()),
-                    BinaryOp::BitOr => self.builder.build_or(l, r, "ibitor").map(|v| v.into()),
-                    BinaryOp::BitXor => self.builder.build_xor(l, r, "ibitxor").map(|v| v.into()),
+                    BinaryOp::Div
+                    | BinaryOp::Mod
+                    | BinaryOp::BitAnd
+                    | BinaryOp::BitOr
+                    | BinaryOp::BitXor
+                    | BinaryOp::Shl
+                    | BinaryOp::Shr => unreachable!("handled above"),
                 };
                 result.map_err(|e| {
                     IRGenError::InvalidOperation(format!("Int operation failed: {}", e))
                 })
             }
+            // Bitwise operators keep an int64 operand in the integer domain
+            (BasicValueEnum::IntValue(l), BasicValueEnum::FloatValue(r))
+                if op.is_bitwise() && l.get_type() == self.type_mapping.get_int64_type() =>
+            {
+                let r_int = self.gen_to_int64(r.into())?;
+                self.gen_binary_op(op, l.into(), r_int.into())
+            }
+            (BasicValueEnum::FloatValue(l), BasicValueEnum::IntValue(r))
+                if op.is_bitwise() && r.get_type() == self.type_mapping.get_int64_type() =>
+            {
+                let l_int = self.gen_to_int64(l.into())?;
+                self.gen_binary_op(op, l_int.into(), r.into())
+            }
             // Handle mixed int/float operations by promoting int to float
             (BasicValueEnum::IntValue(l), BasicValueEnum::FloatValue(r)) => {
                 // Check if left operand is boolean and convert accordingly
@@ -672,12 +914,88 @@ impl<'ctx> IRGenerator<'ctx> {
                 };
                 self.gen_binary_op(op, l.into(), r_float)
             }
+            // Strings concatenate with `+` and compare bytewise. A string variable may
+            // hold null, which equals null only and is an error with any other operator.
+            (BasicValueEnum::PointerValue(l), BasicValueEnum::PointerValue(r)) => {
+                let predicate = match op {
+                    BinaryOp::Add => {
+                        self.gen_string_null_check(op, l, r)?;
+                        return self.gen_string_concat(l, r);
+                    }
+                    BinaryOp::Eq => inkwell::IntPredicate::EQ,
+                    BinaryOp::Ne => inkwell::IntPredicate::NE,
+                    BinaryOp::Lt => inkwell::IntPredicate::SLT,
+                    BinaryOp::Le => inkwell::IntPredicate::SLE,
+                    BinaryOp::Gt => inkwell::IntPredicate::SGT,
+                    BinaryOp::Ge => inkwell::IntPredicate::SGE,
+                    _ => {
+                        return Err(IRGenError::TypeMismatch(format!(
+                            "Unsupported string operation: {:?}",
+                            op
+                        )));
+                    }
+                };
+                let (either_null, both_null) = self.gen_string_null_check(op, l, r)?;
+                let ordering = self.gen_string_compare(l, r)?.into_int_value();
+                let zero = self.type_mapping.get_int_type().const_zero();
+                let comparison_error = |e: inkwell::builder::BuilderError| {
+                    IRGenError::InvalidOperation(format!("String comparison failed: {}", e))
+                };
+                let compared = self
+                    .builder
+                    .build_int_compare(predicate, ordering, zero, "strcmp")
+                    .map_err(comparison_error)?;
+                let null_result = match op {
+                    BinaryOp::Eq => both_null,
+                    BinaryOp::Ne => self
+                        .builder
+                        .build_not(both_null, "not_both_null")
+                        .map_err(comparison_error)?,
+                    // Any other comparison raised an error for null already
+                    _ => return Ok(compared.into()),
+                };
+                self.builder
+                    .build_select(either_null, null_result, compared, "null_or_strcmp")
+                    .map_err(comparison_error)
+            }
             _ => Err(IRGenError::TypeMismatch(
                 "Incompatible types for binary operation".to_string(),
             )),
         }
     }
 
+    /// Apply a bitwise or shift operator to two integers of the same width. Shift
+    /// counts wrap at the width, so on 32 bits `x << 33` shifts by 1.
+    fn gen_int_bitwise(
+        &self,
+        op: BinaryOp,
+        l: IntValue<'ctx>,
+        r: IntValue<'ctx>,
+    ) -> IRGenResult<IntValue<'ctx>> {
+        let result = match op {
+            BinaryOp::BitAnd => self.builder.build_and(l, r, "ibitand"),
+            BinaryOp::BitOr => self.builder.build_or(l, r, "ibitor"),
+            BinaryOp::BitXor => self.builder.build_xor(l, r, "ibitxor"),
+            BinaryOp::Shl | BinaryOp::Shr => {
+                let int_type = l.get_type();
+                let mask = int_type.const_int(u64::from(int_type.get_bit_width() - 1), false);
+                let count = self
+                    .builder
+                    .build_and(r, mask, "shift_count")
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("Bitwise operation failed: {}", e))
+                    })?;
+                if matches!(op, BinaryOp::Shl) {
+                    self.builder.build_left_shift(l, count, "ishl")
+                } else {
+                    self.builder.build_right_shift(l, count, true, "ishr")
+                }
+            }
+            _ => unreachable!("not a bitwise operator: {:?}", op),
+        };
+        result.map_err(|e| IRGenError::InvalidOperation(format!("Bitwise operation failed: {}", e)))
+    }
+
     fn generate_logical_and(
         &mut self,
         lhs: &Expr,
@@ -807,6 +1125,17 @@ impl<'ctx> IRGenerator<'ctx> {
             .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build branch: {}", e)))?;
         let else_end_block = self.builder.get_insert_block().unwrap();
 
+        // Branches giving an int64 or bool and a number meet as numbers, each
+        // converted at the end of its own block
+        let (then_value, else_value) = if then_value.get_type() != else_value.get_type() {
+            (
+                self.convert_before_branch(then_value, then_end_block)?,
+                self.convert_before_branch(else_value, else_end_block)?,
+            )
+        } else {
+            (then_value, else_value)
+        };
+
         // Merge block
         self.builder.position_at_end(merge_block);
 
@@ -823,11 +1152,47 @@ impl<'ctx> IRGenerator<'ctx> {
         }
     }
 
-    /// Get a function by name
-    fn get_function(&self, name: &str) -> IRGenResult<FunctionValue<'ctx>> {
-        self.functions
-            .get(name)
-            .copied()
-            .ok_or_else(|| IRGenError::UndefinedFunction(name.to_string()))
+    /// Convert `value` to a number just before the branch ending `block`
+    fn convert_before_branch(
+        &self,
+        value: BasicValueEnum<'ctx>,
+        block: inkwell::basic_block::BasicBlock<'ctx>,
+    ) -> IRGenResult<BasicValueEnum<'ctx>> {
+        if let Some(branch) = block.get_terminator() {
+            self.builder.position_before(&branch);
+        }
+        self.convert_to_return_type(value)
+    }
+}
+
+/// `expr` without the parentheses around it
+fn without_parens(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(inner) => without_parens(inner),
+        _ => expr,
+    }
+}
+
+/// The operator and operands of a comparison
+fn comparison(expr: &Expr) -> Option<(BinaryOp, &Expr, &Expr)> {
+    let (op, lhs, rhs) = match expr {
+        Expr::EqualEqual(lhs, rhs) => (BinaryOp::Eq, lhs, rhs),
+        Expr::NotEqual(lhs, rhs) => (BinaryOp::Ne, lhs, rhs),
+        Expr::Less(lhs, rhs) => (BinaryOp::Lt, lhs, rhs),
+        Expr::LessEqual(lhs, rhs) => (BinaryOp::Le, lhs, rhs),
+        Expr::Greater(lhs, rhs) => (BinaryOp::Gt, lhs, rhs),
+        Expr::GreaterEqual(lhs, rhs) => (BinaryOp::Ge, lhs, rhs),
+        _ => return None,
+    };
+    Some((op, lhs.as_ref(), rhs.as_ref()))
+}
+
+/// The value of a number literal under any number of signs and parentheses
+fn signed_literal(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Number(n, _) => Some(*n),
+        Expr::Negative(operand) => signed_literal(operand).map(|n| -n),
+        Expr::Positive(operand) | Expr::Paren(operand) => signed_literal(operand),
+        _ => None,
     }
 }
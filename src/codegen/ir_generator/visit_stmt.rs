@@ -1,34 +1,73 @@
-use crate::codegen::ir_generator::{IRGenError, IRGenResult, IRGenerator};
+use crate::codegen::ir_generator::debug_info::stmt_start;
+use crate::codegen::ir_generator::{
+    IRGenError, IRGenResult, IRGenerator, UNREACHABLE_CODE, with_stack,
+};
+use crate::parser::Span;
 use crate::parser::stmt::Stmt;
+use crate::utils::diagnostic::{Diagnostic, Severity};
+use inkwell::basic_block::BasicBlock;
 use inkwell::values::BasicValueEnum;
 
 impl<'ctx> IRGenerator<'ctx> {
     pub fn visit_stmt_impl(&mut self, stmt: &Stmt) -> IRGenResult<BasicValueEnum<'ctx>> {
+        with_stack(|| self.gen_stmt(stmt))
+    }
+
+    fn gen_stmt(&mut self, stmt: &Stmt) -> IRGenResult<BasicValueEnum<'ctx>> {
+        self.set_debug_location(stmt);
+        self.gen_trace_statement(stmt)?;
         match stmt {
             Stmt::Expr(expr) => self.visit_expr_impl(expr),
 
             Stmt::Var(vars) => {
                 let mut last_value = self.gen_number_const(0.0).into();
-                for (name, init_expr) in vars {
+                // Declarations run left to right, each initializer before its own
+                // variable exists: in `var a = a;` the initializer reads the `a` already
+                // in scope, if any
+                for (name, init_expr, _) in vars {
                     let value = if let Some(expr) = init_expr {
                         self.visit_expr_impl(expr)?
                     } else {
                         self.gen_number_const(0.0).into()
                     };
+                    let value = self.declared_value(name, init_expr.as_ref(), value)?;
+
+                    if self.persistent_globals && self.in_top_level() {
+                        let global = self.declare_script_global(name).as_pointer_value();
+                        let value = self.convert_to_global_value(name, value)?;
+                        self.builder.build_store(global, value).map_err(|e| {
+                            IRGenError::InvalidOperation(format!(
+                                "Failed to store global '{}': {}",
+                                name, e
+                            ))
+                        })?;
+                        last_value = value;
+                        continue;
+                    }
 
-                    let alloca = self.declare_variable(name, self.get_value_type(value))?;
+                    // `var` is function-scoped, so declaring a variable again with the
+                    // same type keeps using its slot; code already generated for it,
+                    // such as a loop condition, must see the new value
+                    let value_type = self.get_value_type(value);
+                    let alloca = match self.variables.get(name) {
+                        Some(&alloca) if self.variable_types.get(name) == Some(&value_type) => {
+                            alloca
+                        }
+                        _ => self.declare_variable(name, value_type)?,
+                    };
                     self.builder.build_store(alloca, value).map_err(|e| {
                         IRGenError::InvalidOperation(format!(
                             "Failed to store variable '{}': {}",
                             name, e
                         ))
                     })?;
+                    self.note_callable(name, init_expr.as_ref());
                     last_value = value;
                 }
                 Ok(last_value)
             }
 
-            Stmt::If(cond, then_stmt, else_stmt) => {
+            Stmt::If(cond, then_stmt, else_stmt, _) => {
                 let cond_value = self.visit_expr_impl(cond)?;
 
                 let current_fn = self.current_function.ok_or_else(|| {
@@ -132,18 +171,21 @@ impl<'ctx> IRGenerator<'ctx> {
                 }
             }
 
-            Stmt::Block(stmts) => {
+            Stmt::Block(stmts, _) => {
+                // Functions defined in this block go out of scope with it
+                let saved_functions = self.functions.clone();
                 let mut last_value = self.gen_number_const(0.0).into();
+                let mut previous = None;
                 for stmt in stmts {
-                    // Check if current block already has a terminator
-                    if let Some(current_block) = self.builder.get_insert_block() {
-                        if current_block.get_terminator().is_some() {
-                            // Current block is terminated, skip remaining statements
-                            break;
-                        }
+                    // Statements after one that never completes are skipped
+                    if let (true, Some(previous)) = (self.is_terminated(), previous) {
+                        self.warn_unreachable(previous, stmt);
+                        break;
                     }
                     last_value = self.visit_stmt_impl(stmt)?;
+                    previous = Some(stmt);
                 }
+                self.functions = saved_functions;
                 Ok(last_value)
             }
 
@@ -161,39 +203,75 @@ impl<'ctx> IRGenerator<'ctx> {
             }
 
             Stmt::Break => {
-                // Break statement - should only be used in loops
-                // For now, we'll generate an unreachable instruction
-                self.builder.build_unreachable().map_err(|e| {
-                    IRGenError::InvalidOperation(format!("Failed to build break: {}", e))
+                let &(exit_block, _) = self.loop_targets.last().ok_or_else(|| {
+                    IRGenError::InvalidOperation("'break' outside of a loop".to_string())
                 })?;
+                self.builder
+                    .build_unconditional_branch(exit_block)
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("Failed to build break: {}", e))
+                    })?;
                 Ok(self.gen_number_const(0.0).into())
             }
 
             Stmt::Continue => {
-                // Continue statement - should only be used in loops
-                // For now, we'll generate an unreachable instruction
-                self.builder.build_unreachable().map_err(|e| {
-                    IRGenError::InvalidOperation(format!("Failed to build continue: {}", e))
-                })?;
+                let next_block = self
+                    .loop_targets
+                    .last()
+                    .and_then(|&(_, next_block)| next_block)
+                    .ok_or_else(|| {
+                        IRGenError::InvalidOperation("'continue' outside of a loop".to_string())
+                    })?;
+                self.builder
+                    .build_unconditional_branch(next_block)
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("Failed to build continue: {}", e))
+                    })?;
                 Ok(self.gen_number_const(0.0).into())
             }
 
-            Stmt::While(cond, body) => self.generate_while_loop(cond, body),
+            Stmt::While(cond, body, _) => self.generate_while_loop(cond, body),
 
-            Stmt::DoUntil(body, cond) => self.generate_do_until_loop(body, cond),
+            Stmt::DoUntil(body, cond, _) => self.generate_do_until_loop(body, cond),
 
-            Stmt::Repeat(count_expr, body) => self.generate_repeat_loop(count_expr, body),
+            Stmt::Repeat(count_expr, body, _) => self.generate_repeat_loop(count_expr, body),
 
-            Stmt::For(init, cond, update, body) => {
+            Stmt::For(init, cond, update, body, _) => {
                 // Generate for loop with correct parameter types
                 let init_as_ref = init.as_deref();
                 let cond_as_ref = cond.as_deref();
                 let update_as_ref = update.as_deref();
                 self.generate_for_loop(init_as_ref, cond_as_ref, update_as_ref, body)
             }
+
+            Stmt::Switch(value, cases, _) => self.generate_switch(value, cases),
+
+            Stmt::Function(func_def) => {
+                self.gen_nested_function(func_def)?;
+                Ok(self.gen_number_const(0.0).into())
+            }
+
+            Stmt::Error(span) => Err(IRGenError::SyntaxErrors(vec![span.clone()])),
         }
     }
 
+    /// Generate a loop body, with `break` jumping to `exit_block` and `continue`
+    /// to `next_block`
+    fn gen_loop_body(
+        &mut self,
+        body: &Stmt,
+        exit_block: BasicBlock<'ctx>,
+        next_block: BasicBlock<'ctx>,
+    ) -> IRGenResult<()> {
+        self.loop_targets.push((exit_block, Some(next_block)));
+        self.loop_depth += 1;
+        self.max_loop_depth = self.max_loop_depth.max(self.loop_depth);
+        let result = self.visit_stmt_impl(body);
+        self.loop_depth -= 1;
+        self.loop_targets.pop();
+        result.map(|_| ())
+    }
+
     fn generate_while_loop(
         &mut self,
         cond: &crate::parser::expr::Expr,
@@ -202,17 +280,25 @@ impl<'ctx> IRGenerator<'ctx> {
         let current_fn = self.current_function.ok_or_else(|| {
             IRGenError::InvalidOperation("While loop outside function".to_string())
         })?;
-
-        let cond_block = self.context.append_basic_block(current_fn, "while_cond");
-        let body_block = self.context.append_basic_block(current_fn, "while_body");
-        let exit_block = self.context.append_basic_block(current_fn, "while_exit");
+        let id = self.next_loop_id();
+
+        let cond_block = self
+            .context
+            .append_basic_block(current_fn, &format!("while_cond.{}", id));
+        let body_block = self
+            .context
+            .append_basic_block(current_fn, &format!("while_body.{}", id));
+        let exit_block = self
+            .context
+            .append_basic_block(current_fn, &format!("while_exit.{}", id));
 
         // Jump to condition block
         self.builder
             .build_unconditional_branch(cond_block)
             .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build branch: {}", e)))?;
 
-        // Generate condition block
+        // Generate condition block. The condition is emitted here, never hoisted, so its
+        // side effects (i++, calls) happen on every iteration.
         self.builder.position_at_end(cond_block);
         let cond_value = self.visit_expr_impl(cond)?;
         let cond_i1 = self.convert_to_bool(cond_value)?;
@@ -225,7 +311,7 @@ impl<'ctx> IRGenerator<'ctx> {
 
         // Generate body block
         self.builder.position_at_end(body_block);
-        self.visit_stmt_impl(body)?;
+        self.gen_loop_body(body, exit_block, cond_block)?;
 
         // Jump back to condition (if no terminator)
         if let Some(current_block) = self.builder.get_insert_block() {
@@ -251,10 +337,17 @@ impl<'ctx> IRGenerator<'ctx> {
         let current_fn = self.current_function.ok_or_else(|| {
             IRGenError::InvalidOperation("Do-until loop outside function".to_string())
         })?;
-
-        let body_block = self.context.append_basic_block(current_fn, "do_body");
-        let cond_block = self.context.append_basic_block(current_fn, "do_cond");
-        let exit_block = self.context.append_basic_block(current_fn, "do_exit");
+        let id = self.next_loop_id();
+
+        let body_block = self
+            .context
+            .append_basic_block(current_fn, &format!("do_body.{}", id));
+        let cond_block = self
+            .context
+            .append_basic_block(current_fn, &format!("do_cond.{}", id));
+        let exit_block = self
+            .context
+            .append_basic_block(current_fn, &format!("do_exit.{}", id));
 
         // Jump to body block first
         self.builder
@@ -263,7 +356,7 @@ impl<'ctx> IRGenerator<'ctx> {
 
         // Generate body block
         self.builder.position_at_end(body_block);
-        self.visit_stmt_impl(body)?;
+        self.gen_loop_body(body, exit_block, cond_block)?;
 
         // Jump to condition (if no terminator)
         if let Some(current_block) = self.builder.get_insert_block() {
@@ -280,53 +373,12 @@ impl<'ctx> IRGenerator<'ctx> {
         self.builder.position_at_end(cond_block);
         let cond_value = self.visit_expr_impl(cond)?;
 
-        // Convert condition to i1 if needed (note: until means loop while NOT condition)
-        let cond_i1 = match cond_value {
-            BasicValueEnum::IntValue(int_val) => {
-                let bool_val = if int_val.get_type() == self.type_mapping.get_bool_type() {
-                    int_val
-                } else {
-                    self.builder
-                        .build_int_compare(
-                            inkwell::IntPredicate::NE,
-                            int_val,
-                            int_val.get_type().const_zero(),
-                            "tobool",
-                        )
-                        .map_err(|e| {
-                            IRGenError::InvalidOperation(format!(
-                                "Failed to convert to bool: {}",
-                                e
-                            ))
-                        })?
-                };
-                // Invert for "until" semantics
-                self.builder.build_not(bool_val, "not").map_err(|e| {
-                    IRGenError::InvalidOperation(format!("Failed to build not: {}", e))
-                })?
-            }
-            BasicValueEnum::FloatValue(float_val) => {
-                // until condition is true, so continue while condition is false
-                self.builder
-                    .build_float_compare(
-                        inkwell::FloatPredicate::OEQ, // Equal to zero means false, continue
-                        float_val,
-                        float_val.get_type().const_zero(),
-                        "until_cond",
-                    )
-                    .map_err(|e| {
-                        IRGenError::InvalidOperation(format!(
-                            "Failed to convert float to bool: {}",
-                            e
-                        ))
-                    })?
-            }
-            _ => {
-                return Err(IRGenError::TypeMismatch(
-                    "Invalid condition type".to_string(),
-                ));
-            }
-        };
+        // "until" loops while the condition is false, so invert its truth value
+        let cond_bool = self.convert_to_bool(cond_value)?;
+        let cond_i1 = self
+            .builder
+            .build_not(cond_bool, "until_cond")
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build not: {}", e)))?;
 
         self.builder
             .build_conditional_branch(cond_i1, body_block, exit_block)
@@ -347,23 +399,84 @@ impl<'ctx> IRGenerator<'ctx> {
         let current_fn = self.current_function.ok_or_else(|| {
             IRGenError::InvalidOperation("Repeat loop outside function".to_string())
         })?;
+        if let Some(count) = self.unrolled_repeat_count(count_expr, body) {
+            for _ in 0..count {
+                if self.is_terminated() {
+                    break;
+                }
+                self.visit_stmt_impl(body)?;
+            }
+            return Ok(self.gen_number_const(0.0).into());
+        }
+        let id = self.next_loop_id();
 
-        // Generate the count value
+        // Generate the count value; it is evaluated exactly once, before the loop
         let count_value = self.visit_expr_impl(count_expr)?;
+        let count_value = self.convert_to_return_type(count_value)?;
 
-        // Convert to integer if it's a float
         let count_int = match count_value {
             BasicValueEnum::IntValue(int_val) => int_val,
-            BasicValueEnum::FloatValue(float_val) => self
-                .builder
-                .build_float_to_signed_int(
-                    float_val,
-                    self.type_mapping.get_int_type(),
-                    "repeat_count",
-                )
-                .map_err(|e| {
-                    IRGenError::InvalidOperation(format!("Failed to convert float to int: {}", e))
-                })?,
+            BasicValueEnum::FloatValue(float_val) => {
+                // Counts <= 0 and NaN skip the body, and the upper bound keeps the conversion
+                // below in range. Fractional counts truncate toward zero.
+                let number_type = self.type_mapping.get_number_type();
+                let is_positive = self
+                    .builder
+                    .build_float_compare(
+                        inkwell::FloatPredicate::OGT,
+                        float_val,
+                        number_type.const_zero(),
+                        "repeat_positive",
+                    )
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("Failed to compare count: {}", e))
+                    })?;
+                let clamped = self
+                    .builder
+                    .build_select(
+                        is_positive,
+                        float_val,
+                        number_type.const_zero(),
+                        "repeat_clamped",
+                    )
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("Failed to clamp count: {}", e))
+                    })?
+                    .into_float_value();
+                let max_count = number_type.const_float(i64::MAX as f64);
+                let in_range = self
+                    .builder
+                    .build_float_compare(
+                        inkwell::FloatPredicate::OLT,
+                        clamped,
+                        max_count,
+                        "repeat_in_range",
+                    )
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("Failed to compare count: {}", e))
+                    })?;
+                let int_type = self.type_mapping.get_int_type();
+                let converted = self
+                    .builder
+                    .build_float_to_signed_int(clamped, int_type, "repeat_count")
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!(
+                            "Failed to convert float to int: {}",
+                            e
+                        ))
+                    })?;
+                self.builder
+                    .build_select(
+                        in_range,
+                        converted,
+                        int_type.const_int(i64::MAX as u64, false),
+                        "repeat_count",
+                    )
+                    .map_err(|e| {
+                        IRGenError::InvalidOperation(format!("Failed to clamp count: {}", e))
+                    })?
+                    .into_int_value()
+            }
             _ => {
                 return Err(IRGenError::TypeMismatch(
                     "Repeat count must be numeric".to_string(),
@@ -373,8 +486,11 @@ impl<'ctx> IRGenerator<'ctx> {
 
         // Allocate counter variable
         let counter_alloca = self
-            .builder
-            .build_alloca(self.type_mapping.get_int_type(), "repeat_counter")
+            .entry_block_builder()
+            .build_alloca(
+                self.type_mapping.get_int_type(),
+                &format!("repeat_counter.{}", id),
+            )
             .map_err(|e| {
                 IRGenError::InvalidOperation(format!("Failed to allocate counter: {}", e))
             })?;
@@ -383,9 +499,15 @@ impl<'ctx> IRGenerator<'ctx> {
             .build_store(counter_alloca, zero)
             .map_err(|e| IRGenError::InvalidOperation(format!("Failed to store counter: {}", e)))?;
 
-        let cond_block = self.context.append_basic_block(current_fn, "repeat_cond");
-        let body_block = self.context.append_basic_block(current_fn, "repeat_body");
-        let exit_block = self.context.append_basic_block(current_fn, "repeat_exit");
+        let cond_block = self
+            .context
+            .append_basic_block(current_fn, &format!("repeat_cond.{}", id));
+        let body_block = self
+            .context
+            .append_basic_block(current_fn, &format!("repeat_body.{}", id));
+        let exit_block = self
+            .context
+            .append_basic_block(current_fn, &format!("repeat_exit.{}", id));
 
         // Jump to condition block
         self.builder
@@ -422,38 +544,33 @@ impl<'ctx> IRGenerator<'ctx> {
                 IRGenError::InvalidOperation(format!("Failed to build conditional branch: {}", e))
             })?;
 
-        // Generate body block
+        // Generate body block. The iteration is counted before the body runs, so
+        // `continue` can go straight back to the condition
         self.builder.position_at_end(body_block);
-        self.visit_stmt_impl(body)?;
+        let current_counter = self
+            .builder
+            .build_load(self.type_mapping.get_int_type(), counter_alloca, "counter")
+            .map_err(|e| IRGenError::InvalidOperation(format!("Failed to load counter: {}", e)))?;
+
+        if let BasicValueEnum::IntValue(counter_val) = current_counter {
+            let one = self.type_mapping.get_int_type().const_int(1, false);
+            let incremented = self
+                .builder
+                .build_int_add(counter_val, one, "inc_counter")
+                .map_err(|e| {
+                    IRGenError::InvalidOperation(format!("Failed to increment counter: {}", e))
+                })?;
+            self.builder
+                .build_store(counter_alloca, incremented)
+                .map_err(|e| {
+                    IRGenError::InvalidOperation(format!("Failed to store counter: {}", e))
+                })?;
+        }
+        self.gen_loop_body(body, exit_block, cond_block)?;
 
-        // Increment counter (if no terminator)
+        // Jump back to condition (if no terminator)
         if let Some(current_block) = self.builder.get_insert_block() {
             if current_block.get_terminator().is_none() {
-                let current_counter = self
-                    .builder
-                    .build_load(self.type_mapping.get_int_type(), counter_alloca, "counter")
-                    .map_err(|e| {
-                        IRGenError::InvalidOperation(format!("Failed to load counter: {}", e))
-                    })?;
-
-                if let BasicValueEnum::IntValue(counter_val) = current_counter {
-                    let one = self.type_mapping.get_int_type().const_int(1, false);
-                    let incremented = self
-                        .builder
-                        .build_int_add(counter_val, one, "inc_counter")
-                        .map_err(|e| {
-                            IRGenError::InvalidOperation(format!(
-                                "Failed to increment counter: {}",
-                                e
-                            ))
-                        })?;
-                    self.builder
-                        .build_store(counter_alloca, incremented)
-                        .map_err(|e| {
-                            IRGenError::InvalidOperation(format!("Failed to store counter: {}", e))
-                        })?;
-                }
-
                 self.builder
                     .build_unconditional_branch(cond_block)
                     .map_err(|e| {
@@ -477,23 +594,32 @@ impl<'ctx> IRGenerator<'ctx> {
         let current_fn = self
             .current_function
             .ok_or_else(|| IRGenError::InvalidOperation("For loop outside function".to_string()))?;
+        let id = self.next_loop_id();
 
         // Execute initialization if present
         if let Some(init_stmt) = init {
             self.visit_stmt_impl(init_stmt)?;
         }
 
-        let cond_block = self.context.append_basic_block(current_fn, "for_cond");
-        let body_block = self.context.append_basic_block(current_fn, "for_body");
-        let update_block = self.context.append_basic_block(current_fn, "for_update");
-        let exit_block = self.context.append_basic_block(current_fn, "for_exit");
+        let cond_block = self
+            .context
+            .append_basic_block(current_fn, &format!("for_cond.{}", id));
+        let body_block = self
+            .context
+            .append_basic_block(current_fn, &format!("for_body.{}", id));
+        let update_block = self
+            .context
+            .append_basic_block(current_fn, &format!("for_update.{}", id));
+        let exit_block = self
+            .context
+            .append_basic_block(current_fn, &format!("for_exit.{}", id));
 
         // Jump to condition block
         self.builder
             .build_unconditional_branch(cond_block)
             .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build branch: {}", e)))?;
 
-        // Generate condition block
+        // Generate condition block; re-evaluated on every iteration, like the while condition
         self.builder.position_at_end(cond_block);
         let continue_loop = if let Some(cond_expr) = cond {
             let cond_value = self.visit_expr_impl(cond_expr)?;
@@ -511,7 +637,7 @@ impl<'ctx> IRGenerator<'ctx> {
 
         // Generate body block
         self.builder.position_at_end(body_block);
-        self.visit_stmt_impl(body)?;
+        self.gen_loop_body(body, exit_block, update_block)?;
 
         // Jump to update (if no terminator)
         if let Some(current_block) = self.builder.get_insert_block() {
@@ -536,19 +662,55 @@ impl<'ctx> IRGenerator<'ctx> {
             .build_unconditional_branch(cond_block)
             .map_err(|e| IRGenError::InvalidOperation(format!("Failed to build branch: {}", e)))?;
 
-        // Position at exit block and add terminator if needed
+        // Position at exit block. A loop without a condition only exits through
+        // `break`; without one, nothing after the loop runs
         self.builder.position_at_end(exit_block);
-
-        // For loops with infinite conditions (;;), the exit block is unreachable
-        // but still needs a terminator for LLVM verification
-        if cond.is_none() {
-            // Infinite loop case - exit block is unreachable
+        if exit_block.get_first_use().is_none() {
             self.builder.build_unreachable().map_err(|e| {
                 IRGenError::InvalidOperation(format!("Failed to build unreachable: {}", e))
             })?;
         }
-        // For normal loops, the exit block should already be properly handled by conditional branches
 
         Ok(self.gen_number_const(0.0).into())
     }
+
+    /// Whether the block being generated already ends, so nothing more can follow
+    pub(crate) fn is_terminated(&self) -> bool {
+        self.builder
+            .get_insert_block()
+            .is_some_and(|bb| bb.get_terminator().is_some())
+    }
+
+    /// Warn that `stmt` and the statements after it are skipped, as `previous`
+    /// never completes
+    pub(crate) fn warn_unreachable(&mut self, previous: &Stmt, stmt: &Stmt) {
+        let cause = match previous {
+            Stmt::Return(_) => "'return'",
+            Stmt::Break => "'break'",
+            Stmt::Continue => "'continue'",
+            Stmt::If(..) => "an 'if' whose branches all leave",
+            _ => "a statement that never completes",
+        };
+        self.diagnostics.push(Diagnostic::new(
+            UNREACHABLE_CODE,
+            Severity::Warning,
+            format!("unreachable code after {}", cause),
+            stmt_span(stmt),
+        ));
+    }
+}
+
+/// Where `stmt` is: the whole statement for compound ones, otherwise where it
+/// starts, if anything in it records that
+pub(crate) fn stmt_span(stmt: &Stmt) -> Option<Span> {
+    match stmt {
+        Stmt::If(.., span)
+        | Stmt::Block(_, span)
+        | Stmt::Repeat(.., span)
+        | Stmt::While(.., span)
+        | Stmt::DoUntil(.., span)
+        | Stmt::For(.., span)
+        | Stmt::Switch(.., span) => Some(span.clone()),
+        _ => stmt_start(stmt),
+    }
 }
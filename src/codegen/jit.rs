@@ -1,9 +1,56 @@
+use crate::codegen::ir_generator::INIT_FUNCTION;
+use crate::codegen::ir_generator::symbols::symbol_names;
+use crate::codegen::runtime;
+use crate::codegen::runtime::collections::Collections;
+use crate::codegen::runtime::instances::Instances;
+use crate::codegen::runtime::output::Output;
+use crate::codegen::runtime::profile::Profile;
+use crate::codegen::runtime::trace::Trace;
+use crate::codegen::runtime::trap::{RaisedError, Trap};
 use inkwell::OptimizationLevel;
 use inkwell::execution_engine::{ExecutionEngine, JitFunction};
 use inkwell::module::Module;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::{CStr, c_char};
+
+/// The most arguments a function called from the host, e.g. by
+/// [`JITExecutor::execute_function`], can take
+pub const MAX_HOST_ARGUMENTS: usize = 13;
+
+/// Call the function with `$symbol` in `$engine`, passing `$args` at the given
+/// indices as numbers
+macro_rules! call_with {
+    (@number $index:literal) => {
+        f64
+    };
+    ($engine:expr, $name:expr, $symbol:expr, $args:expr; $($index:literal)*) => {{
+        let func: JitFunction<unsafe extern "C" fn($(call_with!(@number $index)),*) -> f64> =
+            $engine
+                .get_function($symbol)
+                .map_err(|e| format!("Failed to get function '{}': {}", $name, e))?;
+        Ok(func.call($($args[$index]),*))
+    }};
+}
 
 pub struct JITExecutor<'ctx> {
     execution_engine: ExecutionEngine<'ctx>,
+    // Boxed so their addresses, mapped into the engine, stay put
+    collections: Box<Collections>,
+    instances: Box<Instances>,
+    math_epsilon: Box<Cell<f64>>,
+    profile: Box<Profile>,
+    trace: Box<Trace>,
+    trap: Box<Trap>,
+    output: Box<Output>,
+    // The error that stopped the last execution, taken from the trap
+    last_error: RefCell<Option<RaisedError>>,
+    // Whether the top-level statements ran, or are taken to have run
+    initialized: Cell<bool>,
+    // What they returned, 0 if they were only taken to have run or raised an error
+    main_result: Cell<f64>,
+    // Symbols of the functions generated under prefixed names, by GML name
+    symbols: HashMap<String, String>,
 }
 
 impl<'ctx> JITExecutor<'ctx> {
@@ -11,50 +58,237 @@ impl<'ctx> JITExecutor<'ctx> {
         let execution_engine = module
             .create_jit_execution_engine(OptimizationLevel::None)
             .map_err(|e| format!("Failed to create JIT execution engine: {}", e))?;
+        let collections = Box::default();
+        let instances = Box::default();
+        let math_epsilon = Box::default();
+        let profile = Box::default();
+        let trace = Box::default();
+        let trap = Box::default();
+        let output = Box::default();
+        runtime::map_into(
+            &execution_engine,
+            module,
+            &collections,
+            &instances,
+            &math_epsilon,
+            &profile,
+            &trace,
+            &trap,
+            &output,
+        );
 
-        Ok(Self { execution_engine })
+        Ok(Self {
+            execution_engine,
+            collections,
+            instances,
+            math_epsilon,
+            profile,
+            trace,
+            trap,
+            output,
+            last_error: RefCell::new(None),
+            initialized: Cell::new(false),
+            main_result: Cell::new(0.0),
+            symbols: symbol_names(module),
+        })
     }
 
-    /// Execute the main function and return its result
-    pub fn execute_main(&self) -> Result<f64, String> {
-        unsafe {
-            let main_fn: JitFunction<unsafe extern "C" fn() -> f64> = self
-                .execution_engine
-                .get_function("main")
-                .map_err(|e| format!("Failed to get main function: {}", e))?;
+    /// Add another module to the engine, e.g. a second script sharing this one's
+    /// lists, maps and output. Its functions are called by their symbols, as GML
+    /// names resolve to the first module's functions, and its top-level statements
+    /// are not run.
+    pub fn add_module(&self, module: &Module<'ctx>) -> Result<(), String> {
+        self.execution_engine
+            .add_module(module)
+            .map_err(|_| "Module is already in an execution engine".to_string())?;
+        runtime::map_into(
+            &self.execution_engine,
+            module,
+            &self.collections,
+            &self.instances,
+            &self.math_epsilon,
+            &self.profile,
+            &self.trace,
+            &self.trap,
+            &self.output,
+        );
+        Ok(())
+    }
+
+    /// The symbol of the function GML calls `name`, which is the name itself for a
+    /// function generated without a prefix or a symbol given directly
+    pub fn symbol<'a>(&'a self, name: &'a str) -> &'a str {
+        self.symbols.get(name).map_or(name, String::as_str)
+    }
+
+    /// Take the error recorded by the last collection operation or field access that
+    /// failed, e.g. one on a destroyed ds_list or an unbound `self`. The script itself
+    /// carries on with undefined.
+    pub fn take_runtime_error(&self) -> Option<RaisedError> {
+        let collection_error = self.collections.take_error();
+        let field_error = self.instances.take_error();
+        collection_error.or(field_error)
+    }
 
-            Ok(main_fn.call())
+    /// The error that stopped the last execution, with where it was raised and the
+    /// calls it ended; `None` if that execution raised nothing
+    pub fn last_error(&self) -> Option<RaisedError> {
+        self.last_error.borrow().clone()
+    }
+
+    /// The lists and maps scripts created
+    pub fn collections(&self) -> &Collections {
+        &self.collections
+    }
+
+    /// The `self` and `other` instances scripts run against
+    pub fn instances(&self) -> &Instances {
+        &self.instances
+    }
+
+    /// Where `println` prints to
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// What the functions of code generated with profiling recorded; nothing for
+    /// code generated without
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// The statements code generated with tracing ran most recently, kept whether
+    /// or not the run failed; nothing for code generated without
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    /// The epsilon float comparisons allow, if they were generated to allow one.
+    /// Starts at 0, which makes them exact.
+    pub fn math_epsilon(&self) -> f64 {
+        self.math_epsilon.get()
+    }
+
+    /// Set the epsilon float comparisons allow, as `math_set_epsilon` does
+    pub fn set_math_epsilon(&self, epsilon: f64) {
+        self.math_epsilon.set(epsilon);
+    }
+
+    /// Take over the lists and maps `previous` created, the instances bound to it,
+    /// its print callback, its epsilon and whether its top-level statements ran,
+    /// e.g. when a reloaded script replaces it
+    pub fn adopt_runtime_state(&self, previous: &JITExecutor) {
+        self.collections.take_from(&previous.collections);
+        self.instances.take_from(&previous.instances);
+        self.output.take_from(&previous.output);
+        self.math_epsilon.set(previous.math_epsilon.get());
+        self.initialized.set(previous.initialized.get());
+        self.main_result.set(previous.main_result.get());
+    }
+
+    /// Run the top-level statements unless they already ran, so functions called
+    /// before `main` see the globals they initialize. Returns the error that
+    /// stopped them, if any; they are not run again either way.
+    pub fn ensure_initialized(&self) -> Result<(), String> {
+        if self.initialized.replace(true) {
+            return Ok(());
         }
+        let result = self.call_function(INIT_FUNCTION, &[]);
+        runtime::release_strings();
+        let raised = self.take_raised();
+        let result = result?;
+        raised.map_or(Ok(()), Err)?;
+        self.main_result.set(result);
+        Ok(())
+    }
+
+    /// Take the top-level statements to have run without running them, e.g. once
+    /// the globals they would initialize were restored from a snapshot.
+    /// [`Self::execute_main`] then returns 0
+    pub fn mark_initialized(&self) {
+        self.initialized.set(true);
     }
 
-    /// Execute a function by name with given arguments
+    /// Run the top-level statements unless they already ran, and return what they
+    /// returned, or the error that stopped them. Like [`Self::ensure_initialized`],
+    /// this runs them at most once per executor, so calling it again, or after a
+    /// function, returns the first result without resetting any global.
+    pub fn execute_main(&self) -> Result<f64, String> {
+        self.ensure_initialized()?;
+        Ok(self.main_result.get())
+    }
+
+    /// Execute a function by its GML name or its symbol with given arguments,
+    /// returning its result or the error that stopped it. The top-level statements
+    /// run first if they have not.
     pub fn execute_function(&self, name: &str, args: &[f64]) -> Result<f64, String> {
-        match args.len() {
-            0 => unsafe {
-                let func: JitFunction<unsafe extern "C" fn() -> f64> = self
-                    .execution_engine
-                    .get_function(name)
-                    .map_err(|e| format!("Failed to get function '{}': {}", name, e))?;
-
-                Ok(func.call())
-            },
-            1 => unsafe {
-                let func: JitFunction<unsafe extern "C" fn(f64) -> f64> = self
-                    .execution_engine
-                    .get_function(name)
-                    .map_err(|e| format!("Failed to get function '{}': {}", name, e))?;
-
-                Ok(func.call(args[0]))
-            },
-            2 => unsafe {
-                let func: JitFunction<unsafe extern "C" fn(f64, f64) -> f64> = self
-                    .execution_engine
-                    .get_function(name)
-                    .map_err(|e| format!("Failed to get function '{}': {}", name, e))?;
-
-                Ok(func.call(args[0], args[1]))
-            },
-            _ => Err(format!("Unsupported number of arguments: {}", args.len())),
+        self.ensure_initialized()?;
+        let result = self.call_function(name, args);
+        runtime::release_strings();
+        let raised = self.take_raised();
+        let result = result?;
+        raised.map_or(Ok(result), Err)
+    }
+
+    /// Execute a function that takes nothing and returns a string, such as
+    /// [`STRING_RESULT_FUNCTION`](crate::codegen::ir_generator::STRING_RESULT_FUNCTION),
+    /// returning a copy of the string or the error that stopped it. The top-level
+    /// statements run first if they have not.
+    pub fn execute_string_function(&self, name: &str) -> Result<String, String> {
+        self.ensure_initialized()?;
+        let text = unsafe {
+            let func: JitFunction<unsafe extern "C" fn() -> *const c_char> = self
+                .execution_engine
+                .get_function(self.symbol(name))
+                .map_err(|e| format!("Failed to get function '{}': {}", name, e))?;
+
+            // Copied before the strings the call made are released
+            let text = func.call();
+            if text.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(text).to_string_lossy().into_owned()
+            }
+        };
+        runtime::release_strings();
+        self.take_raised().map_or(Ok(text), Err)
+    }
+
+    /// Take the error the code that just ran raised, keeping it as the last error,
+    /// and return its message
+    fn take_raised(&self) -> Option<String> {
+        let error = self.trap.take();
+        let message = error.as_ref().map(RaisedError::to_string);
+        self.last_error.replace(error);
+        message
+    }
+
+    /// Call the function GML calls `name`, or the function with that symbol, with
+    /// up to [`MAX_HOST_ARGUMENTS`] arguments
+    fn call_function(&self, name: &str, args: &[f64]) -> Result<f64, String> {
+        let engine = &self.execution_engine;
+        let symbol = self.symbol(name);
+        unsafe {
+            match args.len() {
+                0 => call_with!(engine, name, symbol, args;),
+                1 => call_with!(engine, name, symbol, args; 0),
+                2 => call_with!(engine, name, symbol, args; 0 1),
+                3 => call_with!(engine, name, symbol, args; 0 1 2),
+                4 => call_with!(engine, name, symbol, args; 0 1 2 3),
+                5 => call_with!(engine, name, symbol, args; 0 1 2 3 4),
+                6 => call_with!(engine, name, symbol, args; 0 1 2 3 4 5),
+                7 => call_with!(engine, name, symbol, args; 0 1 2 3 4 5 6),
+                8 => call_with!(engine, name, symbol, args; 0 1 2 3 4 5 6 7),
+                9 => call_with!(engine, name, symbol, args; 0 1 2 3 4 5 6 7 8),
+                10 => call_with!(engine, name, symbol, args; 0 1 2 3 4 5 6 7 8 9),
+                11 => call_with!(engine, name, symbol, args; 0 1 2 3 4 5 6 7 8 9 10),
+                12 => call_with!(engine, name, symbol, args; 0 1 2 3 4 5 6 7 8 9 10 11),
+                13 => call_with!(engine, name, symbol, args; 0 1 2 3 4 5 6 7 8 9 10 11 12),
+                count => Err(format!(
+                    "'{}' is called with {} arguments, more than the {} a host call can pass",
+                    name, count, MAX_HOST_ARGUMENTS
+                )),
+            }
         }
     }
 
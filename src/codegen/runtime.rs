@@ -0,0 +1,321 @@
+//! Functions compiled scripts call into at runtime.
+//!
+//! Code generation declares them as external functions on first use and
+//! [`map_into`] points those declarations at the Rust implementations below.
+//!
+//! Strings built at runtime are owned by a per-thread arena. Scripts only pass
+//! numbers in and out, so no runtime string outlives the call into the script
+//! that made it, and [`release_strings`] frees them after each call.
+
+use crate::utils::number_format::format_number;
+use collections::Collections;
+use inkwell::execution_engine::ExecutionEngine;
+use inkwell::module::Module;
+use instances::Instances;
+use output::Output;
+use profile::Profile;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::ffi::{CStr, CString, c_char};
+use std::sync::{Mutex, MutexGuard};
+use trace::Trace;
+use trap::Trap;
+
+pub mod collections;
+pub mod instances;
+pub mod output;
+pub mod profile;
+pub mod trace;
+pub mod trap;
+
+pub const STRING_CONCAT: &str = "col_string_concat";
+pub const STRING_COMPARE: &str = "col_string_compare";
+pub const STRING_FROM_NUMBER: &str = "col_string_from_number";
+pub const STRING_FROM_INT64: &str = "col_string_from_int64";
+pub const STRING_TO_NUMBER: &str = "col_string_to_number";
+pub const STRING_FORMAT: &str = "col_string_format";
+pub const STRING_LENGTH: &str = "col_string_length";
+pub const STRING_CHAR_AT: &str = "col_string_char_at";
+pub const STRING_COPY: &str = "col_string_copy";
+pub const STRING_POS: &str = "col_string_pos";
+
+/// Name of the module global holding the epsilon float comparisons allow, when
+/// they are generated to allow one
+pub const MATH_EPSILON_GLOBAL: &str = "__col_math_epsilon";
+
+/// Lock `mutex` even if it is poisoned. Nothing panics while the runtime's locks
+/// are held, but poisoning must never reach a script.
+pub(crate) fn lock_unpoisoned<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+thread_local! {
+    static STRINGS: RefCell<Vec<CString>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Point the runtime functions `module` declares at their implementations, and its
+/// collections, instances, epsilon, profile, trace, trap and output globals at
+/// `collections`, `instances`, `math_epsilon`, `profile`, `trace`, `trap` and
+/// `output`, which must outlive the engine
+#[allow(clippy::too_many_arguments)] // one per kind of state the executor owns
+pub fn map_into(
+    engine: &ExecutionEngine<'_>,
+    module: &Module<'_>,
+    collections: &Collections,
+    instances: &Instances,
+    math_epsilon: &Cell<f64>,
+    profile: &Profile,
+    trace: &Trace,
+    trap: &Trap,
+    output: &Output,
+) {
+    use collections::*;
+    use instances::*;
+    use output::*;
+    use profile::*;
+    use trace::*;
+    use trap::*;
+
+    let functions: [(&str, *const ()); 33] = [
+        (STRING_CONCAT, col_string_concat as *const ()),
+        (STRING_COMPARE, col_string_compare as *const ()),
+        (STRING_FROM_NUMBER, col_string_from_number as *const ()),
+        (STRING_FROM_INT64, col_string_from_int64 as *const ()),
+        (STRING_TO_NUMBER, col_string_to_number as *const ()),
+        (STRING_FORMAT, col_string_format as *const ()),
+        (STRING_LENGTH, col_string_length as *const ()),
+        (STRING_CHAR_AT, col_string_char_at as *const ()),
+        (STRING_COPY, col_string_copy as *const ()),
+        (STRING_POS, col_string_pos as *const ()),
+        (LIST_CREATE, col_ds_list_create as *const ()),
+        (LIST_DESTROY, col_ds_list_destroy as *const ()),
+        (LIST_ADD, col_ds_list_add as *const ()),
+        (LIST_SIZE, col_ds_list_size as *const ()),
+        (LIST_FIND_VALUE, col_ds_list_find_value as *const ()),
+        (LIST_COPY, col_ds_list_copy as *const ()),
+        (MAP_CREATE, col_ds_map_create as *const ()),
+        (MAP_DESTROY, col_ds_map_destroy as *const ()),
+        (MAP_SET, col_ds_map_set as *const ()),
+        (MAP_FIND_VALUE, col_ds_map_find_value as *const ()),
+        (MAP_EXISTS, col_ds_map_exists as *const ()),
+        (MAP_COPY, col_ds_map_copy as *const ()),
+        (GET_SELF_FIELD, col_get_self_field as *const ()),
+        (SET_SELF_FIELD, col_set_self_field as *const ()),
+        (GET_OTHER_FIELD, col_get_other_field as *const ()),
+        (SET_OTHER_FIELD, col_set_other_field as *const ()),
+        (PROFILE_ENTER, col_profile_enter as *const ()),
+        (PROFILE_EXIT, col_profile_exit as *const ()),
+        (TRACE_STATEMENT, col_trace_statement as *const ()),
+        (RAISE, col_raise as *const ()),
+        (UNWIND, col_unwind as *const ()),
+        (STACK_LOW, col_stack_low as *const ()),
+        (PRINTLN, col_println as *const ()),
+    ];
+    for (name, address) in functions {
+        if let Some(function) = module.get_function(name) {
+            engine.add_global_mapping(&function, address as usize);
+        }
+    }
+    if let Some(global) = module.get_global(COLLECTIONS_GLOBAL) {
+        engine.add_global_mapping(&global, collections as *const Collections as usize);
+    }
+    if let Some(global) = module.get_global(INSTANCES_GLOBAL) {
+        engine.add_global_mapping(&global, instances as *const Instances as usize);
+    }
+    if let Some(global) = module.get_global(MATH_EPSILON_GLOBAL) {
+        engine.add_global_mapping(&global, math_epsilon.as_ptr() as usize);
+    }
+    if let Some(global) = module.get_global(PROFILE_GLOBAL) {
+        engine.add_global_mapping(&global, profile as *const Profile as usize);
+    }
+    if let Some(global) = module.get_global(TRACE_GLOBAL) {
+        engine.add_global_mapping(&global, trace as *const Trace as usize);
+    }
+    if let Some(global) = module.get_global(TRAP_GLOBAL) {
+        engine.add_global_mapping(&global, trap as *const Trap as usize);
+    }
+    if let Some(global) = module.get_global(OUTPUT_GLOBAL) {
+        engine.add_global_mapping(&global, output as *const Output as usize);
+    }
+}
+
+/// Free the strings built at runtime on this thread
+pub fn release_strings() {
+    STRINGS.with(|strings| strings.borrow_mut().clear());
+}
+
+/// Move `text` into the arena and return a pointer to it
+fn alloc_string(text: String) -> *const c_char {
+    // Interior NULs cannot be represented; the string ends at the first one
+    let text = match CString::new(text) {
+        Ok(text) => text,
+        Err(e) => {
+            let end = e.nul_position();
+            let mut bytes = e.into_vec();
+            bytes.truncate(end);
+            CString::new(bytes).unwrap()
+        }
+    };
+    let ptr = text.as_ptr();
+    STRINGS.with(|strings| strings.borrow_mut().push(text));
+    ptr
+}
+
+/// The bytes of a script string; null is the empty string
+///
+/// # Safety
+/// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn bytes<'a>(ptr: *const c_char) -> &'a [u8] {
+    if ptr.is_null() {
+        b""
+    } else {
+        unsafe { CStr::from_ptr(ptr) }.to_bytes()
+    }
+}
+
+/// What `string()` gives for null
+pub const UNDEFINED_TEXT: &str = "undefined";
+
+/// Numbers above this are true as conditions
+pub const TRUE_ABOVE: f64 = 0.5;
+
+/// GML's `string()` of a boolean
+pub fn bool_text(value: bool) -> &'static str {
+    if value { "true" } else { "false" }
+}
+
+/// Whether a number is true as a condition: above [`TRUE_ABOVE`], so 0.4 and NaN
+/// are false
+pub fn number_is_true(value: f64) -> bool {
+    value > TRUE_ABOVE
+}
+
+/// GML's `real()`: the number `text` spells, ignoring surrounding whitespace,
+/// or 0 if it is not one
+pub fn parse_number(text: &str) -> f64 {
+    text.trim().parse().unwrap_or(0.0)
+}
+
+/// GML's `string_format()`: `value` with `decimals` digits after the point,
+/// padded with spaces on the left to at least `total` characters before it
+pub fn format_number_padded(value: f64, total: f64, decimals: f64) -> String {
+    let total = total.max(0.0) as usize;
+    let decimals = decimals.max(0.0) as usize;
+    let width = if decimals > 0 {
+        total + 1 + decimals
+    } else {
+        total
+    };
+    format!(
+        "{:>width$.decimals$}",
+        value,
+        width = width,
+        decimals = decimals
+    )
+}
+
+// Positions and lengths in the string functions below count characters (Unicode
+// scalar values), not bytes, so "héllo" has length 5 and its second character is
+// "é". Positions are 1-based as in GML; fractional ones truncate.
+
+/// The 0-based character offset of the 1-based GML position `index`, clamped to
+/// the first character
+fn char_offset(index: f64) -> usize {
+    (index.max(1.0) - 1.0) as usize
+}
+
+/// GML's `string_char_at()`: the character at `index`. Positions before the first
+/// character give the first one, positions past the end the empty string.
+pub fn string_char_at(text: &str, index: f64) -> String {
+    text.chars()
+        .nth(char_offset(index))
+        .map(String::from)
+        .unwrap_or_default()
+}
+
+/// GML's `string_copy()`: up to `count` characters starting at `index`, with the
+/// start clamped to the first character and the end to the last
+pub fn string_copy(text: &str, index: f64, count: f64) -> String {
+    text.chars()
+        .skip(char_offset(index))
+        .take(count.max(0.0) as usize)
+        .collect()
+}
+
+/// GML's `string_pos()`: the position of the first `needle` in `haystack`, or 0
+/// if there is none. An empty needle is never found.
+pub fn string_pos(needle: &str, haystack: &str) -> f64 {
+    if needle.is_empty() {
+        return 0.0;
+    }
+    haystack
+        .find(needle)
+        .map_or(0.0, |byte| (haystack[..byte].chars().count() + 1) as f64)
+}
+
+/// The text of a script string, with any invalid UTF-8 replaced
+///
+/// # Safety
+/// As for [`bytes`].
+unsafe fn text<'a>(ptr: *const c_char) -> Cow<'a, str> {
+    String::from_utf8_lossy(unsafe { bytes(ptr) })
+}
+
+extern "C" fn col_string_concat(lhs: *const c_char, rhs: *const c_char) -> *const c_char {
+    // SAFETY: generated code only passes string values, which are null or NUL-terminated
+    let (lhs, rhs) = unsafe { (bytes(lhs), bytes(rhs)) };
+    let mut text = Vec::with_capacity(lhs.len() + rhs.len());
+    text.extend_from_slice(lhs);
+    text.extend_from_slice(rhs);
+    // Neither part contains a NUL, so neither does the result
+    let text = CString::new(text).unwrap();
+    let ptr = text.as_ptr();
+    STRINGS.with(|strings| strings.borrow_mut().push(text));
+    ptr
+}
+
+/// Compare two strings bytewise: negative, zero or positive like `strcmp`
+extern "C" fn col_string_compare(lhs: *const c_char, rhs: *const c_char) -> i32 {
+    // SAFETY: as in `col_string_concat`
+    let (lhs, rhs) = unsafe { (bytes(lhs), bytes(rhs)) };
+    lhs.cmp(rhs) as i32
+}
+
+extern "C" fn col_string_from_number(value: f64) -> *const c_char {
+    alloc_string(format_number(value))
+}
+
+extern "C" fn col_string_from_int64(value: i64) -> *const c_char {
+    alloc_string(value.to_string())
+}
+
+extern "C" fn col_string_to_number(text: *const c_char) -> f64 {
+    // SAFETY: as in `col_string_concat`
+    let text = unsafe { bytes(text) };
+    std::str::from_utf8(text).map_or(0.0, parse_number)
+}
+
+extern "C" fn col_string_format(value: f64, total: f64, decimals: f64) -> *const c_char {
+    alloc_string(format_number_padded(value, total, decimals))
+}
+
+extern "C" fn col_string_length(text_ptr: *const c_char) -> f64 {
+    // SAFETY: as in `col_string_concat`
+    unsafe { text(text_ptr) }.chars().count() as f64
+}
+
+extern "C" fn col_string_char_at(text_ptr: *const c_char, index: f64) -> *const c_char {
+    // SAFETY: as in `col_string_concat`
+    alloc_string(string_char_at(&unsafe { text(text_ptr) }, index))
+}
+
+extern "C" fn col_string_copy(text_ptr: *const c_char, index: f64, count: f64) -> *const c_char {
+    // SAFETY: as in `col_string_concat`
+    alloc_string(string_copy(&unsafe { text(text_ptr) }, index, count))
+}
+
+extern "C" fn col_string_pos(needle: *const c_char, haystack: *const c_char) -> f64 {
+    // SAFETY: as in `col_string_concat`
+    let (needle, haystack) = unsafe { (text(needle), text(haystack)) };
+    string_pos(&needle, &haystack)
+}
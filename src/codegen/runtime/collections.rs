@@ -0,0 +1,289 @@
+//! `ds_list` and `ds_map` storage.
+//!
+//! Every executor owns one [`Collections`]. Generated code reaches it through the
+//! external global [`COLLECTIONS_GLOBAL`], whose address is mapped to it, and passes
+//! that address to each runtime function. Handles are plain numbers, counting up
+//! from 0 separately for lists and maps, and are never reused.
+//!
+//! Using a handle that does not exist (never created or already destroyed) does not
+//! crash the script: the function returns the undefined value (0) and records an
+//! error the host can read with [`Collections::take_error`].
+
+use crate::codegen::runtime::lock_unpoisoned;
+use crate::codegen::runtime::trap::{ErrorKind, RaisedError};
+use std::collections::HashMap;
+use std::ffi::{CStr, c_char};
+use std::sync::{Mutex, MutexGuard};
+
+/// Name of the module global standing for the executor's collections
+pub const COLLECTIONS_GLOBAL: &str = "__col_collections";
+
+pub const LIST_CREATE: &str = "col_ds_list_create";
+pub const LIST_DESTROY: &str = "col_ds_list_destroy";
+pub const LIST_ADD: &str = "col_ds_list_add";
+pub const LIST_SIZE: &str = "col_ds_list_size";
+pub const LIST_FIND_VALUE: &str = "col_ds_list_find_value";
+pub const LIST_COPY: &str = "col_ds_list_copy";
+pub const MAP_CREATE: &str = "col_ds_map_create";
+pub const MAP_DESTROY: &str = "col_ds_map_destroy";
+pub const MAP_SET: &str = "col_ds_map_set";
+pub const MAP_FIND_VALUE: &str = "col_ds_map_find_value";
+pub const MAP_EXISTS: &str = "col_ds_map_exists";
+pub const MAP_COPY: &str = "col_ds_map_copy";
+
+/// The lists and maps created by one executor's scripts
+#[derive(Debug, Default)]
+pub struct Collections {
+    inner: Mutex<Registry>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    lists: HashMap<u32, Vec<f64>>,
+    maps: HashMap<u32, HashMap<MapKey, f64>>,
+    next_list: u32,
+    next_map: u32,
+    error: Option<RaisedError>,
+}
+
+/// Numbers and strings are different keys, so `1` and `"1"` do not collide
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum MapKey {
+    Number(u64),
+    String(Vec<u8>),
+}
+
+impl MapKey {
+    fn number(value: f64) -> Self {
+        // -0 and 0 are the same key
+        MapKey::Number((value + 0.0).to_bits())
+    }
+}
+
+impl Collections {
+    /// Take the error recorded by the last failed collection operation, if any
+    pub fn take_error(&self) -> Option<RaisedError> {
+        lock_unpoisoned(&self.inner).error.take()
+    }
+
+    /// How many lists and maps exist, i.e. were created and not yet destroyed
+    pub fn live(&self) -> usize {
+        let registry = lock_unpoisoned(&self.inner);
+        registry.lists.len() + registry.maps.len()
+    }
+
+    /// Move every list and map out of `other`, replacing whatever this held. Handles
+    /// stay valid, so numbers a script kept keep referring to the same collections.
+    pub fn take_from(&self, other: &Collections) {
+        let registry = std::mem::take(&mut *lock_unpoisoned(&other.inner));
+        *lock_unpoisoned(&self.inner) = registry;
+    }
+}
+
+impl Registry {
+    fn fail(&mut self, message: String) -> f64 {
+        self.error = Some(RaisedError::recovered(ErrorKind::InvalidHandle, message));
+        0.0
+    }
+
+    fn list(&mut self, function: &str, handle: f64) -> Result<&mut Vec<f64>, f64> {
+        match handle_id(handle).filter(|id| self.lists.contains_key(id)) {
+            Some(id) => Ok(self.lists.get_mut(&id).unwrap()),
+            None => Err(self.fail(format!("{}: {} is not a ds_list", function, handle))),
+        }
+    }
+
+    fn map(&mut self, function: &str, handle: f64) -> Result<&mut HashMap<MapKey, f64>, f64> {
+        match handle_id(handle).filter(|id| self.maps.contains_key(id)) {
+            Some(id) => Ok(self.maps.get_mut(&id).unwrap()),
+            None => Err(self.fail(format!("{}: {} is not a ds_map", function, handle))),
+        }
+    }
+}
+
+fn handle_id(handle: f64) -> Option<u32> {
+    (handle >= 0.0 && handle.fract() == 0.0 && handle <= u32::MAX as f64).then_some(handle as u32)
+}
+
+/// A map key passed from generated code: the string `string` if it is not null,
+/// otherwise the number `number`
+///
+/// # Safety
+/// `string` must be null or point to a NUL-terminated string.
+unsafe fn map_key(string: *const c_char, number: f64) -> MapKey {
+    if string.is_null() {
+        MapKey::number(number)
+    } else {
+        MapKey::String(unsafe { CStr::from_ptr(string) }.to_bytes().to_vec())
+    }
+}
+
+/// # Safety
+/// `collections` must be the address generated code was given, which the executor
+/// keeps alive for as long as the code can run.
+unsafe fn registry<'a>(collections: *const Collections) -> MutexGuard<'a, Registry> {
+    lock_unpoisoned(&unsafe { &*collections }.inner)
+}
+
+pub(super) extern "C" fn col_ds_list_create(collections: *const Collections) -> f64 {
+    // SAFETY: see `registry`; the same holds for every function below
+    let mut registry = unsafe { registry(collections) };
+    let id = registry.next_list;
+    registry.next_list += 1;
+    registry.lists.insert(id, Vec::new());
+    id as f64
+}
+
+pub(super) extern "C" fn col_ds_list_destroy(collections: *const Collections, list: f64) -> f64 {
+    let mut registry = unsafe { registry(collections) };
+    if handle_id(list)
+        .and_then(|id| registry.lists.remove(&id))
+        .is_none()
+    {
+        return registry.fail(format!("ds_list_destroy: {} is not a ds_list", list));
+    }
+    0.0
+}
+
+pub(super) extern "C" fn col_ds_list_add(
+    collections: *const Collections,
+    list: f64,
+    value: f64,
+) -> f64 {
+    let mut registry = unsafe { registry(collections) };
+    match registry.list("ds_list_add", list) {
+        Ok(values) => {
+            values.push(value);
+            0.0
+        }
+        Err(undefined) => undefined,
+    }
+}
+
+pub(super) extern "C" fn col_ds_list_size(collections: *const Collections, list: f64) -> f64 {
+    let mut registry = unsafe { registry(collections) };
+    match registry.list("ds_list_size", list) {
+        Ok(values) => values.len() as f64,
+        Err(undefined) => undefined,
+    }
+}
+
+pub(super) extern "C" fn col_ds_list_find_value(
+    collections: *const Collections,
+    list: f64,
+    position: f64,
+) -> f64 {
+    let mut registry = unsafe { registry(collections) };
+    let value = match registry.list("ds_list_find_value", list) {
+        Ok(values) => handle_id(position).and_then(|i| values.get(i as usize).copied()),
+        Err(undefined) => return undefined,
+    };
+    // Like GML, reading past the end gives undefined without being an error
+    value.unwrap_or(0.0)
+}
+
+/// Replace the values of `list` with a copy of those of `source`, so changing one
+/// afterwards leaves the other as it is
+pub(super) extern "C" fn col_ds_list_copy(
+    collections: *const Collections,
+    list: f64,
+    source: f64,
+) -> f64 {
+    let mut registry = unsafe { registry(collections) };
+    let values = match registry.list("ds_list_copy", source) {
+        Ok(values) => values.clone(),
+        Err(undefined) => return undefined,
+    };
+    match registry.list("ds_list_copy", list) {
+        Ok(target) => {
+            *target = values;
+            0.0
+        }
+        Err(undefined) => undefined,
+    }
+}
+
+pub(super) extern "C" fn col_ds_map_create(collections: *const Collections) -> f64 {
+    let mut registry = unsafe { registry(collections) };
+    let id = registry.next_map;
+    registry.next_map += 1;
+    registry.maps.insert(id, HashMap::new());
+    id as f64
+}
+
+pub(super) extern "C" fn col_ds_map_destroy(collections: *const Collections, map: f64) -> f64 {
+    let mut registry = unsafe { registry(collections) };
+    if handle_id(map)
+        .and_then(|id| registry.maps.remove(&id))
+        .is_none()
+    {
+        return registry.fail(format!("ds_map_destroy: {} is not a ds_map", map));
+    }
+    0.0
+}
+
+pub(super) extern "C" fn col_ds_map_set(
+    collections: *const Collections,
+    map: f64,
+    key_string: *const c_char,
+    key_number: f64,
+    value: f64,
+) -> f64 {
+    let key = unsafe { map_key(key_string, key_number) };
+    let mut registry = unsafe { registry(collections) };
+    match registry.map("ds_map_set", map) {
+        Ok(entries) => {
+            entries.insert(key, value);
+            0.0
+        }
+        Err(undefined) => undefined,
+    }
+}
+
+pub(super) extern "C" fn col_ds_map_find_value(
+    collections: *const Collections,
+    map: f64,
+    key_string: *const c_char,
+    key_number: f64,
+) -> f64 {
+    let key = unsafe { map_key(key_string, key_number) };
+    let mut registry = unsafe { registry(collections) };
+    match registry.map("ds_map_find_value", map) {
+        Ok(entries) => entries.get(&key).copied().unwrap_or(0.0),
+        Err(undefined) => undefined,
+    }
+}
+
+pub(super) extern "C" fn col_ds_map_exists(
+    collections: *const Collections,
+    map: f64,
+    key_string: *const c_char,
+    key_number: f64,
+) -> f64 {
+    let key = unsafe { map_key(key_string, key_number) };
+    let mut registry = unsafe { registry(collections) };
+    match registry.map("ds_map_exists", map) {
+        Ok(entries) => entries.contains_key(&key) as u8 as f64,
+        Err(undefined) => undefined,
+    }
+}
+
+/// Replace the entries of `map` with a copy of those of `source`
+pub(super) extern "C" fn col_ds_map_copy(
+    collections: *const Collections,
+    map: f64,
+    source: f64,
+) -> f64 {
+    let mut registry = unsafe { registry(collections) };
+    let entries = match registry.map("ds_map_copy", source) {
+        Ok(entries) => entries.clone(),
+        Err(undefined) => return undefined,
+    };
+    match registry.map("ds_map_copy", map) {
+        Ok(target) => {
+            *target = entries;
+            0.0
+        }
+        Err(undefined) => undefined,
+    }
+}
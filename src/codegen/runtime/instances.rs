@@ -0,0 +1,180 @@
+//! Fields of the `self` and `other` instances a host binds to a script.
+//!
+//! Every executor owns one [`Instances`]. Like collections, generated code reaches
+//! it through an external global, [`INSTANCES_GLOBAL`], and passes its address to
+//! the field functions along with the field name. Fields hold numbers.
+//!
+//! Reading a field of an instance that is not bound, or a field the instance does
+//! not have, does not crash the script: it gives the undefined value (0) and records
+//! an error the host can read with [`Instances::take_error`]. Assigning a field the
+//! instance does not have yet creates it.
+
+use crate::codegen::runtime::lock_unpoisoned;
+use crate::codegen::runtime::trap::{ErrorKind, RaisedError};
+use std::collections::HashMap;
+use std::ffi::{CStr, c_char};
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+/// Name of the module global standing for the executor's instances
+pub const INSTANCES_GLOBAL: &str = "__col_instances";
+
+pub const GET_SELF_FIELD: &str = "col_get_self_field";
+pub const SET_SELF_FIELD: &str = "col_set_self_field";
+pub const GET_OTHER_FIELD: &str = "col_get_other_field";
+pub const SET_OTHER_FIELD: &str = "col_set_other_field";
+
+/// The instance a script refers to with `self` or `other`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instance {
+    Self_,
+    Other,
+}
+
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instance::Self_ => write!(f, "self"),
+            Instance::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// The instances bound to one executor's scripts
+#[derive(Debug, Default)]
+pub struct Instances {
+    inner: Mutex<Bindings>,
+}
+
+#[derive(Debug, Default)]
+struct Bindings {
+    self_fields: Option<HashMap<String, f64>>,
+    other_fields: Option<HashMap<String, f64>>,
+    error: Option<RaisedError>,
+}
+
+impl Instances {
+    /// Bind `instance` to an object with these fields, replacing any bound before
+    pub fn bind(&self, instance: Instance, fields: HashMap<String, f64>) {
+        *lock_unpoisoned(&self.inner).fields_mut(instance) = Some(fields);
+    }
+
+    /// Unbind `instance`, returning the fields it had, including any the script set
+    pub fn unbind(&self, instance: Instance) -> Option<HashMap<String, f64>> {
+        lock_unpoisoned(&self.inner).fields_mut(instance).take()
+    }
+
+    /// The current value of a field of `instance`, if it is bound and has the field
+    pub fn field(&self, instance: Instance, name: &str) -> Option<f64> {
+        lock_unpoisoned(&self.inner)
+            .fields_mut(instance)
+            .as_ref()
+            .and_then(|fields| fields.get(name).copied())
+    }
+
+    /// Take the error recorded by the last failed field access, if any
+    pub fn take_error(&self) -> Option<RaisedError> {
+        lock_unpoisoned(&self.inner).error.take()
+    }
+
+    /// Move both bindings out of `other`, replacing whatever this held
+    pub fn take_from(&self, other: &Instances) {
+        let bindings = std::mem::take(&mut *lock_unpoisoned(&other.inner));
+        *lock_unpoisoned(&self.inner) = bindings;
+    }
+}
+
+impl Bindings {
+    fn fail(&mut self, message: String) {
+        self.error = Some(RaisedError::recovered(ErrorKind::InvalidField, message));
+    }
+
+    fn fields_mut(&mut self, instance: Instance) -> &mut Option<HashMap<String, f64>> {
+        match instance {
+            Instance::Self_ => &mut self.self_fields,
+            Instance::Other => &mut self.other_fields,
+        }
+    }
+
+    fn get(&mut self, instance: Instance, name: &str) -> f64 {
+        let value = match self.fields_mut(instance) {
+            Some(fields) => fields.get(name).copied().ok_or_else(|| {
+                format!(
+                    "{}.{}: {} has no field '{}'",
+                    instance, name, instance, name
+                )
+            }),
+            None => Err(unbound(instance, name)),
+        };
+        value.unwrap_or_else(|message| {
+            self.fail(message);
+            0.0
+        })
+    }
+
+    fn set(&mut self, instance: Instance, name: &str, value: f64) -> f64 {
+        match self.fields_mut(instance) {
+            Some(fields) => {
+                fields.insert(name.to_string(), value);
+            }
+            None => self.fail(unbound(instance, name)),
+        }
+        value
+    }
+}
+
+fn unbound(instance: Instance, name: &str) -> String {
+    format!(
+        "{}.{}: no instance is bound to {}",
+        instance, name, instance
+    )
+}
+
+/// # Safety
+/// `instances` must be the address generated code was given, which the executor
+/// keeps alive for as long as the code can run, and `name` a NUL-terminated string.
+unsafe fn bindings<'a>(
+    instances: *const Instances,
+    name: *const c_char,
+) -> (MutexGuard<'a, Bindings>, String) {
+    let name = unsafe { CStr::from_ptr(name) }
+        .to_string_lossy()
+        .into_owned();
+    (lock_unpoisoned(&unsafe { &*instances }.inner), name)
+}
+
+pub(super) extern "C" fn col_get_self_field(
+    instances: *const Instances,
+    name: *const c_char,
+) -> f64 {
+    // SAFETY: see `bindings`; generated code passes field names as string constants.
+    // The same holds for every function below.
+    let (mut bindings, name) = unsafe { bindings(instances, name) };
+    bindings.get(Instance::Self_, &name)
+}
+
+pub(super) extern "C" fn col_set_self_field(
+    instances: *const Instances,
+    name: *const c_char,
+    value: f64,
+) -> f64 {
+    let (mut bindings, name) = unsafe { bindings(instances, name) };
+    bindings.set(Instance::Self_, &name, value)
+}
+
+pub(super) extern "C" fn col_get_other_field(
+    instances: *const Instances,
+    name: *const c_char,
+) -> f64 {
+    let (mut bindings, name) = unsafe { bindings(instances, name) };
+    bindings.get(Instance::Other, &name)
+}
+
+pub(super) extern "C" fn col_set_other_field(
+    instances: *const Instances,
+    name: *const c_char,
+    value: f64,
+) -> f64 {
+    let (mut bindings, name) = unsafe { bindings(instances, name) };
+    bindings.set(Instance::Other, &name, value)
+}
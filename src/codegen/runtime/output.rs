@@ -0,0 +1,61 @@
+//! Text scripts print with `println`.
+//!
+//! Generated code joins the arguments into one string and calls [`PRINTLN`] with
+//! the executor's [`Output`], reached through [`OUTPUT_GLOBAL`]. The line goes to
+//! the callback the host registered, or to stdout if there is none.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, c_char};
+use std::fmt;
+
+/// Name of the module global standing for the executor's output
+pub const OUTPUT_GLOBAL: &str = "__col_output";
+
+pub const PRINTLN: &str = "col_println";
+
+/// Receives each line a script prints, newline included
+pub type PrintCallback = Box<dyn FnMut(&str)>;
+
+/// Where the scripts of one executor print to
+#[derive(Default)]
+pub struct Output {
+    callback: RefCell<Option<PrintCallback>>,
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Output")
+            .field("callback", &self.callback.borrow().is_some())
+            .finish()
+    }
+}
+
+impl Output {
+    /// Send printed lines to `callback`, or to stdout if `None`, returning the
+    /// callback registered before
+    pub fn set_callback(&self, callback: Option<PrintCallback>) -> Option<PrintCallback> {
+        self.callback.replace(callback)
+    }
+
+    /// Take over the callback registered with `previous`
+    pub fn take_from(&self, previous: &Output) {
+        self.callback.replace(previous.callback.take());
+    }
+
+    pub(crate) fn print(&self, line: &str) {
+        // A callback printing through the script again goes to stdout
+        match self.callback.try_borrow_mut().as_deref_mut() {
+            Ok(Some(callback)) => callback(line),
+            _ => print!("{}", line),
+        }
+    }
+}
+
+/// Print `text` and a newline
+pub(super) extern "C" fn col_println(output: *const Output, text: *const c_char) {
+    // SAFETY: generated code passes the address the executor mapped for its output,
+    // which it keeps alive while code runs, and a string from the arena
+    let output = unsafe { &*output };
+    let text = unsafe { CStr::from_ptr(text) }.to_string_lossy();
+    output.print(&format!("{}\n", text));
+}
@@ -0,0 +1,109 @@
+//! Call counts and time spent per script function, for finding hot functions.
+//!
+//! Only code generated with profiling on records anything: it calls
+//! [`PROFILE_ENTER`] when a function starts and [`PROFILE_EXIT`] before each of its
+//! returns, passing the executor's [`Profile`], reached through [`PROFILE_GLOBAL`],
+//! and the function's name. Without profiling neither call is emitted.
+//!
+//! A function's time runs from its outermost call to that call's return, so time
+//! spent in recursive calls is not counted twice.
+
+use crate::codegen::runtime::lock_unpoisoned;
+use std::collections::HashMap;
+use std::ffi::{CStr, c_char};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+
+/// Name of the module global standing for the executor's profile
+pub const PROFILE_GLOBAL: &str = "__col_profile";
+
+pub const PROFILE_ENTER: &str = "col_profile_enter";
+pub const PROFILE_EXIT: &str = "col_profile_exit";
+
+/// How often one function was called and how long it ran
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionProfile {
+    /// LLVM name of the function: `main` for top-level code, `outer.inner` for a
+    /// nested function
+    pub name: String,
+    pub calls: u64,
+    pub total_ns: u64,
+}
+
+/// What the profiled functions of one executor's scripts recorded
+#[derive(Debug, Default)]
+pub struct Profile {
+    // Keyed by the address of the name constant, which is fixed per function, so a
+    // call only reads the name the first time
+    inner: Mutex<HashMap<usize, Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    calls: u64,
+    total_ns: u64,
+    // Calls that have not returned yet, and when the outermost one started
+    depth: usize,
+    started: Option<Instant>,
+}
+
+impl Profile {
+    /// Every function called so far, most time first
+    pub fn functions(&self) -> Vec<FunctionProfile> {
+        let mut functions: Vec<FunctionProfile> = lock_unpoisoned(&self.inner)
+            .values()
+            .map(|entry| FunctionProfile {
+                name: entry.name.clone(),
+                calls: entry.calls,
+                total_ns: entry.total_ns,
+            })
+            .collect();
+        functions.sort_by(|a, b| b.total_ns.cmp(&a.total_ns).then(a.name.cmp(&b.name)));
+        functions
+    }
+
+    /// Forget everything recorded so far
+    pub fn clear(&self) {
+        lock_unpoisoned(&self.inner).clear();
+    }
+}
+
+/// # Safety
+/// `profile` must be the address generated code was given, which the executor keeps
+/// alive for as long as the code can run.
+unsafe fn entries<'a>(profile: *const Profile) -> MutexGuard<'a, HashMap<usize, Entry>> {
+    lock_unpoisoned(&unsafe { &*profile }.inner)
+}
+
+pub(super) extern "C" fn col_profile_enter(profile: *const Profile, name: *const c_char) {
+    // SAFETY: see `entries`; generated code passes the function's name as a string
+    // constant. The same holds below.
+    let mut entries = unsafe { entries(profile) };
+    let entry = entries.entry(name as usize).or_insert_with(|| Entry {
+        name: unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned(),
+        calls: 0,
+        total_ns: 0,
+        depth: 0,
+        started: None,
+    });
+    entry.calls += 1;
+    entry.depth += 1;
+    if entry.depth == 1 {
+        entry.started = Some(Instant::now());
+    }
+}
+
+pub(super) extern "C" fn col_profile_exit(profile: *const Profile, name: *const c_char) {
+    let mut entries = unsafe { entries(profile) };
+    let Some(entry) = entries.get_mut(&(name as usize)) else {
+        return;
+    };
+    entry.depth = entry.depth.saturating_sub(1);
+    if entry.depth == 0 {
+        let started = entry.started.take();
+        entry.total_ns += started.map_or(0, |started| started.elapsed().as_nanos() as u64);
+    }
+}
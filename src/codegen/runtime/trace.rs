@@ -0,0 +1,110 @@
+//! The statements a script ran most recently, for seeing what it did without a
+//! debugger.
+//!
+//! Only code generated with tracing on records anything: it calls
+//! [`TRACE_STATEMENT`] as each statement starts, passing the executor's [`Trace`],
+//! reached through [`TRACE_GLOBAL`], and the offset of the statement in its source.
+//! Without tracing the call is not emitted. The trace keeps the last
+//! [`DEFAULT_TRACE_CAPACITY`] statements unless given another capacity, older ones
+//! making room for newer.
+
+use crate::codegen::runtime::lock_unpoisoned;
+use crate::utils::line_index::LineIndex;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Name of the module global standing for the executor's trace
+pub const TRACE_GLOBAL: &str = "__col_trace";
+
+pub const TRACE_STATEMENT: &str = "col_trace_statement";
+
+/// How many statements a trace keeps unless told otherwise
+pub const DEFAULT_TRACE_CAPACITY: usize = 1024;
+
+/// A statement that ran, and when
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Where the statement starts, 1-based
+    pub line: usize,
+    pub column: usize,
+    /// How many traced statements ran before this one, counting those the trace
+    /// no longer keeps
+    pub hit_order: u64,
+}
+
+/// The most recent statements traced code of one executor ran
+#[derive(Debug)]
+pub struct Trace {
+    inner: Mutex<Ring>,
+}
+
+#[derive(Debug)]
+struct Ring {
+    // Source offsets of the statements, oldest first, with their hit order
+    entries: VecDeque<(usize, u64)>,
+    capacity: usize,
+    hits: u64,
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Ring {
+                entries: VecDeque::new(),
+                capacity: DEFAULT_TRACE_CAPACITY,
+                hits: 0,
+            }),
+        }
+    }
+}
+
+impl Trace {
+    /// Keep the last `capacity` statements from now on, dropping the oldest ones
+    /// kept if there are more
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut ring = lock_unpoisoned(&self.inner);
+        ring.capacity = capacity;
+        let excess = ring.entries.len().saturating_sub(capacity);
+        ring.entries.drain(..excess);
+    }
+
+    /// Take the statements kept so far, oldest first, placed in `source`, the
+    /// source the code was generated from. Hit order carries on from where it was.
+    pub fn take(&self, source: &str) -> Vec<TraceEntry> {
+        let entries = std::mem::take(&mut lock_unpoisoned(&self.inner).entries);
+        let lines = LineIndex::new(source);
+        entries
+            .into_iter()
+            .map(|(offset, hit_order)| {
+                let (line, column) = lines.line_col(offset);
+                TraceEntry {
+                    line,
+                    column,
+                    hit_order,
+                }
+            })
+            .collect()
+    }
+
+    /// Forget everything recorded so far, including the hit order
+    pub fn clear(&self) {
+        let mut ring = lock_unpoisoned(&self.inner);
+        ring.entries.clear();
+        ring.hits = 0;
+    }
+}
+
+pub(super) extern "C" fn col_trace_statement(trace: *const Trace, offset: i64) {
+    // SAFETY: `trace` is the address generated code was given, which the executor
+    // keeps alive for as long as the code can run
+    let mut ring = lock_unpoisoned(&unsafe { &*trace }.inner);
+    let hit_order = ring.hits;
+    ring.hits += 1;
+    if ring.capacity == 0 {
+        return;
+    }
+    if ring.entries.len() == ring.capacity {
+        ring.entries.pop_front();
+    }
+    ring.entries.push_back((offset as usize, hit_order));
+}
@@ -0,0 +1,202 @@
+//! Errors that stop a running script, such as dividing by zero under
+//! [`DivByZeroPolicy::Error`](crate::parser::language_options::DivByZeroPolicy::Error)
+//! or calls nested deeper than
+//! [`max_call_depth`](crate::parser::language_options::LanguageOptions::max_call_depth).
+//!
+//! Generated code cannot unwind, so stopping is cooperative: the failing code calls
+//! [`RAISE`] with the executor's [`Trap`], reached through [`TRAP_GLOBAL`], what went
+//! wrong and where, then returns from its function at once. Calls into script
+//! functions that can raise check the flag afterwards, and return too after calling
+//! [`UNWIND`] with the calling function and the call's span, so the whole call into
+//! the script ends and the error collects the calls it ended on the way out. The
+//! executor then turns the error into its result.
+
+use crate::codegen::runtime::lock_unpoisoned;
+use crate::parser::Span;
+use std::ffi::{CStr, c_char};
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Name of the module global standing for the executor's trap
+pub const TRAP_GLOBAL: &str = "__col_trap";
+
+pub const RAISE: &str = "col_raise";
+pub const UNWIND: &str = "col_unwind";
+pub const STACK_LOW: &str = "col_stack_low";
+
+/// How much native stack a call of a script function must leave, for the frames
+/// it makes before the next check: its own, the runtime's and the host's callbacks
+pub const STACK_RESERVE: usize = 256 * 1024;
+
+/// What went wrong in a script that raised an error
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// `/`, `div`, `%` or `mod` by zero under
+    /// [`DivByZeroPolicy::Error`](crate::parser::language_options::DivByZeroPolicy::Error)
+    DivisionByZero,
+    /// More calls open than
+    /// [`max_call_depth`](crate::parser::language_options::LanguageOptions::max_call_depth),
+    /// or too little stack left for another
+    CallDepthExceeded,
+    /// A comparison with NaN under strict math
+    NanComparison,
+    /// `null` as an operand of a string operation other than `==` and `!=`
+    NullOperand,
+    /// Calling a variable that holds no function
+    NotCallable,
+    /// A ds_list or ds_map operation on a handle that was never created or is
+    /// destroyed. The script carries on with undefined.
+    InvalidHandle,
+    /// Reading or writing a field of `self` or `other` with nothing bound to it,
+    /// or reading a field the instance does not have. The script carries on with
+    /// undefined.
+    InvalidField,
+}
+
+impl ErrorKind {
+    fn from_code(code: i32) -> Self {
+        match code {
+            0 => ErrorKind::DivisionByZero,
+            1 => ErrorKind::CallDepthExceeded,
+            2 => ErrorKind::NanComparison,
+            3 => ErrorKind::NullOperand,
+            4 => ErrorKind::NotCallable,
+            _ => unreachable!("unknown error kind {}", code),
+        }
+    }
+}
+
+/// A script function an error stopped, and where it was
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameInfo {
+    /// The function, named as in profiles: `main` for top-level code,
+    /// `outer.inner` for a nested function
+    pub function: String,
+    /// The code that raised the error in the innermost frame, the call it was
+    /// waiting for in the others; `None` if the code has no span
+    pub span: Option<Span>,
+}
+
+/// An error a running script raised, and where. Its message is what the failed call
+/// returns as its error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaisedError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// The function that raised the error; empty for an error the script carries
+    /// on from, which the runtime records without knowing its caller
+    pub function: String,
+    /// The code that raised the error, if it has a span
+    pub span: Option<Span>,
+    /// The calls the error ended, innermost first, so the first is in `function`.
+    /// Callers only show up where calls check for errors, which they do under
+    /// [`DivByZeroPolicy::Error`](crate::parser::language_options::DivByZeroPolicy::Error),
+    /// strict math or a call depth limit; otherwise an error ends only its own
+    /// function.
+    pub call_stack: Vec<FrameInfo>,
+}
+
+impl RaisedError {
+    /// An error of `kind` the script carries on from, recorded by the runtime
+    /// rather than raised by generated code, so without a place or call stack
+    pub(crate) fn recovered(kind: ErrorKind, message: String) -> Self {
+        RaisedError {
+            kind,
+            message,
+            function: String::new(),
+            span: None,
+            call_stack: vec![],
+        }
+    }
+}
+
+impl fmt::Display for RaisedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Whether a running script raised an error, and which
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct Trap {
+    // First, so generated code reads it as the byte at the global's address
+    raised: AtomicBool,
+    error: Mutex<Option<RaisedError>>,
+}
+
+impl Trap {
+    /// Take the error raised since the last call, clearing it
+    pub fn take(&self) -> Option<RaisedError> {
+        self.raised.store(false, Ordering::Relaxed);
+        lock_unpoisoned(&self.error).take()
+    }
+}
+
+/// The span generated code passes as two offsets, both negative for none
+fn span(start: i64, end: i64) -> Option<Span> {
+    (start >= 0 && end >= 0).then(|| start as usize..end as usize)
+}
+
+/// # Safety
+/// `text` must be a string constant of generated code.
+unsafe fn text(text: *const c_char) -> String {
+    unsafe { CStr::from_ptr(text) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Record an error of `kind` unless one was raised already; the first one stops the
+/// script
+pub(super) extern "C" fn col_raise(
+    trap: *const Trap,
+    kind: i32,
+    message: *const c_char,
+    function: *const c_char,
+    start: i64,
+    end: i64,
+) {
+    // SAFETY: generated code passes the address the executor mapped for the trap,
+    // which it keeps alive while code runs, and string constants
+    let trap = unsafe { &*trap };
+    let mut slot = lock_unpoisoned(&trap.error);
+    if slot.is_none() {
+        let function = unsafe { text(function) };
+        let span = span(start, end);
+        *slot = Some(RaisedError {
+            kind: ErrorKind::from_code(kind),
+            message: unsafe { text(message) },
+            function: function.clone(),
+            span: span.clone(),
+            call_stack: vec![FrameInfo { function, span }],
+        });
+    }
+    trap.raised.store(true, Ordering::Relaxed);
+}
+
+/// 1 if less than [`STACK_RESERVE`] of the thread's stack is left, so the call
+/// checking must raise rather than risk overflowing it; 0 if enough is left or the
+/// stack's size is unknown
+pub(super) extern "C" fn col_stack_low() -> i32 {
+    stacker::remaining_stack().is_some_and(|left| left < STACK_RESERVE) as i32
+}
+
+/// Add the caller `function` to the call stack of the error being raised, as it
+/// returns from the call at `start..end`
+pub(super) extern "C" fn col_unwind(
+    trap: *const Trap,
+    function: *const c_char,
+    start: i64,
+    end: i64,
+) {
+    // SAFETY: as in `col_raise`
+    let trap = unsafe { &*trap };
+    if let Some(error) = lock_unpoisoned(&trap.error).as_mut() {
+        error.call_stack.push(FrameInfo {
+            function: unsafe { text(function) },
+            span: span(start, end),
+        });
+    }
+}
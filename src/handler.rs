@@ -1,3 +1,4 @@
+pub mod check_handler;
 pub mod codegen_handler;
 pub mod file_handler;
 pub mod output_handler;
@@ -0,0 +1,325 @@
+use crate::codegen::ir_generator::IRGenerator;
+use crate::parse_handler::ParseHandler;
+use crate::parser::language_options::LanguageOptions;
+use crate::parser::program::Program;
+use crate::parser::visitor::condition_linter::ConditionLinter;
+use crate::parser::visitor::shadow_linter::{SHADOWED_VARIABLE, ShadowLinter};
+use crate::parser::visitor::symbol_table_builder::{Scope, Symbol, SymbolDiagnosticKind};
+use crate::parser::visitor::unused_linter::UnusedLinter;
+use crate::symbol_table_handler::SymbolTableHandler;
+use crate::utils::diagnostic::{Diagnostic, Severity};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Syntax error reported by the parser
+pub const SYNTAX_ERROR: u32 = 101;
+/// `#include` that could not be found, read or parsed
+pub const INCLUDE_ERROR: u32 = 102;
+/// Generated module rejected by the LLVM verifier
+pub const VERIFY_ERROR: u32 = 103;
+/// Source file or directory that could not be read
+pub const READ_ERROR: u32 = 104;
+
+/// Which way of running a script it is checked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CheckMode {
+    /// As `col` runs a file: a function sees only its own variables
+    #[default]
+    Program,
+    /// As [`Script`](crate::script::Script) compiles it: the variables declared
+    /// outside functions are globals every function can use
+    Script,
+}
+
+/// Which of the lints that are off by default to run
+#[derive(Debug, Clone, Default)]
+pub struct CheckOptions {
+    /// Warn about declarations shadowing a variable or parameter, see
+    /// [`ShadowLinter`]. These replace the notes the symbol table gives for the same
+    /// declarations.
+    pub warn_shadowing: bool,
+    /// Warn about local variables and parameters that are never used, see
+    /// [`UnusedLinter`]
+    pub warn_unused: bool,
+}
+
+/// What checking a script that has no errors found
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    /// Functions declared, nested ones included
+    pub functions: usize,
+    /// Variables declared, parameters included
+    pub variables: usize,
+    /// Warnings and notes, in the order they were found
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// The outcome of checking one file
+#[derive(Debug)]
+pub struct FileCheck {
+    pub path: PathBuf,
+    /// The file's source, for rendering diagnostic positions
+    pub content: String,
+    /// All diagnostics found, warnings included, if there was any error
+    pub result: Result<CheckReport, Vec<Diagnostic>>,
+}
+
+impl FileCheck {
+    pub fn passed(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Handle compiling a script for its diagnostics only, without executing it
+pub struct CheckHandler;
+
+impl CheckHandler {
+    /// Collect the diagnostics for the script `content` read from `path`, compiled
+    /// with `language`. Parse and symbol diagnostics accumulate; code generation only
+    /// runs when the earlier phases found no errors, and stops at its first.
+    pub fn check(
+        content: &str,
+        path: &Path,
+        language: &LanguageOptions,
+        options: &CheckOptions,
+    ) -> Vec<Diagnostic> {
+        match Self::check_source(content, path, true, language, options) {
+            Ok(report) => report.warnings,
+            Err(diagnostics) => diagnostics,
+        }
+    }
+
+    /// Lex, parse and build the symbol table for `content` read from `path`, and with
+    /// `generate_ir` also generate and verify its module, all as `language` says.
+    /// Nothing is ever executed, so no JIT is set up.
+    pub fn check_source(
+        content: &str,
+        path: &Path,
+        generate_ir: bool,
+        language: &LanguageOptions,
+        options: &CheckOptions,
+    ) -> Result<CheckReport, Vec<Diagnostic>> {
+        Self::check_source_with_mode(
+            content,
+            path,
+            generate_ir,
+            CheckMode::default(),
+            language,
+            options,
+        )
+    }
+
+    /// [`Self::check_source`] for running the script the way `mode` says
+    pub fn check_source_with_mode(
+        content: &str,
+        path: &Path,
+        generate_ir: bool,
+        mode: CheckMode,
+        language: &LanguageOptions,
+        options: &CheckOptions,
+    ) -> Result<CheckReport, Vec<Diagnostic>> {
+        let program =
+            ParseHandler::parse_program_with_options(content, language).map_err(|errors| {
+                errors
+                    .iter()
+                    .map(|err| {
+                        Diagnostic::new(
+                            SYNTAX_ERROR,
+                            Severity::Error,
+                            err.to_string(),
+                            Some(err.span().into_range()),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })?;
+
+        let program =
+            ParseHandler::resolve_includes(program, content, path, language).map_err(|e| {
+                vec![Diagnostic::new(
+                    INCLUDE_ERROR,
+                    Severity::Error,
+                    e.to_string(),
+                    None,
+                )]
+            })?;
+
+        let (root_scope, symbol_diagnostics) = match mode {
+            CheckMode::Program => SymbolTableHandler::build_symbol_table(&program),
+            CheckMode::Script => SymbolTableHandler::build_script_symbol_table(&program),
+        };
+        let mut diagnostics: Vec<Diagnostic> = symbol_diagnostics
+            .iter()
+            .map(|diagnostic| program.relocate(Diagnostic::from(diagnostic)))
+            .collect();
+        let lints = Self::lint(&program, options);
+        if options.warn_shadowing {
+            // The lint says more about the declarations it warns about
+            let place = |d: &Diagnostic| (d.span.clone(), d.file.as_ref().map(|file| file.start));
+            let shadows: Vec<_> = lints
+                .iter()
+                .filter(|d| d.code == SHADOWED_VARIABLE)
+                .map(place)
+                .collect();
+            diagnostics.retain(|d| {
+                d.code != SymbolDiagnosticKind::Shadowing.code() || !shadows.contains(&place(d))
+            });
+        }
+        diagnostics.extend(lints);
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            return Err(diagnostics);
+        }
+
+        if generate_ir {
+            let context = inkwell::context::Context::create();
+            let mut ir_generator = IRGenerator::new(&context, "check_module");
+            ir_generator.persistent_globals = mode == CheckMode::Script;
+            ir_generator.div_by_zero = language.div_by_zero;
+            ir_generator.exact_integers = language.exact_integers;
+            ir_generator.strict_math = language.strict_math;
+            ir_generator.strict_returns = language.strict_returns;
+            ir_generator.max_call_depth = language.max_call_depth;
+            let result = program.accept(&mut ir_generator);
+            diagnostics.extend(
+                ir_generator
+                    .take_diagnostics()
+                    .into_iter()
+                    .map(|diagnostic| program.relocate(diagnostic)),
+            );
+            if let Err(e) = result {
+                diagnostics.push(program.relocate(Diagnostic::from(&e)));
+                return Err(diagnostics);
+            }
+            if let Err(message) = ir_generator.get_module().verify() {
+                diagnostics.push(Diagnostic::new(
+                    VERIFY_ERROR,
+                    Severity::Error,
+                    message.to_string(),
+                    None,
+                ));
+                return Err(diagnostics);
+            }
+        }
+
+        let (functions, variables) = Self::count_symbols(&root_scope);
+        Ok(CheckReport {
+            functions,
+            variables,
+            warnings: diagnostics,
+        })
+    }
+
+    /// Warnings about code that is valid but probably not what was meant, placed
+    /// in the included files they are in, with the lints `options` turn on
+    pub fn lint(program: &Program, options: &CheckOptions) -> Vec<Diagnostic> {
+        let mut linter = ConditionLinter::new();
+        program.accept(&mut linter);
+        let mut diagnostics = linter.into_diagnostics();
+        if options.warn_shadowing {
+            diagnostics.extend(ShadowLinter::lint(program));
+        }
+        if options.warn_unused {
+            diagnostics.extend(UnusedLinter::lint(program));
+        }
+        diagnostics
+            .into_iter()
+            .map(|diagnostic| program.relocate(diagnostic))
+            .collect()
+    }
+
+    /// Check `path`, or every `.gml` file under it if it is a directory, in path order
+    pub fn check_path(
+        path: &Path,
+        generate_ir: bool,
+        mode: CheckMode,
+        language: &LanguageOptions,
+        options: &CheckOptions,
+    ) -> Vec<FileCheck> {
+        let mut files = vec![];
+        let mut checks = vec![];
+        Self::collect_sources(path, &mut files, &mut checks);
+        files.sort();
+        for file in files {
+            let check = match fs::read_to_string(&file) {
+                Ok(content) => FileCheck {
+                    result: Self::check_source_with_mode(
+                        &content,
+                        &file,
+                        generate_ir,
+                        mode,
+                        language,
+                        options,
+                    ),
+                    path: file,
+                    content,
+                },
+                Err(e) => Self::read_failure(file, e),
+            };
+            checks.push(check);
+        }
+        checks
+    }
+
+    /// Process exit status for a batch of checks: 0 if every file passed, 1 otherwise
+    pub fn exit_status(checks: &[FileCheck]) -> i32 {
+        if checks.iter().all(FileCheck::passed) {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn collect_sources(path: &Path, files: &mut Vec<PathBuf>, failures: &mut Vec<FileCheck>) {
+        if !path.is_dir() {
+            files.push(path.to_path_buf());
+            return;
+        }
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                failures.push(Self::read_failure(path.to_path_buf(), e));
+                return;
+            }
+        };
+        for entry in entries.flatten() {
+            let entry_path = entry.path();
+            if entry_path.is_dir() {
+                Self::collect_sources(&entry_path, files, failures);
+            } else if entry_path.extension().is_some_and(|ext| ext == "gml") {
+                files.push(entry_path);
+            }
+        }
+    }
+
+    fn read_failure(path: PathBuf, error: std::io::Error) -> FileCheck {
+        let message = format!("Cannot read '{}': {}", path.display(), error);
+        FileCheck {
+            path,
+            content: String::new(),
+            result: Err(vec![Diagnostic::new(
+                READ_ERROR,
+                Severity::Error,
+                message,
+                None,
+            )]),
+        }
+    }
+
+    /// Count the functions and variables declared anywhere in `scope`
+    fn count_symbols(scope: &Scope) -> (usize, usize) {
+        let mut functions = 0;
+        let mut variables = 0;
+        for symbol in scope.table.values() {
+            match symbol {
+                Symbol::Function { .. } => functions += 1,
+                Symbol::Variable => variables += 1,
+                Symbol::EnumMember => {}
+            }
+        }
+        for child in &scope.children {
+            let (child_functions, child_variables) = Self::count_symbols(child);
+            functions += child_functions;
+            variables += child_variables;
+        }
+        (functions, variables)
+    }
+}
@@ -1,96 +1,383 @@
 use crate::codegen;
+use crate::output_handler::{OutputHandler, OutputSink, SectionKind};
+use crate::parser::language_options::LanguageOptions;
 use crate::parser::*;
+use crate::utils::number_format::format_number;
 use owo_colors::OwoColorize;
+use std::path::Path;
+
+/// How [`CodeGenHandler`] compiles and runs a program, beyond the semantics
+/// [`LanguageOptions`] give it
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeGenOptions {
+    /// How deeply expressions may nest before code generation fails
+    pub max_expression_depth: usize,
+    /// How many lines of a function's IR to show when it fails verification
+    pub verify_excerpt_lines: usize,
+    /// Print per-function compile statistics after IR generation
+    pub verbose: bool,
+    /// Emit DWARF debug information with the generated IR
+    pub debug_info: bool,
+    /// Count calls and time per function while the script runs, and print them
+    /// after it finishes
+    pub profiling: bool,
+    /// Record the statements the script runs, and print the most recent ones after
+    /// it finishes, whether or not it failed. Needs the source, to place them.
+    pub tracing: bool,
+    /// Write the generated IR to `Sample.ll` after displaying it, as by default
+    pub save_ir: bool,
+    /// Inline calls to functions that only return an expression of at most this
+    /// many nodes, see [`inliner`](crate::parser::inliner). 0, the default, turns
+    /// inlining off.
+    pub inline_threshold: usize,
+    /// Drop the branches of `if`s, `while`s and ternaries whose condition is a
+    /// literal, see [`dead_branches`](crate::parser::dead_branches). Pruning runs
+    /// after inlining, so it also sees conditions inlining made constant.
+    pub prune_dead_branches: bool,
+    /// Compare numbers with `==`, `!=`, `<=` and `>=` within this epsilon, which
+    /// scripts can change with `math_set_epsilon`. `None`, the default, compares
+    /// exactly.
+    pub math_epsilon: Option<f64>,
+    /// Generate `repeat` loops with a literal count of at most this as that many
+    /// copies of their body, see [`unroll`](crate::codegen::ir_generator::unroll).
+    /// 0 keeps every loop.
+    pub max_unrolled_repeat: usize,
+}
+
+impl Default for CodeGenOptions {
+    fn default() -> Self {
+        Self {
+            max_expression_depth: codegen::ir_generator::DEFAULT_MAX_EXPRESSION_DEPTH,
+            verify_excerpt_lines: codegen::ir_generator::DEFAULT_VERIFY_EXCERPT_LINES,
+            verbose: false,
+            debug_info: false,
+            profiling: false,
+            tracing: false,
+            save_ir: true,
+            inline_threshold: 0,
+            prune_dead_branches: false,
+            math_epsilon: None,
+            max_unrolled_repeat: codegen::ir_generator::unroll::DEFAULT_MAX_UNROLLED_REPEAT,
+        }
+    }
+}
 
 /// Handle code generation and execution
 pub struct CodeGenHandler;
 
 impl CodeGenHandler {
-    /// Generate LLVM IR and execute with JIT
-    pub fn generate_ir_and_execute(program: &program::Program) {
-        println!("{}", "Generating LLVM IR...".green());
+    /// Generate LLVM IR and execute with JIT.
+    /// Returns the script's top-level return value (0.0 if it never returns).
+    ///
+    /// `source` is the file the program was parsed from, which debug information
+    /// refers to; without it none is emitted. `options` are the semantics the
+    /// program is compiled with, and `settings` how.
+    pub fn generate_ir_and_execute(
+        out: &mut dyn OutputSink,
+        program: &program::Program,
+        source: Option<(&Path, &str)>,
+        options: &LanguageOptions,
+        settings: &CodeGenOptions,
+    ) -> Option<f64> {
+        let inlined;
+        let program = match settings.inline_threshold {
+            0 => program,
+            threshold => {
+                let mut copy = program.clone();
+                let count = inliner::inline_small_functions(&mut copy, threshold);
+                if settings.verbose {
+                    out.write_section(
+                        SectionKind::Statistics,
+                        &format!("{} {}\n", "Inlined calls:".green(), count),
+                    );
+                }
+                inlined = copy;
+                &inlined
+            }
+        };
+        let pruned;
+        let program = if settings.prune_dead_branches {
+            let mut copy = program.clone();
+            let count = dead_branches::prune_dead_branches(&mut copy);
+            if settings.verbose {
+                out.write_section(
+                    SectionKind::Statistics,
+                    &format!("{} {}\n", "Pruned branches:".green(), count),
+                );
+            }
+            pruned = copy;
+            &pruned
+        } else {
+            program
+        };
+
+        out.write_section(
+            SectionKind::Status,
+            &format!("{}\n", "Generating LLVM IR...".green()),
+        );
         let context = inkwell::context::Context::create();
         let mut ir_generator = codegen::ir_generator::IRGenerator::new(&context, "main_module");
+        ir_generator.max_expression_depth = settings.max_expression_depth;
+        ir_generator.verify_excerpt_lines = settings.verify_excerpt_lines;
+        ir_generator.epsilon_comparisons = settings.math_epsilon.is_some();
+        ir_generator.profiling = settings.profiling;
+        ir_generator.tracing = settings.tracing && source.is_some();
+        ir_generator.div_by_zero = options.div_by_zero;
+        ir_generator.exact_integers = options.exact_integers;
+        ir_generator.strict_math = options.strict_math;
+        ir_generator.strict_returns = options.strict_returns;
+        ir_generator.max_unrolled_repeat = settings.max_unrolled_repeat;
+        ir_generator.max_call_depth = options.max_call_depth;
+        if settings.verbose {
+            ir_generator.collect_stats();
+        }
+        if let (true, Some((path, content))) = (settings.debug_info, source) {
+            ir_generator.enable_debug_info(path, content);
+            ir_generator.enable_included_debug_info(&program.included);
+        }
 
-        match program.accept(&mut ir_generator) {
+        let result = program.accept(&mut ir_generator);
+        Self::display_warnings(
+            out,
+            &ir_generator,
+            program,
+            source.map(|(_, content)| content),
+        );
+        match result {
             Ok(_) => {
-                println!("{}", "IR Generation completed successfully!".green());
+                out.write_section(
+                    SectionKind::Status,
+                    &format!("{}\n", "IR Generation completed successfully!".green()),
+                );
+                if settings.verbose {
+                    OutputHandler::display_compile_stats(out, &ir_generator.take_stats());
+                    out.write_section(
+                        SectionKind::Statistics,
+                        &format!(
+                            "{} {}\n",
+                            "Deepest loop nesting:".green(),
+                            ir_generator.max_loop_depth()
+                        ),
+                    );
+                }
 
                 // Display and save generated IR
-                crate::output_handler::OutputHandler::display_and_save_ir(&ir_generator);
+                OutputHandler::display_and_save_ir(out, &ir_generator, settings.save_ir);
 
                 // Verify and execute the module
-                Self::verify_and_execute_module(&ir_generator);
+                Self::verify_and_execute_module(
+                    out,
+                    &ir_generator,
+                    source.map(|(_, content)| content),
+                    settings,
+                )
             }
             Err(e) => {
-                println!("{}", format!("IR Generation failed: {:?}", e).red());
+                Self::display_generation_error(out, &e);
+                None
             }
         }
     }
 
-    /// Verify the module and execute with JIT if successful
-    fn verify_and_execute_module(ir_generator: &codegen::ir_generator::IRGenerator) {
+    /// Generate IR for `program`, parsed from `content`, as
+    /// [`Self::generate_ir_and_execute`] would, and display each top-level statement
+    /// and function with the IR generated for it, without running anything. Returns
+    /// whether generation succeeded.
+    pub fn display_annotated_ir(
+        out: &mut dyn OutputSink,
+        program: &program::Program,
+        content: &str,
+        options: &LanguageOptions,
+        settings: &CodeGenOptions,
+    ) -> bool {
+        let context = inkwell::context::Context::create();
+        let mut ir_generator = codegen::ir_generator::IRGenerator::new(&context, "main_module");
+        ir_generator.max_expression_depth = settings.max_expression_depth;
+        ir_generator.verify_excerpt_lines = settings.verify_excerpt_lines;
+        ir_generator.epsilon_comparisons = settings.math_epsilon.is_some();
+        ir_generator.div_by_zero = options.div_by_zero;
+        ir_generator.exact_integers = options.exact_integers;
+        ir_generator.strict_math = options.strict_math;
+        ir_generator.strict_returns = options.strict_returns;
+        ir_generator.max_unrolled_repeat = settings.max_unrolled_repeat;
+        ir_generator.max_call_depth = options.max_call_depth;
+        ir_generator.record_annotations();
+
+        let result = program.accept(&mut ir_generator);
+        Self::display_warnings(out, &ir_generator, program, Some(content));
+        match result {
+            Ok(_) => {
+                let mut snippets = ir_generator.take_annotations();
+                // Code spliced in from included files has no lines in `content`
+                snippets.retain(|snippet| program.included_at(snippet.start).is_none());
+                OutputHandler::display_annotated_ir(out, &snippets, content);
+                true
+            }
+            Err(e) => {
+                Self::display_generation_error(out, &e);
+                false
+            }
+        }
+    }
+
+    fn display_generation_error(
+        out: &mut dyn OutputSink,
+        error: &codegen::ir_generator::IRGenError,
+    ) {
+        let diagnostic = crate::utils::diagnostic::Diagnostic::from(error);
+        out.write_section(
+            SectionKind::Diagnostics,
+            &format!(
+                "{}\n",
+                format!("IR Generation failed: {}", diagnostic).red()
+            ),
+        );
+    }
+
+    /// Display the warnings found during generation, with positions if the source
+    /// they refer to is known. `program` is what was generated, which places
+    /// warnings in the files it included.
+    fn display_warnings(
+        out: &mut dyn OutputSink,
+        ir_generator: &codegen::ir_generator::IRGenerator,
+        program: &program::Program,
+        content: Option<&str>,
+    ) {
+        match content {
+            Some(content) => {
+                let diagnostics: Vec<_> = ir_generator
+                    .diagnostics()
+                    .iter()
+                    .map(|diagnostic| program.relocate(diagnostic.clone()))
+                    .collect();
+                OutputHandler::display_diagnostics(out, &diagnostics, content)
+            }
+            None if ir_generator.diagnostics().is_empty() => {}
+            None => {
+                let text: String = ir_generator
+                    .diagnostics()
+                    .iter()
+                    .map(|diagnostic| format!("{}\n", diagnostic.to_string().yellow()))
+                    .collect();
+                out.write_section(SectionKind::Diagnostics, &text);
+            }
+        }
+    }
+
+    /// Verify the module and execute with JIT if successful. `content` is the
+    /// source the module was generated from, if known.
+    fn verify_and_execute_module(
+        out: &mut dyn OutputSink,
+        ir_generator: &codegen::ir_generator::IRGenerator,
+        content: Option<&str>,
+        settings: &CodeGenOptions,
+    ) -> Option<f64> {
         if let Err(errors) = ir_generator.get_module().verify() {
-            println!("{}", "Module verification failed:".red());
-            println!("{}", errors.to_string().red());
+            out.write_section(
+                SectionKind::Diagnostics,
+                &format!(
+                    "{}\n{}\n",
+                    "Module verification failed:".red(),
+                    errors.to_string().red()
+                ),
+            );
+            None
         } else {
-            println!("{}", "Module verification passed!".green());
-            Self::execute_with_jit(ir_generator);
+            out.write_section(
+                SectionKind::Status,
+                &format!("{}\n", "Module verification passed!".green()),
+            );
+            Self::execute_with_jit(out, ir_generator, content, settings)
         }
     }
 
     /// Execute the generated code using JIT
-    fn execute_with_jit(ir_generator: &codegen::ir_generator::IRGenerator) {
-        println!("\n{}", "Executing with JIT...".green());
+    fn execute_with_jit(
+        out: &mut dyn OutputSink,
+        ir_generator: &codegen::ir_generator::IRGenerator,
+        content: Option<&str>,
+        settings: &CodeGenOptions,
+    ) -> Option<f64> {
+        out.write_section(
+            SectionKind::Status,
+            &format!("\n{}\n", "Executing with JIT...".green()),
+        );
 
         match codegen::jit::JITExecutor::new(ir_generator.get_module()) {
             Ok(executor) => {
+                if let Some(epsilon) = settings.math_epsilon {
+                    executor.set_math_epsilon(epsilon);
+                }
+
                 // Execute main function
-                Self::execute_main_function(&executor);
+                let result = Self::execute_main_function(out, &executor);
 
                 // Try to execute test functions
-                Self::execute_test_functions(&executor);
+                Self::execute_test_functions(out, &executor);
+                if settings.profiling {
+                    OutputHandler::display_profile(out, &executor.profile().functions());
+                }
+                if let (true, Some(content)) = (ir_generator.tracing, content) {
+                    OutputHandler::display_trace(out, &executor.trace().take(content));
+                }
+                result
             }
             Err(e) => {
-                println!("{}", format!("Failed to create JIT executor: {}", e).red());
+                out.write_section(
+                    SectionKind::Diagnostics,
+                    &format!(
+                        "{}\n",
+                        format!("Failed to create JIT executor: {}", e).red()
+                    ),
+                );
+                None
             }
         }
     }
 
     /// Execute the main function
-    fn execute_main_function(executor: &codegen::jit::JITExecutor) {
+    fn execute_main_function(
+        out: &mut dyn OutputSink,
+        executor: &codegen::jit::JITExecutor,
+    ) -> Option<f64> {
         match executor.execute_main() {
             Ok(result) => {
-                println!("{} {}", "Main function returned:".green(), result);
+                out.write_section(
+                    SectionKind::Status,
+                    &format!(
+                        "{} {}\n",
+                        "Main function returned:".green(),
+                        format_number(result)
+                    ),
+                );
+                Some(result)
             }
             Err(e) => {
-                println!("{}", format!("JIT execution failed: {}", e).red());
+                out.write_section(
+                    SectionKind::Diagnostics,
+                    &format!("{}\n", format!("JIT execution failed: {}", e).red()),
+                );
+                None
             }
         }
     }
 
     /// Execute test functions if they exist
-    fn execute_test_functions(executor: &codegen::jit::JITExecutor) {
-        // Try to execute the test_short_circuit function if it exists
-        match executor.execute_function("test_short_circuit", &[]) {
-            Ok(result) => {
-                println!("{} {}", "test_short_circuit() returned:".green(), result);
-            }
-            Err(e) => {
-                println!(
-                    "{}",
-                    format!("test_short_circuit execution failed: {}", e).yellow()
-                );
-            }
-        }
-
-        // Try to execute the test_loops function if it exists
-        match executor.execute_function("test_loops", &[]) {
-            Ok(result) => {
-                println!("{} {}", "test_loops() returned:".green(), result);
-            }
-            Err(e) => {
-                println!("{}", format!("test_loops execution failed: {}", e).yellow());
+    fn execute_test_functions(out: &mut dyn OutputSink, executor: &codegen::jit::JITExecutor) {
+        for name in ["test_short_circuit", "test_loops"] {
+            match executor.execute_function(name, &[]) {
+                Ok(result) => out.write_section(
+                    SectionKind::Status,
+                    &format!(
+                        "{} {}\n",
+                        format!("{}() returned:", name).green(),
+                        format_number(result)
+                    ),
+                ),
+                Err(e) => out.write_section(
+                    SectionKind::Status,
+                    &format!("{}\n", format!("{} execution failed: {}", name, e).yellow()),
+                ),
             }
         }
     }
@@ -1,11 +1,85 @@
+use crate::output_handler::{OutputSink, SectionKind};
+use crate::parse_handler::IncludeError;
 use owo_colors::OwoColorize;
+use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of a project manifest, listing the project's source files
+pub const MANIFEST_NAME: &str = "col.toml";
+
+/// One source file of a project, named by its path for diagnostics
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// Why loading or merging a multi-file project failed
+#[derive(Debug)]
+pub enum ProjectError {
+    /// A manifest, directory or source file could not be read
+    Read { path: PathBuf, message: String },
+    /// The manifest is not of the form `files = ["a.gml", ...]`
+    Manifest { path: PathBuf, message: String },
+    /// The project has no source files
+    Empty { path: PathBuf },
+    /// A source file has syntax errors, as `line:column: message`
+    Parse {
+        path: PathBuf,
+        messages: Vec<String>,
+    },
+    /// A source file's `#include` could not be resolved
+    Include(IncludeError),
+    /// Two files define a top-level function of the same name
+    DuplicateFunction {
+        name: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Read { path, message } => {
+                write!(f, "Failed to read '{}': {}", path.display(), message)
+            }
+            ProjectError::Manifest { path, message } => {
+                write!(f, "Invalid manifest '{}': {}", path.display(), message)
+            }
+            ProjectError::Empty { path } => {
+                write!(f, "No .gml files found in '{}'", path.display())
+            }
+            ProjectError::Parse { path, messages } => {
+                write!(f, "Failed to parse '{}':", path.display())?;
+                for message in messages {
+                    write!(f, "\n  {}:{}", path.display(), message)?;
+                }
+                Ok(())
+            }
+            ProjectError::Include(e) => write!(f, "{}", e),
+            ProjectError::DuplicateFunction {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "Function '{}' is defined in both '{}' and '{}'",
+                name,
+                first.display(),
+                second.display()
+            ),
+        }
+    }
+}
 
 /// Handle file operations
 pub struct FileHandler;
 
 impl FileHandler {
     /// Read and validate the source file
+    #[allow(clippy::result_unit_err)] // errors are reported before returning
     pub fn read_source_file(path: &str) -> Result<String, ()> {
         match fs::read_to_string(path) {
             Ok(content) => Ok(content),
@@ -21,12 +95,144 @@ impl FileHandler {
         }
     }
 
+    /// Whether `path` names a project rather than a single script: a directory or a manifest
+    pub fn is_project(path: &Path) -> bool {
+        path.is_dir() || path.file_name().is_some_and(|name| name == MANIFEST_NAME)
+    }
+
+    /// Read every source file of the project at `path`.
+    ///
+    /// A manifest, or a directory containing one, lists the files relative to the
+    /// manifest and they are returned in that order. Any other directory contributes
+    /// every `.gml` file under it, in path order.
+    pub fn read_project(path: &Path) -> Result<Vec<SourceFile>, ProjectError> {
+        let manifest = if path.is_dir() {
+            Some(path.join(MANIFEST_NAME)).filter(|manifest| manifest.is_file())
+        } else {
+            Some(path.to_path_buf())
+        };
+
+        let paths = match manifest {
+            Some(manifest) => {
+                let text = Self::read(&manifest)?;
+                let dir = manifest.parent().unwrap_or(Path::new(""));
+                Self::manifest_files(&text)
+                    .map_err(|message| ProjectError::Manifest {
+                        path: manifest.clone(),
+                        message,
+                    })?
+                    .into_iter()
+                    .map(|file| dir.join(file))
+                    .collect()
+            }
+            None => {
+                let mut paths = vec![];
+                Self::collect_gml_files(path, &mut paths)?;
+                paths.sort();
+                paths
+            }
+        };
+        if paths.is_empty() {
+            return Err(ProjectError::Empty {
+                path: path.to_path_buf(),
+            });
+        }
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let content = Self::read(&path)?;
+                Ok(SourceFile { path, content })
+            })
+            .collect()
+    }
+
     /// Save LLVM IR to file
-    pub fn save_ir_to_file(ir_string: &str) {
+    pub fn save_ir_to_file(out: &mut dyn OutputSink, ir_string: &str) {
         let ir_path = "Sample.ll";
         match fs::write(ir_path, ir_string) {
-            Ok(_) => println!("{} '{}'", "LLVM IR saved to".green(), ir_path),
-            Err(e) => eprintln!("{} {}", "Failed to write IR file:".red(), e),
+            Ok(_) => out.write_section(
+                SectionKind::Status,
+                &format!("{} '{}'\n", "LLVM IR saved to".green(), ir_path),
+            ),
+            Err(e) => out.write_section(
+                SectionKind::Diagnostics,
+                &format!("{} {}\n", "Failed to write IR file:".red(), e),
+            ),
+        }
+    }
+
+    fn read(path: &Path) -> Result<String, ProjectError> {
+        fs::read_to_string(path).map_err(|e| ProjectError::Read {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })
+    }
+
+    fn collect_gml_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ProjectError> {
+        let entries = fs::read_dir(dir).map_err(|e| ProjectError::Read {
+            path: dir.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        for entry in entries.flatten() {
+            let entry_path = entry.path();
+            if entry_path.is_dir() {
+                Self::collect_gml_files(&entry_path, files)?;
+            } else if entry_path.extension().is_some_and(|ext| ext == "gml") {
+                files.push(entry_path);
+            }
+        }
+        Ok(())
+    }
+
+    /// The file list of a manifest. Only the `files` key is read, as an array of
+    /// strings that may span several lines; other keys and `#` comments are ignored.
+    fn manifest_files(text: &str) -> Result<Vec<String>, String> {
+        let text: Vec<&str> = text.lines().map(strip_comment).collect();
+        let text = text.join("\n");
+        let mut offset = 0;
+        let mut value = None;
+        for line in text.split_inclusive('\n') {
+            match line.split_once('=') {
+                Some((key, _)) if key.trim() == "files" => {
+                    value = Some(&text[offset + key.len() + 1..]);
+                    break;
+                }
+                _ => offset += line.len(),
+            }
+        }
+        let value = value.ok_or_else(|| "missing `files = [...]`".to_string())?;
+
+        let array = value
+            .trim_start()
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .map(|(items, _)| items)
+            .ok_or_else(|| "`files` must be an array of file names".to_string())?;
+
+        array
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                item.strip_prefix('"')
+                    .and_then(|item| item.strip_suffix('"'))
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("expected a quoted file name, found `{}`", item))
+            })
+            .collect()
+    }
+}
+
+/// `line` without a trailing `#` comment, leaving `#` inside strings alone
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
         }
     }
+    line
 }
@@ -1,21 +1,155 @@
 use crate::codegen;
+use crate::codegen::ir_generator::annotations::IrSnippet;
 use crate::parser::*;
+use crate::token::{Token, TokenCategory, TokenInfo};
+use crate::utils::diagnostic::{Diagnostic, Severity};
 use owo_colors::OwoColorize;
+use std::fmt::Write;
+
+/// What part of the pipeline's output a section is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SectionKind {
+    /// The source code, or the files a project was loaded from
+    SourceListing,
+    /// The tokens the lexer produced
+    LexerDump,
+    /// The parsed program
+    Ast,
+    SymbolTable,
+    /// The generated LLVM IR, plain or annotated with the source
+    Ir,
+    /// Errors, warnings and notes from any stage
+    Diagnostics,
+    /// Compile statistics and the profile of a run
+    Statistics,
+    /// Progress of code generation and what the script returned
+    Status,
+}
+
+/// Where the handlers send everything they display. Each call is one whole
+/// section, ending in a line break, with the colors the command line shows.
+pub trait OutputSink {
+    fn write_section(&mut self, kind: SectionKind, text: &str);
+}
+
+/// Lets a closure receive the sections, e.g. to forward them to a host's console
+impl<F: FnMut(SectionKind, &str)> OutputSink for F {
+    fn write_section(&mut self, kind: SectionKind, text: &str) {
+        self(kind, text)
+    }
+}
+
+/// Prints every section to stdout, as the command line does
+#[derive(Debug, Default)]
+pub struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn write_section(&mut self, _kind: SectionKind, text: &str) {
+        print!("{}", text);
+    }
+}
+
+/// Keeps every section in memory, in the order it was written
+#[derive(Debug, Default)]
+pub struct BufferSink {
+    pub sections: Vec<(SectionKind, String)>,
+}
+
+impl BufferSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The text of every section of `kind`, in order
+    pub fn text(&self, kind: SectionKind) -> String {
+        self.sections
+            .iter()
+            .filter(|(section, _)| *section == kind)
+            .map(|(_, text)| text.as_str())
+            .collect()
+    }
+
+    /// How many sections of `kind` were written
+    pub fn count(&self, kind: SectionKind) -> usize {
+        self.sections
+            .iter()
+            .filter(|(section, _)| *section == kind)
+            .count()
+    }
+}
+
+impl OutputSink for BufferSink {
+    fn write_section(&mut self, kind: SectionKind, text: &str) {
+        self.sections.push((kind, text.to_string()));
+    }
+}
 
 /// Handle output display operations
 pub struct OutputHandler;
 
 impl OutputHandler {
     /// Display the original source code
-    pub fn display_original_code(content: &str) {
-        println!();
-        println!("----------Output----------");
-        println!();
-        println!("{}\n {}\n", "Original Code:".green(), content);
+    pub fn display_original_code(out: &mut dyn OutputSink, content: &str) {
+        let text = format!(
+            "\n----------Output----------\n\n{}\n {}\n\n",
+            "Original Code:".green(),
+            content
+        );
+        out.write_section(SectionKind::SourceListing, &text);
+    }
+
+    /// Display one token per line with its position and category
+    pub fn display_token_table(out: &mut dyn OutputSink, tokens: &[TokenInfo]) {
+        let mut table = String::new();
+        let _ = writeln!(table, "{}", "Tokens:".green());
+        for info in tokens {
+            let position = format!("{}:{}", info.line, info.column);
+            let category = format!("{:<12}", format!("{:?}", info.token.category()));
+            let text = match info.token {
+                Token::Newline => "↵".to_string(),
+                ref token => format!("{:?}", token),
+            };
+            let (category, text) = match info.token.category() {
+                TokenCategory::Keyword => {
+                    (category.magenta().to_string(), text.magenta().to_string())
+                }
+                TokenCategory::Operator => {
+                    (category.yellow().to_string(), text.yellow().to_string())
+                }
+                TokenCategory::Punctuation => (category, text),
+                TokenCategory::Newline => (category.blue().to_string(), text.blue().to_string()),
+                TokenCategory::Identifier => (category.cyan().to_string(), text.cyan().to_string()),
+                TokenCategory::String => (category.green().to_string(), text.green().to_string()),
+                TokenCategory::Number => (
+                    category.bright_blue().to_string(),
+                    text.bright_blue().to_string(),
+                ),
+                TokenCategory::Error => (category.red().to_string(), text.red().to_string()),
+            };
+            let _ = writeln!(table, "{:>9}  {} {}", position, category, text);
+        }
+        table.push('\n');
+        out.write_section(SectionKind::LexerDump, &table);
+    }
+
+    /// Display the files a project was loaded from, in the order they are merged
+    pub fn display_project_files(
+        out: &mut dyn OutputSink,
+        files: &[crate::file_handler::SourceFile],
+    ) {
+        let mut text = format!(
+            "\n----------Output----------\n\n{}\n",
+            "Project Files:".green()
+        );
+        for file in files {
+            let _ = writeln!(text, " {}", file.path.display());
+        }
+        text.push('\n');
+        out.write_section(SectionKind::SourceListing, &text);
     }
 
     /// Display the parsed AST
-    pub fn display_ast(program: &program::Program) {
+    pub fn display_ast(out: &mut dyn OutputSink, program: &program::Program) {
         // Set to true for pretty-printing the AST
         let is_pretty_print_ast = false;
         let debug_str = if is_pretty_print_ast {
@@ -23,15 +157,106 @@ impl OutputHandler {
         } else {
             format!("{:?}", program)
         };
-        println!(
-            "{}\n {}\n",
+        let mut text = format!(
+            "\n{}\n {}\n\n",
             "AST Parsed:".green(),
             crate::utils::colorize::colorize_brackets(&debug_str)
         );
+
+        let functions: Vec<String> = program
+            .functions()
+            .map(|func_def| format!("{}/{}", func_def.name, func_def.func.args.len()))
+            .collect();
+        if !functions.is_empty() {
+            let _ = writeln!(text, "{} {}\n", "Functions:".green(), functions.join(", "));
+        }
+        out.write_section(SectionKind::Ast, &text);
+    }
+
+    /// Display per-function compile statistics as a table
+    pub fn display_compile_stats(
+        out: &mut dyn OutputSink,
+        stats: &[codegen::ir_generator::compile_stats::FunctionStats],
+    ) {
+        let mut text = String::new();
+        let _ = writeln!(text, "{}", "Compile Statistics:".green());
+        let width = stats
+            .iter()
+            .map(|s| s.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("function".len());
+        let _ = writeln!(
+            text,
+            "  {:<width$}  {:>6}  {:>12}  {:>7}  {:>9}",
+            "function", "blocks", "instructions", "allocas", "time (us)"
+        );
+        for s in stats {
+            let _ = writeln!(
+                text,
+                "  {:<width$}  {:>6}  {:>12}  {:>7}  {:>9}",
+                s.name, s.blocks, s.instructions, s.allocas, s.micros
+            );
+        }
+        text.push('\n');
+        out.write_section(SectionKind::Statistics, &text);
+    }
+
+    /// Display how often each script function was called and how long it ran
+    pub fn display_profile(
+        out: &mut dyn OutputSink,
+        functions: &[codegen::runtime::profile::FunctionProfile],
+    ) {
+        let mut text = String::new();
+        let _ = writeln!(text, "\n{}", "Profile:".green());
+        let width = functions
+            .iter()
+            .map(|f| f.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("function".len());
+        let _ = writeln!(
+            text,
+            "  {:<width$}  {:>8}  {:>12}",
+            "function", "calls", "time (us)"
+        );
+        for f in functions {
+            let _ = writeln!(
+                text,
+                "  {:<width$}  {:>8}  {:>12}",
+                f.name,
+                f.calls,
+                f.total_ns / 1000
+            );
+        }
+        text.push('\n');
+        out.write_section(SectionKind::Statistics, &text);
+    }
+
+    /// Display the statements a run executed most recently, oldest first
+    pub fn display_trace(
+        out: &mut dyn OutputSink,
+        entries: &[codegen::runtime::trace::TraceEntry],
+    ) {
+        let mut text = String::new();
+        let _ = writeln!(text, "\n{}", "Trace:".green());
+        let _ = writeln!(text, "  {:>8}  {:>6}  {:>6}", "hit", "line", "column");
+        for entry in entries {
+            let _ = writeln!(
+                text,
+                "  {:>8}  {:>6}  {:>6}",
+                entry.hit_order, entry.line, entry.column
+            );
+        }
+        text.push('\n');
+        out.write_section(SectionKind::Statistics, &text);
     }
 
     /// Display symbol table
-    pub fn display_symbol_table(root_scope: &visitor::symbol_table_builder::Scope) {
+    pub fn display_symbol_table(
+        out: &mut dyn OutputSink,
+        root_scope: &visitor::symbol_table_builder::Scope,
+    ) {
         let is_pretty_print_symbol_table = true;
         let symbol_table_debug_str = if is_pretty_print_symbol_table {
             format!("{:#?}", root_scope)
@@ -39,21 +264,188 @@ impl OutputHandler {
             format!("{:?}", root_scope)
         };
 
-        println!(
-            "{}\n {}\n",
+        let text = format!(
+            "{}\n {}\n\n",
             "Symbol Table:".green(),
             crate::utils::colorize::colorize_brackets(&symbol_table_debug_str)
         );
+        out.write_section(SectionKind::SymbolTable, &text);
+    }
+
+    /// Display declaration, shadowing and undeclared-variable diagnostics with
+    /// line/column positions in `content`, the source of `program`, or with the path
+    /// too for those in a file it included
+    pub fn display_symbol_diagnostics(
+        out: &mut dyn OutputSink,
+        diagnostics: &[visitor::symbol_table_builder::SymbolDiagnostic],
+        program: &program::Program,
+        content: &str,
+    ) {
+        use crate::utils::line_index::LineIndex;
+        use visitor::symbol_table_builder::SymbolDiagnosticKind;
+
+        if diagnostics.is_empty() {
+            return;
+        }
+        let index = LineIndex::new(content);
+        let position = |offset: usize| match program.included_at(offset) {
+            Some((file, offset)) => {
+                let (line, col) = LineIndex::new(&file.content).line_col(offset);
+                format!("{}:{}:{}", file.path.display(), line, col)
+            }
+            None => {
+                let (line, col) = index.line_col(offset);
+                format!("{}:{}", line, col)
+            }
+        };
+        let mut lines = String::new();
+        for diagnostic in diagnostics {
+            let mut text = format!(
+                "{}: '{}': {}",
+                position(diagnostic.second.start),
+                diagnostic.name,
+                diagnostic.kind.description()
+            );
+            if diagnostic.kind != SymbolDiagnosticKind::UndeclaredVariable {
+                text.push_str(&format!(
+                    " (first declared at {})",
+                    position(diagnostic.first.start)
+                ));
+            }
+            let _ = match diagnostic.kind.severity() {
+                Severity::Error => writeln!(lines, "{} {}", "error:".red(), text),
+                Severity::Warning => writeln!(lines, "{} {}", "warning:".yellow(), text),
+                Severity::Note => writeln!(lines, "{} {}", "note:".blue(), text),
+            };
+        }
+        lines.push('\n');
+        out.write_section(SectionKind::Diagnostics, &lines);
+    }
+
+    /// Display diagnostics one per line as `line:column: severity[Ecode]: message`
+    pub fn display_diagnostics(
+        out: &mut dyn OutputSink,
+        diagnostics: &[Diagnostic],
+        content: &str,
+    ) {
+        if diagnostics.is_empty() {
+            return;
+        }
+        out.write_section(
+            SectionKind::Diagnostics,
+            &Self::rendered_diagnostics(diagnostics, content),
+        );
+    }
+
+    fn rendered_diagnostics(diagnostics: &[Diagnostic], content: &str) -> String {
+        let index = crate::utils::line_index::LineIndex::new(content);
+        let mut text = String::new();
+        for diagnostic in diagnostics {
+            let _ = writeln!(text, "{}", diagnostic.render(&index));
+        }
+        text
+    }
+
+    /// Display the diagnostics of every checked file, then how many passed
+    pub fn display_check_summary(
+        out: &mut dyn OutputSink,
+        checks: &[crate::check_handler::FileCheck],
+    ) {
+        let mut text = String::new();
+        for check in checks {
+            let (status, diagnostics) = match &check.result {
+                Ok(report) => (
+                    format!(
+                        "ok ({} functions, {} variables)",
+                        report.functions, report.variables
+                    )
+                    .green()
+                    .to_string(),
+                    &report.warnings,
+                ),
+                Err(diagnostics) => ("failed".red().to_string(), diagnostics),
+            };
+            let _ = writeln!(text, "{}: {}", check.path.display(), status);
+            text.push_str(&Self::rendered_diagnostics(diagnostics, &check.content));
+        }
+
+        let failed = checks.iter().filter(|check| !check.passed()).count();
+        let summary = format!(
+            "{} file(s) checked, {} passed, {} failed",
+            checks.len(),
+            checks.len() - failed,
+            failed
+        );
+        let _ = if failed == 0 {
+            writeln!(text, "\n{}", summary.green())
+        } else {
+            writeln!(text, "\n{}", summary.red())
+        };
+        out.write_section(SectionKind::Diagnostics, &text);
+    }
+
+    /// Display each top-level statement and function with the IR generated for it
+    pub fn display_annotated_ir(out: &mut dyn OutputSink, snippets: &[IrSnippet], content: &str) {
+        let text = format!("{}\n", Self::annotated_ir(snippets, content));
+        out.write_section(SectionKind::Ir, &text);
+    }
+
+    /// A listing of `content` with the IR of each snippet under its source lines, like
+    /// `objdump -S`. A statement's lines run up to the next snippet; a nested function
+    /// is listed again after the function containing it.
+    pub fn annotated_ir(snippets: &[IrSnippet], content: &str) -> String {
+        let index = crate::utils::line_index::LineIndex::new(content);
+        let last_line = index.line_col(content.len()).0;
+        let mut listing = String::new();
+        for (i, snippet) in snippets.iter().enumerate() {
+            let first = index.line_col(snippet.start).0;
+            let last = match snippet.end {
+                Some(end) => {
+                    // Leave out the terminator and the blank lines it consumed
+                    let text = &content[snippet.start..end];
+                    let trimmed = text.trim_end_matches(|c: char| c.is_whitespace() || c == ';');
+                    index.line_col(snippet.start + trimmed.len()).0
+                }
+                None => snippets[i + 1..]
+                    .iter()
+                    .map(|next| index.line_col(next.start).0)
+                    .find(|&line| line > first)
+                    .map_or(last_line, |line| line - 1),
+            };
+            let mut lines: Vec<usize> = (first..=last.max(first)).collect();
+            while lines.len() > 1
+                && lines
+                    .last()
+                    .is_some_and(|&line| index.line_text(line).trim().is_empty())
+            {
+                lines.pop();
+            }
+
+            for line in lines {
+                listing.push_str(&format!("{:>5} | {}\n", line, index.line_text(line)));
+            }
+            for ir in &snippet.ir {
+                listing.push_str(&format!("{:>5}   {}\n", "", ir));
+            }
+            listing.push('\n');
+        }
+        listing
     }
 
-    /// Display the generated LLVM IR and save to file
-    pub fn display_and_save_ir(ir_generator: &codegen::ir_generator::IRGenerator) {
+    /// Display the generated LLVM IR, and save it to file if `save` is set
+    pub fn display_and_save_ir(
+        out: &mut dyn OutputSink,
+        ir_generator: &codegen::ir_generator::IRGenerator,
+        save: bool,
+    ) {
         // Display generated IR
-        println!("\n{}", "Generated LLVM IR:".green());
         let ir_string = ir_generator.get_module().print_to_string().to_string();
-        println!("{}", ir_string);
+        let text = format!("\n{}\n{}\n", "Generated LLVM IR:".green(), ir_string);
+        out.write_section(SectionKind::Ir, &text);
 
         // Save IR to file
-        crate::file_handler::FileHandler::save_ir_to_file(&ir_string);
+        if save {
+            crate::file_handler::FileHandler::save_ir_to_file(out, &ir_string);
+        }
     }
 }
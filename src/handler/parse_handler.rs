@@ -1,48 +1,414 @@
+use crate::file_handler::{ProjectError, SourceFile};
+use crate::output_handler::{OutputSink, SectionKind};
+use crate::parser::language_options::LanguageOptions;
+use crate::parser::top_level::TopLevel;
+use crate::parser::visitor::depth_checker::{DepthChecker, too_deeply_nested};
 use crate::parser::*;
 use crate::token::*;
+use crate::utils::line_index::LineIndex;
 use ariadne::{Color, Label, Report, ReportKind, Source};
 use chumsky::{input::Stream, prelude::*};
 use logos::Logos;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Characters of an overlong identifier quoted in its diagnostic
+const IDENTIFIER_PREFIX_LENGTH: usize = 16;
+
+/// Why resolving `#include` directives failed
+#[derive(Debug)]
+pub enum IncludeError {
+    /// Not found next to the including file or on any include path
+    NotFound {
+        path: String,
+        requested_by: PathBuf,
+        line: usize,
+        column: usize,
+    },
+    /// A file includes itself, directly or through others; the chain ends with the repeated file
+    Cycle { chain: Vec<PathBuf> },
+    /// The file exists but could not be read
+    Read { path: PathBuf, message: String },
+    /// The included file has syntax errors, as `line:column: message`
+    Parse {
+        path: PathBuf,
+        messages: Vec<String>,
+    },
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncludeError::NotFound {
+                path,
+                requested_by,
+                line,
+                column,
+            } => write!(
+                f,
+                "Cannot find include '{}' requested by '{}' at {}:{}",
+                path,
+                requested_by.display(),
+                line,
+                column
+            ),
+            IncludeError::Cycle { chain } => {
+                let chain: Vec<String> = chain.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "Include cycle: {}", chain.join(" -> "))
+            }
+            IncludeError::Read { path, message } => {
+                write!(f, "Failed to read '{}': {}", path.display(), message)
+            }
+            IncludeError::Parse { path, messages } => {
+                write!(f, "Failed to parse '{}':", path.display())?;
+                for message in messages {
+                    write!(f, "\n  {}:{}", path.display(), message)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
 
 /// Handle parsing operations
 pub struct ParseHandler;
 
 impl ParseHandler {
     /// Perform lexical analysis and display tokens
-    pub fn perform_lexical_analysis(content: &str) {
-        lex_with_output(content);
+    pub fn perform_lexical_analysis(out: &mut dyn OutputSink, content: &str) {
+        lex_with_output(out, strip_bom(content).0);
     }
 
-    /// Parse source code and return AST
-    pub fn parse_source_code(content: &str) -> Result<program::Program, ()> {
-        let token_iter = Token::lexer(content)
-            .spanned()
-            .map(|(tok, span)| match tok {
-                Ok(tok) => (tok, span.into()),
-                Err(_) => {
-                    println!("Error token encountered: {:?}", &content[span.clone()]);
-                    (Token::Error, span.into())
-                }
-            });
+    /// Lex the source and display every token with its position.
+    /// Returns `Err` if the lexer hit unrecognized input.
+    #[allow(clippy::result_unit_err)] // errors are reported before returning
+    pub fn display_token_positions(out: &mut dyn OutputSink, content: &str) -> Result<(), ()> {
+        let content = strip_bom(content).0;
+        let tokens = tokenize(content);
+        crate::output_handler::OutputHandler::display_token_table(out, &tokens);
+
+        let errors: Vec<&TokenInfo> = tokens
+            .iter()
+            .filter(|info| info.token == Token::Error)
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Self::display_lex_errors(out, &errors, content);
+            Err(())
+        }
+    }
 
-        let token_stream =
-            Stream::from_iter(token_iter).map((0..content.len()).into(), |(t, s): (_, _)| (t, s));
+    /// Parse source code and return AST
+    #[allow(clippy::result_unit_err)] // errors are reported before returning
+    pub fn parse_source_code(
+        out: &mut dyn OutputSink,
+        content: &str,
+    ) -> Result<program::Program, ()> {
+        Self::parse_source_code_with_options(out, content, &LanguageOptions::default())
+    }
 
-        println!();
-        match program_parser().parse(token_stream).into_result() {
+    /// [`Self::parse_source_code`] accepting the syntax `options` enable
+    #[allow(clippy::result_unit_err)] // errors are reported before returning
+    pub fn parse_source_code_with_options(
+        out: &mut dyn OutputSink,
+        content: &str,
+        options: &LanguageOptions,
+    ) -> Result<program::Program, ()> {
+        match Self::parse_program_with_options(content, options) {
             Ok(program) => {
-                crate::output_handler::OutputHandler::display_ast(&program);
+                crate::output_handler::OutputHandler::display_ast(out, &program);
                 Ok(program)
             }
             Err(errs) => {
-                Self::display_parse_errors(errs, content);
+                Self::display_parse_errors(out, errs, content);
                 Err(())
             }
         }
     }
 
+    /// Parse source code into an AST without displaying it
+    pub fn parse_program(content: &str) -> Result<program::Program, Vec<Rich<'_, Token<'_>>>> {
+        Self::parse_program_with_options(content, &LanguageOptions::default())
+    }
+
+    /// [`Self::parse_program`] accepting the syntax `options` enable
+    pub fn parse_program_with_options<'src>(
+        content: &'src str,
+        options: &LanguageOptions,
+    ) -> Result<program::Program, Vec<Rich<'src, Token<'src>>>> {
+        match Self::parse_program_partial_with_options(content, options) {
+            (Some(program), errors) if errors.is_empty() => Ok(program),
+            (_, errors) => Err(errors),
+        }
+    }
+
+    /// [`Self::parse_program`], also collecting the comments into
+    /// [`Program::comments`](program::Program::comments) for tools that rewrite the
+    /// source, such as the [formatter](crate::parser::formatter)
+    pub fn parse_program_with_comments(
+        content: &str,
+    ) -> Result<program::Program, Vec<Rich<'_, Token<'_>>>> {
+        let mut program = Self::parse_program(content)?;
+        program.comments = trivia::comments(content);
+        Ok(program)
+    }
+
+    /// Parse source code, recovering from syntax errors. Skipped code is kept in the
+    /// AST as `Stmt::Error` and `TopLevel::Error` nodes, so on errors this still returns
+    /// whatever could be parsed around them, e.g. for building a symbol table.
+    ///
+    /// Input the lexer rejects is reported as its own error. Unrecognized characters
+    /// are then left out, and an unterminated string is parsed as if it were closed at
+    /// the end of its line. Identifiers longer than
+    /// [`CompileLimits::max_identifier_length`](crate::parser::compile_limits::CompileLimits::max_identifier_length)
+    /// characters are reported too, as are ones that are not ASCII under
+    /// [`LanguageOptions::ascii_identifiers`], and parsed as they are.
+    pub fn parse_program_partial(
+        content: &str,
+    ) -> (Option<program::Program>, Vec<Rich<'_, Token<'_>>>) {
+        Self::parse_program_partial_with_options(content, &LanguageOptions::default())
+    }
+
+    /// [`Self::parse_program_partial`] accepting the syntax `options` enable
+    pub fn parse_program_partial_with_options<'src>(
+        content: &'src str,
+        options: &LanguageOptions,
+    ) -> (Option<program::Program>, Vec<Rich<'src, Token<'src>>>) {
+        Self::parse_partial_at(content, options, 0)
+    }
+
+    /// [`Self::parse_program_partial_with_options`] with every span shifted by
+    /// `start`, as for an included file
+    fn parse_partial_at<'src>(
+        content: &'src str,
+        options: &LanguageOptions,
+        start: usize,
+    ) -> (Option<program::Program>, Vec<Rich<'src, Token<'src>>>) {
+        let at = |span: std::ops::Range<usize>| -> SimpleSpan {
+            (span.start + start..span.end + start).into()
+        };
+        let (source, offset) = strip_bom(content);
+        let lines = LineIndex::new(content);
+        let mut tokens: Vec<(Token, SimpleSpan)> = Vec::new();
+        let mut lex_errors: Vec<Rich<Token>> = Vec::new();
+        let max_identifier_length = options.limits.max_identifier_length;
+        for (tok, span) in Token::lexer(source).spanned() {
+            let span = span.start + offset..span.end + offset;
+            match tok {
+                Ok(Token::Identifier(name)) if name.chars().count() > max_identifier_length => {
+                    let prefix: String = name.chars().take(IDENTIFIER_PREFIX_LENGTH).collect();
+                    let message = format!(
+                        "identifier '{}...' exceeds maximum length of {} (was {})",
+                        prefix,
+                        max_identifier_length,
+                        name.chars().count()
+                    );
+                    lex_errors.push(Rich::custom(at(span.clone()), message));
+                    tokens.push((Token::Identifier(name), at(span)));
+                }
+                Ok(Token::Identifier(name)) if options.ascii_identifiers && !name.is_ascii() => {
+                    let message = format!(
+                        "identifier '{}' is not ASCII; disable LanguageOptions::ascii_identifiers \
+                         to use other letters",
+                        name
+                    );
+                    lex_errors.push(Rich::custom(at(span.clone()), message));
+                    tokens.push((Token::Identifier(name), at(span)));
+                }
+                // Without newlines between them, statements can only end with `;`
+                Ok(Token::Newline) if options.strict_semicolons => {}
+                Ok(tok) => tokens.push((tok, at(span))),
+                Err(error) => {
+                    if error == LexError::UnterminatedString {
+                        let quote = content[span.start..].find('"').unwrap_or(0);
+                        let text = &content[span.start + quote + 1..span.end];
+                        tokens.push((Token::String(text), at(span.clone())));
+                    }
+                    let (line, _) = lines.line_col(span.start);
+                    let message = error.describe(&content[span.clone()], line);
+                    lex_errors.push(Rich::custom(at(span), message));
+                }
+            }
+        }
+
+        let token_stream = Stream::from_iter(drop_bracketed_newlines(tokens))
+            .map(at(0..content.len()), |(t, s): (_, _)| (t, s));
+
+        let max_depth = options.limits.max_nesting_depth;
+        let mut nesting = NestingState::new(max_depth);
+        let (program, parse_errors) = program_parser_with(options.clone())
+            .parse_with_state(token_stream, &mut nesting)
+            .into_output_errors();
+        let mut errors = lex_errors;
+        if let Some(span) = nesting.exceeded() {
+            // Everything after the over-deep token fails to parse; only the cause is
+            // worth reporting
+            errors.push(Rich::custom(span, too_deeply_nested(nesting.limit())));
+            errors.sort_by_key(|error| error.span().start);
+            return (None, errors);
+        }
+        errors.extend(parse_errors);
+        errors.sort_by_key(|error| error.span().start);
+        let Some(program) = program else {
+            return (None, errors);
+        };
+
+        let mut checker = DepthChecker::new(max_depth);
+        program.accept(&mut checker);
+        if let Some(span) = checker.exceeded_at() {
+            errors.push(Rich::custom(
+                span.clone().into(),
+                too_deeply_nested(max_depth),
+            ));
+            // Later passes recurse over the AST, so an over-deep one is not returned
+            return (None, errors);
+        }
+        if let Err(exceeded) = options.limits.check(&program) {
+            errors.push(Rich::custom(at(0..0), exceeded.to_string()));
+            return (None, errors);
+        }
+        (Some(program), errors)
+    }
+
+    /// Replace `#include` items in `program`, parsed from `content` at `file`, with the
+    /// top-level items of the included files, parsed with `options` as the including
+    /// file was. Paths are looked up next to the including file first, then in each
+    /// of the options' [`include_paths`](LanguageOptions::include_paths). A file is
+    /// spliced in only once, however many times it is included. The included files
+    /// are recorded in [`Program::included`](program::Program::included), which
+    /// places their spans.
+    pub fn resolve_includes(
+        program: program::Program,
+        content: &str,
+        file: &Path,
+        options: &LanguageOptions,
+    ) -> Result<program::Program, IncludeError> {
+        let root = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+        let next_start = program
+            .included
+            .last()
+            .map_or(content.len(), |last| last.start + last.content.len())
+            + 1;
+        let mut splicer = IncludeSplicer {
+            options,
+            stack: vec![root.clone()],
+            seen: HashSet::from([root]),
+            sources: program.included,
+            next_start,
+        };
+        let body = splicer.splice(program.body, content, file, 0)?;
+        // A file is recorded after the files it includes, whose spans start later
+        splicer.sources.sort_by_key(|source| source.start);
+        Ok(program::Program {
+            body,
+            comments: program.comments,
+            included: splicer.sources,
+        })
+    }
+
+    /// Parse every file of a project and merge them into one program. Each file's
+    /// includes are resolved relative to that file. Functions and enums of all files
+    /// come first, then the top-level statements of each file in order, so code in
+    /// any file can call a function defined in any other.
+    pub fn merge_project(files: &[SourceFile]) -> Result<program::Program, ProjectError> {
+        let mut definitions = vec![];
+        let mut statements = vec![];
+        let mut defined_in: HashMap<String, &Path> = HashMap::new();
+        for file in files {
+            let program = Self::parse_program(&file.content).map_err(|errs| {
+                let index = LineIndex::new(&file.content);
+                let messages = errs
+                    .iter()
+                    .map(|err| {
+                        let (line, column) = index.line_col(err.span().start);
+                        format!("{}:{}: {}", line, column, err)
+                    })
+                    .collect();
+                ProjectError::Parse {
+                    path: file.path.clone(),
+                    messages,
+                }
+            })?;
+            let program = Self::resolve_includes(
+                program,
+                &file.content,
+                &file.path,
+                &LanguageOptions::default(),
+            )
+            .map_err(ProjectError::Include)?;
+
+            for func_def in program.functions() {
+                // Duplicates within one file are the symbol table's to report
+                match defined_in.insert(func_def.name.clone(), &file.path) {
+                    Some(first) if first != file.path.as_path() => {
+                        return Err(ProjectError::DuplicateFunction {
+                            name: func_def.name.clone(),
+                            first: first.to_path_buf(),
+                            second: file.path.clone(),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            for item in program.body {
+                match item {
+                    TopLevel::Statement(_) | TopLevel::Error(_) => statements.push(item),
+                    TopLevel::Function(_) | TopLevel::Enum(_) | TopLevel::Include(..) => {
+                        definitions.push(item)
+                    }
+                }
+            }
+        }
+        definitions.extend(statements);
+        Ok(program::Program {
+            body: definitions,
+            comments: vec![],
+            included: vec![],
+        })
+    }
+
+    /// Find an included file next to the including file or on the include path
+    fn find_include(path: &str, file: &Path, include_paths: &[PathBuf]) -> Option<PathBuf> {
+        let sibling = file.parent().map(|dir| dir.join(path));
+        sibling
+            .into_iter()
+            .chain(include_paths.iter().map(|dir| dir.join(path)))
+            .find(|candidate| candidate.is_file())
+            .and_then(|candidate| fs::canonicalize(candidate).ok())
+    }
+
+    /// Display lexer errors with the offending line excerpt
+    fn display_lex_errors(out: &mut dyn OutputSink, errors: &[&TokenInfo], content: &str) {
+        let mut report = Vec::new();
+        for info in errors {
+            Report::build(ReportKind::Error, ((), info.span.clone()))
+                .with_config(ariadne::Config::new().with_index_type(ariadne::IndexType::Byte))
+                .with_message(format!(
+                    "Unrecognized input at line {}, column {}",
+                    info.line, info.column
+                ))
+                .with_label(
+                    Label::new(((), info.span.clone()))
+                        .with_message(format!(
+                            "{:?} is not a valid token",
+                            &content[info.span.clone()]
+                        ))
+                        .with_color(Color::Red),
+                )
+                .finish()
+                .write(Source::from(content), &mut report)
+                .unwrap();
+        }
+        out.write_section(SectionKind::Diagnostics, &String::from_utf8_lossy(&report));
+    }
+
     /// Display parsing errors
-    fn display_parse_errors(errors: Vec<Rich<Token>>, content: &str) {
+    pub fn display_parse_errors(out: &mut dyn OutputSink, errors: Vec<Rich<Token>>, content: &str) {
+        let mut report = Vec::new();
         for err in errors {
             Report::build(ReportKind::Error, ((), err.span().into_range()))
                 .with_config(ariadne::Config::new().with_index_type(ariadne::IndexType::Byte))
@@ -53,8 +419,108 @@ impl ParseHandler {
                         .with_color(Color::Red),
                 )
                 .finish()
-                .eprint(Source::from(content))
+                .write(Source::from(content), &mut report)
                 .unwrap();
         }
+        out.write_section(SectionKind::Diagnostics, &String::from_utf8_lossy(&report));
+    }
+}
+
+/// The state of resolving the includes of one file
+struct IncludeSplicer<'a> {
+    /// What the including file was parsed with, and where to look for includes
+    options: &'a LanguageOptions,
+    /// The files being spliced, the including file first, for finding cycles
+    stack: Vec<PathBuf>,
+    /// Every file spliced in so far, and the including file
+    seen: HashSet<PathBuf>,
+    sources: Vec<Arc<program::IncludedSource>>,
+    /// Where the spans of the next file spliced in start
+    next_start: usize,
+}
+
+impl IncludeSplicer<'_> {
+    /// `body`, parsed from `content` at `file` with spans shifted by `start`, with
+    /// its `#include` items replaced by the items of the files they include
+    fn splice(
+        &mut self,
+        body: Vec<TopLevel>,
+        content: &str,
+        file: &Path,
+        start: usize,
+    ) -> Result<Vec<TopLevel>, IncludeError> {
+        let mut spliced = Vec::with_capacity(body.len());
+        for item in body {
+            let TopLevel::Include(path, span) = item else {
+                spliced.push(item);
+                continue;
+            };
+
+            let Some(resolved) =
+                ParseHandler::find_include(&path, file, &self.options.include_paths)
+            else {
+                let (line, column) = LineIndex::new(content).line_col(span.start - start);
+                return Err(IncludeError::NotFound {
+                    path,
+                    requested_by: file.to_path_buf(),
+                    line,
+                    column,
+                });
+            };
+            if let Some(first) = self.stack.iter().position(|f| *f == resolved) {
+                let mut chain = self.stack[first..].to_vec();
+                chain.push(resolved);
+                return Err(IncludeError::Cycle { chain });
+            }
+            if !self.seen.insert(resolved.clone()) {
+                continue;
+            }
+
+            let content = fs::read_to_string(&resolved).map_err(|e| IncludeError::Read {
+                path: resolved.clone(),
+                message: e.to_string(),
+            })?;
+            let included_start = self.next_start;
+            let program =
+                match ParseHandler::parse_partial_at(&content, self.options, included_start) {
+                    (Some(program), errors) if errors.is_empty() => program,
+                    (_, errors) => {
+                        let index = LineIndex::new(&content);
+                        let messages = errors
+                            .iter()
+                            .map(|err| {
+                                let (line, column) =
+                                    index.line_col(err.span().start - included_start);
+                                format!("{}:{}: {}", line, column, err)
+                            })
+                            .collect();
+                        return Err(IncludeError::Parse {
+                            path: resolved,
+                            messages,
+                        });
+                    }
+                };
+            self.next_start += content.len() + 1;
+
+            self.stack.push(resolved.clone());
+            let items = self.splice(program.body, &content, &resolved, included_start)?;
+            self.stack.pop();
+            spliced.extend(items);
+            self.sources.push(Arc::new(program::IncludedSource {
+                path: resolved,
+                content,
+                start: included_start,
+            }));
+        }
+        Ok(spliced)
+    }
+}
+
+/// `content` without a leading UTF-8 byte order mark, as some editors save one,
+/// and the length of what was removed
+fn strip_bom(content: &str) -> (&str, usize) {
+    match content.strip_prefix('\u{FEFF}') {
+        Some(rest) => (rest, content.len() - rest.len()),
+        None => (content, 0),
     }
 }
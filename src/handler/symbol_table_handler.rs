@@ -1,15 +1,44 @@
+use crate::output_handler::OutputSink;
+use crate::parser::visitor::symbol_table_builder::{Scope, SymbolDiagnostic, SymbolTableBuilder};
 use crate::parser::*;
 
 /// Handle symbol table building
 pub struct SymbolTableHandler;
 
 impl SymbolTableHandler {
-    /// Build symbol table and display it
-    pub fn build_and_display_symbol_table(program: &program::Program) {
-        let mut root_scope = visitor::symbol_table_builder::Scope::new();
-        let mut builder = visitor::symbol_table_builder::SymbolTableBuilder::new(&mut root_scope);
+    /// Build symbol table and display it along with any declaration diagnostics
+    pub fn build_and_display_symbol_table(
+        out: &mut dyn OutputSink,
+        program: &program::Program,
+        content: &str,
+    ) {
+        let (root_scope, diagnostics) = Self::build_symbol_table(program);
+
+        crate::output_handler::OutputHandler::display_symbol_table(out, &root_scope);
+        crate::output_handler::OutputHandler::display_symbol_diagnostics(
+            out,
+            &diagnostics,
+            program,
+            content,
+        );
+    }
+
+    /// Build the symbol table without printing anything
+    pub fn build_symbol_table(program: &program::Program) -> (Scope, Vec<SymbolDiagnostic>) {
+        let mut root_scope = Scope::new();
+        let mut builder = SymbolTableBuilder::new(&mut root_scope);
         program.accept(&mut builder);
+        let diagnostics = builder.into_diagnostics();
+        (root_scope, diagnostics)
+    }
 
-        crate::output_handler::OutputHandler::display_symbol_table(&root_scope);
+    /// [`Self::build_symbol_table`] for a program compiled as a
+    /// [`Script`](crate::script::Script), whose functions see its top-level variables
+    pub fn build_script_symbol_table(program: &program::Program) -> (Scope, Vec<SymbolDiagnostic>) {
+        let mut root_scope = Scope::new();
+        let mut builder = SymbolTableBuilder::for_script(&mut root_scope);
+        program.accept(&mut builder);
+        let diagnostics = builder.into_diagnostics();
+        (root_scope, diagnostics)
     }
 }
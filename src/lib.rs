@@ -0,0 +1,54 @@
+//! COL, an open-source scripting language inspired by GameMaker Language.
+//!
+//! Hosts embed scripts through [`Script`], which compiles a source file to native code
+//! with LLVM and calls into it:
+//!
+//! ```
+//! use col::{Script, Value};
+//!
+//! let mut script = Script::compile(
+//!     "var speed = 2;\nfunction step(x) { return x + speed; }",
+//! )
+//! .unwrap();
+//! script.run_main().unwrap(); // runs the top-level statements
+//! assert_eq!(script.call("step", &[Value::Number(1.0)]).unwrap(), Value::Number(3.0));
+//!
+//! script.set_global("speed", Value::Number(10.0)).unwrap();
+//! assert_eq!(script.call("step", &[Value::Number(1.0)]).unwrap(), Value::Number(11.0));
+//! ```
+//!
+//! The remaining modules expose the compiler pipeline itself.
+
+pub mod codegen;
+pub mod handler;
+pub mod parser;
+pub mod script;
+pub mod token;
+pub mod utils;
+
+#[cfg(any(test, feature = "bench"))]
+pub mod bench;
+
+mod tests;
+
+use handler::*;
+
+pub use codegen::runtime::instances::Instance;
+pub use codegen::runtime::profile::FunctionProfile;
+pub use codegen::runtime::trace::TraceEntry;
+pub use codegen::runtime::trap::{ErrorKind, FrameInfo, RaisedError};
+pub use parser::build;
+pub use parser::compile_limits::{CompileLimits, Limit, LimitExceeded};
+pub use parser::dead_branches::prune_dead_branches;
+pub use parser::highlight::{HighlightCategory, HighlightSpan, highlight};
+pub use parser::inliner::inline_small_functions;
+pub use parser::language_options::{DEFAULT_MAX_CALL_DEPTH, DivByZeroPolicy, LanguageOptions};
+pub use parser::outline::{OutlineItem, OutlineKind};
+pub use parser::visitor::type_infer::{Type, TypeMap, infer_types};
+pub use script::cache::{CacheConfig, ScriptCache};
+pub use script::constants::ConstantError;
+pub use script::debug::{DebugSession, StepEvent};
+pub use script::eval::EvalError;
+pub use script::state::{StateError, StateReport};
+pub use script::template::ScriptTemplate;
+pub use script::{CompileError, CompileSession, ReloadReport, RuntimeError, Script, Value};
@@ -1,19 +1,221 @@
+#[cfg(feature = "bench")]
+use col::bench;
+use col::handler::*;
+use col::{DivByZeroPolicy, LanguageOptions};
+use std::path::{Path, PathBuf};
+
+use check_handler::*;
 use codegen_handler::*;
-use handler::*;
 use output_handler::*;
 use parse_handler::*;
 use symbol_table_handler::*;
 
-mod codegen;
-mod parser;
-mod token;
-mod utils;
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut out = StdoutSink;
+    #[cfg(feature = "bench")]
+    if args.first().map(String::as_str) == Some("--bench") {
+        let iterations = args.get(1).and_then(|n| n.parse().ok()).unwrap_or(10);
+        if let Err(e) = bench::run_all(iterations) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.first().map(String::as_str) == Some("--tokens") {
+        let Some(path) = args.get(1) else {
+            eprintln!("Usage: col --tokens <file>");
+            std::process::exit(2);
+        };
+        let content = match file_handler::FileHandler::read_source_file(path) {
+            Ok(content) => content,
+            Err(_) => return,
+        };
+        if ParseHandler::display_token_positions(&mut out, &content).is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-mod handler;
-mod tests;
+    if args.first().map(String::as_str) == Some("--emit-annotated") {
+        let Some(path) = args.get(1) else {
+            eprintln!("Usage: col --emit-annotated <file>");
+            std::process::exit(2);
+        };
+        let content = match file_handler::FileHandler::read_source_file(path) {
+            Ok(content) => content,
+            Err(_) => return,
+        };
+        let program = match ParseHandler::parse_program(&content) {
+            Ok(program) => program,
+            Err(errors) => {
+                ParseHandler::display_parse_errors(&mut out, errors, &content);
+                std::process::exit(1);
+            }
+        };
+        let program = match ParseHandler::resolve_includes(
+            program,
+            &content,
+            Path::new(path),
+            &LanguageOptions::default(),
+        ) {
+            Ok(program) => program,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        if !CodeGenHandler::display_annotated_ir(
+            &mut out,
+            &program,
+            &content,
+            &LanguageOptions::default(),
+            &CodeGenOptions::default(),
+        ) {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-fn main() {
-    let path = "ComplexTest.gml";
+    let check_options = CheckOptions {
+        warn_shadowing: args.iter().any(|arg| arg == "--warn-shadowing"),
+        warn_unused: args.iter().any(|arg| arg == "--warn-unused"),
+    };
+    let mut options = LanguageOptions::default();
+    let mut settings = CodeGenOptions::default();
+    if args.iter().any(|arg| arg == "--verbose") {
+        settings.verbose = true;
+    }
+    if args.iter().any(|arg| arg == "--debug-info") {
+        settings.debug_info = true;
+    }
+    if args.iter().any(|arg| arg == "--profile") {
+        settings.profiling = true;
+    }
+    if args.iter().any(|arg| arg == "--trace") {
+        settings.tracing = true;
+    }
+    if args.iter().any(|arg| arg == "--prune-dead-branches") {
+        settings.prune_dead_branches = true;
+    }
+    if let Some(threshold) = args.iter().find_map(|arg| arg.strip_prefix("--inline=")) {
+        match threshold.parse() {
+            Ok(threshold) => settings.inline_threshold = threshold,
+            Err(_) => {
+                eprintln!("--inline expects a node count, e.g. --inline=16");
+                std::process::exit(2);
+            }
+        }
+    }
+    if let Some(epsilon) = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--math-epsilon="))
+    {
+        match epsilon.parse::<f64>() {
+            Ok(epsilon) if epsilon >= 0.0 => settings.math_epsilon = Some(epsilon),
+            _ => {
+                eprintln!(
+                    "--math-epsilon expects a number of at least 0, e.g. --math-epsilon=0.00001"
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if let Some(policy) = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--div-by-zero="))
+    {
+        options.div_by_zero = match policy {
+            "infinity" => DivByZeroPolicy::Infinity,
+            "error" => DivByZeroPolicy::Error,
+            "zero" => DivByZeroPolicy::Zero,
+            _ => {
+                eprintln!(
+                    "--div-by-zero expects infinity, error or zero, e.g. --div-by-zero=error"
+                );
+                std::process::exit(2);
+            }
+        };
+    }
+    if args.iter().any(|arg| arg == "--allow-switch") {
+        options.allow_switch = true;
+    }
+    if args.iter().any(|arg| arg == "--ascii-identifiers") {
+        options.ascii_identifiers = true;
+    }
+    if args.iter().any(|arg| arg == "--strict-semicolons") {
+        options.strict_semicolons = true;
+    }
+    if args.iter().any(|arg| arg == "--exact-integers") {
+        options.exact_integers = true;
+    }
+    if args.iter().any(|arg| arg == "--strict-math") {
+        options.strict_math = true;
+    }
+    if args.iter().any(|arg| arg == "--strict-returns") {
+        options.strict_returns = true;
+    }
+    if let Some(count) = args.iter().find_map(|arg| arg.strip_prefix("--unroll=")) {
+        match count.parse() {
+            Ok(count) => settings.max_unrolled_repeat = count,
+            Err(_) => {
+                eprintln!("--unroll expects a repeat count, e.g. --unroll=8, or 0 to keep loops");
+                std::process::exit(2);
+            }
+        }
+    }
+    options.include_paths.extend(
+        args.iter()
+            .filter_map(|arg| arg.strip_prefix("--include-path="))
+            .map(PathBuf::from),
+    );
+    if args.iter().any(|arg| arg == "--no-max-call-depth") {
+        options.max_call_depth = None;
+    }
+    if let Some(depth) = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--max-call-depth="))
+    {
+        match depth.parse() {
+            Ok(depth) if depth > 0 => options.max_call_depth = Some(depth),
+            _ => {
+                eprintln!(
+                    "--max-call-depth expects a positive call count, e.g. --max-call-depth=10000"
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if args.first().map(String::as_str) == Some("--check") {
+        let Some(path) = args.get(1) else {
+            eprintln!(
+                "Usage: col --check <file-or-dir> [--no-ir] [--script] [--warn-shadowing] [--warn-unused]"
+            );
+            std::process::exit(2);
+        };
+        let generate_ir = !args.iter().any(|arg| arg == "--no-ir");
+        let mode = if args.iter().any(|arg| arg == "--script") {
+            CheckMode::Script
+        } else {
+            CheckMode::Program
+        };
+        let checks =
+            CheckHandler::check_path(Path::new(path), generate_ir, mode, &options, &check_options);
+        OutputHandler::display_check_summary(&mut out, &checks);
+        std::process::exit(CheckHandler::exit_status(&checks));
+    }
+
+    let path = args
+        .iter()
+        .find(|arg| !arg.starts_with("--"))
+        .map_or("ComplexTest.gml", String::as_str);
+
+    if file_handler::FileHandler::is_project(Path::new(path)) {
+        run_project(&mut out, Path::new(path), &options, &settings);
+        return;
+    }
 
     // Read source file
     let content = match file_handler::FileHandler::read_source_file(path) {
@@ -22,20 +224,77 @@ fn main() {
     };
 
     // Display original code
-    OutputHandler::display_original_code(&content);
+    OutputHandler::display_original_code(&mut out, &content);
 
     // Perform lexical analysis
-    ParseHandler::perform_lexical_analysis(&content);
+    ParseHandler::perform_lexical_analysis(&mut out, &content);
 
     // Parse the source code
-    let program = match ParseHandler::parse_source_code(&content) {
+    let program = match ParseHandler::parse_source_code_with_options(&mut out, &content, &options) {
         Ok(program) => program,
         Err(_) => return,
     };
 
+    // Splice in included files
+    let program = match ParseHandler::resolve_includes(program, &content, Path::new(path), &options)
+    {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
     // Build symbol table
-    SymbolTableHandler::build_and_display_symbol_table(&program);
+    SymbolTableHandler::build_and_display_symbol_table(&mut out, &program, &content);
+    OutputHandler::display_diagnostics(
+        &mut out,
+        &CheckHandler::lint(&program, &check_options),
+        &content,
+    );
 
     // Generate LLVM IR and execute with JIT
-    CodeGenHandler::generate_ir_and_execute(&program);
+    CodeGenHandler::generate_ir_and_execute(
+        &mut out,
+        &program,
+        Some((Path::new(path), &content)),
+        &options,
+        &settings,
+    );
+}
+
+/// Compile and run every file of the project at `path` as one program, with
+/// `options` and `settings`
+fn run_project(
+    out: &mut dyn OutputSink,
+    path: &Path,
+    options: &LanguageOptions,
+    settings: &CodeGenOptions,
+) {
+    let files = match file_handler::FileHandler::read_project(path) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    OutputHandler::display_project_files(out, &files);
+
+    // Parse each file and merge them into one program
+    let program = match ParseHandler::merge_project(&files) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    OutputHandler::display_ast(out, &program);
+
+    // Positions are per file, so only the table is shown for the merged program
+    let (root_scope, _) = SymbolTableHandler::build_symbol_table(&program);
+    OutputHandler::display_symbol_table(out, &root_scope);
+
+    // Generate LLVM IR and execute with JIT. Spans of the merged program point
+    // into different files, so no debug information can be emitted for it.
+    CodeGenHandler::generate_ir_and_execute(out, &program, None, options, settings);
 }
@@ -1,19 +1,41 @@
+pub mod build;
+pub mod compile_limits;
+pub mod dead_branches;
+pub mod enum_def;
 pub mod expr;
+pub mod formatter;
 pub mod func;
 pub mod func_def;
+pub mod highlight;
+pub mod inliner;
+pub mod language_options;
+pub mod outline;
 pub mod program;
 pub mod stmt;
 pub mod top_level;
+pub mod trivia;
 pub mod visitor;
 
-use crate::parser::expr::Expr;
+use crate::parser::enum_def::{EnumDef, EnumMember};
+use crate::parser::expr::{AccessorKind, Expr};
+use crate::parser::visitor::depth_checker::{DEFAULT_MAX_NESTING_DEPTH, too_deeply_nested};
 use crate::token::*;
+use chumsky::input::{Checkpoint, Cursor, Input};
+use chumsky::inspector::Inspector;
+use chumsky::recursive::Indirect;
 use chumsky::{input::ValueInput, prelude::*};
 use func::Func;
 use func_def::FuncDef;
+use language_options::LanguageOptions;
 use program::Program;
-use stmt::Stmt;
+use stmt::{Stmt, SwitchCase};
 use top_level::TopLevel;
+
+/// Byte range of a node in the source text
+pub type Span = std::ops::Range<usize>;
+
+/// What the parsers carry: their errors, and how deeply the input nests
+pub(crate) type Extra<'tokens, 'src> = extra::Full<Rich<'tokens, Token<'src>>, NestingState, ()>;
 /*
 ----------------------------------------------------------------------------------------------------
 WARNING!!!
@@ -28,11 +50,16 @@ as it serves as the authoritative reference for the language syntax.
 
 program        -> top_level* EOF ;
 
-top_level      -> statement ";"? | function ;
+top_level      -> statement ";"? | function | enum | include ;
+
+include        -> "#include" string terminator? ;
 
-function       -> "function" identifier "(" parameters? ")" block ;
+function       -> "function" identifier "(" parameters? ")" newline* block ;
 parameters     -> identifier ( "," identifier )* ;
 
+enum           -> "enum" identifier newline* "{" ( enumMember ( "," enumMember )* ","? )? "}" terminator? ;
+enumMember     -> identifier ( "=" expression )? ;   // the value must be constant
+
 block          -> "{" statement* "}" ;
 
 statement      -> exprStmt
@@ -45,6 +72,8 @@ statement      -> exprStmt
                | whileStmt
                | doUntilStmt
                | forStmt
+               | switchStmt
+               | function
                | block ;
 
 exprStmt       -> expression terminator ;
@@ -53,6 +82,12 @@ varStmt        -> "var" variableDecl ("," variableDecl)* terminator ;
 variableDecl   -> IDENTIFIER ("=" expression)? ;
 
 ifStmt         -> "if" ("(" expression ")" | expression) "then"? ifBranch ("else" ifBranch)? ;
+// A parenthesized condition ends at its ")" when a branch can follow it, so
+// `if (x) (y + 1);` evaluates `(y + 1)`. Otherwise the parentheses only start a longer
+// condition, as in `if (a + b) * 2 > 10 then ...`. Either way `=` assigns, as it does
+// everywhere else, so `if x = 5 then ...` assigns 5 to x and takes the branch; the
+// ConditionLinter warns about it. Without "then" the branch may follow on the same
+// line, as in `if x y = 1`.
 
 ifBranch       -> statement_no_term | block ;
 
@@ -63,7 +98,15 @@ continueStmt   -> "continue" terminator ;
 repeatStmt     -> "repeat" "(" expression ")" statement ;
 whileStmt      -> "while" ("(" expression ")" | expression) statement ;
 doUntilStmt    -> "do" statement "until" "(" expression ")" terminator ;
-forStmt        -> "for" "(" (varStmt_no_term | exprStmt_no_term | ";") expression? ";" (exprStmt_no_term)? ")" statement ;
+forStmt        -> "for" "(" forInit? ";" expression? ";" forUpdate? ")" statement ;
+forInit        -> varStmt_no_term | expression ;
+forUpdate      -> expression ( "," expression )* ;
+switchStmt     -> "switch" ("(" expression ")" | expression) newline* "{" newline* switchCase* "}" ;
+switchCase     -> ( "case" expression ( "," expression )* | "default" ) ":" statement* ;
+// Labels with no statements before the next label, as in "case 1: case 2:", join
+// the arm of the next label.
+// switchStmt is only accepted with LanguageOptions::allow_switch. Without it, it is
+// parsed anyway and reported as disabled.
 
 statement_no_term -> exprStmt_no_term
                   | varStmt_no_term
@@ -71,6 +114,10 @@ statement_no_term -> exprStmt_no_term
                   | breakStmt_no_term
                   | continueStmt_no_term
                   | ifStmt
+                  | repeatStmt
+                  | whileStmt
+                  | doUntilStmt
+                  | forStmt
                   | block ;
 
 exprStmt_no_term     -> expression ;
@@ -80,6 +127,20 @@ breakStmt_no_term    -> "break" ;
 continueStmt_no_term -> "continue" ;
 
 terminator     -> ( ";" | newline )+
+
+// newline is any of "\r\n", "\n", "\r", U+2028 and U+2029; all are the same token.
+// With LanguageOptions::strict_semicolons newlines are dropped before parsing, so
+// only ";" terminates.
+// Newlines inside "( ... )" and "[ ... ]", accessors included, are always dropped
+// before parsing, so a call, index or for header may span lines; those inside a
+// "{ ... }" nested in them are kept. Elsewhere a newline ends the statement: "return" followed by a newline
+// returns no value, and consecutive statements on their own lines need no ";".
+
+// Error recovery: a statement that fails to parse becomes an error node covering
+// the tokens up to and including the next ";" or newline, or up to a closing "}"
+// or the next "case" or "default" label.
+// "{ ... }" groups are skipped whole. A top-level item that cannot be recovered
+// as a statement becomes an error node reaching up to the next "function".
 ---
 
 expression     -> assignment ;
@@ -95,21 +156,97 @@ bit_or         -> bit_xor ( "|" bit_xor )* ;
 bit_xor        -> bit_and ( "^" bit_and )* ;
 bit_and        -> equality ( "&" equality )* ;
 equality       -> comparison ( ( "!=" | "==" ) comparison )* ;
-comparison     -> term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
+comparison     -> shift ( ( ">" | ">=" | "<" | "<=" ) shift )* ;
+shift          -> term ( ( "<<" | ">>" ) term )* ;
 term           -> factor ( ( "-" | "+" ) factor )* ;
-factor         -> postfix ( ( "/" | "*" | "%" ) postfix )* ;
-postfix        -> identifier ( "++" | "--" ) | unary ;
+factor         -> unary ( ( "/" | "*" | "%" | "mod" | "div" ) unary )* ;
+// "%" and "mod" are the same operator: the remainder of truncating division, with
+// the sign of the left operand, as in GameMaker. So -7 % 3 is -1, 7 % -3 is 1 and
+// -7 % -3 is -1. To wrap an angle into [0, 360) use ((a % 360) + 360) % 360.
+// "div" is the quotient truncated toward zero, so -7 div 2 is -3. What dividing by
+// zero gives is up to the DivByZeroPolicy the script is compiled with.
 unary          -> ( "!" | "~" | "+" | "-" ) unary
-               | ( "++" | "--" ) identifier
-               | primary ;
+               | ( "++" | "--" ) unary
+               | postfix ;
+postfix        -> accessed ( "++" | "--" )? ;
+accessed       -> primary accessor* ;
+accessor       -> ( "[|" | "[?" | "[@" ) expression "]"
+               | "[#" expression "," expression "]" ;
+// Accessors read collections: `list[| i]` an element of a ds_list, `map[? key]` the
+// value of a key in a ds_map, `grid[# x, y]` a cell of a ds_grid and `array[@ i]` an
+// element of an array written through a reference. Each opening is one token, with
+// no space between "[" and its sigil. Lists and maps are read, and maps assigned
+// with "=", through the ds_* builtins; the other forms parse but do not compile yet.
+// Precedence, tightest first: primary, accessors, postfix "++"/"--", then prefix operators,
+// which nest to the right, so `-x++` is `-(x++)` and `- - -a` is `-(-(-a))`.
+// The operand of "++" and "--" must be a variable or a field. Anything else is
+// reported as an error without stopping the parse. Without spaces `---a` lexes
+// as "--" "-a", a decrement of `-a`, and gets that error too.
 primary & atom -> number | string | "true" | "false" | "null"
-               | identifier ( "(" ( expression ( "," expression )* )? ")" | ( "++" | "--" ) )?
+               | identifier ( "(" arguments? ")" | "." identifier )?
+               | ( "self" | "other" ) ( "." identifier )?
+               | "function" "(" parameters? ")" newline* block
                | "(" expression ")" ;
+arguments      -> expression? ( "," expression? )* ;   // empty slots are undefined,
+                                                       // a trailing "," is ignored
+// "function" in an expression is an anonymous function, e.g.
+// `var cb = function(a) { return a * 2; };`. Calling `cb(5)` calls it. Its body sees
+// its parameters, its own variables and globals, but not the locals around it.
+// A string is "..." on one line, or @"..." which may span lines and writes a
+// quote as "". Neither has escape sequences.
 */
 
 /// The top-level parser for a program, parsing a collection of statements and function definitions.
 pub(crate) fn program_parser<'tokens, 'src: 'tokens, I>()
--> impl Parser<'tokens, I, Program, extra::Err<Rich<'tokens, Token<'src>>>>
+-> impl Parser<'tokens, I, Program, Extra<'tokens, 'src>>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    program_parser_with(LanguageOptions::default())
+}
+
+/// Drop the newlines inside `( ... )` and `[ ... ]` from a token stream, so a call,
+/// index or parenthesized expression may be split over several lines without a
+/// line ending a statement in its middle. A `}` closes whatever `(` and `[` are
+/// still open inside its block, so one missing `)` cannot swallow the line breaks
+/// of the rest of the file.
+pub(crate) fn drop_bracketed_newlines<'src, S>(
+    tokens: impl IntoIterator<Item = (Token<'src>, S)>,
+) -> impl Iterator<Item = (Token<'src>, S)> {
+    // `true` for an open `(` or `[`, `false` for an open `{`, innermost last
+    let mut open: Vec<bool> = Vec::new();
+    tokens.into_iter().filter(move |(token, _)| {
+        match token {
+            Token::LeftParen
+            | Token::LeftBracket
+            | Token::ListAccessor
+            | Token::MapAccessor
+            | Token::GridAccessor
+            | Token::ArrayAccessor => open.push(true),
+            Token::LeftBrace => open.push(false),
+            Token::RightParen | Token::RightBracket => {
+                if open.last() == Some(&true) {
+                    open.pop();
+                }
+            }
+            Token::RightBrace => {
+                while let Some(bracket) = open.pop() {
+                    if !bracket {
+                        break;
+                    }
+                }
+            }
+            Token::Newline => return open.last() != Some(&true),
+            _ => {}
+        }
+        true
+    })
+}
+
+/// [`program_parser`] accepting the syntax `options` enable
+pub(crate) fn program_parser_with<'tokens, 'src: 'tokens, I>(
+    options: LanguageOptions,
+) -> impl Parser<'tokens, I, Program, Extra<'tokens, 'src>>
 where
     I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
 {
@@ -120,10 +257,64 @@ where
         .ignored();
     // endregion
 
+    // region recovery
+    // After a syntax error, tokens are skipped up to a statement boundary: the next
+    // `;` or newline (consumed), or a `}` closing the enclosing block or a `switch`
+    // label (left in place).
+    // A `{ ... }` group is skipped as a whole so its `}` cannot end the block early.
+    let brace_group = recursive(|brace_group| {
+        choice((
+            nested(brace_group),
+            none_of([Token::LeftBrace, Token::RightBrace]).ignored(),
+        ))
+        .repeated()
+        .delimited_by(just(Token::LeftBrace), just(Token::RightBrace))
+    });
+
+    let statement_recovery = choice((
+        brace_group,
+        none_of([
+            Token::LeftBrace,
+            Token::RightBrace,
+            Token::Semicolon,
+            Token::Newline,
+            Token::Case,
+            Token::Default,
+        ])
+        .ignored(),
+    ))
+    .repeated()
+    .at_least(1)
+    .then(choice((just(Token::Semicolon), just(Token::Newline))).or_not())
+    .map_with(|_, e| {
+        let span: SimpleSpan = e.span();
+        Some(Stmt::Error(span.into_range()))
+    });
+
+    // Whatever a statement cannot recover from, such as a stray `}`, is skipped up
+    // to the next `function`
+    let top_level_recovery = any()
+        .then(any().and_is(just(Token::Function).not()).repeated())
+        .map_with(|_, e| {
+            let span: SimpleSpan = e.span();
+            Some(TopLevel::Error(span.into_range()))
+        });
+    // endregion
+
     // region statement
-    let expr = expr_parser();
+    // Declared ahead, as anonymous functions in expressions have statements in them
+    let mut declared: Recursive<Indirect<'tokens, 'tokens, I, Option<Stmt>, Extra<'tokens, 'src>>> =
+        Recursive::declare();
+    let statement = nested(declared.clone());
+    let function_body = statement
+        .clone()
+        .repeated()
+        .collect::<Vec<Option<Stmt>>>()
+        .map(|stmts| stmts.into_iter().flatten().collect::<Vec<Stmt>>())
+        .delimited_by(just(Token::LeftBrace), just(Token::RightBrace));
+    let expr = expr_parser(options.exact_integers, function_body);
 
-    let statement = recursive(|statement| {
+    let definition = {
         // region expr_stmt
         let expr_stmt = expr
             .clone()
@@ -133,12 +324,14 @@ where
         // endregion
 
         // region var_stmt
-        let variable_decl = select! { Token::Identifier(s) => s.to_string() }
-            .then(just(Token::Equal).ignore_then(expr.clone()).or_not());
+        let variable_decl = spanned_ident()
+            .then(just(Token::Equal).ignore_then(expr.clone()).or_not())
+            .map(|((name, span), init)| (name, init, span));
 
         let var_stmt = just(Token::Var)
             .ignore_then(
                 variable_decl
+                    .clone()
                     .separated_by(just(Token::Comma))
                     .allow_trailing()
                     .at_least(1)
@@ -158,21 +351,146 @@ where
         let block = block_content
             .clone()
             .delimited_by(just(Token::LeftBrace), just(Token::RightBrace))
-            .map(|stmts| Some(Stmt::Block(stmts)));
+            .map_with(|stmts, e| Some(Stmt::Block(stmts, SimpleSpan::into_range(e.span()))));
+        // endregion
+
+        // region return_stmt
+        let return_stmt = just(Token::Return)
+            .ignore_then(expr.clone().or_not())
+            .then_ignore(terminator.clone())
+            .map(|expr_opt| Some(Stmt::Return(expr_opt)));
+        // endregion
+
+        // region break_stmt
+        let break_stmt = just(Token::Break)
+            .then_ignore(terminator.clone())
+            .map(|_| Some(Stmt::Break));
+        // endregion
+
+        // region continue_stmt
+        let continue_stmt = just(Token::Continue)
+            .then_ignore(terminator.clone())
+            .map(|_| Some(Stmt::Continue));
+        // endregion
+
+        // region repeat_stmt
+        let repeat_stmt = just(Token::Repeat)
+            .ignore_then(
+                expr.clone()
+                    .delimited_by(just(Token::LeftParen), just(Token::RightParen)),
+            )
+            .then_ignore(just(Token::Newline).repeated())
+            .then(statement.clone())
+            .map_with(|(count, body), e| {
+                let span = SimpleSpan::into_range(e.span());
+                body.map(|stmt| Stmt::Repeat(Box::new(count), Box::new(stmt), span))
+            });
+        // endregion
+
+        // region while_stmt
+        let while_stmt = just(Token::While)
+            .ignore_then(
+                expr.clone()
+                    .delimited_by(just(Token::LeftParen), just(Token::RightParen))
+                    .or(expr.clone()),
+            )
+            .then_ignore(just(Token::Newline).repeated())
+            .then(statement.clone())
+            .map_with(|(cond, body), e| {
+                let span = SimpleSpan::into_range(e.span());
+                body.map(|stmt| Stmt::While(Box::new(cond), Box::new(stmt), span))
+            });
+        // endregion
+
+        // region do_until_stmt
+        let do_until_stmt = just(Token::Do)
+            .ignore_then(just(Token::Newline).repeated())
+            .ignore_then(statement.clone())
+            .then_ignore(just(Token::Newline).repeated())
+            .then_ignore(just(Token::Until))
+            .then(
+                expr.clone()
+                    .delimited_by(just(Token::LeftParen), just(Token::RightParen)),
+            )
+            .then_ignore(terminator.clone())
+            .map_with(|(body, cond), e| {
+                let span = SimpleSpan::into_range(e.span());
+                body.map(|stmt| Stmt::DoUntil(Box::new(stmt), Box::new(cond), span))
+            });
+        // endregion
+
+        // region for_stmt
+        // Each clause may be empty but is always followed by its separator. A
+        // missing one is reported, naming the clause it should follow, and the
+        // header parses on as if it were there.
+        let for_separator = |clause: &'static str| {
+            just(Token::Semicolon)
+                .or_not()
+                .validate(move |semicolon, e, emitter| {
+                    if semicolon.is_none() {
+                        emitter.emit(Rich::custom(
+                            e.span(),
+                            format!("expected ';' after the for-loop {}", clause),
+                        ));
+                    }
+                })
+        };
+        let for_init = choice((
+            just(Token::Var)
+                .ignore_then(
+                    variable_decl
+                        .clone()
+                        .separated_by(just(Token::Comma))
+                        .at_least(1)
+                        .collect::<Vec<_>>(),
+                )
+                .map(|vars| Some(Box::new(Stmt::Var(vars)))),
+            expr.clone().map(|e| Some(Box::new(Stmt::Expr(e)))),
+            empty().to(None),
+        ));
+        // Several updates run left to right, as a block
+        let for_update = expr
+            .clone()
+            .separated_by(just(Token::Comma))
+            .collect::<Vec<_>>()
+            .map_with(|mut exprs, e| match exprs.len() {
+                0 => None,
+                1 => exprs.pop().map(|ex| Box::new(Stmt::Expr(ex))),
+                _ => Some(Box::new(Stmt::Block(
+                    exprs.into_iter().map(Stmt::Expr).collect(),
+                    SimpleSpan::into_range(e.span()),
+                ))),
+            });
+        let for_stmt = just(Token::For)
+            .ignore_then(just(Token::LeftParen))
+            .ignore_then(for_init)
+            .then_ignore(for_separator("initializer"))
+            .then(expr.clone().or_not().map(|e| e.map(Box::new)))
+            .then_ignore(for_separator("condition"))
+            .then(for_update)
+            .then_ignore(just(Token::RightParen))
+            .then_ignore(just(Token::Newline).repeated())
+            .then(statement.clone())
+            .map_with(|(((init, cond), update), body), e| {
+                let span = SimpleSpan::into_range(e.span());
+                body.map(|stmt| Stmt::For(init, cond, update, Box::new(stmt), span))
+            });
         // endregion
 
         // region if_stmt
         let if_stmt = recursive(|if_stmt| {
+            let if_stmt = nested(if_stmt);
             let block = statement
                 .clone()
                 .repeated()
                 .collect::<Vec<Option<Stmt>>>()
                 .map(|stmts| stmts.into_iter().flatten().collect::<Vec<Stmt>>())
                 .delimited_by(just(Token::LeftBrace), just(Token::RightBrace))
-                .map(Stmt::Block);
+                .map_with(|stmts, e| Stmt::Block(stmts, SimpleSpan::into_range(e.span())));
 
-            let variable_decl = select! { Token::Identifier(s) => s.to_string() }
-                .then(just(Token::Equal).ignore_then(expr.clone()).or_not());
+            let variable_decl = spanned_ident()
+                .then(just(Token::Equal).ignore_then(expr.clone()).or_not())
+                .map(|((name, span), init)| (name, init, span));
             let var_stmt_no_term = just(Token::Var)
                 .ignore_then(
                     variable_decl
@@ -190,9 +508,21 @@ where
             let break_stmt_no_term = just(Token::Break).map(|_| Stmt::Break);
             let continue_stmt_no_term = just(Token::Continue).map(|_| Stmt::Continue);
 
+            // Loops keep their own statement body; an empty one stands in as an empty block
+            let loop_stmt = choice((
+                repeat_stmt.clone(),
+                while_stmt.clone(),
+                do_until_stmt.clone(),
+                for_stmt.clone(),
+            ))
+            .map_with(|stmt, e| {
+                stmt.unwrap_or_else(|| Stmt::Block(vec![], SimpleSpan::into_range(e.span())))
+            });
+
             let body = choice((
                 block,
                 if_stmt,
+                loop_stmt,
                 var_stmt_no_term,
                 return_stmt_no_term,
                 break_stmt_no_term,
@@ -200,15 +530,22 @@ where
                 expr.clone().map(Stmt::Expr),
             ));
 
+            let branch = just(Token::Then)
+                .or_not()
+                .ignore_then(just(Token::Newline).repeated())
+                .ignore_then(body.clone());
+
+            // The parenthesized condition is tried together with its branch, so that
+            // a condition that only starts with parentheses is parsed whole instead
+            let condition_and_branch = choice((
+                expr.clone()
+                    .delimited_by(just(Token::LeftParen), just(Token::RightParen))
+                    .then(branch.clone()),
+                expr.clone().then(branch),
+            ));
+
             just(Token::If)
-                .ignore_then(
-                    expr.clone()
-                        .delimited_by(just(Token::LeftParen), just(Token::RightParen))
-                        .or(expr.clone()),
-                )
-                .then_ignore(just(Token::Then).or_not())
-                .then_ignore(just(Token::Newline).repeated())
-                .then(body.clone())
+                .ignore_then(condition_and_branch)
                 .then_ignore(just(Token::Semicolon).or_not())
                 .then_ignore(just(Token::Newline).repeated())
                 .then(
@@ -218,94 +555,103 @@ where
                         .then_ignore(just(Token::Semicolon).or_not())
                         .or_not(),
                 )
-                .map(|((cond, then_stmt), else_stmt)| {
-                    Stmt::If(Box::new(cond), Box::new(then_stmt), else_stmt.map(Box::new))
+                .map_with(|((cond, then_stmt), else_stmt), e| {
+                    Stmt::If(
+                        Box::new(cond),
+                        Box::new(then_stmt),
+                        else_stmt.map(Box::new),
+                        SimpleSpan::into_range(e.span()),
+                    )
                 })
         });
         // endregion
 
-        // region return_stmt
-        let return_stmt = just(Token::Return)
-            .ignore_then(expr.clone().or_not())
-            .then_ignore(terminator.clone())
-            .map(|expr_opt| Some(Stmt::Return(expr_opt)));
-        // endregion
-
-        // region break_stmt
-        let break_stmt = just(Token::Break)
-            .then_ignore(terminator.clone())
-            .map(|_| Some(Stmt::Break));
-        // endregion
-
-        // region continue_stmt
-        let continue_stmt = just(Token::Continue)
-            .then_ignore(terminator.clone())
-            .map(|_| Some(Stmt::Continue));
-        // endregion
+        // region switch_stmt
+        // `None` for `default`
+        let switch_label = choice((
+            just(Token::Case)
+                .ignore_then(
+                    expr.clone()
+                        .separated_by(just(Token::Comma))
+                        .at_least(1)
+                        .collect::<Vec<_>>(),
+                )
+                .map(Some),
+            just(Token::Default).to(None),
+        ))
+        .then_ignore(just(Token::Colon));
 
-        // region repeat_stmt
-        let repeat_stmt = just(Token::Repeat)
-            .ignore_then(
-                expr.clone()
-                    .delimited_by(just(Token::LeftParen), just(Token::RightParen)),
-            )
-            .then_ignore(just(Token::Newline).repeated())
-            .then(statement.clone())
-            .map(|(count, body)| body.map(|stmt| Stmt::Repeat(Box::new(count), Box::new(stmt))));
-        // endregion
+        // Labels with no statements before the next label join that label's arm
+        let switch_cases = switch_label
+            .then(block_content.clone())
+            .repeated()
+            .collect::<Vec<_>>()
+            .map(|arms| {
+                let mut cases = vec![];
+                let mut pending = SwitchCase::default();
+                let count = arms.len();
+                for (i, (labels, body)) in arms.into_iter().enumerate() {
+                    match labels {
+                        Some(labels) => pending.labels.extend(labels),
+                        None => pending.default = true,
+                    }
+                    if body.is_empty() && i + 1 < count {
+                        continue;
+                    }
+                    pending.body = body;
+                    cases.push(std::mem::take(&mut pending));
+                }
+                cases
+            });
 
-        // region while_stmt
-        let while_stmt = just(Token::While)
+        let switch_stmt = just(Token::Switch)
             .ignore_then(
                 expr.clone()
                     .delimited_by(just(Token::LeftParen), just(Token::RightParen))
                     .or(expr.clone()),
             )
             .then_ignore(just(Token::Newline).repeated())
-            .then(statement.clone())
-            .map(|(cond, body)| body.map(|stmt| Stmt::While(Box::new(cond), Box::new(stmt))));
-        // endregion
-
-        // region do_until_stmt
-        let do_until_stmt = just(Token::Do)
-            .ignore_then(just(Token::Newline).repeated())
-            .ignore_then(statement.clone())
-            .then_ignore(just(Token::Newline).repeated())
-            .then_ignore(just(Token::Until))
             .then(
-                expr.clone()
-                    .delimited_by(just(Token::LeftParen), just(Token::RightParen)),
+                just(Token::Newline)
+                    .repeated()
+                    .ignore_then(switch_cases)
+                    .delimited_by(just(Token::LeftBrace), just(Token::RightBrace)),
             )
-            .then_ignore(terminator.clone())
-            .map(|(body, cond)| body.map(|stmt| Stmt::DoUntil(Box::new(stmt), Box::new(cond))));
+            .validate(move |(value, cases), e, emitter| {
+                let span: SimpleSpan = e.span();
+                if !options.allow_switch {
+                    emitter.emit(Rich::custom(
+                        span,
+                        "switch statements are disabled; enable LanguageOptions::allow_switch",
+                    ));
+                }
+                Some(Stmt::Switch(Box::new(value), cases, span.into_range()))
+            });
         // endregion
 
-        // region for_stmt
-        let for_stmt = just(Token::For)
-            .ignore_then(just(Token::LeftParen))
-            .ignore_then(choice((
-                just(Token::Var)
-                    .ignore_then(
-                        select! { Token::Identifier(s) => s.to_string() }
-                            .then(just(Token::Equal).ignore_then(expr.clone()).or_not()),
-                    )
-                    .map(|(name, init)| Some(Box::new(Stmt::Var(vec![(name, init)])))),
-                expr.clone().map(|e| Some(Box::new(Stmt::Expr(e)))),
-                empty().to(None), // Allow empty init - this should come before semicolon
-            )))
-            .then_ignore(just(Token::Semicolon))
-            .then(expr.clone().or_not().map(|e| e.map(Box::new)))
-            .then_ignore(just(Token::Semicolon))
+        // region function_stmt
+        let function_stmt = just(Token::Function)
+            .ignore_then(spanned_ident())
             .then(
-                expr.clone()
-                    .or_not()
-                    .map(|e| e.map(|ex| Box::new(Stmt::Expr(ex)))),
+                select! { Token::Identifier(s) => s.to_string() }
+                    .separated_by(just(Token::Comma))
+                    .allow_trailing()
+                    .collect()
+                    .delimited_by(just(Token::LeftParen), just(Token::RightParen)),
             )
-            .then_ignore(just(Token::RightParen))
             .then_ignore(just(Token::Newline).repeated())
-            .then(statement.clone())
-            .map(|(((init, cond), update), body)| {
-                body.map(|stmt| Stmt::For(init, cond, update, Box::new(stmt)))
+            .then(
+                block_content
+                    .clone()
+                    .delimited_by(just(Token::LeftBrace), just(Token::RightBrace)),
+            )
+            .map_with(|(((name, span), args), body), e| {
+                Some(Stmt::Function(FuncDef {
+                    name,
+                    func: Func { args, body },
+                    span,
+                    extent: SimpleSpan::into_range(e.span()),
+                }))
             });
         // endregion
 
@@ -320,9 +666,13 @@ where
             while_stmt.clone(),
             do_until_stmt.clone(),
             for_stmt.clone(),
+            switch_stmt,
+            function_stmt,
             block,
         ))
-    });
+        .recover_with(via_parser(statement_recovery))
+    };
+    declared.define(definition);
     // endregion
 
     // region function
@@ -340,30 +690,77 @@ where
         .delimited_by(just(Token::LeftParen), just(Token::RightParen));
 
     let function = just(Token::Function)
-        .ignore_then(select! { Token::Identifier(s) => s.to_string() })
+        .ignore_then(spanned_ident())
         .then(parameters)
+        .then_ignore(just(Token::Newline).repeated())
         .then(function_block)
-        .map(|((name, args), body)| {
+        .map_with(|(((name, span), args), body), e| {
             TopLevel::Function(FuncDef {
                 name,
                 func: Func { args, body },
+                span,
+                extent: SimpleSpan::into_range(e.span()),
+            })
+        });
+    // endregion
+
+    // region enum
+    let enum_member = spanned_ident()
+        .then(just(Token::Equal).ignore_then(expr.clone()).or_not())
+        .map(|((name, span), value)| EnumMember { name, value, span });
+
+    // Members may be spread over several lines
+    let newlines = just(Token::Newline).repeated();
+    let enum_def = just(Token::Enum)
+        .ignore_then(spanned_ident())
+        .then_ignore(newlines.clone())
+        .then(
+            enum_member
+                .padded_by(newlines.clone())
+                .separated_by(just(Token::Comma))
+                .allow_trailing()
+                .collect()
+                .then_ignore(newlines)
+                .delimited_by(just(Token::LeftBrace), just(Token::RightBrace)),
+        )
+        .then_ignore(terminator.clone().or_not())
+        .map(|((name, span), members)| {
+            TopLevel::Enum(EnumDef {
+                name,
+                members,
+                span,
             })
         });
     // endregion
 
+    // region include
+    let include = select! { Token::Include(path) => path.to_string() }
+        .map_with(|path, e| {
+            let span: SimpleSpan = e.span();
+            TopLevel::Include(path, span.into_range())
+        })
+        .then_ignore(terminator.clone().or_not());
+    // endregion
+
     // region top_level
     let top_level = choice((
+        include.map(Some),
         function.map(Some),
+        enum_def.map(Some),
         statement.map(|stmt_opt| stmt_opt.map(TopLevel::Statement)),
     ))
-    .recover_with(skip_then_retry_until(any().ignored(), end()));
+    .recover_with(via_parser(top_level_recovery));
 
     let program = top_level
         .repeated()
         .collect::<Vec<_>>()
         .map(|top_levels| {
             let body = top_levels.into_iter().flatten().collect();
-            Program { body }
+            Program {
+                body,
+                comments: vec![],
+                included: vec![],
+            }
         })
         .then_ignore(end());
     // endregion
@@ -371,35 +768,222 @@ where
     program
 }
 
+/// How deeply the parsers wrapped in [`nested`] are recursing at the point being
+/// parsed. Every cycle of the grammar goes through one of them, so input nesting
+/// past the limit fails where it does instead of exhausting the stack. Being the
+/// parser state, the depth is restored whenever the parser backtracks.
+pub(crate) struct NestingState {
+    limit: usize,
+    depth: usize,
+    exceeded: Option<SimpleSpan>,
+}
+
+impl NestingState {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            depth: 0,
+            exceeded: None,
+        }
+    }
+
+    pub(crate) fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The token where nesting first went past the limit, if it did
+    pub(crate) fn exceeded(&self) -> Option<SimpleSpan> {
+        self.exceeded
+    }
+}
+
+impl Default for NestingState {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_NESTING_DEPTH)
+    }
+}
+
+impl<'src, I: Input<'src>> Inspector<'src, I> for NestingState {
+    type Checkpoint = usize;
+
+    fn on_token(&mut self, _: &I::Token) {}
+
+    fn on_save<'parse>(&self, _: &Cursor<'src, 'parse, I>) -> usize {
+        self.depth
+    }
+
+    fn on_rewind<'parse>(&mut self, marker: &Checkpoint<'src, 'parse, I, usize>) {
+        self.depth = *marker.inspector();
+    }
+}
+
+/// `parser`, one level of nesting deeper. Past the limit it fails at the next token
+/// instead, and once that has happened so does every nested parser, so what is left
+/// of the input is skipped without descending again.
+fn nested<'tokens, 'src: 'tokens, I, O, P>(
+    parser: P,
+) -> impl Parser<'tokens, I, O, Extra<'tokens, 'src>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+    P: Parser<'tokens, I, O, Extra<'tokens, 'src>> + Clone,
+{
+    // Custom parsers, unlike `map_with`, also run when only checking the input
+    let enter = custom::<_, I, (), Extra<'tokens, 'src>>(|inp| {
+        let state = inp.state();
+        if state.depth < state.limit && state.exceeded.is_none() {
+            state.depth += 1;
+            return Ok(());
+        }
+        let before = inp.save();
+        inp.next_maybe();
+        let span = inp.span_since(before.cursor());
+        inp.rewind(before);
+        let state = inp.state();
+        state.exceeded.get_or_insert(span);
+        Err(Rich::custom(span, too_deeply_nested(state.limit)))
+    });
+    let leave = custom::<_, I, (), Extra<'tokens, 'src>>(|inp| {
+        inp.state().depth -= 1;
+        Ok(())
+    });
+    enter.ignore_then(parser).then_ignore(leave)
+}
+
+/// Parses an identifier together with its byte range in the source.
+fn spanned_ident<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, (String, Span), Extra<'tokens, 'src>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    select! { Token::Identifier(s) => s.to_string() }.map_with(|name, e| {
+        let span: SimpleSpan = e.span();
+        (name, span.into_range())
+    })
+}
+
+/// Report an increment or decrement of something other than a variable or an
+/// instance field, such as `1++` or `foo()++`, at the operand
+fn check_step_target<'tokens, 'src: 'tokens>(
+    operand: &Expr,
+    span: SimpleSpan,
+    emitter: &mut chumsky::input::Emitter<Rich<'tokens, Token<'src>>>,
+) {
+    let message = match operand {
+        Expr::Identifier(..) | Expr::Field(..) => return,
+        // Most likely `---a` or `+++a`, written to repeat the sign
+        Expr::Negative(_) | Expr::Positive(_) => {
+            "increment/decrement target must be a variable; to repeat a sign, separate it with spaces, e.g. `- - -a`"
+        }
+        _ => "increment/decrement target must be a variable",
+    };
+    emitter.emit(Rich::custom(span, message));
+}
+
+/// A number literal, kept as an integer if `exact_integers` is on and it is one
+/// that fits an `i64`
+fn number_literal(text: &str, exact_integers: bool, span: Span) -> Expr {
+    match text.parse::<i64>() {
+        Ok(n) if exact_integers => Expr::Integer(n, span),
+        _ => Expr::Number(text.parse().unwrap(), span),
+    }
+}
+
 /// Parses a single expression, handling operator precedence, primitives, and function calls.
-fn expr_parser<'tokens, 'src: 'tokens, I>()
--> impl Parser<'tokens, I, Expr, extra::Err<Rich<'tokens, Token<'src>>>> + Clone
+/// Integer literals are kept exact if `exact_integers` is on. `function_body` parses
+/// the `{ ... }` body of an anonymous function.
+fn expr_parser<'tokens, 'src: 'tokens, I, B>(
+    exact_integers: bool,
+    function_body: B,
+) -> impl Parser<'tokens, I, Expr, Extra<'tokens, 'src>> + Clone
 where
     I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+    B: Parser<'tokens, I, Vec<Stmt>, Extra<'tokens, 'src>> + Clone + 'tokens,
 {
     recursive(|expr| {
-        let ident = select! { Token::Identifier(s) => s.to_string() };
-
+        let expr = nested(expr);
         // region Primitives and atoms
+        let instance = choice((
+            just(Token::Self_).map_with(|_, e| Expr::SelfRef(SimpleSpan::into_range(e.span()))),
+            just(Token::Other).map_with(|_, e| Expr::OtherRef(SimpleSpan::into_range(e.span()))),
+        ));
         let atom = choice((
-            select! { Token::Number(x) => Expr::Number(x.parse().unwrap()) },
-            select! { Token::String(x) => Expr::String(x.to_string()) },
-            just(Token::True).to(Expr::True(true)),
-            just(Token::False).to(Expr::False(false)),
-            just(Token::Null).to(Expr::Null),
-            // Function call: identifier followed by a parenthesized list of expressions
-            ident
-                .clone()
+            select! {
+                Token::Number(x) = e => number_literal(x, exact_integers, SimpleSpan::into_range(e.span()))
+            },
+            select! {
+                Token::String(x) = e => Expr::String(x.to_string(), SimpleSpan::into_range(e.span())),
+                Token::VerbatimString(x) = e => Expr::String(x.replace("\"\"", "\""), SimpleSpan::into_range(e.span())),
+            },
+            just(Token::True).map_with(|_, e| Expr::True(true, SimpleSpan::into_range(e.span()))),
+            just(Token::False).map_with(|_, e| Expr::False(false, SimpleSpan::into_range(e.span()))),
+            just(Token::Null).map_with(|_, e| Expr::Null(SimpleSpan::into_range(e.span()))),
+            // Function call: identifier followed by a parenthesized list of arguments.
+            // An argument slot may be left empty, e.g. `foo(1,,3)`, and is then undefined.
+            select! { Token::Identifier(s) => s.to_string() }
                 .then(
                     expr.clone()
+                        .or_not()
+                        .map_with(|arg, e| (arg, e.span()))
+                        .separated_by(just(Token::Comma))
+                        .collect::<Vec<(Option<Expr>, SimpleSpan)>>()
+                        .delimited_by(just(Token::LeftParen), just(Token::RightParen)),
+                )
+                .validate(|(name, mut slots), e, emitter| {
+                    // `foo()` is a single empty slot and `foo(1,)` ends in one
+                    if slots.len() == 1 && slots[0].0.is_none() {
+                        slots.clear();
+                    } else if slots.last().is_some_and(|(arg, _)| arg.is_none()) {
+                        slots.pop();
+                    }
+                    if !slots.is_empty() && slots.iter().all(|(arg, _)| arg.is_none()) {
+                        emitter.emit(Rich::custom(
+                            slots[0].1,
+                            format!(
+                                "call to '{}' has only empty argument slots; expected an argument before ','",
+                                name
+                            ),
+                        ));
+                    }
+                    let args = slots
+                        .into_iter()
+                        .map(|(arg, _)| arg.unwrap_or(Expr::Undefined))
+                        .collect();
+                    Expr::Call(name, args, SimpleSpan::into_range(e.span()))
+                }),
+            // Member access, so far only resolvable on enums
+            spanned_ident()
+                .then_ignore(just(Token::Dot))
+                .then(select! { Token::Identifier(s) => s.to_string() })
+                .map_with(|((object, _), member), e| {
+                    let span: SimpleSpan = e.span();
+                    Expr::Member(object, member, span.into_range())
+                }),
+            // A field of the instance the host bound to `self` or `other`
+            instance
+                .clone()
+                .then_ignore(just(Token::Dot))
+                .then(select! { Token::Identifier(s) => s.to_string() })
+                .map_with(|(object, field), e| {
+                    let span: SimpleSpan = e.span();
+                    Expr::Field(Box::new(object), field, span.into_range())
+                }),
+            instance,
+            // Anonymous function: `function (parameters) { body }`
+            just(Token::Function)
+                .ignore_then(
+                    select! { Token::Identifier(s) => s.to_string() }
                         .separated_by(just(Token::Comma))
                         .allow_trailing()
                         .collect()
                         .delimited_by(just(Token::LeftParen), just(Token::RightParen)),
                 )
-                .map(|(name, args)| Expr::Call(name, args)),
+                .then_ignore(just(Token::Newline).repeated())
+                .then(function_body)
+                .map_with(|(args, body), e| {
+                    Expr::Function(Func { args, body }, SimpleSpan::into_range(e.span()))
+                }),
             // A lone identifier is a variable
-            ident.map(Expr::Identifier),
+            spanned_ident().map(|(name, span)| Expr::Identifier(name, span)),
             // Parenthesized expression
             expr.clone()
                 .delimited_by(just(Token::LeftParen), just(Token::RightParen))
@@ -408,8 +992,77 @@ where
         .boxed();
         // endregion
 
+        // region Accessors
+        // `list[| i]`, `map[? key]`, `grid[# x, y]` and `array[@ i]`, chained left to
+        // right as in `grid[# 0, 0][| 1]`. The wrong number of indices is reported
+        // without stopping the parse.
+        let accessor = choice((
+            just(Token::ListAccessor).to(AccessorKind::List),
+            just(Token::MapAccessor).to(AccessorKind::Map),
+            just(Token::GridAccessor).to(AccessorKind::Grid),
+            just(Token::ArrayAccessor).to(AccessorKind::ArrayRef),
+        ))
+        .then(
+            expr.clone()
+                .separated_by(just(Token::Comma))
+                .at_least(1)
+                .collect::<Vec<Expr>>(),
+        )
+        .then_ignore(just(Token::RightBracket))
+        .validate(|(kind, indices): (AccessorKind, Vec<Expr>), e, emitter| {
+            if indices.len() != kind.arity() {
+                let expected = match kind.arity() {
+                    1 => "one index".to_string(),
+                    n => format!("{} indices", n),
+                };
+                emitter.emit(Rich::custom(
+                    e.span(),
+                    format!(
+                        "the '{}' accessor takes {} but {} were given",
+                        kind.opening(),
+                        expected,
+                        indices.len()
+                    ),
+                ));
+            }
+            (kind, indices)
+        });
+        let accessed = atom
+            .foldl_with(accessor.repeated(), |target, (kind, indices), e| {
+                let span: SimpleSpan = e.span();
+                Expr::Accessor(kind, Box::new(target), indices, span.into_range())
+            })
+            .boxed();
+        // endregion
+
+        // region Postfix operators (increment/decrement)
+        // Postfix operators bind tighter than prefix ones, so `-x++` negates `x++`
+        let postfix = accessed
+            .map_with(|operand, e| (operand, e.span()))
+            .then(
+                choice((
+                    just(Token::Increment).to(Expr::PostIncrement as fn(_) -> _),
+                    just(Token::Decrement).to(Expr::PostDecrement as fn(_) -> _),
+                ))
+                .or_not(),
+            )
+            .validate(|((operand, span), op), _, emitter| match op {
+                // Only variables and fields are valid targets; any other operand is
+                // parsed anyway and reported, so parsing carries on
+                Some(op) => {
+                    check_step_target(&operand, span, emitter);
+                    op(Box::new(operand))
+                }
+                None => operand,
+            })
+            .boxed();
+        // endregion
+
         // region Unary operators
+        // Any number of prefix operators apply over a postfix expression, e.g.
+        // `- ~x--` is `-(~(x--))`
         let unary = recursive(|unary| {
+            let unary = nested(unary);
             choice((
                 just(Token::Not)
                     .ignore_then(unary.clone())
@@ -423,44 +1076,33 @@ where
                 just(Token::Minus)
                     .ignore_then(unary.clone())
                     .map(|e| Expr::Negative(Box::new(e))),
-                // Increment/decrement only work on identifiers
-                just(Token::Increment)
-                    .ignore_then(select! { Token::Identifier(s) => s.to_string() })
-                    .map(|id| Expr::PreIncrement(Box::new(Expr::Identifier(id)))),
-                just(Token::Decrement)
-                    .ignore_then(select! { Token::Identifier(s) => s.to_string() })
-                    .map(|id| Expr::PreDecrement(Box::new(Expr::Identifier(id)))),
-                atom, // Use atom here instead of the old 'primary'
+                choice((
+                    just(Token::Increment).to(Expr::PreIncrement as fn(_) -> _),
+                    just(Token::Decrement).to(Expr::PreDecrement as fn(_) -> _),
+                ))
+                .then(unary.clone().map_with(|operand, e| (operand, e.span())))
+                .validate(|(op, (operand, span)), _, emitter| {
+                    check_step_target(&operand, span, emitter);
+                    op(Box::new(operand))
+                }),
+                postfix,
             ))
         })
         .boxed();
         // endregion
 
-        // region Postfix operators (increment/decrement)
-        let postfix = choice((
-            // Postfix increment/decrement only work on identifiers
-            select! { Token::Identifier(s) => s.to_string() }
-                .then(choice((
-                    just(Token::Increment).to(Expr::PostIncrement as fn(_) -> _),
-                    just(Token::Decrement).to(Expr::PostDecrement as fn(_) -> _),
-                )))
-                .map(|(id, op)| op(Box::new(Expr::Identifier(id)))),
-            // All other unary expressions (without postfix operators)
-            unary.clone(),
-        ))
-        .boxed();
-        // endregion
-
         // region Multiplication, division, modulo
-        let factor = postfix
+        let factor = unary
             .clone()
             .foldl(
                 choice((
                     just(Token::Star).to(Expr::Multiplication as fn(_, _) -> _),
                     just(Token::Slash).to(Expr::Division as fn(_, _) -> _),
                     just(Token::Percent).to(Expr::Percent as fn(_, _) -> _),
+                    just(Token::Mod).to(Expr::Percent as fn(_, _) -> _),
+                    just(Token::Div).to(Expr::IntDivision as fn(_, _) -> _),
                 ))
-                .then(postfix)
+                .then(unary)
                 .repeated(),
                 |lhs, (op, rhs)| op(Box::new(lhs), Box::new(rhs)),
             )
@@ -482,8 +1124,23 @@ where
             .boxed();
         // endregion
 
+        // region Shifts
+        let shift = term
+            .clone()
+            .foldl(
+                choice((
+                    just(Token::ShiftLeft).to(Expr::ShiftLeft as fn(_, _) -> _),
+                    just(Token::ShiftRight).to(Expr::ShiftRight as fn(_, _) -> _),
+                ))
+                .then(term)
+                .repeated(),
+                |lhs, (op, rhs)| op(Box::new(lhs), Box::new(rhs)),
+            )
+            .boxed();
+        // endregion
+
         // region Comparisons
-        let comparison = term
+        let comparison = shift
             .clone()
             .foldl(
                 choice((
@@ -492,7 +1149,7 @@ where
                     just(Token::Less).to(Expr::Less as fn(_, _) -> _),
                     just(Token::LessEqual).to(Expr::LessEqual as fn(_, _) -> _),
                 ))
-                .then(term)
+                .then(shift)
                 .repeated(),
                 |lhs, (op, rhs)| op(Box::new(lhs), Box::new(rhs)),
             )
@@ -600,7 +1257,7 @@ where
                     just(Token::Question)
                         .ignore_then(expr.clone())
                         .then_ignore(just(Token::Colon))
-                        .then(ternary)
+                        .then(nested(ternary))
                         .or_not(),
                 )
                 .map(|(cond, opt)| {
@@ -628,7 +1285,7 @@ where
                         just(Token::SlashEqual).to(Expr::SlashEqual as fn(_, _) -> _),
                         just(Token::PercentEqual).to(Expr::PercentEqual as fn(_, _) -> _),
                     ))
-                    .then(assignment)
+                    .then(nested(assignment))
                     .or_not(),
                 )
                 .map(|(lhs, opt)| {
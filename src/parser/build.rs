@@ -0,0 +1,490 @@
+//! Constructors for putting an AST together by hand, for tests and tools that
+//! generate or compare programs:
+//!
+//! ```
+//! use col::build::*;
+//! use col::handler::parse_handler::ParseHandler;
+//!
+//! // var x = 1; foo(x + 2);
+//! let built = program([
+//!     statement(var_decl([("x", Some(num(1.0)))])),
+//!     statement(expr_stmt(call("foo", [binop_add(ident("x"), num(2.0))]))),
+//! ]);
+//! let parsed = ParseHandler::parse_program("var x = 1; foo(x + 2);").unwrap();
+//! assert_eq!(without_spans(parsed), built);
+//! ```
+//!
+//! There is one constructor for every kind of node. Built nodes have empty spans,
+//! `0..0`, so a parsed program is compared to one after [`without_spans`].
+
+use crate::parser::Span;
+use crate::parser::enum_def::{EnumDef, EnumMember};
+use crate::parser::expr::{AccessorKind, Expr};
+use crate::parser::func::Func;
+use crate::parser::func_def::FuncDef;
+use crate::parser::inliner::children_mut;
+use crate::parser::program::Program;
+use crate::parser::stmt::{Stmt, SwitchCase};
+use crate::parser::top_level::TopLevel;
+
+fn no_span() -> Span {
+    0..0
+}
+
+// region program
+
+pub fn program(body: impl IntoIterator<Item = TopLevel>) -> Program {
+    Program {
+        body: body.into_iter().collect(),
+        comments: vec![],
+        included: vec![],
+    }
+}
+
+pub fn statement(stmt: Stmt) -> TopLevel {
+    TopLevel::Statement(stmt)
+}
+
+/// `function name(params) { body }` at the top level
+pub fn function(
+    name: impl Into<String>,
+    params: &[&str],
+    body: impl IntoIterator<Item = Stmt>,
+) -> TopLevel {
+    TopLevel::Function(func_def(name, params, body))
+}
+
+/// `enum name { members }`, each member with its value if it has one
+pub fn enum_def<S: Into<String>>(
+    name: impl Into<String>,
+    members: impl IntoIterator<Item = (S, Option<Expr>)>,
+) -> TopLevel {
+    TopLevel::Enum(EnumDef {
+        name: name.into(),
+        members: members
+            .into_iter()
+            .map(|(name, value)| EnumMember {
+                name: name.into(),
+                value,
+                span: no_span(),
+            })
+            .collect(),
+        span: no_span(),
+    })
+}
+
+/// `#include "path"`
+pub fn include(path: impl Into<String>) -> TopLevel {
+    TopLevel::Include(path.into(), no_span())
+}
+
+pub fn top_level_error() -> TopLevel {
+    TopLevel::Error(no_span())
+}
+
+fn func_def(
+    name: impl Into<String>,
+    params: &[&str],
+    body: impl IntoIterator<Item = Stmt>,
+) -> FuncDef {
+    FuncDef {
+        name: name.into(),
+        func: Func {
+            args: params.iter().map(|param| param.to_string()).collect(),
+            body: body.into_iter().collect(),
+        },
+        span: no_span(),
+        extent: no_span(),
+    }
+}
+
+// endregion
+
+// region statements
+
+pub fn expr_stmt(expr: Expr) -> Stmt {
+    Stmt::Expr(expr)
+}
+
+/// `var` with its declarators, e.g. `var_decl([("x", Some(num(1.0))), ("y", None)])`
+pub fn var_decl<S: Into<String>>(vars: impl IntoIterator<Item = (S, Option<Expr>)>) -> Stmt {
+    Stmt::Var(
+        vars.into_iter()
+            .map(|(name, init)| (name.into(), init, no_span()))
+            .collect(),
+    )
+}
+
+pub fn if_stmt(cond: Expr, then_stmt: Stmt, else_stmt: Option<Stmt>) -> Stmt {
+    Stmt::If(
+        Box::new(cond),
+        Box::new(then_stmt),
+        else_stmt.map(Box::new),
+        no_span(),
+    )
+}
+
+pub fn block(stmts: impl IntoIterator<Item = Stmt>) -> Stmt {
+    Stmt::Block(stmts.into_iter().collect(), no_span())
+}
+
+pub fn return_stmt(value: Option<Expr>) -> Stmt {
+    Stmt::Return(value)
+}
+
+pub fn break_stmt() -> Stmt {
+    Stmt::Break
+}
+
+pub fn continue_stmt() -> Stmt {
+    Stmt::Continue
+}
+
+pub fn repeat(count: Expr, body: Stmt) -> Stmt {
+    Stmt::Repeat(Box::new(count), Box::new(body), no_span())
+}
+
+pub fn while_loop(cond: Expr, body: Stmt) -> Stmt {
+    Stmt::While(Box::new(cond), Box::new(body), no_span())
+}
+
+pub fn do_until(body: Stmt, cond: Expr) -> Stmt {
+    Stmt::DoUntil(Box::new(body), Box::new(cond), no_span())
+}
+
+pub fn for_loop(init: Option<Stmt>, cond: Option<Expr>, update: Option<Stmt>, body: Stmt) -> Stmt {
+    Stmt::For(
+        init.map(Box::new),
+        cond.map(Box::new),
+        update.map(Box::new),
+        Box::new(body),
+        no_span(),
+    )
+}
+
+pub fn switch(value: Expr, cases: impl IntoIterator<Item = SwitchCase>) -> Stmt {
+    Stmt::Switch(Box::new(value), cases.into_iter().collect(), no_span())
+}
+
+/// `case` with `labels`, e.g. `case 1, 2:`, and the statements after it
+pub fn case(
+    labels: impl IntoIterator<Item = Expr>,
+    body: impl IntoIterator<Item = Stmt>,
+) -> SwitchCase {
+    SwitchCase {
+        labels: labels.into_iter().collect(),
+        default: false,
+        body: body.into_iter().collect(),
+    }
+}
+
+/// `default:` and the statements after it
+pub fn default_case(body: impl IntoIterator<Item = Stmt>) -> SwitchCase {
+    SwitchCase {
+        labels: vec![],
+        default: true,
+        body: body.into_iter().collect(),
+    }
+}
+
+/// A function defined inside another, or inside a block
+pub fn function_stmt(
+    name: impl Into<String>,
+    params: &[&str],
+    body: impl IntoIterator<Item = Stmt>,
+) -> Stmt {
+    Stmt::Function(func_def(name, params, body))
+}
+
+pub fn stmt_error() -> Stmt {
+    Stmt::Error(no_span())
+}
+
+// endregion
+
+// region expressions
+
+pub fn num(value: f64) -> Expr {
+    Expr::Number(value, no_span())
+}
+
+pub fn string(value: impl Into<String>) -> Expr {
+    Expr::String(value.into(), no_span())
+}
+
+/// `true` or `false`
+pub fn boolean(value: bool) -> Expr {
+    if value {
+        Expr::True(true, no_span())
+    } else {
+        Expr::False(false, no_span())
+    }
+}
+
+pub fn null() -> Expr {
+    Expr::Null(no_span())
+}
+
+/// An argument slot left empty in a call
+pub fn undefined() -> Expr {
+    Expr::Undefined
+}
+
+pub fn ident(name: impl Into<String>) -> Expr {
+    Expr::Identifier(name.into(), no_span())
+}
+
+pub fn call(name: impl Into<String>, args: impl IntoIterator<Item = Expr>) -> Expr {
+    Expr::Call(name.into(), args.into_iter().collect(), no_span())
+}
+
+/// `object.member`
+pub fn member(object: impl Into<String>, member: impl Into<String>) -> Expr {
+    Expr::Member(object.into(), member.into(), no_span())
+}
+
+pub fn self_ref() -> Expr {
+    Expr::SelfRef(no_span())
+}
+
+pub fn other_ref() -> Expr {
+    Expr::OtherRef(no_span())
+}
+
+/// `function(params) { body }`, an anonymous function
+pub fn function_expr(params: &[&str], body: impl IntoIterator<Item = Stmt>) -> Expr {
+    Expr::Function(func_def("", params, body).func, no_span())
+}
+
+/// `object.name`, where `object` is `self` or `other`
+pub fn field(object: Expr, name: impl Into<String>) -> Expr {
+    Expr::Field(Box::new(object), name.into(), no_span())
+}
+
+/// `target[| index]` and the other accessors, by `kind`
+pub fn accessor(kind: AccessorKind, target: Expr, indices: impl IntoIterator<Item = Expr>) -> Expr {
+    Expr::Accessor(
+        kind,
+        Box::new(target),
+        indices.into_iter().collect(),
+        no_span(),
+    )
+}
+
+pub fn ternary(cond: Expr, then_expr: Expr, else_expr: Expr) -> Expr {
+    Expr::Ternary(Box::new(cond), Box::new(then_expr), Box::new(else_expr))
+}
+
+macro_rules! unary {
+    ($($(#[$doc:meta])* $name:ident => $variant:ident;)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(operand: Expr) -> Expr {
+                Expr::$variant(Box::new(operand))
+            }
+        )*
+    };
+}
+
+macro_rules! binary {
+    ($($(#[$doc:meta])* $name:ident => $variant:ident;)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(lhs: Expr, rhs: Expr) -> Expr {
+                Expr::$variant(Box::new(lhs), Box::new(rhs))
+            }
+        )*
+    };
+}
+
+unary! {
+    /// `!operand`
+    not => Not;
+    /// `~operand`
+    bit_not => BitNot;
+    /// `+operand`
+    positive => Positive;
+    /// `-operand`
+    negative => Negative;
+    /// `(operand)`
+    paren => Paren;
+    /// `++operand`
+    pre_increment => PreIncrement;
+    /// `operand++`
+    post_increment => PostIncrement;
+    /// `--operand`
+    pre_decrement => PreDecrement;
+    /// `operand--`
+    post_decrement => PostDecrement;
+}
+
+binary! {
+    /// `lhs + rhs`
+    binop_add => Addition;
+    /// `lhs - rhs`
+    binop_sub => Subtraction;
+    /// `lhs * rhs`
+    binop_mul => Multiplication;
+    /// `lhs / rhs`
+    binop_div => Division;
+    /// `lhs div rhs`
+    binop_int_div => IntDivision;
+    /// `lhs % rhs`
+    binop_mod => Percent;
+    /// `lhs > rhs`
+    binop_gt => Greater;
+    /// `lhs >= rhs`
+    binop_ge => GreaterEqual;
+    /// `lhs < rhs`
+    binop_lt => Less;
+    /// `lhs <= rhs`
+    binop_le => LessEqual;
+    /// `lhs == rhs`
+    binop_eq => EqualEqual;
+    /// `lhs != rhs`
+    binop_ne => NotEqual;
+    /// `lhs & rhs`
+    binop_bit_and => BitAnd;
+    /// `lhs ^ rhs`
+    binop_bit_xor => BitXor;
+    /// `lhs | rhs`
+    binop_bit_or => BitOr;
+    /// `lhs << rhs`
+    binop_shl => ShiftLeft;
+    /// `lhs >> rhs`
+    binop_shr => ShiftRight;
+    /// `lhs && rhs`
+    binop_and => And;
+    /// `lhs ^^ rhs`
+    binop_xor => Xor;
+    /// `lhs || rhs`
+    binop_or => Or;
+    /// `target = value`
+    assign => Equal;
+    /// `target += value`
+    assign_add => PlusEqual;
+    /// `target -= value`
+    assign_sub => MinusEqual;
+    /// `target *= value`
+    assign_mul => StarEqual;
+    /// `target /= value`
+    assign_div => SlashEqual;
+    /// `target %= value`
+    assign_mod => PercentEqual;
+}
+
+// endregion
+
+// region spans
+
+/// `program` with every span emptied, as the constructors here leave them
+pub fn without_spans(mut program: Program) -> Program {
+    for top_level in &mut program.body {
+        match top_level {
+            TopLevel::Statement(stmt) => clear_stmt(stmt),
+            TopLevel::Function(func_def) => clear_func_def(func_def),
+            TopLevel::Enum(enum_def) => {
+                enum_def.span = no_span();
+                for member in &mut enum_def.members {
+                    member.span = no_span();
+                    if let Some(value) = &mut member.value {
+                        clear_expr(value);
+                    }
+                }
+            }
+            TopLevel::Include(_, span) | TopLevel::Error(span) => *span = no_span(),
+        }
+    }
+    for comment in &mut program.comments {
+        comment.span = no_span();
+    }
+    program
+}
+
+fn clear_func_def(func_def: &mut FuncDef) {
+    func_def.span = no_span();
+    func_def.extent = no_span();
+    func_def.func.body.iter_mut().for_each(clear_stmt);
+}
+
+fn clear_stmt(stmt: &mut Stmt) {
+    match stmt {
+        Stmt::Expr(expr) | Stmt::Return(Some(expr)) => clear_expr(expr),
+        Stmt::Var(vars) => {
+            for (_, init, span) in vars {
+                if let Some(init) = init {
+                    clear_expr(init);
+                }
+                *span = no_span();
+            }
+        }
+        Stmt::If(cond, then_stmt, else_stmt, span) => {
+            clear_expr(cond);
+            clear_stmt(then_stmt);
+            if let Some(else_stmt) = else_stmt {
+                clear_stmt(else_stmt);
+            }
+            *span = no_span();
+        }
+        Stmt::Block(stmts, span) => {
+            stmts.iter_mut().for_each(clear_stmt);
+            *span = no_span();
+        }
+        Stmt::Repeat(cond, body, span)
+        | Stmt::While(cond, body, span)
+        | Stmt::DoUntil(body, cond, span) => {
+            clear_expr(cond);
+            clear_stmt(body);
+            *span = no_span();
+        }
+        Stmt::For(init, cond, update, body, span) => {
+            for stmt in init.iter_mut().chain(update) {
+                clear_stmt(stmt);
+            }
+            if let Some(cond) = cond {
+                clear_expr(cond);
+            }
+            clear_stmt(body);
+            *span = no_span();
+        }
+        Stmt::Switch(value, cases, span) => {
+            clear_expr(value);
+            for case in cases {
+                case.labels.iter_mut().for_each(clear_expr);
+                case.body.iter_mut().for_each(clear_stmt);
+            }
+            *span = no_span();
+        }
+        Stmt::Function(func_def) => clear_func_def(func_def),
+        Stmt::Error(span) => *span = no_span(),
+        Stmt::Return(None) | Stmt::Break | Stmt::Continue => {}
+    }
+}
+
+fn clear_expr(expr: &mut Expr) {
+    match expr {
+        Expr::Number(_, span)
+        | Expr::Integer(_, span)
+        | Expr::String(_, span)
+        | Expr::True(_, span)
+        | Expr::False(_, span)
+        | Expr::Null(span)
+        | Expr::Identifier(_, span)
+        | Expr::Call(_, _, span)
+        | Expr::Member(_, _, span)
+        | Expr::SelfRef(span)
+        | Expr::OtherRef(span)
+        | Expr::Field(_, _, span)
+        | Expr::Accessor(_, _, _, span) => *span = no_span(),
+        Expr::Function(func, span) => {
+            *span = no_span();
+            func.body.iter_mut().for_each(clear_stmt);
+        }
+        _ => {}
+    }
+    for child in children_mut(expr) {
+        clear_expr(child);
+    }
+}
+
+// endregion
@@ -0,0 +1,201 @@
+//! Upper bounds on the size of a program, for hosts compiling scripts they do not
+//! trust. The program is measured in one cheap pass right after parsing, so one
+//! that is too big is rejected before any code is generated for it. How deeply it
+//! nests and how long its identifiers are is checked while parsing.
+
+use crate::parser::expr::Expr;
+use crate::parser::func_def::FuncDef;
+use crate::parser::program::Program;
+use crate::parser::stmt::Stmt;
+use crate::parser::visitor::depth_checker::DEFAULT_MAX_NESTING_DEPTH;
+use crate::parser::visitor::{Pass, Visitor};
+use std::fmt;
+
+/// Longest identifier allowed by default, as in GML
+pub const DEFAULT_MAX_IDENTIFIER_LENGTH: usize = 64;
+
+/// How big a program may be. The sizes are unlimited by default; nesting and
+/// identifiers are limited to [`DEFAULT_MAX_NESTING_DEPTH`] and
+/// [`DEFAULT_MAX_IDENTIFIER_LENGTH`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompileLimits {
+    /// Statements, functions, enums and includes at the top level
+    pub max_top_level_items: usize,
+    /// Functions, nested ones included
+    pub max_functions: usize,
+    /// Statements in one function or at the top level, counting those nested in
+    /// blocks and loops but not those of the functions defined inside it
+    pub max_statements_per_function: usize,
+    /// Expression nodes in the whole program
+    pub max_expressions: usize,
+    /// Bytes of all string literals together
+    pub max_string_bytes: usize,
+    /// How deeply statements and expressions may nest, so that neither the parser
+    /// nor the passes after it can exhaust the stack
+    pub max_nesting_depth: usize,
+    /// Characters, not bytes, an identifier may have
+    pub max_identifier_length: usize,
+}
+
+impl Default for CompileLimits {
+    fn default() -> Self {
+        Self {
+            max_top_level_items: usize::MAX,
+            max_functions: usize::MAX,
+            max_statements_per_function: usize::MAX,
+            max_expressions: usize::MAX,
+            max_string_bytes: usize::MAX,
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            max_identifier_length: DEFAULT_MAX_IDENTIFIER_LENGTH,
+        }
+    }
+}
+
+impl CompileLimits {
+    /// Measure `program` and report the first size limit it exceeds, in the order
+    /// of the fields. Nesting and identifiers were checked by the parser.
+    pub fn check(&self, program: &Program) -> Result<(), LimitExceeded> {
+        let mut counter = SizeCounter::default();
+        program.accept(&mut counter);
+        let size = counter.size;
+
+        let checks = [
+            (
+                Limit::TopLevelItems,
+                size.top_level_items,
+                self.max_top_level_items,
+            ),
+            (Limit::Functions, size.functions, self.max_functions),
+            (
+                Limit::StatementsPerFunction,
+                size.max_statements,
+                self.max_statements_per_function,
+            ),
+            (Limit::Expressions, size.expressions, self.max_expressions),
+            (Limit::StringBytes, size.string_bytes, self.max_string_bytes),
+        ];
+        match checks.into_iter().find(|&(_, actual, max)| actual > max) {
+            Some((limit, actual, max)) => Err(LimitExceeded {
+                limit,
+                actual,
+                max,
+                function: match limit {
+                    Limit::StatementsPerFunction => size.largest_function,
+                    _ => None,
+                },
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// One of the [`CompileLimits`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    TopLevelItems,
+    Functions,
+    StatementsPerFunction,
+    Expressions,
+    StringBytes,
+}
+
+/// A program bigger than one of its [`CompileLimits`] allow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    /// How much of it the program has
+    pub actual: usize,
+    /// How much the limit allows
+    pub max: usize,
+    /// For [`Limit::StatementsPerFunction`], the function with the most
+    /// statements; `None` for the top level
+    pub function: Option<String>,
+}
+
+impl LimitExceeded {
+    /// How far the program is over the limit
+    pub fn excess(&self) -> usize {
+        self.actual - self.max
+    }
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.limit, &self.function) {
+            (Limit::TopLevelItems, _) => write!(f, "{} top-level items", self.actual)?,
+            (Limit::Functions, _) => write!(f, "{} functions", self.actual)?,
+            (Limit::StatementsPerFunction, Some(function)) => {
+                write!(f, "{} statements in function '{}'", self.actual, function)?
+            }
+            (Limit::StatementsPerFunction, None) => {
+                write!(f, "{} statements at the top level", self.actual)?
+            }
+            (Limit::Expressions, _) => write!(f, "{} expressions", self.actual)?,
+            (Limit::StringBytes, _) => write!(f, "{} bytes of string literals", self.actual)?,
+        }
+        write!(f, " exceed the limit of {} by {}", self.max, self.excess())
+    }
+}
+
+/// What [`CompileLimits`] measure
+#[derive(Debug, Default)]
+struct ProgramSize {
+    top_level_items: usize,
+    functions: usize,
+    max_statements: usize,
+    /// The function `max_statements` were counted in; `None` for the top level
+    largest_function: Option<String>,
+    expressions: usize,
+    string_bytes: usize,
+}
+
+#[derive(Default)]
+struct SizeCounter {
+    size: ProgramSize,
+    // Statements counted so far in each function being measured, innermost last
+    open: Vec<(Option<String>, usize)>,
+}
+
+impl SizeCounter {
+    /// Stop counting the statements of the innermost function
+    fn close(&mut self) {
+        let Some((function, statements)) = self.open.pop() else {
+            return;
+        };
+        if statements > self.size.max_statements {
+            self.size.max_statements = statements;
+            self.size.largest_function = function;
+        }
+    }
+}
+
+impl Pass for SizeCounter {
+    fn visit_program(&mut self, program: &Program) {
+        self.size.top_level_items = program.body.len();
+        self.open.push((None, 0));
+        self.walk_program(program);
+        self.close();
+    }
+
+    fn visit_func_def(&mut self, func_def: &FuncDef) {
+        self.size.functions += 1;
+        self.open.push((Some(func_def.name.clone()), 0));
+        self.walk_func_def(func_def);
+        self.close();
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Some((_, statements)) = self.open.last_mut() {
+            *statements += 1;
+        }
+        self.walk_stmt(stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.size.expressions += 1;
+        if let Expr::String(text, _) = expr {
+            self.size.string_bytes += text.len();
+        }
+        self.walk_expr(expr);
+    }
+}
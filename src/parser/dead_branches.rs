@@ -0,0 +1,255 @@
+//! Pruning of branches whose condition is a literal, at the AST level.
+//!
+//! An `if` whose condition is constant is replaced by the branch it takes, or
+//! removed when that branch is missing; a `while` whose condition is constantly
+//! false is removed; and a ternary with a constant condition becomes the operand it
+//! picks. A condition is constant when it is `true`, `false` or a number literal,
+//! possibly in parentheses or negated with `!`, so a condition that reads a variable
+//! is never pruned, even one that holds a constant.
+//!
+//! `var` is function-scoped, so a variable declared only in a removed branch may
+//! still be used after it. Such variables are declared where the branch was, with
+//! no initializer; those declared earlier in the function are left alone, so their
+//! values are kept.
+
+use crate::parser::Span;
+use crate::parser::expr::Expr;
+use crate::parser::func_def::FuncDef;
+use crate::parser::inliner::children_mut;
+use crate::parser::program::Program;
+use crate::parser::stmt::Stmt;
+use crate::parser::top_level::TopLevel;
+use std::collections::HashSet;
+
+/// Replace `if`s, `while`s and ternaries whose condition is a literal with what
+/// they evaluate. Returns how many were pruned.
+pub fn prune_dead_branches(program: &mut Program) -> usize {
+    let mut pruner = Pruner::default();
+    let body = std::mem::take(&mut program.body);
+    program.body = body
+        .into_iter()
+        .filter_map(|top_level| match top_level {
+            TopLevel::Statement(stmt) => pruner.stmt(stmt).map(TopLevel::Statement),
+            TopLevel::Function(mut func_def) => {
+                pruner.func_def(&mut func_def);
+                Some(TopLevel::Function(func_def))
+            }
+            TopLevel::Enum(_) | TopLevel::Include(..) | TopLevel::Error(_) => Some(top_level),
+        })
+        .collect();
+    pruner.pruned
+}
+
+/// Whether a condition is always true or always false, deciding truth the way
+/// the generated code does: numbers are true when greater than 0.5
+fn constant_truth(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::True(..) => Some(true),
+        Expr::False(..) => Some(false),
+        Expr::Number(value, _) => Some(*value > 0.5),
+        Expr::Integer(value, _) => Some(*value > 0),
+        Expr::Paren(inner) => constant_truth(inner),
+        Expr::Not(operand) => constant_truth(operand).map(|truth| !truth),
+        _ => None,
+    }
+}
+
+/// Both statements in one block, or whichever of them there is
+fn join(first: Option<Stmt>, second: Option<Stmt>, span: Span) -> Option<Stmt> {
+    match (first, second) {
+        (Some(first), Some(second)) => Some(Stmt::Block(vec![first, second], span)),
+        (first, second) => first.or(second),
+    }
+}
+
+/// Variables a statement declares, not counting those of the functions it defines
+fn collect_declarations(stmt: &Stmt, names: &mut Vec<(String, Span)>) {
+    match stmt {
+        Stmt::Var(vars) => {
+            for (name, _, span) in vars {
+                names.push((name.clone(), span.clone()));
+            }
+        }
+        Stmt::If(_, then_stmt, else_stmt, _) => {
+            collect_declarations(then_stmt, names);
+            if let Some(else_stmt) = else_stmt {
+                collect_declarations(else_stmt, names);
+            }
+        }
+        Stmt::Block(stmts, _) => {
+            for stmt in stmts {
+                collect_declarations(stmt, names);
+            }
+        }
+        Stmt::Repeat(_, body, _) | Stmt::While(_, body, _) | Stmt::DoUntil(body, _, _) => {
+            collect_declarations(body, names)
+        }
+        Stmt::For(init, _, update, body, _) => {
+            for stmt in init.iter().chain(update) {
+                collect_declarations(stmt, names);
+            }
+            collect_declarations(body, names);
+        }
+        Stmt::Switch(_, cases, _) => {
+            for stmt in cases.iter().flat_map(|case| &case.body) {
+                collect_declarations(stmt, names);
+            }
+        }
+        Stmt::Expr(_)
+        | Stmt::Return(_)
+        | Stmt::Break
+        | Stmt::Continue
+        | Stmt::Function(_)
+        | Stmt::Error(_) => {}
+    }
+}
+
+#[derive(Default)]
+struct Pruner {
+    /// Variables and parameters declared so far in the function being pruned
+    declared: HashSet<String>,
+    pruned: usize,
+}
+
+impl Pruner {
+    fn func_def(&mut self, func_def: &mut FuncDef) {
+        let params = func_def.func.args.iter().cloned().collect();
+        let outer = std::mem::replace(&mut self.declared, params);
+        let body = std::mem::take(&mut func_def.func.body);
+        func_def.func.body = self.stmts(body);
+        self.declared = outer;
+    }
+
+    fn stmts(&mut self, stmts: Vec<Stmt>) -> Vec<Stmt> {
+        stmts
+            .into_iter()
+            .filter_map(|stmt| self.stmt(stmt))
+            .collect()
+    }
+
+    /// A statement or a block, which an empty one stands in for if it is pruned
+    fn branch(&mut self, stmt: Stmt, span: &Span) -> Stmt {
+        self.stmt(stmt)
+            .unwrap_or_else(|| Stmt::Block(vec![], span.clone()))
+    }
+
+    /// Prune `stmt`, returning what is left of it, if anything
+    fn stmt(&mut self, stmt: Stmt) -> Option<Stmt> {
+        match stmt {
+            Stmt::Expr(mut expr) => {
+                self.expr(&mut expr);
+                Some(Stmt::Expr(expr))
+            }
+            Stmt::Return(Some(mut expr)) => {
+                self.expr(&mut expr);
+                Some(Stmt::Return(Some(expr)))
+            }
+            Stmt::Var(mut vars) => {
+                for (name, init, _) in &mut vars {
+                    if let Some(init) = init {
+                        self.expr(init);
+                    }
+                    self.declared.insert(name.clone());
+                }
+                Some(Stmt::Var(vars))
+            }
+            Stmt::If(mut cond, then_stmt, else_stmt, span) => {
+                self.expr(&mut cond);
+                match constant_truth(&cond) {
+                    Some(true) => {
+                        self.pruned += 1;
+                        let then_stmt = self.stmt(*then_stmt);
+                        let declarations = else_stmt.and_then(|dead| self.declarations(&dead));
+                        join(declarations, then_stmt, span)
+                    }
+                    Some(false) => {
+                        self.pruned += 1;
+                        let declarations = self.declarations(&then_stmt);
+                        let else_stmt = else_stmt.and_then(|stmt| self.stmt(*stmt));
+                        join(declarations, else_stmt, span)
+                    }
+                    None => {
+                        let then_stmt = self.branch(*then_stmt, &span);
+                        let else_stmt = else_stmt.and_then(|stmt| self.stmt(*stmt)).map(Box::new);
+                        Some(Stmt::If(cond, Box::new(then_stmt), else_stmt, span))
+                    }
+                }
+            }
+            Stmt::While(mut cond, body, span) => {
+                self.expr(&mut cond);
+                if constant_truth(&cond) == Some(false) {
+                    self.pruned += 1;
+                    return self.declarations(&body);
+                }
+                let body = self.branch(*body, &span);
+                Some(Stmt::While(cond, Box::new(body), span))
+            }
+            Stmt::Repeat(mut count, body, span) => {
+                self.expr(&mut count);
+                let body = self.branch(*body, &span);
+                Some(Stmt::Repeat(count, Box::new(body), span))
+            }
+            Stmt::DoUntil(body, mut cond, span) => {
+                let body = self.branch(*body, &span);
+                self.expr(&mut cond);
+                Some(Stmt::DoUntil(Box::new(body), cond, span))
+            }
+            Stmt::For(init, mut cond, update, body, span) => {
+                let init = init.and_then(|stmt| self.stmt(*stmt)).map(Box::new);
+                if let Some(cond) = &mut cond {
+                    self.expr(cond);
+                }
+                let update = update.and_then(|stmt| self.stmt(*stmt)).map(Box::new);
+                let body = self.branch(*body, &span);
+                Some(Stmt::For(init, cond, update, Box::new(body), span))
+            }
+            Stmt::Block(stmts, span) => Some(Stmt::Block(self.stmts(stmts), span)),
+            Stmt::Switch(mut value, mut cases, span) => {
+                self.expr(&mut value);
+                for case in &mut cases {
+                    for label in &mut case.labels {
+                        self.expr(label);
+                    }
+                    let body = std::mem::take(&mut case.body);
+                    case.body = self.stmts(body);
+                }
+                Some(Stmt::Switch(value, cases, span))
+            }
+            Stmt::Function(mut func_def) => {
+                self.func_def(&mut func_def);
+                Some(Stmt::Function(func_def))
+            }
+            Stmt::Return(None) | Stmt::Break | Stmt::Continue | Stmt::Error(_) => Some(stmt),
+        }
+    }
+
+    /// A `var` without initializers for the variables only the removed statement
+    /// `dead` declares, if there are any
+    fn declarations(&mut self, dead: &Stmt) -> Option<Stmt> {
+        let mut names = Vec::new();
+        collect_declarations(dead, &mut names);
+        let vars: Vec<_> = names
+            .into_iter()
+            .filter(|(name, _)| self.declared.insert(name.clone()))
+            .map(|(name, span)| (name, None, span))
+            .collect();
+        (!vars.is_empty()).then_some(Stmt::Var(vars))
+    }
+
+    /// Collapse the ternaries in `expr` whose condition is constant, innermost first
+    fn expr(&mut self, expr: &mut Expr) {
+        for child in children_mut(expr) {
+            self.expr(child);
+        }
+
+        let Expr::Ternary(cond, then_expr, else_expr) = expr else {
+            return;
+        };
+        let Some(truth) = constant_truth(cond) else {
+            return;
+        };
+        let taken = if truth { then_expr } else { else_expr };
+        *expr = Expr::Paren(Box::new(std::mem::replace(&mut **taken, Expr::Undefined)));
+        self.pruned += 1;
+    }
+}
@@ -0,0 +1,22 @@
+use crate::parser::Span;
+use crate::parser::expr::Expr;
+
+/// `enum Name { A, B = 5, C }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDef {
+    pub name: String,
+    /// Members in declaration order
+    pub members: Vec<EnumMember>,
+    /// Span of the enum name
+    pub span: Span,
+}
+
+/// One enum member. Without a value it is one more than the previous member, or 0
+/// for the first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumMember {
+    pub name: String,
+    pub value: Option<Expr>,
+    /// Span of the member name
+    pub span: Span,
+}
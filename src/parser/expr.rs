@@ -1,18 +1,47 @@
+use crate::parser::Span;
+use crate::parser::func::Func;
 use crate::parser::visitor::Visitor;
 
-#[derive(Debug, Clone)]
+/// An expression. Equality is derived, so it compares spans as well, and numbers as
+/// `f64`s: an expression holding NaN is not equal to itself, and `0.0` equals `-0.0`.
+/// [`without_spans`](crate::parser::build::without_spans) makes a parsed program
+/// comparable to one put together with the [`build`](crate::parser::build) helpers.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
-    Number(f64),
-    String(String),
-    True(bool),
-    False(bool),
-    Null,
-    Identifier(String),
-    Call(String, Vec<Expr>),
+    Number(f64, Span),
+    /// An integer literal kept exact; only parsed under
+    /// [`LanguageOptions::exact_integers`](crate::parser::language_options::LanguageOptions::exact_integers)
+    Integer(i64, Span),
+    String(String, Span),
+    True(bool, Span),
+    False(bool, Span),
+    Null(Span),
+    /// An argument slot left empty in a call, e.g. the middle one in `foo(1,,3)`
+    Undefined,
+    /// A variable reference and where it appears
+    Identifier(String, Span),
+    /// A call, spanning from the function name to the closing parenthesis
+    Call(String, Vec<Expr>, Span),
+    /// `object.member` and where it appears; only enum members can be resolved
+    Member(String, String, Span),
+    /// `self`, the instance the host runs the script for
+    SelfRef(Span),
+    /// `other`, the instance the host names as the other party, e.g. in a collision
+    OtherRef(Span),
+    /// An anonymous function, e.g. `function(a) { return a * 2; }`, spanning from
+    /// `function` to the closing `}`
+    Function(Func, Span),
+    /// A field of `self` or `other`, e.g. `self.x`, spanning the whole access
+    Field(Box<Expr>, String, Span),
+    /// An accessor such as `list[| i]` or `grid[# x, y]`: its kind, the collection
+    /// and the indices, spanning from the collection to the closing `]`
+    Accessor(AccessorKind, Box<Expr>, Vec<Expr>, Span),
     Addition(Box<Expr>, Box<Expr>),
     Subtraction(Box<Expr>, Box<Expr>),
     Multiplication(Box<Expr>, Box<Expr>),
     Division(Box<Expr>, Box<Expr>),
+    /// `div`: the quotient truncated toward zero
+    IntDivision(Box<Expr>, Box<Expr>),
     Percent(Box<Expr>, Box<Expr>),
     Not(Box<Expr>),
     BitNot(Box<Expr>),
@@ -28,6 +57,8 @@ pub enum Expr {
     BitAnd(Box<Expr>, Box<Expr>),
     BitXor(Box<Expr>, Box<Expr>),
     BitOr(Box<Expr>, Box<Expr>),
+    ShiftLeft(Box<Expr>, Box<Expr>),
+    ShiftRight(Box<Expr>, Box<Expr>),
     And(Box<Expr>, Box<Expr>),
     Xor(Box<Expr>, Box<Expr>),
     Or(Box<Expr>, Box<Expr>),
@@ -49,3 +80,36 @@ impl Expr {
         visitor.visit_expr(self)
     }
 }
+
+/// The collection an accessor reads, by the sigil after its `[`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessorKind {
+    /// `list[| i]`, an element of a ds_list
+    List,
+    /// `map[? key]`, the value of a key in a ds_map
+    Map,
+    /// `grid[# x, y]`, a cell of a ds_grid
+    Grid,
+    /// `array[@ i]`, an element of an array written through a reference
+    ArrayRef,
+}
+
+impl AccessorKind {
+    /// The tokens that open the accessor, as written
+    pub fn opening(self) -> &'static str {
+        match self {
+            AccessorKind::List => "[|",
+            AccessorKind::Map => "[?",
+            AccessorKind::Grid => "[#",
+            AccessorKind::ArrayRef => "[@",
+        }
+    }
+
+    /// How many indices the accessor takes
+    pub fn arity(self) -> usize {
+        match self {
+            AccessorKind::Grid => 2,
+            AccessorKind::List | AccessorKind::Map | AccessorKind::ArrayRef => 1,
+        }
+    }
+}
@@ -0,0 +1,397 @@
+//! Printing a parsed [`Program`] back as source, with a uniform layout: four-space
+//! indentation, one statement per line and a `;` after every simple statement.
+//! Expressions are printed as parsed, so parentheses appear only where the source
+//! had them.
+//!
+//! Comments collected by
+//! [`ParseHandler::parse_program_with_comments`](crate::handler::parse_handler::ParseHandler::parse_program_with_comments)
+//! are put back by position. One that followed code on its line ends the last line
+//! printed before the next statement; any other goes on its own line in front of the
+//! statement after it. Comments inside an expression or a loop header therefore move
+//! out of it. Code the parser skipped while recovering from an error is left out.
+
+use crate::codegen::ir_generator::debug_info::stmt_start;
+use crate::parser::Span;
+use crate::parser::expr::Expr;
+use crate::parser::func_def::FuncDef;
+use crate::parser::program::Program;
+use crate::parser::stmt::Stmt;
+use crate::parser::top_level::TopLevel;
+use crate::parser::trivia::Comment;
+
+const INDENT: &str = "    ";
+
+/// `program` as formatted source, ending with a newline unless it is empty
+pub fn format_program(program: &Program) -> String {
+    let mut formatter = Formatter {
+        out: String::new(),
+        indent: 0,
+        comments: &program.comments,
+        next: 0,
+        inline: false,
+    };
+    let mut previous: Option<&TopLevel> = None;
+    for item in &program.body {
+        let start = match item {
+            TopLevel::Statement(stmt) => stmt_offset(stmt),
+            TopLevel::Function(func_def) => Some(func_def.extent.start),
+            TopLevel::Enum(enum_def) => Some(enum_def.span.start),
+            TopLevel::Include(_, span) => Some(span.start),
+            TopLevel::Error(_) => continue,
+        };
+        // Definitions are set off by a blank line, after what trails the line before
+        if previous.is_some_and(|previous| is_definition(previous) || is_definition(item)) {
+            if let Some(start) = start {
+                formatter.comments_before(start, true);
+            }
+            formatter.blank_line();
+        }
+
+        match item {
+            TopLevel::Statement(stmt) => formatter.stmt(stmt),
+            TopLevel::Function(func_def) => formatter.function(func_def),
+            TopLevel::Enum(enum_def) => {
+                formatter.comments_before(enum_def.span.start, false);
+                formatter.line(&format!("enum {} {{", enum_def.name));
+                formatter.indent += 1;
+                for member in &enum_def.members {
+                    match &member.value {
+                        Some(value) => {
+                            formatter.line(&format!("{} = {},", member.name, expr(value)))
+                        }
+                        None => formatter.line(&format!("{},", member.name)),
+                    }
+                }
+                formatter.indent -= 1;
+                formatter.line("}");
+            }
+            TopLevel::Include(path, span) => {
+                formatter.comments_before(span.start, false);
+                formatter.line(&format!("#include \"{}\"", path));
+            }
+            TopLevel::Error(_) => {}
+        }
+        previous = Some(item);
+    }
+    formatter.comments_before(usize::MAX, false);
+    formatter.out
+}
+
+fn is_definition(item: &TopLevel) -> bool {
+    matches!(item, TopLevel::Function(_) | TopLevel::Enum(_))
+}
+
+/// Where `stmt` starts, or `None` for statements that record no position, such as
+/// `break`
+fn stmt_offset(stmt: &Stmt) -> Option<usize> {
+    match stmt {
+        Stmt::If(.., span)
+        | Stmt::Block(_, span)
+        | Stmt::Repeat(.., span)
+        | Stmt::While(.., span)
+        | Stmt::DoUntil(.., span)
+        | Stmt::For(.., span)
+        | Stmt::Switch(.., span) => Some(span.start),
+        Stmt::Function(func_def) => Some(func_def.extent.start),
+        _ => stmt_start(stmt).map(|span| span.start),
+    }
+}
+
+struct Formatter<'a> {
+    out: String,
+    indent: usize,
+    comments: &'a [Comment],
+    /// The first comment not printed yet
+    next: usize,
+    /// Print each line after the one before, on one line, as for the body of an
+    /// anonymous function
+    inline: bool,
+}
+
+impl Formatter<'_> {
+    fn line(&mut self, text: &str) {
+        if self.inline {
+            self.out.push_str(text);
+            self.out.push(' ');
+            return;
+        }
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn blank_line(&mut self) {
+        if !self.inline && !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Print the comments that start before `offset`, or only those of them that
+    /// trail a line of code
+    fn comments_before(&mut self, offset: usize, only_trailing: bool) {
+        let comments = self.comments;
+        while let Some(comment) = comments.get(self.next) {
+            if comment.span.start >= offset || (only_trailing && !comment.trailing) {
+                return;
+            }
+            self.next += 1;
+            if comment.trailing && !self.out.is_empty() {
+                self.out.pop();
+                self.out.push(' ');
+                self.out.push_str(&comment.text);
+                self.out.push('\n');
+            } else {
+                self.line(&comment.text);
+            }
+        }
+    }
+
+    /// Remove the `}` line just printed, so the next line can start with it, as in
+    /// `} else {`
+    fn reopen_closing_brace(&mut self, body: &Stmt) -> &'static str {
+        if !matches!(body, Stmt::Block(..)) || !self.out.ends_with("}\n") {
+            return "";
+        }
+        self.out.truncate(self.out.len() - 2);
+        let len = self.out.trim_end_matches(' ').len();
+        self.out.truncate(len);
+        "} "
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        if let Some(start) = stmt_offset(stmt) {
+            self.comments_before(start, false);
+        }
+        match stmt {
+            Stmt::Expr(e) => self.line(&format!("{};", expr(e))),
+            Stmt::Var(vars) => self.line(&format!("var {};", declarators(vars))),
+            Stmt::If(cond, then_stmt, else_stmt, _) => {
+                self.if_chain("", cond, then_stmt, else_stmt.as_deref())
+            }
+            Stmt::Block(stmts, span) => self.block("", stmts, span.end),
+            Stmt::Return(Some(e)) => self.line(&format!("return {};", expr(e))),
+            Stmt::Return(None) => self.line("return;"),
+            Stmt::Break => self.line("break;"),
+            Stmt::Continue => self.line("continue;"),
+            Stmt::Repeat(count, body, _) => self.body(&format!("repeat ({})", expr(count)), body),
+            Stmt::While(cond, body, _) => self.body(&format!("while ({})", expr(cond)), body),
+            Stmt::DoUntil(body, cond, _) => {
+                self.body("do", body);
+                let prefix = self.reopen_closing_brace(body);
+                self.line(&format!("{}until ({});", prefix, expr(cond)));
+            }
+            Stmt::For(init, cond, update, body, _) => {
+                let mut header = String::from("for (");
+                match init.as_deref() {
+                    Some(Stmt::Var(vars)) => header.push_str(&format!("var {}", declarators(vars))),
+                    Some(Stmt::Expr(e)) => header.push_str(&expr(e)),
+                    _ => {}
+                }
+                header.push(';');
+                if let Some(cond) = cond {
+                    header.push_str(&format!(" {}", expr(cond)));
+                }
+                header.push(';');
+                let updates: Vec<String> = match update.as_deref() {
+                    Some(Stmt::Expr(e)) => vec![expr(e)],
+                    Some(Stmt::Block(stmts, _)) => stmts
+                        .iter()
+                        .filter_map(|stmt| match stmt {
+                            Stmt::Expr(e) => Some(expr(e)),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => vec![],
+                };
+                if !updates.is_empty() {
+                    header.push_str(&format!(" {}", updates.join(", ")));
+                }
+                header.push(')');
+                self.body(&header, body);
+            }
+            Stmt::Switch(value, cases, span) => {
+                self.line(&format!("switch ({}) {{", expr(value)));
+                self.indent += 1;
+                for case in cases {
+                    for label in &case.labels {
+                        self.line(&format!("case {}:", expr(label)));
+                    }
+                    if case.default {
+                        self.line("default:");
+                    }
+                    self.indent += 1;
+                    for stmt in &case.body {
+                        self.stmt(stmt);
+                    }
+                    self.indent -= 1;
+                }
+                self.comments_before(span.end, false);
+                self.indent -= 1;
+                self.line("}");
+            }
+            Stmt::Function(func_def) => self.function(func_def),
+            Stmt::Error(_) => {}
+        }
+    }
+
+    /// `if` with its `else if` and `else` branches, `prefix` going before the `if`
+    fn if_chain(&mut self, prefix: &str, cond: &Expr, then_stmt: &Stmt, else_stmt: Option<&Stmt>) {
+        self.body(&format!("{}if ({})", prefix, expr(cond)), then_stmt);
+        let Some(else_stmt) = else_stmt else {
+            return;
+        };
+        let prefix = format!("{}else", self.reopen_closing_brace(then_stmt));
+        match else_stmt {
+            Stmt::If(cond, then_stmt, else_stmt, _) => self.if_chain(
+                &format!("{} ", prefix),
+                cond,
+                then_stmt,
+                else_stmt.as_deref(),
+            ),
+            _ => self.body(&prefix, else_stmt),
+        }
+    }
+
+    /// The body of a compound statement: a block after `header` on the same line, or
+    /// any other statement indented on the line below
+    fn body(&mut self, header: &str, body: &Stmt) {
+        match body {
+            Stmt::Block(stmts, span) => self.block(header, stmts, span.end),
+            _ => {
+                self.line(header);
+                self.indent += 1;
+                self.stmt(body);
+                self.indent -= 1;
+            }
+        }
+    }
+
+    /// `header { stmts }`, where `end` is the offset just past the closing brace
+    fn block(&mut self, header: &str, stmts: &[Stmt], end: usize) {
+        if header.is_empty() {
+            self.line("{");
+        } else {
+            self.line(&format!("{} {{", header));
+        }
+        self.indent += 1;
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+        self.comments_before(end, false);
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn function(&mut self, func_def: &FuncDef) {
+        self.comments_before(func_def.extent.start, false);
+        let header = format!(
+            "function {}({})",
+            func_def.name,
+            func_def.func.args.join(", ")
+        );
+        self.block(&header, &func_def.func.body, func_def.extent.end);
+    }
+}
+
+fn declarators(vars: &[(String, Option<Expr>, Span)]) -> String {
+    vars.iter()
+        .map(|(name, init, _)| match init {
+            Some(init) => format!("{} = {}", name, expr(init)),
+            None => name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn expr(e: &Expr) -> String {
+    let (lhs, op, rhs) = match e {
+        Expr::Number(value, _) => return value.to_string(),
+        Expr::Integer(value, _) => return value.to_string(),
+        // Only a verbatim string can hold a quote or a line break
+        Expr::String(value, _) if value.contains(['"', '\r', '\n', '\u{2028}', '\u{2029}']) => {
+            return format!("@\"{}\"", value.replace('"', "\"\""));
+        }
+        Expr::String(value, _) => return format!("\"{}\"", value),
+        Expr::True(..) => return "true".to_string(),
+        Expr::False(..) => return "false".to_string(),
+        Expr::Null(_) => return "null".to_string(),
+        Expr::Undefined => return String::new(),
+        Expr::Identifier(name, _) => return name.clone(),
+        Expr::Call(name, args, _) => {
+            let args: Vec<String> = args.iter().map(expr).collect();
+            return format!("{}({})", name, args.join(", "));
+        }
+        Expr::Member(object, member, _) => return format!("{}.{}", object, member),
+        Expr::SelfRef(_) => return "self".to_string(),
+        Expr::OtherRef(_) => return "other".to_string(),
+        Expr::Field(object, field, _) => return format!("{}.{}", expr(object), field),
+        Expr::Accessor(kind, target, indices, _) => {
+            let indices: Vec<String> = indices.iter().map(expr).collect();
+            return format!("{}{} {}]", expr(target), kind.opening(), indices.join(", "));
+        }
+        Expr::Not(operand) => return prefix("!", operand),
+        Expr::BitNot(operand) => return prefix("~", operand),
+        Expr::Positive(operand) => return prefix("+", operand),
+        Expr::Negative(operand) => return prefix("-", operand),
+        Expr::PreIncrement(operand) => return prefix("++", operand),
+        Expr::PreDecrement(operand) => return prefix("--", operand),
+        Expr::PostIncrement(operand) => return format!("{}++", expr(operand)),
+        Expr::PostDecrement(operand) => return format!("{}--", expr(operand)),
+        Expr::Paren(inner) => return format!("({})", expr(inner)),
+        Expr::Ternary(cond, then_expr, else_expr) => {
+            return format!("{} ? {} : {}", expr(cond), expr(then_expr), expr(else_expr));
+        }
+        Expr::Function(func, _) => {
+            let mut body = Formatter {
+                out: String::new(),
+                indent: 0,
+                comments: &[],
+                next: 0,
+                inline: true,
+            };
+            for stmt in &func.body {
+                body.stmt(stmt);
+            }
+            return format!("function({}) {{ {}}}", func.args.join(", "), body.out);
+        }
+        Expr::Addition(lhs, rhs) => (lhs, "+", rhs),
+        Expr::Subtraction(lhs, rhs) => (lhs, "-", rhs),
+        Expr::Multiplication(lhs, rhs) => (lhs, "*", rhs),
+        Expr::Division(lhs, rhs) => (lhs, "/", rhs),
+        Expr::IntDivision(lhs, rhs) => (lhs, "div", rhs),
+        Expr::Percent(lhs, rhs) => (lhs, "%", rhs),
+        Expr::Greater(lhs, rhs) => (lhs, ">", rhs),
+        Expr::GreaterEqual(lhs, rhs) => (lhs, ">=", rhs),
+        Expr::Less(lhs, rhs) => (lhs, "<", rhs),
+        Expr::LessEqual(lhs, rhs) => (lhs, "<=", rhs),
+        Expr::EqualEqual(lhs, rhs) => (lhs, "==", rhs),
+        Expr::NotEqual(lhs, rhs) => (lhs, "!=", rhs),
+        Expr::BitAnd(lhs, rhs) => (lhs, "&", rhs),
+        Expr::BitXor(lhs, rhs) => (lhs, "^", rhs),
+        Expr::BitOr(lhs, rhs) => (lhs, "|", rhs),
+        Expr::ShiftLeft(lhs, rhs) => (lhs, "<<", rhs),
+        Expr::ShiftRight(lhs, rhs) => (lhs, ">>", rhs),
+        Expr::And(lhs, rhs) => (lhs, "&&", rhs),
+        Expr::Xor(lhs, rhs) => (lhs, "^^", rhs),
+        Expr::Or(lhs, rhs) => (lhs, "||", rhs),
+        Expr::Equal(lhs, rhs) => (lhs, "=", rhs),
+        Expr::PlusEqual(lhs, rhs) => (lhs, "+=", rhs),
+        Expr::MinusEqual(lhs, rhs) => (lhs, "-=", rhs),
+        Expr::StarEqual(lhs, rhs) => (lhs, "*=", rhs),
+        Expr::SlashEqual(lhs, rhs) => (lhs, "/=", rhs),
+        Expr::PercentEqual(lhs, rhs) => (lhs, "%=", rhs),
+    };
+    format!("{} {} {}", expr(lhs), op, expr(rhs))
+}
+
+fn prefix(op: &str, operand: &Expr) -> String {
+    let operand = expr(operand);
+    // `- -a` and `- --a` must not run together into other operators
+    if matches!(op, "-" | "+") && operand.starts_with(op) {
+        format!("{} {}", op, operand)
+    } else {
+        format!("{}{}", op, operand)
+    }
+}
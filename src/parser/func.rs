@@ -1,7 +1,7 @@
 use crate::parser::stmt::Stmt;
 use crate::parser::visitor::Visitor;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Func {
     pub args: Vec<String>,
     pub body: Vec<Stmt>,
@@ -1,10 +1,15 @@
+use crate::parser::Span;
 use crate::parser::func::Func;
 use crate::parser::visitor::Visitor;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FuncDef {
     pub name: String,
     pub func: Func,
+    /// Span of the function name
+    pub span: Span,
+    /// Span of the whole definition, from `function` to the closing `}`
+    pub extent: Span,
 }
 
 impl FuncDef {
@@ -0,0 +1,89 @@
+//! Syntax highlighting from the lexer alone, for editors that colorize a buffer
+//! as it is typed, long before it parses.
+//!
+//! Every token gets the category of its kind, except that an identifier right
+//! after `function` or right before `(` is a function name. Line breaks get no
+//! span, and comments are found in the gaps between tokens as
+//! [`trivia`](crate::parser::trivia) finds them.
+
+use crate::parser::trivia;
+use crate::token::{Token, TokenCategory};
+use logos::Logos;
+
+/// How an editor should color a [`HighlightSpan`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightCategory {
+    Keyword,
+    Operator,
+    Number,
+    /// A string literal, quotes included
+    String,
+    Comment,
+    Identifier,
+    /// A function being called or defined
+    FunctionName,
+    Punctuation,
+    /// Input the lexer rejects, such as an unknown character or an unterminated
+    /// string
+    Error,
+}
+
+/// A piece of source and how to color it; `start` and `end` are byte offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighlightSpan {
+    pub start: usize,
+    pub end: usize,
+    pub category: HighlightCategory,
+}
+
+/// Highlight `source`, in source order. Whitespace and line breaks are not
+/// covered by any span.
+pub fn highlight(source: &str) -> Vec<HighlightSpan> {
+    let mut comments = trivia::comments(source).into_iter().peekable();
+    let mut spans = Vec::new();
+    let mut after_function = false;
+    let mut tokens = Token::lexer(source).spanned().peekable();
+    while let Some((result, span)) = tokens.next() {
+        let category = match &result {
+            Err(_) => HighlightCategory::Error,
+            Ok(Token::Identifier(_)) => {
+                let called = matches!(tokens.peek(), Some((Ok(Token::LeftParen), _)));
+                if after_function || called {
+                    HighlightCategory::FunctionName
+                } else {
+                    HighlightCategory::Identifier
+                }
+            }
+            Ok(token) => match token.category() {
+                TokenCategory::Newline => continue,
+                TokenCategory::Keyword => HighlightCategory::Keyword,
+                TokenCategory::Operator => HighlightCategory::Operator,
+                TokenCategory::Punctuation => HighlightCategory::Punctuation,
+                TokenCategory::Identifier => HighlightCategory::Identifier,
+                TokenCategory::String => HighlightCategory::String,
+                TokenCategory::Number => HighlightCategory::Number,
+                TokenCategory::Error => HighlightCategory::Error,
+            },
+        };
+        after_function = matches!(result, Ok(Token::Function));
+
+        while let Some(comment) = comments.next_if(|comment| comment.span.start < span.start) {
+            spans.push(HighlightSpan {
+                start: comment.span.start,
+                end: comment.span.end,
+                category: HighlightCategory::Comment,
+            });
+        }
+        spans.push(HighlightSpan {
+            start: span.start,
+            end: span.end,
+            category,
+        });
+    }
+    spans.extend(comments.map(|comment| HighlightSpan {
+        start: comment.span.start,
+        end: comment.span.end,
+        category: HighlightCategory::Comment,
+    }));
+    spans
+}
@@ -0,0 +1,449 @@
+//! Inlining of tiny script functions at the AST level.
+//!
+//! A call to a function like `function sq(x) { return x * x; }` is replaced by the
+//! function's return expression with the arguments substituted for the parameters,
+//! e.g. `sq(i)` becomes `(i * i)`. The definition itself is kept, so hosts can still
+//! call it by name.
+//!
+//! Only calls whose meaning cannot change are rewritten:
+//! - the function is defined at top level, no nested function shares its name, and
+//!   its body is a single `return` of an expression of at most `threshold` nodes;
+//! - that expression computes a number, reads no variable but its parameters and
+//!   has no side effects; it calls nothing either, so recursive functions never
+//!   qualify;
+//! - the call passes exactly one argument per parameter, and every argument is free
+//!   of side effects, so evaluating it once per use gives the same result.
+//!
+//! All parameters are replaced at once and the body refers to nothing else, so an
+//! argument can never be captured by a parameter or variable of the function.
+
+use crate::parser::expr::Expr;
+use crate::parser::func_def::FuncDef;
+use crate::parser::program::Program;
+use crate::parser::stmt::Stmt;
+use crate::parser::top_level::TopLevel;
+use std::collections::{HashMap, HashSet};
+
+/// A function whose calls can be replaced by its return expression
+struct Candidate {
+    params: Vec<String>,
+    body: Expr,
+}
+
+/// Replace calls to functions whose body is a single `return` of an expression of
+/// at most `threshold` nodes with that expression. Returns how many calls were
+/// replaced; a threshold of 0 replaces none.
+pub fn inline_small_functions(program: &mut Program, threshold: usize) -> usize {
+    if threshold == 0 {
+        return 0;
+    }
+
+    let mut nested = HashSet::new();
+    for top_level in &program.body {
+        match top_level {
+            TopLevel::Statement(stmt) => collect_nested_names(stmt, &mut nested),
+            TopLevel::Function(func_def) => {
+                for stmt in &func_def.func.body {
+                    collect_nested_names(stmt, &mut nested);
+                }
+            }
+            TopLevel::Enum(_) | TopLevel::Include(..) | TopLevel::Error(_) => {}
+        }
+    }
+
+    let candidates: HashMap<String, Candidate> = program
+        .functions()
+        .filter(|func_def| !nested.contains(&func_def.name))
+        .filter_map(|func_def| candidate(func_def, threshold))
+        .collect();
+    if candidates.is_empty() {
+        return 0;
+    }
+
+    let mut inliner = Inliner {
+        candidates: &candidates,
+        inlined: 0,
+    };
+    for top_level in &mut program.body {
+        match top_level {
+            TopLevel::Statement(stmt) => inliner.stmt(stmt),
+            TopLevel::Function(func_def) => inliner.func_def(func_def),
+            TopLevel::Enum(_) | TopLevel::Include(..) | TopLevel::Error(_) => {}
+        }
+    }
+    inliner.inlined
+}
+
+fn candidate(func_def: &FuncDef, threshold: usize) -> Option<(String, Candidate)> {
+    let [Stmt::Return(Some(body))] = func_def.func.body.as_slice() else {
+        return None;
+    };
+    let params = &func_def.func.args;
+    let qualifies = expr_size(body) <= threshold
+        && is_numeric(body)
+        && is_pure(body, &|name| params.iter().any(|param| param == name));
+    qualifies.then(|| {
+        (
+            func_def.name.clone(),
+            Candidate {
+                params: params.clone(),
+                body: body.clone(),
+            },
+        )
+    })
+}
+
+fn collect_nested_names(stmt: &Stmt, names: &mut HashSet<String>) {
+    match stmt {
+        Stmt::Function(func_def) => {
+            names.insert(func_def.name.clone());
+            for stmt in &func_def.func.body {
+                collect_nested_names(stmt, names);
+            }
+        }
+        Stmt::If(_, then_stmt, else_stmt, _) => {
+            collect_nested_names(then_stmt, names);
+            if let Some(else_stmt) = else_stmt {
+                collect_nested_names(else_stmt, names);
+            }
+        }
+        Stmt::Block(stmts, _) => {
+            for stmt in stmts {
+                collect_nested_names(stmt, names);
+            }
+        }
+        Stmt::Repeat(_, body, _) | Stmt::While(_, body, _) | Stmt::DoUntil(body, _, _) => {
+            collect_nested_names(body, names)
+        }
+        Stmt::For(init, _, update, body, _) => {
+            for stmt in init.iter().chain(update) {
+                collect_nested_names(stmt, names);
+            }
+            collect_nested_names(body, names);
+        }
+        Stmt::Switch(_, cases, _) => {
+            for stmt in cases.iter().flat_map(|case| &case.body) {
+                collect_nested_names(stmt, names);
+            }
+        }
+        Stmt::Expr(_)
+        | Stmt::Var(_)
+        | Stmt::Return(_)
+        | Stmt::Break
+        | Stmt::Continue
+        | Stmt::Error(_) => {}
+    }
+}
+
+/// The number of nodes in an expression
+fn expr_size(expr: &Expr) -> usize {
+    1 + children(expr).into_iter().map(expr_size).sum::<usize>()
+}
+
+/// Whether an expression certainly evaluates to a number, as a function's return
+/// value is converted to one
+fn is_numeric(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(..)
+        | Expr::Subtraction(..)
+        | Expr::Multiplication(..)
+        | Expr::Division(..)
+        | Expr::IntDivision(..)
+        | Expr::Percent(..)
+        | Expr::Negative(_)
+        | Expr::BitNot(_)
+        | Expr::BitAnd(..)
+        | Expr::BitXor(..)
+        | Expr::BitOr(..)
+        | Expr::ShiftLeft(..)
+        | Expr::ShiftRight(..) => true,
+        // `+` also joins strings, so its operands must be numbers
+        Expr::Addition(lhs, rhs) => is_numeric(lhs) || is_numeric(rhs),
+        Expr::Paren(inner) | Expr::Positive(inner) => is_numeric(inner),
+        Expr::Ternary(_, then_expr, else_expr) => is_numeric(then_expr) && is_numeric(else_expr),
+        _ => false,
+    }
+}
+
+/// Whether evaluating an expression has no effect besides its value, reading only
+/// the variables `readable` accepts
+fn is_pure(expr: &Expr, readable: &dyn Fn(&str) -> bool) -> bool {
+    match expr {
+        Expr::Identifier(name, _) => readable(name),
+        Expr::Number(..)
+        | Expr::Integer(..)
+        | Expr::True(..)
+        | Expr::False(..)
+        | Expr::Null(_)
+        | Expr::Undefined
+        | Expr::Member(..) => true,
+        // Strings are allocated, calls may do anything, reading a field of an
+        // unbound instance records an error, and each anonymous function is a
+        // function of its own, so copying one would give two
+        Expr::String(..)
+        | Expr::Function(..)
+        | Expr::Call(..)
+        | Expr::Accessor(..)
+        | Expr::SelfRef(_)
+        | Expr::OtherRef(_)
+        | Expr::Field(..) => false,
+        Expr::Equal(..)
+        | Expr::PlusEqual(..)
+        | Expr::MinusEqual(..)
+        | Expr::StarEqual(..)
+        | Expr::SlashEqual(..)
+        | Expr::PercentEqual(..)
+        | Expr::PreIncrement(_)
+        | Expr::PostIncrement(_)
+        | Expr::PreDecrement(_)
+        | Expr::PostDecrement(_) => false,
+        _ => children(expr)
+            .into_iter()
+            .all(|child| is_pure(child, readable)),
+    }
+}
+
+/// The direct subexpressions of an expression
+fn children(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Number(..)
+        | Expr::Integer(..)
+        | Expr::String(..)
+        | Expr::True(..)
+        | Expr::False(..)
+        | Expr::Null(_)
+        | Expr::Undefined
+        | Expr::Identifier(..)
+        | Expr::Member(..)
+        | Expr::SelfRef(_)
+        | Expr::OtherRef(_)
+        // The body of an anonymous function is statements, with names of its own
+        | Expr::Function(..) => vec![],
+        Expr::Call(_, args, _) => args.iter().collect(),
+        Expr::Field(object, _, _) => vec![&**object],
+        Expr::Accessor(_, target, indices, _) => {
+            std::iter::once(&**target).chain(indices).collect()
+        }
+        Expr::Not(operand)
+        | Expr::BitNot(operand)
+        | Expr::Positive(operand)
+        | Expr::Negative(operand)
+        | Expr::Paren(operand)
+        | Expr::PreIncrement(operand)
+        | Expr::PostIncrement(operand)
+        | Expr::PreDecrement(operand)
+        | Expr::PostDecrement(operand) => vec![&**operand],
+        Expr::Ternary(cond, then_expr, else_expr) => vec![&**cond, &**then_expr, &**else_expr],
+        Expr::Addition(lhs, rhs)
+        | Expr::Subtraction(lhs, rhs)
+        | Expr::Multiplication(lhs, rhs)
+        | Expr::Division(lhs, rhs)
+        | Expr::IntDivision(lhs, rhs)
+        | Expr::Percent(lhs, rhs)
+        | Expr::Greater(lhs, rhs)
+        | Expr::GreaterEqual(lhs, rhs)
+        | Expr::Less(lhs, rhs)
+        | Expr::LessEqual(lhs, rhs)
+        | Expr::EqualEqual(lhs, rhs)
+        | Expr::NotEqual(lhs, rhs)
+        | Expr::BitAnd(lhs, rhs)
+        | Expr::BitXor(lhs, rhs)
+        | Expr::BitOr(lhs, rhs)
+        | Expr::ShiftLeft(lhs, rhs)
+        | Expr::ShiftRight(lhs, rhs)
+        | Expr::And(lhs, rhs)
+        | Expr::Xor(lhs, rhs)
+        | Expr::Or(lhs, rhs)
+        | Expr::Equal(lhs, rhs)
+        | Expr::PlusEqual(lhs, rhs)
+        | Expr::MinusEqual(lhs, rhs)
+        | Expr::StarEqual(lhs, rhs)
+        | Expr::SlashEqual(lhs, rhs)
+        | Expr::PercentEqual(lhs, rhs) => vec![&**lhs, &**rhs],
+    }
+}
+
+/// The direct subexpressions of an expression, for rewriting
+pub(crate) fn children_mut(expr: &mut Expr) -> Vec<&mut Expr> {
+    match expr {
+        Expr::Number(..)
+        | Expr::Integer(..)
+        | Expr::String(..)
+        | Expr::True(..)
+        | Expr::False(..)
+        | Expr::Null(_)
+        | Expr::Undefined
+        | Expr::Identifier(..)
+        | Expr::Member(..)
+        | Expr::SelfRef(_)
+        | Expr::OtherRef(_)
+        | Expr::Function(..) => vec![],
+        Expr::Call(_, args, _) => args.iter_mut().collect(),
+        Expr::Field(object, _, _) => vec![&mut **object],
+        Expr::Accessor(_, target, indices, _) => std::iter::once(&mut **target)
+            .chain(indices.iter_mut())
+            .collect(),
+        Expr::Not(operand)
+        | Expr::BitNot(operand)
+        | Expr::Positive(operand)
+        | Expr::Negative(operand)
+        | Expr::Paren(operand)
+        | Expr::PreIncrement(operand)
+        | Expr::PostIncrement(operand)
+        | Expr::PreDecrement(operand)
+        | Expr::PostDecrement(operand) => vec![&mut **operand],
+        Expr::Ternary(cond, then_expr, else_expr) => {
+            vec![&mut **cond, &mut **then_expr, &mut **else_expr]
+        }
+        Expr::Addition(lhs, rhs)
+        | Expr::Subtraction(lhs, rhs)
+        | Expr::Multiplication(lhs, rhs)
+        | Expr::Division(lhs, rhs)
+        | Expr::IntDivision(lhs, rhs)
+        | Expr::Percent(lhs, rhs)
+        | Expr::Greater(lhs, rhs)
+        | Expr::GreaterEqual(lhs, rhs)
+        | Expr::Less(lhs, rhs)
+        | Expr::LessEqual(lhs, rhs)
+        | Expr::EqualEqual(lhs, rhs)
+        | Expr::NotEqual(lhs, rhs)
+        | Expr::BitAnd(lhs, rhs)
+        | Expr::BitXor(lhs, rhs)
+        | Expr::BitOr(lhs, rhs)
+        | Expr::ShiftLeft(lhs, rhs)
+        | Expr::ShiftRight(lhs, rhs)
+        | Expr::And(lhs, rhs)
+        | Expr::Xor(lhs, rhs)
+        | Expr::Or(lhs, rhs)
+        | Expr::Equal(lhs, rhs)
+        | Expr::PlusEqual(lhs, rhs)
+        | Expr::MinusEqual(lhs, rhs)
+        | Expr::StarEqual(lhs, rhs)
+        | Expr::SlashEqual(lhs, rhs)
+        | Expr::PercentEqual(lhs, rhs) => vec![&mut **lhs, &mut **rhs],
+    }
+}
+
+/// Copy of `body` with every parameter replaced by its argument
+fn substitute(body: &Expr, bindings: &HashMap<&str, &Expr>) -> Expr {
+    let mut expr = body.clone();
+    substitute_in_place(&mut expr, bindings);
+    expr
+}
+
+fn substitute_in_place(expr: &mut Expr, bindings: &HashMap<&str, &Expr>) {
+    match expr {
+        Expr::Identifier(name, _) => {
+            if let Some(&arg) = bindings.get(name.as_str()) {
+                *expr = arg.clone();
+            }
+        }
+        _ => {
+            for child in children_mut(expr) {
+                substitute_in_place(child, bindings);
+            }
+        }
+    }
+}
+
+struct Inliner<'a> {
+    candidates: &'a HashMap<String, Candidate>,
+    inlined: usize,
+}
+
+impl Inliner<'_> {
+    fn func_def(&mut self, func_def: &mut FuncDef) {
+        for stmt in &mut func_def.func.body {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Expr(expr) | Stmt::Return(Some(expr)) => self.expr(expr),
+            Stmt::Var(vars) => {
+                for (_, init, _) in vars {
+                    if let Some(init) = init {
+                        self.expr(init);
+                    }
+                }
+            }
+            Stmt::If(cond, then_stmt, else_stmt, _) => {
+                self.expr(cond);
+                self.stmt(then_stmt);
+                if let Some(else_stmt) = else_stmt {
+                    self.stmt(else_stmt);
+                }
+            }
+            Stmt::Block(stmts, _) => {
+                for stmt in stmts {
+                    self.stmt(stmt);
+                }
+            }
+            Stmt::Repeat(cond, body, _)
+            | Stmt::While(cond, body, _)
+            | Stmt::DoUntil(body, cond, _) => {
+                self.expr(cond);
+                self.stmt(body);
+            }
+            Stmt::For(init, cond, update, body, _) => {
+                if let Some(init) = init {
+                    self.stmt(init);
+                }
+                if let Some(cond) = cond {
+                    self.expr(cond);
+                }
+                if let Some(update) = update {
+                    self.stmt(update);
+                }
+                self.stmt(body);
+            }
+            Stmt::Switch(value, cases, _) => {
+                self.expr(value);
+                for case in cases {
+                    for label in &mut case.labels {
+                        self.expr(label);
+                    }
+                    for stmt in &mut case.body {
+                        self.stmt(stmt);
+                    }
+                }
+            }
+            Stmt::Function(func_def) => self.func_def(func_def),
+            Stmt::Return(None) | Stmt::Break | Stmt::Continue | Stmt::Error(_) => {}
+        }
+    }
+
+    /// Inline the calls in `expr`, innermost first so `sq(sq(x))` is inlined whole
+    fn expr(&mut self, expr: &mut Expr) {
+        for child in children_mut(expr) {
+            self.expr(child);
+        }
+
+        let inlined = {
+            let Expr::Call(name, args, _) = &*expr else {
+                return;
+            };
+            let Some(candidate) = self.candidates.get(name) else {
+                return;
+            };
+            if args.len() != candidate.params.len() || !args.iter().all(is_pure_argument) {
+                return;
+            }
+            let bindings = candidate
+                .params
+                .iter()
+                .map(String::as_str)
+                .zip(args)
+                .collect();
+            substitute(&candidate.body, &bindings)
+        };
+        *expr = Expr::Paren(Box::new(inlined));
+        self.inlined += 1;
+    }
+}
+
+/// Arguments may read any variable, as they are evaluated where the call was
+fn is_pure_argument(arg: &Expr) -> bool {
+    !matches!(arg, Expr::Undefined) && is_pure(arg, &|_| true)
+}
@@ -0,0 +1,78 @@
+//! Syntax and semantics a project opts into or out of. The defaults accept the
+//! language as it always was, so code written without options keeps parsing and
+//! running the same way.
+
+use crate::parser::compile_limits::CompileLimits;
+use std::path::PathBuf;
+
+/// How a program is parsed and compiled. Using syntax that is disabled is reported
+/// with a diagnostic naming the option that enables it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LanguageOptions {
+    /// Accept `switch` statements with `case` and `default` labels
+    pub allow_switch: bool,
+    /// Only accept identifiers of ASCII letters, digits and `_`, rather than any
+    /// Unicode letters
+    pub ascii_identifiers: bool,
+    /// Require a `;` after every statement that takes a terminator; the end of a
+    /// line no longer ends one
+    pub strict_semicolons: bool,
+    /// What `/`, `%` and `div` do with a zero divisor
+    pub div_by_zero: DivByZeroPolicy,
+    /// Stop the script with an error when a comparison has a NaN operand. By
+    /// default the comparison is false, as every ordered comparison with NaN is.
+    pub strict_math: bool,
+    /// Reject a function that returns a value on some paths but can reach its end
+    /// on others. By default it is only a warning, and reaching the end returns 0.
+    pub strict_returns: bool,
+    /// Keep integer literals that fit an `i64` exact, and compute with them as
+    /// integers until an operation needs a fraction; see
+    /// [`integers`](crate::codegen::ir_generator::integers). By default every
+    /// number is an `f64`, so integers above 2^53 lose precision.
+    pub exact_integers: bool,
+    /// Stop the script with an error when more calls of script functions than this
+    /// are open at once, e.g. in recursion without a base case, or when the thread's
+    /// stack is too low for another, instead of letting it overflow the native
+    /// stack. [`DEFAULT_MAX_CALL_DEPTH`] by default; `None` checks nothing.
+    pub max_call_depth: Option<usize>,
+    /// How big the program may be; checked right after parsing
+    pub limits: CompileLimits,
+    /// Directories `#include` looks in, in order, after the directory of the
+    /// including file. A [`Script`](crate::script::Script) compiled from a string
+    /// has no file, so its includes are only looked up here.
+    pub include_paths: Vec<PathBuf>,
+}
+
+impl Default for LanguageOptions {
+    fn default() -> Self {
+        Self {
+            allow_switch: false,
+            ascii_identifiers: false,
+            strict_semicolons: false,
+            div_by_zero: DivByZeroPolicy::default(),
+            strict_math: false,
+            strict_returns: false,
+            exact_integers: false,
+            max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH),
+            limits: CompileLimits::default(),
+            include_paths: vec![],
+        }
+    }
+}
+
+/// A call depth limit deep enough for realistic recursion. Threads whose stack
+/// cannot hold that many calls stop at the stack check first.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+/// What dividing by zero gives. Divisions by a nonzero constant are never checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DivByZeroPolicy {
+    /// `/` gives an infinity (NaN for `0 / 0`) and `%` NaN, as floats do, while
+    /// `div` stops the script with an error, as in GML
+    #[default]
+    Infinity,
+    /// All three stop the script with an error naming the operation and where it is
+    Error,
+    /// All three give 0
+    Zero,
+}
@@ -0,0 +1,171 @@
+//! The outline of a program: where its functions, loops, `if`s and blocks are, for
+//! editors to highlight and fold them without parsing GML themselves.
+
+use crate::parser::Span;
+use crate::parser::func_def::FuncDef;
+use crate::parser::program::Program;
+use crate::parser::stmt::Stmt;
+use crate::parser::top_level::TopLevel;
+use crate::utils::line_index::LineIndex;
+
+/// What kind of code an [`OutlineItem`] covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlineKind {
+    Function,
+    /// A `{ ... }` standing on its own, not the body of another item
+    Block,
+    /// A `repeat`, `while`, `do ... until` or `for` loop
+    Loop,
+    /// An `if`, together with its `else` branch
+    If,
+    /// A `switch` with all its labels
+    Switch,
+}
+
+/// One region of the outline. Lines and columns are 1-based, columns counted in
+/// characters; the end is just past the last character of the region.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineItem {
+    pub kind: OutlineKind,
+    /// The name of a function, `None` for other kinds
+    pub name: Option<String>,
+    /// Index of the innermost item containing this one
+    pub parent: Option<usize>,
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+/// The outline of `program`, parsed from `source`. Items are listed in source
+/// order, each before the items it contains. Code spliced in from included files
+/// is left out.
+pub fn outline(program: &Program, source: &str) -> Vec<OutlineItem> {
+    let mut builder = OutlineBuilder {
+        source,
+        lines: LineIndex::new(source),
+        items: Vec::new(),
+    };
+    for top_level in &program.body {
+        match top_level {
+            TopLevel::Statement(stmt) => builder.stmt(stmt, None),
+            TopLevel::Function(func_def) => builder.function(func_def, None),
+            TopLevel::Enum(_) | TopLevel::Include(..) | TopLevel::Error(_) => {}
+        }
+    }
+    builder.items
+}
+
+struct OutlineBuilder<'a> {
+    source: &'a str,
+    lines: LineIndex<'a>,
+    items: Vec<OutlineItem>,
+}
+
+impl OutlineBuilder<'_> {
+    fn function(&mut self, func_def: &FuncDef, parent: Option<usize>) {
+        let Some(index) = self.push(
+            OutlineKind::Function,
+            Some(func_def.name.clone()),
+            &func_def.extent,
+            parent,
+        ) else {
+            return;
+        };
+        for stmt in &func_def.func.body {
+            self.stmt(stmt, Some(index));
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, parent: Option<usize>) {
+        match stmt {
+            Stmt::Block(stmts, span) => {
+                let Some(index) = self.push(OutlineKind::Block, None, span, parent) else {
+                    return;
+                };
+                for stmt in stmts {
+                    self.stmt(stmt, Some(index));
+                }
+            }
+            Stmt::If(_, then_stmt, else_stmt, span) => {
+                let Some(index) = self.push(OutlineKind::If, None, span, parent) else {
+                    return;
+                };
+                self.body(then_stmt, index);
+                if let Some(else_stmt) = else_stmt {
+                    self.body(else_stmt, index);
+                }
+            }
+            Stmt::Repeat(_, body, span)
+            | Stmt::While(_, body, span)
+            | Stmt::DoUntil(body, _, span) => {
+                let Some(index) = self.push(OutlineKind::Loop, None, span, parent) else {
+                    return;
+                };
+                self.body(body, index);
+            }
+            Stmt::For(_, _, _, body, span) => {
+                let Some(index) = self.push(OutlineKind::Loop, None, span, parent) else {
+                    return;
+                };
+                self.body(body, index);
+            }
+            Stmt::Switch(_, cases, span) => {
+                let Some(index) = self.push(OutlineKind::Switch, None, span, parent) else {
+                    return;
+                };
+                for stmt in cases.iter().flat_map(|case| &case.body) {
+                    self.stmt(stmt, Some(index));
+                }
+            }
+            Stmt::Function(func_def) => self.function(func_def, parent),
+            Stmt::Expr(_)
+            | Stmt::Var(_)
+            | Stmt::Return(_)
+            | Stmt::Break
+            | Stmt::Continue
+            | Stmt::Error(_) => {}
+        }
+    }
+
+    /// The body of a loop or `if` branch, whose braces belong to its owner
+    fn body(&mut self, body: &Stmt, parent: usize) {
+        match body {
+            Stmt::Block(stmts, _) => {
+                for stmt in stmts {
+                    self.stmt(stmt, Some(parent));
+                }
+            }
+            _ => self.stmt(body, Some(parent)),
+        }
+    }
+
+    /// Add an item covering `span` and return its index, or `None` for code spliced
+    /// in from an included file, which has no lines in the source
+    fn push(
+        &mut self,
+        kind: OutlineKind,
+        name: Option<String>,
+        span: &Span,
+        parent: Option<usize>,
+    ) -> Option<usize> {
+        // Statements end with the terminator they consumed, which is not part of them
+        let text = self.source.get(span.clone())?;
+        let end = span.start
+            + text
+                .trim_end_matches(|c: char| c.is_whitespace() || c == ';')
+                .len();
+        let (start_line, start_col) = self.lines.line_col(span.start);
+        let (end_line, end_col) = self.lines.line_col(end);
+        self.items.push(OutlineItem {
+            kind,
+            name,
+            parent,
+            start_line,
+            start_col,
+            end_line,
+            end_col,
+        });
+        Some(self.items.len() - 1)
+    }
+}
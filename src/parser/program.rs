@@ -1,13 +1,145 @@
+use crate::parser::Span;
+use crate::parser::func_def::FuncDef;
+use crate::parser::stmt::Stmt;
 use crate::parser::top_level::TopLevel;
+use crate::parser::trivia::Comment;
 use crate::parser::visitor::Visitor;
+use crate::utils::diagnostic::Diagnostic;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub body: Vec<TopLevel>,
+    /// Comments in source order, only collected by
+    /// [`ParseHandler::parse_program_with_comments`](crate::handler::parse_handler::ParseHandler::parse_program_with_comments)
+    pub comments: Vec<Comment>,
+    /// The files `#include` spliced items from, in the order their spans start
+    pub included: Vec<Arc<IncludedSource>>,
+}
+
+/// A file whose items `#include` spliced into a program. Its spans are shifted by
+/// `start`, which is past the end of the including file and of every file included
+/// before it, so each span of the program points into exactly one file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncludedSource {
+    pub path: PathBuf,
+    pub content: String,
+    pub start: usize,
 }
 
 impl Program {
     pub fn accept<T>(&self, visitor: &mut dyn Visitor<T>) -> T {
         visitor.visit_program(self)
     }
+
+    /// Top-level function definitions in source order, i.e. the functions a host can call
+    /// by name. Nested definitions are not included since they are only visible locally.
+    pub fn functions(&self) -> impl Iterator<Item = &FuncDef> {
+        self.body.iter().filter_map(|top_level| match top_level {
+            TopLevel::Function(func_def) => Some(func_def),
+            TopLevel::Statement(_)
+            | TopLevel::Enum(_)
+            | TopLevel::Include(..)
+            | TopLevel::Error(_) => None,
+        })
+    }
+
+    /// Top-level statements in source order, the code `main` runs
+    pub fn statements(&self) -> impl Iterator<Item = &Stmt> + Clone {
+        self.body.iter().filter_map(|top_level| match top_level {
+            TopLevel::Statement(stmt) => Some(stmt),
+            TopLevel::Function(_)
+            | TopLevel::Enum(_)
+            | TopLevel::Include(..)
+            | TopLevel::Error(_) => None,
+        })
+    }
+
+    /// The included file `offset` is in, with the offset within that file; `None`
+    /// for an offset in the program's own source
+    pub fn included_at(&self, offset: usize) -> Option<(&Arc<IncludedSource>, usize)> {
+        let file = self
+            .included
+            .iter()
+            .rev()
+            .find(|file| file.start <= offset)?;
+        Some((file, offset - file.start))
+    }
+
+    /// `diagnostic` with its span moved into the included file it points into,
+    /// which becomes its [`file`](Diagnostic::file), and so with each of its labels
+    pub fn relocate(&self, mut diagnostic: Diagnostic) -> Diagnostic {
+        if let Some(span) = &mut diagnostic.span {
+            diagnostic.file = self.relocate_span(span).or(diagnostic.file.take());
+        }
+        for label in &mut diagnostic.labels {
+            label.file = self.relocate_span(&mut label.span).or(label.file.take());
+        }
+        diagnostic
+    }
+
+    /// Move `span` into the included file it points into, if it does, and return
+    /// that file
+    fn relocate_span(&self, span: &mut Span) -> Option<Arc<IncludedSource>> {
+        let (file, start) = self.included_at(span.start)?;
+        *span = start..span.end - file.start;
+        Some(file.clone())
+    }
+
+    /// Spans of the error nodes parser recovery left in place of skipped code, in
+    /// source order. Empty for a program that parsed cleanly.
+    pub fn error_spans(&self) -> Vec<Span> {
+        let mut spans = Vec::new();
+        for top_level in &self.body {
+            match top_level {
+                TopLevel::Statement(stmt) => collect_error_spans(stmt, &mut spans),
+                TopLevel::Function(func_def) => {
+                    for stmt in &func_def.func.body {
+                        collect_error_spans(stmt, &mut spans);
+                    }
+                }
+                TopLevel::Error(span) => spans.push(span.clone()),
+                TopLevel::Enum(_) | TopLevel::Include(..) => {}
+            }
+        }
+        spans
+    }
+}
+
+fn collect_error_spans(stmt: &Stmt, spans: &mut Vec<Span>) {
+    match stmt {
+        Stmt::Error(span) => spans.push(span.clone()),
+        Stmt::If(_, then_stmt, else_stmt, _) => {
+            collect_error_spans(then_stmt, spans);
+            if let Some(else_stmt) = else_stmt {
+                collect_error_spans(else_stmt, spans);
+            }
+        }
+        Stmt::Block(stmts, _) => {
+            for stmt in stmts {
+                collect_error_spans(stmt, spans);
+            }
+        }
+        Stmt::Repeat(_, body, _) | Stmt::While(_, body, _) | Stmt::DoUntil(body, _, _) => {
+            collect_error_spans(body, spans)
+        }
+        Stmt::For(init, _, update, body, _) => {
+            for stmt in init.iter().chain(update) {
+                collect_error_spans(stmt, spans);
+            }
+            collect_error_spans(body, spans);
+        }
+        Stmt::Switch(_, cases, _) => {
+            for stmt in cases.iter().flat_map(|case| &case.body) {
+                collect_error_spans(stmt, spans);
+            }
+        }
+        Stmt::Function(func_def) => {
+            for stmt in &func_def.func.body {
+                collect_error_spans(stmt, spans);
+            }
+        }
+        Stmt::Expr(_) | Stmt::Var(_) | Stmt::Return(_) | Stmt::Break | Stmt::Continue => {}
+    }
 }
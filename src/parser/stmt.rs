@@ -1,24 +1,48 @@
+use crate::parser::Span;
 use crate::parser::expr::Expr;
+use crate::parser::func_def::FuncDef;
 use crate::parser::visitor::Visitor;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Stmt {
     Expr(Expr),
-    Var(Vec<(String, Option<Expr>)>),
-    If(Box<Expr>, Box<Stmt>, Option<Box<Stmt>>),
-    Block(Vec<Stmt>),
+    /// Declarators as (name, initializer, name span)
+    Var(Vec<(String, Option<Expr>, Span)>),
+    // Compound statements end with the span of the whole statement, from its
+    // keyword or `{` to the end of its body
+    If(Box<Expr>, Box<Stmt>, Option<Box<Stmt>>, Span),
+    Block(Vec<Stmt>, Span),
     Return(Option<Expr>),
     Break,
     Continue,
-    Repeat(Box<Expr>, Box<Stmt>),
-    While(Box<Expr>, Box<Stmt>),
-    DoUntil(Box<Stmt>, Box<Expr>),
+    Repeat(Box<Expr>, Box<Stmt>, Span),
+    While(Box<Expr>, Box<Stmt>, Span),
+    DoUntil(Box<Stmt>, Box<Expr>, Span),
     For(
         Option<Box<Stmt>>,
         Option<Box<Expr>>,
         Option<Box<Stmt>>,
         Box<Stmt>,
+        Span,
     ),
+    /// The value switched on and the labels in source order. Control falls through
+    /// from one label's statements into the next, until a `break`.
+    Switch(Box<Expr>, Vec<SwitchCase>, Span),
+    Function(FuncDef),
+    /// Tokens skipped while recovering from a syntax error
+    Error(Span),
+}
+
+/// The labels of one arm of a `switch` and the statements up to the next arm.
+/// Labels stacked with nothing between them, as in `case 1: case 2:`, belong to the
+/// arm after them, just as those of `case 1, 2:` do.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SwitchCase {
+    /// The values to match, in source order; empty for `default` alone
+    pub labels: Vec<Expr>,
+    /// Whether `default` is one of the arm's labels
+    pub default: bool,
+    pub body: Vec<Stmt>,
 }
 
 impl Stmt {
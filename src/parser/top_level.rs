@@ -1,11 +1,18 @@
+use crate::parser::Span;
+use crate::parser::enum_def::EnumDef;
 use crate::parser::func_def::FuncDef;
 use crate::parser::stmt::Stmt;
 use crate::parser::visitor::Visitor;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TopLevel {
     Statement(Stmt),
     Function(FuncDef),
+    Enum(EnumDef),
+    /// `#include "path"`; replaced by the included file's items before codegen
+    Include(String, Span),
+    /// Tokens skipped while recovering from a syntax error, up to the next `function`
+    Error(Span),
 }
 
 impl TopLevel {
@@ -0,0 +1,72 @@
+//! Comments, which the lexer skips, recovered for tools that rewrite source and
+//! must keep them, such as the [`formatter`](crate::parser::formatter).
+//!
+//! The lexer skips only whitespace and comments, so every comment lies in a gap
+//! between two tokens. Lexing once more and reading the gaps finds them without a
+//! second grammar for strings, and leaves the normal lexer untouched.
+
+use crate::parser::Span;
+use crate::token::Token;
+use logos::Logos;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `// ...` up to the end of the line
+    Line,
+    /// `/* ... */`, possibly over several lines
+    Block,
+}
+
+/// A comment and where it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub span: Span,
+    pub kind: CommentKind,
+    /// The comment as written, delimiters included
+    pub text: String,
+    /// Whether code comes before it on its line, as in `x = 1; // note`
+    pub trailing: bool,
+}
+
+/// Every comment in `source`, in source order
+pub fn comments(source: &str) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut gap_start = 0;
+    for (_, span) in Token::lexer(source).spanned() {
+        collect_gap(source, gap_start..span.start, &mut comments);
+        gap_start = span.end;
+    }
+    collect_gap(source, gap_start..source.len(), &mut comments);
+    comments
+}
+
+/// The comments in `gap`, which holds nothing but whitespace and comments
+fn collect_gap(source: &str, gap: Span, comments: &mut Vec<Comment>) {
+    let mut offset = gap.start;
+    while offset < gap.end {
+        let rest = &source[offset..gap.end];
+        let (kind, len) = if rest.starts_with("//") {
+            let len = rest.find(is_line_end).unwrap_or(rest.len());
+            (CommentKind::Line, len)
+        } else if rest.starts_with("/*") {
+            let len = rest[2..].find("*/").map_or(rest.len(), |end| end + 4);
+            (CommentKind::Block, len)
+        } else {
+            offset += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+
+        let line_start = source[..offset].rfind(is_line_end).map_or(0, |i| i + 1);
+        comments.push(Comment {
+            span: offset..offset + len,
+            kind,
+            text: rest[..len].to_string(),
+            trailing: !source[line_start..offset].trim().is_empty(),
+        });
+        offset += len;
+    }
+}
+
+fn is_line_end(c: char) -> bool {
+    matches!(c, '\r' | '\n' | '\u{2028}' | '\u{2029}')
+}
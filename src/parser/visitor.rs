@@ -4,10 +4,15 @@ use crate::parser::func_def::FuncDef;
 use crate::parser::program::Program;
 use crate::parser::stmt::Stmt;
 use crate::parser::top_level::TopLevel;
+pub mod condition_linter;
 pub mod dead_code_detector;
+pub mod depth_checker;
 pub mod performance_warner;
+pub mod shadow_linter;
 pub mod symbol_table_builder;
 pub mod type_checker;
+pub mod type_infer;
+pub mod unused_linter;
 
 pub trait Visitor<T> {
     fn visit_program(&mut self, program: &Program) -> T;
@@ -16,4 +21,250 @@ pub trait Visitor<T> {
     fn visit_func(&mut self, func: &Func) -> T;
     fn visit_stmt(&mut self, stmt: &Stmt) -> T;
     fn visit_expr(&mut self, expr: &Expr) -> T;
+
+    // The walks visit every child of a node, in source order, and drop what the
+    // visits return. A visitor calls them for the nodes it has nothing to do with.
+
+    fn walk_program(&mut self, program: &Program)
+    where
+        Self: Sized,
+    {
+        for toplevel in &program.body {
+            toplevel.accept(self);
+        }
+    }
+
+    fn walk_toplevel(&mut self, toplevel: &TopLevel)
+    where
+        Self: Sized,
+    {
+        match toplevel {
+            TopLevel::Statement(stmt) => {
+                stmt.accept(self);
+            }
+            TopLevel::Function(func_def) => {
+                func_def.accept(self);
+            }
+            TopLevel::Enum(enum_def) => {
+                for value in enum_def.members.iter().filter_map(|m| m.value.as_ref()) {
+                    value.accept(self);
+                }
+            }
+            TopLevel::Include(..) | TopLevel::Error(_) => {}
+        }
+    }
+
+    fn walk_func_def(&mut self, func_def: &FuncDef)
+    where
+        Self: Sized,
+    {
+        func_def.func.accept(self);
+    }
+
+    fn walk_func(&mut self, func: &Func)
+    where
+        Self: Sized,
+    {
+        for stmt in &func.body {
+            stmt.accept(self);
+        }
+    }
+
+    fn walk_stmt(&mut self, stmt: &Stmt)
+    where
+        Self: Sized,
+    {
+        match stmt {
+            Stmt::Expr(expr) | Stmt::Return(Some(expr)) => {
+                expr.accept(self);
+            }
+            Stmt::Var(vars) => {
+                for expr in vars.iter().filter_map(|(_, expr, _)| expr.as_ref()) {
+                    expr.accept(self);
+                }
+            }
+            Stmt::If(cond, then_stmt, else_stmt, _) => {
+                cond.accept(self);
+                then_stmt.accept(self);
+                if let Some(else_stmt) = else_stmt {
+                    else_stmt.accept(self);
+                }
+            }
+            Stmt::Block(stmts, _) => {
+                for stmt in stmts {
+                    stmt.accept(self);
+                }
+            }
+            Stmt::Repeat(cond, body, _) | Stmt::While(cond, body, _) => {
+                cond.accept(self);
+                body.accept(self);
+            }
+            Stmt::DoUntil(body, cond, _) => {
+                body.accept(self);
+                cond.accept(self);
+            }
+            Stmt::For(init, cond, update, body, _) => {
+                if let Some(init) = init {
+                    init.accept(self);
+                }
+                if let Some(cond) = cond {
+                    cond.accept(self);
+                }
+                if let Some(update) = update {
+                    update.accept(self);
+                }
+                body.accept(self);
+            }
+            Stmt::Switch(value, cases, _) => {
+                value.accept(self);
+                for case in cases {
+                    for label in &case.labels {
+                        label.accept(self);
+                    }
+                    for stmt in &case.body {
+                        stmt.accept(self);
+                    }
+                }
+            }
+            Stmt::Function(func_def) => {
+                func_def.accept(self);
+            }
+            Stmt::Return(None) | Stmt::Break | Stmt::Continue | Stmt::Error(_) => {}
+        }
+    }
+
+    fn walk_expr(&mut self, expr: &Expr)
+    where
+        Self: Sized,
+    {
+        match expr {
+            Expr::Call(_, args, _) => {
+                for arg in args {
+                    arg.accept(self);
+                }
+            }
+            Expr::Addition(l, r)
+            | Expr::Subtraction(l, r)
+            | Expr::Multiplication(l, r)
+            | Expr::Division(l, r)
+            | Expr::IntDivision(l, r)
+            | Expr::Percent(l, r)
+            | Expr::Greater(l, r)
+            | Expr::GreaterEqual(l, r)
+            | Expr::Less(l, r)
+            | Expr::LessEqual(l, r)
+            | Expr::EqualEqual(l, r)
+            | Expr::NotEqual(l, r)
+            | Expr::BitAnd(l, r)
+            | Expr::BitXor(l, r)
+            | Expr::BitOr(l, r)
+            | Expr::ShiftLeft(l, r)
+            | Expr::ShiftRight(l, r)
+            | Expr::And(l, r)
+            | Expr::Xor(l, r)
+            | Expr::Or(l, r)
+            | Expr::Equal(l, r)
+            | Expr::PlusEqual(l, r)
+            | Expr::MinusEqual(l, r)
+            | Expr::StarEqual(l, r)
+            | Expr::SlashEqual(l, r)
+            | Expr::PercentEqual(l, r) => {
+                l.accept(self);
+                r.accept(self);
+            }
+            Expr::Not(e)
+            | Expr::BitNot(e)
+            | Expr::Positive(e)
+            | Expr::Negative(e)
+            | Expr::Paren(e)
+            | Expr::PreIncrement(e)
+            | Expr::PostIncrement(e)
+            | Expr::PreDecrement(e)
+            | Expr::PostDecrement(e)
+            | Expr::Field(e, _, _) => {
+                e.accept(self);
+            }
+            Expr::Accessor(_, target, indices, _) => {
+                target.accept(self);
+                for index in indices {
+                    index.accept(self);
+                }
+            }
+            Expr::Ternary(cond, then_expr, else_expr) => {
+                cond.accept(self);
+                then_expr.accept(self);
+                else_expr.accept(self);
+            }
+            Expr::Function(func, _) => {
+                func.accept(self);
+            }
+            Expr::Number(..)
+            | Expr::Integer(..)
+            | Expr::String(..)
+            | Expr::True(..)
+            | Expr::False(..)
+            | Expr::Null(_)
+            | Expr::Undefined
+            | Expr::Identifier(..)
+            | Expr::Member(..)
+            | Expr::SelfRef(_)
+            | Expr::OtherRef(_) => {}
+        }
+    }
+}
+
+/// A pass that only looks at some kinds of node, such as a lint. Every method walks
+/// its node by default, so an implementation overrides the ones it cares about and
+/// calls the matching `walk_` method to keep going below them. Every `Pass` is a
+/// `Visitor<()>`, so it is run with `accept` like any other visitor.
+pub trait Pass: Sized {
+    fn visit_program(&mut self, program: &Program) {
+        Visitor::<()>::walk_program(self, program);
+    }
+
+    fn visit_toplevel(&mut self, toplevel: &TopLevel) {
+        Visitor::<()>::walk_toplevel(self, toplevel);
+    }
+
+    fn visit_func_def(&mut self, func_def: &FuncDef) {
+        Visitor::<()>::walk_func_def(self, func_def);
+    }
+
+    fn visit_func(&mut self, func: &Func) {
+        Visitor::<()>::walk_func(self, func);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        Visitor::<()>::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        Visitor::<()>::walk_expr(self, expr);
+    }
+}
+
+impl<P: Pass> Visitor<()> for P {
+    fn visit_program(&mut self, program: &Program) {
+        Pass::visit_program(self, program);
+    }
+
+    fn visit_toplevel(&mut self, toplevel: &TopLevel) {
+        Pass::visit_toplevel(self, toplevel);
+    }
+
+    fn visit_func_def(&mut self, func_def: &FuncDef) {
+        Pass::visit_func_def(self, func_def);
+    }
+
+    fn visit_func(&mut self, func: &Func) {
+        Pass::visit_func(self, func);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        Pass::visit_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        Pass::visit_expr(self, expr);
+    }
 }
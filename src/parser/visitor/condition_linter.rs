@@ -0,0 +1,122 @@
+use crate::parser::expr::*;
+use crate::parser::func::Func;
+use crate::parser::func_def::FuncDef;
+use crate::parser::program::Program;
+use crate::parser::stmt::Stmt;
+use crate::parser::top_level::TopLevel;
+use crate::parser::visitor::Visitor;
+use crate::utils::diagnostic::{Diagnostic, Severity};
+
+/// Diagnostic code for an assignment used as a condition
+pub const ASSIGNMENT_IN_CONDITION: u32 = 401;
+
+/// Flags `if (x = 5)` and the same in loop conditions, the usual typo for `==`.
+/// GML allows it, so this only warns. Wrapping the assignment in a second pair of
+/// parentheses, `if ((x = next()))`, marks it as intended.
+#[derive(Default)]
+pub struct ConditionLinter {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ConditionLinter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consume the linter and return its warnings, in source order
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    fn check_condition(&mut self, cond: &Expr) {
+        // Parentheses the statement syntax requires are not part of the condition,
+        // so any `Paren` here is an extra pair
+        let Expr::Equal(target, _) = cond else {
+            return;
+        };
+        let span = match target.as_ref() {
+            Expr::Identifier(_, span) => Some(span.clone()),
+            _ => None,
+        };
+        self.diagnostics.push(Diagnostic::new(
+            ASSIGNMENT_IN_CONDITION,
+            Severity::Warning,
+            "assignment used as a condition; did you mean '=='? \
+             Wrap it in another pair of parentheses if the assignment is intended"
+                .to_string(),
+            span,
+        ));
+    }
+}
+
+impl Visitor<()> for ConditionLinter {
+    fn visit_program(&mut self, program: &Program) {
+        for toplevel in &program.body {
+            toplevel.accept(self);
+        }
+    }
+
+    fn visit_toplevel(&mut self, toplevel: &TopLevel) {
+        match toplevel {
+            TopLevel::Statement(stmt) => stmt.accept(self),
+            TopLevel::Function(func_def) => func_def.accept(self),
+            TopLevel::Enum(_) | TopLevel::Include(..) | TopLevel::Error(_) => {}
+        }
+    }
+
+    fn visit_func_def(&mut self, func_def: &FuncDef) {
+        func_def.func.accept(self);
+    }
+
+    fn visit_func(&mut self, func: &Func) {
+        for stmt in &func.body {
+            stmt.accept(self);
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::If(cond, then_stmt, else_stmt_opt, _) => {
+                self.check_condition(cond);
+                then_stmt.accept(self);
+                if let Some(else_stmt) = else_stmt_opt {
+                    else_stmt.accept(self);
+                }
+            }
+            Stmt::Block(stmts, _) => {
+                for stmt in stmts {
+                    stmt.accept(self);
+                }
+            }
+            Stmt::Repeat(_, body, _) => body.accept(self),
+            Stmt::While(cond, body, _) => {
+                self.check_condition(cond);
+                body.accept(self);
+            }
+            Stmt::DoUntil(body, cond, _) => {
+                body.accept(self);
+                self.check_condition(cond);
+            }
+            Stmt::For(_, cond_opt, _, body, _) => {
+                if let Some(cond) = cond_opt {
+                    self.check_condition(cond);
+                }
+                body.accept(self);
+            }
+            // The value switched on is compared, not tested for truth
+            Stmt::Switch(_, cases, _) => {
+                for stmt in cases.iter().flat_map(|case| &case.body) {
+                    stmt.accept(self);
+                }
+            }
+            Stmt::Function(func_def) => func_def.accept(self),
+            // Only an anonymous function puts statements, and so conditions, in these
+            Stmt::Expr(_) | Stmt::Var(_) | Stmt::Return(_) => self.walk_stmt(stmt),
+            Stmt::Break | Stmt::Continue | Stmt::Error(_) => {}
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.walk_expr(expr);
+    }
+}
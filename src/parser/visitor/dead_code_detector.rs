@@ -20,6 +20,12 @@ impl Visitor<()> for DeadCodeDetector {
         match toplevel {
             TopLevel::Statement(stmt) => stmt.accept(self),
             TopLevel::Function(func_def) => func_def.accept(self),
+            TopLevel::Enum(enum_def) => {
+                for value in enum_def.members.iter().filter_map(|m| m.value.as_ref()) {
+                    value.accept(self);
+                }
+            }
+            TopLevel::Include(..) | TopLevel::Error(_) => {}
         }
     }
 
@@ -37,20 +43,20 @@ impl Visitor<()> for DeadCodeDetector {
         match stmt {
             Stmt::Expr(expr) => expr.accept(self),
             Stmt::Var(vars) => {
-                for (_, expr_opt) in vars {
+                for (_, expr_opt, _) in vars {
                     if let Some(expr) = expr_opt {
                         expr.accept(self);
                     }
                 }
             }
-            Stmt::If(cond, then_stmt, else_stmt_opt) => {
+            Stmt::If(cond, then_stmt, else_stmt_opt, _) => {
                 cond.accept(self);
                 then_stmt.accept(self);
                 if let Some(else_stmt) = else_stmt_opt {
                     else_stmt.accept(self);
                 }
             }
-            Stmt::Block(stmts) => {
+            Stmt::Block(stmts, _) => {
                 for stmt in stmts {
                     stmt.accept(self);
                 }
@@ -62,19 +68,20 @@ impl Visitor<()> for DeadCodeDetector {
             }
             Stmt::Break => {}
             Stmt::Continue => {}
-            Stmt::Repeat(count, body) => {
+            Stmt::Error(_) => {}
+            Stmt::Repeat(count, body, _) => {
                 count.accept(self);
                 body.accept(self);
             }
-            Stmt::While(cond, body) => {
+            Stmt::While(cond, body, _) => {
                 cond.accept(self);
                 body.accept(self);
             }
-            Stmt::DoUntil(body, cond) => {
+            Stmt::DoUntil(body, cond, _) => {
                 body.accept(self);
                 cond.accept(self);
             }
-            Stmt::For(init, cond_opt, update_opt, body) => {
+            Stmt::For(init, cond_opt, update_opt, body, _) => {
                 if let Some(init_stmt) = init {
                     init_stmt.accept(self);
                 }
@@ -86,12 +93,24 @@ impl Visitor<()> for DeadCodeDetector {
                 }
                 body.accept(self);
             }
+            Stmt::Switch(value, cases, _) => {
+                value.accept(self);
+                for case in cases {
+                    for label in &case.labels {
+                        label.accept(self);
+                    }
+                    for stmt in &case.body {
+                        stmt.accept(self);
+                    }
+                }
+            }
+            Stmt::Function(func_def) => func_def.accept(self),
         }
     }
 
     fn visit_expr(&mut self, expr: &Expr) {
         match expr {
-            Expr::Call(_, args) => {
+            Expr::Call(_, args, _) => {
                 for arg in args {
                     arg.accept(self);
                 }
@@ -100,6 +119,7 @@ impl Visitor<()> for DeadCodeDetector {
             | Expr::Subtraction(l, r)
             | Expr::Multiplication(l, r)
             | Expr::Division(l, r)
+            | Expr::IntDivision(l, r)
             | Expr::Percent(l, r)
             | Expr::Greater(l, r)
             | Expr::GreaterEqual(l, r)
@@ -110,6 +130,8 @@ impl Visitor<()> for DeadCodeDetector {
             | Expr::BitAnd(l, r)
             | Expr::BitXor(l, r)
             | Expr::BitOr(l, r)
+            | Expr::ShiftLeft(l, r)
+            | Expr::ShiftRight(l, r)
             | Expr::And(l, r)
             | Expr::Xor(l, r)
             | Expr::Or(l, r)
@@ -131,17 +153,30 @@ impl Visitor<()> for DeadCodeDetector {
             | Expr::PostIncrement(e)
             | Expr::PreDecrement(e)
             | Expr::PostDecrement(e) => e.accept(self),
+            Expr::Accessor(_, target, indices, _) => {
+                target.accept(self);
+                for index in indices {
+                    index.accept(self);
+                }
+            }
             Expr::Ternary(cond, then_expr, else_expr) => {
                 cond.accept(self);
                 then_expr.accept(self);
                 else_expr.accept(self);
             }
-            Expr::Number(_)
-            | Expr::String(_)
-            | Expr::True(_)
-            | Expr::False(_)
-            | Expr::Null
-            | Expr::Identifier(_) => {}
+            Expr::Function(func, _) => func.accept(self),
+            Expr::Number(..)
+            | Expr::Integer(..)
+            | Expr::String(..)
+            | Expr::True(..)
+            | Expr::False(..)
+            | Expr::Null(_)
+            | Expr::Undefined
+            | Expr::Member(..)
+            | Expr::SelfRef(_)
+            | Expr::OtherRef(_)
+            | Expr::Field(..)
+            | Expr::Identifier(..) => {}
         }
     }
 }
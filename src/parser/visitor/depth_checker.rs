@@ -0,0 +1,314 @@
+use crate::parser::Span;
+use crate::parser::expr::*;
+use crate::parser::func::Func;
+use crate::parser::func_def::FuncDef;
+use crate::parser::program::Program;
+use crate::parser::stmt::Stmt;
+use crate::parser::top_level::TopLevel;
+use crate::parser::visitor::Visitor;
+
+/// Default limit on how deeply statements and expressions may nest
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 512;
+
+/// The error for nesting past `limit`, whether the parser or the checker finds it
+pub(crate) fn too_deeply_nested(limit: usize) -> String {
+    format!("expression too deeply nested (limit {})", limit)
+}
+
+/// Checks that no statement or expression nests deeper than `max_depth`, so the
+/// recursive passes after parsing cannot overflow the stack.
+/// It stops descending as soon as the limit is crossed, so it never recurses deeper itself.
+///
+/// The parser already stops at the limit where it recurses; what is left for the
+/// checker are long chains of left-associative operators, as in `1 + 1 + ... + 1`,
+/// which parse in a loop but nest in the AST.
+pub struct DepthChecker {
+    max_depth: usize,
+    depth: usize,
+    exceeded: Option<Span>,
+}
+
+impl DepthChecker {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            depth: 0,
+            exceeded: None,
+        }
+    }
+
+    pub fn exceeded(&self) -> bool {
+        self.exceeded.is_some()
+    }
+
+    /// Where the limit was crossed: the first operand found below the node that
+    /// crossed it, or the start of the source if it has none
+    pub fn exceeded_at(&self) -> Option<&Span> {
+        self.exceeded.as_ref()
+    }
+
+    /// Run `f` one level deeper, unless that would cross the limit at `location`
+    fn nested(&mut self, location: impl FnOnce() -> Option<Span>, f: impl FnOnce(&mut Self)) {
+        if self.exceeded.is_some() {
+            return;
+        }
+        if self.depth >= self.max_depth {
+            self.exceeded = Some(location().unwrap_or(0..0));
+            return;
+        }
+        self.depth += 1;
+        f(self);
+        self.depth -= 1;
+    }
+}
+
+impl Visitor<()> for DepthChecker {
+    fn visit_program(&mut self, program: &Program) {
+        for toplevel in &program.body {
+            toplevel.accept(self);
+        }
+    }
+
+    fn visit_toplevel(&mut self, toplevel: &TopLevel) {
+        match toplevel {
+            TopLevel::Statement(stmt) => stmt.accept(self),
+            TopLevel::Function(func_def) => func_def.accept(self),
+            TopLevel::Enum(enum_def) => {
+                for value in enum_def.members.iter().filter_map(|m| m.value.as_ref()) {
+                    value.accept(self);
+                }
+            }
+            TopLevel::Include(..) | TopLevel::Error(_) => {}
+        }
+    }
+
+    fn visit_func_def(&mut self, func_def: &FuncDef) {
+        func_def.func.accept(self);
+    }
+
+    fn visit_func(&mut self, func: &Func) {
+        for stmt in &func.body {
+            stmt.accept(self);
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        self.nested(
+            || stmt_location(stmt),
+            |checker| match stmt {
+                Stmt::Expr(expr) => expr.accept(checker),
+                Stmt::Var(vars) => {
+                    for (_, expr_opt, _) in vars {
+                        if let Some(expr) = expr_opt {
+                            expr.accept(checker);
+                        }
+                    }
+                }
+                Stmt::If(cond, then_stmt, else_stmt_opt, _) => {
+                    cond.accept(checker);
+                    then_stmt.accept(checker);
+                    if let Some(else_stmt) = else_stmt_opt {
+                        else_stmt.accept(checker);
+                    }
+                }
+                Stmt::Block(stmts, _) => {
+                    for stmt in stmts {
+                        stmt.accept(checker);
+                    }
+                }
+                Stmt::Return(expr_opt) => {
+                    if let Some(expr) = expr_opt {
+                        expr.accept(checker);
+                    }
+                }
+                Stmt::Break | Stmt::Continue | Stmt::Error(_) => {}
+                Stmt::Repeat(expr, body, _)
+                | Stmt::While(expr, body, _)
+                | Stmt::DoUntil(body, expr, _) => {
+                    expr.accept(checker);
+                    body.accept(checker);
+                }
+                Stmt::For(init, cond_opt, update_opt, body, _) => {
+                    if let Some(init_stmt) = init {
+                        init_stmt.accept(checker);
+                    }
+                    if let Some(cond_expr) = cond_opt {
+                        cond_expr.accept(checker);
+                    }
+                    if let Some(update_stmt) = update_opt {
+                        update_stmt.accept(checker);
+                    }
+                    body.accept(checker);
+                }
+                Stmt::Switch(value, cases, _) => {
+                    value.accept(checker);
+                    for case in cases {
+                        for label in &case.labels {
+                            label.accept(checker);
+                        }
+                        for stmt in &case.body {
+                            stmt.accept(checker);
+                        }
+                    }
+                }
+                Stmt::Function(func_def) => func_def.accept(checker),
+            },
+        );
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.nested(
+            || expr_location(expr),
+            |checker| match expr {
+                Expr::Call(_, args, _) => {
+                    for arg in args {
+                        arg.accept(checker);
+                    }
+                }
+                Expr::Addition(l, r)
+                | Expr::Subtraction(l, r)
+                | Expr::Multiplication(l, r)
+                | Expr::Division(l, r)
+                | Expr::IntDivision(l, r)
+                | Expr::Percent(l, r)
+                | Expr::Greater(l, r)
+                | Expr::GreaterEqual(l, r)
+                | Expr::Less(l, r)
+                | Expr::LessEqual(l, r)
+                | Expr::EqualEqual(l, r)
+                | Expr::NotEqual(l, r)
+                | Expr::BitAnd(l, r)
+                | Expr::BitXor(l, r)
+                | Expr::BitOr(l, r)
+                | Expr::ShiftLeft(l, r)
+                | Expr::ShiftRight(l, r)
+                | Expr::And(l, r)
+                | Expr::Xor(l, r)
+                | Expr::Or(l, r)
+                | Expr::Equal(l, r)
+                | Expr::PlusEqual(l, r)
+                | Expr::MinusEqual(l, r)
+                | Expr::StarEqual(l, r)
+                | Expr::SlashEqual(l, r)
+                | Expr::PercentEqual(l, r) => {
+                    l.accept(checker);
+                    r.accept(checker);
+                }
+                Expr::Not(e)
+                | Expr::BitNot(e)
+                | Expr::Positive(e)
+                | Expr::Negative(e)
+                | Expr::Paren(e)
+                | Expr::PreIncrement(e)
+                | Expr::PostIncrement(e)
+                | Expr::PreDecrement(e)
+                | Expr::PostDecrement(e) => e.accept(checker),
+                Expr::Accessor(_, target, indices, _) => {
+                    target.accept(checker);
+                    for index in indices {
+                        index.accept(checker);
+                    }
+                }
+                Expr::Ternary(cond, then_expr, else_expr) => {
+                    cond.accept(checker);
+                    then_expr.accept(checker);
+                    else_expr.accept(checker);
+                }
+                Expr::Function(func, _) => func.accept(checker),
+                Expr::Number(..)
+                | Expr::Integer(..)
+                | Expr::String(..)
+                | Expr::True(..)
+                | Expr::False(..)
+                | Expr::Null(_)
+                | Expr::Undefined
+                | Expr::Member(..)
+                | Expr::SelfRef(_)
+                | Expr::OtherRef(_)
+                | Expr::Field(..)
+                | Expr::Identifier(..) => {}
+            },
+        );
+    }
+}
+
+/// Where a statement starts, without descending into its operands any further than
+/// [`expr_location`] does
+fn stmt_location(stmt: &Stmt) -> Option<Span> {
+    match stmt {
+        Stmt::If(.., span)
+        | Stmt::Block(_, span)
+        | Stmt::Repeat(.., span)
+        | Stmt::While(.., span)
+        | Stmt::DoUntil(.., span)
+        | Stmt::For(.., span)
+        | Stmt::Switch(.., span)
+        | Stmt::Error(span) => Some(span.clone()),
+        Stmt::Var(vars) => vars.first().map(|(_, _, span)| span.clone()),
+        Stmt::Function(func_def) => Some(func_def.span.clone()),
+        Stmt::Expr(expr) | Stmt::Return(Some(expr)) => expr_location(expr),
+        Stmt::Return(None) | Stmt::Break | Stmt::Continue => None,
+    }
+}
+
+/// The first node with a position found going down the right operands of `expr`,
+/// in a loop, as the node may be far too deep to recurse over. In a chain of
+/// left-associative operators that is the operand after the operator that crossed
+/// the limit.
+fn expr_location(mut expr: &Expr) -> Option<Span> {
+    loop {
+        expr = match expr {
+            Expr::Number(_, span)
+            | Expr::Integer(_, span)
+            | Expr::String(_, span)
+            | Expr::True(_, span)
+            | Expr::False(_, span)
+            | Expr::Null(span)
+            | Expr::Identifier(_, span)
+            | Expr::Call(_, _, span)
+            | Expr::Member(_, _, span)
+            | Expr::SelfRef(span)
+            | Expr::OtherRef(span)
+            | Expr::Function(_, span)
+            | Expr::Field(_, _, span)
+            | Expr::Accessor(_, _, _, span) => return Some(span.clone()),
+            Expr::Undefined => return None,
+            Expr::Not(operand)
+            | Expr::BitNot(operand)
+            | Expr::Positive(operand)
+            | Expr::Negative(operand)
+            | Expr::Paren(operand)
+            | Expr::PreIncrement(operand)
+            | Expr::PostIncrement(operand)
+            | Expr::PreDecrement(operand)
+            | Expr::PostDecrement(operand) => &**operand,
+            Expr::Addition(_, rhs)
+            | Expr::Subtraction(_, rhs)
+            | Expr::Multiplication(_, rhs)
+            | Expr::Division(_, rhs)
+            | Expr::IntDivision(_, rhs)
+            | Expr::Percent(_, rhs)
+            | Expr::Greater(_, rhs)
+            | Expr::GreaterEqual(_, rhs)
+            | Expr::Less(_, rhs)
+            | Expr::LessEqual(_, rhs)
+            | Expr::EqualEqual(_, rhs)
+            | Expr::NotEqual(_, rhs)
+            | Expr::BitAnd(_, rhs)
+            | Expr::BitXor(_, rhs)
+            | Expr::BitOr(_, rhs)
+            | Expr::ShiftLeft(_, rhs)
+            | Expr::ShiftRight(_, rhs)
+            | Expr::And(_, rhs)
+            | Expr::Xor(_, rhs)
+            | Expr::Or(_, rhs)
+            | Expr::Ternary(_, _, rhs)
+            | Expr::Equal(_, rhs)
+            | Expr::PlusEqual(_, rhs)
+            | Expr::MinusEqual(_, rhs)
+            | Expr::StarEqual(_, rhs)
+            | Expr::SlashEqual(_, rhs)
+            | Expr::PercentEqual(_, rhs) => &**rhs,
+        };
+    }
+}
@@ -0,0 +1,112 @@
+use crate::parser::Span;
+use crate::parser::func_def::FuncDef;
+use crate::parser::program::Program;
+use crate::parser::visitor::symbol_table_builder::{Scope, Symbol, SymbolTableBuilder};
+use crate::parser::visitor::{Pass, Visitor};
+use crate::utils::diagnostic::{Diagnostic, Severity};
+use std::collections::{HashMap, HashSet};
+
+/// Diagnostic code for a variable declaration hiding another variable or a parameter
+pub const SHADOWED_VARIABLE: u32 = 402;
+/// Diagnostic code for a parameter with the name of a top-level variable
+pub const PARAMETER_SHADOWS_GLOBAL: u32 = 403;
+
+/// Flags `var x = 10; if (c) { var x = 20; x += 5; }`, where the inner `var` makes a
+/// second `x` and the outer one never changes. Reports each declaration hiding a
+/// variable or parameter of the same function, found by the symbol table builder,
+/// and each parameter named like a top-level variable, which it hides from the whole
+/// function. A local of one function with the name of another's is not reported.
+/// Shadowing is legal, so this only warns, and only when asked to.
+pub struct ShadowLinter {
+    /// Top-level variables, and where they are declared
+    globals: HashMap<String, Span>,
+    /// Every name declared anywhere, which a suggested new name must not be
+    names: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ShadowLinter {
+    /// Lint `program`, returning its warnings in source order
+    pub fn lint(program: &Program) -> Vec<Diagnostic> {
+        let mut root = Scope::new();
+        let mut builder = SymbolTableBuilder::new(&mut root);
+        program.accept(&mut builder);
+        let shadows = builder.shadows().to_vec();
+
+        let mut names = HashSet::new();
+        collect_names(&root, &mut names);
+        let globals = root
+            .table
+            .iter()
+            .filter(|(_, symbol)| matches!(symbol, Symbol::Variable))
+            .map(|(name, _)| (name.clone(), root.sites[name].clone()))
+            .collect();
+        let mut linter = ShadowLinter {
+            globals,
+            names,
+            diagnostics: vec![],
+        };
+        for shadow in shadows {
+            let message = format!(
+                "'{}' shadows a variable this scope can no longer use; rename it, e.g. \
+                 to '{}'",
+                shadow.name,
+                linter.suggest(&shadow.name)
+            );
+            linter.warn(SHADOWED_VARIABLE, message, shadow.inner, shadow.outer);
+        }
+        program.accept(&mut linter);
+
+        let mut diagnostics = linter.diagnostics;
+        diagnostics.sort_by_key(|d| d.span.as_ref().map_or(0, |span| span.start));
+        diagnostics
+    }
+
+    /// A name for a variable called `name` that nothing in the program declares
+    fn suggest(&self, name: &str) -> String {
+        (2..)
+            .map(|n| format!("{}{}", name, n))
+            .find(|candidate| !self.names.contains(candidate))
+            .unwrap()
+    }
+
+    /// Warn at `span` about hiding the variable declared at `shadowed`
+    fn warn(&mut self, code: u32, message: String, span: Span, shadowed: Span) {
+        self.diagnostics.push(
+            Diagnostic::new(code, Severity::Warning, message, Some(span))
+                .with_label(shadowed, "shadowed declaration"),
+        );
+    }
+}
+
+fn collect_names(scope: &Scope, names: &mut HashSet<String>) {
+    names.extend(scope.table.keys().cloned());
+    for child in &scope.children {
+        collect_names(child, names);
+    }
+}
+
+impl Pass for ShadowLinter {
+    fn visit_func_def(&mut self, func_def: &FuncDef) {
+        for param in &func_def.func.args {
+            let Some(global) = self.globals.get(param).cloned() else {
+                continue;
+            };
+            // Parameters have no span of their own
+            let message = format!(
+                "parameter '{}' of '{}' shadows a top-level variable the function can no \
+                 longer use; rename it, e.g. to '{}'",
+                param,
+                func_def.name,
+                self.suggest(param)
+            );
+            self.warn(
+                PARAMETER_SHADOWS_GLOBAL,
+                message,
+                func_def.span.clone(),
+                global,
+            );
+        }
+        Visitor::<()>::walk_func_def(self, func_def);
+    }
+}
@@ -1,61 +1,496 @@
+use crate::parser::Span;
 use crate::parser::expr::*;
 use crate::parser::func::Func;
 use crate::parser::func_def::FuncDef;
 use crate::parser::program::Program;
 use crate::parser::stmt::Stmt;
 use crate::parser::top_level::TopLevel;
-use crate::parser::visitor::Visitor;
-use std::collections::HashMap;
+use crate::parser::visitor::{Pass, Visitor};
+use crate::utils::diagnostic::{Diagnostic, Severity};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub enum Symbol {
     Variable,
-    Function { parameters: Vec<String> },
+    Function {
+        parameters: Vec<String>,
+    },
+    /// An enum member, keyed as `Enum.Member`
+    EnumMember,
 }
 
 pub type SymbolTable = HashMap<String, Symbol>;
 
-#[derive(Debug)]
+/// The construct a [`Scope`] belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScopeKind {
+    /// The top-level code of a program
+    #[default]
+    Global,
+    Function,
+    /// A block that is a statement of its own
+    Block,
+    /// The block an `if` runs when its condition holds
+    IfThen,
+    /// The block after `else`
+    IfElse,
+    /// A `for` loop, holding its init, condition and update
+    ForHeader,
+    /// The block a `for` loop runs
+    ForBody,
+    /// A `while` loop, holding its condition
+    WhileHeader,
+    WhileBody,
+    /// A `repeat` loop, holding its count
+    RepeatHeader,
+    RepeatBody,
+    /// A `do`-`until` loop, holding its condition
+    DoHeader,
+    DoBody,
+    /// The cases of a `switch`
+    Switch,
+}
+
+#[derive(Debug, Default)]
 pub struct Scope {
+    pub kind: ScopeKind,
+    /// The construct the scope belongs to: the function definition, the block, or
+    /// the whole loop or `switch` statement. Empty for the global scope.
+    pub span: Span,
     pub table: SymbolTable,
+    /// Where each symbol in `table` was first declared
+    pub sites: HashMap<String, Span>,
+    /// Scopes opened directly inside this one, in source order
     pub children: Vec<Scope>,
 }
 
 impl Scope {
     pub fn new() -> Self {
+        Self::with_kind(ScopeKind::Global, Span::default())
+    }
+
+    fn with_kind(kind: ScopeKind, span: Span) -> Self {
         Self {
+            kind,
+            span,
             table: SymbolTable::new(),
+            sites: HashMap::new(),
             children: vec![],
         }
     }
+
+    /// The first scope of `kind` declaring `name`, looking at this scope and then at
+    /// its descendants in source order
+    pub fn find(&self, kind: ScopeKind, name: &str) -> Option<&Scope> {
+        if self.kind == kind && self.table.contains_key(name) {
+            return Some(self);
+        }
+        self.children
+            .iter()
+            .find_map(|child| child.find(kind, name))
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolDiagnosticKind {
+    /// A variable declared twice in the same scope
+    DuplicateVariable,
+    /// A function defined twice in the same scope
+    DuplicateFunction,
+    /// A variable and a function sharing a name in the same scope
+    ConflictingDeclaration,
+    /// A parameter name repeated in one function signature
+    DuplicateParameter,
+    /// A declaration hiding one from an enclosing scope; allowed, but reported
+    Shadowing,
+    /// A variable used before any declaration of it in the enclosing function
+    UndeclaredVariable,
+    /// A member declared twice in one enum
+    DuplicateEnumMember,
+    /// `Enum.Member` naming no declared enum member
+    UnknownEnumMember,
+}
+
+impl SymbolDiagnosticKind {
+    /// Stable diagnostic code for this kind of problem
+    pub fn code(self) -> u32 {
+        match self {
+            SymbolDiagnosticKind::DuplicateVariable => 201,
+            SymbolDiagnosticKind::DuplicateFunction => 202,
+            SymbolDiagnosticKind::ConflictingDeclaration => 203,
+            SymbolDiagnosticKind::DuplicateParameter => 204,
+            SymbolDiagnosticKind::Shadowing => 205,
+            SymbolDiagnosticKind::UndeclaredVariable => 206,
+            SymbolDiagnosticKind::DuplicateEnumMember => 207,
+            SymbolDiagnosticKind::UnknownEnumMember => 208,
+        }
+    }
+
+    pub fn severity(self) -> Severity {
+        match self {
+            SymbolDiagnosticKind::UndeclaredVariable
+            | SymbolDiagnosticKind::DuplicateEnumMember
+            | SymbolDiagnosticKind::UnknownEnumMember => Severity::Error,
+            SymbolDiagnosticKind::Shadowing => Severity::Note,
+            _ => Severity::Warning,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            SymbolDiagnosticKind::DuplicateVariable => "variable declared twice",
+            SymbolDiagnosticKind::DuplicateFunction => "function defined twice",
+            SymbolDiagnosticKind::ConflictingDeclaration => {
+                "name declared as both a variable and a function"
+            }
+            SymbolDiagnosticKind::DuplicateParameter => "parameter name repeated",
+            SymbolDiagnosticKind::Shadowing => "declaration shadows an outer one",
+            SymbolDiagnosticKind::UndeclaredVariable => "variable used before it is declared",
+            SymbolDiagnosticKind::DuplicateEnumMember => "enum member declared twice",
+            SymbolDiagnosticKind::UnknownEnumMember => "no such enum member",
+        }
+    }
+}
+
+/// A problem found while building the symbol table.
+/// `first` is the earlier declaration and `second` the one that clashes with it;
+/// for an undeclared variable both are the offending use.
+#[derive(Debug, Clone)]
+pub struct SymbolDiagnostic {
+    pub kind: SymbolDiagnosticKind,
+    pub name: String,
+    pub first: Span,
+    pub second: Span,
+}
+
+impl From<&SymbolDiagnostic> for Diagnostic {
+    fn from(diagnostic: &SymbolDiagnostic) -> Self {
+        Diagnostic::new(
+            diagnostic.kind.code(),
+            diagnostic.kind.severity(),
+            format!("'{}': {}", diagnostic.name, diagnostic.kind.description()),
+            Some(diagnostic.second.clone()),
+        )
+    }
+}
+
+/// How a [`Reference`] uses its variable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Its value is used
+    Read,
+    /// It is assigned, as by `x = 1`. Updating it in a statement of its own, like
+    /// `x += 1;` or `x++;`, only reads it to write it back and is a write too.
+    Write,
+    /// It is updated and the result used, as `x++` is in `y = x++`
+    ReadWrite,
+}
+
+/// A use of a variable, and the declaration it resolves to as the scopes stood there
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    /// Where the variable is used
+    pub span: Span,
+    /// Where the declaration it resolves to is; `None` if no visible scope declares it
+    pub site: Option<Span>,
+    pub access: Access,
+}
+
+/// A variable declaration hiding a variable or parameter of an enclosing scope of the
+/// same function
+#[derive(Debug, Clone)]
+pub struct Shadow {
+    pub name: String,
+    /// Where the hidden variable is declared; the function, for a parameter
+    pub outer: Span,
+    /// Where the hiding declaration is
+    pub inner: Span,
+}
+
+/// An enclosing scope visible from the one being built
+#[derive(Clone, Copy)]
+struct OuterScope<'a> {
+    table: &'a SymbolTable,
+    sites: &'a HashMap<String, Span>,
+    /// Variables are not visible across a function boundary, only functions are
+    variables_visible: bool,
+}
+
+/// Builds the scopes of a program and reports problems with its declarations.
+///
+/// A function body opens a scope, as do a block, a branch of an `if` that is a block,
+/// the cases of a `switch` together, and a loop. A branch that is a single statement
+/// is resolved in the enclosing scope, so every scope but a loop's stands for a pair
+/// of braces in the source. A loop's scope holds its header, which is resolved there:
+/// `for`'s init, condition and update, the condition of `while` and `do`-`until`, and
+/// the count of `repeat`. Its body is resolved there too, in a scope of its own if it
+/// is a block. So a variable declared by a `for` init is visible to the condition, the
+/// update and the body, but not after the loop. Nothing else in a header declares
+/// anything, so the other loop headers, like `if` and `switch` conditions, see the
+/// same names as the enclosing scope, except that a `do` body that is not a block can
+/// declare a name for its `until`.
+///
+/// Built [for a script](Self::for_script), the variables declared outside functions
+/// are globals, as [`Script`](crate::script::Script) compiles them, and every
+/// function can use them.
 pub struct SymbolTableBuilder<'a> {
     scope: &'a mut Scope,
+    /// Enclosing scopes, innermost last
+    outer: Vec<OuterScope<'a>>,
+    diagnostics: Vec<SymbolDiagnostic>,
+    references: Vec<Reference>,
+    shadows: Vec<Shadow>,
+    /// Span of the function whose scope `visit_func` builds next
+    function_site: Span,
+    /// Variables declared so far in the enclosing function. `var` is function-scoped,
+    /// so unlike `scope` this is not reset by blocks.
+    declared: HashSet<String>,
+    /// Whether the expression visited next is a statement of its own, whose value
+    /// is discarded
+    discarded: bool,
+    /// Whether top-level variables are globals functions see
+    script_globals: bool,
+    /// The variables declared outside functions, if they are globals
+    globals: Rc<HashSet<String>>,
 }
 
 impl<'a> SymbolTableBuilder<'a> {
     pub fn new(scope: &'a mut Scope) -> Self {
-        Self { scope }
+        Self {
+            scope,
+            outer: vec![],
+            diagnostics: vec![],
+            references: vec![],
+            shadows: vec![],
+            function_site: Span::default(),
+            declared: HashSet::new(),
+            discarded: false,
+            script_globals: false,
+            globals: Rc::default(),
+        }
+    }
+
+    /// A builder treating the variables declared outside functions as globals that
+    /// functions can use, as in a [`Script`](crate::script::Script)
+    pub fn for_script(scope: &'a mut Scope) -> Self {
+        Self {
+            script_globals: true,
+            ..Self::new(scope)
+        }
+    }
+
+    /// Consume the builder and return the diagnostics it recorded, in source order
+    pub fn into_diagnostics(self) -> Vec<SymbolDiagnostic> {
+        self.diagnostics
+    }
+
+    /// Every variable use visited so far, in source order
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    /// Every variable declaration visited so far that hides another variable, in
+    /// source order. These are the `Shadowing` diagnostics that are not about
+    /// functions.
+    pub fn shadows(&self) -> &[Shadow] {
+        &self.shadows
+    }
+
+    /// Look a name up in the current scope, then outward through the enclosing ones
+    pub fn resolve(&self, name: &str) -> Option<(&Symbol, &Span)> {
+        if let Some(symbol) = self.scope.table.get(name) {
+            return Some((symbol, &self.scope.sites[name]));
+        }
+        self.outer.iter().rev().find_map(|outer| {
+            let symbol = outer.table.get(name)?;
+            if matches!(symbol, Symbol::Variable) && !outer.variables_visible {
+                return None;
+            }
+            Some((symbol, &outer.sites[name]))
+        })
     }
 
-    fn add_symbol(&mut self, name: String, symbol: Symbol) {
+    fn add_symbol(&mut self, name: String, symbol: Symbol, site: Span) {
+        if matches!(symbol, Symbol::Variable) {
+            self.declared.insert(name.clone());
+        }
+        if let Some(existing) = self.scope.table.get(&name) {
+            let kind = match (existing, &symbol) {
+                (Symbol::Variable, Symbol::Variable) => SymbolDiagnosticKind::DuplicateVariable,
+                (Symbol::Function { .. }, Symbol::Function { .. }) => {
+                    SymbolDiagnosticKind::DuplicateFunction
+                }
+                (Symbol::EnumMember, Symbol::EnumMember) => {
+                    SymbolDiagnosticKind::DuplicateEnumMember
+                }
+                _ => SymbolDiagnosticKind::ConflictingDeclaration,
+            };
+            let first = self.scope.sites[&name].clone();
+            self.report(kind, name, first, site);
+            return;
+        }
+
+        if let Some((shadowed, first)) = self.resolve(&name) {
+            let first = first.clone();
+            if matches!((shadowed, &symbol), (Symbol::Variable, Symbol::Variable)) {
+                self.shadows.push(Shadow {
+                    name: name.clone(),
+                    outer: first.clone(),
+                    inner: site.clone(),
+                });
+            }
+            self.report(
+                SymbolDiagnosticKind::Shadowing,
+                name.clone(),
+                first,
+                site.clone(),
+            );
+        }
+        self.scope.sites.insert(name.clone(), site);
         self.scope.table.insert(name, symbol);
     }
+
+    fn report(&mut self, kind: SymbolDiagnosticKind, name: String, first: Span, second: Span) {
+        self.diagnostics.push(SymbolDiagnostic {
+            kind,
+            name,
+            first,
+            second,
+        });
+    }
+
+    /// Build a new child scope of the current one with `f`
+    fn with_child_scope(
+        &mut self,
+        kind: ScopeKind,
+        span: Span,
+        f: impl FnOnce(&mut SymbolTableBuilder<'_>),
+    ) {
+        let is_function = kind == ScopeKind::Function;
+        self.scope.children.push(Scope::with_kind(kind, span));
+        let Scope {
+            table,
+            sites,
+            children,
+            ..
+        } = &mut *self.scope;
+
+        let mut outer: Vec<OuterScope> = self.outer.clone();
+        outer.push(OuterScope {
+            table,
+            sites,
+            variables_visible: true,
+        });
+        if is_function {
+            // The global scope comes first
+            let globals_visible = usize::from(self.script_globals);
+            for scope in outer.iter_mut().skip(globals_visible) {
+                scope.variables_visible = false;
+            }
+        }
+
+        let declared = if is_function {
+            self.globals.as_ref().clone()
+        } else {
+            std::mem::take(&mut self.declared)
+        };
+        let mut sub_visitor = SymbolTableBuilder {
+            scope: children.last_mut().unwrap(),
+            outer,
+            diagnostics: vec![],
+            references: vec![],
+            shadows: vec![],
+            function_site: Span::default(),
+            declared,
+            discarded: false,
+            script_globals: self.script_globals,
+            globals: self.globals.clone(),
+        };
+        f(&mut sub_visitor);
+        let SymbolTableBuilder {
+            diagnostics,
+            references,
+            shadows,
+            declared,
+            ..
+        } = sub_visitor;
+        self.diagnostics.extend(diagnostics);
+        self.references.extend(references);
+        self.shadows.extend(shadows);
+        if !is_function {
+            self.declared = declared;
+        }
+    }
+
+    /// Record a use of the variable `name` at `span`
+    fn reference(&mut self, name: &str, span: &Span, access: Access) {
+        let site = self.resolve(name).map(|(_, site)| site.clone());
+        self.references.push(Reference {
+            name: name.to_string(),
+            span: span.clone(),
+            site,
+            access,
+        });
+        if !self.declared.contains(name) {
+            self.report(
+                SymbolDiagnosticKind::UndeclaredVariable,
+                name.to_string(),
+                span.clone(),
+                span.clone(),
+            );
+        }
+    }
+
+    /// Visit the target of an assignment or update, which uses a variable as `access`
+    /// says
+    fn visit_target(&mut self, target: &Expr, access: Access) {
+        match target {
+            Expr::Identifier(name, span) => self.reference(name, span, access),
+            _ => target.accept(self),
+        }
+    }
+
+    /// Visit `stmt`, a branch or loop body, in a scope of `kind` if it is a block and
+    /// in the current scope otherwise
+    fn visit_body(&mut self, kind: ScopeKind, stmt: &Stmt) {
+        let Stmt::Block(stmts, span) = stmt else {
+            stmt.accept(self);
+            return;
+        };
+        self.with_child_scope(kind, span.clone(), |sub_visitor| {
+            for stmt in stmts {
+                stmt.accept(sub_visitor);
+            }
+        });
+    }
 }
 
 impl<'a> Visitor<()> for SymbolTableBuilder<'a> {
     fn visit_program(&mut self, program: &Program) {
+        if self.script_globals {
+            let mut top_level_vars = TopLevelVars::default();
+            program.accept(&mut top_level_vars);
+            self.globals = Rc::new(top_level_vars.names);
+        }
+        // Enums are global and can be used anywhere, including before their declaration
         for toplevel in &program.body {
-            toplevel.accept(self);
+            if let TopLevel::Enum(enum_def) = toplevel {
+                for member in &enum_def.members {
+                    self.add_symbol(
+                        format!("{}.{}", enum_def.name, member.name),
+                        Symbol::EnumMember,
+                        member.span.clone(),
+                    );
+                }
+            }
         }
+        self.walk_program(program);
     }
 
     fn visit_toplevel(&mut self, toplevel: &TopLevel) {
-        match toplevel {
-            TopLevel::Statement(stmt) => stmt.accept(self),
-            TopLevel::Function(func_def) => func_def.accept(self),
-        }
+        self.walk_toplevel(toplevel);
     }
 
     fn visit_func_def(&mut self, func_def: &FuncDef) {
@@ -64,155 +499,182 @@ impl<'a> Visitor<()> for SymbolTableBuilder<'a> {
             Symbol::Function {
                 parameters: func_def.func.args.clone(),
             },
+            func_def.span.clone(),
         );
+        self.function_site = func_def.span.clone();
         func_def.func.accept(self);
     }
 
     fn visit_func(&mut self, func: &Func) {
-        self.scope.children.push(Scope::new());
-        let mut sub_visitor = SymbolTableBuilder::new(self.scope.children.last_mut().unwrap());
-        for param in &func.args {
-            sub_visitor.add_symbol(param.clone(), Symbol::Variable);
-        }
-        for stmt in &func.body {
-            stmt.accept(&mut sub_visitor);
-        }
+        let site = std::mem::take(&mut self.function_site);
+        self.with_child_scope(ScopeKind::Function, site.clone(), |sub_visitor| {
+            // Every function body gets an implicit `argument_count` local
+            sub_visitor.declared.insert("argument_count".to_string());
+            for (i, param) in func.args.iter().enumerate() {
+                if func.args[..i].contains(param) {
+                    sub_visitor.report(
+                        SymbolDiagnosticKind::DuplicateParameter,
+                        param.clone(),
+                        site.clone(),
+                        site.clone(),
+                    );
+                    continue;
+                }
+                sub_visitor.add_symbol(param.clone(), Symbol::Variable, site.clone());
+            }
+            for stmt in &func.body {
+                stmt.accept(sub_visitor);
+            }
+        });
     }
 
     fn visit_stmt(&mut self, stmt: &Stmt) {
         match stmt {
-            Stmt::Expr(expr) => expr.accept(self),
             Stmt::Var(vars) => {
-                for (name, expr_opt) in vars {
-                    self.add_symbol(name.clone(), Symbol::Variable);
+                for (name, expr_opt, span) in vars {
+                    // The initializer runs before the variable exists, as in codegen
                     if let Some(expr) = expr_opt {
                         expr.accept(self);
                     }
+                    self.add_symbol(name.clone(), Symbol::Variable, span.clone());
                 }
             }
-            Stmt::If(cond, then_stmt, else_stmt_opt) => {
+            Stmt::If(cond, then_stmt, else_stmt_opt, _) => {
                 cond.accept(self);
-                self.scope.children.push(Scope::new());
-                let mut then_visitor =
-                    SymbolTableBuilder::new(self.scope.children.last_mut().unwrap());
-                then_stmt.accept(&mut then_visitor);
-
+                self.visit_body(ScopeKind::IfThen, then_stmt);
                 if let Some(else_stmt) = else_stmt_opt {
-                    self.scope.children.push(Scope::new());
-                    let mut else_visitor =
-                        SymbolTableBuilder::new(self.scope.children.last_mut().unwrap());
-                    else_stmt.accept(&mut else_visitor);
+                    self.visit_body(ScopeKind::IfElse, else_stmt);
                 }
             }
-            Stmt::Block(stmts) => {
-                self.scope.children.push(Scope::new());
-                let mut sub_visitor =
-                    SymbolTableBuilder::new(self.scope.children.last_mut().unwrap());
-                for stmt in stmts {
-                    stmt.accept(&mut sub_visitor);
-                }
+            Stmt::Block(..) => self.visit_body(ScopeKind::Block, stmt),
+            Stmt::Repeat(count, body, span) => {
+                self.with_child_scope(ScopeKind::RepeatHeader, span.clone(), |sub_visitor| {
+                    count.accept(sub_visitor);
+                    sub_visitor.visit_body(ScopeKind::RepeatBody, body);
+                });
             }
-            Stmt::Return(expr_opt) => {
-                if let Some(expr) = expr_opt {
-                    expr.accept(self);
-                }
+            Stmt::While(cond, body, span) => {
+                self.with_child_scope(ScopeKind::WhileHeader, span.clone(), |sub_visitor| {
+                    cond.accept(sub_visitor);
+                    sub_visitor.visit_body(ScopeKind::WhileBody, body);
+                });
             }
-            Stmt::Break => {}
-            Stmt::Continue => {}
-            Stmt::Repeat(count, body) => {
-                count.accept(self);
-                self.scope.children.push(Scope::new());
-                let mut sub_visitor =
-                    SymbolTableBuilder::new(self.scope.children.last_mut().unwrap());
-                body.accept(&mut sub_visitor);
+            Stmt::DoUntil(body, cond, span) => {
+                self.with_child_scope(ScopeKind::DoHeader, span.clone(), |sub_visitor| {
+                    sub_visitor.visit_body(ScopeKind::DoBody, body);
+                    cond.accept(sub_visitor);
+                });
             }
-            Stmt::While(cond, body) => {
-                cond.accept(self);
-                self.scope.children.push(Scope::new());
-                let mut sub_visitor =
-                    SymbolTableBuilder::new(self.scope.children.last_mut().unwrap());
-                body.accept(&mut sub_visitor);
-            }
-            Stmt::DoUntil(body, cond) => {
-                self.scope.children.push(Scope::new());
-                let mut sub_visitor =
-                    SymbolTableBuilder::new(self.scope.children.last_mut().unwrap());
-                body.accept(&mut sub_visitor);
-                cond.accept(self); // Condition is evaluated in the outer scope
-            }
-            Stmt::For(init, cond_opt, update_opt, body) => {
-                self.scope.children.push(Scope::new());
-                let mut sub_visitor =
-                    SymbolTableBuilder::new(self.scope.children.last_mut().unwrap());
-                if let Some(init_stmt) = init {
-                    init_stmt.accept(&mut sub_visitor);
-                }
-                if let Some(cond_expr) = cond_opt {
-                    cond_expr.accept(&mut sub_visitor);
-                }
-                if let Some(update_stmt) = update_opt {
-                    update_stmt.accept(&mut sub_visitor);
-                }
-                body.accept(&mut sub_visitor);
+            Stmt::For(init, cond_opt, update_opt, body, span) => {
+                self.with_child_scope(ScopeKind::ForHeader, span.clone(), |sub_visitor| {
+                    if let Some(init_stmt) = init {
+                        init_stmt.accept(sub_visitor);
+                    }
+                    if let Some(cond_expr) = cond_opt {
+                        cond_expr.accept(sub_visitor);
+                    }
+                    // Several updates are parsed as a block, which is no scope
+                    match update_opt.as_deref() {
+                        Some(Stmt::Block(stmts, _)) => {
+                            for stmt in stmts {
+                                stmt.accept(sub_visitor);
+                            }
+                        }
+                        Some(update_stmt) => update_stmt.accept(sub_visitor),
+                        None => {}
+                    }
+                    sub_visitor.visit_body(ScopeKind::ForBody, body);
+                });
+            }
+            Stmt::Switch(value, cases, span) => {
+                value.accept(self);
+                // Control falls from one label into the next, so they share a scope
+                self.with_child_scope(ScopeKind::Switch, span.clone(), |sub_visitor| {
+                    for case in cases {
+                        for label in &case.labels {
+                            label.accept(sub_visitor);
+                        }
+                        for stmt in &case.body {
+                            stmt.accept(sub_visitor);
+                        }
+                    }
+                });
+            }
+            Stmt::Expr(expr) => {
+                self.discarded = true;
+                expr.accept(self);
+            }
+            Stmt::Return(_) | Stmt::Break | Stmt::Continue | Stmt::Function(_) | Stmt::Error(_) => {
+                self.walk_stmt(stmt)
             }
         }
     }
 
     fn visit_expr(&mut self, expr: &Expr) {
-        // Most expressions that contain other expressions need to be recursively visited.
+        let update = if std::mem::take(&mut self.discarded) {
+            Access::Write
+        } else {
+            Access::ReadWrite
+        };
         match expr {
-            Expr::Call(_, args) => {
-                for arg in args {
-                    arg.accept(self);
+            Expr::Identifier(name, span) => self.reference(name, span, Access::Read),
+            Expr::Equal(target, value) => {
+                self.visit_target(target, Access::Write);
+                value.accept(self);
+            }
+            Expr::PlusEqual(target, value)
+            | Expr::MinusEqual(target, value)
+            | Expr::StarEqual(target, value)
+            | Expr::SlashEqual(target, value)
+            | Expr::PercentEqual(target, value) => {
+                self.visit_target(target, update);
+                value.accept(self);
+            }
+            Expr::PreIncrement(target)
+            | Expr::PostIncrement(target)
+            | Expr::PreDecrement(target)
+            | Expr::PostDecrement(target) => self.visit_target(target, update),
+            // A variable holding a function reference is read by calling it
+            Expr::Call(name, _, span) => {
+                if matches!(self.resolve(name), Some((Symbol::Variable, _))) {
+                    self.reference(name, span, Access::Read);
                 }
+                self.walk_expr(expr);
             }
-            Expr::Addition(l, r)
-            | Expr::Subtraction(l, r)
-            | Expr::Multiplication(l, r)
-            | Expr::Division(l, r)
-            | Expr::Percent(l, r)
-            | Expr::Greater(l, r)
-            | Expr::GreaterEqual(l, r)
-            | Expr::Less(l, r)
-            | Expr::LessEqual(l, r)
-            | Expr::EqualEqual(l, r)
-            | Expr::NotEqual(l, r)
-            | Expr::BitAnd(l, r)
-            | Expr::BitXor(l, r)
-            | Expr::BitOr(l, r)
-            | Expr::And(l, r)
-            | Expr::Xor(l, r)
-            | Expr::Or(l, r)
-            | Expr::Equal(l, r)
-            | Expr::PlusEqual(l, r)
-            | Expr::MinusEqual(l, r)
-            | Expr::StarEqual(l, r)
-            | Expr::SlashEqual(l, r)
-            | Expr::PercentEqual(l, r) => {
-                l.accept(self);
-                r.accept(self);
-            }
-            Expr::Not(e)
-            | Expr::BitNot(e)
-            | Expr::Positive(e)
-            | Expr::Negative(e)
-            | Expr::Paren(e)
-            | Expr::PreIncrement(e)
-            | Expr::PostIncrement(e)
-            | Expr::PreDecrement(e)
-            | Expr::PostDecrement(e) => e.accept(self),
-            Expr::Ternary(cond, then_expr, else_expr) => {
-                cond.accept(self);
-                then_expr.accept(self);
-                else_expr.accept(self);
+            Expr::Function(func, span) => {
+                self.function_site = span.clone();
+                func.accept(self);
             }
-            // Atoms have no children to visit
-            Expr::Number(_)
-            | Expr::String(_)
-            | Expr::True(_)
-            | Expr::False(_)
-            | Expr::Null
-            | Expr::Identifier(_) => {}
+            Expr::Member(object, member, span) => {
+                let name = format!("{}.{}", object, member);
+                if !matches!(self.resolve(&name), Some((Symbol::EnumMember, _))) {
+                    self.report(
+                        SymbolDiagnosticKind::UnknownEnumMember,
+                        name,
+                        span.clone(),
+                        span.clone(),
+                    );
+                }
+            }
+            _ => self.walk_expr(expr),
+        }
+    }
+}
+
+/// The variables a program declares outside its functions
+#[derive(Default)]
+struct TopLevelVars {
+    names: HashSet<String>,
+}
+
+impl Pass for TopLevelVars {
+    fn visit_func(&mut self, _func: &Func) {}
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::Var(vars) = stmt {
+            self.names
+                .extend(vars.iter().map(|(name, _, _)| name.clone()));
         }
+        self.walk_stmt(stmt);
     }
 }
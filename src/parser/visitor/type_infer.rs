@@ -0,0 +1,542 @@
+use crate::parser::Span;
+use crate::parser::expr::*;
+use crate::parser::func::Func;
+use crate::parser::func_def::FuncDef;
+use crate::parser::program::Program;
+use crate::parser::stmt::Stmt;
+use crate::parser::top_level::TopLevel;
+use crate::parser::visitor::Visitor;
+use crate::utils::diagnostic::{Diagnostic, Severity};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Diagnostic code for an operation code generation is certain to reject
+pub const TYPE_ERROR: u32 = 501;
+
+/// The kind of value an expression evaluates to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Number,
+    Bool,
+    String,
+    Null,
+    /// Not known before running, e.g. a variable that is never assigned
+    Unknown,
+}
+
+impl Type {
+    /// Strings and null are both pointers and mix only with each other
+    fn is_string_like(self) -> bool {
+        matches!(self, Type::String | Type::Null)
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Type::Number => "a number",
+            Type::Bool => "a bool",
+            Type::String => "a string",
+            Type::Null => "null",
+            Type::Unknown => "an unknown value",
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Number => write!(f, "number"),
+            Type::Bool => write!(f, "bool"),
+            Type::String => write!(f, "string"),
+            Type::Null => write!(f, "null"),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// The inferred type of every expression with a known position, and the type errors
+/// found on the way
+#[derive(Debug, Default)]
+pub struct TypeMap {
+    types: Vec<(Span, Type)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl TypeMap {
+    /// The type of the innermost expression covering `offset`, a byte offset into
+    /// the source. Operators belong to the expression they combine, so the offset of
+    /// a `+` gives the type of the whole addition.
+    pub fn type_at(&self, offset: usize) -> Option<Type> {
+        self.types
+            .iter()
+            .filter(|(span, _)| span.contains(&offset))
+            .min_by_key(|(span, _)| span.len())
+            .map(|(_, ty)| *ty)
+    }
+
+    /// Every expression's span and type, innermost expressions first
+    pub fn iter(&self) -> impl Iterator<Item = (&Span, Type)> {
+        self.types.iter().map(|(span, ty)| (span, *ty))
+    }
+
+    /// Type errors in source order
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
+
+/// Infer the type of every expression in `program`
+pub fn infer_types(program: &Program) -> TypeMap {
+    let mut inferrer = TypeInferrer::new();
+    program.accept(&mut inferrer);
+    inferrer.into_type_map()
+}
+
+/// Assigns types following the rules code generation applies: comparisons give
+/// bools, arithmetic gives numbers, `+` on two strings concatenates, and strings
+/// never mix with numbers. An operation that is certain to fail is reported and
+/// typed unknown, then inference carries on.
+///
+/// Literals, variables, members and calls carry their own spans; any other
+/// expression spans from its first to its last operand. Variables take the type
+/// of the last value assigned to them before the point of use, in source order.
+/// `visit_expr` gives an expression's type, every other node gives `Unknown`.
+#[derive(Default)]
+pub struct TypeInferrer {
+    map: TypeMap,
+    /// Variables of the global scope, then of each function being inferred
+    scopes: Vec<HashMap<String, Type>>,
+    functions: HashSet<String>,
+}
+
+impl TypeInferrer {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            ..Self::default()
+        }
+    }
+
+    /// Consume the inferrer and return what it found
+    pub fn into_type_map(mut self) -> TypeMap {
+        self.map
+            .diagnostics
+            .sort_by_key(|d| d.span.clone().map(|s| s.start));
+        self.map
+    }
+
+    fn error(&mut self, message: String, span: Option<Span>) {
+        self.map
+            .diagnostics
+            .push(Diagnostic::new(TYPE_ERROR, Severity::Error, message, span));
+    }
+
+    /// A function sees its own variables and the globals, not those of an enclosing function
+    fn visible_scopes(&mut self) -> impl Iterator<Item = &mut HashMap<String, Type>> {
+        let (globals, functions) = self.scopes.split_at_mut(1);
+        functions.last_mut().into_iter().chain(globals)
+    }
+
+    fn lookup(&mut self, name: &str) -> Type {
+        self.visible_scopes()
+            .find_map(|scope| scope.get(name).copied())
+            .unwrap_or(Type::Unknown)
+    }
+
+    fn assign(&mut self, name: &str, ty: Type) {
+        if let Some(scope) = self.visible_scopes().find(|scope| scope.contains_key(name)) {
+            scope.insert(name.to_string(), ty);
+        } else {
+            self.declare(name, ty);
+        }
+    }
+
+    fn declare(&mut self, name: &str, ty: Type) {
+        self.scopes
+            .last_mut()
+            .expect("the global scope is never popped")
+            .insert(name.to_string(), ty);
+    }
+
+    fn check_condition(&mut self, ty: Type, span: Option<Span>) {
+        match ty {
+            Type::String => self.error("a string cannot be used as a condition".to_string(), span),
+            Type::Null => self.error("null cannot be used as a condition".to_string(), span),
+            Type::Number | Type::Bool | Type::Unknown => {}
+        }
+    }
+
+    fn condition(&mut self, cond: &Expr) {
+        let (ty, span) = self.infer(cond);
+        self.check_condition(ty, span);
+    }
+
+    /// The type of `lhs op rhs` for an arithmetic, bitwise or comparison operator
+    fn binary(&mut self, op: &str, lhs: Type, rhs: Type, span: &Option<Span>) -> Type {
+        let comparison = matches!(op, "==" | "!=" | "<" | "<=" | ">" | ">=");
+        if lhs == Type::Unknown || rhs == Type::Unknown {
+            return if comparison {
+                Type::Bool
+            } else if op == "+" {
+                // A string plus anything that does not fail is a string
+                if lhs == Type::String || rhs == Type::String {
+                    Type::String
+                } else {
+                    Type::Unknown
+                }
+            } else {
+                Type::Number
+            };
+        }
+        match (lhs.is_string_like(), rhs.is_string_like()) {
+            (false, false) if comparison => Type::Bool,
+            (false, false) => Type::Number,
+            (true, true) if comparison => Type::Bool,
+            (true, true) if op == "+" => Type::String,
+            _ => {
+                let message = if lhs == Type::String && rhs == Type::String {
+                    format!("cannot apply '{}' to strings", op)
+                } else {
+                    format!(
+                        "cannot apply '{}' to {} and {}",
+                        op,
+                        lhs.describe(),
+                        rhs.describe()
+                    )
+                };
+                self.error(message, span.clone());
+                Type::Unknown
+            }
+        }
+    }
+
+    /// An operator that only takes numbers, like `-x` or `x++`
+    fn numeric_unary(&mut self, op: &str, operand: Type, span: &Option<Span>) -> Type {
+        if operand.is_string_like() {
+            self.error(
+                format!("cannot apply '{}' to {}", op, operand.describe()),
+                span.clone(),
+            );
+            return Type::Unknown;
+        }
+        Type::Number
+    }
+
+    fn call_type(&self, name: &str) -> Type {
+        // Functions a script defines take precedence over built-ins and return numbers
+        if self.functions.contains(name) {
+            return Type::Number;
+        }
+        match name {
+            "string" | "string_format" | "string_char_at" | "string_copy" => Type::String,
+            "ds_map_exists" | "bool" | "is_nan" | "is_infinity" => Type::Bool,
+            "real" | "int64" | "ds_list_create" | "ds_list_destroy" | "ds_list_add"
+            | "ds_list_size" | "ds_list_find_value" | "ds_list_copy" | "ds_map_create"
+            | "ds_map_destroy" | "ds_map_set" | "ds_map_find_value" | "ds_map_copy"
+            | "math_set_epsilon" | "math_get_epsilon" | "string_length" | "string_pos"
+            | "println" => Type::Number,
+            _ => Type::Unknown,
+        }
+    }
+
+    /// Infer the type of `expr` and where it appears, recording both
+    fn infer(&mut self, expr: &Expr) -> (Type, Option<Span>) {
+        let (ty, span) = self.infer_inner(expr);
+        if let Some(span) = &span {
+            self.map.types.push((span.clone(), ty));
+        }
+        (ty, span)
+    }
+
+    fn infer_inner(&mut self, expr: &Expr) -> (Type, Option<Span>) {
+        match expr {
+            Expr::Number(_, span) | Expr::Integer(_, span) => (Type::Number, Some(span.clone())),
+            Expr::String(_, span) => (Type::String, Some(span.clone())),
+            Expr::True(_, span) | Expr::False(_, span) => (Type::Bool, Some(span.clone())),
+            Expr::Null(span) => (Type::Null, Some(span.clone())),
+            // An empty argument slot is passed as 0
+            Expr::Undefined => (Type::Number, None),
+            Expr::Identifier(name, span) => (self.lookup(name), Some(span.clone())),
+            Expr::Member(_, _, span) => (Type::Number, Some(span.clone())),
+            // Instance fields only hold numbers
+            Expr::Field(_, _, span) => (Type::Number, Some(span.clone())),
+            Expr::SelfRef(span) | Expr::OtherRef(span) => (Type::Unknown, Some(span.clone())),
+            // A function reference is held as a number
+            Expr::Function(func, span) => {
+                func.accept(self);
+                (Type::Number, Some(span.clone()))
+            }
+            // Lists and maps are read through their ds_* builtins, which give numbers
+            Expr::Accessor(kind, target, indices, span) => {
+                self.infer(target);
+                for index in indices {
+                    self.infer(index);
+                }
+                let ty = match kind {
+                    AccessorKind::List | AccessorKind::Map => Type::Number,
+                    AccessorKind::Grid | AccessorKind::ArrayRef => Type::Unknown,
+                };
+                (ty, Some(span.clone()))
+            }
+            Expr::Call(name, args, span) => {
+                for arg in args {
+                    self.infer(arg);
+                }
+                (self.call_type(name), Some(span.clone()))
+            }
+
+            Expr::Addition(lhs, rhs) => self.infer_binary("+", lhs, rhs),
+            Expr::Subtraction(lhs, rhs) => self.infer_binary("-", lhs, rhs),
+            Expr::Multiplication(lhs, rhs) => self.infer_binary("*", lhs, rhs),
+            Expr::Division(lhs, rhs) => self.infer_binary("/", lhs, rhs),
+            Expr::IntDivision(lhs, rhs) => self.infer_binary("div", lhs, rhs),
+            Expr::Percent(lhs, rhs) => self.infer_binary("%", lhs, rhs),
+            Expr::Greater(lhs, rhs) => self.infer_binary(">", lhs, rhs),
+            Expr::GreaterEqual(lhs, rhs) => self.infer_binary(">=", lhs, rhs),
+            Expr::Less(lhs, rhs) => self.infer_binary("<", lhs, rhs),
+            Expr::LessEqual(lhs, rhs) => self.infer_binary("<=", lhs, rhs),
+            Expr::EqualEqual(lhs, rhs) => self.infer_binary("==", lhs, rhs),
+            Expr::NotEqual(lhs, rhs) => self.infer_binary("!=", lhs, rhs),
+            Expr::BitAnd(lhs, rhs) => self.infer_binary("&", lhs, rhs),
+            Expr::BitXor(lhs, rhs) => self.infer_binary("^", lhs, rhs),
+            Expr::BitOr(lhs, rhs) => self.infer_binary("|", lhs, rhs),
+            Expr::ShiftLeft(lhs, rhs) => self.infer_binary("<<", lhs, rhs),
+            Expr::ShiftRight(lhs, rhs) => self.infer_binary(">>", lhs, rhs),
+
+            Expr::And(lhs, rhs) | Expr::Xor(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                let (l, l_span) = self.infer(lhs);
+                self.check_condition(l, l_span.clone());
+                let (r, r_span) = self.infer(rhs);
+                self.check_condition(r, r_span.clone());
+                (Type::Bool, join(l_span, r_span))
+            }
+            Expr::Not(operand) => {
+                let (ty, span) = self.infer(operand);
+                self.check_condition(ty, span.clone());
+                (Type::Bool, span)
+            }
+            Expr::BitNot(operand) => {
+                let (ty, span) = self.infer(operand);
+                (self.numeric_unary("~", ty, &span), span)
+            }
+            Expr::Negative(operand) => {
+                let (ty, span) = self.infer(operand);
+                (self.numeric_unary("-", ty, &span), span)
+            }
+            Expr::Positive(operand) | Expr::Paren(operand) => self.infer(operand),
+
+            Expr::Ternary(cond, then_expr, else_expr) => {
+                let (cond_ty, cond_span) = self.infer(cond);
+                self.check_condition(cond_ty, cond_span.clone());
+                let (then_ty, _) = self.infer(then_expr);
+                let (else_ty, else_span) = self.infer(else_expr);
+                let ty = if then_ty == else_ty {
+                    then_ty
+                } else {
+                    Type::Unknown
+                };
+                (ty, join(cond_span, else_span))
+            }
+
+            Expr::Equal(target, value) => {
+                let (ty, value_span) = self.infer(value);
+                let target_span = self.infer_target(target, ty);
+                (ty, join(target_span, value_span))
+            }
+            Expr::PlusEqual(target, value) => self.infer_compound("+=", target, value),
+            Expr::MinusEqual(target, value) => self.infer_compound("-=", target, value),
+            Expr::StarEqual(target, value) => self.infer_compound("*=", target, value),
+            Expr::SlashEqual(target, value) => self.infer_compound("/=", target, value),
+            Expr::PercentEqual(target, value) => self.infer_compound("%=", target, value),
+
+            Expr::PreIncrement(target) | Expr::PostIncrement(target) => {
+                self.infer_step("++", target)
+            }
+            Expr::PreDecrement(target) | Expr::PostDecrement(target) => {
+                self.infer_step("--", target)
+            }
+        }
+    }
+
+    fn infer_binary(&mut self, op: &str, lhs: &Expr, rhs: &Expr) -> (Type, Option<Span>) {
+        let (l, l_span) = self.infer(lhs);
+        let (r, r_span) = self.infer(rhs);
+        let span = join(l_span, r_span);
+        (self.binary(op, l, r, &span), span)
+    }
+
+    /// `x op= value`, typed like `x op value`
+    fn infer_compound(&mut self, op: &str, target: &Expr, value: &Expr) -> (Type, Option<Span>) {
+        let current = match target {
+            Expr::Identifier(name, _) => self.lookup(name),
+            _ => Type::Unknown,
+        };
+        let (value_ty, value_span) = self.infer(value);
+        let target_span = target_span(target);
+        let span = join(target_span, value_span);
+        let ty = self.binary(&op[..1], current, value_ty, &span);
+        self.infer_target(target, ty);
+        (ty, span)
+    }
+
+    fn infer_step(&mut self, op: &str, target: &Expr) -> (Type, Option<Span>) {
+        let (current, span) = self.infer(target);
+        let ty = self.numeric_unary(op, current, &span);
+        if let Expr::Identifier(name, _) = target {
+            self.assign(name, ty);
+        }
+        (ty, span)
+    }
+
+    /// Record that the assignment target `target` now holds a `ty`
+    fn infer_target(&mut self, target: &Expr, ty: Type) -> Option<Span> {
+        match target {
+            Expr::Identifier(name, span) => {
+                self.assign(name, ty);
+                self.map.types.push((span.clone(), ty));
+                Some(span.clone())
+            }
+            _ => self.infer(target).1,
+        }
+    }
+}
+
+/// Where an assignment target appears, without inferring it
+fn target_span(target: &Expr) -> Option<Span> {
+    match target {
+        Expr::Identifier(_, span) | Expr::Member(_, _, span) | Expr::Field(_, _, span) => {
+            Some(span.clone())
+        }
+        _ => None,
+    }
+}
+
+/// The span from the start of `first` to the end of `last`, or whichever is known
+fn join(first: Option<Span>, last: Option<Span>) -> Option<Span> {
+    match (first, last) {
+        (Some(first), Some(last)) => Some(first.start.min(last.start)..first.end.max(last.end)),
+        (first, last) => first.or(last),
+    }
+}
+
+impl Visitor<Type> for TypeInferrer {
+    fn visit_program(&mut self, program: &Program) -> Type {
+        self.functions = program.functions().map(|f| f.name.clone()).collect();
+        for toplevel in &program.body {
+            toplevel.accept(self);
+        }
+        Type::Unknown
+    }
+
+    fn visit_toplevel(&mut self, toplevel: &TopLevel) -> Type {
+        match toplevel {
+            TopLevel::Statement(stmt) => stmt.accept(self),
+            TopLevel::Function(func_def) => func_def.accept(self),
+            TopLevel::Enum(enum_def) => {
+                for value in enum_def.members.iter().filter_map(|m| m.value.as_ref()) {
+                    self.infer(value);
+                }
+                Type::Unknown
+            }
+            TopLevel::Include(..) | TopLevel::Error(_) => Type::Unknown,
+        }
+    }
+
+    fn visit_func_def(&mut self, func_def: &FuncDef) -> Type {
+        self.functions.insert(func_def.name.clone());
+        func_def.func.accept(self)
+    }
+
+    fn visit_func(&mut self, func: &Func) -> Type {
+        // Arguments are always passed as numbers
+        self.scopes.push(
+            func.args
+                .iter()
+                .map(|arg| (arg.clone(), Type::Number))
+                .collect(),
+        );
+        for stmt in &func.body {
+            stmt.accept(self);
+        }
+        self.scopes.pop();
+        Type::Unknown
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) -> Type {
+        match stmt {
+            Stmt::Expr(expr) => {
+                self.infer(expr);
+            }
+            Stmt::Var(vars) => {
+                for (name, expr_opt, span) in vars {
+                    let ty = match expr_opt {
+                        Some(expr) => self.infer(expr).0,
+                        None => Type::Unknown,
+                    };
+                    self.declare(name, ty);
+                    self.map.types.push((span.clone(), ty));
+                }
+            }
+            Stmt::If(cond, then_stmt, else_stmt_opt, _) => {
+                self.condition(cond);
+                then_stmt.accept(self);
+                if let Some(else_stmt) = else_stmt_opt {
+                    else_stmt.accept(self);
+                }
+            }
+            Stmt::Block(stmts, _) => {
+                for stmt in stmts {
+                    stmt.accept(self);
+                }
+            }
+            Stmt::Return(expr_opt) => {
+                if let Some(expr) = expr_opt {
+                    self.infer(expr);
+                }
+            }
+            Stmt::Repeat(count, body, _) => {
+                self.infer(count);
+                body.accept(self);
+            }
+            Stmt::While(cond, body, _) => {
+                self.condition(cond);
+                body.accept(self);
+            }
+            Stmt::DoUntil(body, cond, _) => {
+                body.accept(self);
+                self.condition(cond);
+            }
+            Stmt::For(init, cond, update, body, _) => {
+                if let Some(init) = init {
+                    init.accept(self);
+                }
+                if let Some(cond) = cond {
+                    self.condition(cond);
+                }
+                body.accept(self);
+                if let Some(update) = update {
+                    update.accept(self);
+                }
+            }
+            Stmt::Switch(value, cases, _) => {
+                self.infer(value);
+                for case in cases {
+                    for label in &case.labels {
+                        self.infer(label);
+                    }
+                    for stmt in &case.body {
+                        stmt.accept(self);
+                    }
+                }
+            }
+            Stmt::Function(func_def) => {
+                func_def.accept(self);
+            }
+            Stmt::Break | Stmt::Continue | Stmt::Error(_) => {}
+        }
+        Type::Unknown
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) -> Type {
+        self.infer(expr).0
+    }
+}
@@ -0,0 +1,161 @@
+use crate::parser::Span;
+use crate::parser::program::Program;
+use crate::parser::visitor::symbol_table_builder::{
+    Access, Scope, ScopeKind, Symbol, SymbolTableBuilder,
+};
+use crate::utils::diagnostic::{Diagnostic, Severity};
+use std::collections::HashSet;
+
+/// Diagnostic code for a local variable that is never used
+pub const UNUSED_VARIABLE: u32 = 404;
+/// Diagnostic code for a parameter that is never used
+pub const UNUSED_PARAMETER: u32 = 405;
+/// Diagnostic code for a local variable that is assigned but never read
+pub const UNREAD_VARIABLE: u32 = 406;
+
+/// Flags `var temp;` never read again, and parameters a function ignores. Uses are
+/// resolved by the symbol table builder, so a use counts for the declaration it sees,
+/// in whatever scope it is: a nested block, a loop condition or a `switch` label.
+/// A variable that is only assigned, or only updated by statements like `x += 1;`, is
+/// reported apart from one never used at all. Names starting with `_` are exempt,
+/// and so are top-level variables, which the host can read. Like shadowing, this is
+/// legal, so it only warns, and only when asked to.
+pub struct UnusedLinter;
+
+impl UnusedLinter {
+    /// Lint `program`, returning its warnings in source order
+    pub fn lint(program: &Program) -> Vec<Diagnostic> {
+        let mut root = Scope::new();
+        let mut builder = SymbolTableBuilder::new(&mut root);
+        program.accept(&mut builder);
+        let mut read = HashSet::new();
+        let mut written = HashSet::new();
+        for reference in builder.references() {
+            let Some(site) = &reference.site else {
+                continue;
+            };
+            let declaration = (reference.name.clone(), site.clone());
+            match reference.access {
+                Access::Read | Access::ReadWrite => read.insert(declaration),
+                Access::Write => written.insert(declaration),
+            };
+        }
+
+        let mut diagnostics = vec![];
+        let mut uses = Uses {
+            read: &read,
+            written: &written,
+            diagnostics: &mut diagnostics,
+        };
+        for child in &root.children {
+            uses.check(&root, child);
+        }
+        diagnostics.sort_by_key(|d| d.span.as_ref().map_or(0, |span| span.start));
+        diagnostics
+    }
+}
+
+/// The declarations read and written anywhere, by name and site
+struct Uses<'a> {
+    read: &'a HashSet<(String, Span)>,
+    written: &'a HashSet<(String, Span)>,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl Uses<'_> {
+    /// Report the unused variables of `scope`, a child of `parent`, and of the scopes
+    /// in it. Only scopes inside a function have local variables.
+    fn check(&mut self, parent: &Scope, scope: &Scope) {
+        if scope.kind == ScopeKind::Function {
+            self.check_locals(parent, scope);
+        } else {
+            for child in &scope.children {
+                self.check(scope, child);
+            }
+        }
+    }
+
+    /// Report the unused parameters and variables of `scope`, a child of `parent`
+    /// inside a function, and of the scopes in it
+    fn check_locals(&mut self, parent: &Scope, scope: &Scope) {
+        // Parameters are declared at their function, having no span of their own
+        let (function, parameters) = match scope.kind {
+            ScopeKind::Function => declaring(parent, scope),
+            _ => (String::new(), vec![]),
+        };
+        for parameter in &parameters {
+            let declaration = (parameter.clone(), scope.span.clone());
+            if parameter.starts_with('_') || self.read.contains(&declaration) {
+                continue;
+            }
+            let message = format!(
+                "parameter '{}' of {} is never used; remove it, or rename it to '_{}' if \
+                 it must stay",
+                parameter, function, parameter
+            );
+            self.warn(UNUSED_PARAMETER, message, &scope.span);
+        }
+
+        let mut variables: Vec<_> = scope
+            .table
+            .iter()
+            .filter(|(name, symbol)| {
+                matches!(symbol, Symbol::Variable)
+                    && !name.starts_with('_')
+                    && !parameters.contains(name)
+            })
+            .map(|(name, _)| (name, &scope.sites[name]))
+            .collect();
+        variables.sort_by_key(|(_, site)| site.start);
+        for (name, site) in variables {
+            let declaration = (name.clone(), site.clone());
+            if self.read.contains(&declaration) {
+                continue;
+            }
+            if self.written.contains(&declaration) {
+                let message = format!("variable '{}' is assigned but never read", name);
+                self.warn(UNREAD_VARIABLE, message, site);
+            } else {
+                let message = format!("variable '{}' is never used", name);
+                self.warn(UNUSED_VARIABLE, message, site);
+            }
+        }
+
+        for child in &scope.children {
+            self.check_locals(scope, child);
+        }
+    }
+
+    fn warn(&mut self, code: u32, message: String, span: &Span) {
+        self.diagnostics.push(Diagnostic::new(
+            code,
+            Severity::Warning,
+            message,
+            Some(span.clone()),
+        ));
+    }
+}
+
+/// How to name the function whose scope is `function`, and its parameters, from
+/// `parent`, the scope declaring it. An anonymous function is declared nowhere, so
+/// its parameters are the variables declared at its own span.
+fn declaring(parent: &Scope, function: &Scope) -> (String, Vec<String>) {
+    let named = parent.table.iter().find_map(|(name, symbol)| match symbol {
+        Symbol::Function { parameters } if parent.sites[name] == function.span => {
+            Some((format!("'{}'", name), parameters.clone()))
+        }
+        _ => None,
+    });
+    named.unwrap_or_else(|| {
+        let mut parameters: Vec<String> = function
+            .table
+            .iter()
+            .filter(|(name, symbol)| {
+                matches!(symbol, Symbol::Variable) && function.sites[*name] == function.span
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        parameters.sort();
+        ("an anonymous function".to_string(), parameters)
+    })
+}
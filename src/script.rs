@@ -0,0 +1,777 @@
+use crate::codegen::ir_generator::ir_helpers::SCRIPT_GLOBAL_PREFIX;
+use crate::codegen::ir_generator::symbols::DEFAULT_SYMBOL_PREFIX;
+use crate::codegen::ir_generator::unroll::DEFAULT_MAX_UNROLLED_REPEAT;
+use crate::codegen::ir_generator::{IRGenError, IRGenerator};
+use crate::codegen::jit::JITExecutor;
+use crate::codegen::runtime::instances::Instance;
+use crate::codegen::runtime::profile::FunctionProfile;
+use crate::codegen::runtime::trace::TraceEntry;
+use crate::codegen::runtime::trap::RaisedError;
+use crate::parser::Span;
+use crate::parser::compile_limits::LimitExceeded;
+use crate::parser::expr::Expr;
+use crate::parser::language_options::LanguageOptions;
+use crate::parser::outline::OutlineItem;
+use crate::utils::diagnostic::Diagnostic;
+use constants::ConstantError;
+use inkwell::context::Context;
+use inkwell::module::Module;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use template::ScriptTemplate;
+
+pub mod cache;
+pub mod constants;
+pub mod debug;
+pub mod eval;
+pub mod state;
+pub mod template;
+
+/// A value passed between the host and a script
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Bool(bool),
+    String(String),
+    Null,
+}
+
+impl Value {
+    /// The kind of value this is, as error messages name it
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::String(_) => "string",
+            Value::Null => "null",
+        }
+    }
+
+    /// The number a script receives for this value: `true` is 1, `false` is 0 and
+    /// null is undefined (0). Functions and globals only hold numbers, so a string
+    /// has none.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            Value::Null => Some(0.0),
+            Value::String(_) => None,
+        }
+    }
+
+    /// The text of a string value, borrowed for as long as the value lives
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+/// Why a script failed to compile
+#[derive(Debug)]
+pub enum CompileError {
+    /// Syntax errors, with codes and spans
+    Parse(Vec<Diagnostic>),
+    /// The first error code generation ran into
+    Codegen(Diagnostic),
+    /// The code generated for a function is invalid; this is a compiler bug.
+    /// Names the GML function (`main` for top-level code), with its IR cut to a
+    /// few lines and where it is defined.
+    InvalidFunction {
+        function: String,
+        ir: String,
+        span: Option<Span>,
+    },
+    /// The generated module is invalid; this is a compiler bug
+    Verify(String),
+    /// The JIT could not be created
+    Jit(String),
+    /// Source bytes that are not UTF-8, at this offset
+    InvalidUtf8 { offset: usize },
+    /// The program is bigger than [`LanguageOptions::limits`] allow
+    Limit(LimitExceeded),
+    /// The program assigns to or declares a constant the host defined
+    Constant(ConstantError),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Parse(diagnostics) => {
+                let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            CompileError::Codegen(diagnostic) => write!(f, "{}", diagnostic),
+            CompileError::InvalidFunction { function, ir, .. } => write!(
+                f,
+                "Generated code for function '{}' failed verification:\n{}",
+                function, ir
+            ),
+            CompileError::Verify(message) => write!(f, "Module verification failed: {}", message),
+            CompileError::Jit(message) => write!(f, "{}", message),
+            CompileError::InvalidUtf8 { offset } => {
+                write!(f, "Source is not valid UTF-8 at byte {}", offset)
+            }
+            CompileError::Limit(exceeded) => write!(f, "Program too big: {}", exceeded),
+            CompileError::Constant(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// Why a call into a compiled script failed
+#[derive(Debug, PartialEq)]
+pub enum RuntimeError {
+    UnknownFunction(String),
+    UnknownGlobal(String),
+    /// More arguments than the function declares
+    TooManyArguments {
+        function: String,
+        expected: usize,
+        given: usize,
+    },
+    /// A value that cannot be passed into the script
+    UnsupportedValue(Value),
+    /// An argument of a type the function cannot take; `index` counts from 0
+    UnsupportedArgument {
+        function: String,
+        index: usize,
+        value: Value,
+    },
+    Execution(String),
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::UnknownFunction(name) => write!(f, "Unknown function '{}'", name),
+            RuntimeError::UnknownGlobal(name) => write!(f, "Unknown global '{}'", name),
+            RuntimeError::TooManyArguments {
+                function,
+                expected,
+                given,
+            } => write!(
+                f,
+                "Function '{}' takes {} argument(s) but {} were given",
+                function, expected, given
+            ),
+            RuntimeError::UnsupportedValue(value) => {
+                write!(f, "{:?} cannot be passed to a script", value)
+            }
+            RuntimeError::UnsupportedArgument {
+                function,
+                index,
+                value,
+            } => write!(
+                f,
+                "Argument {} of '{}' is a {}, but script functions only take numbers, bools and null",
+                index,
+                function,
+                value.type_name()
+            ),
+            RuntimeError::Execution(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// How [`Script::reload`] carried the globals over to the new code
+#[derive(Debug, Default, PartialEq)]
+pub struct ReloadReport {
+    /// Globals in both versions, which keep their values
+    pub kept: Vec<String>,
+    /// Globals only the new version declares, which start at 0
+    pub added: Vec<String>,
+    /// Globals only the old version declared, whose values are dropped
+    pub removed: Vec<String>,
+}
+
+/// A compiled script, ready to be called.
+///
+/// Top-level `var`s become globals that live as long as the `Script`: functions
+/// read and write them, and the host can through [`Script::set_global`] and
+/// [`Script::get_global`]. They start at 0 until [`Script::run_main`] or the first
+/// [`Script::call`] runs the top-level statements.
+///
+/// A script is used from the thread that compiled it only; it is neither `Send`
+/// nor `Sync`, so sharing it does not compile:
+///
+/// ```compile_fail
+/// let script = col::Script::compile("var x = 1;").unwrap();
+/// std::thread::spawn(move || script.run_main());
+/// ```
+///
+/// To run a script on several threads, compile one per thread from a
+/// [`ScriptTemplate`], see [`template`](crate::script::template).
+pub struct Script {
+    // Declared before `context` so it is dropped first: the JIT borrows the context
+    executor: JITExecutor<'static>,
+    /// Top-level functions and their arities
+    functions: HashMap<String, usize>,
+    /// Global names, in declaration order, indexing `global_values`
+    globals: Vec<String>,
+    /// Host-owned storage mapped into the JIT for each global
+    global_values: Box<[Cell<f64>]>,
+    outline: Vec<OutlineItem>,
+    /// What the source was parsed with, and [`Script::reload`] parses with
+    options: LanguageOptions,
+    /// Whether functions were instrumented for [`Script::profile`], and are again
+    /// on reload
+    profiling: bool,
+    /// The source statements were traced in, for placing [`Script::take_trace`]'s
+    /// entries; `None` unless the script was compiled traced, and traced again on
+    /// reload
+    traced_source: Option<String>,
+    /// Warnings code generation found
+    warnings: Vec<Diagnostic>,
+    /// Host constants the source was compiled with, and is again on reload
+    constants: HashMap<String, Value>,
+    _context: Box<Context>,
+}
+
+impl Script {
+    /// Parse and compile `source`
+    pub fn compile(source: &str) -> Result<Script, CompileError> {
+        Self::compile_with_options(source, &LanguageOptions::default())
+    }
+
+    /// Parse `source` accepting the syntax `options` enable, and compile it
+    pub fn compile_with_options(
+        source: &str,
+        options: &LanguageOptions,
+    ) -> Result<Script, CompileError> {
+        Self::compile_saving(source, options, &HashMap::new(), false, false, None)
+    }
+
+    /// Compile as [`Script::compile_with_options`] does, with `constants` defined:
+    /// the script reads each as a literal of its value, and branches that a
+    /// constant condition rules out are not compiled at all. Assigning to a
+    /// constant or declaring a variable, parameter or function of the same name is
+    /// a [`CompileError::Constant`]. See [`constants`](crate::script::constants).
+    pub fn compile_with_constants(
+        source: &str,
+        options: &LanguageOptions,
+        constants: &HashMap<String, Value>,
+    ) -> Result<Script, CompileError> {
+        Self::compile_saving(source, options, constants, false, false, None)
+    }
+
+    /// Compile as [`Script::compile_with_options`] does, with every function
+    /// counting its calls and the time they take, see [`Script::profile`]. Scripts
+    /// compiled without this carry no instrumentation at all.
+    pub fn compile_profiled(
+        source: &str,
+        options: &LanguageOptions,
+    ) -> Result<Script, CompileError> {
+        Self::compile_saving(source, options, &HashMap::new(), true, false, None)
+    }
+
+    /// Compile as [`Script::compile_with_options`] does, with every statement
+    /// recording that it ran, see [`Script::take_trace`]. Scripts compiled without
+    /// this carry no instrumentation at all.
+    pub fn compile_traced(source: &str, options: &LanguageOptions) -> Result<Script, CompileError> {
+        Self::compile_saving(source, options, &HashMap::new(), false, true, None)
+    }
+
+    /// Compile `source` with `constants` defined, instrumented for profiling if
+    /// `profiling` and for tracing if `tracing`, writing the generated module as
+    /// bitcode to `bitcode` if given. Failing to write it is not an error.
+    fn compile_saving(
+        source: &str,
+        options: &LanguageOptions,
+        constants: &HashMap<String, Value>,
+        profiling: bool,
+        tracing: bool,
+        bitcode: Option<&Path>,
+    ) -> Result<Script, CompileError> {
+        let template = ScriptTemplate::new(source, options, constants)?;
+        Self::compile_template(
+            &template,
+            profiling,
+            tracing.then_some(source),
+            bitcode,
+            None,
+        )
+    }
+
+    /// Generate code for the program `template` holds, as
+    /// [`Script::compile_saving`] does for source, traced if given the source the
+    /// template was parsed from. With a `string_result`, the script also gets a
+    /// [`STRING_RESULT_FUNCTION`](crate::codegen::ir_generator::STRING_RESULT_FUNCTION)
+    /// returning it.
+    fn compile_template(
+        template: &ScriptTemplate,
+        profiling: bool,
+        traced_source: Option<&str>,
+        bitcode: Option<&Path>,
+        string_result: Option<&Expr>,
+    ) -> Result<Script, CompileError> {
+        let ScriptTemplate {
+            program,
+            outline,
+            options,
+            constants,
+        } = template;
+        let functions = program
+            .functions()
+            .map(|f| (f.name.clone(), f.func.args.len()))
+            .collect();
+
+        let context = Box::new(Context::create());
+        // SAFETY: the context is boxed, so it does not move, and `Script` drops the
+        // executor, the only thing borrowing it, before the context.
+        let context_ref: &'static Context = unsafe { &*(context.as_ref() as *const Context) };
+
+        let mut ir_generator = IRGenerator::new(context_ref, "script");
+        ir_generator.persistent_globals = true;
+        ir_generator.epsilon_comparisons = true;
+        ir_generator.profiling = profiling;
+        ir_generator.tracing = traced_source.is_some();
+        ir_generator.div_by_zero = options.div_by_zero;
+        ir_generator.strict_math = options.strict_math;
+        ir_generator.strict_returns = options.strict_returns;
+        ir_generator.exact_integers = options.exact_integers;
+        ir_generator.max_call_depth = options.max_call_depth;
+        ir_generator.symbol_prefix = Some(DEFAULT_SYMBOL_PREFIX.to_string());
+        ir_generator.max_unrolled_repeat = DEFAULT_MAX_UNROLLED_REPEAT;
+        program
+            .accept(&mut ir_generator)
+            .and_then(|_| match string_result {
+                Some(expr) => ir_generator.gen_string_result(expr),
+                None => Ok(()),
+            })
+            .map_err(|e| match e {
+                IRGenError::InvalidFunction { function, ir, span } => {
+                    CompileError::InvalidFunction { function, ir, span }
+                }
+                e => CompileError::Codegen(Diagnostic::from(&e)),
+            })?;
+        let warnings = ir_generator.take_diagnostics();
+        let module = ir_generator.get_module();
+        module
+            .verify()
+            .map_err(|e| CompileError::Verify(e.to_string()))?;
+        if let Some(path) = bitcode {
+            module.write_bitcode_to_path(path);
+        }
+
+        let executor = JITExecutor::new(module).map_err(CompileError::Jit)?;
+        let mut script = Self::assemble(context, module, executor, functions, outline.clone());
+        script.options = options.clone();
+        script.profiling = profiling;
+        script.traced_source = traced_source.map(str::to_string);
+        script.warnings = warnings;
+        script.constants = constants.clone();
+        Ok(script)
+    }
+
+    /// Finish a script whose module was generated or loaded in `context` and handed
+    /// to `executor`. The executor is created by the caller, so that if that fails
+    /// the module is dropped before the context it lives in.
+    fn assemble(
+        context: Box<Context>,
+        module: &Module<'static>,
+        executor: JITExecutor<'static>,
+        functions: HashMap<String, usize>,
+        outline: Vec<OutlineItem>,
+    ) -> Script {
+        let globals: Vec<String> = module
+            .get_globals()
+            .filter_map(|g| {
+                let name = g.get_name().to_str().ok()?;
+                name.strip_prefix(SCRIPT_GLOBAL_PREFIX).map(str::to_string)
+            })
+            .collect();
+        let global_values: Box<[Cell<f64>]> = globals.iter().map(|_| Cell::new(0.0)).collect();
+
+        for (name, value) in globals.iter().zip(global_values.iter()) {
+            let global = module
+                .get_global(&format!("{}{}", SCRIPT_GLOBAL_PREFIX, name))
+                .unwrap();
+            executor
+                .get_execution_engine()
+                .add_global_mapping(&global, value.as_ptr() as usize);
+        }
+
+        Script {
+            executor,
+            functions,
+            globals,
+            global_values,
+            outline,
+            options: LanguageOptions::default(),
+            profiling: false,
+            traced_source: None,
+            warnings: Vec::new(),
+            constants: HashMap::new(),
+            _context: context,
+        }
+    }
+
+    /// Recompile the script from `source` and switch to the new code, e.g. after the
+    /// file was edited while the host runs.
+    ///
+    /// Globals are matched by name: those in both versions keep their current
+    /// values, new ones start at 0 as in a fresh compile, and removed ones are
+    /// dropped. Lists, maps, bound instances, the print callback and the math epsilon carry over. The top-level
+    /// statements are not run again. `source` is parsed with the options the script
+    /// was compiled with, and profiled and traced if it was; the new code's profile
+    /// and trace start empty. If it does not compile, the error is returned and the script keeps
+    /// running the old code.
+    pub fn reload(&mut self, source: &str) -> Result<ReloadReport, CompileError> {
+        let script = Script::compile_saving(
+            source,
+            &self.options,
+            &self.constants,
+            self.profiling,
+            self.traced_source.is_some(),
+            None,
+        )?;
+
+        let mut report = ReloadReport::default();
+        for (name, value) in script.globals.iter().zip(script.global_values.iter()) {
+            match self.global_index(name) {
+                Ok(index) => {
+                    value.set(self.global_values[index].get());
+                    report.kept.push(name.clone());
+                }
+                Err(_) => report.added.push(name.clone()),
+            }
+        }
+        report.removed = self
+            .globals
+            .iter()
+            .filter(|name| script.global_index(name).is_err())
+            .cloned()
+            .collect();
+        script.executor.adopt_runtime_state(&self.executor);
+
+        *self = script;
+        Ok(report)
+    }
+
+    /// Run the script's top-level statements unless they already ran, and return
+    /// their top-level `return` value. They run once per script, so this returns
+    /// the same value again without resetting any global.
+    pub fn run_main(&self) -> Result<Value, RuntimeError> {
+        self.executor
+            .execute_main()
+            .map(Value::Number)
+            .map_err(RuntimeError::Execution)
+    }
+
+    /// Call a top-level function. Missing arguments are passed as 0, and a function
+    /// can take up to [`MAX_HOST_ARGUMENTS`](crate::codegen::jit::MAX_HOST_ARGUMENTS).
+    /// The top-level statements run first unless [`Script::run_main`] or an earlier
+    /// call ran them.
+    pub fn call(&self, name: &str, args: &[Value]) -> Result<Value, RuntimeError> {
+        let arity = *self
+            .functions
+            .get(name)
+            .ok_or_else(|| RuntimeError::UnknownFunction(name.to_string()))?;
+        if args.len() > arity {
+            return Err(RuntimeError::TooManyArguments {
+                function: name.to_string(),
+                expected: arity,
+                given: args.len(),
+            });
+        }
+
+        let mut numbers = args
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| {
+                arg.as_number()
+                    .ok_or_else(|| RuntimeError::UnsupportedArgument {
+                        function: name.to_string(),
+                        index,
+                        value: arg.clone(),
+                    })
+            })
+            .collect::<Result<Vec<f64>, _>>()?;
+        numbers.resize(arity, 0.0);
+
+        self.executor
+            .execute_function(name, &numbers)
+            .map(Value::Number)
+            .map_err(RuntimeError::Execution)
+    }
+
+    /// Set a global declared at the script's top level. The top-level statements
+    /// run first unless they already did, so they cannot overwrite the value at the
+    /// next call, [`Script::run_main`] included.
+    pub fn set_global(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+        let index = self.global_index(name)?;
+        let number = value
+            .as_number()
+            .ok_or(RuntimeError::UnsupportedValue(value))?;
+        self.executor
+            .ensure_initialized()
+            .map_err(RuntimeError::Execution)?;
+        self.global_values[index].set(number);
+        Ok(())
+    }
+
+    /// Read the current value of a global declared at the script's top level
+    pub fn get_global(&self, name: &str) -> Result<Value, RuntimeError> {
+        let index = self.global_index(name)?;
+        Ok(Value::Number(self.global_values[index].get()))
+    }
+
+    /// Bind `self` or `other` to a host object with these fields, replacing any
+    /// object bound before. The script reads and assigns them as `self.x`, and
+    /// assigning a field the object lacks adds it. Fields hold numbers.
+    ///
+    /// Unlike [`Script::set_global`] this does not run the top-level statements:
+    /// bound before the first call, the object is there for them to read, and
+    /// fields they assign are assigned on it.
+    pub fn bind_instance(
+        &self,
+        instance: Instance,
+        fields: &[(&str, Value)],
+    ) -> Result<(), RuntimeError> {
+        let fields = fields
+            .iter()
+            .map(|(name, value)| {
+                let number = value
+                    .as_number()
+                    .ok_or_else(|| RuntimeError::UnsupportedValue(value.clone()))?;
+                Ok((name.to_string(), number))
+            })
+            .collect::<Result<HashMap<String, f64>, RuntimeError>>()?;
+        self.executor.instances().bind(instance, fields);
+        Ok(())
+    }
+
+    /// Unbind `self` or `other`, returning the fields the object had last
+    pub fn unbind_instance(&self, instance: Instance) -> Option<HashMap<String, Value>> {
+        let fields = self.executor.instances().unbind(instance)?;
+        Some(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name, Value::Number(value)))
+                .collect(),
+        )
+    }
+
+    /// Send the lines the script prints with `println` to `callback`, newline
+    /// included, instead of stdout
+    pub fn set_print_callback(&self, callback: impl FnMut(&str) + 'static) {
+        self.executor
+            .output()
+            .set_callback(Some(Box::new(callback)));
+    }
+
+    /// Print to stdout again, dropping the callback registered with
+    /// [`Script::set_print_callback`]
+    pub fn clear_print_callback(&self) {
+        self.executor.output().set_callback(None);
+    }
+
+    /// The current value of a field of the object bound to `self` or `other`,
+    /// including changes the script made
+    pub fn instance_field(&self, instance: Instance, name: &str) -> Option<Value> {
+        self.executor
+            .instances()
+            .field(instance, name)
+            .map(Value::Number)
+    }
+
+    /// Take the error recorded by the last ds_list or ds_map operation or field
+    /// access that failed, such as using a destroyed handle or reading `self.x`
+    /// with nothing bound to `self`. The call itself still succeeds: the failed
+    /// operation returns undefined (0) to the script. The error is of kind
+    /// [`ErrorKind::InvalidHandle`](crate::ErrorKind::InvalidHandle) or
+    /// [`ErrorKind::InvalidField`](crate::ErrorKind::InvalidField), and has no
+    /// function, span or call stack.
+    pub fn take_runtime_error(&self) -> Option<RaisedError> {
+        self.executor.take_runtime_error()
+    }
+
+    /// The error that stopped the last call into the script, such as a division by
+    /// zero under [`DivByZeroPolicy::Error`](crate::parser::language_options::DivByZeroPolicy::Error):
+    /// its kind, where it was raised and the calls it ended. The call's
+    /// [`RuntimeError::Execution`] holds its message. `None` if the last call raised
+    /// nothing.
+    pub fn last_runtime_error(&self) -> Option<RaisedError> {
+        self.executor.last_error()
+    }
+
+    /// How many ds_lists and ds_maps the script created and has not destroyed.
+    /// Handles are references: assigning one copies the handle, not the
+    /// collection, so a collection lives until `ds_list_destroy` or
+    /// `ds_map_destroy`, or until the script is dropped. `ds_list_copy` and
+    /// `ds_map_copy` copy the contents into another collection.
+    pub fn live_collections(&self) -> usize {
+        self.executor.collections().live()
+    }
+
+    /// The epsilon within which the script's `==`, `!=`, `<=` and `>=` consider
+    /// numbers equal. Starts at 0, which compares exactly.
+    pub fn math_epsilon(&self) -> f64 {
+        self.executor.math_epsilon()
+    }
+
+    /// Set the epsilon within which numbers compare equal, as the script's own
+    /// `math_set_epsilon` does; GameMaker's default is 0.00001
+    pub fn set_math_epsilon(&self, epsilon: f64) {
+        self.executor.set_math_epsilon(epsilon.max(0.0));
+    }
+
+    /// Where the script's functions, loops, `if`s and blocks are in its source,
+    /// see [`outline`](crate::parser::outline)
+    pub fn outline(&self) -> Vec<OutlineItem> {
+        self.outline.clone()
+    }
+
+    /// Calls and time per function since the script was compiled, most time first.
+    /// Empty unless it was compiled with [`Script::compile_profiled`].
+    pub fn profile(&self) -> Vec<FunctionProfile> {
+        self.executor.profile().functions()
+    }
+
+    /// Take the statements the script ran most recently, oldest first, whether or
+    /// not the call running them failed: the last 1024 unless
+    /// [`Script::set_trace_capacity`] says otherwise. A statement is recorded as it
+    /// starts, so after an error the last entry is the statement that raised it.
+    /// Empty unless it was compiled with [`Script::compile_traced`].
+    pub fn take_trace(&self) -> Vec<TraceEntry> {
+        match &self.traced_source {
+            Some(source) => self.executor.trace().take(source),
+            None => vec![],
+        }
+    }
+
+    /// Keep the last `capacity` statements run for [`Script::take_trace`]
+    pub fn set_trace_capacity(&self, capacity: usize) {
+        self.executor.trace().set_capacity(capacity);
+    }
+
+    /// Warnings found while compiling the script, such as code that can never run
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Names of the script's globals, in declaration order
+    pub fn globals(&self) -> impl Iterator<Item = &str> {
+        self.globals.iter().map(String::as_str)
+    }
+
+    fn global_index(&self, name: &str) -> Result<usize, RuntimeError> {
+        self.globals
+            .iter()
+            .position(|g| g == name)
+            .ok_or_else(|| RuntimeError::UnknownGlobal(name.to_string()))
+    }
+}
+
+/// Collects a script's source in chunks, for hosts that receive it piecemeal,
+/// and compiles it once complete. Dropping the session abandons the compile.
+#[derive(Debug, Default)]
+pub struct CompileSession {
+    source: String,
+    /// Start of a character split across the last chunk boundary
+    pending: Vec<u8>,
+    /// Bytes appended so far
+    received: usize,
+    options: LanguageOptions,
+    constants: HashMap<String, Value>,
+}
+
+impl CompileSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A session that compiles as [`Script::compile_with_constants`] does, with
+    /// `options` and `constants`
+    pub fn with_options(options: &LanguageOptions, constants: &HashMap<String, Value>) -> Self {
+        Self {
+            options: options.clone(),
+            constants: constants.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Append the next chunk of UTF-8 source. A character may be split between two
+    /// chunks. After an error the session should be dropped.
+    pub fn append(&mut self, mut chunk: &[u8]) -> Result<(), CompileError> {
+        if !self.pending.is_empty() {
+            // Complete the split character with the first bytes of this chunk
+            let split = self.pending.len();
+            let take = chunk.len().min(4 - split);
+            self.pending.extend_from_slice(&chunk[..take]);
+            let complete = match std::str::from_utf8(&self.pending) {
+                Ok(_) => self.pending.len(),
+                Err(e) if e.valid_up_to() > 0 => e.valid_up_to(),
+                Err(e) if e.error_len().is_none() => {
+                    // Still not the whole character
+                    self.received += chunk.len();
+                    return Ok(());
+                }
+                Err(_) => {
+                    return Err(CompileError::InvalidUtf8 {
+                        offset: self.received - split,
+                    });
+                }
+            };
+            let character = std::str::from_utf8(&self.pending[..complete]).unwrap();
+            self.source.push_str(character);
+            self.pending.clear();
+            self.received += complete - split;
+            chunk = &chunk[complete - split..];
+        }
+
+        match std::str::from_utf8(chunk) {
+            Ok(text) => self.source.push_str(text),
+            Err(e) => {
+                let valid = e.valid_up_to();
+                if e.error_len().is_some() {
+                    return Err(CompileError::InvalidUtf8 {
+                        offset: self.received + valid,
+                    });
+                }
+                self.source
+                    .push_str(std::str::from_utf8(&chunk[..valid]).unwrap());
+                self.pending.extend_from_slice(&chunk[valid..]);
+            }
+        }
+        self.received += chunk.len();
+        Ok(())
+    }
+
+    /// Compile the source appended so far, with the session's options and constants
+    pub fn finish(self) -> Result<Script, CompileError> {
+        if !self.pending.is_empty() {
+            return Err(CompileError::InvalidUtf8 {
+                offset: self.received - self.pending.len(),
+            });
+        }
+        Script::compile_with_constants(&self.source, &self.options, &self.constants)
+    }
+}
@@ -0,0 +1,276 @@
+//! An on-disk cache of compiled scripts, so a host loading the same scripts on
+//! every launch only compiles the ones that changed.
+//!
+//! Entries are keyed by a hash of the source together with the language options,
+//! the host constants, the crate version and the optimization level. Each is two files: the generated module as LLVM bitcode
+//! and a text file with what the script needs besides its code, its functions,
+//! outline and warnings. A hit loads the bitcode, skipping lexing, parsing and code generation.
+//! An entry that cannot be read, or was written for other source or by another
+//! version, is treated as a miss and overwritten.
+
+use super::{CompileError, Script, Value};
+use crate::codegen::jit::JITExecutor;
+use crate::parser::language_options::LanguageOptions;
+use crate::parser::outline::{OutlineItem, OutlineKind};
+use crate::utils::diagnostic::{Diagnostic, Severity};
+use inkwell::context::Context;
+use inkwell::module::Module;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// First line of every entry's metadata
+const HEADER: &str = "col-script-cache 3";
+
+/// Scripts are always JIT compiled without optimization
+const OPT_LEVEL: &str = "O0";
+
+/// Where and whether [`ScriptCache`] keeps compiled scripts
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Directory holding the entries, created on first use
+    pub directory: PathBuf,
+    /// When off, every script is compiled and nothing is read or written
+    pub enabled: bool,
+}
+
+/// Compiles scripts through an on-disk cache, counting hits and misses
+#[derive(Debug)]
+pub struct ScriptCache {
+    config: CacheConfig,
+    hits: Cell<usize>,
+    misses: Cell<usize>,
+}
+
+impl ScriptCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        }
+    }
+
+    /// Compile `source` as [`Script::compile`] does, loading it from the cache if
+    /// it was compiled before and storing it otherwise. Failing to store an entry
+    /// is not an error; the script is still returned.
+    pub fn compile(&self, source: &str) -> Result<Script, CompileError> {
+        self.compile_with_constants(source, &LanguageOptions::default(), &HashMap::new())
+    }
+
+    /// [`ScriptCache::compile`] as [`Script::compile_with_constants`] compiles. A
+    /// script compiled with other options or constants has an entry of its own.
+    pub fn compile_with_constants(
+        &self,
+        source: &str,
+        options: &LanguageOptions,
+        constants: &HashMap<String, Value>,
+    ) -> Result<Script, CompileError> {
+        if !self.config.enabled {
+            return Script::compile_with_constants(source, options, constants);
+        }
+
+        let key = cache_key(source, options, constants);
+        let bitcode = self.config.directory.join(format!("{}.bc", key));
+        let metadata = self.config.directory.join(format!("{}.meta", key));
+        if let Some(mut script) = load(source, &bitcode, &metadata) {
+            self.hits.set(self.hits.get() + 1);
+            script.options = options.clone();
+            script.constants = constants.clone();
+            return Ok(script);
+        }
+
+        self.misses.set(self.misses.get() + 1);
+        let _ = fs::create_dir_all(&self.config.directory);
+        let script =
+            Script::compile_saving(source, options, constants, false, false, Some(&bitcode))?;
+        let _ = fs::write(&metadata, write_metadata(source, &script));
+        Ok(script)
+    }
+
+    /// Scripts loaded from the cache so far
+    pub fn hits(&self) -> usize {
+        self.hits.get()
+    }
+
+    /// Scripts compiled, and stored, because the cache had no usable entry
+    pub fn misses(&self) -> usize {
+        self.misses.get()
+    }
+}
+
+fn cache_key(
+    source: &str,
+    options: &LanguageOptions,
+    constants: &HashMap<String, Value>,
+) -> String {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    OPT_LEVEL.hash(&mut hasher);
+    source.hash(&mut hasher);
+    options.hash(&mut hasher);
+    // By name, as a map has no fixed order
+    let mut constants: Vec<_> = constants.iter().collect();
+    constants.sort_by_key(|(name, _)| *name);
+    for (name, value) in constants {
+        name.hash(&mut hasher);
+        // Numbers are floats, which do not hash; their debug form tells them apart
+        format!("{:?}", value).hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// The script stored for `source`, if the entry is complete and matches it
+fn load(source: &str, bitcode: &Path, metadata: &Path) -> Option<Script> {
+    let text = fs::read_to_string(metadata).ok()?;
+    let (functions, outline, warnings) = read_metadata(source, &text)?;
+    if !bitcode.is_file() {
+        return None;
+    }
+
+    let context = Box::new(Context::create());
+    // SAFETY: as in `Script::compile_saving`
+    let context_ref: &'static Context = unsafe { &*(context.as_ref() as *const Context) };
+    let module = Module::parse_bitcode_from_path(bitcode, context_ref).ok()?;
+    module.verify().ok()?;
+    let executor = JITExecutor::new(&module).ok()?;
+    let mut script = Script::assemble(context, &module, executor, functions, outline);
+    script.warnings = warnings;
+    Some(script)
+}
+
+fn write_metadata(source: &str, script: &Script) -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "{}", HEADER);
+    let _ = writeln!(text, "version {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(text, "opt {}", OPT_LEVEL);
+    let _ = writeln!(text, "source {}", source.len());
+    for (name, arity) in &script.functions {
+        let _ = writeln!(text, "function {} {}", name, arity);
+    }
+    for item in &script.outline {
+        let parent = item.parent.map_or("-".to_string(), |p| p.to_string());
+        let _ = writeln!(
+            text,
+            "outline {} {} {} {} {} {} {}",
+            kind_name(item.kind),
+            parent,
+            item.start_line,
+            item.start_col,
+            item.end_line,
+            item.end_col,
+            item.name.as_deref().unwrap_or("-"),
+        );
+    }
+    for warning in &script.warnings {
+        let span = warning.span.as_ref().map_or("-".to_string(), |span| {
+            format!("{}..{}", span.start, span.end)
+        });
+        let _ = writeln!(
+            text,
+            "warning {} {} {}",
+            warning.code, span, warning.message
+        );
+    }
+    text
+}
+
+type Metadata = (HashMap<String, usize>, Vec<OutlineItem>, Vec<Diagnostic>);
+
+/// The functions, outline and warnings in `text`, if it was written for `source` by
+/// this version
+fn read_metadata(source: &str, text: &str) -> Option<Metadata> {
+    let mut lines = text.lines();
+    let expected = [
+        HEADER.to_string(),
+        format!("version {}", env!("CARGO_PKG_VERSION")),
+        format!("opt {}", OPT_LEVEL),
+        format!("source {}", source.len()),
+    ];
+    for line in expected {
+        if lines.next()? != line {
+            return None;
+        }
+    }
+
+    let mut functions = HashMap::new();
+    let mut outline = Vec::new();
+    let mut warnings = Vec::new();
+    for line in lines {
+        if let Some(warning) = line.strip_prefix("warning ") {
+            warnings.push(read_warning(warning)?);
+            continue;
+        }
+        let fields: Vec<&str> = line.split(' ').collect();
+        match fields.as_slice() {
+            ["function", name, arity] => {
+                functions.insert(name.to_string(), arity.parse().ok()?);
+            }
+            [
+                "outline",
+                kind,
+                parent,
+                start_line,
+                start_col,
+                end_line,
+                end_col,
+                name,
+            ] => {
+                outline.push(OutlineItem {
+                    kind: parse_kind(kind)?,
+                    name: (*name != "-").then(|| name.to_string()),
+                    parent: match *parent {
+                        "-" => None,
+                        parent => Some(parent.parse().ok()?),
+                    },
+                    start_line: start_line.parse().ok()?,
+                    start_col: start_col.parse().ok()?,
+                    end_line: end_line.parse().ok()?,
+                    end_col: end_col.parse().ok()?,
+                });
+            }
+            _ => return None,
+        }
+    }
+    Some((functions, outline, warnings))
+}
+
+/// A warning written as `code start..end message`, with `-` for no span
+fn read_warning(text: &str) -> Option<Diagnostic> {
+    let mut fields = text.splitn(3, ' ');
+    let code = fields.next()?.parse().ok()?;
+    let span = match fields.next()? {
+        "-" => None,
+        span => {
+            let (start, end) = span.split_once("..")?;
+            Some(start.parse().ok()?..end.parse().ok()?)
+        }
+    };
+    let message = fields.next()?.to_string();
+    Some(Diagnostic::new(code, Severity::Warning, message, span))
+}
+
+fn kind_name(kind: OutlineKind) -> &'static str {
+    match kind {
+        OutlineKind::Function => "function",
+        OutlineKind::Block => "block",
+        OutlineKind::Loop => "loop",
+        OutlineKind::If => "if",
+        OutlineKind::Switch => "switch",
+    }
+}
+
+fn parse_kind(name: &str) -> Option<OutlineKind> {
+    match name {
+        "function" => Some(OutlineKind::Function),
+        "block" => Some(OutlineKind::Block),
+        "loop" => Some(OutlineKind::Loop),
+        "if" => Some(OutlineKind::If),
+        "switch" => Some(OutlineKind::Switch),
+        _ => None,
+    }
+}
@@ -0,0 +1,216 @@
+//! Constants a host defines for a script at compile time, such as `PLATFORM = 2` or
+//! `DEBUG = false`, see [`Script::compile_with_constants`].
+//!
+//! Every read of a constant is replaced by its value as a literal before code is
+//! generated, so `if (DEBUG)` becomes `if (false)` and its dead branch is pruned as
+//! [`dead_branches`](crate::parser::dead_branches) prunes literal conditions. A
+//! name the host does not define is left to resolve as a variable, as before.
+
+use super::{Script, Value};
+use crate::parser::Span;
+use crate::parser::expr::Expr;
+use crate::parser::func_def::FuncDef;
+use crate::parser::inliner::children_mut;
+use crate::parser::program::Program;
+use crate::parser::stmt::Stmt;
+use crate::parser::top_level::TopLevel;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A script using a host constant as if it were a variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstantError {
+    /// The script assigns to the constant, or increments or decrements it
+    Assigned { name: String, span: Span },
+    /// The script declares a variable, parameter or function with the constant's
+    /// name; `span` is the declaration's name
+    Redeclared { name: String, span: Span },
+}
+
+impl fmt::Display for ConstantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstantError::Assigned { name, span } => write!(
+                f,
+                "Cannot assign to '{}' at {}..{}: it is a constant defined by the host",
+                name, span.start, span.end
+            ),
+            ConstantError::Redeclared { name, span } => write!(
+                f,
+                "'{}' at {}..{} is already a constant defined by the host",
+                name, span.start, span.end
+            ),
+        }
+    }
+}
+
+/// Replace every read of a constant in `program` with its value, returning how
+/// many were replaced. Fails at the first assignment to a constant or declaration
+/// of one, in source order.
+pub(crate) fn substitute_constants(
+    program: &mut Program,
+    constants: &HashMap<String, Value>,
+) -> Result<usize, ConstantError> {
+    let mut substitution = Substitution {
+        constants,
+        replaced: 0,
+    };
+    for top_level in &mut program.body {
+        match top_level {
+            TopLevel::Statement(stmt) => substitution.stmt(stmt)?,
+            TopLevel::Function(func_def) => substitution.func_def(func_def)?,
+            TopLevel::Enum(enum_def) => {
+                for value in enum_def.members.iter_mut().filter_map(|m| m.value.as_mut()) {
+                    substitution.expr(value)?;
+                }
+            }
+            TopLevel::Include(..) | TopLevel::Error(_) => {}
+        }
+    }
+    Ok(substitution.replaced)
+}
+
+/// The literal a constant's value is written as, at `span`
+fn literal(value: &Value, span: Span) -> Expr {
+    match value {
+        Value::Number(n) => Expr::Number(*n, span),
+        Value::Bool(true) => Expr::True(true, span),
+        Value::Bool(false) => Expr::False(false, span),
+        Value::String(s) => Expr::String(s.clone(), span),
+        Value::Null => Expr::Null(span),
+    }
+}
+
+struct Substitution<'a> {
+    constants: &'a HashMap<String, Value>,
+    replaced: usize,
+}
+
+impl Substitution<'_> {
+    fn check_declared(&self, name: &str, span: &Span) -> Result<(), ConstantError> {
+        if self.constants.contains_key(name) {
+            return Err(ConstantError::Redeclared {
+                name: name.to_string(),
+                span: span.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn func_def(&mut self, func_def: &mut FuncDef) -> Result<(), ConstantError> {
+        self.check_declared(&func_def.name, &func_def.span)?;
+        for param in &func_def.func.args {
+            // Parameters have no span of their own
+            self.check_declared(param, &func_def.span)?;
+        }
+        for stmt in &mut func_def.func.body {
+            self.stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &mut Stmt) -> Result<(), ConstantError> {
+        match stmt {
+            Stmt::Expr(expr) | Stmt::Return(Some(expr)) => self.expr(expr)?,
+            Stmt::Var(vars) => {
+                for (name, init, span) in vars {
+                    if let Some(init) = init {
+                        self.expr(init)?;
+                    }
+                    self.check_declared(name, span)?;
+                }
+            }
+            Stmt::If(cond, then_stmt, else_stmt, _) => {
+                self.expr(cond)?;
+                self.stmt(then_stmt)?;
+                if let Some(else_stmt) = else_stmt {
+                    self.stmt(else_stmt)?;
+                }
+            }
+            Stmt::Block(stmts, _) => {
+                for stmt in stmts {
+                    self.stmt(stmt)?;
+                }
+            }
+            Stmt::Repeat(cond, body, _) | Stmt::While(cond, body, _) => {
+                self.expr(cond)?;
+                self.stmt(body)?;
+            }
+            Stmt::DoUntil(body, cond, _) => {
+                self.stmt(body)?;
+                self.expr(cond)?;
+            }
+            Stmt::For(init, cond, update, body, _) => {
+                if let Some(init) = init {
+                    self.stmt(init)?;
+                }
+                if let Some(cond) = cond {
+                    self.expr(cond)?;
+                }
+                if let Some(update) = update {
+                    self.stmt(update)?;
+                }
+                self.stmt(body)?;
+            }
+            Stmt::Switch(value, cases, _) => {
+                self.expr(value)?;
+                for case in cases {
+                    for label in &mut case.labels {
+                        self.expr(label)?;
+                    }
+                    for stmt in &mut case.body {
+                        self.stmt(stmt)?;
+                    }
+                }
+            }
+            Stmt::Function(func_def) => self.func_def(func_def)?,
+            Stmt::Return(None) | Stmt::Break | Stmt::Continue | Stmt::Error(_) => {}
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &mut Expr) -> Result<(), ConstantError> {
+        let target = match &*expr {
+            Expr::Equal(target, _)
+            | Expr::PlusEqual(target, _)
+            | Expr::MinusEqual(target, _)
+            | Expr::StarEqual(target, _)
+            | Expr::SlashEqual(target, _)
+            | Expr::PercentEqual(target, _)
+            | Expr::PreIncrement(target)
+            | Expr::PostIncrement(target)
+            | Expr::PreDecrement(target)
+            | Expr::PostDecrement(target) => Some(&**target),
+            _ => None,
+        };
+        match target {
+            Some(Expr::Identifier(name, span)) if self.constants.contains_key(name) => {
+                return Err(ConstantError::Assigned {
+                    name: name.clone(),
+                    span: span.clone(),
+                });
+            }
+            _ => {}
+        }
+
+        if let Expr::Identifier(name, span) = &*expr {
+            if let Some(value) = self.constants.get(name) {
+                *expr = literal(value, span.clone());
+                self.replaced += 1;
+            }
+            return Ok(());
+        }
+        for child in children_mut(expr) {
+            self.expr(child)?;
+        }
+        Ok(())
+    }
+}
+
+impl Script {
+    /// The constants the script was compiled with, see
+    /// [`Script::compile_with_constants`]
+    pub fn constants(&self) -> &HashMap<String, Value> {
+        &self.constants
+    }
+}
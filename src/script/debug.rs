@@ -0,0 +1,585 @@
+//! Running a program a statement at a time, for debuggers.
+//!
+//! Compiled scripts run natively and cannot stop between statements, so a
+//! [`DebugSession`] runs the program on an interpreter of its own instead: the
+//! program is [lowered](lower) to instructions, and calls push frames onto a stack
+//! the session keeps, so stepping through deep recursion never recurses in Rust.
+//! Between steps the variables of the running function and the globals can be read.
+//!
+//! The session follows [`Script`](super::Script): variables the top-level code
+//! declares are globals, which functions read and assign unless they have a
+//! local of the same name. Values convert, divide and compare as compiled code
+//! does under the same [`LanguageOptions`], through the runtime's own helpers, and
+//! `println` goes to the same kind of [`Output`]. Only some builtins are available,
+//! see [`DebugSession::step`]; instances, accessors and collections are reported
+//! when the session is created.
+
+mod lower;
+
+use crate::codegen::ir_generator::IRGenError;
+use crate::codegen::ir_generator::builtins::builtin_argument_error;
+use crate::codegen::ir_generator::division::Division;
+use crate::codegen::ir_generator::ir_helpers::STRING_CONDITION;
+use crate::codegen::ir_generator::math_epsilon::compare_within;
+use crate::codegen::ir_generator::nan::nan_comparison_message;
+use crate::codegen::ir_generator::visit_expr::BinaryOp as CompareOp;
+use crate::codegen::runtime;
+use crate::codegen::runtime::output::Output;
+use crate::parser::Span;
+use crate::parser::language_options::LanguageOptions;
+use crate::parser::program::Program;
+use crate::script::{CompileError, RuntimeError, Value};
+use crate::utils::diagnostic::Diagnostic;
+use crate::utils::number_format::format_number;
+use lower::{BinaryOp, Chunk, Lowering, Op, UnaryOp};
+use std::collections::{BTreeSet, HashMap};
+
+/// Where a [`DebugSession`] stopped after a step
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepEvent {
+    /// Paused before the statement at `span`, on the 1-based `line`. Loops pause
+    /// at their own statement once per iteration: `while`, `for` and `repeat`
+    /// before checking whether to run their body, `do` before running it.
+    Statement { span: Span, line: usize },
+    /// Entered the function, before its first statement
+    FunctionEntered { name: String },
+    /// Returned `value` from the function, back in its caller
+    FunctionExited { name: String, value: Value },
+    /// The top-level code returned `value`; stepping further gives this again
+    Finished(Value),
+}
+
+/// A call being run
+struct Frame {
+    /// Index of the function's chunk, 0 for the top-level code
+    chunk: usize,
+    pc: usize,
+    /// Variables in the order they were declared
+    locals: Vec<(String, Value)>,
+    /// Height of the value stack when the call started
+    base: usize,
+}
+
+/// A program run one statement at a time
+pub struct DebugSession {
+    chunks: Vec<Chunk>,
+    functions: HashMap<String, usize>,
+    frames: Vec<Frame>,
+    stack: Vec<Value>,
+    breakpoints: BTreeSet<usize>,
+    finished: Option<Value>,
+    options: LanguageOptions,
+    /// What `math_set_epsilon` last set, 0 at first as in a script
+    math_epsilon: f64,
+    output: Output,
+}
+
+/// Whether `value` is true as a condition, as generated code decides. Code
+/// generation rejects strings as conditions; the session only finds them when it
+/// reaches one.
+fn truthy(value: &Value) -> Result<bool, RuntimeError> {
+    match value {
+        Value::Number(n) => Ok(runtime::number_is_true(*n)),
+        Value::Bool(b) => Ok(*b),
+        Value::Null => Ok(false),
+        Value::String(_) => Err(RuntimeError::Execution(STRING_CONDITION.to_string())),
+    }
+}
+
+fn number(value: &Value, operation: &str) -> Result<f64, RuntimeError> {
+    value
+        .as_number()
+        .ok_or_else(|| RuntimeError::Execution(format!("Cannot use a string in '{}'", operation)))
+}
+
+/// `value` converted as by `string()`
+fn text(value: &Value) -> String {
+    match value {
+        Value::Number(n) => format_number(*n),
+        Value::Bool(b) => runtime::bool_text(*b).to_string(),
+        Value::String(s) => s.clone(),
+        Value::Null => runtime::UNDEFINED_TEXT.to_string(),
+    }
+}
+
+/// `value` converted as by `real()`
+fn real(value: &Value) -> f64 {
+    match value {
+        Value::String(s) => runtime::parse_number(s),
+        _ => value.as_number().unwrap_or(0.0),
+    }
+}
+
+fn unary(op: UnaryOp, value: &Value) -> Result<Value, RuntimeError> {
+    Ok(match op {
+        UnaryOp::Not => Value::Bool(!truthy(value)?),
+        UnaryOp::Negative => Value::Number(-number(value, "-")?),
+        UnaryOp::Positive => Value::Number(number(value, "+")?),
+        UnaryOp::BitNot => Value::Number(!(number(value, "~")? as i32) as f64),
+    })
+}
+
+fn symbol(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Subtract => "-",
+        BinaryOp::Multiply => "*",
+        BinaryOp::Divide => "/",
+        BinaryOp::IntDivide => "div",
+        BinaryOp::Remainder => "%",
+        BinaryOp::Equal => "==",
+        BinaryOp::NotEqual => "!=",
+        BinaryOp::Less => "<",
+        BinaryOp::LessEqual => "<=",
+        BinaryOp::Greater => ">",
+        BinaryOp::GreaterEqual => ">=",
+        BinaryOp::BitAnd => "&",
+        BinaryOp::BitOr => "|",
+        BinaryOp::BitXor => "^",
+        BinaryOp::ShiftLeft => "<<",
+        BinaryOp::ShiftRight => ">>",
+        BinaryOp::Xor => "^^",
+    }
+}
+
+/// The comparison generated code makes for `op`, if it is one
+fn comparison(op: BinaryOp) -> Option<CompareOp> {
+    Some(match op {
+        BinaryOp::Equal => CompareOp::Eq,
+        BinaryOp::NotEqual => CompareOp::Ne,
+        BinaryOp::Less => CompareOp::Lt,
+        BinaryOp::LessEqual => CompareOp::Le,
+        BinaryOp::Greater => CompareOp::Gt,
+        BinaryOp::GreaterEqual => CompareOp::Ge,
+        _ => return None,
+    })
+}
+
+impl DebugSession {
+    /// Prepare `program`, parsed from `source`, to run from its first statement.
+    /// Code the session cannot run, such as instance fields, is reported here
+    /// rather than when it is reached.
+    pub fn new(program: &Program, source: &str) -> Result<Self, CompileError> {
+        Self::new_with_options(program, source, &LanguageOptions::default())
+    }
+
+    /// Like [`DebugSession::new`], running the program as a script compiled with
+    /// `options` runs
+    pub fn new_with_options(
+        program: &Program,
+        source: &str,
+        options: &LanguageOptions,
+    ) -> Result<Self, CompileError> {
+        let (chunks, functions) = Lowering::lower(program, source)
+            .map_err(|e: IRGenError| CompileError::Codegen(Diagnostic::from(&e)))?;
+        Ok(Self {
+            chunks,
+            functions,
+            frames: vec![Frame {
+                chunk: 0,
+                pc: 0,
+                locals: Vec::new(),
+                base: 0,
+            }],
+            stack: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            finished: None,
+            options: options.clone(),
+            math_epsilon: 0.0,
+            output: Output::default(),
+        })
+    }
+
+    /// Send the lines the program prints with `println` to `callback`, newline
+    /// included, instead of stdout
+    pub fn set_print_callback(&self, callback: impl FnMut(&str) + 'static) {
+        self.output.set_callback(Some(Box::new(callback)));
+    }
+
+    /// Print to stdout again, dropping the callback registered with
+    /// [`DebugSession::set_print_callback`]
+    pub fn clear_print_callback(&self) {
+        self.output.set_callback(None);
+    }
+
+    /// Stop [`Self::run_to_breakpoint`] before statements on the 1-based `line`
+    pub fn set_breakpoint(&mut self, line: usize) {
+        self.breakpoints.insert(line);
+    }
+
+    pub fn clear_breakpoint(&mut self, line: usize) {
+        self.breakpoints.remove(&line);
+    }
+
+    /// The variables of the running function, in the order they were declared;
+    /// in the top-level code, the globals
+    pub fn locals(&self) -> Vec<(String, Value)> {
+        self.frames
+            .last()
+            .map(|frame| frame.locals.clone())
+            .unwrap_or_default()
+    }
+
+    /// The variables the top-level code declared, in the order it did
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.frames
+            .first()
+            .map(|frame| frame.locals.clone())
+            .unwrap_or_default()
+    }
+
+    /// The names of the functions being run, outermost first; the top-level code
+    /// is `main`
+    pub fn call_stack(&self) -> Vec<&str> {
+        self.frames
+            .iter()
+            .map(|frame| self.chunks[frame.chunk].name.as_str())
+            .collect()
+    }
+
+    /// Step until the next statement, call or return, or the end. Besides the
+    /// script's own functions, calls may use the builtins `string`, `real`, `bool`,
+    /// `string_format`, `string_length`, `string_char_at`, `string_copy`,
+    /// `string_pos`, `math_set_epsilon`, `math_get_epsilon`, `is_nan`,
+    /// `is_infinity` and `println`. An error ends the session, which then reports
+    /// it as finished with null.
+    pub fn step(&mut self) -> Result<StepEvent, RuntimeError> {
+        if let Some(value) = &self.finished {
+            return Ok(StepEvent::Finished(value.clone()));
+        }
+        let result = self.run_step();
+        if result.is_err() {
+            self.frames.clear();
+            self.stack.clear();
+            self.finished = Some(Value::Null);
+        }
+        result
+    }
+
+    /// Step until paused before a statement on a line with a breakpoint, or until
+    /// the end
+    pub fn run_to_breakpoint(&mut self) -> Result<StepEvent, RuntimeError> {
+        loop {
+            let event = self.step()?;
+            match &event {
+                StepEvent::Statement { line, .. } if self.breakpoints.contains(line) => {
+                    return Ok(event);
+                }
+                StepEvent::Finished(_) => return Ok(event),
+                _ => {}
+            }
+        }
+    }
+
+    /// `lhs op rhs` at `span` in the running function
+    fn binary(
+        &self,
+        op: BinaryOp,
+        lhs: &Value,
+        rhs: &Value,
+        span: Option<&Span>,
+    ) -> Result<Value, RuntimeError> {
+        match (op, lhs, rhs) {
+            (BinaryOp::Add, Value::String(l), Value::String(r)) => {
+                return Ok(Value::String(format!("{}{}", l, r)));
+            }
+            (BinaryOp::Equal, Value::String(l), Value::String(r)) => {
+                return Ok(Value::Bool(l == r));
+            }
+            (BinaryOp::NotEqual, Value::String(l), Value::String(r)) => {
+                return Ok(Value::Bool(l != r));
+            }
+            (BinaryOp::Xor, ..) => return Ok(Value::Bool(truthy(lhs)? != truthy(rhs)?)),
+            _ => {}
+        }
+        let (l, r) = (number(lhs, symbol(op))?, number(rhs, symbol(op))?);
+
+        if let Some(compare) = comparison(op) {
+            // Only number operands are checked, as in compiled code
+            let nan = [lhs, rhs]
+                .iter()
+                .any(|value| matches!(value, Value::Number(n) if n.is_nan()));
+            if self.options.strict_math && nan {
+                let function = self
+                    .frames
+                    .last()
+                    .map_or("main", |frame| self.chunks[frame.chunk].name.as_str());
+                return Err(RuntimeError::Execution(nan_comparison_message(
+                    function, span,
+                )));
+            }
+            // Scripts compare within the epsilon, which is 0 until they set one
+            return Ok(Value::Bool(match compare {
+                CompareOp::Lt => l < r,
+                CompareOp::Gt => l > r,
+                _ => compare_within(compare, l, r, self.math_epsilon),
+            }));
+        }
+
+        let division = match op {
+            BinaryOp::Divide => Some(Division::Quotient),
+            BinaryOp::IntDivide => Some(Division::Truncated),
+            BinaryOp::Remainder => Some(Division::Remainder),
+            _ => None,
+        };
+        if let Some(division) = division {
+            return division
+                .evaluate(l, r, self.options.div_by_zero)
+                .map(Value::Number)
+                .ok_or_else(|| RuntimeError::Execution(division.by_zero_message(span)));
+        }
+
+        // Bitwise operators work on 32-bit integers, as in generated code
+        let (li, ri) = (l as i32, r as i32);
+        Ok(Value::Number(match op {
+            BinaryOp::Add => l + r,
+            BinaryOp::Subtract => l - r,
+            BinaryOp::Multiply => l * r,
+            BinaryOp::BitAnd => (li & ri) as f64,
+            BinaryOp::BitOr => (li | ri) as f64,
+            BinaryOp::BitXor => (li ^ ri) as f64,
+            BinaryOp::ShiftLeft => li.wrapping_shl(ri as u32) as f64,
+            BinaryOp::ShiftRight => li.wrapping_shr(ri as u32) as f64,
+            _ => unreachable!("comparisons and divisions return above"),
+        }))
+    }
+
+    /// Run the builtin `name` on `args`, or `None` if the session does not have it.
+    /// Arguments convert as in compiled code.
+    fn builtin(&mut self, name: &str, args: &[Value]) -> Option<Result<Value, RuntimeError>> {
+        if let Some(message) = builtin_argument_error(name, args.len()) {
+            return Some(Err(RuntimeError::Execution(message)));
+        }
+        Some(Ok(match name {
+            "string" => Value::String(text(&args[0])),
+            "real" => Value::Number(real(&args[0])),
+            "bool" => match truthy(&args[0]) {
+                Ok(value) => Value::Bool(value),
+                Err(e) => return Some(Err(e)),
+            },
+            "string_format" => Value::String(runtime::format_number_padded(
+                real(&args[0]),
+                real(&args[1]),
+                real(&args[2]),
+            )),
+            "string_length" => Value::Number(text(&args[0]).chars().count() as f64),
+            "string_char_at" => {
+                Value::String(runtime::string_char_at(&text(&args[0]), real(&args[1])))
+            }
+            "string_copy" => Value::String(runtime::string_copy(
+                &text(&args[0]),
+                real(&args[1]),
+                real(&args[2]),
+            )),
+            "string_pos" => Value::Number(runtime::string_pos(&text(&args[0]), &text(&args[1]))),
+            "math_set_epsilon" => {
+                // Negative epsilons are 0, as `math_set_epsilon` stores them
+                let epsilon = real(&args[0]);
+                self.math_epsilon = if epsilon < 0.0 { 0.0 } else { epsilon };
+                Value::Number(0.0)
+            }
+            "math_get_epsilon" => Value::Number(self.math_epsilon),
+            "is_nan" => Value::Bool(real(&args[0]).is_nan()),
+            "is_infinity" => Value::Bool(real(&args[0]).is_infinite()),
+            "println" => {
+                let line: String = args.iter().map(text).collect();
+                self.output.print(&format!("{}\n", line));
+                Value::Number(0.0)
+            }
+            _ => return None,
+        }))
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack
+            .pop()
+            .expect("lowered code never pops an empty stack")
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("a running session has a frame")
+    }
+
+    fn load(&self, name: &str) -> Result<Value, RuntimeError> {
+        let frames = [self.frames.last(), self.frames.first()];
+        frames
+            .into_iter()
+            .flatten()
+            .find_map(|frame| {
+                frame
+                    .locals
+                    .iter()
+                    .find(|(local, _)| local == name)
+                    .map(|(_, value)| value.clone())
+            })
+            .ok_or_else(|| RuntimeError::Execution(format!("Undefined variable '{}'", name)))
+    }
+
+    /// Assign the local or, failing that, global `name`, declaring a local if
+    /// neither exists
+    fn store(&mut self, name: &str, value: Value) {
+        let last = self.frames.len() - 1;
+        for index in [last, 0] {
+            let slot = self.frames[index]
+                .locals
+                .iter_mut()
+                .find(|(local, _)| local == name);
+            if let Some((_, slot)) = slot {
+                *slot = value;
+                return;
+            }
+        }
+        self.frames[last].locals.push((name.to_string(), value));
+    }
+
+    fn declare(&mut self, name: &str, value: Value) {
+        let locals = &mut self.frame().locals;
+        match locals.iter_mut().find(|(local, _)| local == name) {
+            Some((_, slot)) => *slot = value,
+            None => locals.push((name.to_string(), value)),
+        }
+    }
+
+    /// Call `name` with the `count` arguments on top of the stack. Returns whether
+    /// a script function was entered.
+    fn call(&mut self, name: &str, count: usize) -> Result<bool, RuntimeError> {
+        let args = self.stack.split_off(self.stack.len() - count);
+        let Some(&chunk) = self.functions.get(name) else {
+            return match self.builtin(name, &args) {
+                Some(result) => {
+                    self.stack.push(result?);
+                    Ok(false)
+                }
+                None => Err(RuntimeError::UnknownFunction(name.to_string())),
+            };
+        };
+        let params = &self.chunks[chunk].params;
+        if args.len() > params.len() {
+            return Err(RuntimeError::TooManyArguments {
+                function: name.to_string(),
+                expected: params.len(),
+                given: args.len(),
+            });
+        }
+        // The top-level code's frame is not a call, as in compiled code
+        if let Some(limit) = self.options.max_call_depth {
+            if self.frames.len() > limit {
+                return Err(RuntimeError::Execution(format!(
+                    "maximum call depth exceeded in function {}",
+                    name
+                )));
+            }
+        }
+        let mut args = args.into_iter();
+        let locals = params
+            .iter()
+            .map(|param| (param.clone(), args.next().unwrap_or(Value::Number(0.0))))
+            .collect();
+        self.frames.push(Frame {
+            chunk,
+            pc: 0,
+            locals,
+            base: self.stack.len(),
+        });
+        Ok(true)
+    }
+
+    fn run_step(&mut self) -> Result<StepEvent, RuntimeError> {
+        loop {
+            let frame = self.frame();
+            let (chunk, pc) = (frame.chunk, frame.pc);
+            frame.pc += 1;
+            match &self.chunks[chunk].code[pc] {
+                Op::Statement(span, line) => {
+                    return Ok(StepEvent::Statement {
+                        span: span.clone(),
+                        line: *line,
+                    });
+                }
+                Op::Push(value) => {
+                    let value = value.clone();
+                    self.stack.push(value);
+                }
+                Op::Load(name) => {
+                    let value = self.load(name)?;
+                    self.stack.push(value);
+                }
+                Op::Store(name) => {
+                    let name = name.clone();
+                    let value = self.pop();
+                    self.store(&name, value);
+                }
+                Op::Declare(name) => {
+                    let name = name.clone();
+                    let value = self.pop();
+                    self.declare(&name, value);
+                }
+                Op::Pop => {
+                    self.pop();
+                }
+                Op::Dup => {
+                    let value = self.stack.last().cloned().expect("nothing to duplicate");
+                    self.stack.push(value);
+                }
+                &Op::Unary(op) => {
+                    let value = self.pop();
+                    self.stack.push(unary(op, &value)?);
+                }
+                Op::Binary(op, span) => {
+                    let (op, span) = (*op, span.clone());
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    let result = self.binary(op, &lhs, &rhs, span.as_ref())?;
+                    self.stack.push(result);
+                }
+                Op::Truthy => {
+                    let value = self.pop();
+                    self.stack.push(Value::Bool(truthy(&value)?));
+                }
+                &Op::Jump(target) => self.frame().pc = target,
+                &Op::JumpIfFalse(target) => {
+                    if !truthy(&self.pop())? {
+                        self.frame().pc = target;
+                    }
+                }
+                &Op::JumpIfTrue(target) => {
+                    if truthy(&self.pop())? {
+                        self.frame().pc = target;
+                    }
+                }
+                &Op::RepeatNext(target) => {
+                    let left = number(&self.pop(), "repeat")?;
+                    // NaN and counts below one end the loop; fractions are cut off
+                    if left >= 1.0 {
+                        self.stack.push(Value::Number(left - 1.0));
+                    } else {
+                        self.stack.push(Value::Number(0.0));
+                        self.frame().pc = target;
+                    }
+                }
+                Op::Call(name, count) => {
+                    let (name, count) = (name.clone(), *count);
+                    if self.call(&name, count)? {
+                        return Ok(StepEvent::FunctionEntered { name });
+                    }
+                }
+                Op::Return => {
+                    let value = self.pop();
+                    let frame = self.frames.pop().expect("a running session has a frame");
+                    self.stack.truncate(frame.base);
+                    if self.frames.is_empty() {
+                        self.finished = Some(value.clone());
+                        return Ok(StepEvent::Finished(value));
+                    }
+                    self.stack.push(value.clone());
+                    return Ok(StepEvent::FunctionExited {
+                        name: self.chunks[frame.chunk].name.clone(),
+                        value,
+                    });
+                }
+            }
+        }
+    }
+}
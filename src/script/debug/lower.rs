@@ -0,0 +1,683 @@
+//! Lowering a program to the instructions a [`DebugSession`](super::DebugSession)
+//! steps through.
+//!
+//! Every function becomes a flat list of [`Op`]s working on a stack of values, with
+//! jumps for control flow, so running one never recurses in Rust. A statement
+//! starts with an [`Op::Statement`] the session pauses at; loops pause at their
+//! own statement once per iteration, so a step always ends, even in `while (true)
+//! {}`. Values a statement keeps on the stack while it runs, the count of a
+//! `repeat` and the value a `switch` compares, are popped by the `break`s and
+//! `continue`s that leave it.
+
+use crate::codegen::ir_generator::IRGenError;
+use crate::codegen::ir_generator::division::operation_span;
+use crate::codegen::ir_generator::enums::eval_constant;
+use crate::codegen::ir_generator::visit_stmt::stmt_span;
+use crate::parser::Span;
+use crate::parser::expr::Expr;
+use crate::parser::func_def::FuncDef;
+use crate::parser::program::Program;
+use crate::parser::stmt::{Stmt, SwitchCase};
+use crate::parser::top_level::TopLevel;
+use crate::script::Value;
+use crate::utils::line_index::LineIndex;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum UnaryOp {
+    Negative,
+    Positive,
+    Not,
+    BitNot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    IntDivide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    Xor,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Op {
+    /// Pause before the statement at this span, on this 1-based line
+    Statement(Span, usize),
+    Push(Value),
+    Load(String),
+    /// Pop a value and assign it to the variable
+    Store(String),
+    /// Pop a value and declare the variable with it in the running function
+    Declare(String),
+    Pop,
+    Dup,
+    Unary(UnaryOp),
+    /// Pop the right operand, then the left one, and push the result. The span is
+    /// where the operation is, for the errors dividing by zero and comparing NaN.
+    Binary(BinaryOp, Option<Span>),
+    /// Replace the value on top with whether it is true
+    Truthy,
+    Jump(usize),
+    /// Pop a value and jump if it is false
+    JumpIfFalse(usize),
+    /// Pop a value and jump if it is true
+    JumpIfTrue(usize),
+    /// With the iterations a `repeat` has left on top: jump if less than one is,
+    /// otherwise count one off
+    RepeatNext(usize),
+    /// Call the function with the arguments on top of the stack, the last on top
+    Call(String, usize),
+    /// Pop the result and return it from the running function
+    Return,
+}
+
+/// A function lowered to instructions
+#[derive(Debug)]
+pub(super) struct Chunk {
+    pub name: String,
+    pub params: Vec<String>,
+    pub code: Vec<Op>,
+}
+
+/// Where `break` and `continue` go from inside a statement
+struct Target {
+    /// Values kept on the stack when the target is reached
+    depth: usize,
+    /// Jumps to patch with the address after the statement
+    breaks: Vec<usize>,
+    /// Where `continue` goes, `None` for a `switch`, which `continue` leaves
+    /// for the loop around it; unknown while the loop is still being lowered
+    continues: Option<Vec<usize>>,
+}
+
+/// Lowers one program into a chunk per function, the first being the top-level
+/// code
+pub(super) struct Lowering<'a> {
+    lines: LineIndex<'a>,
+    enum_members: HashMap<String, f64>,
+    chunks: Vec<Chunk>,
+    /// Index of every function by name
+    functions: HashMap<String, usize>,
+}
+
+type LowerResult<T> = Result<T, IRGenError>;
+
+fn unsupported(what: &str, span: Span) -> IRGenError {
+    IRGenError::Unsupported {
+        message: format!("{} cannot be debugged yet", what),
+        span,
+    }
+}
+
+/// The variable an assignment writes
+fn assigned_variable(expr: &Expr) -> LowerResult<&str> {
+    match expr {
+        Expr::Identifier(name, _) => Ok(name),
+        Expr::Paren(inner) => assigned_variable(inner),
+        Expr::Field(.., span) => Err(unsupported("Assigning a field", span.clone())),
+        Expr::Accessor(.., span) => Err(unsupported("Assigning through an accessor", span.clone())),
+        _ => Err(IRGenError::InvalidOperation(
+            "Can only assign to a variable".to_string(),
+        )),
+    }
+}
+
+impl<'a> Lowering<'a> {
+    /// Lower `program`, parsed from `source`, into its chunks and the index of
+    /// each function by name
+    pub(super) fn lower(
+        program: &Program,
+        source: &'a str,
+    ) -> LowerResult<(Vec<Chunk>, HashMap<String, usize>)> {
+        let error_spans = program.error_spans();
+        if !error_spans.is_empty() {
+            return Err(IRGenError::SyntaxErrors(error_spans));
+        }
+
+        let mut lowering = Lowering {
+            lines: LineIndex::new(source),
+            enum_members: HashMap::new(),
+            chunks: Vec::new(),
+            functions: HashMap::new(),
+        };
+        for top_level in &program.body {
+            if let TopLevel::Enum(enum_def) = top_level {
+                let mut next = 0.0;
+                for member in &enum_def.members {
+                    let key = format!("{}.{}", enum_def.name, member.name);
+                    let value = match &member.value {
+                        Some(expr) => lowering.constant(expr).ok_or_else(|| {
+                            IRGenError::InvalidOperation(format!(
+                                "Value of enum member '{}' is not a constant",
+                                key
+                            ))
+                        })?,
+                        None => next,
+                    };
+                    lowering.enum_members.insert(key, value);
+                    next = value + 1.0;
+                }
+            }
+        }
+
+        let mut main = FunctionLowering::new(&mut lowering);
+        for top_level in &program.body {
+            match top_level {
+                TopLevel::Statement(stmt) => main.stmt(stmt)?,
+                TopLevel::Function(func_def) => main.lowering.function(func_def)?,
+                TopLevel::Enum(_) => {}
+                TopLevel::Include(path, _) => {
+                    return Err(IRGenError::InvalidOperation(format!(
+                        "Unresolved include '{}'",
+                        path
+                    )));
+                }
+                TopLevel::Error(span) => return Err(IRGenError::SyntaxErrors(vec![span.clone()])),
+            }
+        }
+        let code = main.finish();
+        lowering.chunks.insert(
+            0,
+            Chunk {
+                name: "main".to_string(),
+                params: Vec::new(),
+                code,
+            },
+        );
+        // Functions were numbered before the top-level code was put first
+        for index in lowering.functions.values_mut() {
+            *index += 1;
+        }
+        Ok((lowering.chunks, lowering.functions))
+    }
+
+    fn constant(&self, expr: &Expr) -> Option<f64> {
+        eval_constant(expr, &|object, member| {
+            self.enum_members
+                .get(&format!("{}.{}", object, member))
+                .copied()
+        })
+    }
+
+    /// Lower a function into its own chunk. Functions defined inside others are
+    /// callable from anywhere, unlike in compiled code.
+    fn function(&mut self, func_def: &FuncDef) -> LowerResult<()> {
+        let mut body = FunctionLowering::new(self);
+        for stmt in &func_def.func.body {
+            body.stmt(stmt)?;
+        }
+        let code = body.finish();
+        self.functions
+            .insert(func_def.name.clone(), self.chunks.len());
+        self.chunks.push(Chunk {
+            name: func_def.name.clone(),
+            params: func_def.func.args.clone(),
+            code,
+        });
+        Ok(())
+    }
+}
+
+/// Lowers the statements of one function
+struct FunctionLowering<'l, 'a> {
+    lowering: &'l mut Lowering<'a>,
+    code: Vec<Op>,
+    /// Values the statements being lowered keep on the stack
+    depth: usize,
+    targets: Vec<Target>,
+    /// Whether statements are lowered without a pause, as parts of a `for`
+    unpaused: bool,
+}
+
+impl<'l, 'a> FunctionLowering<'l, 'a> {
+    fn new(lowering: &'l mut Lowering<'a>) -> Self {
+        Self {
+            lowering,
+            code: Vec::new(),
+            depth: 0,
+            targets: Vec::new(),
+            unpaused: false,
+        }
+    }
+
+    /// The code of the function, which returns 0 if it reaches its end
+    fn finish(mut self) -> Vec<Op> {
+        self.code.push(Op::Push(Value::Number(0.0)));
+        self.code.push(Op::Return);
+        self.code
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.code.push(op);
+        self.code.len() - 1
+    }
+
+    /// Point the jump at `at` to the next instruction
+    fn patch(&mut self, at: usize) {
+        let here = self.code.len();
+        match &mut self.code[at] {
+            Op::Jump(target)
+            | Op::JumpIfFalse(target)
+            | Op::JumpIfTrue(target)
+            | Op::RepeatNext(target) => *target = here,
+            _ => unreachable!("only jumps are patched"),
+        }
+    }
+
+    fn pause_at(&mut self, stmt: &Stmt) {
+        if self.unpaused {
+            return;
+        }
+        if let Some(span) = stmt_span(stmt) {
+            let (line, _) = self.lowering.lines.line_col(span.start);
+            self.emit(Op::Statement(span, line));
+        }
+    }
+
+    /// Pop what was pushed since `depth` values were kept
+    fn unwind_to(&mut self, depth: usize) {
+        for _ in depth..self.depth {
+            self.emit(Op::Pop);
+        }
+    }
+
+    /// Lower a loop body, with `continue` going to `next` if it is known yet;
+    /// returns the jumps `continue` made otherwise
+    fn loop_body(
+        &mut self,
+        body: &Stmt,
+        next: Option<usize>,
+    ) -> LowerResult<(Vec<usize>, Vec<usize>)> {
+        self.targets.push(Target {
+            depth: self.depth,
+            breaks: Vec::new(),
+            continues: Some(Vec::new()),
+        });
+        let result = self.stmt(body);
+        let target = self.targets.pop().expect("the loop's target was pushed");
+        result?;
+        let mut continues = target.continues.unwrap_or_default();
+        if let Some(next) = next {
+            for &at in &continues {
+                if let Op::Jump(to) = &mut self.code[at] {
+                    *to = next;
+                }
+            }
+            continues.clear();
+        }
+        Ok((target.breaks, continues))
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> LowerResult<()> {
+        match stmt {
+            Stmt::Expr(expr) => {
+                self.pause_at(stmt);
+                self.expr(expr)?;
+                self.emit(Op::Pop);
+            }
+            Stmt::Var(vars) => {
+                self.pause_at(stmt);
+                for (name, init, _) in vars {
+                    match init {
+                        Some(init) => self.expr(init)?,
+                        None => {
+                            self.emit(Op::Push(Value::Number(0.0)));
+                        }
+                    }
+                    self.emit(Op::Declare(name.clone()));
+                }
+            }
+            Stmt::If(cond, then_stmt, else_stmt, _) => {
+                self.pause_at(stmt);
+                self.expr(cond)?;
+                let to_else = self.emit(Op::JumpIfFalse(0));
+                self.stmt(then_stmt)?;
+                match else_stmt {
+                    Some(else_stmt) => {
+                        let to_end = self.emit(Op::Jump(0));
+                        self.patch(to_else);
+                        self.stmt(else_stmt)?;
+                        self.patch(to_end);
+                    }
+                    None => self.patch(to_else),
+                }
+            }
+            Stmt::Block(stmts, _) => {
+                for stmt in stmts {
+                    self.stmt(stmt)?;
+                }
+            }
+            Stmt::Return(value) => {
+                self.pause_at(stmt);
+                match value {
+                    Some(value) => self.expr(value)?,
+                    None => {
+                        self.emit(Op::Push(Value::Number(0.0)));
+                    }
+                }
+                self.emit(Op::Return);
+            }
+            Stmt::Break => {
+                let Some(index) = self.targets.len().checked_sub(1) else {
+                    return Err(IRGenError::InvalidOperation(
+                        "'break' outside of a loop".to_string(),
+                    ));
+                };
+                self.unwind_to(self.targets[index].depth);
+                let at = self.emit(Op::Jump(0));
+                self.targets[index].breaks.push(at);
+            }
+            Stmt::Continue => {
+                let Some(index) = self.targets.iter().rposition(|t| t.continues.is_some()) else {
+                    return Err(IRGenError::InvalidOperation(
+                        "'continue' outside of a loop".to_string(),
+                    ));
+                };
+                self.unwind_to(self.targets[index].depth);
+                let at = self.emit(Op::Jump(0));
+                if let Some(continues) = &mut self.targets[index].continues {
+                    continues.push(at);
+                }
+            }
+            Stmt::While(cond, body, _) => {
+                let head = self.code.len();
+                self.pause_at(stmt);
+                self.expr(cond)?;
+                let to_exit = self.emit(Op::JumpIfFalse(0));
+                let (breaks, _) = self.loop_body(body, Some(head))?;
+                self.emit(Op::Jump(head));
+                self.patch(to_exit);
+                breaks.into_iter().for_each(|at| self.patch(at));
+            }
+            Stmt::DoUntil(body, cond, _) => {
+                let head = self.code.len();
+                self.pause_at(stmt);
+                let (breaks, continues) = self.loop_body(body, None)?;
+                continues.into_iter().for_each(|at| self.patch(at));
+                self.expr(cond)?;
+                self.emit(Op::JumpIfFalse(head));
+                breaks.into_iter().for_each(|at| self.patch(at));
+            }
+            Stmt::Repeat(count, body, _) => {
+                self.expr(count)?;
+                self.depth += 1;
+                let head = self.code.len();
+                self.pause_at(stmt);
+                let to_exit = self.emit(Op::RepeatNext(0));
+                let (breaks, _) = self.loop_body(body, Some(head))?;
+                self.emit(Op::Jump(head));
+                self.patch(to_exit);
+                breaks.into_iter().for_each(|at| self.patch(at));
+                self.depth -= 1;
+                self.emit(Op::Pop);
+            }
+            Stmt::For(init, cond, update, body, _) => {
+                if let Some(init) = init {
+                    self.unpaused(init)?;
+                }
+                let head = self.code.len();
+                self.pause_at(stmt);
+                let to_exit = match cond {
+                    Some(cond) => {
+                        self.expr(cond)?;
+                        Some(self.emit(Op::JumpIfFalse(0)))
+                    }
+                    None => None,
+                };
+                let (breaks, continues) = self.loop_body(body, None)?;
+                continues.into_iter().for_each(|at| self.patch(at));
+                if let Some(update) = update {
+                    self.unpaused(update)?;
+                }
+                self.emit(Op::Jump(head));
+                to_exit.into_iter().for_each(|at| self.patch(at));
+                breaks.into_iter().for_each(|at| self.patch(at));
+            }
+            Stmt::Switch(value, cases, _) => {
+                self.pause_at(stmt);
+                self.expr(value)?;
+                self.depth += 1;
+                self.switch(cases)?;
+                self.depth -= 1;
+                self.emit(Op::Pop);
+            }
+            Stmt::Function(func_def) => self.lowering.function(func_def)?,
+            Stmt::Error(span) => return Err(IRGenError::SyntaxErrors(vec![span.clone()])),
+        }
+        Ok(())
+    }
+
+    /// Lower the `init` or `update` of a `for`, which the loop's own pause covers
+    fn unpaused(&mut self, stmt: &Stmt) -> LowerResult<()> {
+        self.unpaused = true;
+        let result = self.stmt(stmt);
+        self.unpaused = false;
+        result
+    }
+
+    /// Lower the labels of a `switch`, whose value is on top of the stack
+    fn switch(&mut self, cases: &[SwitchCase]) -> LowerResult<()> {
+        let mut to_cases = Vec::with_capacity(cases.len());
+        for case in cases {
+            let mut to_case = Vec::with_capacity(case.labels.len());
+            for label in &case.labels {
+                self.emit(Op::Dup);
+                self.expr(label)?;
+                self.emit(Op::Binary(BinaryOp::Equal, None));
+                to_case.push(self.emit(Op::JumpIfTrue(0)));
+            }
+            to_cases.push(to_case);
+        }
+        let to_default = self.emit(Op::Jump(0));
+
+        self.targets.push(Target {
+            depth: self.depth,
+            breaks: Vec::new(),
+            continues: None,
+        });
+        let mut default = None;
+        for (case, to_case) in cases.iter().zip(to_cases) {
+            to_case.into_iter().for_each(|at| self.patch(at));
+            if case.default {
+                default = Some(self.code.len());
+            }
+            for stmt in &case.body {
+                self.stmt(stmt)?;
+            }
+        }
+        let target = self.targets.pop().expect("the switch's target was pushed");
+        match default {
+            Some(default) => self.code[to_default] = Op::Jump(default),
+            None => self.patch(to_default),
+        }
+        target.breaks.into_iter().for_each(|at| self.patch(at));
+        Ok(())
+    }
+
+    fn binary(&mut self, lhs: &Expr, rhs: &Expr, op: BinaryOp) -> LowerResult<()> {
+        self.expr(lhs)?;
+        self.expr(rhs)?;
+        self.emit(Op::Binary(op, operation_span(lhs, rhs)));
+        Ok(())
+    }
+
+    /// Assign `value` combined with the variable by `op`, if any, and keep the
+    /// result
+    fn assign(&mut self, target: &Expr, value: &Expr, op: Option<BinaryOp>) -> LowerResult<()> {
+        let name = assigned_variable(target)?.to_string();
+        if let Some(op) = op {
+            self.emit(Op::Load(name.clone()));
+            self.expr(value)?;
+            self.emit(Op::Binary(op, operation_span(target, value)));
+        } else {
+            self.expr(value)?;
+        }
+        self.emit(Op::Dup);
+        self.emit(Op::Store(name));
+        Ok(())
+    }
+
+    /// Add `delta` to the variable, keeping its old value if `postfix`, its new
+    /// one otherwise
+    fn step_variable(&mut self, target: &Expr, delta: f64, postfix: bool) -> LowerResult<()> {
+        let name = assigned_variable(target)?.to_string();
+        self.emit(Op::Load(name.clone()));
+        if postfix {
+            self.emit(Op::Dup);
+        }
+        self.emit(Op::Push(Value::Number(delta)));
+        self.emit(Op::Binary(BinaryOp::Add, None));
+        if !postfix {
+            self.emit(Op::Dup);
+        }
+        self.emit(Op::Store(name));
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> LowerResult<()> {
+        match expr {
+            Expr::Number(n, _) => {
+                self.emit(Op::Push(Value::Number(*n)));
+            }
+            Expr::Integer(n, _) => {
+                self.emit(Op::Push(Value::Number(*n as f64)));
+            }
+            Expr::String(s, _) => {
+                self.emit(Op::Push(Value::String(s.clone())));
+            }
+            Expr::True(..) => {
+                self.emit(Op::Push(Value::Bool(true)));
+            }
+            Expr::False(..) => {
+                self.emit(Op::Push(Value::Bool(false)));
+            }
+            Expr::Null(_) => {
+                self.emit(Op::Push(Value::Null));
+            }
+            Expr::Undefined => {
+                self.emit(Op::Push(Value::Number(0.0)));
+            }
+            Expr::Identifier(name, _) => {
+                self.emit(Op::Load(name.clone()));
+            }
+            Expr::Call(name, args, _) => {
+                for arg in args {
+                    self.expr(arg)?;
+                }
+                self.emit(Op::Call(name.clone(), args.len()));
+            }
+            Expr::Member(object, member, span) => {
+                let key = format!("{}.{}", object, member);
+                let Some(&value) = self.lowering.enum_members.get(&key) else {
+                    return Err(unsupported(&format!("'{}'", key), span.clone()));
+                };
+                self.emit(Op::Push(Value::Number(value)));
+            }
+            Expr::SelfRef(span) | Expr::OtherRef(span) | Expr::Field(.., span) => {
+                return Err(unsupported("An instance", span.clone()));
+            }
+            Expr::Function(_, span) => {
+                return Err(unsupported("An anonymous function", span.clone()));
+            }
+            Expr::Accessor(kind, .., span) => {
+                return Err(unsupported(
+                    &format!("The '{}' accessor", kind.opening()),
+                    span.clone(),
+                ));
+            }
+            Expr::Addition(l, r) => self.binary(l, r, BinaryOp::Add)?,
+            Expr::Subtraction(l, r) => self.binary(l, r, BinaryOp::Subtract)?,
+            Expr::Multiplication(l, r) => self.binary(l, r, BinaryOp::Multiply)?,
+            Expr::Division(l, r) => self.binary(l, r, BinaryOp::Divide)?,
+            Expr::IntDivision(l, r) => self.binary(l, r, BinaryOp::IntDivide)?,
+            Expr::Percent(l, r) => self.binary(l, r, BinaryOp::Remainder)?,
+            Expr::Greater(l, r) => self.binary(l, r, BinaryOp::Greater)?,
+            Expr::GreaterEqual(l, r) => self.binary(l, r, BinaryOp::GreaterEqual)?,
+            Expr::Less(l, r) => self.binary(l, r, BinaryOp::Less)?,
+            Expr::LessEqual(l, r) => self.binary(l, r, BinaryOp::LessEqual)?,
+            Expr::EqualEqual(l, r) => self.binary(l, r, BinaryOp::Equal)?,
+            Expr::NotEqual(l, r) => self.binary(l, r, BinaryOp::NotEqual)?,
+            Expr::BitAnd(l, r) => self.binary(l, r, BinaryOp::BitAnd)?,
+            Expr::BitXor(l, r) => self.binary(l, r, BinaryOp::BitXor)?,
+            Expr::BitOr(l, r) => self.binary(l, r, BinaryOp::BitOr)?,
+            Expr::ShiftLeft(l, r) => self.binary(l, r, BinaryOp::ShiftLeft)?,
+            Expr::ShiftRight(l, r) => self.binary(l, r, BinaryOp::ShiftRight)?,
+            Expr::Xor(l, r) => self.binary(l, r, BinaryOp::Xor)?,
+            Expr::Not(e) => {
+                self.expr(e)?;
+                self.emit(Op::Unary(UnaryOp::Not));
+            }
+            Expr::BitNot(e) => {
+                self.expr(e)?;
+                self.emit(Op::Unary(UnaryOp::BitNot));
+            }
+            Expr::Positive(e) => {
+                self.expr(e)?;
+                self.emit(Op::Unary(UnaryOp::Positive));
+            }
+            Expr::Negative(e) => {
+                self.expr(e)?;
+                self.emit(Op::Unary(UnaryOp::Negative));
+            }
+            Expr::Paren(e) => self.expr(e)?,
+            // The right operand only runs when the left does not decide the result
+            Expr::And(l, r) | Expr::Or(l, r) => {
+                let is_and = matches!(expr, Expr::And(..));
+                self.expr(l)?;
+                let to_short = if is_and {
+                    self.emit(Op::JumpIfFalse(0))
+                } else {
+                    self.emit(Op::JumpIfTrue(0))
+                };
+                self.expr(r)?;
+                self.emit(Op::Truthy);
+                let to_end = self.emit(Op::Jump(0));
+                self.patch(to_short);
+                self.emit(Op::Push(Value::Bool(!is_and)));
+                self.patch(to_end);
+            }
+            Expr::Ternary(cond, then_expr, else_expr) => {
+                self.expr(cond)?;
+                let to_else = self.emit(Op::JumpIfFalse(0));
+                self.expr(then_expr)?;
+                let to_end = self.emit(Op::Jump(0));
+                self.patch(to_else);
+                self.expr(else_expr)?;
+                self.patch(to_end);
+            }
+            Expr::Equal(target, value) => self.assign(target, value, None)?,
+            Expr::PlusEqual(target, value) => self.assign(target, value, Some(BinaryOp::Add))?,
+            Expr::MinusEqual(target, value) => {
+                self.assign(target, value, Some(BinaryOp::Subtract))?
+            }
+            Expr::StarEqual(target, value) => {
+                self.assign(target, value, Some(BinaryOp::Multiply))?
+            }
+            Expr::SlashEqual(target, value) => {
+                self.assign(target, value, Some(BinaryOp::Divide))?
+            }
+            Expr::PercentEqual(target, value) => {
+                self.assign(target, value, Some(BinaryOp::Remainder))?
+            }
+            Expr::PreIncrement(target) => self.step_variable(target, 1.0, false)?,
+            Expr::PostIncrement(target) => self.step_variable(target, 1.0, true)?,
+            Expr::PreDecrement(target) => self.step_variable(target, -1.0, false)?,
+            Expr::PostDecrement(target) => self.step_variable(target, -1.0, true)?,
+        }
+        Ok(())
+    }
+}
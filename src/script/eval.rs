@@ -0,0 +1,136 @@
+//! Evaluating a snippet of source for its value, e.g. a console or a config
+//! expression.
+//!
+//! [`Script::eval`] compiles the source with its last top-level statement, which
+//! must be an expression, turned into the script's `return`, and runs it once. The
+//! type inference tells which kind of value the expression gives. `main` only
+//! returns numbers, so a bool comes back as 1 or 0 and is turned back into a bool,
+//! while a string is returned by a function of its own, called once the
+//! statements before it ran.
+
+use super::template::ScriptTemplate;
+use super::{CompileError, RuntimeError, Script, Value};
+use crate::codegen::ir_generator::STRING_RESULT_FUNCTION;
+use crate::parser::expr::Expr;
+use crate::parser::language_options::LanguageOptions;
+use crate::parser::stmt::Stmt;
+use crate::parser::top_level::TopLevel;
+use crate::parser::visitor::type_infer::{Type, TypeInferrer};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Why [`Script::eval`] gave no value
+#[derive(Debug)]
+pub enum EvalError {
+    Compile(CompileError),
+    Runtime(RuntimeError),
+    /// The source does not end in an expression statement, so there is no value
+    /// to give; names what it ends in instead
+    NoResult(&'static str),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Compile(error) => write!(f, "{}", error),
+            EvalError::Runtime(error) => write!(f, "{}", error),
+            EvalError::NoResult(found) => write!(
+                f,
+                "The script ends in {}, not an expression, so it has no value; \
+                 end it with the expression to evaluate, e.g. `x + 1;`",
+                found
+            ),
+        }
+    }
+}
+
+impl From<CompileError> for EvalError {
+    fn from(error: CompileError) -> Self {
+        EvalError::Compile(error)
+    }
+}
+
+impl From<RuntimeError> for EvalError {
+    fn from(error: RuntimeError) -> Self {
+        EvalError::Runtime(error)
+    }
+}
+
+/// What a source ending in `top_level` ends in, as [`EvalError::NoResult`] names it
+fn describe(top_level: &TopLevel) -> &'static str {
+    match top_level {
+        TopLevel::Statement(Stmt::Var(_)) => "a variable declaration",
+        TopLevel::Function(_) | TopLevel::Statement(Stmt::Function(_)) => "a function definition",
+        TopLevel::Enum(_) => "an enum",
+        TopLevel::Include(..) => "an #include",
+        TopLevel::Statement(Stmt::Return(_)) => "a 'return'",
+        TopLevel::Error(_) | TopLevel::Statement(Stmt::Error(_)) => "a syntax error",
+        TopLevel::Statement(_) => "a statement",
+    }
+}
+
+impl Script {
+    /// Run `source` once and return the value of its last top-level statement,
+    /// which must be an expression: `eval("var d = a - b; d * 2;", ...)` gives
+    /// `d * 2`. A source ending in anything else, such as a `var` or a function,
+    /// is an [`EvalError::NoResult`].
+    ///
+    /// `globals` are declared before the source's own statements, with these values;
+    /// like any global they hold numbers, so a string is a
+    /// [`RuntimeError::UnsupportedValue`]. The source may declare them again to
+    /// assign them. The value is a bool or a string when the expression is
+    /// certain to give one, see [`infer_types`](crate::infer_types), and a number
+    /// otherwise.
+    pub fn eval(source: &str, globals: &[(&str, Value)]) -> Result<Value, EvalError> {
+        let mut template =
+            ScriptTemplate::new(source, &LanguageOptions::default(), &HashMap::new())?;
+        let program = Arc::make_mut(&mut template.program);
+        let expr = match program.body.pop() {
+            Some(TopLevel::Statement(Stmt::Expr(expr))) => expr,
+            Some(top_level) => return Err(EvalError::NoResult(describe(&top_level))),
+            None => return Err(EvalError::NoResult("nothing")),
+        };
+
+        let mut declarations = Vec::with_capacity(globals.len());
+        for (name, value) in globals {
+            let number = value
+                .as_number()
+                .ok_or_else(|| RuntimeError::UnsupportedValue(value.clone()))?;
+            declarations.push(TopLevel::Statement(Stmt::Var(vec![(
+                name.to_string(),
+                Some(Expr::Number(number, 0..0)),
+                0..0,
+            )])));
+        }
+        program.body.splice(0..0, declarations);
+
+        // The statements before it decide the types of the variables it reads
+        let mut inferrer = TypeInferrer::new();
+        program.accept(&mut inferrer);
+        let ty = expr.accept(&mut inferrer);
+
+        if ty == Type::String {
+            let script = Script::compile_template(&template, false, None, None, Some(&expr))?;
+            let text = script
+                .executor
+                .execute_string_function(STRING_RESULT_FUNCTION)
+                .map_err(RuntimeError::Execution)?;
+            return Ok(Value::String(text));
+        }
+
+        let last = match ty {
+            Type::Null => Stmt::Expr(expr),
+            _ => Stmt::Return(Some(expr)),
+        };
+        program.body.push(TopLevel::Statement(last));
+
+        let script = Script::compile_template(&template, false, None, None, None)?;
+        let number = script.run_main()?.as_number().unwrap_or_default();
+        Ok(match ty {
+            Type::Bool => Value::Bool(number != 0.0),
+            Type::Null => Value::Null,
+            _ => Value::Number(number),
+        })
+    }
+}
@@ -0,0 +1,186 @@
+//! Snapshots of a script's globals, e.g. for save games.
+//!
+//! A snapshot is a header, `COLS` and a little-endian `u16` version, then the number
+//! of globals as a `u32` and each global as its name, a `u32` length and UTF-8
+//! bytes, followed by its value: a tag byte and the value's bytes. Numbers are
+//! little-endian `f64`s, bools one byte, strings a `u32` length and UTF-8 bytes,
+//! and null nothing. Globals only hold numbers today; the other tags are read so
+//! that snapshots can carry them once globals do.
+
+use super::{Script, Value};
+use std::fmt;
+
+const MAGIC: &[u8; 4] = b"COLS";
+
+/// Version of the encoding [`Script::save_state`] writes
+const VERSION: u16 = 1;
+
+const TAG_NUMBER: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_STRING: u8 = 2;
+const TAG_NULL: u8 = 3;
+
+/// Why a snapshot could not be loaded. Nothing is restored when loading fails.
+#[derive(Debug, PartialEq)]
+pub enum StateError {
+    /// The bytes do not start with a snapshot header
+    NotASnapshot,
+    /// A snapshot written by a newer version of the encoding
+    UnsupportedVersion(u16),
+    /// The snapshot ends in the middle of a global
+    Truncated,
+    /// A global's name or a string value is not UTF-8
+    InvalidUtf8,
+    /// A value with a tag no version writes
+    UnknownTag(u8),
+    /// A value the global cannot hold, such as a string
+    UnsupportedValue { name: String, value: Value },
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::NotASnapshot => write!(f, "Not a script state snapshot"),
+            StateError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported snapshot version {}", version)
+            }
+            StateError::Truncated => write!(f, "Snapshot is truncated"),
+            StateError::InvalidUtf8 => write!(f, "Snapshot has text that is not UTF-8"),
+            StateError::UnknownTag(tag) => {
+                write!(f, "Snapshot has a value with unknown tag {}", tag)
+            }
+            StateError::UnsupportedValue { name, value } => {
+                write!(f, "Global '{}' cannot hold a {}", name, value.type_name())
+            }
+        }
+    }
+}
+
+/// How [`Script::load_state`] matched a snapshot to the script's globals
+#[derive(Debug, Default, PartialEq)]
+pub struct StateReport {
+    /// Globals in both, which take the snapshot's values
+    pub restored: Vec<String>,
+    /// Globals only the snapshot has, which are ignored
+    pub unknown: Vec<String>,
+    /// Globals only the script declares, which start over at 0
+    pub missing: Vec<String>,
+}
+
+impl Script {
+    /// Encode the current values of all globals, see [`state`](crate::script::state)
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.globals.len() as u32).to_le_bytes());
+        for (name, value) in self.globals.iter().zip(self.global_values.iter()) {
+            write_str(&mut bytes, name);
+            bytes.push(TAG_NUMBER);
+            bytes.extend_from_slice(&value.get().to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Set the globals to the values in a snapshot from [`Script::save_state`],
+    /// matching them by name as [`Script::reload`] does. Globals the snapshot lacks
+    /// are set to 0, as in a fresh compile, and ones the script lacks are ignored and
+    /// listed in the report. The snapshot is checked as a whole before any global
+    /// changes.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<StateReport, StateError> {
+        let snapshot = read_snapshot(bytes)?;
+
+        let mut numbers = Vec::new();
+        let mut report = StateReport::default();
+        for (name, value) in snapshot {
+            let Ok(index) = self.global_index(&name) else {
+                report.unknown.push(name);
+                continue;
+            };
+            let number = value
+                .as_number()
+                .ok_or_else(|| StateError::UnsupportedValue {
+                    name: name.clone(),
+                    value: value.clone(),
+                })?;
+            numbers.push((index, number));
+            report.restored.push(name);
+        }
+
+        for value in self.global_values.iter() {
+            value.set(0.0);
+        }
+        for (index, number) in numbers {
+            self.global_values[index].set(number);
+        }
+        // Running the top-level statements now would overwrite what was restored
+        self.executor.mark_initialized();
+        report.missing = self
+            .globals
+            .iter()
+            .filter(|name| !report.restored.contains(*name))
+            .cloned()
+            .collect();
+        Ok(report)
+    }
+}
+
+fn write_str(bytes: &mut Vec<u8>, text: &str) {
+    bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(text.as_bytes());
+}
+
+/// The globals in a snapshot, in the order they were written
+fn read_snapshot(bytes: &[u8]) -> Result<Vec<(String, Value)>, StateError> {
+    let mut reader = Reader { bytes };
+    if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(StateError::NotASnapshot);
+    }
+    let version = u16::from_le_bytes(reader.array()?);
+    if version > VERSION {
+        return Err(StateError::UnsupportedVersion(version));
+    }
+
+    let count = u32::from_le_bytes(reader.array()?);
+    let mut globals = Vec::new();
+    for _ in 0..count {
+        let name = reader.string()?;
+        let value = match reader.array::<1>()?[0] {
+            TAG_NUMBER => Value::Number(f64::from_le_bytes(reader.array()?)),
+            TAG_BOOL => Value::Bool(reader.array::<1>()?[0] != 0),
+            TAG_STRING => Value::String(reader.string()?),
+            TAG_NULL => Value::Null,
+            tag => return Err(StateError::UnknownTag(tag)),
+        };
+        globals.push((name, value));
+    }
+    Ok(globals)
+}
+
+/// Reads a snapshot front to back
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.bytes.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let bytes = self.take(N)?;
+        Ok(bytes.try_into().expect("took N bytes"))
+    }
+
+    /// A length-prefixed UTF-8 string
+    fn string(&mut self) -> Result<String, StateError> {
+        let len = u32::from_le_bytes(self.array()?) as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| StateError::InvalidUtf8)
+    }
+}
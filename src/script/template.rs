@@ -0,0 +1,94 @@
+//! Compiling one script on several threads.
+//!
+//! A [`Script`] is neither `Send` nor `Sync`: its code lives in an LLVM context
+//! and its globals in cells the code writes without locking, so it stays on the
+//! thread that compiled it and the compiler rejects any attempt to share it. A
+//! host running scripts from a job system parses the source once into a
+//! [`ScriptTemplate`], which is both, and compiles a script from it on each
+//! thread. The scripts share only the parsed program; each has its own code,
+//! globals, lists and maps.
+
+use super::constants::substitute_constants;
+use super::{CompileError, Script, Value};
+use crate::parse_handler::ParseHandler;
+use crate::parser::compile_limits::CompileLimits;
+use crate::parser::dead_branches::prune_dead_branches;
+use crate::parser::language_options::LanguageOptions;
+use crate::parser::outline::{self, OutlineItem};
+use crate::parser::program::Program;
+use crate::utils::diagnostic::{Diagnostic, Severity};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A parsed and checked script, ready to be compiled any number of times on any
+/// thread
+#[derive(Debug, Clone)]
+pub struct ScriptTemplate {
+    pub(super) program: Arc<Program>,
+    pub(super) outline: Vec<OutlineItem>,
+    pub(super) options: LanguageOptions,
+    pub(super) constants: HashMap<String, Value>,
+}
+
+impl ScriptTemplate {
+    /// Parse `source` and check it as [`Script::compile_with_constants`] does,
+    /// reporting the same errors except those of code generation
+    pub fn new(
+        source: &str,
+        options: &LanguageOptions,
+        constants: &HashMap<String, Value>,
+    ) -> Result<ScriptTemplate, CompileError> {
+        // Limits are checked below, to report which one was exceeded
+        let unlimited = LanguageOptions {
+            limits: CompileLimits::default(),
+            ..options.clone()
+        };
+        let program =
+            ParseHandler::parse_program_with_options(source, &unlimited).map_err(|errors| {
+                CompileError::Parse(
+                    errors
+                        .iter()
+                        .map(|err| {
+                            Diagnostic::new(
+                                crate::check_handler::SYNTAX_ERROR,
+                                Severity::Error,
+                                err.to_string(),
+                                Some(err.span().into_range()),
+                            )
+                        })
+                        .collect(),
+                )
+            })?;
+        // The source has no file, so includes are only looked up in the include paths
+        let mut program = ParseHandler::resolve_includes(program, source, Path::new(""), options)
+            .map_err(|e| {
+            CompileError::Parse(vec![Diagnostic::new(
+                crate::check_handler::INCLUDE_ERROR,
+                Severity::Error,
+                e.to_string(),
+                None,
+            )])
+        })?;
+        options
+            .limits
+            .check(&program)
+            .map_err(CompileError::Limit)?;
+        if substitute_constants(&mut program, constants).map_err(CompileError::Constant)? > 0 {
+            prune_dead_branches(&mut program);
+        }
+        let outline = outline::outline(&program, source);
+        Ok(ScriptTemplate {
+            program: Arc::new(program),
+            outline,
+            options: options.clone(),
+            constants: constants.clone(),
+        })
+    }
+
+    /// Compile a new script from the template, on the calling thread. Its globals
+    /// start at 0, as in a fresh compile.
+    pub fn compile(&self) -> Result<Script, CompileError> {
+        Script::compile_template(self, false, None, None, None)
+    }
+}
@@ -1,5 +1,48 @@
+mod accessor_test;
+mod annotated_ir_test;
+mod bench_test;
+mod build_test;
+mod call_depth_test;
 mod codegen_comprehensive_test;
 mod codegen_test;
+mod collections_test;
+mod compile_limits_test;
+mod dead_branches_test;
+mod debug_info_test;
+mod debug_session_test;
+mod diagnostic_test;
+mod div_by_zero_test;
+mod enum_test;
+mod exact_integer_test;
+mod formatter_test;
+mod function_expr_test;
+mod highlight_test;
+mod include_test;
+mod inliner_test;
+mod language_options_test;
+mod nan_test;
+mod nesting_depth_test;
+mod number_format_test;
+mod output_sink_test;
 mod parser_test;
+mod println_test;
+mod profile_test;
+mod project_test;
+mod runtime_error_test;
+mod script_cache_test;
+mod script_constants_test;
+mod script_eval_test;
+mod script_state_test;
+mod script_template_test;
+mod script_test;
+mod shadow_linter_test;
+mod string_builtin_test;
 mod symbol_table_builder_tests;
+mod symbols_test;
 mod tests_helper;
+mod trace_test;
+mod type_infer_test;
+mod unicode_identifier_test;
+mod unroll_test;
+mod unused_linter_test;
+mod visitor_test;
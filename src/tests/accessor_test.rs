@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod tests {
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::build::*;
+    use crate::parser::expr::AccessorKind;
+    use crate::script::{CompileError, Script, Value};
+    use crate::tests::tests_helper::parse_gml;
+
+    #[test]
+    fn test_each_accessor_parses_to_its_kind() {
+        let cases = [
+            (
+                "l[| i]",
+                accessor(AccessorKind::List, ident("l"), [ident("i")]),
+            ),
+            (
+                r#"m[? "k"]"#,
+                accessor(AccessorKind::Map, ident("m"), [string("k")]),
+            ),
+            (
+                "g[# x, y + 1]",
+                accessor(
+                    AccessorKind::Grid,
+                    ident("g"),
+                    [ident("x"), binop_add(ident("y"), num(1.0))],
+                ),
+            ),
+            (
+                "a[@ 0]",
+                accessor(AccessorKind::ArrayRef, ident("a"), [num(0.0)]),
+            ),
+            (
+                "g[# 0, 0][| 1]",
+                accessor(
+                    AccessorKind::List,
+                    accessor(AccessorKind::Grid, ident("g"), [num(0.0), num(0.0)]),
+                    [num(1.0)],
+                ),
+            ),
+        ];
+        for (src, expected) in cases {
+            let parsed = without_spans(parse_gml(&format!("{};", src)));
+            assert_eq!(parsed, program([statement(expr_stmt(expected))]), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_accessor_formats_as_written() {
+        let src = "x = m[? \"k\"] + 1;";
+        let program = parse_gml(src);
+        let formatted = crate::parser::formatter::format_program(&program);
+        assert!(formatted.contains(r#"m[? "k"]"#), "{}", formatted);
+    }
+
+    #[test]
+    fn test_map_accessor_end_to_end() {
+        let src = r#"
+            var m = ds_map_create();
+            m[? "hp"] = 5;
+            m[? "hp"] = m[? "hp"] * 2;
+            var l = ds_list_create();
+            ds_list_add(l, 3, 4);
+            return m[? "hp"] + l[| 1];
+        "#;
+        let script = Script::compile(src).unwrap();
+        assert_eq!(script.run_main().unwrap(), Value::Number(14.0));
+    }
+
+    #[test]
+    fn test_unsupported_accessor_is_reported_at_its_span() {
+        let src = "var g = 0;\nvar v = g[# 1, 2];\n";
+        let Err(CompileError::Codegen(diagnostic)) = Script::compile(src) else {
+            panic!("expected a code generation error");
+        };
+        assert_eq!(diagnostic.code, 309);
+        assert!(
+            diagnostic
+                .message
+                .contains("'[#' accessor is not supported yet")
+        );
+        let span = diagnostic.span.unwrap();
+        assert_eq!(&src[span], "g[# 1, 2]");
+    }
+
+    #[test]
+    fn test_statements_around_accessors_still_parse() {
+        let src = "var a = 1;\nvar b = arr[@ 0];\nvar c = grid[# 1];\nvar d = 2;\n";
+        let (program, errors) = ParseHandler::parse_program_partial(src);
+        let program = program.unwrap();
+        // Only the grid accessor missing its second index is an error
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .to_string()
+                .contains("takes 2 indices but 1 were given")
+        );
+        assert_eq!(program.body.len(), 4);
+        assert!(program.error_spans().is_empty());
+    }
+}
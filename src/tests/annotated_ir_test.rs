@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::codegen::ir_generator::annotations::IrSnippet;
+    use crate::output_handler::OutputHandler;
+    use crate::tests::tests_helper::*;
+    use inkwell::context::Context;
+
+    const SRC: &str = "var base = 10;
+function add(a, b) {
+    return a + b;
+}
+var total = add(base, 5);
+";
+
+    fn annotations(src: &str) -> Vec<IrSnippet> {
+        let program = parse_gml(src);
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        ir_generator.record_annotations();
+        program.accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().verify().unwrap();
+        ir_generator.take_annotations()
+    }
+
+    #[test]
+    fn test_snippets_follow_source_order() {
+        let snippets = annotations(SRC);
+        let functions: Vec<&str> = snippets.iter().map(|s| s.function.as_str()).collect();
+        assert_eq!(functions, vec!["main", "add", "main"]);
+        assert_eq!(snippets[1].start, SRC.find("function").unwrap());
+        assert!(
+            snippets[1]
+                .ir
+                .iter()
+                .any(|line| line.starts_with("define double @add("))
+        );
+        assert!(
+            snippets[2]
+                .ir
+                .iter()
+                .any(|line| line.contains("call double @add"))
+        );
+    }
+
+    #[test]
+    fn test_listing_puts_ir_under_each_source_line() {
+        let listing = OutputHandler::annotated_ir(&annotations(SRC), SRC);
+        let lines: Vec<&str> = listing.lines().collect();
+        let position = |text: &str| {
+            lines
+                .iter()
+                .position(|line| line.contains(text))
+                .unwrap_or_else(|| panic!("{} not in\n{}", text, listing))
+        };
+
+        let base = position("1 | var base = 10;");
+        let add = position("2 | function add(a, b) {");
+        let total = position("5 | var total = add(base, 5);");
+        assert!(base < add && add < total, "{}", listing);
+        for line in [base, add + 3, total] {
+            assert!(!lines[line + 1].contains(" | "), "{}", listing);
+            assert!(!lines[line + 1].trim().is_empty(), "{}", listing);
+        }
+
+        // The function's body is listed under the function only
+        let fadd = position("fadd");
+        assert!(add < fadd && fadd < total, "{}", listing);
+        let main_ir = lines[base + 1..add]
+            .iter()
+            .chain(&lines[total + 1..])
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(!main_ir.contains("define"), "{}", main_ir);
+        assert!(!main_ir.contains("fadd"), "{}", main_ir);
+    }
+}
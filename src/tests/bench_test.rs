@@ -0,0 +1,12 @@
+#[cfg(test)]
+mod tests {
+    use crate::bench::{run_workload, workloads};
+
+    #[test]
+    fn test_workloads_run_one_iteration() {
+        for workload in workloads() {
+            let timings = run_workload(&workload, 1).unwrap();
+            assert_eq!(timings.execution.is_some(), workload.entry.is_some());
+        }
+    }
+}
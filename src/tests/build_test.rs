@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use crate::LanguageOptions;
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::build::*;
+    use crate::parser::expr::{AccessorKind, Expr};
+    use crate::tests::tests_helper::parse_gml;
+
+    #[test]
+    fn test_every_expression_builds_as_parsed() {
+        let (a, b) = (|| ident("a"), || ident("b"));
+        let cases: Vec<(&str, Expr)> = vec![
+            ("1.5", num(1.5)),
+            (r#""hi""#, string("hi")),
+            ("true", boolean(true)),
+            ("false", boolean(false)),
+            ("null", null()),
+            ("foo(1,,a)", call("foo", [num(1.0), undefined(), a()])),
+            ("a", a()),
+            ("Color.Red", member("Color", "Red")),
+            ("self", self_ref()),
+            ("other", other_ref()),
+            ("other.hp", field(other_ref(), "hp")),
+            (
+                "function(a) { return a; }",
+                function_expr(&["a"], [return_stmt(Some(a()))]),
+            ),
+            ("a[? b]", accessor(AccessorKind::Map, a(), [b()])),
+            ("a ? b : 1", ternary(a(), b(), num(1.0))),
+            ("!a", not(a())),
+            ("~a", bit_not(a())),
+            ("+a", positive(a())),
+            ("-a", negative(a())),
+            ("(a)", paren(a())),
+            ("++a", pre_increment(a())),
+            ("a++", post_increment(a())),
+            ("--a", pre_decrement(a())),
+            ("a--", post_decrement(a())),
+            ("a + b", binop_add(a(), b())),
+            ("a - b", binop_sub(a(), b())),
+            ("a * b", binop_mul(a(), b())),
+            ("a / b", binop_div(a(), b())),
+            ("a div b", binop_int_div(a(), b())),
+            ("a % b", binop_mod(a(), b())),
+            ("a mod b", binop_mod(a(), b())),
+            ("a > b", binop_gt(a(), b())),
+            ("a >= b", binop_ge(a(), b())),
+            ("a < b", binop_lt(a(), b())),
+            ("a <= b", binop_le(a(), b())),
+            ("a == b", binop_eq(a(), b())),
+            ("a != b", binop_ne(a(), b())),
+            ("a & b", binop_bit_and(a(), b())),
+            ("a ^ b", binop_bit_xor(a(), b())),
+            ("a | b", binop_bit_or(a(), b())),
+            ("a << b", binop_shl(a(), b())),
+            ("a >> b", binop_shr(a(), b())),
+            ("a && b", binop_and(a(), b())),
+            ("a ^^ b", binop_xor(a(), b())),
+            ("a || b", binop_or(a(), b())),
+            ("a = b", assign(a(), b())),
+            ("a += b", assign_add(a(), b())),
+            ("a -= b", assign_sub(a(), b())),
+            ("a *= b", assign_mul(a(), b())),
+            ("a /= b", assign_div(a(), b())),
+            ("a %= b", assign_mod(a(), b())),
+        ];
+        for (src, expected) in cases {
+            let parsed = without_spans(parse_gml(&format!("{};", src)));
+            assert_eq!(parsed, program([statement(expr_stmt(expected))]), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_every_statement_builds_as_parsed() {
+        let src = r#"
+            #include "lib.gml"
+            enum Color { Red, Green = 2 }
+            function f(a, b) {
+                var t, u = 1;
+                if (a) return a; else return;
+                switch (a) {
+                    case Color.Red:
+                        t = 1;
+                        break;
+                    default:
+                        continue;
+                }
+                function g() {}
+            }
+            { repeat (2) while (a) do a--; until (a); }
+            for (;;) {}
+        "#;
+        let options = LanguageOptions {
+            allow_switch: true,
+            ..LanguageOptions::default()
+        };
+        let parsed = ParseHandler::parse_program_with_options(src, &options).unwrap();
+
+        let t = || ident("t");
+        let expected = program([
+            include("lib.gml"),
+            enum_def("Color", [("Red", None), ("Green", Some(num(2.0)))]),
+            function(
+                "f",
+                &["a", "b"],
+                [
+                    var_decl([("t", None), ("u", Some(num(1.0)))]),
+                    if_stmt(
+                        ident("a"),
+                        return_stmt(Some(ident("a"))),
+                        Some(return_stmt(None)),
+                    ),
+                    switch(
+                        ident("a"),
+                        [
+                            case(
+                                [member("Color", "Red")],
+                                [expr_stmt(assign(t(), num(1.0))), break_stmt()],
+                            ),
+                            default_case([continue_stmt()]),
+                        ],
+                    ),
+                    function_stmt("g", &[], []),
+                ],
+            ),
+            statement(block([repeat(
+                num(2.0),
+                while_loop(
+                    ident("a"),
+                    do_until(expr_stmt(post_decrement(ident("a"))), ident("a")),
+                ),
+            )])),
+            statement(for_loop(None, None, None, block([]))),
+        ]);
+        assert_eq!(without_spans(parsed), expected);
+    }
+
+    #[test]
+    fn test_spans_take_part_in_equality() {
+        let src = "x = 1;";
+        assert_eq!(parse_gml(src), parse_gml(src));
+        assert_ne!(parse_gml(src), parse_gml(&format!(" {}", src)));
+        assert_eq!(
+            without_spans(parse_gml(src)),
+            without_spans(parse_gml(&format!(" {}", src)))
+        );
+    }
+}
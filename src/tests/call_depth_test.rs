@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::language_options::{DEFAULT_MAX_CALL_DEPTH, LanguageOptions};
+    use crate::script::{RuntimeError, Script, Value};
+    use crate::tests::tests_helper::compile_with;
+
+    const SRC: &str = r#"
+        function forever(n) { return forever(n + 1); }
+        function nest(n) {
+            if (n <= 0) { return 0; }
+            return nest(n - 1) + 1;
+        }
+        function fib(n) {
+            if (n < 2) { return n; }
+            return fib(n - 1) + fib(n - 2);
+        }
+    "#;
+
+    fn exceeded(function: &str) -> RuntimeError {
+        RuntimeError::Execution(format!(
+            "maximum call depth exceeded in function {}",
+            function
+        ))
+    }
+
+    #[test]
+    fn test_runaway_recursion_stops_with_an_error() {
+        // The default options on a thread of the default size, which cannot hold
+        // DEFAULT_MAX_CALL_DEPTH calls, so the stack check has to stop it
+        let (runaway, after) = std::thread::spawn(|| {
+            let script = Script::compile(SRC).unwrap();
+            let runaway = script.call("forever", &[Value::Number(0.0)]);
+            let after = script.call("nest", &[Value::Number(100.0)]);
+            (runaway, after)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(runaway, Err(exceeded("forever")));
+        // Every call returned through its decrement, so the next one starts at 0
+        assert_eq!(after, Ok(Value::Number(100.0)));
+    }
+
+    #[test]
+    fn test_call_depth_is_limited_by_default() {
+        assert_eq!(
+            LanguageOptions::default().max_call_depth,
+            Some(DEFAULT_MAX_CALL_DEPTH)
+        );
+    }
+
+    #[test]
+    fn test_limit_counts_open_calls() {
+        let options = LanguageOptions {
+            max_call_depth: Some(5),
+            ..LanguageOptions::default()
+        };
+        let script = compile_with(SRC, &options);
+        assert_eq!(
+            script.call("nest", &[Value::Number(4.0)]),
+            Ok(Value::Number(4.0))
+        );
+        assert_eq!(
+            script.call("nest", &[Value::Number(5.0)]),
+            Err(exceeded("nest"))
+        );
+    }
+
+    #[test]
+    fn test_fibonacci_runs_under_the_guard() {
+        let options = LanguageOptions {
+            max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH),
+            ..LanguageOptions::default()
+        };
+        let script = compile_with(SRC, &options);
+        assert_eq!(
+            script.call("fib", &[Value::Number(20.0)]),
+            Ok(Value::Number(6765.0))
+        );
+    }
+}
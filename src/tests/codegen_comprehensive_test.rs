@@ -554,81 +554,7 @@ mod tests {
 
     #[test]
     fn test_all_supported_ast_nodes() {
-        let src = r#"
-            function helper(x, y) {
-                return x * y;
-            }
-            
-            function test() {
-                // Literals
-                var num = 42;
-                var str = "test";
-                var bool_val = true;
-                var null_val = null;
-                
-                // Variable declarations and assignments
-                var a, b = 5, c;
-                a = 10;
-                c = a + b;
-                
-                // All arithmetic operators
-                var add = a + b;
-                var sub = a - b;
-                var mul = a * b;
-                var division = a / b;
-                var modulo = a % b;
-                
-                // All comparison operators
-                var eq = (a == b);
-                var ne = (a != b);
-                var lt = (a < c);
-                var le = (a <= c);
-                var gt = (c > a);
-                var ge = (c >= a);
-                
-                // All logical operators
-                var and_op = eq && ne;
-                var or_op = eq || ne;
-                var xor_op = eq ^^ ne;
-                var not_op = !eq;
-                
-                // All bitwise operators
-                var bit_and = a & b;
-                var bit_or = a | b;
-                var bit_xor = a ^ b;
-                var bit_not = ~a;
-                
-                // All unary operators
-                var pos = +a;
-                var neg = -a;
-                
-                // Increment/decrement
-                var pre_inc = ++a;
-                var post_inc = a++;
-                var pre_dec = --b;
-                var post_dec = b--;
-                
-                // Compound assignments
-                c += 1;
-                c -= 1;
-                c *= 2;
-                c /= 2;
-                c %= 10;
-                
-                // Ternary operator
-                var ternary = a > b ? a : b;
-                
-                // Function calls
-                var call_result = helper(3, 4);
-                
-                // Parenthesized expressions
-                var paren = (a + b) * (c - 1);
-                
-                // Return expression
-                return call_result + paren;
-            }
-        "#;
-        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        let result = compile_and_execute_function(ALL_AST_NODES, "test", &[]).unwrap();
         // Just ensure it compiles and runs without error
         assert!(result >= 0.0);
     }
@@ -127,6 +127,31 @@ mod tests {
         assert_eq!(result, 1.0);
     }
 
+    #[test]
+    fn test_modulo_takes_the_sign_of_the_dividend() {
+        let src = r#"
+            enum Folded { Value = -7 % 3 }
+            function test(a, b) { return a % b; }
+            function keyword(a, b) { return a mod b; }
+            function folded() { return Folded.Value; }
+            function wrap(angle) { return ((angle % 360) + 360) % 360; }
+        "#;
+        for (a, b, expected) in [(-7.0, 3.0, -1.0), (7.0, -3.0, 1.0), (-7.0, -3.0, -1.0)] {
+            for function in ["test", "keyword"] {
+                let result = compile_and_execute_function(src, function, &[a, b]).unwrap();
+                assert_eq!(result, expected, "{}({}, {})", function, a, b);
+            }
+        }
+        assert_eq!(
+            compile_and_execute_function(src, "folded", &[]).unwrap(),
+            -1.0
+        );
+        for (angle, expected) in [(-90.0, 270.0), (725.0, 5.0), (-720.0, 0.0), (359.5, 359.5)] {
+            let result = compile_and_execute_function(src, "wrap", &[angle]).unwrap();
+            assert_eq!(result, expected, "wrap({})", angle);
+        }
+    }
+
     #[test]
     fn test_complex_arithmetic() {
         let src = r#"
@@ -158,6 +183,30 @@ mod tests {
         assert_eq!(result, -42.0);
     }
 
+    #[test]
+    fn test_repeated_unary_negative() {
+        let src = r#"
+            function test() { return - - 5; }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 5.0);
+    }
+
+    #[test]
+    fn test_negated_post_increment() {
+        let src = r#"
+            function test() {
+                var x = 5;
+                var y = -(x++);
+                var z = -x++;
+                return y * 100 + z * 10 + x;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        // y is -5, z is -6 and x ends at 7
+        assert_eq!(result, -500.0 - 60.0 + 7.0);
+    }
+
     #[test]
     fn test_logical_not() {
         let src = r#"
@@ -376,6 +425,15 @@ mod tests {
         assert_eq!(result, 6.0); // 5 ^ 3 = 0101 ^ 0011 = 0110 = 6
     }
 
+    #[test]
+    fn test_shifts() {
+        let src = r#"
+            function test() { return (1 + 2 << 3) - (-16 >> 2); }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 28.0); // 3 << 3 = 24, -16 >> 2 = -4
+    }
+
     // ===============================
     // COMPOUND ASSIGNMENT TESTS
     // ===============================
@@ -594,6 +652,56 @@ mod tests {
         assert_eq!(result, 3.0);
     }
 
+    #[test]
+    fn test_do_until_numeric_condition() {
+        let src = r#"
+            function test() {
+                var n = 3;
+                var count = 0;
+                do {
+                    n = n - 1;
+                    count = count + 1;
+                } until (!n);
+                return count;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 3.0);
+    }
+
+    #[test]
+    fn test_do_until_true_runs_once() {
+        let src = r#"
+            function test() {
+                var i = 0;
+                do {
+                    i = i + 1;
+                } until (true);
+                return i;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 1.0);
+    }
+
+    #[test]
+    fn test_do_until_call_condition() {
+        let src = r#"
+            function done(n) {
+                return n >= 4;
+            }
+            function test() {
+                var i = 0;
+                do {
+                    i = i + 1;
+                } until (done(i));
+                return i;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 4.0);
+    }
+
     #[test]
     fn test_repeat_loop() {
         let src = r#"
@@ -672,6 +780,59 @@ mod tests {
         assert_eq!(result, 20.0); // 0+2+4+6+8 = 20
     }
 
+    #[test]
+    fn test_while_condition_post_increment_runs_each_iteration() {
+        let src = r#"
+            function test() {
+                var i = 0;
+                var iterations = 0;
+                while (i++ < 3) {
+                    iterations++;
+                }
+                return iterations * 10 + i;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        // Three passing checks (0, 1, 2), then 3 < 3 fails and still increments i to 4
+        assert_eq!(result, 34.0);
+    }
+
+    #[test]
+    fn test_for_condition_call_is_reemitted_each_iteration() {
+        let src = r#"
+            function below(n, limit) {
+                return n < limit;
+            }
+            function test() {
+                var calls = 0;
+                var body = 0;
+                for (var i = 0; below(calls++, 5); i++) {
+                    body++;
+                }
+                return calls * 100 + body * 10 + i;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        // The condition is evaluated six times: five passes and the final failing check
+        assert_eq!(result, 655.0);
+    }
+
+    #[test]
+    fn test_do_until_condition_mutates_state() {
+        let src = r#"
+            function test() {
+                var checks = 0;
+                var body = 0;
+                do {
+                    body++;
+                } until (++checks >= 3);
+                return checks * 10 + body;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 33.0);
+    }
+
     // ===============================
     // FUNCTION TESTS
     // ===============================
@@ -1110,4 +1271,1099 @@ mod tests {
         // (5+3)*2 - (5-3)/2 = 16 - 1 = 15; 15 + (5%3) = 15 + 2 = 17; 17 > 10 ? 17 : 0 = 17
         assert_eq!(result, 17.0);
     }
+
+    #[test]
+    fn test_call_with_missing_arguments() {
+        let src = r#"
+            function add3(a, b, c) {
+                return a + b + c;
+            }
+            function test() {
+                return add3(7);
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 7.0);
+    }
+
+    #[test]
+    fn test_var_initializers_run_left_to_right() {
+        let src = r#"
+            function test() {
+                var a = 1, b = a + 1, c = b * 2;
+                return c;
+            }
+        "#;
+        assert_eq!(compile_and_execute_function(src, "test", &[]).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn test_var_initializer_reads_variable_it_redeclares() {
+        let src = r#"
+            function test() {
+                var a = 7;
+                if (true) {
+                    var a = a;
+                    return a;
+                }
+                return -1;
+            }
+        "#;
+        assert_eq!(compile_and_execute_function(src, "test", &[]).unwrap(), 7.0);
+
+        let src = r#"
+            function test() {
+                var a = 7;
+                {
+                    var a = a + 1, b = a * 10;
+                    return a + b;
+                }
+            }
+        "#;
+        assert_eq!(
+            compile_and_execute_function(src, "test", &[]).unwrap(),
+            88.0
+        );
+    }
+
+    #[test]
+    fn test_redeclared_var_in_loop_updates_same_variable() {
+        let src = r#"
+            function test() {
+                var i = 0;
+                while (i < 5) {
+                    var i = i + 1;
+                }
+                return i;
+            }
+        "#;
+        assert_eq!(compile_and_execute_function(src, "test", &[]).unwrap(), 5.0);
+    }
+
+    #[test]
+    fn test_chained_assignment_of_comparison_into_declared_vars() {
+        let src = r#"
+            function test(x) {
+                var a;
+                var b;
+                var c;
+                a = b = c = (x > 0);
+                return a + b * 10 + c * 100;
+            }
+        "#;
+        assert_eq!(
+            compile_and_execute_function(src, "test", &[5.0]).unwrap(),
+            111.0
+        );
+        assert_eq!(
+            compile_and_execute_function(src, "test", &[-5.0]).unwrap(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_chained_assignment_mixed_bool_and_number_vars() {
+        let src = r#"
+            function test() {
+                var flag = false;
+                var n;
+                n = flag = 3;
+                if (flag) {
+                    return n;
+                }
+                return -1;
+            }
+        "#;
+        // Stored into a bool, 3 becomes true, which is 1 as a number
+        assert_eq!(compile_and_execute_function(src, "test", &[]).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_assigning_number_to_string_var_is_type_mismatch() {
+        let src = r#"
+            function test() {
+                var s = "text";
+                s = 1;
+                return 0;
+            }
+        "#;
+        let err = compile_and_execute_function(src, "test", &[]).unwrap_err();
+        assert!(err.contains("TypeMismatch"), "{}", err);
+    }
+
+    #[test]
+    fn test_compile_stats_per_function() {
+        use crate::codegen::ir_generator::IRGenerator;
+        use inkwell::context::Context;
+
+        let program = parse_gml(
+            r#"
+            function twice(x) {
+                return x * 2;
+            }
+            var total = twice(3);
+            function outer(n) {
+                var sum = 0;
+                for (var i = 0; i < n; i++) {
+                    sum += i;
+                }
+                function inner(m) {
+                    return m;
+                }
+                return sum + inner(1);
+            }
+        "#,
+        );
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        ir_generator.collect_stats();
+        program.accept(&mut ir_generator).unwrap();
+
+        let stats = ir_generator.take_stats();
+        let names: Vec<&str> = stats.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["main", "twice", "outer", "outer.inner"]);
+        assert!(stats.iter().all(|s| s.instructions > 0 && s.blocks > 0));
+
+        let outer = &stats[2];
+        assert!(outer.blocks > stats[1].blocks);
+        // n, argument_count, sum and i
+        assert!(outer.allocas >= 4, "{:?}", outer);
+
+        // Taking the stats drains them
+        assert!(ir_generator.take_stats().is_empty());
+    }
+
+    #[test]
+    fn test_max_loop_depth() {
+        use crate::codegen::ir_generator::IRGenerator;
+        use inkwell::context::Context;
+
+        let program = parse_gml(
+            r#"
+            while (false) {
+                repeat (2) {
+                    function f(n) {
+                        for (var i = 0; i < n; i++) {}
+                        return n;
+                    }
+                }
+            }
+            function g(n) {
+                do {
+                    while (n > 0) {
+                        repeat (n) { n -= 1; }
+                    }
+                } until (true);
+                return n;
+            }
+        "#,
+        );
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        assert_eq!(ir_generator.max_loop_depth(), 0);
+        program.accept(&mut ir_generator).unwrap();
+        assert_eq!(ir_generator.max_loop_depth(), 3);
+    }
+
+    #[test]
+    fn test_compile_stats_disabled_by_default() {
+        use crate::codegen::ir_generator::IRGenerator;
+        use inkwell::context::Context;
+
+        let program = parse_gml("function f() { return 1; }");
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        program.accept(&mut ir_generator).unwrap();
+        assert!(ir_generator.take_stats().is_empty());
+    }
+
+    #[test]
+    fn test_call_with_elided_arguments() {
+        let src = r#"
+            function digits(a, b, c) {
+                return argument_count * 1000 + a * 100 + b * 10 + c;
+            }
+            function test() {
+                return digits(1,,3);
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 3103.0);
+    }
+
+    #[test]
+    fn test_argument_count() {
+        let src = r#"
+            function count(a, b, c) {
+                return argument_count;
+            }
+            function test() {
+                return count(1) * 10 + count(1, 2, 3);
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 13.0);
+    }
+
+    #[test]
+    fn test_argument_count_from_host_call() {
+        let src = r#"
+            function count(a, b) {
+                return argument_count;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "count", &[1.0, 2.0]).unwrap();
+        assert_eq!(result, 2.0);
+    }
+
+    #[test]
+    fn test_too_many_arguments_is_error() {
+        let src = r#"
+            function add3(a, b, c) {
+                return a + b + c;
+            }
+            function test() {
+                return add3(1, 2, 3, 4, 5);
+            }
+        "#;
+        let err = compile_and_execute_function(src, "test", &[]).unwrap_err();
+        assert!(err.contains("ArgumentCountMismatch"), "{}", err);
+    }
+
+    #[test]
+    fn test_too_many_arguments_permissive() {
+        use crate::codegen::ir_generator::IRGenerator;
+        use crate::codegen::jit::JITExecutor;
+        use inkwell::context::Context;
+
+        let program = parse_gml(
+            r#"
+            function first(a) {
+                return a * 10 + argument_count;
+            }
+            function test() {
+                return first(1, 2, 3);
+            }
+        "#,
+        );
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        ir_generator.permissive_arity = true;
+        program.accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().verify().unwrap();
+
+        let executor = JITExecutor::new(ir_generator.get_module()).unwrap();
+        assert_eq!(executor.execute_function("test", &[]).unwrap(), 13.0);
+    }
+
+    #[test]
+    fn test_nested_function_called_from_outer() {
+        let src = r#"
+            function outer(x) {
+                function square(n) {
+                    return n * n;
+                }
+                return square(x) + 1;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "outer", &[4.0]).unwrap();
+        assert_eq!(result, 17.0);
+    }
+
+    #[test]
+    fn test_function_defined_in_if_block() {
+        let src = r#"
+            function test() {
+                var result = 0;
+                if (true) {
+                    function twice(n) { return n * 2; }
+                    result = twice(21);
+                }
+                return result;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 42.0);
+    }
+
+    #[test]
+    fn test_nested_function_not_visible_outside_block() {
+        let src = r#"
+            function test() {
+                {
+                    function hidden() { return 1; }
+                }
+                return hidden();
+            }
+        "#;
+        let err = compile_and_execute_function(src, "test", &[]).unwrap_err();
+        assert!(err.contains("UndefinedFunction(\"hidden\")"), "{}", err);
+    }
+
+    #[test]
+    fn test_nested_functions_with_same_name() {
+        let src = r#"
+            function a() {
+                function helper() { return 1; }
+                return helper();
+            }
+            function b() {
+                function helper() { return 2; }
+                return helper();
+            }
+            function test() {
+                return a() * 10 + b();
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 12.0);
+    }
+
+    #[test]
+    fn test_top_level_return_after_statements() {
+        let src = r#"
+            var a = 2;
+            var b = a * 3;
+            return a + b;
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 8.0);
+    }
+
+    #[test]
+    fn test_top_level_return_inside_if() {
+        let src = r#"
+            var x = 5;
+            if (x > 3) {
+                return 1;
+            } else {
+                return 2;
+            }
+            x = 10;
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_top_level_return_inside_while() {
+        let src = r#"
+            var i = 0;
+            while (true) {
+                i++;
+                if (i == 4) return i * 10;
+            }
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 40.0);
+    }
+
+    #[test]
+    fn test_top_level_return_inside_repeat() {
+        let src = r#"
+            var i = 0;
+            repeat (10) {
+                i++;
+                if (i >= 3) return i;
+            }
+            return -1;
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_top_level_return_followed_by_function() {
+        let src = r#"
+            return 7;
+            function later() {
+                return 3;
+            }
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 7.0);
+        assert_eq!(
+            compile_and_execute_function(src, "later", &[]).unwrap(),
+            3.0
+        );
+    }
+
+    #[test]
+    fn test_top_level_without_return_is_zero() {
+        let src = r#"
+            var x = 42;
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_repeat_zero_skips_body() {
+        let src = r#"
+            function test() {
+                var n = 0;
+                repeat (0) { n++; }
+                return n;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 0.0);
+    }
+
+    #[test]
+    fn test_if_else_branch_loops_without_braces() {
+        let src = r#"
+            function test() {
+                var counter = 0;
+                if (true) repeat(3) counter++;
+                if (false) repeat(5) counter++; else while (counter < 10) counter++;
+                return counter;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 10.0);
+    }
+
+    #[test]
+    fn test_if_branch_repeat_sets_counter() {
+        let src = r#"
+            function test() {
+                var counter = 0;
+                if (true) repeat(3) counter++;
+                return counter;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 3.0);
+    }
+
+    #[test]
+    fn test_repeat_negative_skips_body() {
+        let src = r#"
+            function test() {
+                var n = 0;
+                repeat (-3) { n++; }
+                return n;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 0.0);
+    }
+
+    #[test]
+    fn test_repeat_fractional_count_truncates() {
+        let src = r#"
+            function test() {
+                var n = 0;
+                repeat (2.9) { n++; }
+                return n;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 2.0);
+    }
+
+    #[test]
+    fn test_repeat_nan_count_skips_body() {
+        let src = r#"
+            function test(x) {
+                var n = 0;
+                repeat (x) { n++; }
+                return n;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[f64::NAN]).unwrap();
+        assert_eq!(result, 0.0);
+    }
+
+    #[test]
+    fn test_repeat_count_evaluated_once() {
+        let src = r#"
+            function test() {
+                var i = 3;
+                var n = 0;
+                repeat (i++) { n++; }
+                return n * 10 + i;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 34.0);
+    }
+
+    #[test]
+    fn test_repeat_boolean_count() {
+        let src = r#"
+            function test() {
+                var n = 0;
+                repeat (true) { n++; }
+                return n;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 1.0);
+    }
+
+    #[test]
+    fn test_bool_number_comparisons() {
+        // (source, numeric value where true is 1 and false is 0)
+        let operands = [
+            ("true", 1.0),
+            ("false", 0.0),
+            ("1", 1.0),
+            ("0", 0.0),
+            ("2", 2.0),
+            ("(2 > 1)", 1.0),
+        ];
+        type Compare = fn(f64, f64) -> bool;
+        let operators: [(&str, Compare); 6] = [
+            ("==", |a, b| a == b),
+            ("!=", |a, b| a != b),
+            ("<", |a, b| a < b),
+            ("<=", |a, b| a <= b),
+            (">", |a, b| a > b),
+            (">=", |a, b| a >= b),
+        ];
+
+        for (op, expected_fn) in operators {
+            for (lhs, lhs_value) in operands {
+                for (rhs, rhs_value) in operands {
+                    let src = format!("function test() {{ return {} {} {}; }}", lhs, op, rhs);
+                    let result = compile_and_execute_function(&src, "test", &[]).unwrap();
+                    let expected = if expected_fn(lhs_value, rhs_value) {
+                        1.0
+                    } else {
+                        0.0
+                    };
+                    assert_eq!(result, expected, "{} {} {}", lhs, op, rhs);
+                }
+            }
+        }
+    }
+
+    fn compile_to_ir(src: &str) -> String {
+        use crate::codegen::ir_generator::IRGenerator;
+        use inkwell::context::Context;
+
+        let program = parse_gml(src);
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        program.accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().print_to_string().to_string()
+    }
+
+    #[test]
+    fn test_ir_is_identical_across_compilations() {
+        let src = r#"
+            var total = 0;
+            function zeta(x) { return x * 2; }
+            function alpha(a, b) {
+                function helper(n) { return n + 1; }
+                return helper(a) + zeta(b);
+            }
+            function mid() { return alpha(1, 2); }
+            total = mid();
+        "#;
+        let first = compile_to_ir(src);
+        for _ in 0..5 {
+            assert_eq!(compile_to_ir(src), first);
+        }
+    }
+
+    #[test]
+    fn test_functions_emitted_in_source_order() {
+        let src = r#"
+            function zeta() { return 1; }
+            function alpha() {
+                function inner() { return 2; }
+                return inner();
+            }
+            function mid() { return 3; }
+            function beta() { return 4; }
+        "#;
+        let ir = compile_to_ir(src);
+        let order = [
+            "@main(",
+            "@zeta(",
+            "@alpha(",
+            "@alpha.inner(",
+            "@mid(",
+            "@beta(",
+        ];
+        let positions: Vec<usize> = order
+            .iter()
+            .map(|name| ir.find(&format!("define double {}", name)).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", ir);
+    }
+
+    #[test]
+    fn test_sequential_repeat_loops_get_unique_names() {
+        let src = r#"
+            function test() {
+                var n = 0;
+                repeat (2) { n++; }
+                repeat (3) { n++; }
+                repeat (4) { n++; }
+                return n;
+            }
+        "#;
+        let ir = compile_to_ir(src);
+        let test_fn = &ir[ir.find("define double @test(").unwrap()..];
+        for id in 0..3 {
+            assert!(
+                test_fn.contains(&format!("%repeat_counter.{} = alloca i32", id)),
+                "{}",
+                test_fn
+            );
+        }
+        let labels: Vec<&str> = test_fn
+            .lines()
+            .filter_map(|line| line.split_once(':').map(|(label, _)| label))
+            .filter(|label| label.starts_with("repeat_"))
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "repeat_cond.0",
+                "repeat_body.0",
+                "repeat_exit.0",
+                "repeat_cond.1",
+                "repeat_body.1",
+                "repeat_exit.1",
+                "repeat_cond.2",
+                "repeat_body.2",
+                "repeat_exit.2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_loops_get_unique_names_per_function() {
+        let src = r#"
+            function test() {
+                var n = 0;
+                while (n < 10) {
+                    while (n < 5) { n++; }
+                    for (var i = 0; i < 2; i++) { n++; }
+                    do { n++; } until (true);
+                }
+                return n;
+            }
+            function second() {
+                var m = 0;
+                while (m < 3) { m++; }
+                return m;
+            }
+        "#;
+        let ir = compile_to_ir(src);
+        let test_start = ir.find("define double @test(").unwrap();
+        let second_start = ir.find("define double @second(").unwrap();
+        let test_fn = &ir[test_start..second_start];
+        for label in [
+            "while_cond.0:",
+            "while_cond.1:",
+            "for_cond.2:",
+            "for_update.2:",
+            "do_body.3:",
+            "do_cond.3:",
+        ] {
+            assert!(test_fn.contains(label), "missing {} in {}", label, test_fn);
+        }
+        // Numbering restarts in every function
+        assert!(ir[second_start..].contains("while_cond.0:"));
+    }
+
+    #[test]
+    fn test_nested_repeat_counts_negative_and_fractional() {
+        let src = r#"
+            function test() {
+                var n = 0;
+                repeat (3.7) {
+                    repeat (-2) { n += 100; }
+                    repeat (1.5) { n++; }
+                    repeat (0.4) { n += 1000; }
+                }
+                for (var i = 0; i < 2.5; i += 0.5) { n += 10; }
+                return n;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 53.0);
+    }
+
+    #[test]
+    fn test_number_truthiness_boundary() {
+        let src = r#"
+            function truthy(x) {
+                if (x) return 1;
+                return 0;
+            }
+        "#;
+        for (value, expected) in [
+            (0.4, 0.0),
+            (0.5, 0.0),
+            (0.51, 1.0),
+            (1.0, 1.0),
+            (-1.0, 0.0),
+            (f64::NAN, 0.0),
+        ] {
+            let result = compile_and_execute_function(src, "truthy", &[value]).unwrap();
+            assert_eq!(result, expected, "if ({})", value);
+        }
+    }
+
+    #[test]
+    fn test_truthiness_in_loops_and_operators() {
+        let src = r#"
+            function test() {
+                var n = 0;
+                var running = 1;
+                while (running) { n++; if (n >= 3) running = 0; }
+                while (0.4) { n += 100; }
+                do { n += 10; } until (0.6);
+                for (var i = 0; 0.5; i++) { n += 1000; }
+                return n;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "test", &[]).unwrap();
+        assert_eq!(result, 13.0);
+
+        let src = r#"
+            function ops(x) {
+                var n = (x ? 1 : 0) + (!x ? 10 : 0) + (x && 1 ? 100 : 0);
+                return n + (x || 0 ? 1000 : 0) + (x ^^ 1 ? 10000 : 0);
+            }
+        "#;
+        let result = compile_and_execute_function(src, "ops", &[0.4]).unwrap();
+        assert_eq!(result, 10010.0);
+        let result = compile_and_execute_function(src, "ops", &[0.6]).unwrap();
+        assert_eq!(result, 1101.0);
+    }
+
+    #[test]
+    fn test_string_and_null_conditions_are_errors() {
+        for src in [
+            r#"if ("yes") { x = 1; }"#,
+            r#"var s = "a"; while (s) { s = "b"; }"#,
+            "var n = null; x = n ? 1 : 0;",
+        ] {
+            let err = compile_and_execute(src).unwrap_err();
+            assert!(err.contains("TypeMismatch"), "{}: {}", src, err);
+            assert!(
+                err.contains("cannot be used as a condition"),
+                "{}: {}",
+                src,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_int64_shift_round_trips_exactly() {
+        // 2^60 + 1 is not representable as a double; it must never become one
+        let src = r#"
+            function low_bit() {
+                var big = (int64(1) << 60) | 1;
+                return big - (int64(1) << 60);
+            }
+            function high_bits() {
+                var big = (int64(1) << 60) | 1;
+                return big >> 59;
+            }
+        "#;
+        let result = compile_and_execute_function(src, "low_bit", &[]).unwrap();
+        assert_eq!(result, 1.0);
+        let result = compile_and_execute_function(src, "high_bits", &[]).unwrap();
+        assert_eq!(result, 2.0);
+    }
+
+    #[test]
+    fn test_int64_truncates() {
+        let src = r#"
+            function truncate(x) { return int64(x); }
+            function halve(x) { return int64(x) / 2; }
+        "#;
+        for (value, expected) in [(7.9, 7.0), (-7.9, -7.0), (0.4, 0.0)] {
+            let result = compile_and_execute_function(src, "truncate", &[value]).unwrap();
+            assert_eq!(result, expected, "int64({})", value);
+        }
+        // `/` is still real division
+        let result = compile_and_execute_function(src, "halve", &[7.0]).unwrap();
+        assert_eq!(result, 3.5);
+    }
+
+    #[test]
+    fn test_bool_builtin_follows_truthiness() {
+        let src = r#"
+            function to_bool(x) { return bool(x); }
+        "#;
+        for (value, expected) in [(0.4, 0.0), (0.5, 0.0), (0.6, 1.0), (-1.0, 0.0)] {
+            let result = compile_and_execute_function(src, "to_bool", &[value]).unwrap();
+            assert_eq!(result, expected, "bool({})", value);
+        }
+        let result = compile_and_execute(r#"return string(bool(0.6)) == "true";"#);
+        assert_eq!(result.unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_verification_failure_names_the_function() {
+        use crate::codegen::ir_generator::{IRGenError, IRGenerator};
+        use crate::utils::diagnostic::Diagnostic;
+        use inkwell::context::Context;
+
+        let program = parse_gml("function fine(a) { return a + 1; }");
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        program.accept(&mut ir_generator).unwrap();
+
+        // A function whose block has no terminator, as a codegen bug would leave it
+        let number = context.f64_type();
+        let broken = ir_generator.module.add_function(
+            "broken",
+            number.fn_type(&[number.into()], false),
+            None,
+        );
+        ir_generator
+            .builder
+            .position_at_end(context.append_basic_block(broken, "entry"));
+        let param = broken.get_first_param().unwrap().into_float_value();
+        for _ in 0..5 {
+            ir_generator
+                .builder
+                .build_float_add(param, param, "sum")
+                .unwrap();
+        }
+
+        ir_generator.verify_excerpt_lines = 4;
+        let err = ir_generator
+            .verify_function(broken, "broken", Some(&(9..15)))
+            .unwrap_err();
+        let IRGenError::InvalidFunction { function, ir, span } = &err else {
+            panic!("Expected InvalidFunction, got {:?}", err);
+        };
+        assert_eq!(function, "broken");
+        assert_eq!(span, &Some(9..15));
+        assert!(ir.contains("define double @broken"), "{}", ir);
+        assert!(!ir.contains("@fine"), "{}", ir);
+        assert_eq!(ir.lines().count(), 5, "{}", ir);
+        assert!(ir.ends_with("more lines)"), "{}", ir);
+
+        let diagnostic = Diagnostic::from(&err);
+        assert_eq!(diagnostic.code, 307);
+        assert!(diagnostic.message.contains("function 'broken'"));
+
+        // Functions generated from the program pass
+        let fine = ir_generator.module.get_function("fine").unwrap();
+        assert!(ir_generator.verify_function(fine, "fine", None).is_ok());
+    }
+
+    #[test]
+    fn test_function_cannot_read_main_locals() {
+        let src = "var secret = 5;\nfunction peek() { return secret; }\nreturn peek();";
+        let err = compile_and_execute(src).unwrap_err();
+        assert!(err.contains("UndefinedVariable(\"secret\")"), "{}", err);
+    }
+
+    #[test]
+    fn test_main_locals_survive_function_definitions() {
+        let src = r#"
+            var a = 2;
+            function shadow(x) {
+                var a = x * 10;
+                function inner() { var a = 100; return a; }
+                return a + inner();
+            }
+            var b = a;
+            return a + b + shadow(1);
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 114.0);
+    }
+
+    #[test]
+    fn test_double_negation_is_truthiness() {
+        for (src, expected) in [
+            ("return !!true;", 1.0),
+            ("return !!false;", 0.0),
+            ("return !!7;", 1.0),
+            ("return !!0.3;", 0.0),
+            ("return !!!2;", 0.0),
+            ("var x = 4; return !(!x) + 1;", 2.0),
+        ] {
+            assert_eq!(compile_and_execute(src).unwrap(), expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_negated_comparisons_at_boundaries() {
+        let src = r#"
+            function negated(a, b) {
+                var low = (!(a < b) ? 1 : 0) + (!(a <= b) ? 2 : 0) + (!(a > b) ? 4 : 0);
+                return low + (!(a >= b) ? 8 : 0) + (!(a == b) ? 16 : 0) + (!(a != b) ? 32 : 0);
+            }
+        "#;
+        for (a, b) in [
+            (1.0, 2.0),
+            (2.0, 2.0),
+            (3.0, 2.0),
+            (-0.0, 0.0),
+            (0.1 + 0.2, 0.3),
+        ] {
+            let expected = [
+                !(a < b),
+                !(a <= b),
+                !(a > b),
+                !(a >= b),
+                !(a == b),
+                !(a != b),
+            ]
+            .iter()
+            .enumerate()
+            .map(|(bit, &set)| if set { f64::from(1 << bit) } else { 0.0 })
+            .sum::<f64>();
+            assert_eq!(
+                compile_and_execute_function(src, "negated", &[a, b]).unwrap(),
+                expected,
+                "{} vs {}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn test_negated_comparison_inverts_the_predicate() {
+        let ir = compile_to_ir("var x = 3;\nvar y = !(x < 5);");
+        let main_start = ir.find("define double @__col_init(").unwrap();
+        let main = &ir[main_start..main_start + ir[main_start..].find("\n}").unwrap()];
+        assert_eq!(main.matches("fcmp").count(), 1, "{}", main);
+        assert!(main.contains("fcmp uge"), "{}", main);
+        assert!(!main.contains("xor"), "{}", main);
+    }
+
+    const LOOP_FORMS: [&str; 5] = [
+        "while (x < 10) { BODY x++; }",
+        "for (; x < 10; x++) { BODY }",
+        "repeat (10) { BODY x++; }",
+        "do { BODY x++; } until (x >= 10);",
+        "while (true) { while (x < 10) { BODY x++; } break; }",
+    ];
+
+    const LOOP_BODIES: [(&str, f64); 5] = [
+        ("if (x == 5) return x;", 5.0),
+        ("if (x != 5) x = x; else return x;", 5.0),
+        ("if (x == 5) return x; else return x + 50;", 50.0),
+        ("return x + 7;", 7.0),
+        ("if (x == 5) break;", 105.0),
+    ];
+
+    #[test]
+    fn test_leaving_loops_at_top_level() {
+        for form in LOOP_FORMS {
+            for (body, expected) in LOOP_BODIES {
+                let src = format!(
+                    "var x = 0;\n{}\nreturn x + 100;",
+                    form.replace("BODY", body)
+                );
+                assert_eq!(compile_and_execute(&src), Ok(expected), "{}", src);
+            }
+        }
+    }
+
+    #[test]
+    fn test_leaving_loops_in_a_function() {
+        for form in LOOP_FORMS {
+            for (body, expected) in LOOP_BODIES {
+                let src = format!(
+                    "function f(x) {{\n{}\nreturn x + 100;\n}}",
+                    form.replace("BODY", body)
+                );
+                assert_eq!(
+                    compile_and_execute_function(&src, "f", &[0.0]),
+                    Ok(expected),
+                    "{}",
+                    src
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_continue_skips_to_the_next_iteration() {
+        for src in [
+            "var i = 0; var n = 0; while (i < 10) { i++; if (i % 2 == 0) continue; n++; } return n;",
+            "var n = 0; for (var i = 0; i < 10; i++) { if (i % 2 == 0) continue; n++; } return n;",
+            "var i = 0; var n = 0; repeat (10) { i++; if (i % 2 == 0) continue; n++; } return n;",
+            "var i = 0; var n = 0; do { i++; if (i % 2 == 0) continue; n++; } until (i >= 10); return n;",
+        ] {
+            assert_eq!(compile_and_execute(src), Ok(5.0), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_break_and_continue_outside_loops_are_errors() {
+        for src in [
+            "break;",
+            "if (true) continue;",
+            "while (false) {} function f() { break; }",
+        ] {
+            let error = compile_and_execute(src).unwrap_err();
+            assert!(error.contains("outside of a loop"), "{}: {}", src, error);
+        }
+    }
+
+    #[test]
+    fn test_call_split_across_lines() {
+        let src = "function add3(a, b, c) {\n    return a + b * c\n}\n\
+                   var total = add3(\n    1,\n    2,\n    (3\n    + 4)\n)\n\
+                   return total\n";
+        assert_eq!(compile_and_execute(src), Ok(15.0));
+    }
+
+    #[test]
+    fn test_null_equals_only_null() {
+        for (src, expected) in [
+            ("return null == null;", 1.0),
+            ("return null != null;", 0.0),
+            ("var x = 0; return x == null;", 0.0),
+            ("var x = 0; return null != x;", 1.0),
+            (r#"return null != "a";"#, 1.0),
+            (r#"return null == "";"#, 0.0),
+            (r#"var s = null; return s == null;"#, 1.0),
+            (r#"var s = "a"; return s == null;"#, 0.0),
+            (r#"var s = null; var t = null; return s == t;"#, 1.0),
+            (r#"var s = null; var t = ""; return s == t;"#, 0.0),
+            ("return true == null;", 0.0),
+            ("if (null) { return 1; } else { return 2; }", 2.0),
+            ("return !null;", 1.0),
+        ] {
+            assert_eq!(compile_and_execute(src), Ok(expected), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_null_operands_are_errors() {
+        for src in [
+            "x = null * 2;",
+            "x = 1 + null;",
+            "x = null < 1;",
+            r#"x = "a" + null;"#,
+        ] {
+            let err = compile_and_execute(src).unwrap_err();
+            assert!(err.contains("TypeMismatch"), "{}: {}", src, err);
+            assert!(
+                err.contains("null cannot be an operand"),
+                "{}: {}",
+                src,
+                err
+            );
+        }
+
+        // A string variable holding null is only found out when the code runs
+        for src in [
+            r#"var s = null; return s + "a";"#,
+            r#"var s = null; return s < "a";"#,
+        ] {
+            let err = compile_and_execute(src).unwrap_err();
+            assert!(
+                err.contains("null cannot be an operand"),
+                "{}: {}",
+                src,
+                err
+            );
+        }
+    }
 }
@@ -0,0 +1,163 @@
+#[cfg(test)]
+mod tests {
+    use crate::ErrorKind;
+    use crate::script::{Script, Value};
+    use crate::tests::tests_helper::*;
+
+    #[test]
+    fn test_list_sum() {
+        let src = r#"
+            var list = ds_list_create();
+            for (var i = 1; i <= 5; i++) {
+                ds_list_add(list, i * 10);
+            }
+            var total = 0;
+            for (var i = 0; i < ds_list_size(list); i++) {
+                total += ds_list_find_value(list, i);
+            }
+            ds_list_destroy(list);
+            return total;
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 150.0);
+    }
+
+    #[test]
+    fn test_list_add_several_values() {
+        let src = r#"
+            var list = ds_list_create();
+            ds_list_add(list, 1, 2, 3);
+            return ds_list_size(list) * 10 + ds_list_find_value(list, 2);
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 33.0);
+    }
+
+    #[test]
+    fn test_list_read_past_end_is_undefined() {
+        let script = Script::compile(
+            "function test() { var list = ds_list_create(); ds_list_add(list, 7); return ds_list_find_value(list, 5); }",
+        )
+        .unwrap();
+        assert_eq!(script.call("test", &[]).unwrap(), Value::Number(0.0));
+        assert_eq!(script.take_runtime_error(), None);
+    }
+
+    #[test]
+    fn test_map_round_trip() {
+        let src = r#"
+            var map = ds_map_create();
+            ds_map_set(map, "hp", 42);
+            ds_map_set(map, 1, 5);
+            ds_map_set(map, "1", 7);
+            var found = ds_map_exists(map, "hp") && !ds_map_exists(map, "mp");
+            var total = ds_map_find_value(map, "hp") + ds_map_find_value(map, 1) * 100;
+            return total + ds_map_find_value(map, "1") * 1000 + found * 10000;
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 17542.0);
+    }
+
+    #[test]
+    fn test_handles_persist_across_calls() {
+        let script = Script::compile(
+            r#"
+            var scores = 0;
+            function init() { scores = ds_map_create(); return scores; }
+            function record(x) { ds_map_set(scores, "last", x); return 0; }
+            function last() { return ds_map_find_value(scores, "last"); }
+            "#,
+        )
+        .unwrap();
+        script.call("init", &[]).unwrap();
+        script.call("record", &[Value::Number(9.0)]).unwrap();
+        assert_eq!(script.call("last", &[]).unwrap(), Value::Number(9.0));
+    }
+
+    #[test]
+    fn test_double_destroy_is_safe() {
+        let script = Script::compile(
+            r#"
+            function test() {
+                var list = ds_list_create();
+                ds_list_destroy(list);
+                ds_list_destroy(list);
+                return 1;
+            }
+            function use_after_destroy() {
+                var map = ds_map_create();
+                ds_map_destroy(map);
+                return ds_map_find_value(map, "x");
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(script.call("test", &[]).unwrap(), Value::Number(1.0));
+        let error = script.take_runtime_error().unwrap();
+        assert_eq!(error.kind, ErrorKind::InvalidHandle);
+        assert!(error.message.contains("ds_list_destroy"), "{}", error);
+        assert_eq!(script.take_runtime_error(), None);
+
+        assert_eq!(
+            script.call("use_after_destroy", &[]).unwrap(),
+            Value::Number(0.0)
+        );
+        let error = script.take_runtime_error().unwrap();
+        assert!(error.message.contains("is not a ds_map"), "{}", error);
+    }
+
+    #[test]
+    fn test_handles_are_references_and_copies_are_independent() {
+        let src = r#"
+            var a = ds_list_create();
+            ds_list_add(a, 1);
+            var b = a;
+            ds_list_add(b, 2);
+            var c = ds_list_create();
+            ds_list_copy(c, a);
+            ds_list_add(c, 3);
+            var m = ds_map_create();
+            ds_map_set(m, "x", 1);
+            var n = ds_map_create();
+            ds_map_copy(n, m);
+            ds_map_set(n, "x", 5);
+            return ds_list_size(a) * 100 + ds_list_size(c) * 10 + ds_map_find_value(m, "x");
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 231.0);
+    }
+
+    #[test]
+    fn test_destroyed_collections_are_freed() {
+        let script = Script::compile(
+            r#"
+            function churn(n) {
+                repeat (n) {
+                    var list = ds_list_create();
+                    ds_list_add(list, 1, 2, 3);
+                    var copy = ds_list_create();
+                    ds_list_copy(copy, list);
+                    var map = ds_map_create();
+                    ds_map_set(map, "size", ds_list_size(copy));
+                    ds_list_destroy(list);
+                    ds_list_destroy(copy);
+                    ds_map_destroy(map);
+                }
+            }
+            function keep() { return ds_list_create(); }
+            "#,
+        )
+        .unwrap();
+        for _ in 0..10 {
+            script.call("churn", &[Value::Number(1000.0)]).unwrap();
+            assert_eq!(script.live_collections(), 0);
+        }
+        script.call("keep", &[]).unwrap();
+        assert_eq!(script.live_collections(), 1);
+        assert_eq!(script.take_runtime_error(), None);
+    }
+
+    #[test]
+    fn test_collection_argument_errors() {
+        let err = compile_and_execute("x = ds_list_add(ds_list_create());").unwrap_err();
+        assert!(err.contains("ArgumentCountMismatch"), "{}", err);
+        let err = compile_and_execute(r#"x = ds_list_add(ds_list_create(), "a");"#).unwrap_err();
+        assert!(err.contains("TypeMismatch"), "{}", err);
+    }
+}
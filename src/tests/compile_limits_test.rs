@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests {
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::compile_limits::{CompileLimits, Limit, LimitExceeded};
+    use crate::parser::language_options::LanguageOptions;
+    use crate::script::{CompileError, Script};
+    use crate::tests::tests_helper::parse_gml;
+
+    /// A function of `count` statements
+    fn many_statements(count: usize) -> String {
+        format!(
+            "function big() {{\n    var x = 0;\n{}}}\n",
+            "    x += 1;\n".repeat(count - 1)
+        )
+    }
+
+    fn with_statement_limit(max: usize) -> LanguageOptions {
+        LanguageOptions {
+            limits: CompileLimits {
+                max_statements_per_function: max,
+                ..CompileLimits::default()
+            },
+            ..LanguageOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_statement_limit_is_reported_with_the_excess() {
+        let src = many_statements(10_000);
+        let errors = ParseHandler::parse_program_with_options(&src, &with_statement_limit(5_000))
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "10000 statements in function 'big' exceed the limit of 5000 by 5000"
+        );
+    }
+
+    #[test]
+    fn test_program_within_raised_limits_parses() {
+        let src = many_statements(10_000);
+        assert!(
+            ParseHandler::parse_program_with_options(&src, &with_statement_limit(10_000)).is_ok()
+        );
+        // The defaults are unlimited
+        assert!(ParseHandler::parse_program(&src).is_ok());
+    }
+
+    #[test]
+    fn test_script_reports_the_exceeded_limit() {
+        let src = many_statements(10_000);
+        let Err(CompileError::Limit(exceeded)) =
+            Script::compile_with_options(&src, &with_statement_limit(5_000))
+        else {
+            panic!("Expected the statement limit to be exceeded");
+        };
+        assert_eq!(
+            exceeded,
+            LimitExceeded {
+                limit: Limit::StatementsPerFunction,
+                actual: 10_000,
+                max: 5_000,
+                function: Some("big".to_string()),
+            }
+        );
+        assert_eq!(exceeded.excess(), 5_000);
+    }
+
+    #[test]
+    fn test_each_limit_is_measured() {
+        let program = parse_gml(
+            r#"
+            var greeting = "hello";
+            function f(a) {
+                function g() { return "!"; }
+                if (a) { a = a + 1; }
+                return g();
+            }
+            "#,
+        );
+        let exceeded = |limits: CompileLimits| limits.check(&program).unwrap_err();
+        let unlimited = CompileLimits::default();
+        assert_eq!(unlimited.check(&program), Ok(()));
+
+        let top_level = exceeded(CompileLimits {
+            max_top_level_items: 1,
+            ..unlimited
+        });
+        assert_eq!(
+            (top_level.limit, top_level.actual),
+            (Limit::TopLevelItems, 2)
+        );
+
+        let functions = exceeded(CompileLimits {
+            max_functions: 1,
+            ..unlimited
+        });
+        assert_eq!((functions.limit, functions.actual), (Limit::Functions, 2));
+
+        // The definition of `g`, the `if`, its block, the assignment and the
+        // `return`; the statements of `g` count towards `g`
+        let statements = exceeded(CompileLimits {
+            max_statements_per_function: 3,
+            ..unlimited
+        });
+        assert_eq!(statements.actual, 5);
+        assert_eq!(statements.function.as_deref(), Some("f"));
+
+        // "hello", "!", the condition, the five nodes of a = a + 1, and g()
+        let expressions = exceeded(CompileLimits {
+            max_expressions: 0,
+            ..unlimited
+        });
+        assert_eq!(
+            (expressions.limit, expressions.actual),
+            (Limit::Expressions, 9)
+        );
+
+        let strings = exceeded(CompileLimits {
+            max_string_bytes: 5,
+            ..unlimited
+        });
+        assert_eq!(
+            strings.to_string(),
+            "6 bytes of string literals exceed the limit of 5 by 1"
+        );
+    }
+
+    #[test]
+    fn test_nesting_and_identifier_limits_are_options() {
+        let options = |limits: CompileLimits| LanguageOptions {
+            limits,
+            ..LanguageOptions::default()
+        };
+        let src = "var long_name = ((((1))));";
+        assert!(
+            ParseHandler::parse_program_with_options(src, &options(CompileLimits::default()))
+                .is_ok()
+        );
+
+        let errors = ParseHandler::parse_program_with_options(
+            src,
+            &options(CompileLimits {
+                max_nesting_depth: 4,
+                ..CompileLimits::default()
+            }),
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "expression too deeply nested (limit 4)"
+        );
+
+        let errors = ParseHandler::parse_program_with_options(
+            src,
+            &options(CompileLimits {
+                max_identifier_length: 8,
+                ..CompileLimits::default()
+            }),
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "identifier 'long_name...' exceeds maximum length of 8 (was 9)"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::dead_branches::prune_dead_branches;
+    use crate::tests::tests_helper::*;
+
+    fn debug_script(flag: &str) -> String {
+        format!(
+            r#"
+            var total = 1;
+            if ({flag}) {{
+                var logged = heavy_logging(total);
+                total += logged;
+            }} else {{
+                total = total * 10;
+            }}
+            while ({flag}) {{
+                total += 100;
+                break;
+            }}
+            return total + ({flag} ? 1000 : 2000);
+            "#
+        )
+    }
+
+    const HELPERS: &str = "function heavy_logging(x) { return x + 5; }";
+
+    #[test]
+    fn pruned_program_drops_the_dead_branch() {
+        let mut program = parse_gml(&format!("{}{}", HELPERS, debug_script("false")));
+        assert_eq!(prune_dead_branches(&mut program), 3);
+        let pruned = format!("{:?}", &program.body[1..]);
+        assert!(!pruned.contains("heavy_logging"), "{}", pruned);
+        assert!(!pruned.contains("While"), "{}", pruned);
+        assert!(!pruned.contains("Ternary"), "{}", pruned);
+    }
+
+    #[test]
+    fn results_are_unchanged_for_both_outcomes() {
+        for (flag, expected) in [("true", 1107.0), ("false", 2010.0)] {
+            let src = format!("{}{}", HELPERS, debug_script(flag));
+            assert_eq!(compile_and_execute(&src).unwrap(), expected, "{}", flag);
+
+            let mut program = parse_gml(&src);
+            assert!(prune_dead_branches(&mut program) > 0);
+            assert_eq!(execute_program(&program).unwrap(), expected, "{}", flag);
+        }
+    }
+
+    #[test]
+    fn conditions_reading_variables_are_never_pruned() {
+        let src = r#"
+            var DEBUG = false;
+            var total = 1;
+            if (DEBUG) { total = 2; }
+            while (DEBUG) { total = 3; }
+            return DEBUG ? 4 : total;
+        "#;
+        let mut program = parse_gml(src);
+        assert_eq!(prune_dead_branches(&mut program), 0);
+        assert_eq!(execute_program(&program).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn variables_declared_in_pruned_branches_stay_declared() {
+        let src = r#"
+            var kept = 7;
+            if (0) {
+                var kept = 1;
+                var late = 2;
+            }
+            late = kept + 1;
+            return late;
+        "#;
+        let mut program = parse_gml(src);
+        assert_eq!(prune_dead_branches(&mut program), 1);
+        let pruned = format!("{:?}", program.body);
+        assert!(pruned.contains("\"late\", None"), "{}", pruned);
+        assert!(!pruned.contains("\"kept\", None"), "{}", pruned);
+        assert_eq!(execute_program(&program).unwrap(), 8.0);
+    }
+}
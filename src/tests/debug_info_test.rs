@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::codegen::jit::JITExecutor;
+    use crate::tests::tests_helper::*;
+    use inkwell::context::Context;
+    use std::path::Path;
+
+    const SRC: &str = "var base = 10;
+function add(a, b) {
+    return a + b;
+}
+function twice(x) {
+    var doubled = add(x, x);
+    return doubled;
+}
+return twice(base) + 1;";
+
+    #[test]
+    fn test_debug_info_describes_functions_and_lines() {
+        let program = parse_gml(SRC);
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        ir_generator.enable_debug_info(Path::new("scripts/twice.gml"), SRC);
+        program.accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().verify().unwrap();
+
+        let ir = ir_generator.get_module().print_to_string().to_string();
+        assert!(ir.contains("!DIFile(filename: \"twice.gml\", directory: \"scripts\")"));
+        for (name, line) in [("main", 1), ("add", 2), ("twice", 5)] {
+            let subprogram = format!("!DISubprogram(name: \"{}\"", name);
+            let start = ir.find(&subprogram).unwrap_or_else(|| panic!("{}", ir));
+            let entry = &ir[start..ir[start..].find('\n').unwrap() + start];
+            assert!(entry.contains(&format!("line: {},", line)), "{}", entry);
+        }
+        // `var doubled` starts on line 6, after four spaces and `var `
+        assert!(ir.contains("!DILocation(line: 6, column: 9,"), "{}", ir);
+
+        let executor = JITExecutor::new(ir_generator.get_module()).unwrap();
+        assert_eq!(executor.execute_main().unwrap(), 21.0);
+    }
+
+    #[test]
+    fn test_no_debug_info_by_default() {
+        let program = parse_gml(SRC);
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        program.accept(&mut ir_generator).unwrap();
+
+        let ir = ir_generator.get_module().print_to_string().to_string();
+        assert!(!ir.contains("!DISubprogram"));
+        assert_eq!(compile_and_execute(SRC).unwrap(), 21.0);
+    }
+}
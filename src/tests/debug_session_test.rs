@@ -0,0 +1,221 @@
+#[cfg(test)]
+mod tests {
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::language_options::{DivByZeroPolicy, LanguageOptions};
+    use crate::script::debug::{DebugSession, StepEvent};
+    use crate::script::{CompileError, RuntimeError, Script, Value};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn session(src: &str) -> DebugSession {
+        let program = ParseHandler::parse_program(src).unwrap();
+        DebugSession::new(&program, src).unwrap()
+    }
+
+    /// The result of the top-level code of `src` and what it printed, run by a
+    /// script and by a session with the same `options`
+    fn run_both(
+        src: &str,
+        options: &LanguageOptions,
+    ) -> [(Result<Value, RuntimeError>, String); 2] {
+        let printed = Rc::new(RefCell::new(String::new()));
+        let capture = |printed: &Rc<RefCell<String>>| {
+            let printed = Rc::clone(printed);
+            move |line: &str| printed.borrow_mut().push_str(line)
+        };
+
+        let script = Script::compile_with_options(src, options).unwrap();
+        script.set_print_callback(capture(&printed));
+        let compiled = (script.run_main(), printed.take());
+
+        let program = ParseHandler::parse_program(src).unwrap();
+        let mut session = DebugSession::new_with_options(&program, src, options).unwrap();
+        session.set_print_callback(capture(&printed));
+        let debugged = match session.run_to_breakpoint() {
+            // The host gets a number from the top-level code, as from a script
+            Ok(StepEvent::Finished(value)) => Ok(Value::Number(value.as_number().unwrap())),
+            Ok(event) => panic!("unexpected {:?}", event),
+            Err(e) => Err(e),
+        };
+        [compiled, (debugged, printed.take())]
+    }
+
+    fn assert_same(src: &str, options: &LanguageOptions) {
+        let [compiled, debugged] = run_both(src, options);
+        assert_eq!(compiled, debugged, "{}", src);
+    }
+
+    fn number(locals: &[(String, Value)], name: &str) -> Option<f64> {
+        locals.iter().find_map(|(local, value)| match value {
+            Value::Number(n) if local == name => Some(*n),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_stepping_through_a_loop() {
+        let src =
+            "var total = 0;\nfor (var i = 0; i < 3; i++) {\n    total += i;\n}\nreturn total;\n";
+        let mut session = session(src);
+        let mut lines = Vec::new();
+        let mut counters = Vec::new();
+        let result = loop {
+            match session.step().unwrap() {
+                StepEvent::Statement { span, line } => {
+                    if line == 3 {
+                        assert_eq!(&src[span], "total");
+                        counters.push(number(&session.globals(), "i").unwrap());
+                    }
+                    lines.push(line);
+                }
+                StepEvent::Finished(value) => break value,
+                event => panic!("unexpected {:?}", event),
+            }
+        };
+        assert_eq!(lines, [1, 2, 3, 2, 3, 2, 3, 2, 5]);
+        assert_eq!(counters, [0.0, 1.0, 2.0]);
+        assert_eq!(result, Value::Number(3.0));
+        assert_eq!(session.locals(), session.globals());
+        // A finished session stays finished
+        assert_eq!(session.step(), Ok(StepEvent::Finished(Value::Number(3.0))));
+    }
+
+    #[test]
+    fn test_breakpoint_in_function() {
+        let src = "function add(a, b) {\n    var sum = a + b;\n    return sum;\n}\n\
+                   var total = 0;\nfor (var i = 0; i < 4; i++) {\n    total = add(total, i);\n}\n\
+                   return total;\n";
+        let mut session = session(src);
+        session.set_breakpoint(3);
+        for _ in 0..3 {
+            let event = session.run_to_breakpoint().unwrap();
+            assert!(
+                matches!(event, StepEvent::Statement { line: 3, .. }),
+                "{:?}",
+                event
+            );
+        }
+        let locals = session.locals();
+        assert_eq!(number(&locals, "a"), Some(1.0));
+        assert_eq!(number(&locals, "b"), Some(2.0));
+        assert_eq!(number(&locals, "sum"), Some(3.0));
+        assert_eq!(number(&session.globals(), "i"), Some(2.0));
+        assert_eq!(session.call_stack(), ["main", "add"]);
+
+        assert_eq!(
+            session.step(),
+            Ok(StepEvent::FunctionExited {
+                name: "add".to_string(),
+                value: Value::Number(3.0),
+            })
+        );
+        assert_eq!(session.call_stack(), ["main"]);
+        let event = session.step().unwrap();
+        assert!(
+            matches!(event, StepEvent::Statement { line: 6, .. }),
+            "{:?}",
+            event
+        );
+        session.step().unwrap();
+        assert_eq!(
+            session.step(),
+            Ok(StepEvent::FunctionEntered {
+                name: "add".to_string()
+            })
+        );
+
+        session.clear_breakpoint(3);
+        assert_eq!(
+            session.run_to_breakpoint(),
+            Ok(StepEvent::Finished(Value::Number(6.0)))
+        );
+    }
+
+    #[test]
+    fn test_deep_recursion() {
+        let src = "function count(n) {\n    if (n <= 0) return 0;\n    return 1 + count(n - 1);\n}\n\
+                   return count(5000);\n";
+        assert_eq!(
+            session(src).run_to_breakpoint(),
+            Ok(StepEvent::Finished(Value::Number(5000.0)))
+        );
+    }
+
+    #[test]
+    fn test_unsupported_code_is_reported_up_front() {
+        let src = "var x = 1;\nself.hp = x;\n";
+        let program = ParseHandler::parse_program(src).unwrap();
+        let Err(CompileError::Codegen(diagnostic)) = DebugSession::new(&program, src) else {
+            panic!("expected a codegen error");
+        };
+        assert_eq!(diagnostic.code, 309);
+        assert!(diagnostic.message.contains("cannot be debugged yet"));
+    }
+
+    #[test]
+    fn test_values_print_as_compiled_code_prints_them() {
+        let src = "function show() {\n    println(true, \" \", 1 < 0, \" \", string(2 > 1));\n\
+                   println(0.1 + 0.2, \" \", 1 / 4, \" \", null);\n    return 0;\n}\n\
+                   return show();\n";
+        let [compiled, debugged] = run_both(src, &LanguageOptions::default());
+        assert_eq!(compiled.1, "true false true\n0.3 0.25 undefined\n");
+        assert_eq!(compiled, debugged);
+    }
+
+    #[test]
+    fn test_comparisons_use_the_epsilon() {
+        let src = "math_set_epsilon(0.1);\nvar hits = 0;\nif (1 == 1.05) hits += 1;\n\
+                   if (0.95 >= 1) hits += 2;\nif (1 != 1.05) hits += 4;\n\
+                   if (1 < 1.05) hits += 8;\nreturn hits + math_get_epsilon();\n";
+        let [compiled, debugged] = run_both(src, &LanguageOptions::default());
+        assert_eq!(compiled.0, Ok(Value::Number(11.1)));
+        assert_eq!(compiled, debugged);
+    }
+
+    #[test]
+    fn test_division_by_zero_follows_the_policy() {
+        for policy in [
+            DivByZeroPolicy::Infinity,
+            DivByZeroPolicy::Error,
+            DivByZeroPolicy::Zero,
+        ] {
+            let options = LanguageOptions {
+                div_by_zero: policy,
+                ..LanguageOptions::default()
+            };
+            assert_same("var zero = 0;\nreturn 5 / zero;\n", &options);
+            assert_same("var zero = 0;\nreturn 5 div zero;\n", &options);
+            assert_same("var zero = 0;\nreturn 5 % zero == 0;\n", &options);
+        }
+    }
+
+    #[test]
+    fn test_call_depth_and_strict_math_follow_the_options() {
+        let options = LanguageOptions {
+            max_call_depth: Some(5),
+            strict_math: true,
+            ..LanguageOptions::default()
+        };
+        let nest =
+            "function nest(n) {\n    if (n <= 0) return 0;\n    return nest(n - 1) + 1;\n}\n";
+        assert_same(&format!("{}return nest(4);\n", nest), &options);
+        assert_same(&format!("{}return nest(5);\n", nest), &options);
+        assert_same("var x = 0 / 0;\nreturn x < 1;\n", &options);
+    }
+
+    #[test]
+    fn test_string_conditions_are_rejected() {
+        let src = "function f() {\n    var s = \"a\";\n    if (s) return 1;\n    return 0;\n}\n\
+                   return f();\n";
+        let Err(CompileError::Codegen(diagnostic)) = Script::compile(src) else {
+            panic!("expected a codegen error");
+        };
+        assert!(diagnostic.message.contains("cannot be used as a condition"));
+        assert_eq!(
+            session(src).run_to_breakpoint(),
+            Err(RuntimeError::Execution(
+                "A string cannot be used as a condition".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,548 @@
+#[cfg(test)]
+mod tests {
+    use crate::check_handler::{
+        CheckHandler, CheckMode, CheckOptions, INCLUDE_ERROR, READ_ERROR, SYNTAX_ERROR,
+    };
+    use crate::codegen::ir_generator::{IRGenError, MISSING_RETURN, UNREACHABLE_CODE};
+    use crate::parser::language_options::LanguageOptions;
+    use crate::parser::visitor::condition_linter::ASSIGNMENT_IN_CONDITION;
+    use crate::utils::diagnostic::{Diagnostic, Severity};
+    use crate::utils::line_index::LineIndex;
+    use std::fs;
+    use std::path::Path;
+
+    fn check(src: &str) -> Vec<Diagnostic> {
+        CheckHandler::check(
+            src,
+            Path::new("check_test.gml"),
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+    }
+
+    #[test]
+    fn test_two_undeclared_variables_give_two_diagnostics() {
+        let src = "var a = 1;\nb = a + c;\n";
+        let diagnostics = check(src);
+        assert_eq!(diagnostics.len(), 2, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].code, diagnostics[1].code);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+
+        let spans: Vec<&str> = diagnostics
+            .iter()
+            .map(|d| &src[d.span.clone().unwrap()])
+            .collect();
+        assert_eq!(spans, ["b", "c"]);
+    }
+
+    #[test]
+    fn test_var_is_function_scoped_for_undeclared_check() {
+        let src = r#"
+            function f(x) {
+                if (x) { var y = 1; } else { y = 2; }
+                for (var i = 0; i < 3; i++) {}
+                return y + i + argument_count;
+            }
+        "#;
+        assert!(check(src).is_empty(), "{:?}", check(src));
+    }
+
+    #[test]
+    fn test_function_body_cannot_see_outer_variables() {
+        let diagnostics = check("var g = 1;\nfunction f() { return g; }\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "'g': variable used before it is declared"
+        );
+    }
+
+    #[test]
+    fn test_script_functions_see_top_level_variables() {
+        let src = "var counter = 0;\nfunction bump() { counter += 1; return counter; }\n";
+        let path = Path::new("check_test.gml");
+        let report = CheckHandler::check_source_with_mode(
+            src,
+            path,
+            true,
+            CheckMode::Script,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap();
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+        // Run as a program, a function still cannot see them
+        let diagnostics = CheckHandler::check_source_with_mode(
+            src,
+            path,
+            true,
+            CheckMode::Program,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(diagnostics[0].code, 206);
+
+        // Nor can it see a function's locals, or top-level variables never declared
+        let src = "var counter = 0;\nfunction f() { var local = 1; }\nfunction g() { return local + total; }\n";
+        let diagnostics = CheckHandler::check_source_with_mode(
+            src,
+            path,
+            false,
+            CheckMode::Script,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap_err();
+        let names: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "'local': variable used before it is declared",
+                "'total': variable used before it is declared"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors_have_code_and_span() {
+        let diagnostics = check("var = ;\n");
+        assert!(!diagnostics.is_empty());
+        assert!(diagnostics.iter().all(|d| d.code == SYNTAX_ERROR));
+        assert!(diagnostics.iter().all(|d| d.span.is_some()));
+    }
+
+    #[test]
+    fn test_missing_include_is_a_diagnostic() {
+        let diagnostics = check("#include \"no_such_file.gml\"\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, INCLUDE_ERROR);
+    }
+
+    #[test]
+    fn test_warnings_do_not_stop_codegen() {
+        let src = "var a = 1;\nvar a = 2;\nfunction f() { return f(1, 2); }\n";
+        let diagnostics = check(src);
+        let codes: Vec<u32> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, [201, 305]);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(diagnostics[1].span.is_none());
+    }
+
+    #[test]
+    fn test_ir_gen_error_codes_are_distinct() {
+        let errors = [
+            IRGenError::UndefinedVariable("x".to_string()),
+            IRGenError::UndefinedFunction("f".to_string()),
+            IRGenError::TypeMismatch(String::new()),
+            IRGenError::InvalidOperation(String::new()),
+            IRGenError::ArgumentCountMismatch(String::new()),
+        ];
+        let mut codes: Vec<u32> = errors.iter().map(IRGenError::code).collect();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+        assert_eq!(
+            Diagnostic::from(&errors[0]).to_string(),
+            "error[E0301]: Undefined variable 'x'"
+        );
+    }
+
+    #[test]
+    fn test_render_includes_position() {
+        let src = "var a;\n  a = b;\n";
+        let diagnostics = check(src);
+        let index = LineIndex::new(src);
+        assert_eq!(
+            diagnostics[0].render(&index),
+            "2:7: error[E0206]: 'b': variable used before it is declared"
+        );
+    }
+
+    #[test]
+    fn test_check_source_report_counts() {
+        let src = "var a = 1;\nvar a = 2;\nfunction f(x) { var y = x; return y; }\n";
+        let report = CheckHandler::check_source(
+            src,
+            Path::new("check_test.gml"),
+            true,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(report.functions, 1);
+        // a, x and y
+        assert_eq!(report.variables, 3);
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_check_source_without_ir_skips_codegen_errors() {
+        let src = "function f() { return f(1, 2); }\n";
+        let path = Path::new("check_test.gml");
+        assert!(
+            CheckHandler::check_source(
+                src,
+                path,
+                false,
+                &LanguageOptions::default(),
+                &CheckOptions::default()
+            )
+            .is_ok()
+        );
+        let diagnostics = CheckHandler::check_source(
+            src,
+            path,
+            true,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(diagnostics[0].code, 305);
+    }
+
+    #[test]
+    fn test_check_directory() {
+        let dir = std::env::temp_dir().join(format!("col_check_dir_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(
+            dir.join("good.gml"),
+            "var a = 1;\nfunction f() { return 2; }\n",
+        )
+        .unwrap();
+        fs::write(dir.join("nested/bad.gml"), "var a = 1;\nb = a;\n").unwrap();
+        fs::write(dir.join("notes.txt"), "not a script").unwrap();
+
+        let checks = CheckHandler::check_path(
+            &dir,
+            true,
+            CheckMode::Program,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        );
+        let names: Vec<_> = checks
+            .iter()
+            .map(|c| c.path.strip_prefix(&dir).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(names, [Path::new("good.gml"), Path::new("nested/bad.gml")]);
+        assert!(checks[0].passed());
+        let diagnostics = checks[1].result.as_ref().unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, 206);
+        assert_eq!(CheckHandler::exit_status(&checks), 1);
+        assert_eq!(CheckHandler::exit_status(&checks[..1]), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_parses_with_the_language_options() {
+        let src = "function f(x) {\n    switch (x) { case 1: return 2; }\n    return 0;\n}\n";
+        let path = Path::new("check_test.gml");
+        let options = LanguageOptions {
+            allow_switch: true,
+            ..LanguageOptions::default()
+        };
+        assert!(
+            CheckHandler::check_source(src, path, true, &options, &CheckOptions::default()).is_ok()
+        );
+
+        let diagnostics = CheckHandler::check_source(
+            src,
+            path,
+            true,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, SYNTAX_ERROR);
+        assert_eq!(
+            diagnostics[0].message,
+            "switch statements are disabled; enable LanguageOptions::allow_switch"
+        );
+    }
+
+    #[test]
+    fn test_check_missing_file_is_read_error() {
+        let checks = CheckHandler::check_path(
+            Path::new("no_such_dir/missing.gml"),
+            false,
+            CheckMode::Program,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        );
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].result.as_ref().unwrap_err()[0].code, READ_ERROR);
+        assert_eq!(CheckHandler::exit_status(&checks), 1);
+    }
+
+    /// The source text each assignment-in-condition warning points at
+    fn assignment_warnings(src: &str) -> Vec<&str> {
+        let report = CheckHandler::check_source(
+            src,
+            Path::new("check_test.gml"),
+            true,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap();
+        report
+            .warnings
+            .iter()
+            .filter(|d| d.code == ASSIGNMENT_IN_CONDITION)
+            .inspect(|d| assert_eq!(d.severity, Severity::Warning))
+            .map(|d| &src[d.span.clone().unwrap()])
+            .collect()
+    }
+
+    #[test]
+    fn test_assignment_in_conditions_warns() {
+        let src = r#"
+            function f(a, b, c, d) {
+                if (a = 5) { a = 1; }
+                if b = 2 { b = 1; }
+                while (c = 0) { c = 1; }
+                for (var i = 0; d = 0; i++) { d = 1; }
+                do { a = 2; } until (a = 2);
+                return a;
+            }
+        "#;
+        assert_eq!(assignment_warnings(src), ["a", "b", "c", "d", "a"]);
+    }
+
+    #[test]
+    fn test_assignment_in_condition_opt_out_and_comparisons() {
+        let src = r#"
+            function f(a, b) {
+                if ((a = b)) { a = 1; }
+                while ((b = 0)) { b = 1; }
+                if (a == 5) { a = 2; }
+                if ((a = 1) && b) { b = 2; }
+                return a;
+            }
+        "#;
+        assert!(assignment_warnings(src).is_empty());
+    }
+
+    #[test]
+    fn test_assignment_in_condition_still_compiles() {
+        let src = "function f(a) { if (a = 3) { return a * 2; } return 0; }";
+        let script = crate::Script::compile(src).unwrap();
+        assert_eq!(
+            script.call("f", &[crate::Value::Number(0.0)]).unwrap(),
+            crate::Value::Number(6.0)
+        );
+    }
+
+    /// The message and source text of each unreachable-code warning
+    fn unreachable_warnings(src: &str) -> Vec<(String, &str)> {
+        let report = CheckHandler::check_source(
+            src,
+            Path::new("check_test.gml"),
+            true,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap();
+        report
+            .warnings
+            .iter()
+            .filter(|d| d.code == UNREACHABLE_CODE)
+            .inspect(|d| assert_eq!(d.severity, Severity::Warning))
+            .map(|d| (d.message.clone(), &src[d.span.clone().unwrap()]))
+            .collect()
+    }
+
+    #[test]
+    fn test_code_after_return_warns_once() {
+        let src = r#"
+            function f(x, dead) {
+                return x;
+                dead = 1;
+                dead = 2;
+            }
+        "#;
+        assert_eq!(
+            unreachable_warnings(src),
+            [("unreachable code after 'return'".to_string(), "dead")]
+        );
+        let script = crate::Script::compile(src).unwrap();
+        let codes: Vec<u32> = script.warnings().iter().map(|d| d.code).collect();
+        assert_eq!(codes, [UNREACHABLE_CODE]);
+    }
+
+    #[test]
+    fn test_code_after_break_and_branches_that_leave_warns() {
+        let src = r#"
+            function f(x, y) {
+                while (x) {
+                    break;
+                    y = 1;
+                }
+                if (x) { return 1; } else { return 2; }
+                return y;
+            }
+        "#;
+        assert_eq!(
+            unreachable_warnings(src),
+            [
+                ("unreachable code after 'break'".to_string(), "y"),
+                (
+                    "unreachable code after an 'if' whose branches all leave".to_string(),
+                    "y"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_last_statements_do_not_warn() {
+        let src = r#"
+            function f(x) {
+                while (x > 0) {
+                    x -= 1;
+                    if (x == 2) { break; }
+                }
+                { x = 1; return x; }
+            }
+            return f(3);
+        "#;
+        assert!(unreachable_warnings(src).is_empty());
+    }
+
+    /// The source text each missing-return warning points at
+    fn missing_return_warnings(src: &str) -> Vec<&str> {
+        let report = CheckHandler::check_source(
+            src,
+            Path::new("check_test.gml"),
+            true,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap();
+        report
+            .warnings
+            .iter()
+            .filter(|d| d.code == MISSING_RETURN)
+            .inspect(|d| assert_eq!(d.severity, Severity::Warning))
+            .map(|d| &src[d.span.clone().unwrap()])
+            .collect()
+    }
+
+    #[test]
+    fn test_some_paths_without_return_warn() {
+        let src = r#"
+            function f(x) {
+                if (x > 0) { return 1; }
+            }
+            function g(x) {
+                if (x > 0) { return 1; } else { x = 2; }
+            }
+        "#;
+        assert_eq!(
+            missing_return_warnings(src),
+            ["if (x > 0) { return 1; }", "x"]
+        );
+        let report = CheckHandler::check_source(
+            src,
+            Path::new("check_test.gml"),
+            true,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            report.warnings[0].message,
+            "not every path through 'f' returns a value; reaching the end returns 0"
+        );
+    }
+
+    #[test]
+    fn test_functions_returning_on_every_path_or_none_do_not_warn() {
+        let src = r#"
+            function f(x) {
+                if (x > 0) { return 1; } else if (x < 0) { return -1; } else { return 0; }
+            }
+            function g(x) {
+                while (x > 0) { if (x == 3) { return x; } x -= 1; }
+                return 0;
+            }
+            function procedure(x) {
+                if (x > 0) { x = 1; }
+                if (x) { return; }
+            }
+        "#;
+        assert!(missing_return_warnings(src).is_empty());
+
+        // A switch returns when every label falls through to a return
+        let options = crate::parser::language_options::LanguageOptions {
+            allow_switch: true,
+            ..Default::default()
+        };
+        let returning = "function h(x) {
+    switch (x) {
+        case 1: x = 2;
+        default: return x;
+    }
+}";
+        let breaking = "function h(x) {
+    switch (x) {
+        case 1: break;
+        default: return x;
+    }
+}";
+        for (src, warns) in [(returning, false), (breaking, true)] {
+            let script = crate::Script::compile_with_options(src, &options).unwrap();
+            assert_eq!(
+                script.warnings().iter().any(|d| d.code == MISSING_RETURN),
+                warns,
+                "{}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn test_falling_off_the_end_returns_zero() {
+        let src = r#"
+            function f(x) {
+                if (x > 0) { return x; }
+                x + 5;
+            }
+            function g(x) {
+                x += 5;
+            }
+        "#;
+        let script = crate::Script::compile(src).unwrap();
+        let codes: Vec<u32> = script.warnings().iter().map(|d| d.code).collect();
+        assert_eq!(codes, [MISSING_RETURN]);
+        for (name, arg, expected) in [("f", 2.0, 2.0), ("f", -2.0, 0.0), ("g", 1.0, 0.0)] {
+            assert_eq!(
+                script.call(name, &[crate::Value::Number(arg)]),
+                Ok(crate::Value::Number(expected)),
+                "{}({})",
+                name,
+                arg
+            );
+        }
+    }
+
+    #[test]
+    fn test_strict_returns_rejects_missing_returns() {
+        let src = "function f(x) {\n    if (x > 0) { return 1; }\n}";
+        let options = crate::parser::language_options::LanguageOptions {
+            strict_returns: true,
+            ..Default::default()
+        };
+        let Err(crate::script::CompileError::Codegen(diagnostic)) =
+            crate::Script::compile_with_options(src, &options)
+        else {
+            panic!("Expected a missing return error");
+        };
+        assert_eq!(diagnostic.code, MISSING_RETURN);
+        assert_eq!(&src[diagnostic.span.unwrap()], "if (x > 0) { return 1; }");
+    }
+}
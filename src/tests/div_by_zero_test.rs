@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::parser::language_options::{DivByZeroPolicy, LanguageOptions};
+    use crate::script::{RuntimeError, Script, Value};
+    use crate::tests::tests_helper::{compile_with, parse_gml};
+    use inkwell::context::Context;
+
+    const SRC: &str = r#"
+        var reached = 0;
+        function quotient() { return 5 / 0; }
+        function truncated() { return 5 div 0; }
+        function remainder(x) { return 5 % x; }
+        function outer(x) {
+            var q = remainder(x);
+            reached = 1;
+            return q;
+        }
+    "#;
+
+    fn error_at(operation: &str, text: &str) -> RuntimeError {
+        let start = SRC.find(text).unwrap();
+        RuntimeError::Execution(format!(
+            "{} by zero at {}..{}",
+            operation,
+            start,
+            start + text.len()
+        ))
+    }
+
+    #[test]
+    fn test_infinity_policy() {
+        let options = LanguageOptions {
+            div_by_zero: DivByZeroPolicy::Infinity,
+            ..LanguageOptions::default()
+        };
+        let script = compile_with(SRC, &options);
+        assert_eq!(
+            script.call("quotient", &[]).unwrap(),
+            Value::Number(f64::INFINITY)
+        );
+        let Value::Number(remainder) = script.call("remainder", &[Value::Number(0.0)]).unwrap()
+        else {
+            panic!("Expected a number");
+        };
+        assert!(remainder.is_nan());
+        assert_eq!(
+            script.call("truncated", &[]),
+            Err(error_at("integer division", "5 div 0"))
+        );
+    }
+
+    #[test]
+    fn test_error_policy() {
+        let options = LanguageOptions {
+            div_by_zero: DivByZeroPolicy::Error,
+            ..LanguageOptions::default()
+        };
+        let script = compile_with(SRC, &options);
+        assert_eq!(
+            script.call("quotient", &[]),
+            Err(error_at("division", "5 / 0"))
+        );
+        assert_eq!(
+            script.call("truncated", &[]),
+            Err(error_at("integer division", "5 div 0"))
+        );
+        assert_eq!(
+            script.call("remainder", &[Value::Number(3.0)]).unwrap(),
+            Value::Number(2.0)
+        );
+    }
+
+    #[test]
+    fn test_error_stops_the_callers() {
+        let options = LanguageOptions {
+            div_by_zero: DivByZeroPolicy::Error,
+            ..LanguageOptions::default()
+        };
+        let script = compile_with(SRC, &options);
+        assert_eq!(
+            script.call("outer", &[Value::Number(0.0)]),
+            Err(error_at("modulo", "5 % x"))
+        );
+        assert_eq!(script.get_global("reached").unwrap(), Value::Number(0.0));
+
+        // The error is cleared, so the next call succeeds
+        assert_eq!(
+            script.call("outer", &[Value::Number(2.0)]).unwrap(),
+            Value::Number(1.0)
+        );
+        assert_eq!(script.get_global("reached").unwrap(), Value::Number(1.0));
+    }
+
+    #[test]
+    fn test_zero_policy() {
+        let options = LanguageOptions {
+            div_by_zero: DivByZeroPolicy::Zero,
+            ..LanguageOptions::default()
+        };
+        let script = compile_with(SRC, &options);
+        for function in ["quotient", "truncated"] {
+            assert_eq!(script.call(function, &[]).unwrap(), Value::Number(0.0));
+        }
+        assert_eq!(
+            script.call("remainder", &[Value::Number(0.0)]).unwrap(),
+            Value::Number(0.0)
+        );
+    }
+
+    #[test]
+    fn test_div_truncates_toward_zero() {
+        let src = "function f(a, b) { return a div b; }";
+        let script = Script::compile(src).unwrap();
+        for (a, b, expected) in [(7.0, 2.0, 3.0), (-7.0, 2.0, -3.0), (7.5, -2.0, -3.0)] {
+            assert_eq!(
+                script
+                    .call("f", &[Value::Number(a), Value::Number(b)])
+                    .unwrap(),
+                Value::Number(expected),
+                "{} div {}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn test_nonzero_constant_divisor_is_not_checked() {
+        let ir = |src: &str| {
+            let context = Context::create();
+            let mut ir_generator = IRGenerator::new(&context, "test_module");
+            ir_generator.div_by_zero = DivByZeroPolicy::Error;
+            parse_gml(src).accept(&mut ir_generator).unwrap();
+            ir_generator.get_module().print_to_string().to_string()
+        };
+        let constant = ir("function f(x) { return x / 2; }");
+        assert!(!constant.contains("div_zero"), "{}", constant);
+        let variable = ir("function f(x, y) { return x / y; }");
+        assert!(variable.contains("div_zero"), "{}", variable);
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::expr::Expr;
+    use crate::parser::top_level::TopLevel;
+    use crate::parser::visitor::Visitor;
+    use crate::parser::visitor::symbol_table_builder::{
+        Scope, Symbol, SymbolDiagnosticKind, SymbolTableBuilder,
+    };
+    use crate::tests::tests_helper::*;
+
+    const COLORS: &str = "enum Colors { Red, Green = 5, Blue }\n";
+
+    fn symbol_diagnostics(src: &str) -> Vec<SymbolDiagnosticKind> {
+        let program = parse_gml(src);
+        let mut scope = Scope::new();
+        let mut builder = SymbolTableBuilder::new(&mut scope);
+        builder.visit_program(&program);
+        builder.into_diagnostics().iter().map(|d| d.kind).collect()
+    }
+
+    #[test]
+    fn test_parse_enum_declaration() {
+        let program = parse_gml("enum Colors { Red, Green = 5, Blue, }");
+        let TopLevel::Enum(enum_def) = &program.body[0] else {
+            panic!("Expected an enum declaration");
+        };
+        assert_eq!(enum_def.name, "Colors");
+        let names: Vec<&str> = enum_def.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["Red", "Green", "Blue"]);
+        assert!(enum_def.members[0].value.is_none());
+        assert!(matches!(enum_def.members[1].value, Some(Expr::Number(n, _)) if n == 5.0));
+    }
+
+    #[test]
+    fn test_parse_enum_over_several_lines() {
+        let program = parse_gml("enum Colors {\n    Red,\n    Green = 5,\n    Blue,\n}\nx = 1;\n");
+        let TopLevel::Enum(enum_def) = &program.body[0] else {
+            panic!("Expected an enum declaration");
+        };
+        assert_eq!(enum_def.members.len(), 3);
+        assert_eq!(program.body.len(), 2);
+    }
+
+    #[test]
+    fn test_member_in_arithmetic() {
+        let src = format!("{}return Colors.Green * 2 + 1;", COLORS);
+        assert_eq!(compile_and_execute(&src).unwrap(), 11.0);
+    }
+
+    #[test]
+    fn test_members_auto_increment() {
+        let src = format!(
+            "{}return (Colors.Red == 0) + (Colors.Blue == 6) * 10;",
+            COLORS
+        );
+        assert_eq!(compile_and_execute(&src).unwrap(), 11.0);
+    }
+
+    #[test]
+    fn test_enum_usable_before_declaration_and_in_functions() {
+        let src = format!("function blue() {{ return Colors.Blue; }}\n{}", COLORS);
+        assert_eq!(
+            compile_and_execute_function(&src, "blue", &[]).unwrap(),
+            6.0
+        );
+    }
+
+    #[test]
+    fn test_member_value_from_constant_expression() {
+        let src = r#"
+            enum Flags { A = (3 - 2), B = 2, C = Flags.B * 4 | 1, D }
+            return Flags.C * 100 + Flags.D;
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 910.0);
+    }
+
+    #[test]
+    fn test_non_constant_initializer_is_error() {
+        let src = "var x = 3;\nenum Sizes { Small = x, Large }\n";
+        let err = compile_and_execute(src).unwrap_err();
+        assert!(
+            err.contains("Value of enum member 'Sizes.Small' is not a constant"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_unknown_member_is_error() {
+        let src = format!("{}return Colors.Purple;", COLORS);
+        let err = compile_and_execute(&src).unwrap_err();
+        assert!(err.contains("Colors.Purple"), "{}", err);
+    }
+
+    #[test]
+    fn test_symbol_table_registers_members() {
+        let program = parse_gml(COLORS);
+        let mut scope = Scope::new();
+        let mut builder = SymbolTableBuilder::new(&mut scope);
+        builder.visit_program(&program);
+        assert!(builder.into_diagnostics().is_empty());
+        for name in ["Colors.Red", "Colors.Green", "Colors.Blue"] {
+            assert!(
+                matches!(scope.table.get(name), Some(Symbol::EnumMember)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_symbol_diagnostics_for_members() {
+        assert_eq!(
+            symbol_diagnostics("var x = Colors.Red;\nenum Colors { Red, Red }"),
+            [SymbolDiagnosticKind::DuplicateEnumMember]
+        );
+        assert_eq!(
+            symbol_diagnostics(&format!("{}var x = Colors.Purple;", COLORS)),
+            [SymbolDiagnosticKind::UnknownEnumMember]
+        );
+    }
+}
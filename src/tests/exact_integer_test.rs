@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::language_options::LanguageOptions;
+    use crate::script::{Script, Value};
+    use crate::tests::tests_helper::compile_with;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const SRC: &str = r#"
+        function big() {
+            var x = 9007199254740993;
+            println(x);
+            println(string(9007199254740993));
+            return x - 9007199254740992;
+        }
+        function bits() {
+            var mask = (1 << 62) | 3;
+            println(mask);
+            return mask - (1 << 62);
+        }
+        function half() {
+            return 7 / 2;
+        }
+        function shrink() {
+            var x = 1;
+            for (var i = 0; i < 3; i++) {
+                x /= 2;
+            }
+            return x + i;
+        }
+    "#;
+
+    /// What `script` prints from now on
+    fn capture(script: &Script) -> Rc<RefCell<String>> {
+        let captured = Rc::new(RefCell::new(String::new()));
+        let sink = Rc::clone(&captured);
+        script.set_print_callback(move |line| sink.borrow_mut().push_str(line));
+        captured
+    }
+
+    #[test]
+    fn test_large_literals_round_trip() {
+        let options = LanguageOptions {
+            exact_integers: true,
+            ..LanguageOptions::default()
+        };
+        let script = compile_with(SRC, &options);
+        let captured = capture(&script);
+        assert_eq!(script.call("big", &[]), Ok(Value::Number(1.0)));
+        assert_eq!(*captured.borrow(), "9007199254740993\n9007199254740993\n");
+    }
+
+    #[test]
+    fn test_bit_operations_use_all_64_bits() {
+        let options = LanguageOptions {
+            exact_integers: true,
+            ..LanguageOptions::default()
+        };
+        let script = compile_with(SRC, &options);
+        let captured = capture(&script);
+        assert_eq!(script.call("bits", &[]), Ok(Value::Number(3.0)));
+        assert_eq!(*captured.borrow(), "4611686018427387907\n");
+    }
+
+    #[test]
+    fn test_fractions_still_give_numbers() {
+        let options = LanguageOptions {
+            exact_integers: true,
+            ..LanguageOptions::default()
+        };
+        let script = compile_with(SRC, &options);
+        assert_eq!(script.call("half", &[]), Ok(Value::Number(3.5)));
+        // `x` is given fractions, so it is a number from its declaration on
+        assert_eq!(script.call("shrink", &[]), Ok(Value::Number(3.125)));
+    }
+
+    #[test]
+    fn test_without_the_option_numbers_are_floats() {
+        let script = compile_with(SRC, &LanguageOptions::default());
+        let captured = capture(&script);
+        assert_eq!(script.call("big", &[]), Ok(Value::Number(0.0)));
+        // Shift counts wrap at 32 bits, so `1 << 62` is `1 << 30`
+        assert_eq!(script.call("bits", &[]), Ok(Value::Number(3.0)));
+        // 2^53, as 15 significant digits show any number
+        assert_eq!(
+            *captured.borrow(),
+            "9007199254740990\n9007199254740990\n1073741827\n"
+        );
+        assert_eq!(script.call("half", &[]), Ok(Value::Number(3.5)));
+        assert_eq!(script.call("shrink", &[]), Ok(Value::Number(3.125)));
+    }
+}
@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod tests {
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::formatter::format_program;
+    use crate::parser::trivia::{CommentKind, comments};
+
+    fn format(src: &str) -> String {
+        format_program(&ParseHandler::parse_program_with_comments(src).unwrap())
+    }
+
+    #[test]
+    fn test_comments_are_found_with_their_position() {
+        let src = "// file\nx = \"// not a comment\"; /* note */\n";
+        let found = comments(src);
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert_eq!(found[0].text, "// file");
+        assert_eq!(found[0].kind, CommentKind::Line);
+        assert!(!found[0].trailing);
+        assert_eq!(found[1].text, "/* note */");
+        assert_eq!(found[1].kind, CommentKind::Block);
+        assert!(found[1].trailing);
+        assert_eq!(&src[found[1].span.clone()], "/* note */");
+    }
+
+    #[test]
+    fn test_plain_parse_keeps_no_comments() {
+        let program = ParseHandler::parse_program("x = 1; // note\n").unwrap();
+        assert!(program.comments.is_empty());
+    }
+
+    #[test]
+    fn test_comments_round_trip() {
+        let src = "// Counts things\n\
+                   var count = 1; // how many\n\
+                   \n\
+                   function add(a, b) {\n    return a + b;\n}\n\
+                   \n\
+                   /* Helpers\n   below */\n\
+                   function twice(x) {\n    return add(x, x);\n}\n";
+        let formatted = format(src);
+        assert_eq!(formatted, src);
+        assert_eq!(format(&formatted), formatted);
+    }
+
+    #[test]
+    fn test_layout_is_normalized_around_comments() {
+        let src = "function f(x){\n  if (x > 0) { // positive\n    return 1\n  }\n  else return -1\n  // never here\n}";
+        let expected = "function f(x) {\n    if (x > 0) { // positive\n        return 1;\n    } else\n        return -1;\n    // never here\n}\n";
+        let formatted = format(src);
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted), formatted);
+    }
+
+    #[test]
+    fn test_anonymous_function_is_kept_on_one_line() {
+        let src = "var cb = function(a){\n  if (a) return a*2;\n};";
+        let expected = "var cb = function(a) { if (a) return a * 2; };\n";
+        let formatted = format(src);
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted), formatted);
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::runtime::trap::ErrorKind;
+    use crate::script::{CompileError, Script, Value};
+
+    const SRC: &str = r#"
+        function double_five() {
+            var cb = function(a) { return a * 2; };
+            return cb(5);
+        }
+        function apply(f, x) {
+            return f(x);
+        }
+        function triple(x) {
+            var by_three = function(n) { return n * 3; };
+            return apply(by_three, x);
+        }
+        function pick(second) {
+            var add = function(a, b) { return a + b; };
+            var sub = function(a, b) { return a - b; };
+            var chosen = add;
+            if (second) chosen = sub;
+            return chosen(10, 4);
+        }
+        function call_number() {
+            return apply(42, 1);
+        }
+    "#;
+
+    #[test]
+    fn test_assigned_function_is_called() {
+        let script = Script::compile(SRC).unwrap();
+        assert_eq!(script.call("double_five", &[]), Ok(Value::Number(10.0)));
+    }
+
+    #[test]
+    fn test_function_passed_as_argument_is_called() {
+        let script = Script::compile(SRC).unwrap();
+        assert_eq!(
+            script.call("triple", &[Value::Number(4.0)]),
+            Ok(Value::Number(12.0))
+        );
+    }
+
+    #[test]
+    fn test_reassigned_variable_calls_what_it_holds() {
+        let script = Script::compile(SRC).unwrap();
+        assert_eq!(
+            script.call("pick", &[Value::Number(0.0)]),
+            Ok(Value::Number(14.0))
+        );
+        assert_eq!(
+            script.call("pick", &[Value::Number(1.0)]),
+            Ok(Value::Number(6.0))
+        );
+    }
+
+    #[test]
+    fn test_calling_a_number_raises_an_error() {
+        let script = Script::compile(SRC).unwrap();
+        assert!(script.call("call_number", &[]).is_err());
+        let error = script.last_runtime_error().unwrap();
+        assert_eq!(error.kind, ErrorKind::NotCallable);
+        assert_eq!(error.message, "'f' does not hold a function");
+        assert_eq!(error.function, "apply");
+        assert_eq!(&SRC[error.span.unwrap()], "f(x)");
+    }
+
+    #[test]
+    fn test_capturing_a_local_is_reported_at_its_use() {
+        let src =
+            "function f(x) {\n    var cb = function(a) { return a + x; };\n    return cb(1);\n}\n";
+        let Err(CompileError::Codegen(diagnostic)) = Script::compile(src) else {
+            panic!("expected a code generation error");
+        };
+        assert_eq!(diagnostic.code, 309);
+        assert!(
+            diagnostic
+                .message
+                .contains("uses 'x', a local variable of 'f'"),
+            "{}",
+            diagnostic.message
+        );
+        let span = diagnostic.span.unwrap();
+        assert_eq!(span.start, src.find("x; }").unwrap());
+        assert_eq!(&src[span], "x");
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::highlight::{HighlightCategory, HighlightSpan, highlight};
+
+    fn spans(source: &str) -> Vec<(&str, HighlightCategory)> {
+        highlight(source)
+            .into_iter()
+            .map(|span| (&source[span.start..span.end], span.category))
+            .collect()
+    }
+
+    #[test]
+    fn every_category_with_exact_spans() {
+        use HighlightCategory::*;
+        let source = "function add(a) { // sum\n  return max(a, \"x y\") + 1.5 @ }";
+        let expected = [
+            (0, 8, Keyword),
+            (9, 12, FunctionName),
+            (12, 13, Punctuation),
+            (13, 14, Identifier),
+            (14, 15, Punctuation),
+            (16, 17, Punctuation),
+            (18, 24, Comment),
+            (27, 33, Keyword),
+            (34, 37, FunctionName),
+            (37, 38, Punctuation),
+            (38, 39, Identifier),
+            (39, 40, Punctuation),
+            (41, 46, String),
+            (46, 47, Punctuation),
+            (48, 49, Operator),
+            (50, 53, Number),
+            (54, 55, Error),
+            (56, 57, Punctuation),
+        ]
+        .map(|(start, end, category)| HighlightSpan {
+            start,
+            end,
+            category,
+        });
+        assert_eq!(highlight(source), expected);
+    }
+
+    #[test]
+    fn definitions_and_calls_are_function_names() {
+        use HighlightCategory::*;
+        assert_eq!(
+            spans("function\nf() {}\nx = f;"),
+            vec![
+                ("function", Keyword),
+                ("f", FunctionName),
+                ("(", Punctuation),
+                (")", Punctuation),
+                ("{", Punctuation),
+                ("}", Punctuation),
+                ("x", Identifier),
+                ("=", Operator),
+                ("f", Identifier),
+                (";", Punctuation),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_keep_their_place() {
+        use HighlightCategory::*;
+        assert_eq!(
+            spans("/* a */ x /* b */\n// c"),
+            vec![
+                ("/* a */", Comment),
+                ("x", Identifier),
+                ("/* b */", Comment),
+                ("// c", Comment),
+            ]
+        );
+    }
+}
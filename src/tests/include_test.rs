@@ -0,0 +1,303 @@
+#[cfg(test)]
+mod tests {
+    use crate::check_handler::{CheckHandler, CheckOptions};
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::codegen::jit::JITExecutor;
+    use crate::parse_handler::{IncludeError, ParseHandler};
+    use crate::parser::language_options::LanguageOptions;
+    use crate::parser::program::Program;
+    use crate::parser::top_level::TopLevel;
+    use crate::script::{Script, Value};
+    use crate::tests::tests_helper::*;
+    use crate::utils::line_index::LineIndex;
+    use inkwell::context::Context;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn resolve(file: &Path, include_paths: &[PathBuf]) -> Result<Program, IncludeError> {
+        let content = fs::read_to_string(file).unwrap();
+        let program = parse_gml(&content);
+        let options = LanguageOptions {
+            include_paths: include_paths.to_vec(),
+            ..LanguageOptions::default()
+        };
+        ParseHandler::resolve_includes(program, &content, file, &options)
+    }
+
+    fn execute(program: &Program, func_name: &str) -> f64 {
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        program.accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().verify().unwrap();
+        let executor = JITExecutor::new(ir_generator.get_module()).unwrap();
+        executor.execute_function(func_name, &[]).unwrap()
+    }
+
+    fn function_names(program: &Program) -> Vec<&str> {
+        program.functions().map(|f| f.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_include_makes_functions_callable() {
+        let dir = temp_dir_with(
+            "col_include",
+            "callable",
+            &[
+                ("lib.gml", "function double(x) { return x * 2; }"),
+                (
+                    "main.gml",
+                    "#include \"lib.gml\"\nfunction test() { return double(21); }",
+                ),
+            ],
+        );
+        let program = resolve(&dir.join("main.gml"), &[]).unwrap();
+        assert!(
+            !program
+                .body
+                .iter()
+                .any(|item| matches!(item, TopLevel::Include(..)))
+        );
+        assert_eq!(execute(&program, "test"), 42.0);
+    }
+
+    #[test]
+    fn test_include_from_subdirectory_is_relative_to_includer() {
+        let dir = temp_dir_with(
+            "col_include",
+            "relative",
+            &[
+                (
+                    "lib/a.gml",
+                    "#include \"b.gml\"\nfunction a() { return b() + 1; }",
+                ),
+                ("lib/b.gml", "function b() { return 10; }"),
+                (
+                    "main.gml",
+                    "#include \"lib/a.gml\"\nfunction test() { return a(); }",
+                ),
+            ],
+        );
+        let program = resolve(&dir.join("main.gml"), &[]).unwrap();
+        assert_eq!(function_names(&program), ["b", "a", "test"]);
+        assert_eq!(execute(&program, "test"), 11.0);
+    }
+
+    #[test]
+    fn test_include_found_on_include_path() {
+        let dir = temp_dir_with(
+            "col_include",
+            "search_path",
+            &[
+                ("shared/util.gml", "function one() { return 1; }"),
+                (
+                    "src/main.gml",
+                    "#include \"util.gml\"\nfunction test() { return one(); }",
+                ),
+            ],
+        );
+        let program = resolve(&dir.join("src/main.gml"), &[dir.join("shared")]).unwrap();
+        assert_eq!(execute(&program, "test"), 1.0);
+    }
+
+    #[test]
+    fn test_diamond_include_is_spliced_once() {
+        let dir = temp_dir_with(
+            "col_include",
+            "diamond",
+            &[
+                ("base.gml", "function base() { return 5; }"),
+                (
+                    "left.gml",
+                    "#include \"base.gml\"\nfunction left() { return base(); }",
+                ),
+                (
+                    "right.gml",
+                    "#include \"base.gml\"\nfunction right() { return base(); }",
+                ),
+                (
+                    "main.gml",
+                    "#include \"left.gml\"\n#include \"right.gml\"\nfunction test() { return left() + right(); }",
+                ),
+            ],
+        );
+        let program = resolve(&dir.join("main.gml"), &[]).unwrap();
+        assert_eq!(function_names(&program), ["base", "left", "right", "test"]);
+        assert_eq!(execute(&program, "test"), 10.0);
+    }
+
+    #[test]
+    fn test_include_cycle_is_reported() {
+        let dir = temp_dir_with(
+            "col_include",
+            "cycle",
+            &[
+                ("a.gml", "#include \"b.gml\"\nfunction a() { return 1; }"),
+                ("b.gml", "#include \"a.gml\"\nfunction b() { return 2; }"),
+            ],
+        );
+        let err = resolve(&dir.join("a.gml"), &[]).unwrap_err();
+        let IncludeError::Cycle { chain } = err else {
+            panic!("expected a cycle, got {}", err);
+        };
+        let names: Vec<_> = chain
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["a.gml", "b.gml", "a.gml"]);
+    }
+
+    #[test]
+    fn test_missing_include_names_requesting_file() {
+        let dir = temp_dir_with(
+            "col_include",
+            "missing",
+            &[("main.gml", "var x = 1;\n  #include \"nowhere.gml\"\n")],
+        );
+        let err = resolve(&dir.join("main.gml"), &[]).unwrap_err();
+        let IncludeError::NotFound {
+            path,
+            requested_by,
+            line,
+            column,
+        } = &err
+        else {
+            panic!("expected a missing include, got {}", err);
+        };
+        assert_eq!(path, "nowhere.gml");
+        assert_eq!(requested_by, &dir.join("main.gml"));
+        assert_eq!((*line, *column), (2, 3));
+        assert!(err.to_string().contains("main.gml"));
+    }
+
+    #[test]
+    fn test_parse_error_in_included_file() {
+        let dir = temp_dir_with(
+            "col_include",
+            "parse_error",
+            &[
+                ("broken.gml", "function f( { return 1; }"),
+                ("main.gml", "#include \"broken.gml\"\n"),
+            ],
+        );
+        let err = resolve(&dir.join("main.gml"), &[]).unwrap_err();
+        assert!(matches!(err, IncludeError::Parse { .. }), "{}", err);
+    }
+
+    #[test]
+    fn test_diagnostic_in_included_file_names_that_file() {
+        let dir = temp_dir_with(
+            "col_include",
+            "diagnostic",
+            &[
+                ("lib.gml", "\nfunction f() {\n    return missing;\n}\n"),
+                ("main.gml", "var x = 1;\n#include \"lib.gml\"\n"),
+            ],
+        );
+        let path = dir.join("main.gml");
+        let content = fs::read_to_string(&path).unwrap();
+        let diagnostics = CheckHandler::check_source(
+            &content,
+            &path,
+            false,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap_err();
+        let [diagnostic] = diagnostics.as_slice() else {
+            panic!("expected one diagnostic, got {:?}", diagnostics);
+        };
+        let file = diagnostic
+            .file
+            .as_ref()
+            .expect("placed in the included file");
+        assert!(file.path.ends_with("lib.gml"), "{}", file.path.display());
+        assert_eq!(diagnostic.span, Some(27..34));
+
+        let rendered = diagnostic.render(&LineIndex::new(&content));
+        let expected = format!("{}:3:12: error[E0206]", file.path.display());
+        assert!(rendered.starts_with(&expected), "{}", rendered);
+    }
+
+    #[test]
+    fn test_debug_info_of_included_code_refers_to_its_file() {
+        let dir = temp_dir_with(
+            "col_include",
+            "debug_info",
+            &[
+                (
+                    "lib.gml",
+                    "\n\nfunction double(x) {\n    return x * 2;\n}\n",
+                ),
+                ("main.gml", "#include \"lib.gml\"\nreturn double(21);\n"),
+            ],
+        );
+        let path = dir.join("main.gml");
+        let content = fs::read_to_string(&path).unwrap();
+        let program = resolve(&path, &[]).unwrap();
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        ir_generator.enable_debug_info(&path, &content);
+        ir_generator.enable_included_debug_info(&program.included);
+        program.accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().verify().unwrap();
+
+        let ir = ir_generator.get_module().print_to_string().to_string();
+        assert!(ir.contains("!DIFile(filename: \"lib.gml\""), "{}", ir);
+        let start = ir.find("!DISubprogram(name: \"double\"").unwrap();
+        let entry = &ir[start..ir[start..].find('\n').unwrap() + start];
+        assert!(entry.contains("line: 3,"), "{}", entry);
+        // `return x * 2;` is on line 4 of lib.gml, after four spaces
+        assert!(ir.contains("!DILocation(line: 4, column: 12,"), "{}", ir);
+    }
+
+    #[test]
+    fn test_included_file_parses_with_the_includer_options() {
+        let dir = temp_dir_with(
+            "col_include",
+            "options",
+            &[
+                (
+                    "pick.gml",
+                    "function pick() { var x = 1; switch (x) { case 1: return 10; } return 0; }",
+                ),
+                ("main.gml", "#include \"pick.gml\"\n"),
+            ],
+        );
+        let main = dir.join("main.gml");
+        let err = resolve(&main, &[]).unwrap_err();
+        assert!(matches!(err, IncludeError::Parse { .. }), "{}", err);
+
+        let content = fs::read_to_string(&main).unwrap();
+        let options = LanguageOptions {
+            allow_switch: true,
+            ..LanguageOptions::default()
+        };
+        let program =
+            ParseHandler::resolve_includes(parse_gml(&content), &content, &main, &options).unwrap();
+        assert_eq!(execute(&program, "pick"), 10.0);
+    }
+
+    #[test]
+    fn test_script_looks_up_includes_on_its_include_paths() {
+        let dir = temp_dir_with(
+            "col_include",
+            "script",
+            &[("lib.gml", "function double(x) { return x * 2; }")],
+        );
+        let src = "#include \"lib.gml\"\nfunction run() { return double(21); }";
+        assert!(Script::compile(src).is_err());
+
+        let options = LanguageOptions {
+            include_paths: vec![dir],
+            ..LanguageOptions::default()
+        };
+        let script = Script::compile_with_options(src, &options).unwrap();
+        assert_eq!(script.call("run", &[]), Ok(Value::Number(42.0)));
+    }
+
+    #[test]
+    fn test_unresolved_include_fails_codegen() {
+        let result = compile_and_execute("#include \"lib.gml\"\n");
+        assert!(result.unwrap_err().contains("Unresolved include"));
+    }
+}
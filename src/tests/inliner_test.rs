@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::inliner::inline_small_functions;
+    use crate::parser::program::Program;
+    use crate::parser::top_level::TopLevel;
+    use crate::tests::tests_helper::*;
+
+    const SUM_OF_SQUARES: &str = r#"
+        function sq(x) { return x * x; }
+        var total = 0;
+        for (var i = 0; i < 10; i++) {
+            total += sq(i);
+        }
+        return total;
+    "#;
+
+    /// Names of the functions still called in the program's top-level statements
+    fn called_functions(program: &Program) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+        for top_level in &program.body {
+            if let TopLevel::Statement(stmt) = top_level {
+                let text = format!("{:?}", stmt);
+                for part in text.split("Call(\"").skip(1) {
+                    let name = part.split('"').next().unwrap_or_default();
+                    if !names.iter().any(|known| known == name) {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    #[test]
+    fn inlined_loop_gives_the_same_result() {
+        let expected = compile_and_execute(SUM_OF_SQUARES).unwrap();
+        assert_eq!(expected, 285.0);
+
+        let mut program = parse_gml(SUM_OF_SQUARES);
+        assert_eq!(inline_small_functions(&mut program, 8), 1);
+        assert!(called_functions(&program).is_empty());
+        assert_eq!(execute_program(&program).unwrap(), expected);
+    }
+
+    #[test]
+    fn inlining_is_off_at_threshold_zero_and_respects_size() {
+        let mut program = parse_gml(SUM_OF_SQUARES);
+        assert_eq!(inline_small_functions(&mut program, 0), 0);
+        // `x * x` is three nodes
+        assert_eq!(inline_small_functions(&mut program, 2), 0);
+        assert_eq!(called_functions(&program), vec!["sq".to_string()]);
+    }
+
+    #[test]
+    fn recursive_functions_are_never_inlined() {
+        let src = r#"
+            function fact(n) { return n <= 1 ? 1 : n * fact(n - 1); }
+            return fact(5);
+        "#;
+        let mut program = parse_gml(src);
+        assert_eq!(inline_small_functions(&mut program, 100), 0);
+        assert_eq!(called_functions(&program), vec!["fact".to_string()]);
+        assert_eq!(execute_program(&program).unwrap(), 120.0);
+    }
+
+    #[test]
+    fn arguments_are_substituted_simultaneously() {
+        let src = r#"
+            function sub(x, y) { return x - y; }
+            function sq(x) { return x * x; }
+            var x = 10;
+            var y = 3;
+            return sub(y, x) + sq(sq(2));
+        "#;
+        let expected = compile_and_execute(src).unwrap();
+        assert_eq!(expected, 9.0);
+
+        let mut program = parse_gml(src);
+        assert_eq!(inline_small_functions(&mut program, 8), 3);
+        assert!(called_functions(&program).is_empty());
+        assert_eq!(execute_program(&program).unwrap(), expected);
+    }
+
+    #[test]
+    fn calls_with_side_effects_are_kept() {
+        let src = r#"
+            function sq(x) { return x * x; }
+            var i = 3;
+            var a = sq(i++);
+            return a * 10 + i;
+        "#;
+        let expected = compile_and_execute(src).unwrap();
+        assert_eq!(expected, 94.0);
+
+        let mut program = parse_gml(src);
+        assert_eq!(inline_small_functions(&mut program, 8), 0);
+        assert_eq!(execute_program(&program).unwrap(), expected);
+    }
+
+    #[test]
+    fn functions_reading_other_variables_or_returning_bools_are_kept() {
+        let src = r#"
+            function scaled(x) { return x * scale; }
+            function is_big(x) { return x > 10; }
+            var a = scaled(3) + is_big(4);
+        "#;
+        let mut program = parse_gml(src);
+        assert_eq!(inline_small_functions(&mut program, 8), 0);
+        assert_eq!(
+            called_functions(&program),
+            vec!["scaled".to_string(), "is_big".to_string()]
+        );
+    }
+}
@@ -0,0 +1,308 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::{DUPLICATE_CASE, IRGenerator};
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::language_options::LanguageOptions;
+    use crate::parser::stmt::Stmt;
+    use crate::parser::top_level::TopLevel;
+    use crate::script::{CompileError, Script, Value};
+    use inkwell::context::Context;
+
+    const SWITCH: &str = r#"
+        function classify(x) {
+            var result = 0;
+            switch (x) {
+                case 1:
+                    result = 10;
+                    break;
+                case 2:
+                case 3:
+                    result += 20;
+                case 4:
+                    result += 30;
+                    break;
+                default:
+                    result = -1;
+            }
+            return result;
+        }
+    "#;
+
+    fn with_switch() -> LanguageOptions {
+        LanguageOptions {
+            allow_switch: true,
+            ..LanguageOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_switch_parses_when_allowed() {
+        let program = ParseHandler::parse_program_with_options(SWITCH, &with_switch()).unwrap();
+        let TopLevel::Function(func_def) = &program.body[0] else {
+            panic!("Expected a function, got {:?}", program.body[0]);
+        };
+        let Stmt::Switch(_, cases, _) = &func_def.func.body[1] else {
+            panic!("Expected a switch, got {:?}", func_def.func.body[1]);
+        };
+        // `case 2:` has no statements, so it joins the arm of `case 3:`
+        let labels: Vec<usize> = cases.iter().map(|case| case.labels.len()).collect();
+        assert_eq!(labels, [1, 2, 1, 0]);
+        let defaults: Vec<bool> = cases.iter().map(|case| case.default).collect();
+        assert_eq!(defaults, [false, false, false, true]);
+        assert_eq!(cases[1].body.len(), 1);
+    }
+
+    #[test]
+    fn test_switch_is_reported_when_disabled() {
+        let errors = ParseHandler::parse_program(SWITCH).unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(
+            errors[0].to_string(),
+            "switch statements are disabled; enable LanguageOptions::allow_switch"
+        );
+        let start = SWITCH.find("switch").unwrap();
+        assert_eq!(errors[0].span().start, start);
+    }
+
+    #[test]
+    fn test_switch_labels_fall_through() {
+        let script = Script::compile_with_options(SWITCH, &with_switch()).unwrap();
+        for (x, expected) in [
+            (1.0, 10.0),
+            (2.0, 50.0),
+            (3.0, 50.0),
+            (4.0, 30.0),
+            (5.0, -1.0),
+        ] {
+            assert_eq!(
+                script.call("classify", &[Value::Number(x)]).unwrap(),
+                Value::Number(expected),
+                "classify({})",
+                x
+            );
+        }
+    }
+
+    #[test]
+    fn test_break_leaves_the_switch_and_continue_the_loop() {
+        let src = r#"
+            function count(n) {
+                var total = 0;
+                for (var i = 0; i < n; i++) {
+                    switch (i % 3) {
+                        case 0:
+                            continue;
+                        case 1:
+                            total += 1;
+                            break;
+                        default:
+                            return total + 100;
+                    }
+                    total += 10;
+                }
+                return total;
+            }
+        "#;
+        let script = Script::compile_with_options(src, &with_switch()).unwrap();
+        assert_eq!(
+            script.call("count", &[Value::Number(2.0)]).unwrap(),
+            Value::Number(11.0)
+        );
+        assert_eq!(
+            script.call("count", &[Value::Number(3.0)]).unwrap(),
+            Value::Number(111.0)
+        );
+    }
+
+    #[test]
+    fn test_switch_on_strings() {
+        let src = r#"
+            function code(name) {
+                var s = string(name);
+                switch (s) {
+                    case "a": return 1;
+                    case "b": return 2;
+                }
+                return 0;
+            }
+        "#;
+        let script = Script::compile_with_options(src, &with_switch()).unwrap();
+        assert_eq!(
+            script.call("code", &[Value::Number(1.0)]).unwrap(),
+            Value::Number(0.0)
+        );
+        let script =
+            Script::compile_with_options(&src.replace("string(name)", "\"b\""), &with_switch())
+                .unwrap();
+        assert_eq!(
+            script.call("code", &[Value::Number(1.0)]).unwrap(),
+            Value::Number(2.0)
+        );
+    }
+
+    const STACKED: &str = r#"
+        function bucket(x) {
+            switch (x) {
+                case 1:
+                case 2:
+                case 3:
+                    return 10;
+                case -4:
+                case 5:
+                    return 20;
+                default:
+                    return 0;
+            }
+        }
+    "#;
+
+    #[test]
+    fn test_stacked_and_listed_labels_share_an_arm() {
+        let listed = STACKED
+            .replace(
+                "case 1:\n                case 2:\n                case 3:",
+                "case 1, 2, 3:",
+            )
+            .replace("case -4:\n                case 5:", "case -4, 5:");
+        assert!(listed.contains("case 1, 2, 3:") && listed.contains("case -4, 5:"));
+        for src in [STACKED, listed.as_str()] {
+            let script = Script::compile_with_options(src, &with_switch()).unwrap();
+            for (x, expected) in [
+                (1.0, 10.0),
+                (2.0, 10.0),
+                (3.0, 10.0),
+                (-4.0, 20.0),
+                (5.0, 20.0),
+                (2.5, 0.0),
+                (4.0, 0.0),
+                (1e300, 0.0),
+                (f64::NAN, 0.0),
+            ] {
+                assert_eq!(
+                    script.call("bucket", &[Value::Number(x)]).unwrap(),
+                    Value::Number(expected),
+                    "bucket({}) in {}",
+                    x,
+                    src
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_integer_labels_jump_through_one_switch() {
+        let ir = |src: &str| {
+            let program = ParseHandler::parse_program_with_options(src, &with_switch()).unwrap();
+            let context = Context::create();
+            let mut ir_generator = IRGenerator::new(&context, "test_module");
+            program.accept(&mut ir_generator).unwrap();
+            ir_generator.get_module().print_to_string().to_string()
+        };
+        assert!(ir(STACKED).contains("switch i64"), "{}", ir(STACKED));
+        let strings = STACKED.replace("case 5:", "case \"5\":");
+        assert!(!ir(&strings).contains("switch i64"), "{}", ir(&strings));
+    }
+
+    #[test]
+    fn test_integer_labels_match_within_epsilon() {
+        let src = r#"
+            function pick(x) {
+                switch (x) {
+                    case 2: return 1;
+                }
+                return 0;
+            }
+        "#;
+        let script = Script::compile_with_options(src, &with_switch()).unwrap();
+        assert_eq!(
+            script.call("pick", &[Value::Number(2.05)]).unwrap(),
+            Value::Number(0.0)
+        );
+        script.set_math_epsilon(0.1);
+        assert_eq!(
+            script.call("pick", &[Value::Number(2.05)]).unwrap(),
+            Value::Number(1.0)
+        );
+        assert_eq!(
+            script.call("pick", &[Value::Number(2.5)]).unwrap(),
+            Value::Number(0.0)
+        );
+    }
+
+    #[test]
+    fn test_listed_string_labels() {
+        let src = r#"
+            function code(n) {
+                switch (string(n)) {
+                    case "1", "2": return 10;
+                    case "3":
+                    case "4": return 30;
+                }
+                return 0;
+            }
+        "#;
+        let script = Script::compile_with_options(src, &with_switch()).unwrap();
+        for (n, expected) in [
+            (1.0, 10.0),
+            (2.0, 10.0),
+            (3.0, 30.0),
+            (4.0, 30.0),
+            (5.0, 0.0),
+        ] {
+            assert_eq!(
+                script.call("code", &[Value::Number(n)]).unwrap(),
+                Value::Number(expected),
+                "code({})",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn test_duplicate_label_is_an_error() {
+        let src = "function f(x) {\n    switch (x) {\n        case 1, 2: return 1;\n        case 3, 2: return 2;\n    }\n    return 0;\n}\n";
+        let Err(CompileError::Codegen(diagnostic)) =
+            Script::compile_with_options(src, &with_switch())
+        else {
+            panic!("expected a code generation error");
+        };
+        assert_eq!(diagnostic.code, DUPLICATE_CASE);
+        let first = src.find("2:").unwrap();
+        let duplicate = src.rfind("2:").unwrap();
+        assert_eq!(diagnostic.span, Some(duplicate..duplicate + 1));
+        assert_eq!(
+            diagnostic.message,
+            format!(
+                "Duplicate case label 2, already matched at {}..{}",
+                first,
+                first + 1
+            )
+        );
+    }
+
+    #[test]
+    fn test_strict_semicolons() {
+        let strict = LanguageOptions {
+            strict_semicolons: true,
+            ..LanguageOptions::default()
+        };
+        let src = "x = 1;\ny = 2;\nfunction f() {\n    return 3;\n}\n";
+        assert!(ParseHandler::parse_program_with_options(src, &strict).is_ok());
+        assert!(ParseHandler::parse_program_with_options("x = 1\ny = 2;\n", &strict).is_err());
+        assert!(ParseHandler::parse_program("x = 1\ny = 2\n").is_ok());
+    }
+
+    #[test]
+    fn test_reload_keeps_the_options() {
+        let mut script = Script::compile_with_options(SWITCH, &with_switch()).unwrap();
+        script.reload(&SWITCH.replace("-1", "-2")).unwrap();
+        assert_eq!(
+            script.call("classify", &[Value::Number(9.0)]).unwrap(),
+            Value::Number(-2.0)
+        );
+        assert!(matches!(
+            Script::compile(SWITCH),
+            Err(CompileError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::language_options::LanguageOptions;
+    use crate::script::{RuntimeError, Value};
+    use crate::tests::tests_helper::{compile_and_execute, compile_with};
+
+    const SRC: &str = r#"
+        function classify(x) {
+            var n = 0;
+            if (x >= 0) n += 1;
+            if (x < 0) n += 10;
+            if (x == x) n += 100;
+            if (x != x) n += 1000;
+            if (!(x < 0)) n += 10000;
+            return n;
+        }
+        function nan() { return 0 / 0; }
+    "#;
+
+    #[test]
+    fn test_is_nan_and_is_infinity() {
+        let src = r#"
+            return (is_nan(0 / 0) + is_nan(1) * 10 + is_infinity(1 / 0) * 100
+                + is_infinity(-1 / 0) * 1000 + is_infinity(0 / 0) * 10000
+                + is_nan("abc") * 100000);
+        "#;
+        assert_eq!(compile_and_execute(src), Ok(1101.0));
+    }
+
+    #[test]
+    fn test_comparisons_with_nan_are_false() {
+        let script = compile_with(SRC, &LanguageOptions::default());
+        assert_eq!(
+            script.call("classify", &[Value::Number(f64::NAN)]),
+            Ok(Value::Number(10000.0))
+        );
+        assert_eq!(
+            script.call("classify", &[Value::Number(1.0)]),
+            Ok(Value::Number(10101.0))
+        );
+    }
+
+    #[test]
+    fn test_strict_math_raises_on_nan_comparisons() {
+        let options = LanguageOptions {
+            strict_math: true,
+            ..LanguageOptions::default()
+        };
+        let script = compile_with(SRC, &options);
+        let start = SRC.find("x >= 0").unwrap();
+        assert_eq!(
+            script.call("classify", &[Value::Number(f64::NAN)]),
+            Err(RuntimeError::Execution(format!(
+                "comparison with NaN in 'classify' at {}..{}",
+                start,
+                start + "x >= 0".len()
+            )))
+        );
+        assert_eq!(
+            script.call("classify", &[Value::Number(1.0)]),
+            Ok(Value::Number(10101.0))
+        );
+
+        // Only comparisons are checked
+        let Ok(Value::Number(nan)) = script.call("nan", &[]) else {
+            panic!("Expected a number");
+        };
+        assert!(nan.is_nan());
+    }
+}
@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::{IRGenError, IRGenerator};
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::visitor::depth_checker::DepthChecker;
+    use crate::script::{CompileError, Script};
+    use crate::tests::tests_helper::*;
+    use inkwell::context::Context;
+
+    fn nested_parens(depth: usize) -> String {
+        format!("var x = {}1{};", "(".repeat(depth), ")".repeat(depth))
+    }
+
+    fn addition_chain(terms: usize) -> String {
+        format!("var x = 1{};", " + 1".repeat(terms))
+    }
+
+    fn assert_too_deep(src: &str) {
+        let errors = ParseHandler::parse_program(src).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].to_string().contains("too deeply nested"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn test_deeply_nested_parentheses_are_rejected() {
+        assert_too_deep(&nested_parens(5000));
+    }
+
+    #[test]
+    fn test_long_addition_chain_is_rejected() {
+        assert_too_deep(&addition_chain(10000));
+    }
+
+    #[test]
+    fn test_very_deep_parentheses_fail_at_the_limit() {
+        // Far deeper than the stack could take if the parser descended all the way
+        let src = nested_parens(50_000);
+        let errors = ParseHandler::parse_program(&src).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let span = errors[0].span().into_range();
+        assert_eq!(&src[span.clone()], "(");
+        assert!(span.start > "var x = ".len() + 100, "{:?}", span);
+        assert!(span.start < "var x = ".len() + 1000, "{:?}", span);
+    }
+
+    #[test]
+    fn test_long_addition_chain_fails_at_an_operand() {
+        let src = addition_chain(10000);
+        let errors = ParseHandler::parse_program(&src).unwrap_err();
+        let span = errors[0].span().into_range();
+        assert_eq!(&src[span.clone()], "1");
+        assert!(span.start > 0, "{:?}", span);
+    }
+
+    #[test]
+    fn test_deeply_nested_blocks_are_rejected() {
+        let src = format!("{}x = 1;{}", "{".repeat(3000), "}".repeat(3000));
+        assert_too_deep(&src);
+    }
+
+    #[test]
+    fn test_deep_unary_chain_is_rejected() {
+        assert_too_deep(&format!("var x = {}1;", "- ".repeat(3000)));
+    }
+
+    #[test]
+    fn test_script_reports_nesting_as_compile_error() {
+        assert!(matches!(
+            Script::compile(&nested_parens(5000)),
+            Err(CompileError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_normal_nesting_is_unaffected() {
+        let src = format!(
+            "function test() {{ {} return x; }}",
+            nested_parens(63).replace("1", "2")
+        );
+        assert_eq!(
+            compile_and_execute_function(&src, "test", &[]).unwrap(),
+            2.0
+        );
+
+        let src = format!("function test() {{ {} return x; }}", addition_chain(63));
+        assert_eq!(
+            compile_and_execute_function(&src, "test", &[]).unwrap(),
+            64.0
+        );
+    }
+
+    #[test]
+    fn test_depth_checker_limit() {
+        let program = parse_gml(&nested_parens(10));
+        let mut checker = DepthChecker::new(8);
+        program.accept(&mut checker);
+        assert!(checker.exceeded());
+
+        let mut checker = DepthChecker::new(16);
+        program.accept(&mut checker);
+        assert!(!checker.exceeded());
+    }
+
+    #[test]
+    fn test_codegen_depth_limit() {
+        let program = parse_gml(&addition_chain(40));
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        ir_generator.max_expression_depth = 32;
+        let err = program.accept(&mut ir_generator).unwrap_err();
+        assert!(
+            matches!(&err, IRGenError::InvalidOperation(message) if message == "expression too deeply nested"),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_codegen_at_parser_limit_does_not_overflow() {
+        // Just inside the default parser limit; code generation must not exhaust the
+        // test thread's stack either
+        let src = addition_chain(500);
+        assert_eq!(compile_and_execute(&src).unwrap(), 0.0);
+    }
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::runtime::parse_number;
+    use crate::tests::tests_helper::compile_and_execute;
+    use crate::utils::number_format::format_number;
+
+    #[test]
+    fn test_format_number() {
+        for (value, expected) in [
+            (0.1 + 0.2, "0.3"),
+            (3.0, "3"),
+            (-12.0, "-12"),
+            (3.5, "3.5"),
+            (1.23456, "1.23456"),
+            (-0.0, "0"),
+            (1e20, "100000000000000000000"),
+            (1e21, "1e+21"),
+            (-1.5e22, "-1.5e+22"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (1.5e-7, "1.5e-7"),
+            (123456.789, "123456.789"),
+            (1.0 / 3.0, "0.333333333333333"),
+            (2.0f64.powi(53), "9007199254740990"),
+            (f64::NAN, "NaN"),
+            (f64::INFINITY, "inf"),
+            (f64::NEG_INFINITY, "-inf"),
+        ] {
+            assert_eq!(format_number(value), expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_formatted_numbers_read_back() {
+        // Bit patterns from a fixed linear congruential sequence, so every run
+        // checks the same numbers across all magnitudes
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut checked = 0;
+        while checked < 10_000 {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            let x = f64::from_bits(state);
+            if !x.is_finite() {
+                continue;
+            }
+            let back = parse_number(&format_number(x));
+            let tolerance = x.abs() * 1e-14;
+            assert!(
+                (back - x).abs() <= tolerance,
+                "{:?} formats as {} and reads back as {:?}",
+                x,
+                format_number(x),
+                back
+            );
+            checked += 1;
+        }
+    }
+
+    #[test]
+    fn test_string_uses_the_format() {
+        let result = compile_and_execute(r#"return string(0.1 + 0.2) == "0.3";"#);
+        assert_eq!(result.unwrap(), 1.0);
+        let result =
+            compile_and_execute(r#"return string(1000000 * 1000000 * 1000000000) == "1e+21";"#);
+        assert_eq!(result.unwrap(), 1.0);
+    }
+}
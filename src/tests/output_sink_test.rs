@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod tests {
+    use crate::check_handler::{CheckHandler, CheckOptions};
+    use crate::codegen_handler::{CodeGenHandler, CodeGenOptions};
+    use crate::output_handler::{BufferSink, OutputHandler, SectionKind};
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::language_options::LanguageOptions;
+    use crate::symbol_table_handler::SymbolTableHandler;
+
+    const SRC: &str =
+        "var base = 20;\nfunction twice(x) { return x * 2; }\nreturn twice(base) + 1;";
+
+    #[test]
+    fn test_pipeline_writes_each_section_to_the_sink() {
+        let mut out = BufferSink::new();
+        OutputHandler::display_original_code(&mut out, SRC);
+        ParseHandler::perform_lexical_analysis(&mut out, SRC);
+        let program = ParseHandler::parse_source_code(&mut out, SRC).unwrap();
+        SymbolTableHandler::build_and_display_symbol_table(&mut out, &program, SRC);
+        OutputHandler::display_diagnostics(
+            &mut out,
+            &CheckHandler::lint(&program, &CheckOptions::default()),
+            SRC,
+        );
+        let result = CodeGenHandler::generate_ir_and_execute(
+            &mut out,
+            &program,
+            None,
+            &LanguageOptions::default(),
+            &CodeGenOptions {
+                save_ir: false,
+                ..CodeGenOptions::default()
+            },
+        );
+        assert_eq!(result, Some(41.0));
+
+        for kind in [
+            SectionKind::SourceListing,
+            SectionKind::LexerDump,
+            SectionKind::Ast,
+            SectionKind::SymbolTable,
+            SectionKind::Ir,
+        ] {
+            assert_eq!(out.count(kind), 1, "{:?}", kind);
+        }
+        // Stages without anything to report write no section
+        assert_eq!(out.count(SectionKind::Diagnostics), 0);
+        assert!(out.text(SectionKind::SourceListing).contains(SRC));
+        assert!(out.text(SectionKind::Ir).contains("define"));
+        assert!(
+            out.text(SectionKind::Status)
+                .contains("Main function returned:")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_are_diagnostics() {
+        let mut kinds = Vec::new();
+        let mut sink = |kind: SectionKind, _: &str| kinds.push(kind);
+        assert!(ParseHandler::parse_source_code(&mut sink, "var = ;").is_err());
+        assert_eq!(kinds, [SectionKind::Diagnostics]);
+    }
+}
@@ -1,5 +1,7 @@
 #[cfg(test)]
 mod tests {
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::build::*;
     use crate::parser::expr::Expr;
     use crate::parser::func_def::FuncDef;
     use crate::parser::program_parser;
@@ -30,31 +32,26 @@ mod tests {
         let p = parse_gml(src);
         assert_eq!(p.body.len(), 1);
         let block = match &p.body[0] {
-            TopLevel::Statement(Stmt::Block(block)) => block,
+            TopLevel::Statement(Stmt::Block(block, _)) => block,
             _ => panic!("Expected a block statement"),
         };
         assert_eq!(block.len(), 3);
         assert!(matches!(block[0], Stmt::Expr(Expr::Addition(_, _))));
-        assert!(matches!(block[1], Stmt::Expr(Expr::Number(3.0))));
-        assert!(matches!(block[2], Stmt::Expr(Expr::Number(4.0))));
+        assert!(matches!(block[1], Stmt::Expr(Expr::Number(3.0, _))));
+        assert!(matches!(block[2], Stmt::Expr(Expr::Number(4.0, _))));
     }
 
     #[test]
     fn var_stmt_and_variable_decl_list() {
         let src = "var a=1, b, c=2\n";
-        let p = parse_gml(src);
-        match &p.body[0] {
-            TopLevel::Statement(Stmt::Var(vars)) => {
-                assert_eq!(vars.len(), 3);
-                assert_eq!(vars[0].0, "a");
-                assert!(vars[0].1.is_some());
-                assert_eq!(vars[1].0, "b");
-                assert!(vars[1].1.is_none());
-                assert_eq!(vars[2].0, "c");
-                assert!(vars[2].1.is_some());
-            }
-            _ => panic!("expected var stmt"),
-        }
+        assert_eq!(
+            without_spans(parse_gml(src)),
+            program([statement(var_decl([
+                ("a", Some(num(1.0))),
+                ("b", None),
+                ("c", Some(num(2.0))),
+            ]))])
+        );
     }
 
     #[test]
@@ -69,9 +66,9 @@ mod tests {
 
         // if (1) { x = 2; }
         match &p.body[0] {
-            TopLevel::Statement(Stmt::If(cond, then_stmt, else_stmt)) => {
-                assert!(matches!(**cond, Expr::Number(1.0)));
-                assert!(matches!(**then_stmt, Stmt::Block(_)));
+            TopLevel::Statement(Stmt::If(cond, then_stmt, else_stmt, _)) => {
+                assert!(matches!(**cond, Expr::Number(1.0, _)));
+                assert!(matches!(**then_stmt, Stmt::Block(_, _)));
                 assert!(else_stmt.is_none());
             }
             _ => panic!("Expected if statement"),
@@ -79,8 +76,8 @@ mod tests {
 
         // if 0 then x = 3 else x = 4;
         match &p.body[1] {
-            TopLevel::Statement(Stmt::If(cond, then_stmt, else_stmt)) => {
-                assert!(matches!(**cond, Expr::Number(0.0)));
+            TopLevel::Statement(Stmt::If(cond, then_stmt, else_stmt, _)) => {
+                assert!(matches!(**cond, Expr::Number(0.0, _)));
                 assert!(matches!(**then_stmt, Stmt::Expr(Expr::Equal(_, _))));
                 assert!(else_stmt.is_some());
                 assert!(matches!(
@@ -93,11 +90,11 @@ mod tests {
 
         // if 1 x = 5 else { x = 6; }
         match &p.body[2] {
-            TopLevel::Statement(Stmt::If(cond, then_stmt, else_stmt)) => {
-                assert!(matches!(**cond, Expr::Number(1.0)));
+            TopLevel::Statement(Stmt::If(cond, then_stmt, else_stmt, _)) => {
+                assert!(matches!(**cond, Expr::Number(1.0, _)));
                 assert!(matches!(**then_stmt, Stmt::Expr(Expr::Equal(_, _))));
                 assert!(else_stmt.is_some());
-                assert!(matches!(**else_stmt.as_ref().unwrap(), Stmt::Block(_)));
+                assert!(matches!(**else_stmt.as_ref().unwrap(), Stmt::Block(_, _)));
             }
             _ => panic!("Expected if statement"),
         }
@@ -113,10 +110,78 @@ mod tests {
     "#;
         let p = parse_gml(src);
         assert_eq!(p.body.len(), 4);
-        assert!(matches!(&p.body[0], TopLevel::Statement(Stmt::If(_, _, _))));
-        assert!(matches!(&p.body[1], TopLevel::Statement(Stmt::If(_, _, _))));
-        assert!(matches!(&p.body[2], TopLevel::Statement(Stmt::If(_, _, _))));
-        assert!(matches!(&p.body[3], TopLevel::Statement(Stmt::If(_, _, _))));
+        assert!(matches!(
+            &p.body[0],
+            TopLevel::Statement(Stmt::If(_, _, _, _))
+        ));
+        assert!(matches!(
+            &p.body[1],
+            TopLevel::Statement(Stmt::If(_, _, _, _))
+        ));
+        assert!(matches!(
+            &p.body[2],
+            TopLevel::Statement(Stmt::If(_, _, _, _))
+        ));
+        assert!(matches!(
+            &p.body[3],
+            TopLevel::Statement(Stmt::If(_, _, _, _))
+        ));
+    }
+
+    /// The condition and then-branch of the only statement of `src`, an `if`
+    fn if_parts(src: &str) -> (Expr, Stmt) {
+        let p = parse_gml(src);
+        assert_eq!(p.body.len(), 1, "{}", src);
+        match &p.body[0] {
+            TopLevel::Statement(Stmt::If(cond, then_stmt, None, _)) => {
+                ((**cond).clone(), (**then_stmt).clone())
+            }
+            other => panic!("Expected if statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn if_then_without_parentheses() {
+        let (cond, then_stmt) = if_parts("if x then y = 1");
+        assert!(matches!(cond, Expr::Identifier(ref name, _) if name == "x"));
+        assert!(matches!(then_stmt, Stmt::Expr(Expr::Equal(_, _))));
+    }
+
+    #[test]
+    fn if_parenthesized_branch_after_parenthesized_condition() {
+        let (cond, then_stmt) = if_parts("if (x) (y+1);");
+        assert!(matches!(cond, Expr::Identifier(ref name, _) if name == "x"));
+        let Stmt::Expr(Expr::Paren(inner)) = then_stmt else {
+            panic!("Expected a parenthesized branch, got {:?}", then_stmt);
+        };
+        assert!(matches!(*inner, Expr::Addition(_, _)));
+    }
+
+    #[test]
+    fn if_condition_starting_with_parentheses() {
+        let (cond, then_stmt) = if_parts("if (a + b) * 2 > 10 then y = 1");
+        let Expr::Greater(lhs, _) = cond else {
+            panic!("Expected the whole comparison as condition, got {:?}", cond);
+        };
+        assert!(matches!(*lhs, Expr::Multiplication(_, _)));
+        assert!(matches!(then_stmt, Stmt::Expr(Expr::Equal(_, _))));
+    }
+
+    #[test]
+    fn if_condition_assignment_keeps_its_branch() {
+        // `=` assigns in a condition, with or without parentheses
+        for src in ["if x = 5 then y = 1", "if (x = 5) y = 1"] {
+            let (cond, then_stmt) = if_parts(src);
+            let Expr::Equal(target, value) = cond else {
+                panic!("Expected an assignment as condition in {}", src);
+            };
+            assert!(matches!(*target, Expr::Identifier(ref name, _) if name == "x"));
+            assert!(matches!(*value, Expr::Number(5.0, _)));
+            let Stmt::Expr(Expr::Equal(target, _)) = then_stmt else {
+                panic!("Expected the branch to assign in {}", src);
+            };
+            assert!(matches!(*target, Expr::Identifier(ref name, _) if name == "y"));
+        }
     }
 
     #[test]
@@ -140,18 +205,16 @@ mod tests {
         while 1 x++;
         do x++; until(0);
     "#;
-        let p = parse_gml(src);
-        assert_eq!(p.body.len(), 4);
-        assert!(matches!(
-            &p.body[0],
-            TopLevel::Statement(Stmt::Repeat(_, _))
-        ));
-        assert!(matches!(&p.body[1], TopLevel::Statement(Stmt::While(_, _))));
-        assert!(matches!(&p.body[2], TopLevel::Statement(Stmt::While(_, _))));
-        assert!(matches!(
-            &p.body[3],
-            TopLevel::Statement(Stmt::DoUntil(_, _))
-        ));
+        let x_plus_plus = || expr_stmt(post_increment(ident("x")));
+        assert_eq!(
+            without_spans(parse_gml(src)),
+            program([
+                statement(repeat(num(3.0), x_plus_plus())),
+                statement(while_loop(num(1.0), block([break_stmt()]))),
+                statement(while_loop(num(1.0), x_plus_plus())),
+                statement(do_until(x_plus_plus(), num(0.0))),
+            ])
+        );
     }
 
     #[test]
@@ -161,52 +224,24 @@ mod tests {
         for (x = 0; x < 1; ) { }
         for (; ; ) break;
     "#;
-        let p = parse_gml(src);
-        assert_eq!(p.body.len(), 3);
-
-        // for (var i = 0; i < 3; i++) x += i;
-        match &p.body[0] {
-            TopLevel::Statement(Stmt::For(init, cond, post, body)) => {
-                assert!(init.is_some());
-                assert!(matches!(**init.as_ref().unwrap(), Stmt::Var(_)));
-                assert!(cond.is_some());
-                assert!(matches!(**cond.as_ref().unwrap(), Expr::Less(_, _)));
-                assert!(post.is_some());
-                assert!(matches!(
-                    **post.as_ref().unwrap(),
-                    Stmt::Expr(Expr::PostIncrement(_))
-                ));
-                assert!(matches!(**body, Stmt::Expr(Expr::PlusEqual(_, _))));
-            }
-            _ => panic!("Expected for statement"),
-        }
-
-        // for (x = 0; x < 1; ) { }
-        match &p.body[1] {
-            TopLevel::Statement(Stmt::For(init, cond, post, body)) => {
-                assert!(init.is_some());
-                assert!(matches!(
-                    **init.as_ref().unwrap(),
-                    Stmt::Expr(Expr::Equal(_, _))
-                ));
-                assert!(cond.is_some());
-                assert!(matches!(**cond.as_ref().unwrap(), Expr::Less(_, _)));
-                assert!(post.is_none());
-                assert!(matches!(**body, Stmt::Block(_)));
-            }
-            _ => panic!("Expected for statement"),
-        }
-
-        // for (; ; ) break;
-        match &p.body[2] {
-            TopLevel::Statement(Stmt::For(init, cond, post, body)) => {
-                assert!(init.is_none());
-                assert!(cond.is_none());
-                assert!(post.is_none());
-                assert!(matches!(**body, Stmt::Break));
-            }
-            _ => panic!("Expected for statement"),
-        }
+        assert_eq!(
+            without_spans(parse_gml(src)),
+            program([
+                statement(for_loop(
+                    Some(var_decl([("i", Some(num(0.0)))])),
+                    Some(binop_lt(ident("i"), num(3.0))),
+                    Some(expr_stmt(post_increment(ident("i")))),
+                    expr_stmt(assign_add(ident("x"), ident("i"))),
+                )),
+                statement(for_loop(
+                    Some(expr_stmt(assign(ident("x"), num(0.0)))),
+                    Some(binop_lt(ident("x"), num(1.0))),
+                    None,
+                    block([]),
+                )),
+                statement(for_loop(None, None, None, break_stmt())),
+            ])
+        );
     }
 
     #[test]
@@ -215,32 +250,14 @@ mod tests {
         a = 1;
         a += 2; a -= 3; a *= 4; a /= 5; a %= 6;
     "#;
-        let p = parse_gml(src);
-        assert_eq!(p.body.len(), 6);
-        assert!(matches!(
-            &p.body[0],
-            TopLevel::Statement(Stmt::Expr(Expr::Equal(_, _)))
-        ));
-        assert!(matches!(
-            &p.body[1],
-            TopLevel::Statement(Stmt::Expr(Expr::PlusEqual(_, _)))
-        ));
-        assert!(matches!(
-            &p.body[2],
-            TopLevel::Statement(Stmt::Expr(Expr::MinusEqual(_, _)))
-        ));
-        assert!(matches!(
-            &p.body[3],
-            TopLevel::Statement(Stmt::Expr(Expr::StarEqual(_, _)))
-        ));
-        assert!(matches!(
-            &p.body[4],
-            TopLevel::Statement(Stmt::Expr(Expr::SlashEqual(_, _)))
-        ));
-        assert!(matches!(
-            &p.body[5],
-            TopLevel::Statement(Stmt::Expr(Expr::PercentEqual(_, _)))
-        ));
+        let operators = [
+            assign, assign_add, assign_sub, assign_mul, assign_div, assign_mod,
+        ];
+        let expected = operators
+            .iter()
+            .zip(1..)
+            .map(|(op, n)| statement(expr_stmt(op(ident("a"), num(n as f64)))));
+        assert_eq!(without_spans(parse_gml(src)), program(expected));
     }
 
     #[test]
@@ -251,9 +268,10 @@ mod tests {
         1 | 2 ^ 3 & 4;
         1 < 2 <= 2 == 2 != 3 > 1 >= 0;
         (1 + 2) * 3 / 4 % 2 - +1;
+        1 << 2 + 3 < 4 >> 1;
     "#;
         let p = parse_gml(src);
-        assert_eq!(p.body.len(), 5);
+        assert_eq!(p.body.len(), 6);
         assert!(matches!(
             &p.body[0],
             TopLevel::Statement(Stmt::Expr(Expr::Ternary(_, _, _)))
@@ -274,6 +292,32 @@ mod tests {
             &p.body[4],
             TopLevel::Statement(Stmt::Expr(Expr::Subtraction(_, _)))
         ));
+        // Shifts bind looser than arithmetic and tighter than comparisons
+        match &p.body[5] {
+            TopLevel::Statement(Stmt::Expr(Expr::Less(lhs, rhs))) => {
+                assert!(
+                    matches!(&**lhs, Expr::ShiftLeft(_, r) if matches!(**r, Expr::Addition(..)))
+                );
+                assert!(matches!(**rhs, Expr::ShiftRight(..)));
+            }
+            other => panic!("expected a comparison of shifts, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn self_and_other_fields() {
+        let p = parse_gml("self.x += other.hp;");
+        match &p.body[0] {
+            TopLevel::Statement(Stmt::Expr(Expr::PlusEqual(lhs, rhs))) => {
+                assert!(
+                    matches!(&**lhs, Expr::Field(object, field, span) if matches!(**object, Expr::SelfRef(_)) && field == "x" && *span == (0..6))
+                );
+                assert!(
+                    matches!(&**rhs, Expr::Field(object, field, _) if matches!(**object, Expr::OtherRef(_)) && field == "hp")
+                );
+            }
+            other => panic!("expected a field assignment, got {:?}", other),
+        }
     }
 
     #[test]
@@ -320,49 +364,39 @@ mod tests {
     #[test]
     fn chained_assignment() {
         let src = "a = b = 1;";
-        let p = parse_gml(src);
-        assert_eq!(p.body.len(), 1);
-        let expr = match &p.body[0] {
-            TopLevel::Statement(Stmt::Expr(expr)) => expr,
-            _ => panic!("Expected an expression statement"),
-        };
-        assert!(matches!(expr, Expr::Equal(_, _)));
-        if let Expr::Equal(_, right) = expr {
-            assert!(matches!(**right, Expr::Equal(_, _)));
-        }
+        assert_eq!(
+            without_spans(parse_gml(src)),
+            program([statement(expr_stmt(assign(
+                ident("a"),
+                assign(ident("b"), num(1.0))
+            )))])
+        );
     }
 
     #[test]
     fn nested_ternary() {
         let src = "1 ? 2 : 3 ? 4 : 5;";
-        let p = parse_gml(src);
-        assert_eq!(p.body.len(), 1);
-        let expr = match &p.body[0] {
-            TopLevel::Statement(Stmt::Expr(expr)) => expr,
-            _ => panic!("Expected an expression statement"),
-        };
-        assert!(matches!(expr, Expr::Ternary(_, _, _)));
-        if let Expr::Ternary(_, _, else_expr) = expr {
-            assert!(matches!(**else_expr, Expr::Ternary(_, _, _)));
-        }
+        assert_eq!(
+            without_spans(parse_gml(src)),
+            program([statement(expr_stmt(ternary(
+                num(1.0),
+                num(2.0),
+                ternary(num(3.0), num(4.0), num(5.0))
+            )))])
+        );
     }
 
     #[test]
     fn nested_nested_ternary() {
         let src = "1 ? 2 : 3 ? 4 : 5 ? 6 : 7;";
-        let p = parse_gml(src);
-        assert_eq!(p.body.len(), 1);
-        let expr = match &p.body[0] {
-            TopLevel::Statement(Stmt::Expr(expr)) => expr,
-            _ => panic!("Expected an expression statement"),
-        };
-        assert!(matches!(expr, Expr::Ternary(_, _, _)));
-        if let Expr::Ternary(_, _, else_expr) = expr {
-            assert!(matches!(**else_expr, Expr::Ternary(_, _, _)));
-            if let Expr::Ternary(_, _, else_expr2) = &**else_expr {
-                assert!(matches!(**else_expr2, Expr::Ternary(_, _, _)));
-            }
-        }
+        assert_eq!(
+            without_spans(parse_gml(src)),
+            program([statement(expr_stmt(ternary(
+                num(1.0),
+                num(2.0),
+                ternary(num(3.0), num(4.0), ternary(num(5.0), num(6.0), num(7.0)))
+            )))])
+        );
     }
 
     #[test]
@@ -371,7 +405,7 @@ mod tests {
         let p = parse_gml(src);
         assert_eq!(p.body.len(), 1);
         match &p.body[0] {
-            TopLevel::Function(FuncDef { name, func }) => {
+            TopLevel::Function(FuncDef { name, func, .. }) => {
                 assert_eq!(name, "bar");
                 assert_eq!(func.args.len(), 0);
                 assert_eq!(func.body.len(), 0);
@@ -380,13 +414,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn for_header_clauses_may_each_be_empty() {
+        let src = r#"
+        for (; i < 3; i++) x += i;
+        for (i = 0;; i++) break;
+        for (i = 0; i < 3;) i++;
+        for (;;) break;
+    "#;
+        let p = parse_gml(src);
+        let clauses: Vec<(bool, bool, bool)> = p
+            .body
+            .iter()
+            .map(|item| match item {
+                TopLevel::Statement(Stmt::For(init, cond, post, _, _)) => {
+                    (init.is_some(), cond.is_some(), post.is_some())
+                }
+                _ => panic!("Expected for statement, got {:?}", item),
+            })
+            .collect();
+        assert_eq!(
+            clauses,
+            [
+                (false, true, true),
+                (true, false, true),
+                (true, true, false),
+                (false, false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn for_header_with_declaration_and_update_lists() {
+        let p = parse_gml("for (var i = 0, j = 3; i < j; i++, j--) {}");
+        match &p.body[0] {
+            TopLevel::Statement(Stmt::For(init, _, post, _, _)) => {
+                assert!(matches!(init.as_deref(), Some(Stmt::Var(vars)) if vars.len() == 2));
+                assert!(
+                    matches!(post.as_deref(), Some(Stmt::Block(updates, _)) if updates.len() == 2)
+                );
+            }
+            _ => panic!("Expected for statement"),
+        }
+    }
+
+    #[test]
+    fn for_header_missing_semicolon_is_reported() {
+        for (src, clause) in [
+            ("for (var i = 0 i < 3; i++) {}", "initializer"),
+            ("for (i = 0; i < 3 i++) {}", "condition"),
+        ] {
+            let errors = ParseHandler::parse_program(src).unwrap_err();
+            assert_eq!(errors.len(), 1, "{}: {:?}", src, errors);
+            assert_eq!(
+                errors[0].to_string(),
+                format!("expected ';' after the for-loop {}", clause)
+            );
+        }
+    }
+
     #[test]
     fn for_with_compound_update() {
         let src = "for (var i = 0; i < 3; i += 1) x += i;";
         let p = parse_gml(src);
         assert_eq!(p.body.len(), 1);
         match &p.body[0] {
-            TopLevel::Statement(Stmt::For(_, _, post, _)) => {
+            TopLevel::Statement(Stmt::For(_, _, post, _, _)) => {
                 assert!(post.is_some());
                 assert!(matches!(
                     **post.as_ref().unwrap(),
@@ -419,7 +512,7 @@ mod tests {
             _ => panic!("Expected an expression statement"),
         };
         match expr {
-            Expr::Call(name, args) => {
+            Expr::Call(name, args, _) => {
                 assert_eq!(name, "foo");
                 assert_eq!(args.len(), 1);
                 assert!(matches!(args[0], Expr::PostIncrement(_)));
@@ -428,6 +521,105 @@ mod tests {
         }
     }
 
+    fn call_args(src: &str) -> Vec<Expr> {
+        let p = parse_gml(src);
+        match p.body.into_iter().next() {
+            Some(TopLevel::Statement(Stmt::Expr(Expr::Call(_, args, _)))) => args,
+            _ => panic!("Expected a call statement"),
+        }
+    }
+
+    #[test]
+    fn call_arguments_trailing_comma_and_elided_slots() {
+        assert!(call_args("foo();").is_empty());
+        assert_eq!(call_args("foo(1, 2,);").len(), 2);
+
+        let args = call_args("foo(1,,3);");
+        assert_eq!(args.len(), 3);
+        assert!(matches!(args[0], Expr::Number(n, _) if n == 1.0));
+        assert!(matches!(args[1], Expr::Undefined));
+        assert!(matches!(args[2], Expr::Number(n, _) if n == 3.0));
+
+        let args = call_args("foo(,2,,);");
+        assert_eq!(args.len(), 3);
+        assert!(matches!(args[0], Expr::Undefined));
+        assert!(matches!(args[2], Expr::Undefined));
+    }
+
+    /// The expression of the single statement `c = <src>;`
+    fn assigned_expr(src: &str) -> Expr {
+        let p = parse_gml(&format!("c = {};", src));
+        match &p.body[0] {
+            TopLevel::Statement(Stmt::Expr(Expr::Equal(_, value))) => (**value).clone(),
+            other => panic!("Expected an assignment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn prefix_operators_apply_over_postfix() {
+        let e = assigned_expr("- -x");
+        let Expr::Negative(inner) = &e else {
+            panic!("{:?}", e)
+        };
+        assert!(matches!(&**inner, Expr::Negative(x) if matches!(**x, Expr::Identifier(..))));
+
+        let e = assigned_expr("- - -a");
+        let Expr::Negative(inner) = &e else {
+            panic!("{:?}", e)
+        };
+        assert!(matches!(&**inner, Expr::Negative(x) if matches!(**x, Expr::Negative(_))));
+
+        // Postfix binds tighter, so `-x++` negates the old value of x
+        let e = assigned_expr("-x++");
+        assert!(
+            matches!(&e, Expr::Negative(x) if matches!(**x, Expr::PostIncrement(_))),
+            "{:?}",
+            e
+        );
+        let e = assigned_expr("-(x--)");
+        assert!(
+            matches!(&e, Expr::Negative(x) if matches!(**x, Expr::Paren(_))),
+            "{:?}",
+            e
+        );
+        let e = assigned_expr("!~+x--");
+        assert!(matches!(&e, Expr::Not(_)), "{:?}", e);
+        let e = assigned_expr("-2 * 3");
+        assert!(matches!(&e, Expr::Multiplication(lhs, _) if matches!(**lhs, Expr::Negative(_))));
+    }
+
+    #[test]
+    fn unspaced_repeated_signs_are_targeted_error() {
+        for (src, span) in [("c = ---a;\nx = 1;", 6..8), ("c = +++a;\nx = 1;", 6..8)] {
+            let (program, errors) = ParseHandler::parse_program_partial(src);
+            assert_eq!(errors.len(), 1, "{}: {:?}", src, errors);
+            let message = errors[0].to_string();
+            assert!(
+                message.contains("increment/decrement target must be a variable")
+                    && message.contains("- - -a"),
+                "{}",
+                message
+            );
+            assert_eq!(errors[0].span().into_range(), span, "{}", src);
+            assert_eq!(program.unwrap().body.len(), 2);
+        }
+        parse_err("c = -x++ ++;");
+    }
+
+    #[test]
+    fn call_with_only_empty_slots_is_targeted_error() {
+        let errors = ParseHandler::parse_program("x = foo(,);").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .to_string()
+                .contains("call to 'foo' has only empty argument slots"),
+            "{}",
+            errors[0]
+        );
+        assert_eq!(errors[0].span().start, 8);
+    }
+
     fn parse_err(src: &str) {
         let token_iter = Token::lexer(src).spanned().map(|(tok, span)| match tok {
             Ok(tok) => (tok, span.into()),
@@ -462,6 +654,41 @@ mod tests {
         parse_err("a = ? : 1;");
     }
 
+    #[test]
+    fn increment_of_non_variable_is_targeted_error() {
+        let cases = [
+            ("foo()++;\nx = 1;", 0..5),
+            ("1++;\nx = 1;", 0..1),
+            ("c = ++1;\nx = 1;", 6..7),
+            ("c = --(a);\nx = 1;", 6..9),
+        ];
+        for (src, span) in cases {
+            let (program, errors) = ParseHandler::parse_program_partial(src);
+            assert_eq!(errors.len(), 1, "{}: {:?}", src, errors);
+            assert!(
+                errors[0]
+                    .to_string()
+                    .contains("increment/decrement target must be a variable"),
+                "{}",
+                errors[0]
+            );
+            assert_eq!(errors[0].span().into_range(), span, "{}", src);
+            // The statement after the bad one is still parsed
+            let program = program.unwrap();
+            assert!(
+                matches!(
+                    program.body.last(),
+                    Some(TopLevel::Statement(Stmt::Expr(Expr::Equal(..))))
+                ),
+                "{}: {:?}",
+                src,
+                program.body
+            );
+        }
+        // Instance fields are valid targets
+        assert!(ParseHandler::parse_program("self.hp--;\n++other.hits;").is_ok());
+    }
+
     #[test]
     fn long_program_should_fail_due_to_invalid_unary_inside() {
         let src = r#"
@@ -538,4 +765,423 @@ mod tests {
         let p = parse_gml(src);
         assert_eq!(p.body.len(), 5);
     }
+
+    #[test]
+    fn nested_function_definition() {
+        let src = r#"
+            function outer(a) {
+                function inner(b) {
+                    return b * 2;
+                }
+                if (a) {
+                    function in_if() { return 1; }
+                }
+                return inner(a);
+            }
+        "#;
+        let p = parse_gml(src);
+        assert_eq!(p.body.len(), 1);
+        let TopLevel::Function(outer) = &p.body[0] else {
+            panic!("expected function");
+        };
+        assert!(matches!(
+            &outer.func.body[0],
+            Stmt::Function(FuncDef { name, func, .. }) if name == "inner" && func.args == ["b"]
+        ));
+        let Stmt::If(_, then_stmt, None, _) = &outer.func.body[1] else {
+            panic!("expected if");
+        };
+        let Stmt::Block(stmts, _) = then_stmt.as_ref() else {
+            panic!("expected block");
+        };
+        assert!(matches!(&stmts[0], Stmt::Function(FuncDef { name, .. }) if name == "in_if"));
+    }
+
+    #[test]
+    fn program_functions_in_source_order() {
+        let src = r#"
+            function on_step(dt) { return dt; }
+            var x = 1;
+            function on_create() {
+                function helper() { return 0; }
+                return helper();
+            }
+            function on_collision(a, b, c) { return a + b + c; }
+        "#;
+        let p = parse_gml(src);
+        let functions: Vec<(&str, usize)> = p
+            .functions()
+            .map(|f| (f.name.as_str(), f.func.args.len()))
+            .collect();
+        assert_eq!(
+            functions,
+            [("on_step", 1), ("on_create", 0), ("on_collision", 3)]
+        );
+    }
+
+    #[test]
+    fn program_functions_empty() {
+        let p = parse_gml("var x = 1;\nx += 2;\n");
+        assert_eq!(p.functions().count(), 0);
+    }
+
+    #[test]
+    fn if_branch_loop_statements() {
+        let src = r#"
+        if (x) repeat (3) b++;
+        if (x) while (y) doThing();
+        if (x) do b++; until (b > 3);
+        if (x) for (;;) break;
+        if (a) b = 1; else repeat (3) b++;
+        if (a) b = 1; else while (y) y--;
+        if (a) b = 1; else do b++; until (b);
+        if (a) repeat (3) b++; else for(;;) break;
+    "#;
+        let p = parse_gml(src);
+        assert_eq!(p.body.len(), 8);
+
+        let branches: Vec<(&Stmt, Option<&Stmt>)> = p
+            .body
+            .iter()
+            .map(|item| match item {
+                TopLevel::Statement(Stmt::If(_, then_stmt, else_stmt, _)) => {
+                    (then_stmt.as_ref(), else_stmt.as_deref())
+                }
+                _ => panic!("Expected if statement"),
+            })
+            .collect();
+
+        assert!(matches!(branches[0], (Stmt::Repeat(_, _, _), None)));
+        assert!(matches!(branches[1], (Stmt::While(_, _, _), None)));
+        assert!(matches!(branches[2], (Stmt::DoUntil(_, _, _), None)));
+        assert!(matches!(
+            branches[3],
+            (Stmt::For(_, None, None, _, _), None)
+        ));
+        assert!(matches!(branches[4].1, Some(Stmt::Repeat(_, _, _))));
+        assert!(matches!(branches[5].1, Some(Stmt::While(_, _, _))));
+        assert!(matches!(branches[6].1, Some(Stmt::DoUntil(_, _, _))));
+        assert!(matches!(
+            branches[7],
+            (Stmt::Repeat(_, _, _), Some(Stmt::For(_, None, None, _, _)))
+        ));
+    }
+
+    #[test]
+    fn if_branch_loop_dangling_else_binds_inner_if() {
+        let p = parse_gml("if (a) while (b) if (c) x = 1; else x = 2;");
+        let TopLevel::Statement(Stmt::If(_, then_stmt, None, _)) = &p.body[0] else {
+            panic!("Expected if statement without else");
+        };
+        let Stmt::While(_, body, _) = then_stmt.as_ref() else {
+            panic!("Expected while loop as then-branch");
+        };
+        assert!(matches!(body.as_ref(), Stmt::If(_, _, Some(_), _)));
+    }
+
+    #[test]
+    fn recovery_keeps_functions_after_a_syntax_error() {
+        use crate::parser::visitor::symbol_table_builder::Symbol;
+        use crate::symbol_table_handler::SymbolTableHandler;
+
+        let src =
+            "function one() {\n    var x = ;\n    return 1;\n}\nfunction two(a) { return a; }\n";
+        let (program, errors) = ParseHandler::parse_program_partial(src);
+        assert!(!errors.is_empty());
+        let program = program.expect("recovery should produce a partial AST");
+
+        let (scope, _) = SymbolTableHandler::build_symbol_table(&program);
+        assert!(matches!(
+            scope.table.get("one"),
+            Some(Symbol::Function { .. })
+        ));
+        assert!(matches!(
+            scope.table.get("two"),
+            Some(Symbol::Function { parameters }) if parameters == &["a"]
+        ));
+
+        // The bad statement is replaced, the rest of function one is kept
+        let TopLevel::Function(one) = &program.body[0] else {
+            panic!("Expected function one");
+        };
+        let Stmt::Error(span) = &one.func.body[0] else {
+            panic!("Expected an error node, got {:?}", one.func.body[0]);
+        };
+        assert_eq!(&src[span.clone()], "var x = ;");
+        assert!(matches!(one.func.body[1], Stmt::Return(Some(_))));
+        assert_eq!(program.error_spans(), vec![span.clone()]);
+    }
+
+    #[test]
+    fn recovery_resumes_at_the_next_statement() {
+        let src = "x = 1;\ny = (2 + ;\nz = 3;\n";
+        let (program, errors) = ParseHandler::parse_program_partial(src);
+        assert_eq!(errors.len(), 1);
+        let body = program.unwrap().body;
+        assert_eq!(body.len(), 3);
+        assert!(matches!(body[0], TopLevel::Statement(Stmt::Expr(_))));
+        let TopLevel::Statement(Stmt::Error(span)) = &body[1] else {
+            panic!("Expected an error node, got {:?}", body[1]);
+        };
+        assert_eq!(&src[span.clone()], "y = (2 + ;");
+        assert!(matches!(body[2], TopLevel::Statement(Stmt::Expr(_))));
+    }
+
+    #[test]
+    fn stray_character_is_reported_and_skipped() {
+        let src = "x = 1;\ny = @2;\nz = 3;\n";
+        let (program, errors) = ParseHandler::parse_program_partial(src);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(
+            errors[0].to_string(),
+            "unrecognized character '@' at line 2"
+        );
+        assert_eq!(errors[0].span().into_range(), 11..12);
+        let program = program.unwrap();
+        assert_eq!(program.body.len(), 3);
+        assert!(program.error_spans().is_empty());
+    }
+
+    #[test]
+    fn unterminated_string_ends_at_its_line() {
+        let src = "x = 1;\ns = \"abc\nz = 3;\n";
+        let (program, errors) = ParseHandler::parse_program_partial(src);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(
+            errors[0].to_string(),
+            "unterminated string starting at line 2"
+        );
+        assert_eq!(errors[0].span().into_range(), 11..15);
+        let body = program.unwrap().body;
+        assert_eq!(body.len(), 3);
+        assert!(matches!(
+            &body[1],
+            TopLevel::Statement(Stmt::Expr(Expr::Equal(_, value)))
+                if matches!(**value, Expr::String(ref s, _) if s == "abc")
+        ));
+    }
+
+    fn string_value(src: &str) -> String {
+        let (program, errors) = ParseHandler::parse_program_partial(src);
+        assert!(errors.is_empty(), "{:?}", errors);
+        match &program.unwrap().body[0] {
+            TopLevel::Statement(Stmt::Expr(Expr::Equal(_, value))) => match &**value {
+                Expr::String(s, _) => s.clone(),
+                other => panic!("Expected a string, got {:?}", other),
+            },
+            other => panic!("Expected an assignment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn verbatim_string_spans_lines() {
+        let value = string_value("s = @\"one\ntwo\r\nthree\";\nz = 3;\n");
+        assert_eq!(value, "one\ntwo\r\nthree");
+        assert_eq!(value.len(), 14);
+    }
+
+    #[test]
+    fn verbatim_string_doubled_quotes_are_one_quote() {
+        assert_eq!(string_value(r#"s = @"say ""hi""";"#), r#"say "hi""#);
+        assert_eq!(string_value(r#"s = @"""";"#), "\"");
+    }
+
+    #[test]
+    fn unterminated_verbatim_string_ends_at_its_first_line() {
+        let src = "x = 1;\ns = @\"abc\nz = 3;\n";
+        let (program, errors) = ParseHandler::parse_program_partial(src);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(
+            errors[0].to_string(),
+            "unterminated string starting at line 2"
+        );
+        assert_eq!(errors[0].span().into_range(), 11..16);
+        let body = program.unwrap().body;
+        assert_eq!(body.len(), 3);
+        assert!(matches!(
+            &body[1],
+            TopLevel::Statement(Stmt::Expr(Expr::Equal(_, value)))
+                if matches!(**value, Expr::String(ref s, _) if s == "abc")
+        ));
+    }
+
+    #[test]
+    fn identifier_at_the_length_limit_parses() {
+        let name = "a".repeat(64);
+        let src = format!("{} = 1;\n", name);
+        let (program, errors) = ParseHandler::parse_program_partial(&src);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(matches!(
+            &program.unwrap().body[0],
+            TopLevel::Statement(Stmt::Expr(Expr::Equal(target, _)))
+                if matches!(**target, Expr::Identifier(ref s, _) if *s == name)
+        ));
+    }
+
+    #[test]
+    fn overlong_identifier_is_one_error() {
+        let name = format!("long_{}", "x".repeat(60));
+        let src = format!("a = 1;\n{} = 2;\nb = 3;\n", name);
+        let (program, errors) = ParseHandler::parse_program_partial(&src);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(
+            errors[0].to_string(),
+            "identifier 'long_xxxxxxxxxxx...' exceeds maximum length of 64 (was 65)"
+        );
+        assert_eq!(errors[0].span().into_range(), 7..72);
+        // The identifier is kept whole, so the rest of the file parses as usual
+        let program = program.unwrap();
+        assert_eq!(program.body.len(), 3);
+        assert!(program.error_spans().is_empty());
+    }
+
+    #[test]
+    fn recovery_skips_stray_brace_up_to_next_function() {
+        let src = "x = 1;\n}\ny = 2;\nfunction f() { return 1; }\n";
+        let (program, errors) = ParseHandler::parse_program_partial(src);
+        assert!(!errors.is_empty());
+        let body = program.unwrap().body;
+        assert_eq!(body.len(), 3);
+        let TopLevel::Error(span) = &body[1] else {
+            panic!("Expected a top-level error node, got {:?}", body[1]);
+        };
+        assert_eq!(&src[span.clone()], "}\ny = 2;\n");
+        assert!(matches!(&body[2], TopLevel::Function(f) if f.name == "f"));
+    }
+
+    #[test]
+    fn recovery_keeps_braced_groups_together() {
+        let src = "function f() {\n    if x == { y = 1; }\n    return 2;\n}\n";
+        let (program, _) = ParseHandler::parse_program_partial(src);
+        let body = program.unwrap().body;
+        let TopLevel::Function(f) = &body[0] else {
+            panic!("Expected function f, got {:?}", body[0]);
+        };
+        assert_eq!(f.func.body.len(), 2);
+        assert!(matches!(f.func.body[0], Stmt::Error(_)));
+        assert!(matches!(f.func.body[1], Stmt::Return(Some(_))));
+    }
+
+    #[test]
+    fn parse_program_rejects_recovered_programs() {
+        assert!(ParseHandler::parse_program("x = ;\ny = 1;\n").is_err());
+        let (program, errors) = ParseHandler::parse_program_partial("x = 1;\n");
+        assert!(errors.is_empty());
+        assert!(program.unwrap().error_spans().is_empty());
+    }
+
+    #[test]
+    fn codegen_refuses_programs_with_error_nodes() {
+        use crate::codegen::ir_generator::{IRGenError, IRGenerator};
+        use crate::parser::visitor::Visitor;
+        use crate::utils::diagnostic::Diagnostic;
+
+        let src = "x = ;\nfunction f() { var y = );\n return 1; }\n";
+        let (program, _) = ParseHandler::parse_program_partial(src);
+        let program = program.unwrap();
+        let context = inkwell::context::Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        let err = ir_generator.visit_program(&program).unwrap_err();
+        let IRGenError::SyntaxErrors(spans) = &err else {
+            panic!("Expected SyntaxErrors, got {:?}", err);
+        };
+        assert_eq!(spans.len(), 2);
+        let diagnostic = Diagnostic::from(&err);
+        assert_eq!(diagnostic.code, 306);
+        assert!(
+            diagnostic.message.contains("2 syntax error(s)"),
+            "{}",
+            diagnostic.message
+        );
+    }
+
+    /// A program exercising every statement that ends at a line break, with
+    /// comments and Allman-style braces
+    const LINE_ENDING_PROGRAM: &str = "var a = 1, b\n\
+        // a comment\n\
+        enum Color\n{\n    Red,\n    Green = 2\n}\n\
+        function f(x)\n{\n    return x * 2\n}\n\
+        if (a > 0)\n{\n    b = f(a)\n}\nelse\n    b = 0\n\
+        do\n{\n    a++\n}\nuntil (a > 3)\n\
+        for (var i = 0; i < 2; i++)\n{\n    b += i\n}\n\
+        while (a > 0)\n    a--\n\
+        repeat (2)\n{\n    function g()\n    {\n        return\n    }\n}\n";
+
+    fn parse_debug(src: &str) -> String {
+        let program = ParseHandler::parse_program(src)
+            .unwrap_or_else(|errs| panic!("Parse failed: {:?}", errs));
+        format!("{:?}", program)
+    }
+
+    #[test]
+    fn line_endings_produce_identical_asts() {
+        // `\r` has the length of `\n`, so even the spans must match
+        let lf = parse_debug(LINE_ENDING_PROGRAM);
+        assert_eq!(parse_debug(&LINE_ENDING_PROGRAM.replace('\n', "\r")), lf);
+
+        // The longer endings are compared against `\n` padded to the same length
+        let padded = |padding: &str| LINE_ENDING_PROGRAM.replace('\n', &format!("{}\n", padding));
+        assert_eq!(
+            parse_debug(&LINE_ENDING_PROGRAM.replace('\n', "\r\n")),
+            parse_debug(&padded(" "))
+        );
+        assert_eq!(
+            parse_debug(&LINE_ENDING_PROGRAM.replace('\n', "\u{2028}")),
+            parse_debug(&padded("  "))
+        );
+        assert_eq!(
+            parse_debug(&LINE_ENDING_PROGRAM.replace('\n', "\u{2029}")),
+            parse_debug(&padded("  "))
+        );
+    }
+
+    #[test]
+    fn line_comment_ends_at_a_lone_carriage_return() {
+        let program = parse_debug("// comment\rx = 1\r");
+        assert!(program.contains("Identifier(\"x\""), "{}", program);
+    }
+
+    #[test]
+    fn byte_order_mark_is_skipped() {
+        let src = "\u{FEFF}var x = 1;\nx = 2\n";
+        let program = ParseHandler::parse_program(src)
+            .unwrap_or_else(|errs| panic!("Parse failed: {:?}", errs));
+        assert_eq!(program.body.len(), 2);
+        // Spans still index the text as read, mark included
+        let TopLevel::Statement(Stmt::Var(vars)) = &program.body[0] else {
+            panic!("Expected var statement, got {:?}", program.body[0]);
+        };
+        assert_eq!(&src[vars[0].2.clone()], "x");
+    }
+
+    #[test]
+    fn newlines_end_statements_only_outside_brackets() {
+        // (source, top-level statements, what the first of them parses to)
+        let cases = [
+            ("var x = 5\nvar y = 6", 2, "Var("),
+            ("x = 1\ny = 2\n\nz = 3", 3, "Equal("),
+            ("return\nx", 2, "Return(None)"),
+            ("foo(\n1,\n2\n)", 1, "Call(\"foo\""),
+            ("for (var i = 0;\ni < 3;\ni++\n) {}", 1, "For("),
+            ("x = (1\n+ 2\n)", 1, "Paren("),
+            ("foo(1,\nbar(\n2)\n)\ny = 1", 2, "Call(\"foo\""),
+        ];
+        for (src, statements, first) in cases {
+            let program = ParseHandler::parse_program(src)
+                .unwrap_or_else(|errs| panic!("Parse failed for {:?}: {:?}", src, errs));
+            assert_eq!(program.body.len(), statements, "{:?}", src);
+            let parsed = format!("{:?}", program.body[0]);
+            assert!(parsed.contains(first), "{:?}: {}", src, parsed);
+        }
+    }
+
+    #[test]
+    fn unclosed_paren_keeps_newlines_after_its_block() {
+        // The `}` closes the stray `(`, so the newline still ends `y = 1`
+        let (program, errors) = ParseHandler::parse_program_partial("{ f(\n}\ny = 1\nz = 2");
+        assert!(!errors.is_empty());
+        let program = program.unwrap();
+        let parsed = format!("{:?}", program.body);
+        assert!(parsed.contains("Identifier(\"z\""), "{}", parsed);
+    }
 }
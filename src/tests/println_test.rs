@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use crate::script::{Script, Value};
+    use crate::tests::tests_helper::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const SRC: &str = r#"
+        function show(x, y) {
+            println("x=", x, " y=", y);
+            println(true, null, 3);
+            println(1, 2, 3, 4, 5, 6, 7, "eight");
+            println("");
+            return 1;
+        }
+    "#;
+
+    fn capture(script: &Script) -> Rc<RefCell<String>> {
+        let captured = Rc::new(RefCell::new(String::new()));
+        let sink = Rc::clone(&captured);
+        script.set_print_callback(move |line| sink.borrow_mut().push_str(line));
+        captured
+    }
+
+    #[test]
+    fn test_println_joins_its_arguments_into_one_line() {
+        let script = Script::compile(SRC).unwrap();
+        let captured = capture(&script);
+        let result = script
+            .call("show", &[Value::Number(1.0), Value::Number(2.5)])
+            .unwrap();
+        assert_eq!(result, Value::Number(1.0));
+        assert_eq!(
+            *captured.borrow(),
+            "x=1 y=2.5\ntrueundefined3\n1234567eight\n\n"
+        );
+    }
+
+    #[test]
+    fn test_println_without_a_callback_prints_to_stdout() {
+        let script = Script::compile(SRC).unwrap();
+        let captured = capture(&script);
+        script.clear_print_callback();
+        script.call("show", &[]).unwrap();
+        assert!(captured.borrow().is_empty());
+
+        assert_eq!(
+            compile_and_execute(r#"println("a", 1); return 2;"#),
+            Ok(2.0)
+        );
+    }
+
+    #[test]
+    fn test_reload_keeps_the_print_callback() {
+        let mut script = Script::compile(SRC).unwrap();
+        let captured = capture(&script);
+        script
+            .reload(r#"function show(x, y) { println("v2 ", x); }"#)
+            .unwrap();
+        script.call("show", &[Value::Number(4.0)]).unwrap();
+        assert_eq!(*captured.borrow(), "v2 4\n");
+    }
+
+    #[test]
+    fn test_println_needs_an_argument() {
+        let err = compile_and_execute("println();").unwrap_err();
+        assert!(err.contains("ArgumentCountMismatch"), "{}", err);
+    }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::parser::language_options::LanguageOptions;
+    use crate::script::{Script, Value};
+    use crate::tests::tests_helper::parse_gml;
+    use inkwell::context::Context;
+
+    const FIB: &str = r#"
+        function fib(n) {
+            if (n < 2) {
+                return n;
+            }
+            return fib(n - 1) + fib(n - 2);
+        }
+        var result = fib(15);
+    "#;
+
+    #[test]
+    fn test_profile_counts_calls() {
+        let script = Script::compile_profiled(FIB, &LanguageOptions::default()).unwrap();
+        script.run_main().unwrap();
+        assert_eq!(script.get_global("result").unwrap(), Value::Number(610.0));
+
+        let profile = script.profile();
+        let calls = |name: &str| {
+            profile
+                .iter()
+                .find(|f| f.name == name)
+                .map(|f| f.calls)
+                .unwrap_or_else(|| panic!("{} not profiled: {:?}", name, profile))
+        };
+        // fib(n) makes 2 * fib(n + 1) - 1 calls in all
+        assert_eq!(calls("fib"), 1973);
+        assert_eq!(calls("main"), 1);
+        assert_eq!(profile.len(), 2);
+
+        script.call("fib", &[Value::Number(1.0)]).unwrap();
+        assert_eq!(
+            script
+                .profile()
+                .iter()
+                .find(|f| f.name == "fib")
+                .unwrap()
+                .calls,
+            1974
+        );
+    }
+
+    #[test]
+    fn test_profiling_off_emits_nothing() {
+        let program = parse_gml(FIB);
+        for profiling in [false, true] {
+            let context = Context::create();
+            let mut ir_generator = IRGenerator::new(&context, "test_module");
+            ir_generator.profiling = profiling;
+            program.accept(&mut ir_generator).unwrap();
+            let ir = ir_generator.get_module().print_to_string().to_string();
+            assert_eq!(ir.contains("col_profile_"), profiling, "{}", ir);
+        }
+
+        let script = Script::compile(FIB).unwrap();
+        script.run_main().unwrap();
+        assert!(script.profile().is_empty());
+    }
+
+    #[test]
+    fn test_reload_keeps_profiling() {
+        let mut script = Script::compile_profiled(FIB, &LanguageOptions::default()).unwrap();
+        script.reload(&FIB.replace("fib(15)", "fib(3)")).unwrap();
+        assert!(script.profile().is_empty());
+        script.call("fib", &[Value::Number(3.0)]).unwrap();
+        assert_eq!(script.profile()[0].calls, 5);
+    }
+}
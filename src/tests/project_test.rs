@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::codegen::jit::JITExecutor;
+    use crate::file_handler::{FileHandler, ProjectError};
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::program::Program;
+    use crate::tests::tests_helper::temp_dir_with;
+    use inkwell::context::Context;
+    use std::path::Path;
+
+    fn load(path: &Path) -> Result<Program, ProjectError> {
+        let files = FileHandler::read_project(path)?;
+        ParseHandler::merge_project(&files)
+    }
+
+    fn execute_main(program: &Program) -> f64 {
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        program.accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().verify().unwrap();
+        let executor = JITExecutor::new(ir_generator.get_module()).unwrap();
+        executor.execute_main().unwrap()
+    }
+
+    #[test]
+    fn test_directory_project_calls_across_files() {
+        let dir = temp_dir_with(
+            "col_project",
+            "directory",
+            &[
+                ("a.gml", "var base = 20;\nreturn helper(base) + 1;"),
+                ("b.gml", "function helper(x) { return x * 2; }"),
+            ],
+        );
+        assert!(FileHandler::is_project(&dir));
+        let program = load(&dir).unwrap();
+        assert_eq!(execute_main(&program), 41.0);
+    }
+
+    #[test]
+    fn test_manifest_lists_files_in_order() {
+        let dir = temp_dir_with(
+            "col_project",
+            "manifest",
+            &[
+                (
+                    "col.toml",
+                    "# entry point last\nfiles = [\n    \"lib/math.gml\",\n    \"main.gml\", # runs\n]\n",
+                ),
+                ("main.gml", "return triple(3);"),
+                ("lib/math.gml", "function triple(x) { return x * 3; }"),
+                ("unlisted.gml", "function triple(x) { return 0; }"),
+            ],
+        );
+        let manifest = dir.join("col.toml");
+        let files = FileHandler::read_project(&manifest).unwrap();
+        let names: Vec<&Path> = files
+            .iter()
+            .map(|file| file.path.strip_prefix(&dir).unwrap())
+            .collect();
+        assert_eq!(names, [Path::new("lib/math.gml"), Path::new("main.gml")]);
+        // A directory with a manifest uses it instead of every file under it
+        assert_eq!(execute_main(&load(&dir).unwrap()), 9.0);
+    }
+
+    #[test]
+    fn test_duplicate_function_names_both_files() {
+        let dir = temp_dir_with(
+            "col_project",
+            "duplicate",
+            &[
+                ("first.gml", "function step() { return 1; }"),
+                ("second.gml", "function step() { return 2; }"),
+            ],
+        );
+        let err = load(&dir).unwrap_err();
+        assert!(
+            matches!(&err, ProjectError::DuplicateFunction { name, .. } if name == "step"),
+            "{:?}",
+            err
+        );
+        let message = err.to_string();
+        assert!(message.contains("first.gml"), "{}", message);
+        assert!(message.contains("second.gml"), "{}", message);
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::runtime::trap::{ErrorKind, FrameInfo};
+    use crate::parser::language_options::{DivByZeroPolicy, LanguageOptions};
+    use crate::script::{RuntimeError, Script, Value};
+
+    const SRC: &str = r#"
+        function outer(x) { return middle(x) + 1; }
+        function middle(x) { return inner(x) * 2; }
+        function inner(x) { return 10 / x; }
+    "#;
+
+    fn compile(options: LanguageOptions) -> Script {
+        Script::compile_with_options(SRC, &options).unwrap()
+    }
+
+    /// The frame of `function`, stopped at the first `text` after its definition
+    fn frame(function: &str, text: &str) -> FrameInfo {
+        let definition = SRC.find(&format!("function {}", function)).unwrap();
+        let start = definition + SRC[definition..].find(text).unwrap();
+        FrameInfo {
+            function: function.to_string(),
+            span: Some(start..start + text.len()),
+        }
+    }
+
+    #[test]
+    fn test_call_depth_error_records_the_call_chain() {
+        let script = compile(LanguageOptions {
+            max_call_depth: Some(2),
+            ..LanguageOptions::default()
+        });
+        let result = script.call("outer", &[Value::Number(1.0)]);
+        let error = script.last_runtime_error().unwrap();
+        assert_eq!(result, Err(RuntimeError::Execution(error.message.clone())));
+        assert_eq!(error.kind, ErrorKind::CallDepthExceeded);
+        assert_eq!(error.function, "inner");
+        // The innermost frame is where the function that went too deep is defined
+        assert_eq!(
+            error.call_stack,
+            vec![
+                frame("inner", "inner"),
+                frame("middle", "inner(x)"),
+                frame("outer", "middle(x)"),
+            ]
+        );
+        assert_eq!(error.span, error.call_stack[0].span);
+    }
+
+    #[test]
+    fn test_division_error_spans_the_operation() {
+        let script = compile(LanguageOptions {
+            div_by_zero: DivByZeroPolicy::Error,
+            ..LanguageOptions::default()
+        });
+        assert!(script.call("outer", &[Value::Number(0.0)]).is_err());
+        let error = script.last_runtime_error().unwrap();
+        assert_eq!(error.kind, ErrorKind::DivisionByZero);
+        assert_eq!(&SRC[error.span.clone().unwrap()], "10 / x");
+        let functions: Vec<&str> = error
+            .call_stack
+            .iter()
+            .map(|frame| frame.function.as_str())
+            .collect();
+        assert_eq!(functions, ["inner", "middle", "outer"]);
+
+        // A call that completes clears it
+        assert_eq!(
+            script.call("outer", &[Value::Number(5.0)]),
+            Ok(Value::Number(5.0))
+        );
+        assert_eq!(script.last_runtime_error(), None);
+    }
+}
@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::language_options::{DivByZeroPolicy, LanguageOptions};
+    use crate::script::Value;
+    use crate::script::cache::{CacheConfig, ScriptCache};
+    use crate::tests::tests_helper::temp_dir_with;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const SRC: &str = r#"
+        var offset = 3;
+        function shift(x) {
+            if (x > 0) { return x + offset; }
+            return x;
+        }
+    "#;
+
+    /// A cache in a fresh directory under the system temp dir
+    fn fresh_cache(name: &str) -> (ScriptCache, PathBuf) {
+        let dir = temp_dir_with("col_cache", name, &[]);
+        let cache = ScriptCache::new(CacheConfig {
+            directory: dir.clone(),
+            enabled: true,
+        });
+        (cache, dir)
+    }
+
+    #[test]
+    fn test_second_compile_is_a_hit() {
+        let (cache, dir) = fresh_cache("hit");
+        let first = cache.compile(SRC).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+
+        let second = cache.compile(SRC).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        second.run_main().unwrap();
+        assert_eq!(
+            second.call("shift", &[Value::Number(4.0)]).unwrap(),
+            Value::Number(7.0)
+        );
+        assert_eq!(second.globals().collect::<Vec<_>>(), vec!["offset"]);
+        assert_eq!(second.outline(), first.outline());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_changed_source_misses() {
+        let (cache, dir) = fresh_cache("miss");
+        cache.compile(SRC).unwrap();
+        let changed = SRC.replace("offset = 3", "offset = 4");
+        let script = cache.compile(&changed).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (0, 2));
+        script.run_main().unwrap();
+        assert_eq!(
+            script.call("shift", &[Value::Number(4.0)]).unwrap(),
+            Value::Number(8.0)
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_corrupt_entry_is_recompiled() {
+        let (cache, dir) = fresh_cache("corrupt");
+        cache.compile(SRC).unwrap();
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "bc") {
+                fs::write(path, b"not bitcode").unwrap();
+            }
+        }
+
+        let script = cache.compile(SRC).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (0, 2));
+        script.run_main().unwrap();
+        assert_eq!(
+            script.call("shift", &[Value::Number(1.0)]).unwrap(),
+            Value::Number(4.0)
+        );
+        // The entry was overwritten with a good one
+        cache.compile(SRC).unwrap();
+        assert_eq!(cache.hits(), 1);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_options_and_constants_are_part_of_the_key() {
+        let (cache, dir) = fresh_cache("keyed");
+        let src = "function bonus(x) { if (DOUBLE) { return x / 0; } return x; }";
+        let double = |on: bool| HashMap::from([("DOUBLE".to_string(), Value::Bool(on))]);
+        let zero = LanguageOptions {
+            div_by_zero: DivByZeroPolicy::Zero,
+            ..LanguageOptions::default()
+        };
+
+        let compiled = [
+            (
+                LanguageOptions::default(),
+                double(false),
+                Value::Number(2.0),
+            ),
+            (
+                LanguageOptions::default(),
+                double(true),
+                Value::Number(f64::INFINITY),
+            ),
+            (zero.clone(), double(true), Value::Number(0.0)),
+        ];
+        for (options, constants, expected) in &compiled {
+            let script = cache
+                .compile_with_constants(src, options, constants)
+                .unwrap();
+            assert_eq!(
+                script.call("bonus", &[Value::Number(2.0)]).unwrap(),
+                *expected
+            );
+        }
+        assert_eq!((cache.hits(), cache.misses()), (0, 3));
+
+        for (options, constants, expected) in &compiled {
+            let script = cache
+                .compile_with_constants(src, options, constants)
+                .unwrap();
+            assert_eq!(
+                script.call("bonus", &[Value::Number(2.0)]).unwrap(),
+                *expected
+            );
+        }
+        assert_eq!((cache.hits(), cache.misses()), (3, 3));
+
+        // A hit keeps them for reloading
+        let mut script = cache
+            .compile_with_constants(src, &zero, &double(true))
+            .unwrap();
+        script
+            .reload(&format!("{}\nfunction half(x) {{ return x / 0; }}", src))
+            .unwrap();
+        assert_eq!(
+            script.call("half", &[Value::Number(2.0)]).unwrap(),
+            Value::Number(0.0)
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+}
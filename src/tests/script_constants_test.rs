@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::parser::dead_branches::prune_dead_branches;
+    use crate::parser::language_options::LanguageOptions;
+    use crate::script::constants::{ConstantError, substitute_constants};
+    use crate::script::{CompileError, Script, Value};
+    use crate::tests::tests_helper::parse_gml;
+    use inkwell::context::Context;
+    use std::collections::HashMap;
+
+    const SRC: &str = r#"
+        function heavy_logging(x) { return x * 2; }
+        function run(x) {
+            if (DEBUG) {
+                x = heavy_logging(x);
+            }
+            return x + PLATFORM;
+        }
+    "#;
+
+    fn constants(debug: bool) -> HashMap<String, Value> {
+        HashMap::from([
+            ("DEBUG".to_string(), Value::Bool(debug)),
+            ("PLATFORM".to_string(), Value::Number(2.0)),
+        ])
+    }
+
+    fn compile(src: &str, constants: &HashMap<String, Value>) -> Result<Script, CompileError> {
+        Script::compile_with_constants(src, &LanguageOptions::default(), constants)
+    }
+
+    /// The IR `Script` generates for `SRC` with the constants substituted
+    fn ir(debug: bool) -> String {
+        let mut program = parse_gml(SRC);
+        assert_eq!(
+            substitute_constants(&mut program, &constants(debug)).unwrap(),
+            2
+        );
+        prune_dead_branches(&mut program);
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        program.accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().print_to_string().to_string()
+    }
+
+    #[test]
+    fn debug_flag_selects_the_code_generated() {
+        let calls = |ir: &str| ir.matches("call double @heavy_logging").count();
+        assert_eq!(calls(&ir(true)), 1);
+        assert_eq!(calls(&ir(false)), 0);
+
+        for (debug, expected) in [(true, 12.0), (false, 7.0)] {
+            let script = compile(SRC, &constants(debug)).unwrap();
+            assert_eq!(
+                script.call("run", &[Value::Number(5.0)]).unwrap(),
+                Value::Number(expected)
+            );
+            assert_eq!(script.constants(), &constants(debug));
+        }
+    }
+
+    #[test]
+    fn assigning_or_declaring_a_constant_is_an_error() {
+        let src = "function f() { PLATFORM += 1; }";
+        let Err(CompileError::Constant(error)) = compile(src, &constants(true)) else {
+            panic!("Expected a constant error");
+        };
+        let start = src.find("PLATFORM").unwrap();
+        assert_eq!(
+            error,
+            ConstantError::Assigned {
+                name: "PLATFORM".to_string(),
+                span: start..start + "PLATFORM".len(),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Cannot assign to 'PLATFORM' at 15..23: it is a constant defined by the host"
+        );
+
+        for src in ["var DEBUG = 1;", "function f(DEBUG) { return 0; }"] {
+            assert!(
+                matches!(
+                    compile(src, &constants(true)),
+                    Err(CompileError::Constant(ConstantError::Redeclared { .. }))
+                ),
+                "{}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn undefined_names_stay_variables() {
+        let src = r#"
+            var DEBUG = 3;
+            function f() { return DEBUG + 1; }
+        "#;
+        let script = compile(src, &HashMap::new()).unwrap();
+        script.run_main().unwrap();
+        assert_eq!(script.call("f", &[]).unwrap(), Value::Number(4.0));
+        assert_eq!(script.get_global("DEBUG").unwrap(), Value::Number(3.0));
+    }
+}
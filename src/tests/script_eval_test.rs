@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use crate::script::eval::EvalError;
+    use crate::script::{RuntimeError, Script, Value};
+
+    #[test]
+    fn test_eval_arithmetic_with_globals() {
+        let globals = [("a", Value::Number(7.0)), ("b", Value::Number(3.0))];
+        let value = Script::eval("var d = a - b;\nd * 2 + a;", &globals).unwrap();
+        assert_eq!(value, Value::Number(15.0));
+    }
+
+    #[test]
+    fn test_eval_tags_bools_and_strings() {
+        let globals = [("hp", Value::Number(5.0))];
+        assert_eq!(
+            Script::eval("hp > 3;", &globals).unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            Script::eval("println(\"before\");\n\"hp: \" + string(hp);", &globals).unwrap(),
+            Value::String("hp: 5".to_string())
+        );
+    }
+
+    #[test]
+    fn test_eval_without_final_expression() {
+        let error = Script::eval("var x = 1 + 2;", &[]).unwrap_err();
+        assert!(matches!(
+            error,
+            EvalError::NoResult("a variable declaration")
+        ));
+        assert!(error.to_string().contains("not an expression"));
+
+        let error = Script::eval("x;\nfunction f() { return 1; }", &[]).unwrap_err();
+        assert!(matches!(
+            error,
+            EvalError::NoResult("a function definition")
+        ));
+    }
+
+    #[test]
+    fn test_eval_rejects_string_globals() {
+        let globals = [("name", Value::from("col"))];
+        let error = Script::eval("name;", &globals).unwrap_err();
+        assert!(matches!(
+            error,
+            EvalError::Runtime(RuntimeError::UnsupportedValue(_))
+        ));
+    }
+
+    #[test]
+    fn test_compiled_main_still_returns_zero() {
+        let script = Script::compile("var x = 2;\nx * 21;").unwrap();
+        assert_eq!(script.run_main().unwrap(), Value::Number(0.0));
+    }
+}
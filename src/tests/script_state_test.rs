@@ -0,0 +1,91 @@
+#[cfg(test)]
+mod tests {
+    use crate::script::state::{StateError, StateReport};
+    use crate::script::{Script, Value};
+
+    const SRC: &str = r#"
+        var gold = 10;
+        var level = 1;
+        var lives = 3;
+        function play() {
+            gold += 25;
+            level += 1;
+            lives -= 1;
+        }
+    "#;
+
+    fn globals(script: &Script) -> Vec<(String, Value)> {
+        script
+            .globals()
+            .map(|name| (name.to_string(), script.get_global(name).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_state_survives_recompiling() {
+        let script = Script::compile(SRC).unwrap();
+        script.run_main().unwrap();
+        script.call("play", &[]).unwrap();
+        let saved = globals(&script);
+        let state = script.save_state();
+        drop(script);
+
+        let mut script = Script::compile(SRC).unwrap();
+        let report = script.load_state(&state).unwrap();
+        assert_eq!(globals(&script), saved);
+        assert_eq!(
+            saved,
+            [
+                ("gold".to_string(), Value::Number(35.0)),
+                ("level".to_string(), Value::Number(2.0)),
+                ("lives".to_string(), Value::Number(2.0)),
+            ]
+        );
+        assert_eq!(report.restored, ["gold", "level", "lives"]);
+        assert!(report.unknown.is_empty() && report.missing.is_empty());
+    }
+
+    #[test]
+    fn test_state_from_a_modified_script() {
+        let modified = Script::compile(&SRC.replace("level", "xp")).unwrap();
+        modified.run_main().unwrap();
+
+        let mut script = Script::compile(SRC).unwrap();
+        script.run_main().unwrap();
+        let report = script.load_state(&modified.save_state()).unwrap();
+        assert_eq!(
+            report,
+            StateReport {
+                restored: vec!["gold".to_string(), "lives".to_string()],
+                unknown: vec!["xp".to_string()],
+                missing: vec!["level".to_string()],
+            }
+        );
+        // What the snapshot lacks starts over, as in a fresh compile
+        assert_eq!(script.get_global("level").unwrap(), Value::Number(0.0));
+        assert_eq!(script.get_global("gold").unwrap(), Value::Number(10.0));
+    }
+
+    #[test]
+    fn test_bad_state_changes_nothing() {
+        let mut script = Script::compile(SRC).unwrap();
+        script.run_main().unwrap();
+        let state = script.save_state();
+
+        assert_eq!(
+            script.load_state(b"not a snapshot"),
+            Err(StateError::NotASnapshot)
+        );
+        assert_eq!(
+            script.load_state(&state[..state.len() - 1]),
+            Err(StateError::Truncated)
+        );
+        let mut newer = state.clone();
+        newer[4] = 99;
+        assert_eq!(
+            script.load_state(&newer),
+            Err(StateError::UnsupportedVersion(99))
+        );
+        assert_eq!(script.get_global("gold").unwrap(), Value::Number(10.0));
+    }
+}
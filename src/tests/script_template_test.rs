@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::language_options::LanguageOptions;
+    use crate::script::template::ScriptTemplate;
+    use crate::script::{CompileError, Value};
+    use std::collections::HashMap;
+
+    const COUNTER: &str = r#"
+        var counter = 0;
+        var step = STEP;
+        function bump() {
+            counter += step;
+            return counter;
+        }
+    "#;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_template_is_send_and_sync() {
+        assert_send_sync::<ScriptTemplate>();
+    }
+
+    #[test]
+    fn test_scripts_from_one_template_run_concurrently() {
+        let constants = HashMap::from([("STEP".to_string(), Value::Number(1.0))]);
+        let template =
+            ScriptTemplate::new(COUNTER, &LanguageOptions::default(), &constants).unwrap();
+
+        let results: Vec<f64> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (1..=4)
+                .map(|thread| {
+                    let template = &template;
+                    scope.spawn(move || {
+                        let mut script = template.compile().unwrap();
+                        script.run_main().unwrap();
+                        script
+                            .set_global("step", Value::Number(thread as f64))
+                            .unwrap();
+                        for _ in 0..1000 {
+                            script.call("bump", &[]).unwrap();
+                        }
+                        let Value::Number(counter) = script.get_global("counter").unwrap() else {
+                            panic!("globals hold numbers");
+                        };
+                        counter
+                    })
+                })
+                .collect();
+            threads.into_iter().map(|t| t.join().unwrap()).collect()
+        });
+        assert_eq!(results, [1000.0, 2000.0, 3000.0, 4000.0]);
+
+        // The template is untouched by the scripts compiled from it
+        let script = template.compile().unwrap();
+        assert_eq!(script.call("bump", &[]).unwrap(), Value::Number(1.0));
+    }
+
+    #[test]
+    fn test_template_reports_compile_errors() {
+        let options = LanguageOptions::default();
+        assert!(matches!(
+            ScriptTemplate::new("function f( {", &options, &HashMap::new()),
+            Err(CompileError::Parse(_))
+        ));
+        let constants = HashMap::from([("STEP".to_string(), Value::Number(1.0))]);
+        assert!(matches!(
+            ScriptTemplate::new("STEP = 2;", &options, &constants),
+            Err(CompileError::Constant(_))
+        ));
+    }
+}
@@ -0,0 +1,646 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::language_options::LanguageOptions;
+    use crate::parser::outline::{OutlineItem, OutlineKind};
+    use crate::script::{CompileError, CompileSession, ReloadReport, RuntimeError, Script, Value};
+    use crate::{ErrorKind, Instance};
+    use std::collections::HashMap;
+
+    const COUNTER: &str = r#"
+        var counter = 0;
+        var step = 1;
+        function bump() {
+            counter += step;
+            return counter;
+        }
+        function add(a, b) {
+            return a + b;
+        }
+    "#;
+
+    #[test]
+    fn test_compile_and_call() {
+        let script = Script::compile(COUNTER).unwrap();
+        let result = script
+            .call("add", &[Value::Number(2.0), Value::Number(3.0)])
+            .unwrap();
+        assert_eq!(result, Value::Number(5.0));
+    }
+
+    #[test]
+    fn test_call_with_many_arguments() {
+        let script = Script::compile(
+            r#"
+            function digits(a, b, c, d) {
+                return a * 1000 + b * 100 + c * 10 + d;
+            }
+            function many(a, b, c, d, e, f, g, h, i, j, k, l, m) {
+                return a + b + c + d + e + f + g + h + i + j + k + l + m;
+            }
+            "#,
+        )
+        .unwrap();
+        let numbers = |count: usize| {
+            (1..=count)
+                .map(|n| Value::Number(n as f64))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            script.call("digits", &numbers(4)).unwrap(),
+            Value::Number(1234.0)
+        );
+        // Missing arguments are 0
+        assert_eq!(
+            script.call("digits", &numbers(3)).unwrap(),
+            Value::Number(1230.0)
+        );
+        assert_eq!(
+            script.call("many", &numbers(13)).unwrap(),
+            Value::Number(91.0)
+        );
+    }
+
+    #[test]
+    fn test_globals_persist_across_calls() {
+        let script = Script::compile(COUNTER).unwrap();
+        script.run_main().unwrap();
+        for expected in [1.0, 2.0, 3.0] {
+            assert_eq!(script.call("bump", &[]).unwrap(), Value::Number(expected));
+        }
+        assert_eq!(script.get_global("counter").unwrap(), Value::Number(3.0));
+    }
+
+    #[test]
+    fn test_init_then_update_share_state() {
+        let script = Script::compile(
+            r#"
+            var health = 0;
+            function init() { health = 100; return health; }
+            function update() { health -= 1; return health; }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(script.call("init", &[]).unwrap(), Value::Number(100.0));
+        assert_eq!(script.call("update", &[]).unwrap(), Value::Number(99.0));
+        assert_eq!(script.call("update", &[]).unwrap(), Value::Number(98.0));
+        assert_eq!(script.get_global("health").unwrap(), Value::Number(98.0));
+    }
+
+    #[test]
+    fn test_call_before_run_main_initializes_once() {
+        let script = Script::compile(
+            r#"
+            var base = 10;
+            var inits = 0;
+            inits += 1;
+            function get() { return base + 1; }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(script.get_global("base").unwrap(), Value::Number(0.0));
+        for _ in 0..3 {
+            assert_eq!(script.call("get", &[]).unwrap(), Value::Number(11.0));
+        }
+        assert_eq!(script.get_global("inits").unwrap(), Value::Number(1.0));
+
+        // run_main does not run them again
+        assert_eq!(script.run_main().unwrap(), Value::Number(0.0));
+        script.call("get", &[]).unwrap();
+        assert_eq!(script.get_global("inits").unwrap(), Value::Number(1.0));
+    }
+
+    #[test]
+    fn test_set_global_between_calls() {
+        let mut script = Script::compile(COUNTER).unwrap();
+        script.run_main().unwrap();
+        assert_eq!(script.call("bump", &[]).unwrap(), Value::Number(1.0));
+
+        script.set_global("step", Value::Number(10.0)).unwrap();
+        assert_eq!(script.call("bump", &[]).unwrap(), Value::Number(11.0));
+
+        script.set_global("counter", Value::Bool(true)).unwrap();
+        assert_eq!(script.call("bump", &[]).unwrap(), Value::Number(11.0));
+    }
+
+    #[test]
+    fn test_set_global_before_first_call_is_kept() {
+        let mut script = Script::compile(COUNTER).unwrap();
+        script.set_global("step", Value::Number(10.0)).unwrap();
+        assert_eq!(script.call("bump", &[]).unwrap(), Value::Number(10.0));
+        assert_eq!(script.get_global("step").unwrap(), Value::Number(10.0));
+    }
+
+    #[test]
+    fn test_top_level_statements_see_instance_bound_before_first_call() {
+        let script = Script::compile(
+            "var speed = self.base * 2;\nself.ready = 1;\nfunction get() { return speed; }",
+        )
+        .unwrap();
+        script
+            .bind_instance(Instance::Self_, &[("base", Value::Number(4.0))])
+            .unwrap();
+        assert_eq!(script.call("get", &[]).unwrap(), Value::Number(8.0));
+        assert_eq!(
+            script.instance_field(Instance::Self_, "ready"),
+            Some(Value::Number(1.0))
+        );
+        assert_eq!(script.take_runtime_error(), None);
+    }
+
+    #[test]
+    fn test_local_initializer_reads_shadowed_global() {
+        let mut script = Script::compile(
+            r#"
+            var scale = 3;
+            function scaled(x) {
+                var scale = scale * 2;
+                return scale * x;
+            }
+        "#,
+        )
+        .unwrap();
+        script.run_main().unwrap();
+        assert_eq!(
+            script.call("scaled", &[Value::Number(1.0)]).unwrap(),
+            Value::Number(6.0)
+        );
+        assert_eq!(script.get_global("scale").unwrap(), Value::Number(3.0));
+        script.set_global("scale", Value::Number(5.0)).unwrap();
+        assert_eq!(
+            script.call("scaled", &[Value::Number(1.0)]).unwrap(),
+            Value::Number(10.0)
+        );
+    }
+
+    #[test]
+    fn test_globals_before_run_main_are_zero() {
+        let script = Script::compile(COUNTER).unwrap();
+        assert_eq!(script.get_global("step").unwrap(), Value::Number(0.0));
+        script.run_main().unwrap();
+        assert_eq!(script.get_global("step").unwrap(), Value::Number(1.0));
+        assert_eq!(script.globals().collect::<Vec<_>>(), ["counter", "step"]);
+    }
+
+    #[test]
+    fn test_run_main_returns_top_level_return() {
+        let script = Script::compile("var x = 20;\nreturn x + 1;").unwrap();
+        assert_eq!(script.run_main().unwrap(), Value::Number(21.0));
+    }
+
+    #[test]
+    fn test_missing_arguments_are_zero() {
+        let script = Script::compile(COUNTER).unwrap();
+        let result = script.call("add", &[Value::Number(4.0)]).unwrap();
+        assert_eq!(result, Value::Number(4.0));
+    }
+
+    #[test]
+    fn test_call_errors() {
+        let mut script = Script::compile(COUNTER).unwrap();
+        assert_eq!(
+            script.call("missing", &[]),
+            Err(RuntimeError::UnknownFunction("missing".to_string()))
+        );
+        assert_eq!(
+            script.call("bump", &[Value::Null]),
+            Err(RuntimeError::TooManyArguments {
+                function: "bump".to_string(),
+                expected: 0,
+                given: 1,
+            })
+        );
+        assert_eq!(
+            script.call("add", &[Value::Number(1.0), Value::String("x".to_string())]),
+            Err(RuntimeError::UnsupportedArgument {
+                function: "add".to_string(),
+                index: 1,
+                value: Value::String("x".to_string()),
+            })
+        );
+        assert_eq!(
+            script.set_global("nope", Value::Number(1.0)),
+            Err(RuntimeError::UnknownGlobal("nope".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_each_value_type_as_a_number_argument() {
+        let script = Script::compile("function twice(x) { return x * 2; }").unwrap();
+        let twice = |arg: Value| script.call("twice", &[arg]);
+        assert_eq!(twice(Value::Number(2.5)), Ok(Value::Number(5.0)));
+        assert_eq!(twice(Value::Bool(true)), Ok(Value::Number(2.0)));
+        assert_eq!(twice(Value::Bool(false)), Ok(Value::Number(0.0)));
+        assert_eq!(twice(Value::Null), Ok(Value::Number(0.0)));
+
+        let error = twice(Value::String("2".to_string())).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Argument 0 of 'twice' is a string, but script functions only take numbers, bools and null"
+        );
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert!(matches!(
+            Script::compile("function f( {"),
+            Err(CompileError::Parse(diagnostics)) if !diagnostics.is_empty()
+        ));
+        let Err(CompileError::Codegen(diagnostic)) = Script::compile("x = y;") else {
+            panic!("expected a codegen error");
+        };
+        assert_eq!(diagnostic.code, 301);
+    }
+
+    #[test]
+    fn test_string_global_is_rejected() {
+        assert!(matches!(
+            Script::compile("var name = \"col\";"),
+            Err(CompileError::Codegen(_))
+        ));
+    }
+
+    #[test]
+    fn test_scripts_are_independent() {
+        let first = Script::compile(COUNTER).unwrap();
+        let second = Script::compile(COUNTER).unwrap();
+        first.run_main().unwrap();
+        second.run_main().unwrap();
+        first.call("bump", &[]).unwrap();
+        first.call("bump", &[]).unwrap();
+        assert_eq!(second.call("bump", &[]).unwrap(), Value::Number(1.0));
+    }
+
+    fn compile_in_chunks(source: &[u8], size: usize) -> Result<Script, CompileError> {
+        let mut session = CompileSession::new();
+        for chunk in source.chunks(size) {
+            session.append(chunk)?;
+        }
+        session.finish()
+    }
+
+    #[test]
+    fn test_chunked_compile_matches_single_buffer() {
+        let source = format!(
+            "// Zähler für Schritte ✓\n{}function label() {{ var s = \"naïve ☃\"; return 1; }}\n",
+            COUNTER
+        );
+        for size in [1, 2, 3, 7] {
+            let whole = Script::compile(&source).unwrap();
+            let chunked = compile_in_chunks(source.as_bytes(), size).unwrap();
+            assert_eq!(
+                chunked.globals().collect::<Vec<_>>(),
+                whole.globals().collect::<Vec<_>>()
+            );
+            whole.run_main().unwrap();
+            chunked.run_main().unwrap();
+            for _ in 0..3 {
+                assert_eq!(chunked.call("bump", &[]), whole.call("bump", &[]));
+            }
+            let args = [Value::Number(2.0), Value::Number(5.0)];
+            assert_eq!(chunked.call("add", &args), whole.call("add", &args));
+        }
+    }
+
+    #[test]
+    fn test_chunked_compile_rejects_invalid_utf8() {
+        let mut session = CompileSession::new();
+        session.append(b"var a = 1;").unwrap();
+        assert!(matches!(
+            session.append(b"\n\xff;"),
+            Err(CompileError::InvalidUtf8 { offset: 11 })
+        ));
+
+        // A split character that turns out not to continue
+        let mut session = CompileSession::new();
+        session
+            .append("var é".as_bytes().split_last().unwrap().1)
+            .unwrap();
+        assert!(matches!(
+            session.append(b"x"),
+            Err(CompileError::InvalidUtf8 { offset: 4 })
+        ));
+
+        // Source ending in the middle of a character
+        let mut session = CompileSession::new();
+        session.append(&"x = \"☃".as_bytes()[..6]).unwrap();
+        assert!(matches!(
+            session.finish(),
+            Err(CompileError::InvalidUtf8 { offset: 5 })
+        ));
+    }
+
+    #[test]
+    fn test_chunked_compile_reports_syntax_errors() {
+        assert!(matches!(
+            compile_in_chunks(b"var = ;", 3),
+            Err(CompileError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_chunked_compile_with_options_and_constants() {
+        let source =
+            "function pick(x) {\n    switch (x) { case 1: return STEP; }\n    return 0;\n}\n";
+        let options = LanguageOptions {
+            allow_switch: true,
+            ..LanguageOptions::default()
+        };
+        let constants = HashMap::from([("STEP".to_string(), Value::Number(4.0))]);
+        let mut session = CompileSession::with_options(&options, &constants);
+        for chunk in source.as_bytes().chunks(5) {
+            session.append(chunk).unwrap();
+        }
+        let script = session.finish().unwrap();
+        assert_eq!(
+            script.call("pick", &[Value::Number(1.0)]).unwrap(),
+            Value::Number(4.0)
+        );
+
+        // Without them the switch does not parse
+        assert!(matches!(
+            compile_in_chunks(source.as_bytes(), 5),
+            Err(CompileError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_reload_keeps_globals_and_survives_bad_source() {
+        let mut script = Script::compile(COUNTER).unwrap();
+        script.run_main().unwrap();
+        script.call("bump", &[]).unwrap();
+        assert_eq!(script.call("bump", &[]).unwrap(), Value::Number(2.0));
+
+        let v2 = r#"
+            var counter = 0;
+            var bonus = 5;
+            function bump() {
+                counter += 10;
+                return counter;
+            }
+        "#;
+        let report = script.reload(v2).unwrap();
+        assert_eq!(
+            report,
+            ReloadReport {
+                kept: vec!["counter".to_string()],
+                added: vec!["bonus".to_string()],
+                removed: vec!["step".to_string()],
+            }
+        );
+        assert_eq!(script.call("bump", &[]).unwrap(), Value::Number(12.0));
+        assert_eq!(script.get_global("bonus").unwrap(), Value::Number(0.0));
+        assert_eq!(
+            script.call("add", &[]),
+            Err(RuntimeError::UnknownFunction("add".to_string()))
+        );
+
+        assert!(matches!(
+            script.reload("function bump( {"),
+            Err(CompileError::Parse(_))
+        ));
+        assert_eq!(script.call("bump", &[]).unwrap(), Value::Number(22.0));
+    }
+
+    const COMPARISONS: &str = r#"
+        function point_three() {
+            return 0.1 + 0.2 == 0.3 ? 1 : 0;
+        }
+        // One bit per operator: == != < <= > >=
+        function compare(a, b) {
+            var low = (a == b ? 1 : 0) + (a != b ? 2 : 0) + (a < b ? 4 : 0);
+            return low + (a <= b ? 8 : 0) + (a > b ? 16 : 0) + (a >= b ? 32 : 0);
+        }
+    "#;
+
+    fn compare(script: &Script, a: f64, b: f64) -> Value {
+        script
+            .call("compare", &[Value::Number(a), Value::Number(b)])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_math_epsilon_comparisons() {
+        let script = Script::compile(COMPARISONS).unwrap();
+        assert_eq!(script.math_epsilon(), 0.0);
+        assert_eq!(script.call("point_three", &[]).unwrap(), Value::Number(0.0));
+        assert_eq!(
+            compare(&script, 1.0, 1.000001),
+            Value::Number(2.0 + 4.0 + 8.0)
+        );
+        assert_eq!(compare(&script, 2.0, 2.0), Value::Number(1.0 + 8.0 + 32.0));
+
+        script.set_math_epsilon(0.00001);
+        assert_eq!(script.call("point_three", &[]).unwrap(), Value::Number(1.0));
+        // Within epsilon: equal, though `<` and `>` still see the difference
+        assert_eq!(
+            compare(&script, 1.0, 1.000001),
+            Value::Number(1.0 + 4.0 + 8.0 + 32.0)
+        );
+        // Further apart than epsilon: as exact comparisons
+        assert_eq!(compare(&script, 1.0, 1.5), Value::Number(2.0 + 4.0 + 8.0));
+        assert_eq!(compare(&script, 1.5, 1.0), Value::Number(2.0 + 16.0 + 32.0));
+    }
+
+    #[test]
+    fn test_math_set_epsilon_from_the_script() {
+        let mut script = Script::compile(
+            r#"
+            function loosen(epsilon) {
+                math_set_epsilon(epsilon);
+                return math_get_epsilon();
+            }
+            function same(a, b) {
+                return a == b ? 1 : 0;
+            }
+            "#,
+        )
+        .unwrap();
+        let same = |script: &Script| {
+            script
+                .call("same", &[Value::Number(1.0), Value::Number(1.005)])
+                .unwrap()
+        };
+        assert_eq!(same(&script), Value::Number(0.0));
+        assert_eq!(
+            script.call("loosen", &[Value::Number(0.01)]).unwrap(),
+            Value::Number(0.01)
+        );
+        assert_eq!(script.math_epsilon(), 0.01);
+        assert_eq!(same(&script), Value::Number(1.0));
+        assert_eq!(
+            script.call("loosen", &[Value::Number(-1.0)]).unwrap(),
+            Value::Number(0.0)
+        );
+
+        script.set_math_epsilon(0.01);
+        script
+            .reload("function same(a, b) { return a != b ? 0 : 1; }")
+            .unwrap();
+        assert_eq!(script.math_epsilon(), 0.01);
+        assert_eq!(same(&script), Value::Number(1.0));
+    }
+
+    #[test]
+    fn test_reload_carries_collections_over() {
+        let mut script = Script::compile(
+            r#"
+            var list = 0;
+            function make() { list = ds_list_create(); ds_list_add(list, 7); return list; }
+            "#,
+        )
+        .unwrap();
+        script.call("make", &[]).unwrap();
+        script
+            .reload("var list = 0;\nfunction first() { return ds_list_find_value(list, 0); }")
+            .unwrap();
+        assert_eq!(script.call("first", &[]).unwrap(), Value::Number(7.0));
+        assert_eq!(script.take_runtime_error(), None);
+    }
+
+    #[test]
+    fn test_self_fields_are_the_hosts() {
+        let script = Script::compile("self.x += 2;\nreturn self.x;").unwrap();
+        script
+            .bind_instance(Instance::Self_, &[("x", Value::Number(3.0))])
+            .unwrap();
+        assert_eq!(script.run_main().unwrap(), Value::Number(5.0));
+        assert_eq!(
+            script.instance_field(Instance::Self_, "x"),
+            Some(Value::Number(5.0))
+        );
+        assert_eq!(script.take_runtime_error(), None);
+    }
+
+    #[test]
+    fn test_other_fields_are_separate_from_self() {
+        let script = Script::compile(
+            "function hit(damage) {\n    other.hp -= damage;\n    self.score = 1;\n    return other.hp;\n}",
+        )
+        .unwrap();
+        script
+            .bind_instance(Instance::Self_, &[("hp", Value::Number(100.0))])
+            .unwrap();
+        script
+            .bind_instance(Instance::Other, &[("hp", Value::Number(10.0))])
+            .unwrap();
+        assert_eq!(
+            script.call("hit", &[Value::Number(4.0)]).unwrap(),
+            Value::Number(6.0)
+        );
+        let fields = script.unbind_instance(Instance::Self_).unwrap();
+        assert_eq!(fields["hp"], Value::Number(100.0));
+        // Assigning a missing field adds it
+        assert_eq!(fields["score"], Value::Number(1.0));
+    }
+
+    #[test]
+    fn test_unbound_self_is_a_runtime_error() {
+        let script = Script::compile("function get() { return self.x + 1; }").unwrap();
+        assert_eq!(script.call("get", &[]).unwrap(), Value::Number(1.0));
+        let error = script.take_runtime_error().unwrap();
+        assert_eq!(error.kind, ErrorKind::InvalidField);
+        assert_eq!(error.message, "self.x: no instance is bound to self");
+        // Recovered from, so the call's own error channel stays clear
+        assert_eq!(script.last_runtime_error(), None);
+
+        script.bind_instance(Instance::Self_, &[]).unwrap();
+        assert_eq!(script.call("get", &[]).unwrap(), Value::Number(1.0));
+        assert_eq!(
+            script.take_runtime_error().map(|e| e.message).as_deref(),
+            Some("self.x: self has no field 'x'")
+        );
+    }
+
+    #[test]
+    fn test_bare_self_does_not_compile() {
+        assert!(matches!(
+            Script::compile("var me = self;"),
+            Err(CompileError::Codegen(_))
+        ));
+    }
+
+    const OUTLINED: &str = "function first(a) {
+    return a;
+}
+
+function second(n) {
+    var total = 0;
+    for (var i = 0; i < n; i++) {
+        total += i;
+    }
+    return total;
+}
+";
+
+    fn item(
+        kind: OutlineKind,
+        name: Option<&str>,
+        parent: Option<usize>,
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> OutlineItem {
+        OutlineItem {
+            kind,
+            name: name.map(str::to_string),
+            parent,
+            start_line: start.0,
+            start_col: start.1,
+            end_line: end.0,
+            end_col: end.1,
+        }
+    }
+
+    #[test]
+    fn test_outline_nests_loops_in_functions() {
+        let expected = vec![
+            item(OutlineKind::Function, Some("first"), None, (1, 1), (3, 2)),
+            item(OutlineKind::Function, Some("second"), None, (5, 1), (11, 2)),
+            item(OutlineKind::Loop, None, Some(1), (7, 5), (9, 6)),
+        ];
+        let script = Script::compile(OUTLINED).unwrap();
+        assert_eq!(script.outline(), expected);
+
+        let crlf = Script::compile(&OUTLINED.replace('\n', "\r\n")).unwrap();
+        assert_eq!(crlf.outline(), expected);
+    }
+
+    #[test]
+    fn test_outline_of_if_and_block() {
+        let script =
+            Script::compile("var x = 0;\nif (x) {\n    { x = 1; }\n} else x = 2;\n").unwrap();
+        assert_eq!(
+            script.outline(),
+            vec![
+                item(OutlineKind::If, None, None, (2, 1), (4, 13)),
+                item(OutlineKind::Block, None, Some(0), (3, 5), (3, 15)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_values_built_from_host_types() {
+        let mut script = Script::compile(COUNTER).unwrap();
+        script.run_main().unwrap();
+        for (value, number) in [
+            (Value::from(4.5), 4.5),
+            (Value::from(true), 1.0),
+            (Value::from(false), 0.0),
+            (Value::Null, 0.0),
+        ] {
+            assert_eq!(value.as_number(), Some(number), "{:?}", value);
+            script.set_global("step", value).unwrap();
+            assert_eq!(script.get_global("step").unwrap(), Value::Number(number));
+        }
+
+        let text = String::from("col");
+        let value = Value::from(text.as_str());
+        drop(text);
+        assert_eq!(value.as_str(), Some("col"));
+        assert_eq!(value.as_number(), None);
+        assert_eq!(Value::from(1.0).as_str(), None);
+        assert_eq!(
+            script.set_global("step", value.clone()),
+            Err(RuntimeError::UnsupportedValue(value))
+        );
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use crate::check_handler::{CheckHandler, CheckOptions};
+    use crate::parser::language_options::LanguageOptions;
+    use crate::parser::visitor::shadow_linter::{
+        PARAMETER_SHADOWS_GLOBAL, SHADOWED_VARIABLE, ShadowLinter,
+    };
+    use crate::parser::visitor::symbol_table_builder::SymbolDiagnosticKind;
+    use crate::tests::tests_helper::parse_gml;
+    use crate::utils::diagnostic::{Diagnostic, Severity};
+    use crate::utils::line_index::LineIndex;
+    use std::path::Path;
+
+    const NESTED: &str = "var x = 10;\nif (true) {\n    var x = 20;\n    x += 5;\n}\n";
+
+    fn lint(src: &str) -> Vec<Diagnostic> {
+        ShadowLinter::lint(&parse_gml(src))
+    }
+
+    #[test]
+    fn test_nested_block_warns_with_both_locations() {
+        let diagnostics = lint(NESTED);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.code, SHADOWED_VARIABLE);
+        assert_eq!(diagnostic.severity, Severity::Warning);
+
+        let inner = diagnostic.span.clone().unwrap();
+        assert_eq!(&NESTED[inner.clone()], "x");
+        assert_eq!(inner.start, NESTED.rfind("var x").unwrap() + 4);
+        assert_eq!(diagnostic.labels.len(), 1);
+        assert_eq!(diagnostic.labels[0].span, 4..5);
+        assert_eq!(
+            diagnostic.render(&LineIndex::new(NESTED)),
+            "3:9: warning[E0402]: 'x' shadows a variable this scope can no longer use; \
+             rename it, e.g. to 'x2'\n    1:5: shadowed declaration"
+        );
+        assert!(
+            diagnostic.message.contains("rename it, e.g. to 'x2'"),
+            "{}",
+            diagnostic.message
+        );
+    }
+
+    #[test]
+    fn test_suggested_name_is_not_taken() {
+        let src = "var x = 1;\nvar x2 = 2;\nif (true) { var x = 3; }\n";
+        let diagnostics = lint(src);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert!(
+            diagnostics[0].message.ends_with("'x3'"),
+            "{:?}",
+            diagnostics
+        );
+    }
+
+    #[test]
+    fn test_loop_variable_shadowing_parameter_warns() {
+        let src = "function f(i) {\n    for (var i = 0; i < 3; i++) {}\n    return i;\n}\n";
+        let diagnostics = lint(src);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].code, SHADOWED_VARIABLE);
+        let inner = diagnostics[0].span.clone().unwrap();
+        assert_eq!(&src[inner], "i");
+        // A parameter is located by the name of its function
+        assert_eq!(diagnostics[0].labels[0].span, 9..10);
+    }
+
+    #[test]
+    fn test_sibling_scopes_and_other_functions_do_not_warn() {
+        let src = r#"
+            var total = 0;
+            function f() {
+                if (true) { var t = 1; } else { var t = 2; }
+                { var u = 1; }
+                { var u = 2; }
+                var total = 3;
+                return total;
+            }
+        "#;
+        assert!(lint(src).is_empty(), "{:?}", lint(src));
+    }
+
+    #[test]
+    fn test_parameter_shadowing_global_warns() {
+        let src = "var speed = 1;\nfunction move(speed) {\n    return speed;\n}\n";
+        let diagnostics = lint(src);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].code, PARAMETER_SHADOWS_GLOBAL);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        let span = diagnostics[0].span.clone().unwrap();
+        assert_eq!(&src[span], "move");
+        assert_eq!(diagnostics[0].labels[0].span, 4..9);
+    }
+
+    #[test]
+    fn test_off_by_default() {
+        let diagnostics = CheckHandler::lint(&parse_gml(NESTED), &CheckOptions::default());
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn test_warning_replaces_the_shadowing_note() {
+        let path = Path::new("check_test.gml");
+        let shadowing = SymbolDiagnosticKind::Shadowing.code();
+        let report = CheckHandler::check_source(
+            NESTED,
+            path,
+            false,
+            &LanguageOptions::default(),
+            &CheckOptions::default(),
+        )
+        .unwrap();
+        let codes: Vec<_> = report.warnings.iter().map(|d| d.code).collect();
+        assert_eq!(codes, [shadowing]);
+
+        let options = CheckOptions {
+            warn_shadowing: true,
+            ..CheckOptions::default()
+        };
+        let report =
+            CheckHandler::check_source(NESTED, path, false, &LanguageOptions::default(), &options)
+                .unwrap();
+        let codes: Vec<_> = report.warnings.iter().map(|d| d.code).collect();
+        assert_eq!(codes, [SHADOWED_VARIABLE]);
+    }
+}
@@ -0,0 +1,160 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::runtime::{
+        format_number_padded, parse_number, string_char_at, string_copy, string_pos,
+    };
+    use crate::tests::tests_helper::*;
+
+    #[test]
+    fn test_format_number_padded() {
+        assert_eq!(format_number_padded(1.23456, 4.0, 2.0), "   1.23");
+        assert_eq!(format_number_padded(42.0, 4.0, 0.0), "  42");
+        assert_eq!(format_number_padded(12345.0, 2.0, 1.0), "12345.0");
+    }
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number(" 42 "), 42.0);
+        assert_eq!(parse_number("-1.5"), -1.5);
+        assert_eq!(parse_number("abc"), 0.0);
+        assert_eq!(parse_number(""), 0.0);
+    }
+
+    #[test]
+    fn test_string_of_number() {
+        let result = compile_and_execute(r#"return string(3) == "3" && string(3.5) == "3.5";"#);
+        assert_eq!(result.unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_string_of_bool_and_string() {
+        let result = compile_and_execute(
+            r#"return string(true) == "true" && string(1 > 2) == "false" && string("a") == "a";"#,
+        );
+        assert_eq!(result.unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_string_of_null() {
+        let result = compile_and_execute(r#"return string(null) == "undefined";"#);
+        assert_eq!(result.unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_real_of_string() {
+        assert_eq!(
+            compile_and_execute(r#"return real("42") + 1;"#).unwrap(),
+            43.0
+        );
+        assert_eq!(compile_and_execute(r#"return real("abc");"#).unwrap(), 0.0);
+        assert_eq!(compile_and_execute("return real(7);").unwrap(), 7.0);
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        let src = r#"
+            var label = "score: " + string(12);
+            return label == "score: 12";
+        "#;
+        assert_eq!(compile_and_execute(src).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_string_comparison() {
+        let src = r#"return "apple" < "banana" && "b" > "a" && "a" != "b" && "a" <= "a";"#;
+        assert_eq!(compile_and_execute(src).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_string_format() {
+        let src = r#"return string_format(1.23456, 4, 2) == "   1.23";"#;
+        assert_eq!(compile_and_execute(src).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_builtin_argument_count() {
+        let err = compile_and_execute("return string(1, 2);").unwrap_err();
+        assert!(err.contains("ArgumentCountMismatch"), "{}", err);
+        let err = compile_and_execute("return string_format(1);").unwrap_err();
+        assert!(err.contains("ArgumentCountMismatch"), "{}", err);
+    }
+
+    #[test]
+    fn test_user_function_shadows_builtin() {
+        let src = "function real(x) { return x * 2; }\nreturn real(4);";
+        assert_eq!(compile_and_execute(src).unwrap(), 8.0);
+    }
+
+    #[test]
+    fn test_string_and_number_do_not_mix() {
+        let err = compile_and_execute(r#"return "a" + 1;"#).unwrap_err();
+        assert!(err.contains("TypeMismatch"), "{}", err);
+    }
+
+    #[test]
+    fn test_string_positions_count_characters() {
+        assert_eq!(string_char_at("héllo", 2.0), "é");
+        assert_eq!(string_char_at("héllo", 3.0), "l");
+        assert_eq!(string_copy("héllo", 2.0, 3.0), "éll");
+        assert_eq!(string_pos("llo", "héllo"), 3.0);
+        assert_eq!(string_pos("o", "hello world"), 5.0);
+    }
+
+    #[test]
+    fn test_string_positions_clamp() {
+        assert_eq!(string_char_at("abc", 0.0), "a");
+        assert_eq!(string_char_at("abc", -5.0), "a");
+        assert_eq!(string_char_at("abc", 4.0), "");
+        assert_eq!(string_char_at("abc", f64::NAN), "a");
+        assert_eq!(string_char_at("", 1.0), "");
+        assert_eq!(string_copy("abcdef", 0.0, 2.0), "ab");
+        assert_eq!(string_copy("abcdef", 5.0, 10.0), "ef");
+        assert_eq!(string_copy("abcdef", 9.0, 2.0), "");
+        assert_eq!(string_copy("abcdef", 2.0, -1.0), "");
+        assert_eq!(string_copy("abcdef", 2.5, 1e300), "bcdef");
+    }
+
+    #[test]
+    fn test_string_pos_miss_is_zero() {
+        assert_eq!(string_pos("z", "abc"), 0.0);
+        assert_eq!(string_pos("", "abc"), 0.0);
+        assert_eq!(string_pos("abcd", "abc"), 0.0);
+    }
+
+    #[test]
+    fn test_string_inspection_in_scripts() {
+        for (src, expected) in [
+            (r#"return string_length("hello");"#, 5.0),
+            (r#"return string_length("héllo");"#, 5.0),
+            (r#"return string_length("");"#, 0.0),
+            ("return string_length(@\"one\ntwo\nthree\");", 13.0),
+            (r#"return string_length(@"a""b");"#, 3.0),
+            (r#"return string_length(123);"#, 3.0),
+            (r#"return string_char_at("héllo", 2) == "é";"#, 1.0),
+            (r#"return string_char_at("abc", 10) == "";"#, 1.0),
+            (r#"return string_copy("héllo", 2, 3) + "!" == "éll!";"#, 1.0),
+            (r#"return string_copy("abc", -3, 100) == "abc";"#, 1.0),
+            (r#"return string_pos("llo", "héllo");"#, 3.0),
+            (r#"return string_pos("x", "héllo");"#, 0.0),
+            (
+                r#"var s = "a,b"; return string_copy(s, 1, string_pos(",", s) - 1) == "a";"#,
+                1.0,
+            ),
+        ] {
+            assert_eq!(compile_and_execute(src), Ok(expected), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_string_inspection_argument_count() {
+        for src in [
+            "return string_length();",
+            r#"return string_char_at("a");"#,
+            r#"return string_copy("a", 1);"#,
+            r#"return string_pos("a", "b", "c");"#,
+        ] {
+            let err = compile_and_execute(src).unwrap_err();
+            assert!(err.contains("ArgumentCountMismatch"), "{}: {}", src, err);
+        }
+    }
+}
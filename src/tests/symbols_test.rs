@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::codegen::ir_generator::symbols::{DEFAULT_SYMBOL_PREFIX, symbol_names};
+    use crate::codegen::jit::JITExecutor;
+    use crate::script::{Script, Value};
+    use crate::tests::tests_helper::parse_gml;
+    use inkwell::context::Context;
+
+    fn prefixed<'ctx>(context: &'ctx Context, module: &str, src: &str) -> IRGenerator<'ctx> {
+        let mut ir_generator = IRGenerator::new(context, module);
+        ir_generator.symbol_prefix = Some(DEFAULT_SYMBOL_PREFIX.to_string());
+        parse_gml(src).accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().verify().unwrap();
+        ir_generator
+    }
+
+    #[test]
+    fn test_ir_uses_prefixed_symbols() {
+        let context = Context::create();
+        let src = "function update(x) {\n    function helper() { return 1; }\n    return x + helper();\n}\nreturn update(1);";
+        let ir_generator = prefixed(&context, "mod1", src);
+        let ir = ir_generator.get_module().print_to_string().to_string();
+        for symbol in [
+            "define double @col_mod1_main(",
+            "define double @col_mod1___col_init(",
+            "define double @col_mod1_update(",
+            "define double @col_mod1_update.helper(",
+        ] {
+            assert!(ir.contains(symbol), "{} missing from\n{}", symbol, ir);
+        }
+        assert!(!ir.contains("define double @update("), "{}", ir);
+
+        let symbols = symbol_names(ir_generator.get_module());
+        assert_eq!(symbols["update"], "col_mod1_update");
+        assert_eq!(symbols["main"], "col_mod1_main");
+    }
+
+    #[test]
+    fn test_executor_resolves_gml_names() {
+        let context = Context::create();
+        let ir_generator = prefixed(
+            &context,
+            "mod1",
+            "function update(x) { return x * 2; }\nreturn 7;",
+        );
+        let executor = JITExecutor::new(ir_generator.get_module()).unwrap();
+        assert_eq!(executor.symbol("update"), "col_mod1_update");
+        assert_eq!(executor.execute_function("update", &[4.0]), Ok(8.0));
+        assert_eq!(
+            executor.execute_function("col_mod1_update", &[5.0]),
+            Ok(10.0)
+        );
+        assert_eq!(executor.execute_main(), Ok(7.0));
+    }
+
+    #[test]
+    fn test_two_modules_define_the_same_function() {
+        let context = Context::create();
+        let first = prefixed(&context, "mod1", "function update(x) { return x + 1; }");
+        let second = prefixed(&context, "mod2", "function update(x) { return x * 10; }");
+        let executor = JITExecutor::new(first.get_module()).unwrap();
+        executor.add_module(second.get_module()).unwrap();
+
+        assert_eq!(
+            executor.execute_function("col_mod1_update", &[3.0]),
+            Ok(4.0)
+        );
+        assert_eq!(
+            executor.execute_function("col_mod2_update", &[3.0]),
+            Ok(30.0)
+        );
+        // GML names resolve to the first module's functions
+        assert_eq!(executor.execute_function("update", &[3.0]), Ok(4.0));
+    }
+
+    #[test]
+    fn test_script_calls_by_plain_name() {
+        let script =
+            Script::compile("function main_loop(t) { return t + 1; }\nreturn main_loop(1);")
+                .unwrap();
+        assert_eq!(script.run_main().unwrap(), Value::Number(2.0));
+        assert_eq!(
+            script.call("main_loop", &[Value::Number(41.0)]).unwrap(),
+            Value::Number(42.0)
+        );
+    }
+}
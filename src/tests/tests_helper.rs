@@ -1,11 +1,111 @@
 use crate::codegen::ir_generator::IRGenerator;
 use crate::codegen::jit::JITExecutor;
+use crate::parser::language_options::LanguageOptions;
 use crate::parser::program::Program;
-use crate::parser::program_parser;
+use crate::parser::{drop_bracketed_newlines, program_parser};
+use crate::script::Script;
 use crate::token::Token;
 use chumsky::{input::Stream, prelude::*};
 use inkwell::context::Context;
 use logos::Logos;
+use std::fs;
+use std::path::PathBuf;
+
+/// A program using every kind of expression, for tests that need to meet them all
+pub(crate) const ALL_AST_NODES: &str = r#"
+    enum Color { Red, Green = 2 }
+
+    function helper(x, y) {
+        return x * y;
+    }
+
+    function first(x, y, z) {
+        return x;
+    }
+
+    // Only compiled: running it needs instances bound to self and other
+    function swap_fields() {
+        self.hp = other.hp;
+    }
+
+    function test() {
+        // Literals
+        var num = 42;
+        var str = "test";
+        var bool_val = true;
+        var bool_false = false;
+        var null_val = null;
+
+        // Variable declarations and assignments
+        var a, b = 5, c;
+        a = 10;
+        c = a + b;
+
+        // All arithmetic operators
+        var add = a + b;
+        var sub = a - b;
+        var mul = a * b;
+        var division = a / b;
+        var modulo = a % b;
+        var int_div = a div b;
+        var mod_kw = a mod b;
+
+        // All comparison operators
+        var eq = (a == b);
+        var ne = (a != b);
+        var lt = (a < c);
+        var le = (a <= c);
+        var gt = (c > a);
+        var ge = (c >= a);
+
+        // All logical operators
+        var and_op = eq && ne;
+        var or_op = eq || ne;
+        var xor_op = eq ^^ ne;
+        var not_op = !eq;
+
+        // All bitwise operators
+        var bit_and = a & b;
+        var bit_or = a | b;
+        var bit_xor = a ^ b;
+        var bit_not = ~a;
+        var shl = a << 1;
+        var shr = a >> 1;
+
+        // All unary operators
+        var pos = +a;
+        var neg = -a;
+
+        // Increment/decrement
+        var pre_inc = ++a;
+        var post_inc = a++;
+        var pre_dec = --b;
+        var post_dec = b--;
+
+        // Compound assignments
+        c += 1;
+        c -= 1;
+        c *= 2;
+        c /= 2;
+        c %= 10;
+
+        // Ternary operator
+        var ternary = a > b ? a : b;
+
+        // Function calls
+        var call_result = helper(3, 4);
+        var skipped = first(a, , b);
+
+        // Enum members
+        var member = Color.Green;
+
+        // Parenthesized expressions
+        var paren = (a + b) * (c - 1);
+
+        // Return expression
+        return call_result + paren;
+    }
+"#;
 
 /// Helper function to parse GML source code into an AST
 pub(crate) fn parse_gml(src: &str) -> Program {
@@ -13,16 +113,26 @@ pub(crate) fn parse_gml(src: &str) -> Program {
         Ok(tok) => (tok, span.into()),
         Err(_) => (Token::Error, span.into()),
     });
-    let stream = Stream::from_iter(token_iter).map((0..src.len()).into(), |(t, s): (_, _)| (t, s));
+    let stream = Stream::from_iter(drop_bracketed_newlines(token_iter))
+        .map((0..src.len()).into(), |(t, s): (_, _)| (t, s));
     match program_parser().parse(stream).into_result() {
         Ok(p) => p,
         Err(errs) => panic!("Parse failed: {:?}", errs),
     }
 }
 
+/// Helper function to compile `src` as a script with `options`
+pub(crate) fn compile_with(src: &str, options: &LanguageOptions) -> Script {
+    Script::compile_with_options(src, options).unwrap()
+}
+
 /// Helper function to compile and execute GML code, returning the main function result
 pub(crate) fn compile_and_execute(src: &str) -> Result<f64, String> {
-    let program = parse_gml(src);
+    execute_program(&parse_gml(src))
+}
+
+/// Helper function to compile and execute an already parsed program
+pub(crate) fn execute_program(program: &Program) -> Result<f64, String> {
     let context = Context::create();
     let mut ir_generator = IRGenerator::new(&context, "test_module");
 
@@ -63,3 +173,17 @@ pub(crate) fn compile_and_execute_function(
     let executor = JITExecutor::new(ir_generator.get_module())?;
     executor.execute_function(func_name, args)
 }
+
+/// Helper function to create a fresh directory under the system temp dir, named
+/// after `prefix`, `name` and the process, holding `files` as (relative path,
+/// content) pairs
+pub(crate) fn temp_dir_with(prefix: &str, name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}_{}_{}", prefix, name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (file, content) in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::parser::language_options::{DivByZeroPolicy, LanguageOptions};
+    use crate::script::{Script, Value};
+    use crate::tests::tests_helper::parse_gml;
+    use inkwell::context::Context;
+
+    const LOOP: &str = "function count() {
+    var total = 0;
+    for (var i = 0; i < 5; i += 1) {
+        total += i;
+    }
+    return total;
+}
+";
+
+    const DIVIDE: &str = "function divide(n) {
+    var a = 1;
+    var b = a / n;
+    return b;
+}
+";
+
+    #[test]
+    fn test_trace_records_each_loop_iteration() {
+        let script = Script::compile_traced(LOOP, &LanguageOptions::default()).unwrap();
+        assert_eq!(script.call("count", &[]), Ok(Value::Number(10.0)));
+
+        let trace = script.take_trace();
+        let body: Vec<_> = trace.iter().filter(|entry| entry.line == 4).collect();
+        assert_eq!(body.len(), 5, "{:?}", trace);
+        assert!(body.iter().all(|entry| entry.column == 9), "{:?}", trace);
+        assert!(
+            trace.windows(2).all(|w| w[0].hit_order < w[1].hit_order),
+            "{:?}",
+            trace
+        );
+        assert_eq!(trace.first().map(|entry| entry.line), Some(2));
+        assert_eq!(trace.last().map(|entry| entry.line), Some(6));
+        assert!(script.take_trace().is_empty());
+    }
+
+    #[test]
+    fn test_failed_run_keeps_trace_up_to_the_error() {
+        let options = LanguageOptions {
+            div_by_zero: DivByZeroPolicy::Error,
+            ..LanguageOptions::default()
+        };
+        let script = Script::compile_traced(DIVIDE, &options).unwrap();
+        assert!(script.call("divide", &[Value::Number(0.0)]).is_err());
+
+        let lines: Vec<_> = script.take_trace().iter().map(|entry| entry.line).collect();
+        assert_eq!(lines, [2, 3]);
+    }
+
+    #[test]
+    fn test_trace_keeps_the_most_recent_entries() {
+        let script = Script::compile_traced(LOOP, &LanguageOptions::default()).unwrap();
+        script.set_trace_capacity(3);
+        script.call("count", &[]).unwrap();
+        let full = Script::compile_traced(LOOP, &LanguageOptions::default()).unwrap();
+        full.call("count", &[]).unwrap();
+
+        let full = full.take_trace();
+        assert_eq!(script.take_trace(), full[full.len() - 3..]);
+    }
+
+    #[test]
+    fn test_tracing_off_emits_nothing() {
+        let program = parse_gml(LOOP);
+        for tracing in [false, true] {
+            let context = Context::create();
+            let mut ir_generator = IRGenerator::new(&context, "test_module");
+            ir_generator.tracing = tracing;
+            program.accept(&mut ir_generator).unwrap();
+            let ir = ir_generator.get_module().print_to_string().to_string();
+            assert_eq!(ir.contains("col_trace_"), tracing, "{}", ir);
+        }
+
+        let script = Script::compile(LOOP).unwrap();
+        script.call("count", &[]).unwrap();
+        assert!(script.take_trace().is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use crate::infer_types;
+    use crate::parser::visitor::type_infer::{TYPE_ERROR, Type};
+    use crate::tests::tests_helper::*;
+
+    fn offset_of(src: &str, needle: &str) -> usize {
+        src.find(needle).unwrap()
+    }
+
+    #[test]
+    fn test_if_condition_is_bool() {
+        let src = "var x = 3;\nif (x > 1) { x = 0; }";
+        let types = infer_types(&parse_gml(src));
+        assert_eq!(types.type_at(offset_of(src, ">")), Some(Type::Bool));
+        assert_eq!(types.type_at(offset_of(src, "x >")), Some(Type::Number));
+        assert!(types.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_concatenation_is_string() {
+        let src = r#"var label = "score: " + string(12);"#;
+        let types = infer_types(&parse_gml(src));
+        assert_eq!(types.type_at(offset_of(src, "+")), Some(Type::String));
+        assert_eq!(types.type_at(offset_of(src, "label")), Some(Type::String));
+        assert_eq!(types.type_at(offset_of(src, "12")), Some(Type::Number));
+    }
+
+    #[test]
+    fn test_variables_keep_assigned_type() {
+        let src = "var s = \"a\";\nvar n = s == \"a\";\nfunction f(a) { return a; }\nx = f(1) + s;";
+        let types = infer_types(&parse_gml(src));
+        assert_eq!(types.type_at(offset_of(src, "n =")), Some(Type::Bool));
+        assert_eq!(types.type_at(offset_of(src, "a; }")), Some(Type::Number));
+        assert_eq!(types.type_at(offset_of(src, "f(1)")), Some(Type::Number));
+        // A number plus a string is an error, typed unknown
+        assert_eq!(types.type_at(src.rfind("+").unwrap()), Some(Type::Unknown));
+        assert_eq!(types.diagnostics().len(), 1);
+    }
+
+    #[test]
+    fn test_bad_multiplication_is_reported() {
+        let src = "var a = 2;\nvar b = \"x\" * a;\nvar c = -\"y\";";
+        let types = infer_types(&parse_gml(src));
+        let diagnostics = types.diagnostics();
+        assert_eq!(diagnostics.len(), 2, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].code, TYPE_ERROR);
+        assert_eq!(
+            diagnostics[0].message,
+            "cannot apply '*' to a string and a number"
+        );
+        let start = offset_of(src, "\"x\"");
+        assert_eq!(diagnostics[0].span, Some(start..start + "\"x\" * a".len()));
+        // Inference goes on past the error
+        assert_eq!(diagnostics[1].message, "cannot apply '-' to a string");
+    }
+
+    #[test]
+    fn test_string_condition_is_reported() {
+        let src = "var s = \"a\";\nwhile (s) { s = null; }";
+        let types = infer_types(&parse_gml(src));
+        let diagnostics = types.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "a string cannot be used as a condition"
+        );
+        assert_eq!(types.type_at(offset_of(src, "null")), Some(Type::Null));
+    }
+}
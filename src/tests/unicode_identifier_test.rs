@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::codegen::jit::JITExecutor;
+    use crate::parse_handler::ParseHandler;
+    use crate::parser::language_options::LanguageOptions;
+    use crate::script::{Script, Value};
+    use inkwell::context::Context;
+
+    const SRC: &str = r#"
+        var 变量 = 10;
+        function 攻击(力量) {
+            var 伤害 = 力量 * 2;
+            return 伤害 + 1;
+        }
+        return 攻击(变量);
+    "#;
+
+    #[test]
+    fn test_unicode_program_compiles_and_runs() {
+        let program = ParseHandler::parse_program(SRC).unwrap();
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        program.accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().verify().unwrap();
+
+        let executor = JITExecutor::new(ir_generator.get_module()).unwrap();
+        assert_eq!(executor.execute_main(), Ok(21.0));
+        assert_eq!(executor.execute_function("攻击", &[4.0]), Ok(9.0));
+
+        let script = Script::compile(SRC).unwrap();
+        assert_eq!(
+            script.call("攻击", &[Value::Number(5.0)]),
+            Ok(Value::Number(11.0))
+        );
+    }
+
+    #[test]
+    fn test_length_limit_counts_characters() {
+        let name = "变".repeat(64);
+        let src = format!("{} = 1;\n", name);
+        let (_, errors) = ParseHandler::parse_program_partial(&src);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let src = format!("{}量 = 1;\n", name);
+        let (_, errors) = ParseHandler::parse_program_partial(&src);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0]
+                .to_string()
+                .ends_with("exceeds maximum length of 64 (was 65)")
+        );
+    }
+
+    #[test]
+    fn test_ascii_identifiers_option_rejects_others() {
+        let options = LanguageOptions {
+            ascii_identifiers: true,
+            ..LanguageOptions::default()
+        };
+        let src = "var hp = 1;\nvar 变量 = hp;\n";
+        let (program, errors) = ParseHandler::parse_program_partial_with_options(src, &options);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(
+            errors[0].to_string(),
+            "identifier '变量' is not ASCII; disable LanguageOptions::ascii_identifiers \
+             to use other letters"
+        );
+        assert_eq!(&src[errors[0].span().into_range()], "变量");
+        // The rest of the file still parses
+        assert_eq!(program.unwrap().body.len(), 2);
+
+        assert!(Script::compile_with_options(SRC, &options).is_err());
+    }
+}
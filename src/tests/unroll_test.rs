@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use crate::codegen::ir_generator::IRGenerator;
+    use crate::codegen::ir_generator::unroll::DEFAULT_MAX_UNROLLED_REPEAT;
+    use crate::codegen::jit::JITExecutor;
+    use crate::script::{Script, Value};
+    use crate::tests::tests_helper::parse_gml;
+    use inkwell::context::Context;
+
+    /// The IR of `src` with unrolling on, and what it returns
+    fn compile_and_run(src: &str) -> (String, f64) {
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        ir_generator.max_unrolled_repeat = DEFAULT_MAX_UNROLLED_REPEAT;
+        parse_gml(src).accept(&mut ir_generator).unwrap();
+        ir_generator.get_module().verify().unwrap();
+        let ir = ir_generator.get_module().print_to_string().to_string();
+        let executor = JITExecutor::new(ir_generator.get_module()).unwrap();
+        (ir, executor.execute_main().unwrap())
+    }
+
+    #[test]
+    fn test_small_constant_count_is_unrolled() {
+        let (ir, result) = compile_and_run(
+            "var x = 1; var step = 3;\nrepeat (4) { x += step; step *= 2; }\nreturn x;",
+        );
+        assert!(!ir.contains("repeat_cond"), "{}", ir);
+        assert!(!ir.contains("repeat_counter"), "{}", ir);
+        assert_eq!(result, 1.0 + 3.0 + 6.0 + 12.0 + 24.0);
+    }
+
+    #[test]
+    fn test_large_count_keeps_the_loop() {
+        let (ir, result) = compile_and_run("var x = 0;\nrepeat (100) x += 2;\nreturn x;");
+        assert!(ir.contains("repeat_cond.0"), "{}", ir);
+        assert_eq!(result, 200.0);
+    }
+
+    #[test]
+    fn test_variable_count_keeps_the_loop() {
+        let (ir, result) = compile_and_run("var n = 3; var x = 0;\nrepeat (n) x += 5;\nreturn x;");
+        assert!(ir.contains("repeat_cond.0"), "{}", ir);
+        assert_eq!(result, 15.0);
+    }
+
+    #[test]
+    fn test_body_leaving_early_is_not_unrolled() {
+        let (ir, result) =
+            compile_and_run("var x = 0;\nrepeat (4) { x += 1; if (x == 2) break; }\nreturn x;");
+        assert!(ir.contains("repeat_cond.0"), "{}", ir);
+        assert_eq!(result, 2.0);
+
+        let (ir, result) = compile_and_run(
+            "var x = 0;\nrepeat (4) { for (var i = 0; i < 2; i++) { x += 1; continue; } }\nreturn x;",
+        );
+        assert!(ir.contains("repeat_cond.0"), "{}", ir);
+        assert_eq!(result, 8.0);
+    }
+
+    #[test]
+    fn test_bare_generator_keeps_loops() {
+        let context = Context::create();
+        let mut ir_generator = IRGenerator::new(&context, "test_module");
+        parse_gml("var x = 0;\nrepeat (4) x += 1;\nreturn x;")
+            .accept(&mut ir_generator)
+            .unwrap();
+        let ir = ir_generator.get_module().print_to_string().to_string();
+        assert!(ir.contains("repeat_cond.0"), "{}", ir);
+    }
+
+    #[test]
+    fn test_scripts_unroll() {
+        let script =
+            Script::compile("function f(x) {\n    repeat (3) x *= 2;\n    return x;\n}").unwrap();
+        assert_eq!(
+            script.call("f", &[Value::Number(5.0)]),
+            Ok(Value::Number(40.0))
+        );
+    }
+}
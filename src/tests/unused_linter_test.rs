@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod tests {
+    use crate::check_handler::{CheckHandler, CheckOptions};
+    use crate::parser::language_options::LanguageOptions;
+    use crate::parser::visitor::unused_linter::{
+        UNREAD_VARIABLE, UNUSED_PARAMETER, UNUSED_VARIABLE, UnusedLinter,
+    };
+    use crate::tests::tests_helper::parse_gml;
+    use crate::utils::diagnostic::{Diagnostic, Severity};
+    use std::path::Path;
+
+    const UNUSED: &str = "function f() {\n    var temp = 1;\n    return 0;\n}\n";
+
+    fn lint(src: &str) -> Vec<Diagnostic> {
+        UnusedLinter::lint(&parse_gml(src))
+    }
+
+    #[test]
+    fn test_unused_local_warns_at_its_declaration() {
+        let diagnostics = lint(UNUSED);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].code, UNUSED_VARIABLE);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        let span = diagnostics[0].span.clone().unwrap();
+        assert_eq!(&UNUSED[span], "temp");
+        assert_eq!(diagnostics[0].message, "variable 'temp' is never used");
+    }
+
+    #[test]
+    fn test_uses_in_nested_scopes_count() {
+        let src = r#"
+            function f(n) {
+                var limit = 3;
+                var total = 0;
+                for (var i = 0; i < limit; i++) {
+                    if (i > 1) { total += n; }
+                }
+                switch (n) { case limit: return total; }
+                return total;
+            }
+        "#;
+        assert!(lint(src).is_empty(), "{:?}", lint(src));
+    }
+
+    #[test]
+    fn test_anonymous_function_is_checked_and_calling_it_reads_it() {
+        let src =
+            "function f() {\n    var cb = function(a, b) { return a; };\n    return cb(1, 2);\n}\n";
+        let diagnostics = lint(src);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].code, UNUSED_PARAMETER);
+        assert_eq!(
+            diagnostics[0].message,
+            "parameter 'b' of an anonymous function is never used; remove it, or rename \
+             it to '_b' if it must stay"
+        );
+    }
+
+    #[test]
+    fn test_unused_parameter_warns_with_its_function() {
+        let src = "function move(speed, _dir) {\n    return 1;\n}\n";
+        let diagnostics = lint(src);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].code, UNUSED_PARAMETER);
+        let span = diagnostics[0].span.clone().unwrap();
+        assert_eq!(&src[span], "move");
+        assert!(
+            diagnostics[0].message.contains("rename it to '_speed'"),
+            "{}",
+            diagnostics[0].message
+        );
+    }
+
+    #[test]
+    fn test_write_only_variable_is_reported_apart() {
+        let src = "function f() {\n    var count = 0;\n    count += 1;\n    count = 5;\n}\n";
+        let diagnostics = lint(src);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].code, UNREAD_VARIABLE);
+        assert_eq!(
+            diagnostics[0].message,
+            "variable 'count' is assigned but never read"
+        );
+
+        // An update whose result is used reads the variable
+        let src = "function f() {\n    var count = 0;\n    return count++;\n}\n";
+        assert!(lint(src).is_empty(), "{:?}", lint(src));
+    }
+
+    #[test]
+    fn test_underscores_and_top_level_variables_are_exempt() {
+        let src = "var unused = 1;\nfunction f() {\n    var _scratch = 2;\n    return 0;\n}\n";
+        assert!(lint(src).is_empty(), "{:?}", lint(src));
+    }
+
+    #[test]
+    fn test_off_by_default() {
+        let diagnostics = CheckHandler::lint(&parse_gml(UNUSED), &CheckOptions::default());
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn test_check_options_turn_it_on() {
+        let options = CheckOptions {
+            warn_unused: true,
+            ..CheckOptions::default()
+        };
+        let report = CheckHandler::check_source(
+            UNUSED,
+            Path::new("check_test.gml"),
+            true,
+            &LanguageOptions::default(),
+            &options,
+        )
+        .unwrap();
+        let codes: Vec<_> = report.warnings.iter().map(|d| d.code).collect();
+        assert_eq!(codes, [UNUSED_VARIABLE]);
+    }
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::expr::Expr;
+    use crate::parser::stmt::Stmt;
+    use crate::parser::visitor::{Pass, Visitor};
+    use crate::tests::tests_helper::{ALL_AST_NODES, parse_gml};
+    use std::collections::HashSet;
+    use std::mem::{Discriminant, discriminant};
+
+    /// Counts identifier uses, and which kinds of expression it went through
+    #[derive(Default)]
+    struct IdentifierCounter {
+        identifiers: usize,
+        kinds: HashSet<Discriminant<Expr>>,
+    }
+
+    impl Pass for IdentifierCounter {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let Expr::Identifier(..) = expr {
+                self.identifiers += 1;
+            }
+            self.kinds.insert(discriminant(expr));
+            self.walk_expr(expr);
+        }
+    }
+
+    #[test]
+    fn test_pass_reaches_every_expression() {
+        let program = parse_gml(ALL_AST_NODES);
+        let mut counter = IdentifierCounter::default();
+        program.accept(&mut counter);
+
+        // 2 in helper, 1 in first and 68 in test
+        assert_eq!(counter.identifiers, 71);
+        // Every variant of Expr
+        assert_eq!(counter.kinds.len(), 48);
+    }
+
+    /// Counts loops, stopping at the first one it meets on each path
+    #[derive(Default)]
+    struct OuterLoops(usize);
+
+    impl Pass for OuterLoops {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            match stmt {
+                Stmt::While(..) | Stmt::Repeat(..) | Stmt::DoUntil(..) | Stmt::For(..) => {
+                    self.0 += 1
+                }
+                _ => self.walk_stmt(stmt),
+            }
+        }
+    }
+
+    #[test]
+    fn test_pass_can_stop_walking() {
+        let program = parse_gml(
+            r#"
+            function f(n) {
+                while (n > 0) {
+                    repeat (n) { n -= 1; }
+                }
+                if (n == 0) {
+                    for (var i = 0; i < 3; i += 1) {}
+                }
+            }
+            repeat (2) {}
+        "#,
+        );
+        let mut counter = OuterLoops::default();
+        program.accept(&mut counter);
+        assert_eq!(counter.0, 3);
+    }
+}
@@ -1,15 +1,43 @@
 mod test;
 
+use crate::output_handler::{OutputSink, SectionKind};
+use crate::utils::line_index::LineIndex;
 use logos::Logos;
 use owo_colors::OwoColorize;
-use std::fmt;
+use std::fmt::{self, Write};
+
+/// Why the lexer rejected a piece of input
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LexError {
+    /// A character no token starts with, e.g. `@` not followed by `"`
+    #[default]
+    UnrecognizedCharacter,
+    /// A `"` with no closing quote before the end of its line, or a `@"` with none
+    /// before the end of the file
+    UnterminatedString,
+}
+
+impl LexError {
+    /// Describe the error for the rejected `slice`, which starts on `line`
+    pub fn describe(&self, slice: &str, line: usize) -> String {
+        match self {
+            LexError::UnrecognizedCharacter => {
+                format!("unrecognized character '{}' at line {}", slice, line)
+            }
+            LexError::UnterminatedString => {
+                format!("unterminated string starting at line {}", line)
+            }
+        }
+    }
+}
 
 #[derive(Logos, Debug, PartialEq)]
+#[logos(error = LexError)]
 #[logos(skip r"[ \t]+")]
-#[logos(skip r"//[^\n]*")]
+#[logos(skip r"//[^\r\n\x{2028}\x{2029}]*")]
 #[logos(skip r"/\*([^*]|\*[^/])*\*/")]
 #[derive(Clone)]
-pub(crate) enum Token<'a> {
+pub enum Token<'a> {
     Error,
     // region Keywords
     // See: https://manual.gamemaker.io/monthly/en/#t=GameMaker_Language%2FGML_Overview%2FLanguage_Features.htm&rhsearch=globalvar
@@ -203,7 +231,9 @@ pub(crate) enum Token<'a> {
 
     // ----------------------------------------
     // region Punctuations
-    #[regex(r"(?:\r\n|\n|\r)")]
+    /// Any line ending: `\r\n`, `\n`, a lone `\r`, or the Unicode line and
+    /// paragraph separators U+2028 and U+2029, which end a line like `\n` does
+    #[regex(r"(?:\r\n|\n|\r|\x{2028}|\x{2029})")]
     Newline,
     #[token(";")]
     Semicolon,
@@ -223,6 +253,15 @@ pub(crate) enum Token<'a> {
     LeftBracket,
     #[token("]")]
     RightBracket,
+    // Accessors: `list[| i]`, `map[? key]`, `grid[# x, y]` and `array[@ i]`
+    #[token("[|")]
+    ListAccessor,
+    #[token("[?")]
+    MapAccessor,
+    #[token("[#")]
+    GridAccessor,
+    #[token("[@")]
+    ArrayAccessor,
     #[token("?")]
     Question,
     #[token(":")]
@@ -230,12 +269,24 @@ pub(crate) enum Token<'a> {
 
     // endregion
 
+    // ----------------------------------------
+    // region Directives
+    // `#include "file.gml"`, resolved by ParseHandler::resolve_includes
+    #[regex(r#"#include[ \t]+"[^"\r\n]*""#, |lex| {
+    let slice = lex.slice();
+    &slice[slice.find('"').unwrap() + 1..slice.len() - 1]
+    })]
+    Include(&'a str),
+    // endregion
+
     // ----------------------------------------
     // region Literals
 
     // See https://manual.gamemaker.io/lts/en/index.htm#t=GameMaker_Language%2FGML_Overview%2FVariables_And_Variable_Scope.htm
-    // Maximum length will be configurable in future
-    #[regex(r"[a-zA-Z_][a-zA-Z0-9_]{0,63}")]
+    // Identifiers are lexed whole, however long, and may use any Unicode letters,
+    // e.g. `变量`; the parser checks the length limit and, if asked to, that they
+    // are ASCII
+    #[regex(r"[\p{XID_Start}_]\p{XID_Continue}*")]
     Identifier(&'a str),
 
     // [^"\n]* means that there cannot be " and newline characters in the middle,
@@ -244,8 +295,15 @@ pub(crate) enum Token<'a> {
     let slice = lex.slice();
     &slice[1..slice.len()-1]
     })]
+    // A string missing its closing quote ends with its line, not the file
+    #[regex(r#""[^"\r\n\x{2028}\x{2029}]*"#, unterminated_string)]
     String(&'a str),
 
+    // `@"..."` may span lines, and writes a quote as `""`. The token holds the text
+    // between the quotes as written, doubled quotes included.
+    #[token("@\"", verbatim_string)]
+    VerbatimString(&'a str),
+
     #[regex(r"\d+(\.\d+)?")]
     Number(&'a str),
     // endregion
@@ -374,42 +432,202 @@ impl fmt::Display for Token<'_> {
             Token::RightBrace => write!(f, "}}"),
             Token::LeftBracket => write!(f, "["),
             Token::RightBracket => write!(f, "]"),
+            Token::ListAccessor => write!(f, "[|"),
+            Token::MapAccessor => write!(f, "[?"),
+            Token::GridAccessor => write!(f, "[#"),
+            Token::ArrayAccessor => write!(f, "[@"),
             Token::Question => write!(f, "?"),
             Token::Colon => write!(f, ":"),
             // endregion
 
+            // ----------------------------------------
+            // region Directives
+            Token::Include(path) => write!(f, "#include \"{}\"", path),
+            // endregion
+
             // ----------------------------------------
             // region Literals
             Token::Identifier(s) => write!(f, "{}", s),
             Token::String(s) => write!(f, "{}", s),
+            Token::VerbatimString(s) => write!(f, "{}", s),
             Token::Number(s) => write!(f, "{}", s),
             // endregion
         }
     }
 }
 
-pub(crate) fn lex_with_output(input: &'_ str) -> Vec<Token<'_>> {
-    let mut lex = Token::lexer(input);
-    let mut tokens = Vec::new();
-    println!();
-    println!("{}", "(Test) Lexer output :".green());
-
-    while let Some(result) = lex.next() {
-        match result {
-            Ok(token) => {
-                if token == Token::Newline {
-                    println!("{}", "↵ Newline".blue());
-                } else {
-                    print!("{:?} ", token);
-                }
-                tokens.push(token);
-            }
-            Err(_) => {
-                println!("{}", "Lexer error encountered!".red());
-                panic!("Lexer failed on input: {:?}", input);
+/// Coarse classification of tokens, used when displaying them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenCategory {
+    Keyword,
+    Operator,
+    Punctuation,
+    Newline,
+    Identifier,
+    String,
+    Number,
+    Error,
+}
+
+impl Token<'_> {
+    pub fn category(&self) -> TokenCategory {
+        match self {
+            Token::Error => TokenCategory::Error,
+            Token::Identifier(_) => TokenCategory::Identifier,
+            Token::String(_) | Token::VerbatimString(_) => TokenCategory::String,
+            Token::Number(_) => TokenCategory::Number,
+            Token::Newline => TokenCategory::Newline,
+            Token::Semicolon
+            | Token::Comma
+            | Token::Dot
+            | Token::LeftParen
+            | Token::RightParen
+            | Token::LeftBrace
+            | Token::RightBrace
+            | Token::LeftBracket
+            | Token::RightBracket
+            | Token::ListAccessor
+            | Token::MapAccessor
+            | Token::GridAccessor
+            | Token::ArrayAccessor
+            | Token::Question
+            | Token::Colon => TokenCategory::Punctuation,
+            Token::Equal
+            | Token::PlusEqual
+            | Token::MinusEqual
+            | Token::StarEqual
+            | Token::SlashEqual
+            | Token::PercentEqual
+            | Token::And
+            | Token::Or
+            | Token::Xor
+            | Token::NullishEqual
+            | Token::Nullish
+            | Token::Less
+            | Token::LessEqual
+            | Token::EqualEqual
+            | Token::NotEqual
+            | Token::Greater
+            | Token::GreaterEqual
+            | Token::BitOr
+            | Token::BitAnd
+            | Token::BitXor
+            | Token::ShiftLeft
+            | Token::ShiftRight
+            | Token::Increment
+            | Token::Decrement
+            | Token::Plus
+            | Token::Minus
+            | Token::Star
+            | Token::Slash
+            | Token::Percent
+            | Token::Not
+            | Token::BitNot => TokenCategory::Operator,
+            _ => TokenCategory::Keyword,
+        }
+    }
+}
+
+/// A token together with its location in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenInfo<'a> {
+    pub token: Token<'a>,
+    /// Byte range of the token in the source
+    pub span: std::ops::Range<usize>,
+    /// 1-based line of the first character
+    pub line: usize,
+    /// 1-based column (in characters) of the first character
+    pub column: usize,
+}
+
+fn unterminated_string<'a>(_: &mut logos::Lexer<'a, Token<'a>>) -> Result<&'a str, LexError> {
+    Err(LexError::UnterminatedString)
+}
+
+/// Lex the rest of a verbatim string once its `@"` is matched. One left unterminated
+/// only takes the rest of its first line, so lexing picks up again on the next.
+fn verbatim_string<'a>(lex: &mut logos::Lexer<'a, Token<'a>>) -> Result<&'a str, LexError> {
+    let rest = lex.remainder();
+    let bytes = rest.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'"', Some(b'"')) => i += 2,
+            (b'"', _) => {
+                lex.bump(i + 1);
+                return Ok(&rest[..i]);
             }
+            _ => i += 1,
         }
     }
-    println!("\n");
+    let line_end = rest
+        .find(['\r', '\n', '\u{2028}', '\u{2029}'])
+        .unwrap_or(rest.len());
+    lex.bump(line_end);
+    Err(LexError::UnterminatedString)
+}
+
+/// Lex the whole input, attaching positions to every token.
+/// Unrecognized input is kept as `Token::Error` so callers can report it.
+pub fn tokenize(input: &'_ str) -> Vec<TokenInfo<'_>> {
+    let line_index = LineIndex::new(input);
+    Token::lexer(input)
+        .spanned()
+        .map(|(result, span)| {
+            let (line, column) = line_index.line_col(span.start);
+            TokenInfo {
+                token: result.unwrap_or(Token::Error),
+                span,
+                line,
+                column,
+            }
+        })
+        .collect()
+}
+
+/// The tokens of `input`, writing their listing to `out` as a lexer dump
+pub fn lex_with_output<'a>(out: &mut dyn OutputSink, input: &'a str) -> Vec<Token<'a>> {
+    let (tokens, output) = lexer_output(input);
+    out.write_section(SectionKind::LexerDump, &output);
     tokens
 }
+
+/// The tokens of `input` and the listing [`lex_with_output`] writes for them
+pub fn lexer_output(input: &'_ str) -> (Vec<Token<'_>>, String) {
+    let line_index = LineIndex::new(input);
+    let mut tokens = Vec::new();
+    let mut output = String::new();
+    let _ = writeln!(output);
+    let _ = writeln!(output, "{}", "(Test) Lexer output :".green());
+
+    for info in tokenize(input) {
+        let _ = match info.token {
+            Token::Newline => writeln!(output, "{}", "↵ Newline".blue()),
+            Token::Error => {
+                let _ = writeln!(output);
+                let _ = writeln!(
+                    output,
+                    "{}",
+                    format!(
+                        "Lexer error at {}:{}: unrecognized input {:?}",
+                        info.line,
+                        info.column,
+                        &input[info.span.clone()]
+                    )
+                    .red()
+                );
+                let _ = writeln!(
+                    output,
+                    "{:>5} | {}",
+                    info.line,
+                    line_index.line_text(info.line)
+                );
+                writeln!(output, "{:>5} | {}^", "", " ".repeat(info.column - 1))
+            }
+            ref token => write!(output, "{:?} ", token),
+        };
+        tokens.push(info.token);
+    }
+    let _ = writeln!(output, "\n");
+    (tokens, output)
+}
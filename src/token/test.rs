@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod tests {
+    use crate::output_handler::BufferSink;
     use crate::token::*;
+    use logos::Logos;
 
     // ---------------------------
     // ClassificationTest
@@ -63,7 +65,7 @@ mod tests {
             Token::Else,
         ];
 
-        let tokens = lex_with_output(input);
+        let tokens = lex_with_output(&mut BufferSink::default(), input);
         assert_eq!(tokens, expected);
     }
 
@@ -105,7 +107,7 @@ mod tests {
             Token::BitNot,
         ];
 
-        let tokens = lex_with_output(input);
+        let tokens = lex_with_output(&mut BufferSink::default(), input);
         assert_eq!(tokens, expected);
     }
 
@@ -128,7 +130,52 @@ mod tests {
             Token::Newline,
         ];
 
-        let tokens = lex_with_output(input);
+        let tokens = lex_with_output(&mut BufferSink::default(), input);
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let input = "l[| 0] m[?\"k\"] g[# 1, 2] a[@ i] [ |";
+        let expected = vec![
+            Token::Identifier("l"),
+            Token::ListAccessor,
+            Token::Number("0"),
+            Token::RightBracket,
+            Token::Identifier("m"),
+            Token::MapAccessor,
+            Token::String("k"),
+            Token::RightBracket,
+            Token::Identifier("g"),
+            Token::GridAccessor,
+            Token::Number("1"),
+            Token::Comma,
+            Token::Number("2"),
+            Token::RightBracket,
+            Token::Identifier("a"),
+            Token::ArrayAccessor,
+            Token::Identifier("i"),
+            Token::RightBracket,
+            Token::LeftBracket,
+            Token::BitOr,
+        ];
+
+        let tokens = lex_with_output(&mut BufferSink::default(), input);
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let input = "变量 = café_2 + _x1";
+        let expected = vec![
+            Token::Identifier("变量"),
+            Token::Equal,
+            Token::Identifier("café_2"),
+            Token::Plus,
+            Token::Identifier("_x1"),
+        ];
+
+        let tokens = lex_with_output(&mut BufferSink::default(), input);
         assert_eq!(tokens, expected);
     }
 
@@ -143,7 +190,7 @@ mod tests {
             Token::Number("3.14"),
         ];
 
-        let tokens = lex_with_output(input);
+        let tokens = lex_with_output(&mut BufferSink::default(), input);
         assert_eq!(tokens, expected);
     }
 
@@ -158,7 +205,7 @@ mod tests {
             Token::Newline,
         ];
 
-        let tokens = lex_with_output(input);
+        let tokens = lex_with_output(&mut BufferSink::default(), input);
         assert_eq!(tokens, expected);
     }
 
@@ -202,7 +249,116 @@ mod tests {
             Token::Newline,
         ];
 
-        let tokens = lex_with_output(input);
+        let tokens = lex_with_output(&mut BufferSink::default(), input);
         assert_eq!(tokens, expected);
     }
+
+    // ---------------------------
+    // PositionTest
+    // ---------------------------
+
+    fn positions(input: &str) -> Vec<(Token<'_>, usize, usize)> {
+        tokenize(input)
+            .into_iter()
+            .map(|info| (info.token, info.line, info.column))
+            .collect()
+    }
+
+    #[test]
+    fn test_positions_with_crlf_and_multiline_comment() {
+        let input = "var x = 1;\r\n/* spans\r\n   lines */ y = \"s\"\r\n\tz";
+        let expected = vec![
+            (Token::Var, 1, 1),
+            (Token::Identifier("x"), 1, 5),
+            (Token::Equal, 1, 7),
+            (Token::Number("1"), 1, 9),
+            (Token::Semicolon, 1, 10),
+            (Token::Newline, 1, 11),
+            (Token::Identifier("y"), 3, 13),
+            (Token::Equal, 3, 15),
+            (Token::String("s"), 3, 17),
+            (Token::Newline, 3, 20),
+            (Token::Identifier("z"), 4, 2),
+        ];
+        assert_eq!(positions(input), expected);
+    }
+
+    #[test]
+    fn test_positions_spans_and_lone_cr() {
+        let input = "a\rbb\n  cc";
+        let tokens = tokenize(input);
+        assert_eq!(tokens[0].span, 0..1);
+        assert_eq!((tokens[2].line, tokens[2].column), (2, 1));
+        assert_eq!(tokens[2].span, 2..4);
+        assert_eq!((tokens[4].line, tokens[4].column), (3, 3));
+        assert_eq!(tokens[4].span, 7..9);
+    }
+
+    #[test]
+    fn test_columns_count_characters() {
+        let input = "\"héllo\" x";
+        let tokens = tokenize(input);
+        assert_eq!(tokens[1].token, Token::Identifier("x"));
+        assert_eq!((tokens[1].line, tokens[1].column), (1, 9));
+    }
+
+    #[test]
+    fn test_lexer_error_is_reported_not_panicking() {
+        let input = "x = 1\ny = @ 2";
+        let tokens = lex_with_output(&mut BufferSink::default(), input);
+        assert!(tokens.contains(&Token::Error));
+        assert_eq!(tokens.last(), Some(&Token::Number("2")));
+
+        let error = tokenize(input)
+            .into_iter()
+            .find(|info| info.token == Token::Error)
+            .unwrap();
+        assert_eq!((error.line, error.column), (2, 5));
+        assert_eq!(&input[error.span], "@");
+    }
+
+    #[test]
+    fn test_unterminated_string_stops_at_end_of_line() {
+        let input = "s = \"abc\r\nt = \"x\"";
+        let tokens: Vec<_> = Token::lexer(input).spanned().collect();
+        assert_eq!(tokens[2], (Err(LexError::UnterminatedString), 4..8));
+        assert_eq!(tokens[3], (Ok(Token::Newline), 8..10));
+        assert_eq!(tokens.last(), Some(&(Ok(Token::String("x")), 14..17)));
+    }
+
+    #[test]
+    fn test_verbatim_string() {
+        let input = "s = @\"a \"\"b\"\"\nc\"\nt = 1";
+        let tokens: Vec<_> = Token::lexer(input).spanned().collect();
+        assert_eq!(
+            tokens[2],
+            (Ok(Token::VerbatimString("a \"\"b\"\"\nc")), 4..16)
+        );
+        assert_eq!(tokens[3], (Ok(Token::Newline), 16..17));
+        assert_eq!(tokens.last(), Some(&(Ok(Token::Number("1")), 21..22)));
+    }
+
+    #[test]
+    fn test_unterminated_verbatim_string_stops_at_end_of_line() {
+        let input = "s = @\"abc\nt = \"x\"";
+        let tokens: Vec<_> = Token::lexer(input).spanned().collect();
+        assert_eq!(tokens[2], (Err(LexError::UnterminatedString), 4..9));
+        assert_eq!(tokens[3], (Ok(Token::Newline), 9..10));
+        assert_eq!(tokens.last(), Some(&(Ok(Token::String("x")), 14..17)));
+    }
+
+    #[test]
+    fn test_include_directive() {
+        let input = "#include \"lib/util.gml\"\n#include\t\"a b.gml\"";
+        let tokens = lex_with_output(&mut BufferSink::default(), input);
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Include("lib/util.gml"),
+                Token::Newline,
+                Token::Include("a b.gml"),
+            ]
+        );
+        assert_eq!(tokens[0].to_string(), "#include \"lib/util.gml\"");
+    }
 }
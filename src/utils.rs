@@ -1 +1,4 @@
 pub mod colorize;
+pub mod diagnostic;
+pub mod line_index;
+pub mod number_format;
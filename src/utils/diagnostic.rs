@@ -0,0 +1,113 @@
+use crate::parser::Span;
+use crate::parser::program::IncludedSource;
+use crate::utils::line_index::LineIndex;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// Another place a [`Diagnostic`] refers to, such as the declaration a name hides
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    /// What is there, e.g. "shadowed declaration"
+    pub message: String,
+    /// The included file `span` is in, as for [`Diagnostic::file`]
+    pub file: Option<Arc<IncludedSource>>,
+}
+
+/// A compiler message in a form tools can act on without parsing text.
+///
+/// Codes are stable and grouped by phase: 1xx parsing, 2xx symbol resolution,
+/// 3xx code generation, 4xx lints, 5xx type inference. `span` is `None` when the
+/// phase does not track positions.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: u32,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+    /// The included file `span` is in, `None` for the file being compiled; see
+    /// [`Program::relocate`](crate::parser::program::Program::relocate)
+    pub file: Option<Arc<IncludedSource>>,
+    /// Other places the message refers to
+    pub labels: Vec<Label>,
+}
+
+impl Diagnostic {
+    pub fn new(code: u32, severity: Severity, message: String, span: Option<Span>) -> Self {
+        Self {
+            code,
+            severity,
+            message,
+            span,
+            file: None,
+            labels: vec![],
+        }
+    }
+
+    /// This diagnostic also pointing at `span`, which holds what `message` says
+    pub fn with_label(mut self, span: Span, message: &str) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.to_string(),
+            file: None,
+        });
+        self
+    }
+
+    /// Render as a `line:column: severity[Ecode]: message` line, with `index`
+    /// placing the span in the file being compiled. A span in an included file is
+    /// placed in that file instead and rendered as `path:line:column: ...`. Each
+    /// label follows on an indented line of its own, as `line:column: message`.
+    pub fn render(&self, index: &LineIndex) -> String {
+        let Some(span) = &self.span else {
+            return self.to_string();
+        };
+        let mut text = format!("{}: {}", position(span.start, &self.file, index), self);
+        for label in &self.labels {
+            text.push_str(&format!(
+                "\n    {}: {}",
+                position(label.span.start, &label.file, index),
+                label.message
+            ));
+        }
+        text
+    }
+}
+
+/// Where `offset` is, as `line:column` in the file being compiled or
+/// `path:line:column` in an included `file`
+fn position(offset: usize, file: &Option<Arc<IncludedSource>>, index: &LineIndex) -> String {
+    match file {
+        Some(file) => {
+            let (line, column) = LineIndex::new(&file.content).line_col(offset);
+            format!("{}:{}:{}", file.path.display(), line, column)
+        }
+        None => {
+            let (line, column) = index.line_col(offset);
+            format!("{}:{}", line, column)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[E{:04}]: {}", self.severity, self.code, self.message)
+    }
+}
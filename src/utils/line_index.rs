@@ -0,0 +1,61 @@
+/// Maps byte offsets in a source string to 1-based line and column numbers.
+///
+/// `\r\n`, `\n`, a lone `\r`, U+2028 and U+2029 each end a line, matching the
+/// lexer's `Newline` token.
+/// Columns are counted in characters, not bytes.
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let bytes = source.as_bytes();
+        let mut line_starts = vec![0];
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                    line_starts.push(i + 2);
+                    i += 2;
+                    continue;
+                }
+                b'\r' | b'\n' => line_starts.push(i + 1),
+                // U+2028 LINE SEPARATOR and U+2029 PARAGRAPH SEPARATOR
+                0xE2 if matches!(bytes.get(i + 1..i + 3), Some([0x80, 0xA8 | 0xA9])) => {
+                    line_starts.push(i + 3);
+                    i += 3;
+                    continue;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Get the 1-based (line, column) of a byte offset
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let column = self.source[start..offset].chars().count() + 1;
+        (line + 1, column)
+    }
+
+    /// Get the text of a 1-based line without its terminator
+    pub fn line_text(&self, line: usize) -> &'a str {
+        let Some(&start) = self.line_starts.get(line.saturating_sub(1)) else {
+            return "";
+        };
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.source.len());
+        self.source[start..end].trim_end_matches(['\r', '\n', '\u{2028}', '\u{2029}'])
+    }
+}
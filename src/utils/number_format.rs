@@ -0,0 +1,55 @@
+/// How many significant digits a number is written with. Enough for every number
+/// to read back within a rounding error, few enough that `0.1 + 0.2` is "0.3".
+pub const SIGNIFICANT_DIGITS: usize = 15;
+
+/// The decimal exponents numbers are written without scientific notation for:
+/// from 0.000001 up to, but not including, 1e21
+pub const MIN_FIXED_EXPONENT: i32 = -6;
+pub const MAX_FIXED_EXPONENT: i32 = 20;
+
+/// Format a number the way GML's `string()` does, and everything else showing a
+/// script's numbers: rounded to [`SIGNIFICANT_DIGITS`], without trailing zeros, and
+/// integers without a point, so 3 is "3", 0.1 + 0.2 is "0.3" and -0 is "0". Numbers
+/// of magnitude 1e21 and up or below 0.000001 use scientific notation, as in
+/// "1e+21" and "1.5e-7". NaN is "NaN" and infinities are "inf" and "-inf".
+pub fn format_number(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    if value == 0.0 {
+        return "0".to_string();
+    }
+
+    // Rounds to the significant digits first, so the exponent is the rounded one's
+    let scientific = format!("{:.*e}", SIGNIFICANT_DIGITS - 1, value.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("`{:e}` always writes an exponent");
+    let exponent: i32 = exponent.parse().expect("`{:e}` writes an integer exponent");
+    let digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    let digits = digits.trim_end_matches('0');
+    let sign = if value < 0.0 { "-" } else { "" };
+
+    if !(MIN_FIXED_EXPONENT..=MAX_FIXED_EXPONENT).contains(&exponent) {
+        let (first, rest) = digits.split_at(1);
+        let point = if rest.is_empty() { "" } else { "." };
+        let exponent_sign = if exponent > 0 { "+" } else { "" };
+        return format!(
+            "{}{}{}{}e{}{}",
+            sign, first, point, rest, exponent_sign, exponent
+        );
+    }
+    if exponent < 0 {
+        let zeros = "0".repeat((-exponent - 1) as usize);
+        return format!("{}0.{}{}", sign, zeros, digits);
+    }
+    let whole = exponent as usize + 1;
+    if digits.len() <= whole {
+        format!("{}{}{}", sign, digits, "0".repeat(whole - digits.len()))
+    } else {
+        format!("{}{}.{}", sign, &digits[..whole], &digits[whole..])
+    }
+}